@@ -14,8 +14,8 @@ use surroundfi::{
     state::{
         surroundfi_account::{Balance, LendingAccount, SurroundfiAccount, ACCOUNT_FLAG_DEPRECATED},
         surroundfi_group::{
-            Bank, BankConfig, BankConfigOpt, BankOperationalState, InterestRateConfig,
-            InterestRateConfigOpt, RiskTier, WrappedI80F48,
+            Bank, BankConfig, BankConfigOpt, BankOperationalState, GroupConfigOpt,
+            InterestRateConfig, InterestRateConfigOpt, RiskTier, WrappedI80F48,
         },
     },
 };
@@ -104,6 +104,18 @@ pub enum GroupCommand {
         new_admin: Pubkey,
         #[clap(long)]
         is_arena_group: bool,
+        /// Asset value (in USD) below which insolvent accounts can be handled as bankrupt
+        #[clap(long)]
+        bankruptcy_threshold: Option<f64>,
+        /// Let accounts with staked collateral borrow stablecoin (asset tag 4) banks
+        #[clap(long)]
+        staked_stable_borrow_enabled: Option<bool>,
+    },
+    /// Set which restricted Token-2022 extensions the mints of new banks may have
+    ConfigureMintExtensions {
+        /// Bitmask of `MINT_EXTENSION_*` flags, e.g. 32 for permanent delegates
+        #[clap(long)]
+        permitted_mint_extensions: u64,
     },
     AddBank {
         #[clap(long)]
@@ -194,6 +206,27 @@ pub enum GroupCommand {
         #[clap(long)]
         surroundfi_group: Pubkey,
     },
+    ConfigureFeeDistribution {
+        #[clap(
+            long = "recipient",
+            required = true,
+            help = "Fee recipient as <WALLET>:<SHARE_BPS>, repeat for each recipient (shares must sum to 10000)"
+        )]
+        recipients: Vec<String>,
+    },
+    DistributeFees {
+        #[clap(help = "Banks to distribute fees for, all banks in the group if empty")]
+        banks: Vec<Pubkey>,
+    },
+    InitReferrerFees {
+        #[clap(long, help = "Defaults to the signer")]
+        referrer: Option<Pubkey>,
+    },
+    ClaimReferrerFees {
+        bank: Pubkey,
+        #[clap(long, help = "Wallet to receive the fees, defaults to the signer")]
+        destination: Option<Pubkey>,
+    },
 }
 
 #[derive(Clone, Copy, Debug, Parser, ArgEnum)]
@@ -274,6 +307,12 @@ pub enum BankCommand {
         pf_ir: Option<f64>,
         #[clap(long, help = "Protocol origination fee")]
         pf_or: Option<f64>,
+        #[clap(long, help = "Flashloan fee, to depositors")]
+        fl_fee: Option<f64>,
+        #[clap(long, help = "Flashloan fee, to group fees")]
+        fl_group_fee: Option<f64>,
+        #[clap(long, help = "Share of the group's origination fee paid to referrers")]
+        referrer_fee_share: Option<f64>,
         #[clap(long, arg_enum, help = "Bank risk tier")]
         risk_tier: Option<RiskTierArg>,
         #[clap(long, help = "0 = default, 1 = SOL, 2 = Staked SOL LST")]
@@ -318,6 +357,10 @@ pub enum BankCommand {
         rate_apr: f64,
         #[clap(long)]
         total_amount_ui: f64,
+        #[clap(long, help = "Unix timestamp, defaults to starting immediately")]
+        start_time: Option<i64>,
+        #[clap(long, help = "Unix timestamp, defaults to no end")]
+        end_time: Option<i64>,
     },
     UpdateEmissions {
         bank: Pubkey,
@@ -331,6 +374,15 @@ pub enum BankCommand {
         rate: Option<f64>,
         #[clap(long)]
         additional_amount_ui: Option<f64>,
+        #[clap(long, help = "Unix timestamp, 0 to start immediately")]
+        start_time: Option<i64>,
+        #[clap(long, help = "Unix timestamp, 0 for no end")]
+        end_time: Option<i64>,
+    },
+    ReclaimEmissions {
+        bank: Pubkey,
+        #[clap(help = "Destination address, defaults to the profile authority")]
+        destination_address: Option<Pubkey>,
     },
     SettleAllEmissions {
         bank: Pubkey,
@@ -352,6 +404,48 @@ pub enum BankCommand {
         #[clap(help = "Destination address, defaults to the profile authority")]
         destination_address: Option<Pubkey>,
     },
+    ConfigureInsuranceStaking {
+        bank: Pubkey,
+        #[clap(
+            long,
+            help = "Share of insurance fees going to stakers, between 0 and 1"
+        )]
+        fee_share: f64,
+        #[clap(
+            long,
+            help = "Seconds between requesting an unstake and withdrawing it"
+        )]
+        cooldown: i64,
+        #[clap(
+            long,
+            help = "Seconds after the cooldown to withdraw an unstake before it must be requested again"
+        )]
+        unstake_window: i64,
+    },
+    ClearStakedSettingsOverrides {
+        bank: Pubkey,
+        #[clap(
+            long,
+            help = "STAKED_OVERRIDE_* flags to hand back to the group's staked settings, defaults to all"
+        )]
+        overrides: Option<u8>,
+    },
+    InsuranceStake {
+        bank: Pubkey,
+        ui_amount: f64,
+    },
+    InsuranceRequestUnstake {
+        bank: Pubkey,
+        #[clap(default_value_t = 0.)]
+        ui_amount: f64,
+        #[clap(long, action)]
+        all: bool,
+    },
+    InsuranceUnstake {
+        bank: Pubkey,
+        #[clap(help = "Destination address, defaults to the profile authority")]
+        destination_address: Option<Pubkey>,
+    },
 }
 
 #[derive(Debug, Parser)]
@@ -409,7 +503,12 @@ pub enum ProfileCommand {
 
 #[derive(Debug, Parser)]
 pub enum AccountCommand {
-    List,
+    List {
+        /// Only look up the PDA accounts derived from indices up to this one, instead of scanning
+        /// the program for every account of the authority (for RPCs without program scans)
+        #[clap(long)]
+        pda_max_index: Option<u16>,
+    },
     Use {
         account: Pubkey,
     },
@@ -441,7 +540,11 @@ pub enum AccountCommand {
         #[clap(long)]
         ui_asset_amount: f64,
     },
-    Create,
+    Create {
+        /// Create the account at the PDA derived from this index instead of a fresh keypair
+        #[clap(long)]
+        pda_index: Option<u16>,
+    },
     Close,
     SetFlag {
         account_pk: Pubkey,
@@ -607,7 +710,22 @@ fn group(subcmd: GroupCommand, global_options: &GlobalOptions) -> Result<()> {
         GroupCommand::Update {
             new_admin,
             is_arena_group,
-        } => processor::group_configure(config, profile, new_admin, is_arena_group),
+            bankruptcy_threshold,
+            staked_stable_borrow_enabled,
+        } => processor::group_configure(
+            config,
+            profile,
+            new_admin,
+            is_arena_group,
+            GroupConfigOpt {
+                bankruptcy_threshold: bankruptcy_threshold.map(|x| I80F48::from_num(x).into()),
+            },
+            staked_stable_borrow_enabled,
+        ),
+
+        GroupCommand::ConfigureMintExtensions {
+            permitted_mint_extensions,
+        } => processor::group_configure_mint_extensions(config, profile, permitted_mint_extensions),
 
         GroupCommand::AddBank {
             mint: bank_mint,
@@ -705,6 +823,18 @@ fn group(subcmd: GroupCommand, global_options: &GlobalOptions) -> Result<()> {
         GroupCommand::PropagateFee { surroundfi_group } => {
             processor::propagate_fee(config, surroundfi_group)
         }
+        GroupCommand::ConfigureFeeDistribution { recipients } => {
+            processor::group_configure_fee_distribution(config, profile, recipients)
+        }
+        GroupCommand::DistributeFees { banks } => {
+            processor::group_distribute_fees(config, profile, banks)
+        }
+        GroupCommand::InitReferrerFees { referrer } => {
+            processor::group_init_referrer_fees(config, profile, referrer)
+        }
+        GroupCommand::ClaimReferrerFees { bank, destination } => {
+            processor::group_claim_referrer_fees(config, profile, bank, destination)
+        }
     }
 }
 
@@ -742,6 +872,9 @@ fn bank(subcmd: BankCommand, global_options: &GlobalOptions) -> Result<()> {
             pf_fa,
             pf_ir,
             pf_or,
+            fl_fee,
+            fl_group_fee,
+            referrer_fee_share,
             risk_tier,
             asset_tag,
             usd_init_limit,
@@ -780,6 +913,9 @@ fn bank(subcmd: BankCommand, global_options: &GlobalOptions) -> Result<()> {
                         protocol_fixed_fee_apr: pf_fa.map(|x| I80F48::from_num(x).into()),
                         protocol_ir_fee: pf_ir.map(|x| I80F48::from_num(x).into()),
                         protocol_origination_fee: pf_or.map(|x| I80F48::from_num(x).into()),
+                        flashloan_fee: fl_fee.map(|x| I80F48::from_num(x).into()),
+                        flashloan_group_fee: fl_group_fee.map(|x| I80F48::from_num(x).into()),
+                        referrer_fee_share: referrer_fee_share.map(|x| I80F48::from_num(x).into()),
                     }),
                     risk_tier: risk_tier.map(|x| x.into()),
                     asset_tag,
@@ -805,8 +941,19 @@ fn bank(subcmd: BankCommand, global_options: &GlobalOptions) -> Result<()> {
             mint,
             rate_apr: rate,
             total_amount_ui: total_ui,
+            start_time,
+            end_time,
         } => processor::bank_setup_emissions(
-            &config, &profile, bank, deposits, borrows, mint, rate, total_ui,
+            &config,
+            &profile,
+            bank,
+            deposits,
+            borrows,
+            mint,
+            rate,
+            total_ui,
+            start_time,
+            end_time,
         ),
         BankCommand::UpdateEmissions {
             bank,
@@ -815,6 +962,8 @@ fn bank(subcmd: BankCommand, global_options: &GlobalOptions) -> Result<()> {
             disable,
             rate,
             additional_amount_ui,
+            start_time,
+            end_time,
         } => processor::bank_update_emissions(
             &config,
             &profile,
@@ -824,7 +973,13 @@ fn bank(subcmd: BankCommand, global_options: &GlobalOptions) -> Result<()> {
             disable,
             rate,
             additional_amount_ui,
+            start_time,
+            end_time,
         ),
+        BankCommand::ReclaimEmissions {
+            bank,
+            destination_address,
+        } => processor::bank_reclaim_emissions(&config, &profile, bank, destination_address),
         BankCommand::SettleAllEmissions { bank } => {
             processor::emissions::claim_all_emissions_for_bank(&config, &profile, bank)
         }
@@ -843,6 +998,34 @@ fn bank(subcmd: BankCommand, global_options: &GlobalOptions) -> Result<()> {
         } => {
             processor::admin::process_withdraw_insurance(config, bank, amount, destination_address)
         }
+        BankCommand::ConfigureInsuranceStaking {
+            bank,
+            fee_share,
+            cooldown,
+            unstake_window,
+        } => processor::bank_configure_insurance_staking(
+            config,
+            profile,
+            bank,
+            fee_share,
+            cooldown,
+            unstake_window,
+        ),
+        BankCommand::ClearStakedSettingsOverrides { bank, overrides } => {
+            processor::bank_clear_staked_settings_overrides(config, profile, bank, overrides)
+        }
+        BankCommand::InsuranceStake { bank, ui_amount } => {
+            processor::bank_insurance_stake(config, bank, ui_amount)
+        }
+        BankCommand::InsuranceRequestUnstake {
+            bank,
+            ui_amount,
+            all,
+        } => processor::bank_insurance_request_unstake(config, bank, ui_amount, all),
+        BankCommand::InsuranceUnstake {
+            bank,
+            destination_address,
+        } => processor::bank_insurance_unstake(config, bank, destination_address),
     }
 }
 
@@ -927,13 +1110,15 @@ fn process_account_subcmd(subcmd: AccountCommand, global_options: &GlobalOptions
 
     if !global_options.skip_confirmation {
         match subcmd {
-            AccountCommand::Get { .. } | AccountCommand::List => (),
+            AccountCommand::Get { .. } | AccountCommand::List { .. } => (),
             _ => get_consent(&subcmd, &profile)?,
         }
     }
 
     match subcmd {
-        AccountCommand::List => processor::surroundfi_account_list(profile, &config),
+        AccountCommand::List { pda_max_index } => {
+            processor::surroundfi_account_list(profile, &config, pda_max_index)
+        }
         AccountCommand::Use { account } => {
             processor::surroundfi_account_use(profile, &config, account)
         }
@@ -972,7 +1157,9 @@ fn process_account_subcmd(subcmd: AccountCommand, global_options: &GlobalOptions
             liability_bank_pk,
             ui_asset_amount,
         ),
-        AccountCommand::Create => processor::surroundfi_account_create(&profile, &config),
+        AccountCommand::Create { pda_index } => {
+            processor::surroundfi_account_create(&profile, &config, pda_index)
+        }
         AccountCommand::Close => processor::surroundfi_account_close(&profile, &config),
         AccountCommand::SetFlag {
             flashloans_enabled: flashloan,
@@ -51,7 +51,7 @@ pub fn claim_all_emissions_for_bank(
                 bank: bank_pk,
             }
             .to_account_metas(Some(true)),
-            data: surroundfi::instruction::LendingAccountSettleEmissions {}.data(),
+            data: surroundfi::instruction::LendingAccountSettleEmissions { stream_id: None }.data(),
         })
         .collect::<Vec<_>>();
 
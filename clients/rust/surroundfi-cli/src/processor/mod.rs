@@ -10,12 +10,14 @@ use {
         utils::{
             bank_to_oracle_key, calc_emissions_rate, find_bank_emssions_auth_pda,
             find_bank_emssions_token_account_pda, find_bank_vault_authority_pda,
-            find_bank_vault_pda, find_fee_state_pda, load_observation_account_metas,
-            process_transaction, EXP_10_I80F48,
+            find_bank_vault_pda, find_fee_distribution_pda, find_fee_state_pda,
+            find_insurance_stake_pda, find_referrer_fees_pda, find_surroundfi_account_pda,
+            load_observation_account_metas, process_transaction, transfer_hook_metas, ui_to_native,
+            EXP_10_I80F48,
         },
     },
     anchor_client::{
-        anchor_lang::{InstructionData, ToAccountMetas},
+        anchor_lang::{AccountDeserialize, InstructionData, ToAccountMetas},
         Cluster,
     },
     anchor_spl::token_2022::spl_token_2022,
@@ -24,15 +26,25 @@ use {
     log::info,
     surroundfi::{
         constants::{
-            EMISSIONS_FLAG_BORROW_ACTIVE, EMISSIONS_FLAG_LENDING_ACTIVE,
-            PYTH_PUSH_PYTH_SPONSORED_SHARD_ID, ZERO_AMOUNT_THRESHOLD,
+            ASSET_TAG_STAKED, EMISSIONS_FLAG_BORROW_ACTIVE, EMISSIONS_FLAG_LENDING_ACTIVE,
+            MINT_EXTENSIONS_RESTRICTED, MINT_EXTENSION_CONFIDENTIAL_TRANSFER,
+            MINT_EXTENSION_DEFAULT_FROZEN, MINT_EXTENSION_FREEZE_AUTHORITY,
+            MINT_EXTENSION_INTEREST_BEARING, MINT_EXTENSION_METADATA,
+            MINT_EXTENSION_MINT_CLOSE_AUTHORITY, MINT_EXTENSION_NON_TRANSFERABLE,
+            MINT_EXTENSION_PAUSABLE, MINT_EXTENSION_PERMANENT_DELEGATE,
+            MINT_EXTENSION_TRANSFER_FEE, MINT_EXTENSION_TRANSFER_HOOK, MINT_EXTENSION_UNKNOWN,
+            PYTH_PUSH_PYTH_SPONSORED_SHARD_ID, STAKED_OVERRIDES, STAKED_OVERRIDE_ASSET_WEIGHTS,
+            STAKED_OVERRIDE_DEPOSIT_LIMIT, STAKED_OVERRIDE_LIABILITY_WEIGHTS,
+            STAKED_OVERRIDE_ORACLE, STAKED_OVERRIDE_ORACLE_MAX_AGE, STAKED_OVERRIDE_RISK_TIER,
+            STAKED_OVERRIDE_TOTAL_ASSET_VALUE_INIT_LIMIT, ZERO_AMOUNT_THRESHOLD,
         },
         prelude::*,
         state::{
-            surroundfi_account::{BankAccountWrapper, SurroundfiAccount},
+            fee_distribution::{FeeDistribution, FeeRecipientConfig},
+            surroundfi_account::{BalanceSide, BankAccountWrapper, SurroundfiAccount},
             surroundfi_group::{
-                Bank, BankConfigCompact, BankConfigOpt, BankOperationalState, BankVaultType,
-                InterestRateConfig, WrappedI80F48,
+                Bank, BankConfig, BankConfigCompact, BankConfigOpt, BankOperationalState,
+                BankVaultType, GroupConfigOpt, InterestRateConfig, WrappedI80F48,
             },
             price::{OraclePriceFeedAdapter, OracleSetup, PriceAdapter, PythPushOraclePriceFeed},
         },
@@ -67,6 +79,7 @@ use {
         fs, io,
         mem::size_of,
         ops::{Neg, Not},
+        str::FromStr,
         time::{Duration, SystemTime, UNIX_EPOCH},
     },
     switchboard_solana::AggregatorAccountData,
@@ -201,7 +214,64 @@ Last Update: {:?}h ago ({})
             .as_secs_f32()
             / 3600_f32,
         bank.last_update
-    )
+    );
+
+    if bank.config.asset_tag == ASSET_TAG_STAKED {
+        print_staked_settings_overrides(&bank.config);
+    }
+    if bank.mint_extensions != 0 {
+        print_mint_extensions(bank.mint_extensions);
+    }
+}
+
+fn print_mint_extensions(mint_extensions: u64) {
+    println!("Mint Extensions:");
+    for (extension, flag) in [
+        ("Transfer Fee", MINT_EXTENSION_TRANSFER_FEE),
+        ("Transfer Hook", MINT_EXTENSION_TRANSFER_HOOK),
+        ("Interest Bearing", MINT_EXTENSION_INTEREST_BEARING),
+        ("Mint Close Authority", MINT_EXTENSION_MINT_CLOSE_AUTHORITY),
+        ("Metadata", MINT_EXTENSION_METADATA),
+        ("Permanent Delegate", MINT_EXTENSION_PERMANENT_DELEGATE),
+        ("Default Frozen", MINT_EXTENSION_DEFAULT_FROZEN),
+        ("Non Transferable", MINT_EXTENSION_NON_TRANSFERABLE),
+        ("Confidential Transfer", MINT_EXTENSION_CONFIDENTIAL_TRANSFER),
+        ("Freeze Authority", MINT_EXTENSION_FREEZE_AUTHORITY),
+        ("Pausable", MINT_EXTENSION_PAUSABLE),
+        ("Unknown", MINT_EXTENSION_UNKNOWN),
+    ] {
+        if mint_extensions & flag == 0 {
+            continue;
+        }
+        if flag & MINT_EXTENSIONS_RESTRICTED != 0 {
+            println!("  {} (restricted)", extension);
+        } else {
+            println!("  {}", extension);
+        }
+    }
+}
+
+fn print_staked_settings_overrides(config: &BankConfig) {
+    println!("Staked Settings:");
+    for (field, flag) in [
+        ("Oracle", STAKED_OVERRIDE_ORACLE),
+        ("Asset Weights", STAKED_OVERRIDE_ASSET_WEIGHTS),
+        ("Liab Weights", STAKED_OVERRIDE_LIABILITY_WEIGHTS),
+        ("Deposit Limit", STAKED_OVERRIDE_DEPOSIT_LIMIT),
+        (
+            "USD Soft limit",
+            STAKED_OVERRIDE_TOTAL_ASSET_VALUE_INIT_LIMIT,
+        ),
+        ("Oracle Max Age", STAKED_OVERRIDE_ORACLE_MAX_AGE),
+        ("Risk Tier", STAKED_OVERRIDE_RISK_TIER),
+    ] {
+        let source = if config.is_staked_settings_override(flag) {
+            "overridden"
+        } else {
+            "inherited"
+        };
+        println!("  {}: {}", field, source);
+    }
 }
 
 pub fn group_create(
@@ -262,6 +332,8 @@ pub fn group_configure(
     profile: Profile,
     new_admin: Pubkey,
     is_arena_group: bool,
+    group_config: GroupConfigOpt,
+    staked_stable_borrow_enabled: Option<bool>,
 ) -> Result<()> {
     let rpc_client = config.sfi_program.rpc();
 
@@ -283,6 +355,8 @@ pub fn group_configure(
         .args(surroundfi::instruction::SurroundfiGroupConfigure {
             new_admin,
             is_arena_group,
+            group_config,
+            staked_stable_borrow_enabled,
         })
         .instructions()?;
 
@@ -299,6 +373,44 @@ pub fn group_configure(
     Ok(())
 }
 
+pub fn group_configure_mint_extensions(
+    config: Config,
+    profile: Profile,
+    permitted_mint_extensions: u64,
+) -> Result<()> {
+    let rpc_client = config.sfi_program.rpc();
+
+    if profile.surroundfi_group.is_none() {
+        bail!("Surroundfi group not specified in profile [{}]", profile.name);
+    }
+
+    let signing_keypairs = config.get_signers(false);
+    let configure_mint_extensions_ixs = config
+        .sfi_program
+        .request()
+        .signer(*signing_keypairs.first().unwrap())
+        .accounts(surroundfi::accounts::SurroundfiGroupConfigureMintExtensions {
+            surroundfi_group: profile.surroundfi_group.unwrap(),
+            admin: config.authority(),
+        })
+        .args(surroundfi::instruction::SurroundfiGroupConfigureMintExtensions {
+            permitted_mint_extensions,
+        })
+        .instructions()?;
+
+    let recent_blockhash = rpc_client.get_latest_blockhash().unwrap();
+    let message = Message::new(&configure_mint_extensions_ixs, Some(&config.authority()));
+    let mut transaction = Transaction::new_unsigned(message);
+    transaction.partial_sign(&signing_keypairs, recent_blockhash);
+
+    match process_transaction(&transaction, &rpc_client, config.get_tx_mode()) {
+        Ok(sig) => println!("Mint extensions configured (sig: {})", sig),
+        Err(err) => println!("Error during mint extensions configuration:\n{:#?}", err),
+    };
+
+    Ok(())
+}
+
 #[allow(clippy::too_many_arguments)]
 
 pub fn group_add_bank(
@@ -389,6 +501,7 @@ pub fn group_add_bank(
             risk_tier,
             oracle_max_age,
             global_fee_wallet,
+            transfer_hook_metas(&bank_mint, &mint_account),
         )?
     } else {
         create_bank_ix(
@@ -407,6 +520,7 @@ pub fn group_add_bank(
             risk_tier,
             oracle_max_age,
             global_fee_wallet,
+            transfer_hook_metas(&bank_mint, &mint_account),
         )?
     };
 
@@ -446,6 +560,7 @@ fn create_bank_ix_with_seed(
     risk_tier: crate::RiskTierArg,
     oracle_max_age: u16,
     global_fee_wallet: Pubkey,
+    transfer_hook_metas: Vec<AccountMeta>,
 ) -> Result<Vec<Instruction>> {
     use solana_sdk::commitment_config::CommitmentConfig;
 
@@ -518,6 +633,7 @@ fn create_bank_ix_with_seed(
             fee_state: find_fee_state_pda(&config.program_id).0,
             global_fee_wallet,
         })
+        .accounts(transfer_hook_metas)
         .args(surroundfi::instruction::LendingPoolAddBankWithSeed {
             bank_config: BankConfigCompact {
                 asset_weight_init,
@@ -559,6 +675,7 @@ fn create_bank_ix(
     risk_tier: crate::RiskTierArg,
     oracle_max_age: u16,
     global_fee_wallet: Pubkey,
+    transfer_hook_metas: Vec<AccountMeta>,
 ) -> Result<Vec<Instruction>> {
     let add_bank_ixs_builder = config.sfi_program.request();
     let add_bank_ixs = add_bank_ixs_builder
@@ -610,6 +727,7 @@ fn create_bank_ix(
             fee_state: find_fee_state_pda(&config.program_id).0,
             global_fee_wallet,
         })
+        .accounts(transfer_hook_metas)
         .args(surroundfi::instruction::LendingPoolAddBank {
             bank_config: BankConfigCompact {
                 asset_weight_init,
@@ -797,14 +915,11 @@ fn handle_bankruptcy_for_an_account(
     Ok(())
 }
 
-const BANKRUPTCY_CHUNKS: usize = 4;
-
 pub fn handle_bankruptcy_for_accounts(
     config: &Config,
     profile: &Profile,
     accounts: Vec<Pubkey>,
 ) -> Result<()> {
-    let mut instructions = vec![];
     let rpc_client = config.sfi_program.rpc();
 
     let banks = HashMap::from_iter(load_all_banks(
@@ -812,54 +927,25 @@ pub fn handle_bankruptcy_for_accounts(
         Some(profile.surroundfi_group.unwrap()),
     )?);
 
+    println!("Handling {} bankruptcies", accounts.len());
+
     for account in accounts {
         let surroundfi_account = config
             .sfi_program
             .account::<SurroundfiAccount>(account)
             .unwrap();
 
-        surroundfi_account
-            .lending_account
-            .balances
-            .iter()
-            .filter(|b| {
-                b.is_active()
-                    && banks
-                        .get(&b.bank_pk)
-                        .unwrap()
-                        .get_liability_amount(b.liability_shares.into())
-                        .unwrap()
-                        .is_positive_with_tolerance(ZERO_AMOUNT_THRESHOLD)
-            })
-            .map(|b| b.bank_pk)
-            .collect::<Vec<Pubkey>>()
-            .iter()
-            .for_each(|bank_pk| {
-                instructions.push(
-                    make_bankruptcy_ix(
-                        config,
-                        profile,
-                        &banks,
-                        account,
-                        &surroundfi_account,
-                        *bank_pk,
-                    )
-                    .unwrap(),
-                );
-            });
-    }
-
-    println!("Handling {} bankruptcies", instructions.len());
+        let handle_bankruptcy_ix =
+            make_account_bankruptcy_ix(config, profile, &banks, account, &surroundfi_account)?;
 
-    let chunks = instructions.chunks(BANKRUPTCY_CHUNKS);
-
-    for chunk in chunks {
         let recent_blockhash = rpc_client.get_latest_blockhash().unwrap();
 
         let signing_keypairs = config.get_signers(false);
 
-        let mut ixs = vec![ComputeBudgetInstruction::set_compute_unit_limit(1_400_000)];
-        ixs.extend_from_slice(chunk);
+        let ixs = vec![
+            ComputeBudgetInstruction::set_compute_unit_limit(1_400_000),
+            handle_bankruptcy_ix,
+        ];
 
         let message = Message::new(&ixs, Some(&config.authority()));
 
@@ -867,7 +953,7 @@ pub fn handle_bankruptcy_for_accounts(
         transaction.partial_sign(&signing_keypairs, recent_blockhash);
 
         match process_transaction(&transaction, &rpc_client, config.get_tx_mode()) {
-            Ok(sig) => println!("Bankruptcy handled (sig: {})", sig),
+            Ok(sig) => println!("Bankruptcy handled for {} (sig: {})", account, sig),
             Err(err) => println!("Error during bankruptcy handling:\n{:#?}", err),
         };
     }
@@ -875,65 +961,84 @@ pub fn handle_bankruptcy_for_accounts(
     Ok(())
 }
 
-fn make_bankruptcy_ix(
+/// Resolves all liabilities of the account, the banks are passed in the order of its balances.
+/// The banks of its assets are writable.
+fn make_account_bankruptcy_ix(
     config: &Config,
     profile: &Profile,
     banks: &HashMap<Pubkey, Bank>,
     surroundfi_account_pk: Pubkey,
     surroundfi_account: &SurroundfiAccount,
-    bank_pk: Pubkey,
 ) -> Result<Instruction> {
-    println!("Handling bankruptcy for bank {}", bank_pk);
     let rpc_client = config.sfi_program.rpc();
 
-    let bank = banks.get(&bank_pk).unwrap();
-
-    let bank_mint_account = rpc_client.get_account(&bank.mint)?;
-    let token_program = bank_mint_account.owner;
     let mut handle_bankruptcy_ix = Instruction {
         program_id: config.program_id,
-        accounts: surroundfi::accounts::LendingPoolHandleBankruptcy {
+        accounts: surroundfi::accounts::LendingPoolHandleAccountBankruptcy {
             group: profile.surroundfi_group.unwrap(),
             signer: config.fee_payer.pubkey(),
-            bank: bank_pk,
             surroundfi_account: surroundfi_account_pk,
-            liquidity_vault: find_bank_vault_pda(
-                &bank_pk,
-                BankVaultType::Liquidity,
-                &config.program_id,
-            )
-            .0,
-            insurance_vault: find_bank_vault_pda(
-                &bank_pk,
-                BankVaultType::Insurance,
-                &config.program_id,
-            )
-            .0,
-            insurance_vault_authority: find_bank_vault_authority_pda(
-                &bank_pk,
-                BankVaultType::Insurance,
-                &config.program_id,
-            )
-            .0,
-            token_program,
         }
         .to_account_metas(Some(true)),
-        data: surroundfi::instruction::LendingPoolHandleBankruptcy {}.data(),
+        data: surroundfi::instruction::LendingPoolHandleAccountBankruptcy {}.data(),
     };
 
-    if token_program == spl_token_2022::ID {
-        handle_bankruptcy_ix
-            .accounts
-            .push(AccountMeta::new_readonly(bank.mint, false));
+    let liability_bank_pks = surroundfi_account
+        .lending_account
+        .balances
+        .iter()
+        .filter(|b| b.is_active() && !b.is_empty(BalanceSide::Liabilities))
+        .map(|b| b.bank_pk);
+
+    for bank_pk in liability_bank_pks {
+        println!("Handling bankruptcy for bank {}", bank_pk);
+        let bank = banks.get(&bank_pk).unwrap();
+
+        let token_program = rpc_client.get_account(&bank.mint)?.owner;
+        handle_bankruptcy_ix.accounts.extend([
+            AccountMeta::new(bank_pk, false),
+            AccountMeta::new(
+                find_bank_vault_pda(&bank_pk, BankVaultType::Liquidity, &config.program_id).0,
+                false,
+            ),
+            AccountMeta::new(
+                find_bank_vault_pda(&bank_pk, BankVaultType::Insurance, &config.program_id).0,
+                false,
+            ),
+            AccountMeta::new_readonly(
+                find_bank_vault_authority_pda(
+                    &bank_pk,
+                    BankVaultType::Insurance,
+                    &config.program_id,
+                )
+                .0,
+                false,
+            ),
+            AccountMeta::new_readonly(token_program, false),
+        ]);
+        if token_program == spl_token_2022::ID {
+            handle_bankruptcy_ix
+                .accounts
+                .push(AccountMeta::new_readonly(bank.mint, false));
+        }
     }
-    handle_bankruptcy_ix
-        .accounts
-        .extend(load_observation_account_metas(
-            surroundfi_account,
-            banks,
-            vec![bank_pk],
-            vec![],
-        ));
+
+    // The banks of asset balances are writable, their dust is forfeited
+    let asset_bank_pks = surroundfi_account
+        .lending_account
+        .balances
+        .iter()
+        .filter(|b| b.is_active() && !b.is_empty(BalanceSide::Assets))
+        .map(|b| b.bank_pk)
+        .collect::<Vec<_>>();
+    handle_bankruptcy_ix.accounts.extend(
+        load_observation_account_metas(surroundfi_account, banks, vec![], vec![])
+            .into_iter()
+            .map(|mut meta| {
+                meta.is_writable |= asset_bank_pks.contains(&meta.pubkey);
+                meta
+            }),
+    );
 
     Ok(handle_bankruptcy_ix)
 }
@@ -1141,6 +1246,283 @@ pub fn propagate_fee(config: Config, surroundfi_group: Pubkey) -> Result<()> {
     Ok(())
 }
 
+pub fn group_configure_fee_distribution(
+    config: Config,
+    profile: Profile,
+    recipients: Vec<String>,
+) -> Result<()> {
+    let rpc_client = config.sfi_program.rpc();
+    let surroundfi_group_pubkey = profile.surroundfi_group.ok_or_else(|| {
+        anyhow!(
+            "Surroundfi group does not exist for profile [{}]",
+            profile.name
+        )
+    })?;
+
+    let recipients = recipients
+        .iter()
+        .map(|recipient| {
+            let (wallet, share_bps) = recipient.split_once(':').ok_or_else(|| {
+                anyhow!(
+                    "Invalid recipient {}, expected <WALLET>:<SHARE_BPS>",
+                    recipient
+                )
+            })?;
+            Ok(FeeRecipientConfig {
+                wallet: Pubkey::from_str(wallet)?,
+                share_bps: share_bps.parse()?,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let fee_distribution =
+        find_fee_distribution_pda(&surroundfi_group_pubkey, &config.program_id).0;
+    let is_initialized = rpc_client.get_account(&fee_distribution).is_ok();
+
+    let request = config.sfi_program.request();
+    let fee_distribution_ixs = if is_initialized {
+        request
+            .accounts(surroundfi::accounts::SurroundfiGroupConfigureFeeDistribution {
+                group: surroundfi_group_pubkey,
+                admin: config.authority(),
+                fee_distribution,
+            })
+            .args(surroundfi::instruction::SurroundfiGroupConfigureFeeDistribution { recipients })
+            .instructions()?
+    } else {
+        request
+            .accounts(surroundfi::accounts::SurroundfiGroupInitFeeDistribution {
+                group: surroundfi_group_pubkey,
+                admin: config.authority(),
+                fee_distribution,
+                system_program: system_program::id(),
+            })
+            .args(surroundfi::instruction::SurroundfiGroupInitFeeDistribution { recipients })
+            .instructions()?
+    };
+
+    let recent_blockhash = rpc_client.get_latest_blockhash().unwrap();
+    let message = Message::new(&fee_distribution_ixs, Some(&config.authority()));
+    let mut transaction = Transaction::new_unsigned(message);
+    transaction.partial_sign(&config.get_signers(false), recent_blockhash);
+
+    match process_transaction(&transaction, &rpc_client, config.get_tx_mode()) {
+        Ok(sig) => println!("Fee distribution configured (sig: {})", sig),
+        Err(err) => {
+            println!("Error during fee distribution configuration:\n{:#?}", err);
+            return Err(anyhow!("Error during fee distribution configuration"));
+        }
+    };
+
+    Ok(())
+}
+
+pub fn group_distribute_fees(config: Config, profile: Profile, banks: Vec<Pubkey>) -> Result<()> {
+    let rpc_client = config.sfi_program.rpc();
+    let surroundfi_group_pubkey = profile.surroundfi_group.ok_or_else(|| {
+        anyhow!(
+            "Surroundfi group does not exist for profile [{}]",
+            profile.name
+        )
+    })?;
+
+    let fee_distribution_pubkey =
+        find_fee_distribution_pda(&surroundfi_group_pubkey, &config.program_id).0;
+    let fee_distribution: FeeDistribution =
+        config.sfi_program.account(fee_distribution_pubkey)?;
+
+    let banks = if banks.is_empty() {
+        load_all_banks(&config, Some(surroundfi_group_pubkey))?
+    } else {
+        banks
+            .into_iter()
+            .map(|bank_pk| Ok((bank_pk, config.sfi_program.account::<Bank>(bank_pk)?)))
+            .collect::<Result<Vec<_>>>()?
+    };
+
+    for (bank_pk, bank) in banks {
+        let token_program = rpc_client.get_account(&bank.mint)?.owner;
+
+        let mut ixs = vec![];
+        let mut recipient_metas = vec![];
+        for recipient in fee_distribution.active_recipients() {
+            ixs.push(create_associated_token_account_idempotent(
+                &config.authority(),
+                &recipient.wallet,
+                &bank.mint,
+                &token_program,
+            ));
+            recipient_metas.push(AccountMeta::new(
+                anchor_spl::associated_token::get_associated_token_address_with_program_id(
+                    &recipient.wallet,
+                    &bank.mint,
+                    &token_program,
+                ),
+                false,
+            ));
+        }
+
+        let mut ix = Instruction {
+            program_id: config.program_id,
+            accounts: surroundfi::accounts::LendingPoolDistributeFees {
+                group: surroundfi_group_pubkey,
+                bank: bank_pk,
+                fee_distribution: fee_distribution_pubkey,
+                fee_vault: find_bank_vault_pda(&bank_pk, BankVaultType::Fee, &config.program_id).0,
+                fee_vault_authority: find_bank_vault_authority_pda(
+                    &bank_pk,
+                    BankVaultType::Fee,
+                    &config.program_id,
+                )
+                .0,
+                token_program,
+            }
+            .to_account_metas(Some(true)),
+            data: surroundfi::instruction::LendingPoolDistributeFees {}.data(),
+        };
+        if token_program == spl_token_2022::ID {
+            ix.accounts
+                .push(AccountMeta::new_readonly(bank.mint, false));
+        }
+        ix.accounts.extend(recipient_metas);
+        ixs.push(ix);
+
+        let recent_blockhash = rpc_client.get_latest_blockhash().unwrap();
+        let message = Message::new(&ixs, Some(&config.authority()));
+        let mut transaction = Transaction::new_unsigned(message);
+        transaction.partial_sign(&config.get_signers(false), recent_blockhash);
+
+        match process_transaction(&transaction, &rpc_client, config.get_tx_mode()) {
+            Ok(sig) => println!("Fees distributed for bank {} (sig: {})", bank_pk, sig),
+            Err(err) => println!("Error distributing fees for bank {}:\n{:#?}", bank_pk, err),
+        };
+    }
+
+    Ok(())
+}
+
+pub fn group_init_referrer_fees(
+    config: Config,
+    profile: Profile,
+    referrer: Option<Pubkey>,
+) -> Result<()> {
+    let rpc_client = config.sfi_program.rpc();
+    let surroundfi_group_pubkey = profile.surroundfi_group.ok_or_else(|| {
+        anyhow!(
+            "Surroundfi group does not exist for profile [{}]",
+            profile.name
+        )
+    })?;
+    let referrer = referrer.unwrap_or(config.authority());
+
+    let init_referrer_fees_ixs = config
+        .sfi_program
+        .request()
+        .accounts(surroundfi::accounts::SurroundfiGroupInitReferrerFees {
+            group: surroundfi_group_pubkey,
+            referrer,
+            fee_payer: config.authority(),
+            referrer_fees: find_referrer_fees_pda(
+                &surroundfi_group_pubkey,
+                &referrer,
+                &config.program_id,
+            )
+            .0,
+            system_program: system_program::id(),
+        })
+        .args(surroundfi::instruction::SurroundfiGroupInitReferrerFees {})
+        .instructions()?;
+
+    let recent_blockhash = rpc_client.get_latest_blockhash().unwrap();
+    let message = Message::new(&init_referrer_fees_ixs, Some(&config.authority()));
+    let mut transaction = Transaction::new_unsigned(message);
+    transaction.partial_sign(&config.get_signers(false), recent_blockhash);
+
+    match process_transaction(&transaction, &rpc_client, config.get_tx_mode()) {
+        Ok(sig) => println!("Referrer fees created for {} (sig: {})", referrer, sig),
+        Err(err) => {
+            println!("Error during referrer fees creation:\n{:#?}", err);
+            return Err(anyhow!("Error during referrer fees creation"));
+        }
+    };
+
+    Ok(())
+}
+
+pub fn group_claim_referrer_fees(
+    config: Config,
+    profile: Profile,
+    bank_pk: Pubkey,
+    destination: Option<Pubkey>,
+) -> Result<()> {
+    let rpc_client = config.sfi_program.rpc();
+    let surroundfi_group_pubkey = profile.surroundfi_group.ok_or_else(|| {
+        anyhow!(
+            "Surroundfi group does not exist for profile [{}]",
+            profile.name
+        )
+    })?;
+
+    let bank: Bank = config.sfi_program.account(bank_pk)?;
+    let token_program = rpc_client.get_account(&bank.mint)?.owner;
+    let destination = destination.unwrap_or(config.authority());
+    let destination_ata =
+        anchor_spl::associated_token::get_associated_token_address_with_program_id(
+            &destination,
+            &bank.mint,
+            &token_program,
+        );
+
+    let create_ata_ix = create_associated_token_account_idempotent(
+        &config.authority(),
+        &destination,
+        &bank.mint,
+        &token_program,
+    );
+
+    let mut ix = Instruction {
+        program_id: config.program_id,
+        accounts: surroundfi::accounts::LendingPoolClaimReferrerFees {
+            group: surroundfi_group_pubkey,
+            referrer: config.authority(),
+            referrer_fees: find_referrer_fees_pda(
+                &surroundfi_group_pubkey,
+                &config.authority(),
+                &config.program_id,
+            )
+            .0,
+            bank: bank_pk,
+            liquidity_vault_authority: find_bank_vault_authority_pda(
+                &bank_pk,
+                BankVaultType::Liquidity,
+                &config.program_id,
+            )
+            .0,
+            liquidity_vault: bank.liquidity_vault,
+            destination_token_account: destination_ata,
+            token_program,
+        }
+        .to_account_metas(Some(true)),
+        data: surroundfi::instruction::LendingPoolClaimReferrerFees {}.data(),
+    };
+    if token_program == spl_token_2022::ID {
+        ix.accounts
+            .push(AccountMeta::new_readonly(bank.mint, false));
+    }
+
+    let recent_blockhash = rpc_client.get_latest_blockhash().unwrap();
+    let message = Message::new(&[create_ata_ix, ix], Some(&config.authority()));
+    let mut transaction = Transaction::new_unsigned(message);
+    transaction.partial_sign(&config.get_signers(false), recent_blockhash);
+
+    match process_transaction(&transaction, &rpc_client, config.get_tx_mode()) {
+        Ok(sig) => println!("Referrer fees claimed (sig: {})", sig),
+        Err(err) => println!("Error during referrer fees claim:\n{:#?}", err),
+    };
+
+    Ok(())
+}
+
 // --------------------------------------------------------------------------------------------------------------------
 // bank
 // --------------------------------------------------------------------------------------------------------------------
@@ -1457,6 +1839,8 @@ pub fn bank_setup_emissions(
     mint: Pubkey,
     rate: f64,
     total: f64,
+    start_time: Option<i64>,
+    end_time: Option<i64>,
 ) -> Result<()> {
     let rpc_client = config.sfi_program.rpc();
 
@@ -1495,6 +1879,11 @@ pub fn bank_setup_emissions(
     );
     println!("Emissions flag: {:b}", flags);
     println!("Total native emissions: {}", total_emissions);
+    println!(
+        "Start time: {}, end time: {}",
+        start_time.map_or("immediately".to_string(), |t| t.to_string()),
+        end_time.map_or("none".to_string(), |t| t.to_string())
+    );
 
     // Get (y or n) input from user
     println!("Is this correct? (y/n)");
@@ -1530,6 +1919,8 @@ pub fn bank_setup_emissions(
             flags,
             rate,
             total_emissions,
+            start_time,
+            end_time,
         }
         .data(),
     };
@@ -1560,6 +1951,8 @@ pub fn bank_update_emissions(
     disable: bool,
     rate: Option<f64>,
     additional_emissions: Option<f64>,
+    start_time: Option<i64>,
+    end_time: Option<i64>,
 ) -> Result<()> {
     assert!(!(disable && (deposits || borrows)));
 
@@ -1607,10 +2000,12 @@ pub fn bank_update_emissions(
     };
 
     println!(
-        "Changes:\n\tRate: {:?}\n\tAdditional emissions: {:?}\n\tFlags: {:?}",
+        "Changes:\n\tRate: {:?}\n\tAdditional emissions: {:?}\n\tFlags: {:?}\n\tStart time: {:?}\n\tEnd time: {:?}",
         emissions_rate.map(|rate| format!("{} tokens per 1M bank tokens per YEAR", rate)),
         additional_emissions,
         emissions_flags.map(|flags| format!("{:b}", flags)),
+        start_time,
+        end_time,
     );
 
     // Get (y or n) input from user
@@ -1646,6 +2041,8 @@ pub fn bank_update_emissions(
             emissions_flags,
             emissions_rate,
             additional_emissions,
+            emissions_start_time: start_time,
+            emissions_end_time: end_time,
         }
         .data(),
     };
@@ -1665,6 +2062,73 @@ pub fn bank_update_emissions(
     Ok(())
 }
 
+pub fn bank_reclaim_emissions(
+    config: &Config,
+    profile: &Profile,
+    bank_pk: Pubkey,
+    destination_address: Option<Pubkey>,
+) -> Result<()> {
+    let rpc_client = config.sfi_program.rpc();
+
+    let bank = config
+        .sfi_program
+        .account::<Bank>(bank_pk)
+        .unwrap_or_else(|_| panic!("Bank {} not found", bank_pk));
+
+    let emissions_mint = bank.emissions_mint;
+    let token_program = rpc_client.get_account(&emissions_mint)?.owner;
+    let destination_address = destination_address.unwrap_or(config.authority());
+    let destination_ata =
+        anchor_spl::associated_token::get_associated_token_address_with_program_id(
+            &destination_address,
+            &emissions_mint,
+            &token_program,
+        );
+
+    let create_ata_ix = create_associated_token_account_idempotent(
+        &config.authority(),
+        &destination_address,
+        &emissions_mint,
+        &token_program,
+    );
+
+    let ix = Instruction {
+        program_id: config.program_id,
+        accounts: surroundfi::accounts::LendingPoolReclaimEmissions {
+            group: profile.surroundfi_group.expect("surroundfi group not set"),
+            admin: config.authority(),
+            bank: bank_pk,
+            emissions_mint,
+            emissions_auth: find_bank_emssions_auth_pda(bank_pk, emissions_mint, config.program_id)
+                .0,
+            emissions_vault: find_bank_emssions_token_account_pda(
+                bank_pk,
+                emissions_mint,
+                config.program_id,
+            )
+            .0,
+            destination_account: destination_ata,
+            token_program,
+        }
+        .to_account_metas(Some(true)),
+        data: surroundfi::instruction::LendingPoolReclaimEmissions {}.data(),
+    };
+
+    let recent_blockhash = rpc_client.get_latest_blockhash().unwrap();
+    let signing_keypairs = config.get_signers(false);
+
+    let message = Message::new(&[create_ata_ix, ix], Some(&config.authority()));
+    let mut transaction = Transaction::new_unsigned(message);
+    transaction.partial_sign(&signing_keypairs, recent_blockhash);
+
+    match process_transaction(&transaction, &rpc_client, config.get_tx_mode()) {
+        Ok(sig) => println!("Tx succeded (sig: {})", sig),
+        Err(err) => println!("Error:\n{:#?}", err),
+    };
+
+    Ok(())
+}
+
 pub fn bank_configure(
     config: Config,
     profile: Profile,
@@ -1757,6 +2221,260 @@ pub fn bank_configure_oracle(
     Ok(())
 }
 
+pub fn bank_configure_insurance_staking(
+    config: Config,
+    profile: Profile,
+    bank_pk: Pubkey,
+    fee_share: f64,
+    cooldown: i64,
+    unstake_window: i64,
+) -> Result<()> {
+    let rpc_client = config.sfi_program.rpc();
+
+    let configure_ixs = config
+        .sfi_program
+        .request()
+        .accounts(surroundfi::accounts::LendingPoolConfigureInsuranceStaking {
+            group: profile.surroundfi_group.unwrap(),
+            admin: config.authority(),
+            bank: bank_pk,
+        })
+        .args(surroundfi::instruction::LendingPoolConfigureInsuranceStaking {
+            fee_share: I80F48::from_num(fee_share).into(),
+            cooldown,
+            unstake_window,
+        })
+        .instructions()?;
+
+    let recent_blockhash = rpc_client.get_latest_blockhash().unwrap();
+    let message = Message::new(&configure_ixs, Some(&config.authority()));
+    let mut transaction = Transaction::new_unsigned(message);
+    transaction.partial_sign(&config.get_signers(false), recent_blockhash);
+
+    let sig = process_transaction(&transaction, &rpc_client, config.get_tx_mode())?;
+
+    println!("Transaction signature: {}", sig);
+
+    Ok(())
+}
+
+/// Run `propagate_staked_settings` afterwards to restore the cleared fields
+pub fn bank_clear_staked_settings_overrides(
+    config: Config,
+    profile: Profile,
+    bank_pk: Pubkey,
+    overrides: Option<u8>,
+) -> Result<()> {
+    let rpc_client = config.sfi_program.rpc();
+
+    let clear_ixs = config
+        .sfi_program
+        .request()
+        .accounts(surroundfi::accounts::LendingPoolClearStakedSettingsOverrides {
+            group: profile.surroundfi_group.unwrap(),
+            admin: config.authority(),
+            bank: bank_pk,
+        })
+        .args(surroundfi::instruction::LendingPoolClearStakedSettingsOverrides {
+            overrides: overrides.unwrap_or(STAKED_OVERRIDES),
+        })
+        .instructions()?;
+
+    let recent_blockhash = rpc_client.get_latest_blockhash().unwrap();
+    let message = Message::new(&clear_ixs, Some(&config.authority()));
+    let mut transaction = Transaction::new_unsigned(message);
+    transaction.partial_sign(&config.get_signers(false), recent_blockhash);
+
+    let sig = process_transaction(&transaction, &rpc_client, config.get_tx_mode())?;
+
+    println!("Transaction signature: {}", sig);
+
+    Ok(())
+}
+
+/// Stake from the authority's ATA, creating its insurance stake account first if needed
+pub fn bank_insurance_stake(config: Config, bank_pk: Pubkey, amount_ui: f64) -> Result<()> {
+    let rpc_client = config.sfi_program.rpc();
+
+    let bank: Bank = config.sfi_program.account(bank_pk)?;
+    let token_program = rpc_client.get_account(&bank.mint)?.owner;
+    let insurance_stake =
+        find_insurance_stake_pda(&bank_pk, &config.authority(), &config.program_id).0;
+
+    let mut ixs = vec![];
+    if rpc_client.get_account(&insurance_stake).is_err() {
+        ixs.extend(
+            config
+                .sfi_program
+                .request()
+                .accounts(surroundfi::accounts::LendingPoolInitInsuranceStake {
+                    bank: bank_pk,
+                    authority: config.authority(),
+                    insurance_stake,
+                    system_program: system_program::id(),
+                })
+                .args(surroundfi::instruction::LendingPoolInitInsuranceStake {})
+                .instructions()?,
+        );
+    }
+
+    let mut stake_ix = Instruction {
+        program_id: config.program_id,
+        accounts: surroundfi::accounts::LendingPoolInsuranceStake {
+            group: bank.group,
+            bank: bank_pk,
+            authority: config.authority(),
+            insurance_stake,
+            signer_token_account:
+                anchor_spl::associated_token::get_associated_token_address_with_program_id(
+                    &config.authority(),
+                    &bank.mint,
+                    &token_program,
+                ),
+            insurance_vault: bank.insurance_vault,
+            token_program,
+        }
+        .to_account_metas(Some(true)),
+        data: surroundfi::instruction::LendingPoolInsuranceStake {
+            amount: ui_to_native(amount_ui, bank.mint_decimals),
+        }
+        .data(),
+    };
+    if token_program == spl_token_2022::ID {
+        stake_ix
+            .accounts
+            .push(AccountMeta::new_readonly(bank.mint, false));
+    }
+    ixs.push(stake_ix);
+
+    let recent_blockhash = rpc_client.get_latest_blockhash().unwrap();
+    let message = Message::new(&ixs, Some(&config.authority()));
+    let mut transaction = Transaction::new_unsigned(message);
+    transaction.partial_sign(&config.get_signers(false), recent_blockhash);
+
+    match process_transaction(&transaction, &rpc_client, config.get_tx_mode()) {
+        Ok(sig) => println!(
+            "Staked {} into the insurance fund (sig: {})",
+            amount_ui, sig
+        ),
+        Err(err) => println!("Error during insurance stake:\n{:#?}", err),
+    };
+
+    Ok(())
+}
+
+pub fn bank_insurance_request_unstake(
+    config: Config,
+    bank_pk: Pubkey,
+    amount_ui: f64,
+    unstake_all: bool,
+) -> Result<()> {
+    let rpc_client = config.sfi_program.rpc();
+
+    let bank: Bank = config.sfi_program.account(bank_pk)?;
+
+    let request_unstake_ixs = config
+        .sfi_program
+        .request()
+        .accounts(surroundfi::accounts::LendingPoolInsuranceRequestUnstake {
+            bank: bank_pk,
+            authority: config.authority(),
+            insurance_stake: find_insurance_stake_pda(
+                &bank_pk,
+                &config.authority(),
+                &config.program_id,
+            )
+            .0,
+        })
+        .args(surroundfi::instruction::LendingPoolInsuranceRequestUnstake {
+            amount: ui_to_native(amount_ui, bank.mint_decimals),
+            unstake_all: if unstake_all { Some(true) } else { None },
+        })
+        .instructions()?;
+
+    let recent_blockhash = rpc_client.get_latest_blockhash().unwrap();
+    let message = Message::new(&request_unstake_ixs, Some(&config.authority()));
+    let mut transaction = Transaction::new_unsigned(message);
+    transaction.partial_sign(&config.get_signers(false), recent_blockhash);
+
+    match process_transaction(&transaction, &rpc_client, config.get_tx_mode()) {
+        Ok(sig) => println!(
+            "Unstake requested, withdrawable in {}s for {}s (sig: {})",
+            bank.insurance_stake_cooldown, bank.insurance_stake_unstake_window, sig
+        ),
+        Err(err) => println!("Error during unstake request:\n{:#?}", err),
+    };
+
+    Ok(())
+}
+
+pub fn bank_insurance_unstake(
+    config: Config,
+    bank_pk: Pubkey,
+    destination: Option<Pubkey>,
+) -> Result<()> {
+    let rpc_client = config.sfi_program.rpc();
+
+    let bank: Bank = config.sfi_program.account(bank_pk)?;
+    let token_program = rpc_client.get_account(&bank.mint)?.owner;
+    let destination = destination.unwrap_or(config.authority());
+    let destination_ata =
+        anchor_spl::associated_token::get_associated_token_address_with_program_id(
+            &destination,
+            &bank.mint,
+            &token_program,
+        );
+
+    let create_ata_ix = create_associated_token_account_idempotent(
+        &config.authority(),
+        &destination,
+        &bank.mint,
+        &token_program,
+    );
+
+    let mut ix = Instruction {
+        program_id: config.program_id,
+        accounts: surroundfi::accounts::LendingPoolInsuranceUnstake {
+            group: bank.group,
+            bank: bank_pk,
+            authority: config.authority(),
+            insurance_stake: find_insurance_stake_pda(
+                &bank_pk,
+                &config.authority(),
+                &config.program_id,
+            )
+            .0,
+            insurance_vault: bank.insurance_vault,
+            insurance_vault_authority: find_bank_vault_authority_pda(
+                &bank_pk,
+                BankVaultType::Insurance,
+                &config.program_id,
+            )
+            .0,
+            destination_token_account: destination_ata,
+            token_program,
+        }
+        .to_account_metas(Some(true)),
+        data: surroundfi::instruction::LendingPoolInsuranceUnstake {}.data(),
+    };
+    if token_program == spl_token_2022::ID {
+        ix.accounts
+            .push(AccountMeta::new_readonly(bank.mint, false));
+    }
+
+    let recent_blockhash = rpc_client.get_latest_blockhash().unwrap();
+    let message = Message::new(&[create_ata_ix, ix], Some(&config.authority()));
+    let mut transaction = Transaction::new_unsigned(message);
+    transaction.partial_sign(&config.get_signers(false), recent_blockhash);
+
+    match process_transaction(&transaction, &rpc_client, config.get_tx_mode()) {
+        Ok(sig) => println!("Unstaked from the insurance fund (sig: {})", sig),
+        Err(err) => println!("Error during insurance unstake:\n{:#?}", err),
+    };
+
+    Ok(())
+}
+
 // --------------------------------------------------------------------------------------------------------------------
 // Profile
 // --------------------------------------------------------------------------------------------------------------------
@@ -1923,16 +2641,27 @@ pub fn delete_profile(name: String) -> Result<()> {
 // Surroundfi Accounts
 // --------------------------------------------------------------------------------------------------------------------
 
-pub fn surroundfi_account_list(profile: Profile, config: &Config) -> Result<()> {
+/// Max number of addresses fetched in one `get_multiple_accounts` call.
+const PDA_ACCOUNT_DISCOVERY_BATCH_SIZE: usize = 100;
+
+pub fn surroundfi_account_list(
+    profile: Profile,
+    config: &Config,
+    pda_max_index: Option<u16>,
+) -> Result<()> {
     let group = profile.surroundfi_group.expect("Missing surroundfi group");
     let authority = config.authority();
 
     let banks = HashMap::from_iter(load_all_banks(config, Some(group))?);
 
-    let accounts = config.sfi_program.accounts::<SurroundfiAccount>(vec![
-        RpcFilterType::Memcmp(Memcmp::new_raw_bytes(8, group.to_bytes().to_vec())),
-        RpcFilterType::Memcmp(Memcmp::new_raw_bytes(8 + 32, authority.to_bytes().to_vec())),
-    ])?;
+    // Scanning the program finds accounts created from a keypair as well as PDA accounts
+    let accounts = match pda_max_index {
+        Some(max_index) => find_pda_surroundfi_accounts(config, &group, &authority, max_index)?,
+        None => config.sfi_program.accounts::<SurroundfiAccount>(vec![
+            RpcFilterType::Memcmp(Memcmp::new_raw_bytes(8, group.to_bytes().to_vec())),
+            RpcFilterType::Memcmp(Memcmp::new_raw_bytes(8 + 32, authority.to_bytes().to_vec())),
+        ])?,
+    };
 
     if accounts.is_empty() {
         println!("No surroundfi accounts found");
@@ -1952,6 +2681,36 @@ pub fn surroundfi_account_list(profile: Profile, config: &Config) -> Result<()>
     Ok(())
 }
 
+/// Find the PDA accounts derived (without a third-party id) from every index up to `max_index`.
+/// All indices are checked, so accounts after a gap in the indices are found too.
+fn find_pda_surroundfi_accounts(
+    config: &Config,
+    group: &Pubkey,
+    authority: &Pubkey,
+    max_index: u16,
+) -> Result<Vec<(Pubkey, SurroundfiAccount)>> {
+    let rpc_client = config.sfi_program.rpc();
+    let mut accounts = vec![];
+
+    let addresses = (0..=max_index)
+        .map(|index| {
+            find_surroundfi_account_pda(group, authority, index, None, &config.program_id).0
+        })
+        .collect::<Vec<_>>();
+
+    for batch in addresses.chunks(PDA_ACCOUNT_DISCOVERY_BATCH_SIZE) {
+        for (address, maybe_account) in batch.iter().zip(rpc_client.get_multiple_accounts(batch)?) {
+            if let Some(account) = maybe_account {
+                let surroundfi_account =
+                    SurroundfiAccount::try_deserialize(&mut account.data.as_slice())?;
+                accounts.push((*address, surroundfi_account));
+            }
+        }
+    }
+
+    Ok(accounts)
+}
+
 pub fn print_account(
     address: Pubkey,
     surroundfi_account: SurroundfiAccount,
@@ -1995,6 +2754,7 @@ pub fn print_account(
             let mut baw = BankAccountWrapper {
                 bank: &mut bank,
                 balance: &mut balance,
+                emissions_streams_settled: false,
             };
 
             // Current timestamp
@@ -2475,36 +3235,79 @@ pub fn surroundfi_account_liquidate(
     Ok(())
 }
 
-pub fn surroundfi_account_create(profile: &Profile, config: &Config) -> Result<()> {
+pub fn surroundfi_account_create(
+    profile: &Profile,
+    config: &Config,
+    pda_index: Option<u16>,
+) -> Result<()> {
     let signer = config.get_non_ms_authority_keypair()?;
 
     let rpc_client = config.sfi_program.rpc();
+    let surroundfi_group = profile.surroundfi_group.unwrap();
 
     let surroundfi_account_key = Keypair::new();
 
-    let ix = Instruction {
-        program_id: config.program_id,
-        accounts: surroundfi::accounts::SurroundfiAccountInitialize {
-            surroundfi_group: profile.surroundfi_group.unwrap(),
-            surroundfi_account: surroundfi_account_key.pubkey(),
-            system_program: system_program::ID,
-            authority: signer.pubkey(),
-            fee_payer: signer.pubkey(),
+    let (ix, surroundfi_account_pk) = match pda_index {
+        Some(account_index) => {
+            let (surroundfi_account_pk, _) = find_surroundfi_account_pda(
+                &surroundfi_group,
+                &signer.pubkey(),
+                account_index,
+                None,
+                &config.program_id,
+            );
+
+            let ix = Instruction {
+                program_id: config.program_id,
+                accounts: surroundfi::accounts::SurroundfiAccountInitializePda {
+                    surroundfi_group,
+                    surroundfi_account: surroundfi_account_pk,
+                    system_program: system_program::ID,
+                    authority: signer.pubkey(),
+                    fee_payer: signer.pubkey(),
+                }
+                .to_account_metas(Some(true)),
+                data: surroundfi::instruction::SurroundfiAccountInitializePda {
+                    account_index,
+                    third_party_id: None,
+                }
+                .data(),
+            };
+
+            (ix, surroundfi_account_pk)
+        }
+        None => {
+            let ix = Instruction {
+                program_id: config.program_id,
+                accounts: surroundfi::accounts::SurroundfiAccountInitialize {
+                    surroundfi_group,
+                    surroundfi_account: surroundfi_account_key.pubkey(),
+                    system_program: system_program::ID,
+                    authority: signer.pubkey(),
+                    fee_payer: signer.pubkey(),
+                }
+                .to_account_metas(Some(true)),
+                data: surroundfi::instruction::SurroundfiAccountInitialize.data(),
+            };
+
+            (ix, surroundfi_account_key.pubkey())
         }
-        .to_account_metas(Some(true)),
-        data: surroundfi::instruction::SurroundfiAccountInitialize.data(),
+    };
+
+    let signers: Vec<&Keypair> = if pda_index.is_some() {
+        vec![signer]
+    } else {
+        vec![signer, &surroundfi_account_key]
     };
 
     let recent_blockhash = rpc_client.get_latest_blockhash().unwrap();
     let tx = Transaction::new_signed_with_payer(
         &[ix],
         Some(&signer.pubkey()),
-        &[signer, &surroundfi_account_key],
+        &signers,
         recent_blockhash,
     );
 
-    let surroundfi_account_pk = surroundfi_account_key.pubkey();
-
     match process_transaction(&tx, &config.sfi_program.rpc(), config.get_tx_mode()) {
         Ok(_sig) => print!("{surroundfi_account_pk}"),
         Err(err) => println!("Error during initialize:\n{err:#?}"),
@@ -2521,7 +3324,7 @@ pub fn surroundfi_account_create(profile: &Profile, config: &Config) -> Result<(
         None,
         None,
         None,
-        Some(surroundfi_account_key.pubkey()),
+        Some(surroundfi_account_pk),
     )?;
 
     Ok(())
@@ -1,5 +1,9 @@
 use {
     crate::config::TxMode,
+    anchor_spl::token_2022::spl_token_2022::{
+        self,
+        extension::{transfer_hook, StateWithExtensions},
+    },
     anyhow::{bail, Result},
     fixed::types::I80F48,
     fixed_macro::types::I80F48,
@@ -7,8 +11,9 @@ use {
     surroundfi::{
         bank_authority_seed, bank_seed,
         constants::{
-            EMISSIONS_AUTH_SEED, EMISSIONS_TOKEN_ACCOUNT_SEED, FEE_STATE_SEED,
-            PYTH_PUSH_PYTH_SPONSORED_SHARD_ID,
+            EMISSIONS_AUTH_SEED, EMISSIONS_TOKEN_ACCOUNT_SEED, FEE_DISTRIBUTION_SEED,
+            FEE_STATE_SEED, INSURANCE_STAKE_SEED, PYTH_PUSH_PYTH_SPONSORED_SHARD_ID,
+            REFERRER_FEES_SEED, SURROUNDFI_ACCOUNT_SEED,
         },
         state::{
             surroundfi_account::SurroundfiAccount,
@@ -18,7 +23,8 @@ use {
     },
     solana_client::rpc_client::RpcClient,
     solana_sdk::{
-        instruction::AccountMeta, pubkey::Pubkey, signature::Signature, transaction::Transaction,
+        account::Account, instruction::AccountMeta, pubkey::Pubkey, signature::Signature,
+        transaction::Transaction,
     },
     std::collections::HashMap,
 };
@@ -130,6 +136,62 @@ pub fn find_fee_state_pda(program_id: &Pubkey) -> (Pubkey, u8) {
     Pubkey::find_program_address(&[FEE_STATE_SEED.as_bytes()], program_id)
 }
 
+pub fn find_fee_distribution_pda(group: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[FEE_DISTRIBUTION_SEED.as_bytes(), group.as_ref()],
+        program_id,
+    )
+}
+
+pub fn find_referrer_fees_pda(
+    group: &Pubkey,
+    referrer: &Pubkey,
+    program_id: &Pubkey,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            REFERRER_FEES_SEED.as_bytes(),
+            group.as_ref(),
+            referrer.as_ref(),
+        ],
+        program_id,
+    )
+}
+
+pub fn find_insurance_stake_pda(
+    bank: &Pubkey,
+    authority: &Pubkey,
+    program_id: &Pubkey,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            INSURANCE_STAKE_SEED.as_bytes(),
+            bank.as_ref(),
+            authority.as_ref(),
+        ],
+        program_id,
+    )
+}
+
+pub fn find_surroundfi_account_pda(
+    group: &Pubkey,
+    authority: &Pubkey,
+    account_index: u16,
+    third_party_id: Option<u16>,
+    program_id: &Pubkey,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            SURROUNDFI_ACCOUNT_SEED.as_bytes(),
+            group.as_ref(),
+            authority.as_ref(),
+            &account_index.to_le_bytes(),
+            &third_party_id.unwrap_or(0).to_le_bytes(),
+        ],
+        program_id,
+    )
+}
+
 pub const EXP_10_I80F48: [I80F48; 15] = [
     I80F48!(1),
     I80F48!(10),
@@ -181,7 +243,7 @@ pub fn load_observation_account_metas(
         .flat_map(|(bank, bank_pk)| {
             let oracle_key = bank_to_oracle_key(&bank.config, PYTH_PUSH_PYTH_SPONSORED_SHARD_ID);
 
-            vec![
+            let mut metas = vec![
                 AccountMeta {
                     pubkey: *bank_pk,
                     is_signer: false,
@@ -192,12 +254,41 @@ pub fn load_observation_account_metas(
                     is_signer: false,
                     is_writable: false,
                 },
-            ]
+            ];
+            // Interest-bearing mints are priced at their UI amount, which needs the mint
+            if bank.is_interest_bearing() {
+                metas.push(AccountMeta::new_readonly(bank.mint, false));
+            }
+            metas
         })
         .collect::<Vec<_>>();
     account_metas
 }
 
+/// The hook program and extra account metas account of a Token-2022 mint with a transfer hook,
+/// which `lending_pool_add_bank` validates. Empty for any other mint.
+pub fn transfer_hook_metas(mint: &Pubkey, mint_account: &Account) -> Vec<AccountMeta> {
+    if mint_account.owner != spl_token_2022::ID {
+        return vec![];
+    }
+
+    StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&mint_account.data)
+        .ok()
+        .and_then(|mint_state| transfer_hook::get_program_id(&mint_state))
+        .map(|hook_program_id| {
+            let (extra_metas_address, _) = Pubkey::find_program_address(
+                &[b"extra-account-metas", mint.as_ref()],
+                &hook_program_id,
+            );
+
+            vec![
+                AccountMeta::new_readonly(hook_program_id, false),
+                AccountMeta::new_readonly(extra_metas_address, false),
+            ]
+        })
+        .unwrap_or_default()
+}
+
 pub fn calc_emissions_rate(ui_rate: f64, emissions_mint_decimals: u8) -> u64 {
     (ui_rate * 10u64.pow(emissions_mint_decimals as u32) as f64) as u64
 }
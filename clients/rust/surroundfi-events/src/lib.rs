@@ -0,0 +1,1271 @@
+//! Typed decoders for every version of the events emitted by the surroundfi program.
+//!
+//! Events are Anchor events: an 8 byte discriminator derived from the event name, followed by the
+//! borsh serialized event. Since version 2, the first byte after the discriminator is the event's
+//! schema version (`surroundfi::events::EVENT_SCHEMA_VERSION`). Each version's layouts live in
+//! their own module and never change once released, so events stay decodable after upgrades.
+//!
+//! Events of the marginfi program this one was forked from predate schema versions. Their layouts,
+//! ported from the ETL's IDLs, live in the `marginfi_*` modules and are tried when an event doesn't
+//! match the layout of its apparent version.
+
+use anchor_lang::prelude::Pubkey;
+use base64::{engine::general_purpose, Engine};
+use serde::{ser::SerializeStruct, Serialize, Serializer};
+use thiserror::Error;
+
+/// Declare the `Event` enum of a version, decoding its events by discriminator
+macro_rules! decode_events {
+    ($($name:ident),* $(,)?) => {
+        #[derive(Debug, Clone, PartialEq, serde::Serialize)]
+        #[serde(untagged)]
+        pub enum Event {
+            $($name($name),)*
+        }
+
+        impl Event {
+            /// Names of the events of this version
+            pub const NAMES: &'static [&'static str] = &[$(stringify!($name),)*];
+
+            pub fn name(&self) -> &'static str {
+                match self {
+                    $(Event::$name(_) => stringify!($name),)*
+                }
+            }
+
+            /// Event data, as logged by `emit!`
+            pub fn data(&self) -> Vec<u8> {
+                match self {
+                    $(Event::$name(event) => anchor_lang::Event::data(event),)*
+                }
+            }
+
+            /// None if no event of this version has `discriminator`
+            pub fn decode(discriminator: &[u8], payload: &[u8]) -> Option<std::io::Result<Self>> {
+                $(
+                    if discriminator == <$name as anchor_lang::Discriminator>::DISCRIMINATOR {
+                        return Some(
+                            <$name as anchor_lang::AnchorDeserialize>::try_from_slice(payload)
+                                .map(Event::$name),
+                        );
+                    }
+                )*
+                None
+            }
+        }
+    };
+}
+
+pub mod marginfi_mainnet_v0;
+pub mod marginfi_v0;
+pub mod marginfi_v3;
+pub mod marginfi_v4;
+pub mod types;
+pub mod v1;
+pub mod v2;
+
+pub const LATEST_VERSION: u8 = v2::VERSION;
+
+const PROGRAM_DATA: &str = "Program data: ";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SurroundfiEvent {
+    MarginfiV0(marginfi_v0::Event),
+    MarginfiV3(marginfi_v3::Event),
+    MarginfiV4(marginfi_v4::Event),
+    MarginfiMainnetV0(marginfi_mainnet_v0::Event),
+    V1(v1::Event),
+    V2(v2::Event),
+}
+
+impl SurroundfiEvent {
+    /// 0 for the marginfi layouts
+    pub fn version(&self) -> u8 {
+        match self {
+            SurroundfiEvent::MarginfiV0(_) => marginfi_v0::VERSION,
+            SurroundfiEvent::MarginfiV3(_) => marginfi_v3::VERSION,
+            SurroundfiEvent::MarginfiV4(_) => marginfi_v4::VERSION,
+            SurroundfiEvent::MarginfiMainnetV0(_) => marginfi_mainnet_v0::VERSION,
+            SurroundfiEvent::V1(_) => v1::VERSION,
+            SurroundfiEvent::V2(_) => v2::VERSION,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SurroundfiEvent::MarginfiV0(event) => event.name(),
+            SurroundfiEvent::MarginfiV3(event) => event.name(),
+            SurroundfiEvent::MarginfiV4(event) => event.name(),
+            SurroundfiEvent::MarginfiMainnetV0(event) => event.name(),
+            SurroundfiEvent::V1(event) => event.name(),
+            SurroundfiEvent::V2(event) => event.name(),
+        }
+    }
+}
+
+/// Serialized as `{ "name", "version", "data" }`, `data` holding the event's fields
+impl Serialize for SurroundfiEvent {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut event = serializer.serialize_struct("SurroundfiEvent", 3)?;
+        event.serialize_field("name", self.name())?;
+        event.serialize_field("version", &self.version())?;
+        match self {
+            SurroundfiEvent::MarginfiV0(data) => event.serialize_field("data", data)?,
+            SurroundfiEvent::MarginfiV3(data) => event.serialize_field("data", data)?,
+            SurroundfiEvent::MarginfiV4(data) => event.serialize_field("data", data)?,
+            SurroundfiEvent::MarginfiMainnetV0(data) => event.serialize_field("data", data)?,
+            SurroundfiEvent::V1(data) => event.serialize_field("data", data)?,
+            SurroundfiEvent::V2(data) => event.serialize_field("data", data)?,
+        }
+        event.end()
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum DecodeError {
+    #[error("Event data is shorter than a discriminator")]
+    TooShort,
+    #[error("Unknown event discriminator {0:?}")]
+    UnknownDiscriminator([u8; 8]),
+    #[error("Unknown event schema version {0}")]
+    UnknownVersion(u8),
+    #[error("Event does not match its version {version} layout: {source}")]
+    InvalidLayout { version: u8, source: std::io::Error },
+    #[error("Invalid program data log: {0}")]
+    InvalidLog(#[from] base64::DecodeError),
+}
+
+/// Schema version of an event payload, the event data after its discriminator
+pub fn schema_version(payload: &[u8]) -> u8 {
+    match payload.first() {
+        // Version 1 headers start with the `signer` option tag
+        None | Some(0) | Some(1) => v1::VERSION,
+        Some(version) => *version,
+    }
+}
+
+/// Decode event data, as logged by `emit!`, with the layout of the version it was emitted with
+pub fn decode_event(data: &[u8]) -> Result<SurroundfiEvent, DecodeError> {
+    if data.len() < 8 {
+        return Err(DecodeError::TooShort);
+    }
+    let (discriminator, payload) = data.split_at(8);
+
+    let version = schema_version(payload);
+    let result = decode_version(version, discriminator, payload);
+
+    if result.is_ok() {
+        return result;
+    }
+
+    // The version 1 `EditStakedSettingsEvent` has no header, its first byte belongs to a pubkey
+    if version != v1::VERSION {
+        if let Ok(event) = decode_version(v1::VERSION, discriminator, payload) {
+            return Ok(event);
+        }
+    }
+
+    decode_marginfi(discriminator, payload).map_or(result, Ok)
+}
+
+/// Decode an event emitted with one of the marginfi layouts, newest first. Those predate schema
+/// versions, and events whose layout didn't change since decode as the later version.
+fn decode_marginfi(discriminator: &[u8], payload: &[u8]) -> Option<SurroundfiEvent> {
+    marginfi_mainnet_v0::Event::decode(discriminator, payload)
+        .and_then(Result::ok)
+        .map(SurroundfiEvent::MarginfiMainnetV0)
+        .or_else(|| {
+            marginfi_v4::Event::decode(discriminator, payload)
+                .and_then(Result::ok)
+                .map(SurroundfiEvent::MarginfiV4)
+        })
+        .or_else(|| {
+            marginfi_v3::Event::decode(discriminator, payload)
+                .and_then(Result::ok)
+                .map(SurroundfiEvent::MarginfiV3)
+        })
+        .or_else(|| {
+            marginfi_v0::Event::decode(discriminator, payload)
+                .and_then(Result::ok)
+                .map(SurroundfiEvent::MarginfiV0)
+        })
+}
+
+fn decode_version(
+    version: u8,
+    discriminator: &[u8],
+    payload: &[u8],
+) -> Result<SurroundfiEvent, DecodeError> {
+    let decoded =
+        match version {
+            v1::VERSION => v1::Event::decode(discriminator, payload)
+                .map(|result| result.map(SurroundfiEvent::V1)),
+            v2::VERSION => v2::Event::decode(discriminator, payload)
+                .map(|result| result.map(SurroundfiEvent::V2)),
+            _ => return Err(DecodeError::UnknownVersion(version)),
+        };
+
+    match decoded {
+        Some(Ok(event)) => Ok(event),
+        Some(Err(source)) => Err(DecodeError::InvalidLayout { version, source }),
+        None => Err(DecodeError::UnknownDiscriminator(
+            discriminator.try_into().unwrap(),
+        )),
+    }
+}
+
+/// Decode the event of a `Program data: <base64>` log, None for any other log
+pub fn decode_log(log: &str) -> Option<Result<SurroundfiEvent, DecodeError>> {
+    let data = log.strip_prefix(PROGRAM_DATA)?;
+
+    Some(
+        general_purpose::STANDARD
+            .decode(data)
+            .map_err(DecodeError::from)
+            .and_then(|data| decode_event(&data)),
+    )
+}
+
+/// Decode the events `program_id` logged in a transaction, skipping data logged by the programs it
+/// invokes and by other top-level instructions
+pub fn decode_logs(
+    program_id: &Pubkey,
+    logs: &[String],
+) -> Vec<Result<SurroundfiEvent, DecodeError>> {
+    let program_id = program_id.to_string();
+    let mut invocations: Vec<&str> = vec![];
+    let mut events = vec![];
+
+    for log in logs {
+        if let Some((program, status)) = log
+            .strip_prefix("Program ")
+            .and_then(|log| log.split_once(' '))
+        {
+            if status.starts_with("invoke [") {
+                invocations.push(program);
+                continue;
+            }
+            if status == "success" || status.starts_with("failed") {
+                invocations.pop();
+                continue;
+            }
+        }
+
+        if invocations.last() == Some(&program_id.as_str()) {
+            if let Some(event) = decode_log(log) {
+                events.push(event);
+            }
+        }
+    }
+
+    events
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_lang::{idl::build::IdlBuild, Event};
+    use std::collections::BTreeMap;
+    use surroundfi::events::{
+        AccountEventHeader, EditStakedSettingsEvent, GroupEventHeader, LendingAccountDepositEvent,
+        ReferrerEventStats, EVENT_SCHEMA_VERSION,
+    };
+    use surroundfi::instructions::{StakedSettingsConfig, StakedSettingsEditConfig};
+    use surroundfi::state::surroundfi_group::{
+        BankConfigOpt, BankOperationalState, InterestRateConfigOpt, RiskTier, WrappedI80F48,
+    };
+
+    #[test]
+    fn latest_version_matches_program() {
+        assert_eq!(LATEST_VERSION, EVENT_SCHEMA_VERSION);
+    }
+
+    #[test]
+    fn decodes_current_events() {
+        let event = LendingAccountDepositEvent {
+            header: AccountEventHeader {
+                version: EVENT_SCHEMA_VERSION,
+                signer: Some(Pubkey::new_unique()),
+                surroundfi_account: Pubkey::new_unique(),
+                surroundfi_account_authority: Pubkey::new_unique(),
+                surroundfi_group: Pubkey::new_unique(),
+            },
+            bank: Pubkey::new_unique(),
+            mint: Pubkey::new_unique(),
+            amount: 1_000,
+            referrer: Some(ReferrerEventStats {
+                referrer: Pubkey::new_unique(),
+                fee: 0,
+                lifetime_fees: 5,
+                referred_borrows: 2,
+                referred_deposits: 3,
+            }),
+        };
+
+        let decoded = decode_event(&event.data()).unwrap();
+        assert_eq!(decoded.version(), 2);
+        assert_eq!(decoded.name(), "LendingAccountDepositEvent");
+
+        let decoded = match decoded {
+            SurroundfiEvent::V2(v2::Event::LendingAccountDepositEvent(decoded)) => decoded,
+            decoded => panic!("Decoded as {:?}", decoded),
+        };
+        assert_eq!(
+            decoded.header.surroundfi_account,
+            event.header.surroundfi_account
+        );
+        assert_eq!(decoded.amount, 1_000);
+        assert_eq!(decoded.referrer.unwrap().referred_deposits, 3);
+
+        let event = EditStakedSettingsEvent {
+            header: GroupEventHeader {
+                version: EVENT_SCHEMA_VERSION,
+                signer: Some(Pubkey::new_unique()),
+                surroundfi_group: Pubkey::new_unique(),
+            },
+            staked_settings: Pubkey::new_unique(),
+            settings: Default::default(),
+        };
+        assert!(matches!(
+            decode_event(&event.data()).unwrap(),
+            SurroundfiEvent::V2(v2::Event::EditStakedSettingsEvent(_))
+        ));
+    }
+
+    fn group_header() -> GroupEventHeader {
+        GroupEventHeader {
+            version: EVENT_SCHEMA_VERSION,
+            signer: Some(Pubkey::new_unique()),
+            surroundfi_group: Pubkey::new_unique(),
+        }
+    }
+
+    fn account_header() -> AccountEventHeader {
+        AccountEventHeader {
+            version: EVENT_SCHEMA_VERSION,
+            signer: Some(Pubkey::new_unique()),
+            surroundfi_account: Pubkey::new_unique(),
+            surroundfi_account_authority: Pubkey::new_unique(),
+            surroundfi_group: Pubkey::new_unique(),
+        }
+    }
+
+    fn wrapped(value: u8) -> WrappedI80F48 {
+        WrappedI80F48 { value: [value; 16] }
+    }
+
+    /// Decode a program event as the latest version, which must encode it back to the same data.
+    /// Returns the event's name.
+    fn round_trip<E: Event>(event: E) -> &'static str {
+        let data = event.data();
+        let decoded = match decode_event(&data).unwrap() {
+            SurroundfiEvent::V2(decoded) => decoded,
+            decoded => panic!("{} decoded as {:?}", std::any::type_name::<E>(), decoded),
+        };
+        assert!(std::any::type_name::<E>().ends_with(&format!("::{}", decoded.name())));
+        assert_eq!(decoded.data(), data, "{}", decoded.name());
+        decoded.name()
+    }
+
+    #[test]
+    fn round_trips_every_current_event() {
+        use surroundfi::events::*;
+
+        let mut names = [
+            round_trip(SurroundfiGroupCreateEvent {
+                header: group_header(),
+            }),
+            round_trip(SurroundfiGroupConfigureEvent {
+                header: group_header(),
+                admin: Pubkey::new_unique(),
+                flags: 3,
+                bankruptcy_threshold: wrapped(21),
+            }),
+            round_trip(SurroundfiGroupConfigureAssetTagMatrixEvent {
+                header: group_header(),
+                coexist: [1, 2, 3, 4, 5, 6, 7, 8],
+                liquidation: [8, 7, 6, 5, 4, 3, 2, 1],
+            }),
+            round_trip(SurroundfiGroupConfigureArenaEvent {
+                header: group_header(),
+                max_leverage: wrapped(22),
+                liquidation_fee: wrapped(23),
+                swap_program: Pubkey::new_unique(),
+            }),
+            round_trip(SurroundfiGroupConfigureMintExtensionsEvent {
+                header: group_header(),
+                permitted_mint_extensions: 9,
+            }),
+            round_trip(LendingPoolBankCreateEvent {
+                header: group_header(),
+                bank: Pubkey::new_unique(),
+                mint: Pubkey::new_unique(),
+            }),
+            round_trip(LendingPoolBankConfigureEvent {
+                header: group_header(),
+                bank: Pubkey::new_unique(),
+                mint: Pubkey::new_unique(),
+                config: BankConfigOpt {
+                    asset_weight_init: Some(wrapped(1)),
+                    asset_weight_maint: Some(wrapped(2)),
+                    liability_weight_init: Some(wrapped(3)),
+                    liability_weight_maint: Some(wrapped(4)),
+                    deposit_limit: Some(5),
+                    borrow_limit: Some(6),
+                    operational_state: Some(BankOperationalState::ReduceOnly),
+                    interest_rate_config: Some(InterestRateConfigOpt {
+                        optimal_utilization_rate: Some(wrapped(7)),
+                        plateau_interest_rate: Some(wrapped(8)),
+                        max_interest_rate: Some(wrapped(9)),
+                        insurance_fee_fixed_apr: Some(wrapped(10)),
+                        insurance_ir_fee: Some(wrapped(11)),
+                        protocol_fixed_fee_apr: Some(wrapped(12)),
+                        protocol_ir_fee: Some(wrapped(13)),
+                        protocol_origination_fee: Some(wrapped(14)),
+                        flashloan_fee: Some(wrapped(15)),
+                        flashloan_group_fee: Some(wrapped(16)),
+                        referrer_fee_share: Some(wrapped(17)),
+                    }),
+                    risk_tier: Some(RiskTier::Isolated),
+                    asset_tag: Some(2),
+                    total_asset_value_init_limit: Some(18),
+                    oracle_max_age: Some(60),
+                    permissionless_bad_debt_settlement: Some(true),
+                    freeze_settings: Some(false),
+                },
+            }),
+            round_trip(LendingPoolBankConfigureOracleEvent {
+                header: group_header(),
+                bank: Pubkey::new_unique(),
+                oracle_setup: 4,
+                oracle: Pubkey::new_unique(),
+            }),
+            round_trip(LendingPoolBankConfigureFrozenEvent {
+                header: group_header(),
+                bank: Pubkey::new_unique(),
+                mint: Pubkey::new_unique(),
+                deposit_limit: 1,
+                borrow_limit: 2,
+            }),
+            round_trip(LendingPoolBankRefreshMintExtensionsEvent {
+                header: group_header(),
+                bank: Pubkey::new_unique(),
+                mint: Pubkey::new_unique(),
+                mint_extensions: 4,
+            }),
+            round_trip(SurroundfiGroupConfigureFeeEvent {
+                header: group_header(),
+                program_fee_enabled: true,
+                group_flags: 1,
+            }),
+            round_trip(SurroundfiGroupPropagateFeeEvent {
+                header: group_header(),
+                global_fee_wallet: Pubkey::new_unique(),
+                program_fee_fixed: wrapped(24),
+                program_fee_rate: wrapped(25),
+            }),
+            round_trip(FeeStateInitEvent {
+                header: group_header(),
+                fee_state: Pubkey::new_unique(),
+                global_fee_admin: Pubkey::new_unique(),
+                global_fee_wallet: Pubkey::new_unique(),
+                bank_init_flat_sol_fee: 1_000,
+                program_fee_fixed: wrapped(26),
+                program_fee_rate: wrapped(27),
+            }),
+            round_trip(FeeStateEditEvent {
+                header: group_header(),
+                fee_state: Pubkey::new_unique(),
+                global_fee_admin: Pubkey::new_unique(),
+                global_fee_wallet: Pubkey::new_unique(),
+                bank_init_flat_sol_fee: 2_000,
+                program_fee_fixed: wrapped(28),
+                program_fee_rate: wrapped(29),
+            }),
+            round_trip(SurroundfiGroupInitReferrerFeesEvent {
+                header: group_header(),
+                referrer: Pubkey::new_unique(),
+                referrer_fees: Pubkey::new_unique(),
+            }),
+            round_trip(InitStakedSettingsEvent {
+                header: group_header(),
+                staked_settings: Pubkey::new_unique(),
+                settings: StakedSettingsConfig {
+                    oracle: Pubkey::new_unique(),
+                    asset_weight_init: wrapped(1),
+                    asset_weight_maint: wrapped(2),
+                    deposit_limit: 3,
+                    total_asset_value_init_limit: 4,
+                    oracle_max_age: 5,
+                    risk_tier: RiskTier::Isolated,
+                    stable_liability_weight_init: wrapped(6),
+                    stable_liability_weight_maint: wrapped(7),
+                },
+            }),
+            round_trip(EditStakedSettingsEvent {
+                header: group_header(),
+                staked_settings: Pubkey::new_unique(),
+                settings: StakedSettingsEditConfig {
+                    oracle: Some(Pubkey::new_unique()),
+                    asset_weight_init: Some(wrapped(1)),
+                    asset_weight_maint: Some(wrapped(2)),
+                    deposit_limit: Some(3),
+                    total_asset_value_init_limit: Some(4),
+                    oracle_max_age: Some(5),
+                    risk_tier: Some(RiskTier::Collateral),
+                    stable_liability_weight_init: Some(wrapped(6)),
+                    stable_liability_weight_maint: Some(wrapped(7)),
+                },
+            }),
+            round_trip(LendingPoolBankPropagateStakedSettingsEvent {
+                header: group_header(),
+                bank: Pubkey::new_unique(),
+                staked_settings: Pubkey::new_unique(),
+                overrides: 5,
+            }),
+            round_trip(LendingPoolBankClearStakedSettingsOverridesEvent {
+                header: group_header(),
+                bank: Pubkey::new_unique(),
+                cleared: 1,
+                overrides: 4,
+            }),
+            round_trip(LendingPoolBankAccrueInterestEvent {
+                header: group_header(),
+                bank: Pubkey::new_unique(),
+                mint: Pubkey::new_unique(),
+                delta: 3_600,
+                fees_collected: 1.5,
+                insurance_collected: 2.5,
+            }),
+            round_trip(LendingPoolBankCollectFeesEvent {
+                header: group_header(),
+                bank: Pubkey::new_unique(),
+                mint: Pubkey::new_unique(),
+                group_fees_collected: 1.0,
+                group_fees_outstanding: 2.0,
+                insurance_fees_collected: 3.0,
+                insurance_fees_outstanding: 4.0,
+                insurance_staker_fees: wrapped(30),
+            }),
+            round_trip(LendingPoolBankHandleBankruptcyEvent {
+                header: account_header(),
+                bank: Pubkey::new_unique(),
+                mint: Pubkey::new_unique(),
+                bad_debt: 10.0,
+                covered_amount: 6.0,
+                staker_covered_amount: wrapped(31),
+                socialized_amount: 4.0,
+            }),
+            round_trip(LendingPoolBankWithdrawFeesEvent {
+                header: group_header(),
+                bank: Pubkey::new_unique(),
+                mint: Pubkey::new_unique(),
+                destination_account: Pubkey::new_unique(),
+                amount: 100,
+            }),
+            round_trip(LendingPoolBankWithdrawInsuranceEvent {
+                header: group_header(),
+                bank: Pubkey::new_unique(),
+                mint: Pubkey::new_unique(),
+                destination_account: Pubkey::new_unique(),
+                amount: 200,
+            }),
+            round_trip(LendingPoolBankInitReceiptMintEvent {
+                header: group_header(),
+                bank: Pubkey::new_unique(),
+                receipt_mint: Pubkey::new_unique(),
+            }),
+            round_trip(LendingPoolBankReceiptDepositEvent {
+                header: group_header(),
+                bank: Pubkey::new_unique(),
+                mint: Pubkey::new_unique(),
+                amount: 100,
+                receipt_amount: 90,
+            }),
+            round_trip(LendingPoolBankReceiptRedeemEvent {
+                header: group_header(),
+                bank: Pubkey::new_unique(),
+                mint: Pubkey::new_unique(),
+                amount: 110,
+                receipt_amount: 100,
+            }),
+            round_trip(LendingPoolBankSetupEmissionsEvent {
+                header: group_header(),
+                bank: Pubkey::new_unique(),
+                emissions_mint: Pubkey::new_unique(),
+                flags: 1,
+                rate: 2,
+                total_emissions: 3,
+                start_time: 4,
+                end_time: 5,
+            }),
+            round_trip(LendingPoolBankUpdateEmissionsEvent {
+                header: group_header(),
+                bank: Pubkey::new_unique(),
+                emissions_mint: Pubkey::new_unique(),
+                flags: 1,
+                rate: 2,
+                additional_emissions: 3,
+                emissions_remaining: wrapped(32),
+                start_time: 5,
+                end_time: 6,
+            }),
+            round_trip(LendingPoolBankReclaimEmissionsEvent {
+                header: group_header(),
+                bank: Pubkey::new_unique(),
+                emissions_mint: Pubkey::new_unique(),
+                amount: 7,
+            }),
+            round_trip(LendingPoolBankInitEmissionsProgramEvent {
+                header: group_header(),
+                bank: Pubkey::new_unique(),
+                emissions_program: Pubkey::new_unique(),
+            }),
+            round_trip(LendingPoolBankAddEmissionsStreamEvent {
+                header: group_header(),
+                bank: Pubkey::new_unique(),
+                stream_id: 2,
+                emissions_mint: Pubkey::new_unique(),
+                funder: Pubkey::new_unique(),
+                flags: 1,
+                rate: 3,
+                start_time: 4,
+                end_time: 5,
+            }),
+            round_trip(LendingPoolBankFundEmissionsStreamEvent {
+                header: group_header(),
+                bank: Pubkey::new_unique(),
+                stream_id: 2,
+                amount: 6,
+            }),
+            round_trip(SurroundfiGroupInitFeeDistributionEvent {
+                header: group_header(),
+                fee_distribution: Pubkey::new_unique(),
+                recipients: vec![Pubkey::new_unique(), Pubkey::new_unique()],
+                shares_bps: vec![7_000, 3_000],
+            }),
+            round_trip(SurroundfiGroupConfigureFeeDistributionEvent {
+                header: group_header(),
+                recipients: vec![Pubkey::new_unique(), Pubkey::new_unique()],
+                shares_bps: vec![6_000, 4_000],
+            }),
+            round_trip(LendingPoolBankDistributeFeesEvent {
+                header: group_header(),
+                bank: Pubkey::new_unique(),
+                mint: Pubkey::new_unique(),
+                recipients: vec![Pubkey::new_unique(), Pubkey::new_unique()],
+                amounts: vec![70, 30],
+            }),
+            round_trip(LendingPoolBankClaimReferrerFeesEvent {
+                header: group_header(),
+                bank: Pubkey::new_unique(),
+                mint: Pubkey::new_unique(),
+                referrer: Pubkey::new_unique(),
+                amount: 8,
+            }),
+            round_trip(LendingPoolBankConfigureInsuranceStakingEvent {
+                header: group_header(),
+                bank: Pubkey::new_unique(),
+                mint: Pubkey::new_unique(),
+                fee_share: wrapped(33),
+                cooldown: 86_400,
+                unstake_window: 3_600,
+            }),
+            round_trip(LendingPoolBankInitInsuranceStakeEvent {
+                header: group_header(),
+                bank: Pubkey::new_unique(),
+                insurance_stake: Pubkey::new_unique(),
+            }),
+            round_trip(LendingPoolBankInsuranceStakeEvent {
+                header: group_header(),
+                bank: Pubkey::new_unique(),
+                mint: Pubkey::new_unique(),
+                insurance_stake: Pubkey::new_unique(),
+                amount: 100,
+                shares: wrapped(34),
+            }),
+            round_trip(LendingPoolBankInsuranceRequestUnstakeEvent {
+                header: group_header(),
+                bank: Pubkey::new_unique(),
+                mint: Pubkey::new_unique(),
+                insurance_stake: Pubkey::new_unique(),
+                shares: wrapped(35),
+                unstakeable_at: 1_000,
+            }),
+            round_trip(LendingPoolBankInsuranceUnstakeEvent {
+                header: group_header(),
+                bank: Pubkey::new_unique(),
+                mint: Pubkey::new_unique(),
+                insurance_stake: Pubkey::new_unique(),
+                shares: wrapped(36),
+                amount: 51,
+            }),
+            round_trip(SurroundfiAccountCreateEvent {
+                header: account_header(),
+            }),
+            round_trip(SurroundfiAccountCloseEvent {
+                header: account_header(),
+            }),
+            round_trip(SurroundfiAccountInitExtensionEvent {
+                header: account_header(),
+                extension: Pubkey::new_unique(),
+            }),
+            round_trip(SurroundfiAccountCloseExtensionEvent {
+                header: account_header(),
+                extension: Pubkey::new_unique(),
+            }),
+            round_trip(SurroundfiAccountSetFlagEvent {
+                header: account_header(),
+                flag: 2,
+                account_flags: 3,
+            }),
+            round_trip(SurroundfiAccountUnsetFlagEvent {
+                header: account_header(),
+                flag: 2,
+                account_flags: 1,
+            }),
+            round_trip(LendingAccountDepositEvent {
+                header: account_header(),
+                bank: Pubkey::new_unique(),
+                mint: Pubkey::new_unique(),
+                amount: 1_000,
+                referrer: Some(ReferrerEventStats {
+                    referrer: Pubkey::new_unique(),
+                    fee: 0,
+                    lifetime_fees: 5,
+                    referred_borrows: 2,
+                    referred_deposits: 3,
+                }),
+            }),
+            round_trip(LendingAccountRepayEvent {
+                header: account_header(),
+                bank: Pubkey::new_unique(),
+                mint: Pubkey::new_unique(),
+                amount: 500,
+                close_balance: true,
+            }),
+            round_trip(LendingAccountBorrowEvent {
+                header: account_header(),
+                bank: Pubkey::new_unique(),
+                mint: Pubkey::new_unique(),
+                amount: 500,
+                referrer: Some(ReferrerEventStats {
+                    referrer: Pubkey::new_unique(),
+                    fee: 1,
+                    lifetime_fees: 6,
+                    referred_borrows: 3,
+                    referred_deposits: 3,
+                }),
+            }),
+            round_trip(LendingAccountCloseBalanceEvent {
+                header: account_header(),
+                bank: Pubkey::new_unique(),
+                mint: Pubkey::new_unique(),
+            }),
+            round_trip(LendingAccountStartFlashloanEvent {
+                header: account_header(),
+                end_index: 3,
+            }),
+            round_trip(LendingAccountEndFlashloanEvent {
+                header: account_header(),
+            }),
+            round_trip(LendingAccountCallbackFlashloanEvent {
+                header: account_header(),
+                bank: Pubkey::new_unique(),
+                mint: Pubkey::new_unique(),
+                amount: 1_000,
+                fee: 9,
+                callback_program: Pubkey::new_unique(),
+            }),
+            round_trip(LendingAccountWithdrawEvent {
+                header: account_header(),
+                bank: Pubkey::new_unique(),
+                mint: Pubkey::new_unique(),
+                amount: 400,
+                close_balance: true,
+            }),
+            round_trip(LendingAccountOpenLeveragedPositionEvent {
+                header: account_header(),
+                collateral_bank: Pubkey::new_unique(),
+                debt_bank: Pubkey::new_unique(),
+                deposited: 300,
+                borrowed: 200,
+                swap_proceeds: 199,
+            }),
+            round_trip(LendingAccountCloseLeveragedPositionEvent {
+                header: account_header(),
+                collateral_bank: Pubkey::new_unique(),
+                debt_bank: Pubkey::new_unique(),
+                withdrawn: 300,
+                repaid: 200,
+                swap_proceeds: 201,
+                closed: true,
+            }),
+            round_trip(LendingAccountLiquidateEvent {
+                header: account_header(),
+                liquidatee_surroundfi_account: Pubkey::new_unique(),
+                liquidatee_surroundfi_account_authority: Pubkey::new_unique(),
+                asset_bank: Pubkey::new_unique(),
+                asset_mint: Pubkey::new_unique(),
+                liability_bank: Pubkey::new_unique(),
+                liability_mint: Pubkey::new_unique(),
+                liquidatee_pre_health: -1.0,
+                liquidatee_post_health: 0.5,
+                pre_balances: LiquidationBalances {
+                    liquidatee_asset_balance: 1.0,
+                    liquidatee_liability_balance: 2.0,
+                    liquidator_asset_balance: 3.0,
+                    liquidator_liability_balance: 4.0,
+                },
+                post_balances: LiquidationBalances {
+                    liquidatee_asset_balance: 5.0,
+                    liquidatee_liability_balance: 6.0,
+                    liquidator_asset_balance: 7.0,
+                    liquidator_liability_balance: 8.0,
+                },
+            }),
+            round_trip(SurroundfiAccountTransferAccountAuthorityEvent {
+                header: account_header(),
+                old_account_authority: Pubkey::new_unique(),
+                new_account_authority: Pubkey::new_unique(),
+            }),
+            round_trip(LendingAccountPulseHealthEvent {
+                header: account_header(),
+                engine_ok: true,
+                healthy: true,
+                asset_value_maint: wrapped(37),
+                liability_value_maint: wrapped(38),
+            }),
+            round_trip(SurroundfiAccountUpdateEmissionsDestinationAccountEvent {
+                header: account_header(),
+                emissions_destination_account: Pubkey::new_unique(),
+            }),
+            round_trip(LendingAccountSettleEmissionsEvent {
+                header: account_header(),
+                bank: Pubkey::new_unique(),
+                emissions_outstanding: wrapped(39),
+            }),
+            round_trip(LendingAccountWithdrawEmissionsEvent {
+                header: account_header(),
+                bank: Pubkey::new_unique(),
+                emissions_mint: Pubkey::new_unique(),
+                destination_account: Pubkey::new_unique(),
+                amount: 2,
+            }),
+            round_trip(LendingAccountInitEmissionsAccrualEvent {
+                header: account_header(),
+                bank: Pubkey::new_unique(),
+                emissions_accrual: Pubkey::new_unique(),
+            }),
+            round_trip(LendingAccountSettleStreamEmissionsEvent {
+                header: account_header(),
+                bank: Pubkey::new_unique(),
+                stream_id: Some(1),
+            }),
+            round_trip(LendingAccountWithdrawStreamEmissionsEvent {
+                header: account_header(),
+                bank: Pubkey::new_unique(),
+                stream_id: 1,
+                emissions_mint: Pubkey::new_unique(),
+                destination_account: Pubkey::new_unique(),
+                amount: 3,
+            }),
+        ];
+
+        names.sort_unstable();
+        let mut expected = v2::Event::NAMES.to_vec();
+        expected.sort_unstable();
+        assert_eq!(names.to_vec(), expected);
+    }
+
+    /// The layout of `T` and of the types nested in it, by name, as the IDL describes them. Docs,
+    /// paths and zero-copy details are left out, they don't change how an event is encoded.
+    fn idl_layouts<T: IdlBuild>() -> BTreeMap<String, serde_json::Value> {
+        let mut types = BTreeMap::new();
+        T::insert_types(&mut types);
+        types.extend(T::create_type().map(|ty| (String::new(), ty)));
+
+        types
+            .into_values()
+            .map(|ty| {
+                let mut ty = serde_json::to_value(ty).unwrap();
+                normalize_idl(&mut ty);
+                (ty["name"].as_str().unwrap().to_string(), ty)
+            })
+            .collect()
+    }
+
+    fn normalize_idl(value: &mut serde_json::Value) {
+        match value {
+            serde_json::Value::Object(fields) => {
+                for key in ["docs", "serialization", "repr"] {
+                    fields.remove(key);
+                }
+                if let Some(serde_json::Value::String(name)) = fields.get_mut("name") {
+                    *name = name.rsplit("::").next().unwrap().to_string();
+                }
+                fields.values_mut().for_each(normalize_idl);
+            }
+            serde_json::Value::Array(values) => values.iter_mut().for_each(normalize_idl),
+            _ => {}
+        }
+    }
+
+    /// Compares the layout of each v2 event with the program's event of the same name, and returns
+    /// the names
+    macro_rules! assert_layouts_match_program {
+        ($($name:ident),* $(,)?) => {{
+            $(
+                assert_eq!(
+                    idl_layouts::<v2::$name>(),
+                    idl_layouts::<surroundfi::events::$name>(),
+                    "{} changed, bump EVENT_SCHEMA_VERSION and add a version",
+                    stringify!($name)
+                );
+            )*
+            [$(stringify!($name)),*]
+        }};
+    }
+
+    /// `v2` is a copy of the program's current layouts, this catches any change to them that
+    /// `round_trips_every_current_event` can't see, e.g. swapped fields of the same type
+    #[test]
+    fn layouts_match_program() {
+        let mut names = assert_layouts_match_program!(
+            SurroundfiGroupCreateEvent,
+            SurroundfiGroupConfigureEvent,
+            SurroundfiGroupConfigureAssetTagMatrixEvent,
+            SurroundfiGroupConfigureArenaEvent,
+            SurroundfiGroupConfigureMintExtensionsEvent,
+            LendingPoolBankCreateEvent,
+            LendingPoolBankConfigureEvent,
+            LendingPoolBankConfigureOracleEvent,
+            LendingPoolBankConfigureFrozenEvent,
+            LendingPoolBankRefreshMintExtensionsEvent,
+            SurroundfiGroupConfigureFeeEvent,
+            SurroundfiGroupPropagateFeeEvent,
+            FeeStateInitEvent,
+            FeeStateEditEvent,
+            SurroundfiGroupInitReferrerFeesEvent,
+            InitStakedSettingsEvent,
+            EditStakedSettingsEvent,
+            LendingPoolBankPropagateStakedSettingsEvent,
+            LendingPoolBankClearStakedSettingsOverridesEvent,
+            LendingPoolBankAccrueInterestEvent,
+            LendingPoolBankCollectFeesEvent,
+            LendingPoolBankHandleBankruptcyEvent,
+            LendingPoolBankWithdrawFeesEvent,
+            LendingPoolBankWithdrawInsuranceEvent,
+            LendingPoolBankInitReceiptMintEvent,
+            LendingPoolBankReceiptDepositEvent,
+            LendingPoolBankReceiptRedeemEvent,
+            LendingPoolBankSetupEmissionsEvent,
+            LendingPoolBankUpdateEmissionsEvent,
+            LendingPoolBankReclaimEmissionsEvent,
+            LendingPoolBankInitEmissionsProgramEvent,
+            LendingPoolBankAddEmissionsStreamEvent,
+            LendingPoolBankFundEmissionsStreamEvent,
+            SurroundfiGroupInitFeeDistributionEvent,
+            SurroundfiGroupConfigureFeeDistributionEvent,
+            LendingPoolBankDistributeFeesEvent,
+            LendingPoolBankClaimReferrerFeesEvent,
+            LendingPoolBankConfigureInsuranceStakingEvent,
+            LendingPoolBankInitInsuranceStakeEvent,
+            LendingPoolBankInsuranceStakeEvent,
+            LendingPoolBankInsuranceRequestUnstakeEvent,
+            LendingPoolBankInsuranceUnstakeEvent,
+            SurroundfiAccountCreateEvent,
+            SurroundfiAccountCloseEvent,
+            SurroundfiAccountInitExtensionEvent,
+            SurroundfiAccountCloseExtensionEvent,
+            SurroundfiAccountSetFlagEvent,
+            SurroundfiAccountUnsetFlagEvent,
+            LendingAccountDepositEvent,
+            LendingAccountRepayEvent,
+            LendingAccountBorrowEvent,
+            LendingAccountCloseBalanceEvent,
+            LendingAccountStartFlashloanEvent,
+            LendingAccountEndFlashloanEvent,
+            LendingAccountCallbackFlashloanEvent,
+            LendingAccountWithdrawEvent,
+            LendingAccountOpenLeveragedPositionEvent,
+            LendingAccountCloseLeveragedPositionEvent,
+            LendingAccountLiquidateEvent,
+            SurroundfiAccountTransferAccountAuthorityEvent,
+            LendingAccountPulseHealthEvent,
+            SurroundfiAccountUpdateEmissionsDestinationAccountEvent,
+            LendingAccountSettleEmissionsEvent,
+            LendingAccountWithdrawEmissionsEvent,
+            LendingAccountInitEmissionsAccrualEvent,
+            LendingAccountSettleStreamEmissionsEvent,
+            LendingAccountWithdrawStreamEmissionsEvent,
+        );
+
+        names.sort_unstable();
+        let mut expected = v2::Event::NAMES.to_vec();
+        expected.sort_unstable();
+        assert_eq!(names.to_vec(), expected);
+    }
+
+    #[test]
+    fn decodes_marginfi_events() {
+        let event = marginfi_v0::LendingPoolBankAccrueInterestEvent {
+            header: marginfi_v0::GroupEventHeader {
+                version: "0.1.0".to_string(),
+                signer: None,
+                marginfi_group: Pubkey::new_unique(),
+            },
+            mint: Pubkey::new_unique(),
+            delta: 60,
+            fees_collected: 1.5,
+            insurance_collected: 0.5,
+        };
+        let decoded = decode_event(&event.data()).unwrap();
+        assert_eq!(decoded.version(), 0);
+        assert_eq!(
+            decoded,
+            SurroundfiEvent::MarginfiV0(marginfi_v0::Event::LendingPoolBankAccrueInterestEvent(
+                event
+            ))
+        );
+
+        let balances = types::LiquidationBalances {
+            liquidatee_asset_balance: 1.0,
+            liquidatee_liability_balance: 2.0,
+            liquidator_asset_balance: 3.0,
+            liquidator_liability_balance: 4.0,
+        };
+        let event = marginfi_v3::LendingAccountLiquidateEvent {
+            header: marginfi_v3::AccountEventHeader {
+                signer: Pubkey::new_unique(),
+                marginfi_account: Pubkey::new_unique(),
+                marginfi_account_authority: Pubkey::new_unique(),
+                marginfi_group: Pubkey::new_unique(),
+            },
+            liquidatee_marginfi_account: Pubkey::new_unique(),
+            liquidatee_marginfi_account_authority: Pubkey::new_unique(),
+            asset_bank: Pubkey::new_unique(),
+            asset_mint: Pubkey::new_unique(),
+            liability_bank: Pubkey::new_unique(),
+            liability_mint: Pubkey::new_unique(),
+            liquidatee_pre_health: -1.0,
+            liquidatee_post_health: 0.5,
+            pre_balances: balances.clone(),
+            post_balances: balances,
+        };
+        assert_eq!(
+            decode_event(&event.data()).unwrap(),
+            SurroundfiEvent::MarginfiV3(marginfi_v3::Event::LendingAccountLiquidateEvent(event))
+        );
+
+        let header = marginfi_v4::GroupEventHeader {
+            signer: Some(Pubkey::new_unique()),
+            marginfi_group: Pubkey::new_unique(),
+        };
+        let interest_rate_config = marginfi_v4::InterestRateConfigOpt {
+            optimal_utilization_rate: Some(types::WrappedI80F48 { value: [1; 16] }),
+            plateau_interest_rate: None,
+            max_interest_rate: None,
+            insurance_fee_fixed_apr: None,
+            insurance_ir_fee: None,
+            protocol_fixed_fee_apr: None,
+            protocol_ir_fee: Some(types::WrappedI80F48 { value: [2; 16] }),
+        };
+        let oracle = marginfi_v4::OracleConfig {
+            setup: marginfi_v4::OracleSetup::Pyth,
+            keys: [Pubkey::new_unique(); 5],
+        };
+        let event = marginfi_v4::LendingPoolBankConfigureEvent {
+            header: header.clone(),
+            bank: Pubkey::new_unique(),
+            mint: Pubkey::new_unique(),
+            config: marginfi_v4::BankConfigOpt {
+                asset_weight_init: None,
+                asset_weight_maint: None,
+                liability_weight_init: None,
+                liability_weight_maint: None,
+                deposit_limit: Some(100),
+                borrow_limit: None,
+                operational_state: Some(types::BankOperationalState::Paused),
+                oracle: Some(oracle.clone()),
+                interest_rate_config: Some(interest_rate_config.clone()),
+            },
+        };
+        assert_eq!(
+            decode_event(&event.data()).unwrap(),
+            SurroundfiEvent::MarginfiV4(marginfi_v4::Event::LendingPoolBankConfigureEvent(event))
+        );
+
+        // Mainnet bank configs also have a risk tier
+        let event = marginfi_mainnet_v0::LendingPoolBankConfigureEvent {
+            header: header.clone(),
+            bank: Pubkey::new_unique(),
+            mint: Pubkey::new_unique(),
+            config: marginfi_mainnet_v0::BankConfigOpt {
+                asset_weight_init: None,
+                asset_weight_maint: None,
+                liability_weight_init: None,
+                liability_weight_maint: None,
+                deposit_limit: Some(100),
+                borrow_limit: None,
+                operational_state: None,
+                oracle: Some(oracle),
+                interest_rate_config: Some(interest_rate_config),
+                risk_tier: Some(types::RiskTier::Isolated),
+            },
+        };
+        assert_eq!(
+            decode_event(&event.data()).unwrap(),
+            SurroundfiEvent::MarginfiMainnetV0(
+                marginfi_mainnet_v0::Event::LendingPoolBankConfigureEvent(event)
+            )
+        );
+
+        // Unchanged on mainnet, decodes as the later layout
+        let event = marginfi_v4::MarginfiGroupConfigureEvent {
+            header,
+            config: marginfi_v4::GroupConfig {
+                admin: Some(Pubkey::new_unique()),
+            },
+        };
+        assert_eq!(
+            decode_event(&event.data()).unwrap(),
+            SurroundfiEvent::MarginfiMainnetV0(
+                marginfi_mainnet_v0::Event::MarginfiGroupConfigureEvent(event)
+            )
+        );
+    }
+
+    #[test]
+    fn decodes_unversioned_events() {
+        let event = v1::LendingAccountDepositEvent {
+            header: v1::AccountEventHeader {
+                signer: None,
+                surroundfi_account: Pubkey::new_unique(),
+                surroundfi_account_authority: Pubkey::new_unique(),
+                surroundfi_group: Pubkey::new_unique(),
+            },
+            bank: Pubkey::new_unique(),
+            mint: Pubkey::new_unique(),
+            amount: 7,
+        };
+        assert_eq!(
+            decode_event(&event.data()).unwrap(),
+            SurroundfiEvent::V1(v1::Event::LendingAccountDepositEvent(event))
+        );
+
+        // Headerless, the first byte of the group looks like a version
+        let mut group = [0; 32];
+        group[0] = v2::VERSION;
+        let event = v1::EditStakedSettingsEvent {
+            group: Pubkey::new_from_array(group),
+            settings: v1::StakedSettingsEditConfig {
+                oracle: None,
+                asset_weight_init: None,
+                asset_weight_maint: None,
+                deposit_limit: Some(10),
+                total_asset_value_init_limit: None,
+                oracle_max_age: None,
+                risk_tier: None,
+            },
+        };
+        assert_eq!(
+            decode_event(&event.data()).unwrap(),
+            SurroundfiEvent::V1(v1::Event::EditStakedSettingsEvent(event))
+        );
+    }
+
+    #[test]
+    fn serializes_pubkeys_in_base58() {
+        let event = v1::SurroundfiAccountCreateEvent {
+            header: v1::AccountEventHeader {
+                signer: None,
+                surroundfi_account: Pubkey::new_unique(),
+                surroundfi_account_authority: Pubkey::new_unique(),
+                surroundfi_group: Pubkey::new_unique(),
+            },
+        };
+
+        let json = serde_json::to_value(SurroundfiEvent::V1(
+            v1::Event::SurroundfiAccountCreateEvent(event.clone()),
+        ))
+        .unwrap();
+        assert_eq!(json["name"], "SurroundfiAccountCreateEvent");
+        assert_eq!(json["version"], 1);
+        assert_eq!(json["data"]["header"]["signer"], serde_json::Value::Null);
+        assert_eq!(
+            json["data"]["header"]["surroundfi_account"],
+            event.header.surroundfi_account.to_string()
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_events() {
+        let mut data = v1::SurroundfiAccountCreateEvent {
+            header: v1::AccountEventHeader {
+                signer: None,
+                surroundfi_account: Pubkey::new_unique(),
+                surroundfi_account_authority: Pubkey::new_unique(),
+                surroundfi_group: Pubkey::new_unique(),
+            },
+        }
+        .data();
+
+        data[8] = LATEST_VERSION + 1;
+        assert!(matches!(
+            decode_event(&data),
+            Err(DecodeError::UnknownVersion(version)) if version == LATEST_VERSION + 1
+        ));
+
+        data[0] ^= 1;
+        data[8] = 0;
+        assert!(matches!(
+            decode_event(&data),
+            Err(DecodeError::UnknownDiscriminator(_))
+        ));
+
+        assert!(matches!(decode_event(&[0; 4]), Err(DecodeError::TooShort)));
+    }
+
+    #[test]
+    fn decodes_only_logs_of_the_program() {
+        let program_id = Pubkey::new_unique();
+        let other_program_id = Pubkey::new_unique();
+        let event = v1::SurroundfiAccountCreateEvent {
+            header: v1::AccountEventHeader {
+                signer: None,
+                surroundfi_account: Pubkey::new_unique(),
+                surroundfi_account_authority: Pubkey::new_unique(),
+                surroundfi_group: Pubkey::new_unique(),
+            },
+        };
+        let data_log = format!(
+            "{}{}",
+            PROGRAM_DATA,
+            general_purpose::STANDARD.encode(event.data())
+        );
+        let logs = [
+            format!("Program {} invoke [1]", program_id),
+            "Program log: Instruction: SurroundfiAccountInitialize".to_string(),
+            format!("Program {} invoke [2]", other_program_id),
+            format!("{}AAAAAAAAAAA=", PROGRAM_DATA),
+            format!("Program {} success", other_program_id),
+            data_log.clone(),
+            format!(
+                "Program {} consumed 10000 of 200000 compute units",
+                program_id
+            ),
+            format!("Program {} success", program_id),
+            format!("Program {} invoke [1]", other_program_id),
+            data_log,
+            format!("Program {} success", other_program_id),
+        ];
+
+        let events = decode_logs(&program_id, &logs);
+        assert_eq!(events.len(), 1);
+        assert_eq!(
+            *events[0].as_ref().unwrap(),
+            SurroundfiEvent::V1(v1::Event::SurroundfiAccountCreateEvent(event))
+        );
+    }
+}
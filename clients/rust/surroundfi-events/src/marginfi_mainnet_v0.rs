@@ -0,0 +1,68 @@
+//! Layouts of the marginfi mainnet IDL version 0 (program `MFv2hWf3…`, see the ETL's
+//! `idls/mainnet`): those of `marginfi_v4`, with a risk tier in bank configs.
+
+use anchor_lang::prelude::*;
+use serde::Serialize;
+
+use crate::marginfi_v4::{GroupEventHeader, InterestRateConfigOpt, OracleConfig};
+use crate::types::{serialize_pubkey, BankOperationalState, RiskTier, WrappedI80F48};
+
+pub use crate::marginfi_v4::{
+    LendingAccountBorrowEvent, LendingAccountDepositEvent, LendingAccountLiquidateEvent,
+    LendingAccountRepayEvent, LendingAccountWithdrawEvent, LendingPoolBankAccrueInterestEvent,
+    LendingPoolBankCollectFeesEvent, LendingPoolBankCreateEvent,
+    LendingPoolBankHandleBankruptcyEvent, MarginfiAccountCreateEvent, MarginfiGroupConfigureEvent,
+    MarginfiGroupCreateEvent,
+};
+
+/// Predates schema versions
+pub const VERSION: u8 = 0;
+
+decode_events! {
+    MarginfiGroupCreateEvent,
+    MarginfiGroupConfigureEvent,
+    LendingPoolBankCreateEvent,
+    LendingPoolBankConfigureEvent,
+    LendingPoolBankAccrueInterestEvent,
+    LendingPoolBankCollectFeesEvent,
+    LendingPoolBankHandleBankruptcyEvent,
+    MarginfiAccountCreateEvent,
+    LendingAccountDepositEvent,
+    LendingAccountRepayEvent,
+    LendingAccountBorrowEvent,
+    LendingAccountWithdrawEvent,
+    LendingAccountLiquidateEvent,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LendingPoolBankConfigureEvent {
+    pub header: GroupEventHeader,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub bank: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub mint: Pubkey,
+    pub config: BankConfigOpt,
+}
+
+// Types nested in events
+
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, PartialEq, Serialize)]
+pub struct BankConfigOpt {
+    pub asset_weight_init: Option<WrappedI80F48>,
+    pub asset_weight_maint: Option<WrappedI80F48>,
+
+    pub liability_weight_init: Option<WrappedI80F48>,
+    pub liability_weight_maint: Option<WrappedI80F48>,
+
+    pub deposit_limit: Option<u64>,
+    pub borrow_limit: Option<u64>,
+
+    pub operational_state: Option<BankOperationalState>,
+
+    pub oracle: Option<OracleConfig>,
+
+    pub interest_rate_config: Option<InterestRateConfigOpt>,
+
+    pub risk_tier: Option<RiskTier>,
+}
@@ -0,0 +1,120 @@
+//! Layouts of the marginfi IDL versions 0 to 2 (devnet program `A7vUDErN…`, see the ETL's
+//! `idls/devnet`), which emitted identical events. Headers start with the program's version
+//! string, and interest accruals don't name their bank.
+
+use anchor_lang::prelude::*;
+use serde::Serialize;
+
+use crate::types::{serialize_pubkey, serialize_pubkey_option};
+
+/// Predates schema versions
+pub const VERSION: u8 = 0;
+
+decode_events! {
+    LendingPoolBankAddEvent,
+    LendingPoolBankAccrueInterestEvent,
+    MarginfiAccountCreateEvent,
+    LendingAccountDepositEvent,
+    LendingAccountRepayEvent,
+    LendingAccountBorrowEvent,
+    LendingAccountWithdrawEvent,
+}
+
+// Event headers
+
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, PartialEq, Serialize)]
+pub struct GroupEventHeader {
+    pub version: String,
+    #[serde(serialize_with = "serialize_pubkey_option")]
+    pub signer: Option<Pubkey>,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub marginfi_group: Pubkey,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, PartialEq, Serialize)]
+pub struct AccountEventHeader {
+    pub version: String,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub signer: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub marginfi_account: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub marginfi_group: Pubkey,
+}
+
+// marginfi group events
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LendingPoolBankAddEvent {
+    pub header: GroupEventHeader,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub bank: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub mint: Pubkey,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LendingPoolBankAccrueInterestEvent {
+    pub header: GroupEventHeader,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub mint: Pubkey,
+    pub delta: u64,
+    pub fees_collected: f64,
+    pub insurance_collected: f64,
+}
+
+// marginfi account events
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MarginfiAccountCreateEvent {
+    pub header: AccountEventHeader,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LendingAccountDepositEvent {
+    pub header: AccountEventHeader,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub bank: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub mint: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LendingAccountRepayEvent {
+    pub header: AccountEventHeader,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub bank: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub close_balance: bool,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LendingAccountBorrowEvent {
+    pub header: AccountEventHeader,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub bank: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub mint: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LendingAccountWithdrawEvent {
+    pub header: AccountEventHeader,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub bank: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub close_balance: bool,
+}
@@ -0,0 +1,159 @@
+//! Layouts of the marginfi IDL version 3 (devnet program `A7vUDErN…`, see the ETL's
+//! `idls/devnet`). Headers lost their version string, account headers name the account's
+//! authority but always have a signer.
+
+use anchor_lang::prelude::*;
+use serde::Serialize;
+
+use crate::types::{serialize_pubkey, serialize_pubkey_option, LiquidationBalances};
+
+/// Predates schema versions
+pub const VERSION: u8 = 0;
+
+decode_events! {
+    LendingPoolBankAddEvent,
+    LendingPoolBankAccrueInterestEvent,
+    MarginfiAccountCreateEvent,
+    LendingAccountDepositEvent,
+    LendingAccountRepayEvent,
+    LendingAccountBorrowEvent,
+    LendingAccountWithdrawEvent,
+    LendingPoolHandleBankruptcyEvent,
+    LendingAccountLiquidateEvent,
+}
+
+// Event headers
+
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, PartialEq, Serialize)]
+pub struct GroupEventHeader {
+    #[serde(serialize_with = "serialize_pubkey_option")]
+    pub signer: Option<Pubkey>,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub marginfi_group: Pubkey,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, PartialEq, Serialize)]
+pub struct AccountEventHeader {
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub signer: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub marginfi_account: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub marginfi_account_authority: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub marginfi_group: Pubkey,
+}
+
+// marginfi group events
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LendingPoolBankAddEvent {
+    pub header: GroupEventHeader,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub bank: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub mint: Pubkey,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LendingPoolBankAccrueInterestEvent {
+    pub header: GroupEventHeader,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub bank: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub mint: Pubkey,
+    pub delta: u64,
+    pub fees_collected: f64,
+    pub insurance_collected: f64,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LendingPoolHandleBankruptcyEvent {
+    pub header: AccountEventHeader,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub bank: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub mint: Pubkey,
+    pub bad_debt: f64,
+    pub covered_amount: f64,
+    pub socialized_amount: f64,
+}
+
+// marginfi account events
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MarginfiAccountCreateEvent {
+    pub header: AccountEventHeader,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LendingAccountDepositEvent {
+    pub header: AccountEventHeader,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub bank: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub mint: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LendingAccountRepayEvent {
+    pub header: AccountEventHeader,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub bank: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub close_balance: bool,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LendingAccountBorrowEvent {
+    pub header: AccountEventHeader,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub bank: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub mint: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LendingAccountWithdrawEvent {
+    pub header: AccountEventHeader,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub bank: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub close_balance: bool,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LendingAccountLiquidateEvent {
+    pub header: AccountEventHeader,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub liquidatee_marginfi_account: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub liquidatee_marginfi_account_authority: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub asset_bank: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub asset_mint: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub liability_bank: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub liability_mint: Pubkey,
+    pub liquidatee_pre_health: f64,
+    pub liquidatee_post_health: f64,
+    pub pre_balances: LiquidationBalances,
+    pub post_balances: LiquidationBalances,
+}
@@ -0,0 +1,255 @@
+//! Layouts of the marginfi IDL version 4 (devnet program `A7vUDErN…`, and version 0 of devnet
+//! program `5Lt5xXZG…`, see the ETL's `idls/devnet`). Headers have the version 1 layout, but group
+//! and account events are still named after marginfi.
+
+use anchor_lang::prelude::*;
+use serde::Serialize;
+
+use crate::types::{
+    serialize_pubkey, serialize_pubkey_option, serialize_pubkey_vec, BankOperationalState,
+    LiquidationBalances, WrappedI80F48,
+};
+
+/// Predates schema versions
+pub const VERSION: u8 = 0;
+
+decode_events! {
+    MarginfiGroupCreateEvent,
+    MarginfiGroupConfigureEvent,
+    LendingPoolBankCreateEvent,
+    LendingPoolBankConfigureEvent,
+    LendingPoolBankAccrueInterestEvent,
+    LendingPoolBankCollectFeesEvent,
+    LendingPoolBankHandleBankruptcyEvent,
+    MarginfiAccountCreateEvent,
+    LendingAccountDepositEvent,
+    LendingAccountRepayEvent,
+    LendingAccountBorrowEvent,
+    LendingAccountWithdrawEvent,
+    LendingAccountLiquidateEvent,
+}
+
+// Event headers
+
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, PartialEq, Serialize)]
+pub struct GroupEventHeader {
+    #[serde(serialize_with = "serialize_pubkey_option")]
+    pub signer: Option<Pubkey>,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub marginfi_group: Pubkey,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, PartialEq, Serialize)]
+pub struct AccountEventHeader {
+    #[serde(serialize_with = "serialize_pubkey_option")]
+    pub signer: Option<Pubkey>,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub marginfi_account: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub marginfi_account_authority: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub marginfi_group: Pubkey,
+}
+
+// marginfi group events
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MarginfiGroupCreateEvent {
+    pub header: GroupEventHeader,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MarginfiGroupConfigureEvent {
+    pub header: GroupEventHeader,
+    pub config: GroupConfig,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LendingPoolBankCreateEvent {
+    pub header: GroupEventHeader,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub bank: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub mint: Pubkey,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LendingPoolBankConfigureEvent {
+    pub header: GroupEventHeader,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub bank: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub mint: Pubkey,
+    pub config: BankConfigOpt,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LendingPoolBankAccrueInterestEvent {
+    pub header: GroupEventHeader,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub bank: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub mint: Pubkey,
+    pub delta: u64,
+    pub fees_collected: f64,
+    pub insurance_collected: f64,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LendingPoolBankCollectFeesEvent {
+    pub header: GroupEventHeader,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub bank: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub mint: Pubkey,
+    pub group_fees_collected: f64,
+    pub group_fees_outstanding: f64,
+    pub insurance_fees_collected: f64,
+    pub insurance_fees_outstanding: f64,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LendingPoolBankHandleBankruptcyEvent {
+    pub header: AccountEventHeader,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub bank: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub mint: Pubkey,
+    pub bad_debt: f64,
+    pub covered_amount: f64,
+    pub socialized_amount: f64,
+}
+
+// marginfi account events
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MarginfiAccountCreateEvent {
+    pub header: AccountEventHeader,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LendingAccountDepositEvent {
+    pub header: AccountEventHeader,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub bank: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub mint: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LendingAccountRepayEvent {
+    pub header: AccountEventHeader,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub bank: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub close_balance: bool,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LendingAccountBorrowEvent {
+    pub header: AccountEventHeader,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub bank: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub mint: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LendingAccountWithdrawEvent {
+    pub header: AccountEventHeader,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub bank: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub close_balance: bool,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LendingAccountLiquidateEvent {
+    pub header: AccountEventHeader,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub liquidatee_marginfi_account: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub liquidatee_marginfi_account_authority: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub asset_bank: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub asset_mint: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub liability_bank: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub liability_mint: Pubkey,
+    pub liquidatee_pre_health: f64,
+    pub liquidatee_post_health: f64,
+    pub pre_balances: LiquidationBalances,
+    pub post_balances: LiquidationBalances,
+}
+
+// Types nested in events
+
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, PartialEq, Serialize)]
+pub struct GroupConfig {
+    #[serde(serialize_with = "serialize_pubkey_option")]
+    pub admin: Option<Pubkey>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum OracleSetup {
+    None,
+    Pyth,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, PartialEq, Serialize)]
+pub struct OracleConfig {
+    pub setup: OracleSetup,
+    #[serde(serialize_with = "serialize_pubkey_vec")]
+    pub keys: [Pubkey; 5],
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, PartialEq, Serialize)]
+pub struct InterestRateConfigOpt {
+    pub optimal_utilization_rate: Option<WrappedI80F48>,
+    pub plateau_interest_rate: Option<WrappedI80F48>,
+    pub max_interest_rate: Option<WrappedI80F48>,
+
+    pub insurance_fee_fixed_apr: Option<WrappedI80F48>,
+    pub insurance_ir_fee: Option<WrappedI80F48>,
+    pub protocol_fixed_fee_apr: Option<WrappedI80F48>,
+    pub protocol_ir_fee: Option<WrappedI80F48>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, PartialEq, Serialize)]
+pub struct BankConfigOpt {
+    pub asset_weight_init: Option<WrappedI80F48>,
+    pub asset_weight_maint: Option<WrappedI80F48>,
+
+    pub liability_weight_init: Option<WrappedI80F48>,
+    pub liability_weight_maint: Option<WrappedI80F48>,
+
+    pub deposit_limit: Option<u64>,
+    pub borrow_limit: Option<u64>,
+
+    pub operational_state: Option<BankOperationalState>,
+
+    pub oracle: Option<OracleConfig>,
+
+    pub interest_rate_config: Option<InterestRateConfigOpt>,
+}
@@ -0,0 +1,70 @@
+//! Types nested in events whose layout never changed across versions.
+
+use anchor_lang::prelude::*;
+use fixed::types::I80F48;
+use serde::{Serialize, Serializer};
+
+/// Serialized as a number
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct WrappedI80F48 {
+    pub value: [u8; 16],
+}
+
+impl From<WrappedI80F48> for I80F48 {
+    fn from(w: WrappedI80F48) -> Self {
+        Self::from_le_bytes(w.value)
+    }
+}
+
+impl Serialize for WrappedI80F48 {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_f64(I80F48::from(*self).to_num::<f64>())
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, PartialEq, Serialize)]
+pub struct LiquidationBalances {
+    pub liquidatee_asset_balance: f64,
+    pub liquidatee_liability_balance: f64,
+    pub liquidator_asset_balance: f64,
+    pub liquidator_liability_balance: f64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum RiskTier {
+    Collateral,
+    Isolated,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum BankOperationalState {
+    Paused,
+    Operational,
+    ReduceOnly,
+}
+
+// Pubkeys are serialized in base58, as the explorers and the ETL show them
+
+pub fn serialize_pubkey<S: Serializer>(
+    pubkey: &Pubkey,
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error> {
+    serializer.collect_str(pubkey)
+}
+
+pub fn serialize_pubkey_option<S: Serializer>(
+    pubkey: &Option<Pubkey>,
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error> {
+    match pubkey {
+        Some(pubkey) => serializer.collect_str(pubkey),
+        None => serializer.serialize_none(),
+    }
+}
+
+pub fn serialize_pubkey_vec<S: Serializer>(
+    pubkeys: &[Pubkey],
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error> {
+    serializer.collect_seq(pubkeys.iter().map(Pubkey::to_string))
+}
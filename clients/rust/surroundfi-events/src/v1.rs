@@ -0,0 +1,307 @@
+//! Layouts emitted before event headers carried a version. Every event of this version starts with
+//! a header, except `EditStakedSettingsEvent`.
+
+use anchor_lang::prelude::*;
+use serde::Serialize;
+
+use crate::types::{
+    serialize_pubkey, serialize_pubkey_option, BankOperationalState, LiquidationBalances, RiskTier,
+    WrappedI80F48,
+};
+
+pub const VERSION: u8 = 1;
+
+decode_events! {
+    SurroundfiGroupCreateEvent,
+    SurroundfiGroupConfigureEvent,
+    LendingPoolBankCreateEvent,
+    LendingPoolBankConfigureEvent,
+    LendingPoolBankConfigureOracleEvent,
+    LendingPoolBankConfigureFrozenEvent,
+    EditStakedSettingsEvent,
+    LendingPoolBankAccrueInterestEvent,
+    LendingPoolBankCollectFeesEvent,
+    LendingPoolBankHandleBankruptcyEvent,
+    SurroundfiAccountCreateEvent,
+    LendingAccountDepositEvent,
+    LendingAccountRepayEvent,
+    LendingAccountBorrowEvent,
+    LendingAccountWithdrawEvent,
+    LendingAccountLiquidateEvent,
+    SurroundfiAccountTransferAccountAuthorityEvent,
+}
+
+// Event headers
+
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, PartialEq, Serialize)]
+pub struct GroupEventHeader {
+    #[serde(serialize_with = "serialize_pubkey_option")]
+    pub signer: Option<Pubkey>,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub surroundfi_group: Pubkey,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, PartialEq, Serialize)]
+pub struct AccountEventHeader {
+    #[serde(serialize_with = "serialize_pubkey_option")]
+    pub signer: Option<Pubkey>,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub surroundfi_account: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub surroundfi_account_authority: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub surroundfi_group: Pubkey,
+}
+
+// surroundfi group events
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SurroundfiGroupCreateEvent {
+    pub header: GroupEventHeader,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SurroundfiGroupConfigureEvent {
+    pub header: GroupEventHeader,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub admin: Pubkey,
+    pub flags: u64,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LendingPoolBankCreateEvent {
+    pub header: GroupEventHeader,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub bank: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub mint: Pubkey,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LendingPoolBankConfigureEvent {
+    pub header: GroupEventHeader,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub bank: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub mint: Pubkey,
+    pub config: BankConfigOpt,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LendingPoolBankConfigureOracleEvent {
+    pub header: GroupEventHeader,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub bank: Pubkey,
+    pub oracle_setup: u8,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub oracle: Pubkey,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LendingPoolBankConfigureFrozenEvent {
+    pub header: GroupEventHeader,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub bank: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub mint: Pubkey,
+    pub deposit_limit: u64,
+    pub borrow_limit: u64,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EditStakedSettingsEvent {
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub group: Pubkey,
+    pub settings: StakedSettingsEditConfig,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LendingPoolBankAccrueInterestEvent {
+    pub header: GroupEventHeader,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub bank: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub mint: Pubkey,
+    pub delta: u64,
+    pub fees_collected: f64,
+    pub insurance_collected: f64,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LendingPoolBankCollectFeesEvent {
+    pub header: GroupEventHeader,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub bank: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub mint: Pubkey,
+    pub group_fees_collected: f64,
+    pub group_fees_outstanding: f64,
+    pub insurance_fees_collected: f64,
+    pub insurance_fees_outstanding: f64,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LendingPoolBankHandleBankruptcyEvent {
+    pub header: AccountEventHeader,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub bank: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub mint: Pubkey,
+    pub bad_debt: f64,
+    pub covered_amount: f64,
+    pub socialized_amount: f64,
+}
+
+// surroundfi account events
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SurroundfiAccountCreateEvent {
+    pub header: AccountEventHeader,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LendingAccountDepositEvent {
+    pub header: AccountEventHeader,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub bank: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub mint: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LendingAccountRepayEvent {
+    pub header: AccountEventHeader,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub bank: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub close_balance: bool,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LendingAccountBorrowEvent {
+    pub header: AccountEventHeader,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub bank: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub mint: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LendingAccountWithdrawEvent {
+    pub header: AccountEventHeader,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub bank: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub close_balance: bool,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LendingAccountLiquidateEvent {
+    pub header: AccountEventHeader,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub liquidatee_surroundfi_account: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub liquidatee_surroundfi_account_authority: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub asset_bank: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub asset_mint: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub liability_bank: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub liability_mint: Pubkey,
+    pub liquidatee_pre_health: f64,
+    pub liquidatee_post_health: f64,
+    pub pre_balances: LiquidationBalances,
+    pub post_balances: LiquidationBalances,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SurroundfiAccountTransferAccountAuthorityEvent {
+    pub header: AccountEventHeader,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub old_account_authority: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub new_account_authority: Pubkey,
+}
+
+// Types nested in events
+
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, PartialEq, Serialize)]
+pub struct InterestRateConfigOpt {
+    pub optimal_utilization_rate: Option<WrappedI80F48>,
+    pub plateau_interest_rate: Option<WrappedI80F48>,
+    pub max_interest_rate: Option<WrappedI80F48>,
+
+    pub insurance_fee_fixed_apr: Option<WrappedI80F48>,
+    pub insurance_ir_fee: Option<WrappedI80F48>,
+    pub protocol_fixed_fee_apr: Option<WrappedI80F48>,
+    pub protocol_ir_fee: Option<WrappedI80F48>,
+    pub protocol_origination_fee: Option<WrappedI80F48>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, PartialEq, Serialize)]
+pub struct BankConfigOpt {
+    pub asset_weight_init: Option<WrappedI80F48>,
+    pub asset_weight_maint: Option<WrappedI80F48>,
+
+    pub liability_weight_init: Option<WrappedI80F48>,
+    pub liability_weight_maint: Option<WrappedI80F48>,
+
+    pub deposit_limit: Option<u64>,
+    pub borrow_limit: Option<u64>,
+
+    pub operational_state: Option<BankOperationalState>,
+
+    pub interest_rate_config: Option<InterestRateConfigOpt>,
+
+    pub risk_tier: Option<RiskTier>,
+
+    pub asset_tag: Option<u8>,
+
+    pub total_asset_value_init_limit: Option<u64>,
+
+    pub oracle_max_age: Option<u16>,
+
+    pub permissionless_bad_debt_settlement: Option<bool>,
+
+    pub freeze_settings: Option<bool>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, PartialEq, Serialize)]
+pub struct StakedSettingsEditConfig {
+    #[serde(serialize_with = "serialize_pubkey_option")]
+    pub oracle: Option<Pubkey>,
+
+    pub asset_weight_init: Option<WrappedI80F48>,
+    pub asset_weight_maint: Option<WrappedI80F48>,
+
+    pub deposit_limit: Option<u64>,
+    pub total_asset_value_init_limit: Option<u64>,
+
+    pub oracle_max_age: Option<u16>,
+    pub risk_tier: Option<RiskTier>,
+}
@@ -0,0 +1,971 @@
+//! Layouts since `EVENT_SCHEMA_VERSION` 2, the first version written in event headers.
+//!
+//! The program's current layouts: the `layouts_match_program` test compares these with the
+//! program's IDL and fails on any change, which calls for a new version and module.
+
+use anchor_lang::prelude::*;
+use serde::Serialize;
+
+use crate::types::{
+    serialize_pubkey, serialize_pubkey_option, serialize_pubkey_vec, BankOperationalState,
+    LiquidationBalances, RiskTier, WrappedI80F48,
+};
+
+pub const VERSION: u8 = 2;
+
+decode_events! {
+    SurroundfiGroupCreateEvent,
+    SurroundfiGroupConfigureEvent,
+    SurroundfiGroupConfigureAssetTagMatrixEvent,
+    SurroundfiGroupConfigureArenaEvent,
+    SurroundfiGroupConfigureMintExtensionsEvent,
+    LendingPoolBankCreateEvent,
+    LendingPoolBankConfigureEvent,
+    LendingPoolBankConfigureOracleEvent,
+    LendingPoolBankConfigureFrozenEvent,
+    LendingPoolBankRefreshMintExtensionsEvent,
+    SurroundfiGroupConfigureFeeEvent,
+    SurroundfiGroupPropagateFeeEvent,
+    FeeStateInitEvent,
+    FeeStateEditEvent,
+    SurroundfiGroupInitReferrerFeesEvent,
+    InitStakedSettingsEvent,
+    EditStakedSettingsEvent,
+    LendingPoolBankPropagateStakedSettingsEvent,
+    LendingPoolBankClearStakedSettingsOverridesEvent,
+    LendingPoolBankAccrueInterestEvent,
+    LendingPoolBankCollectFeesEvent,
+    LendingPoolBankHandleBankruptcyEvent,
+    LendingPoolBankWithdrawFeesEvent,
+    LendingPoolBankWithdrawInsuranceEvent,
+    LendingPoolBankInitReceiptMintEvent,
+    LendingPoolBankReceiptDepositEvent,
+    LendingPoolBankReceiptRedeemEvent,
+    LendingPoolBankSetupEmissionsEvent,
+    LendingPoolBankUpdateEmissionsEvent,
+    LendingPoolBankReclaimEmissionsEvent,
+    LendingPoolBankInitEmissionsProgramEvent,
+    LendingPoolBankAddEmissionsStreamEvent,
+    LendingPoolBankFundEmissionsStreamEvent,
+    SurroundfiGroupInitFeeDistributionEvent,
+    SurroundfiGroupConfigureFeeDistributionEvent,
+    LendingPoolBankDistributeFeesEvent,
+    LendingPoolBankClaimReferrerFeesEvent,
+    LendingPoolBankConfigureInsuranceStakingEvent,
+    LendingPoolBankInitInsuranceStakeEvent,
+    LendingPoolBankInsuranceStakeEvent,
+    LendingPoolBankInsuranceRequestUnstakeEvent,
+    LendingPoolBankInsuranceUnstakeEvent,
+    SurroundfiAccountCreateEvent,
+    SurroundfiAccountCloseEvent,
+    SurroundfiAccountInitExtensionEvent,
+    SurroundfiAccountCloseExtensionEvent,
+    SurroundfiAccountSetFlagEvent,
+    SurroundfiAccountUnsetFlagEvent,
+    LendingAccountDepositEvent,
+    LendingAccountRepayEvent,
+    LendingAccountBorrowEvent,
+    LendingAccountCloseBalanceEvent,
+    LendingAccountStartFlashloanEvent,
+    LendingAccountEndFlashloanEvent,
+    LendingAccountCallbackFlashloanEvent,
+    LendingAccountWithdrawEvent,
+    LendingAccountOpenLeveragedPositionEvent,
+    LendingAccountCloseLeveragedPositionEvent,
+    LendingAccountLiquidateEvent,
+    SurroundfiAccountTransferAccountAuthorityEvent,
+    LendingAccountPulseHealthEvent,
+    SurroundfiAccountUpdateEmissionsDestinationAccountEvent,
+    LendingAccountSettleEmissionsEvent,
+    LendingAccountWithdrawEmissionsEvent,
+    LendingAccountInitEmissionsAccrualEvent,
+    LendingAccountSettleStreamEmissionsEvent,
+    LendingAccountWithdrawStreamEmissionsEvent,
+}
+
+// Event headers
+
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, PartialEq, Serialize)]
+pub struct GroupEventHeader {
+    /// Always `VERSION`
+    pub version: u8,
+    #[serde(serialize_with = "serialize_pubkey_option")]
+    pub signer: Option<Pubkey>,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub surroundfi_group: Pubkey,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, PartialEq, Serialize)]
+pub struct AccountEventHeader {
+    /// Always `VERSION`
+    pub version: u8,
+    #[serde(serialize_with = "serialize_pubkey_option")]
+    pub signer: Option<Pubkey>,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub surroundfi_account: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub surroundfi_account_authority: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub surroundfi_group: Pubkey,
+}
+
+// surroundfi group events
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SurroundfiGroupCreateEvent {
+    pub header: GroupEventHeader,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SurroundfiGroupConfigureEvent {
+    pub header: GroupEventHeader,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub admin: Pubkey,
+    pub flags: u64,
+    pub bankruptcy_threshold: WrappedI80F48,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SurroundfiGroupConfigureAssetTagMatrixEvent {
+    pub header: GroupEventHeader,
+    pub coexist: [u8; 8],
+    pub liquidation: [u8; 8],
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SurroundfiGroupConfigureArenaEvent {
+    pub header: GroupEventHeader,
+    pub max_leverage: WrappedI80F48,
+    pub liquidation_fee: WrappedI80F48,
+    pub swap_program: Pubkey,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SurroundfiGroupConfigureMintExtensionsEvent {
+    pub header: GroupEventHeader,
+    pub permitted_mint_extensions: u64,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LendingPoolBankCreateEvent {
+    pub header: GroupEventHeader,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub bank: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub mint: Pubkey,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LendingPoolBankConfigureEvent {
+    pub header: GroupEventHeader,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub bank: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub mint: Pubkey,
+    pub config: BankConfigOpt,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LendingPoolBankConfigureOracleEvent {
+    pub header: GroupEventHeader,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub bank: Pubkey,
+    pub oracle_setup: u8,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub oracle: Pubkey,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LendingPoolBankConfigureFrozenEvent {
+    pub header: GroupEventHeader,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub bank: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub mint: Pubkey,
+    pub deposit_limit: u64,
+    pub borrow_limit: u64,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LendingPoolBankRefreshMintExtensionsEvent {
+    pub header: GroupEventHeader,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub bank: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub mint: Pubkey,
+    pub mint_extensions: u64,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SurroundfiGroupConfigureFeeEvent {
+    pub header: GroupEventHeader,
+    pub program_fee_enabled: bool,
+    pub group_flags: u64,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SurroundfiGroupPropagateFeeEvent {
+    pub header: GroupEventHeader,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub global_fee_wallet: Pubkey,
+    pub program_fee_fixed: WrappedI80F48,
+    pub program_fee_rate: WrappedI80F48,
+}
+
+/// The fee state is program-wide, the header's `surroundfi_group` is always the default pubkey
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FeeStateInitEvent {
+    pub header: GroupEventHeader,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub fee_state: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub global_fee_admin: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub global_fee_wallet: Pubkey,
+    pub bank_init_flat_sol_fee: u32,
+    pub program_fee_fixed: WrappedI80F48,
+    pub program_fee_rate: WrappedI80F48,
+}
+
+/// The fee state is program-wide, the header's `surroundfi_group` is always the default pubkey
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FeeStateEditEvent {
+    pub header: GroupEventHeader,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub fee_state: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub global_fee_admin: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub global_fee_wallet: Pubkey,
+    pub bank_init_flat_sol_fee: u32,
+    pub program_fee_fixed: WrappedI80F48,
+    pub program_fee_rate: WrappedI80F48,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SurroundfiGroupInitReferrerFeesEvent {
+    pub header: GroupEventHeader,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub referrer: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub referrer_fees: Pubkey,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct InitStakedSettingsEvent {
+    pub header: GroupEventHeader,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub staked_settings: Pubkey,
+    pub settings: StakedSettingsConfig,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EditStakedSettingsEvent {
+    pub header: GroupEventHeader,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub staked_settings: Pubkey,
+    pub settings: StakedSettingsEditConfig,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LendingPoolBankPropagateStakedSettingsEvent {
+    pub header: GroupEventHeader,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub bank: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub staked_settings: Pubkey,
+    /// `STAKED_OVERRIDE_*` flags left as configured on the bank
+    pub overrides: u8,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LendingPoolBankClearStakedSettingsOverridesEvent {
+    pub header: GroupEventHeader,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub bank: Pubkey,
+    /// `STAKED_OVERRIDE_*` flags cleared
+    pub cleared: u8,
+    /// `STAKED_OVERRIDE_*` flags still set
+    pub overrides: u8,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LendingPoolBankAccrueInterestEvent {
+    pub header: GroupEventHeader,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub bank: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub mint: Pubkey,
+    pub delta: u64,
+    pub fees_collected: f64,
+    pub insurance_collected: f64,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LendingPoolBankCollectFeesEvent {
+    pub header: GroupEventHeader,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub bank: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub mint: Pubkey,
+    pub group_fees_collected: f64,
+    pub group_fees_outstanding: f64,
+    pub insurance_fees_collected: f64,
+    pub insurance_fees_outstanding: f64,
+    /// Insurance fees credited to insurance stakers since the last credit, collected or not
+    pub insurance_staker_fees: WrappedI80F48,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LendingPoolBankHandleBankruptcyEvent {
+    pub header: AccountEventHeader,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub bank: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub mint: Pubkey,
+    pub bad_debt: f64,
+    pub covered_amount: f64,
+    /// Part of the insurance vault's outflow (including any transfer fee) taken from insurance
+    /// stakers
+    pub staker_covered_amount: WrappedI80F48,
+    pub socialized_amount: f64,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LendingPoolBankWithdrawFeesEvent {
+    pub header: GroupEventHeader,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub bank: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub mint: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub destination_account: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LendingPoolBankWithdrawInsuranceEvent {
+    pub header: GroupEventHeader,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub bank: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub mint: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub destination_account: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LendingPoolBankInitReceiptMintEvent {
+    pub header: GroupEventHeader,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub bank: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub receipt_mint: Pubkey,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LendingPoolBankReceiptDepositEvent {
+    pub header: GroupEventHeader,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub bank: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub receipt_amount: u64,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LendingPoolBankReceiptRedeemEvent {
+    pub header: GroupEventHeader,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub bank: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub receipt_amount: u64,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LendingPoolBankSetupEmissionsEvent {
+    pub header: GroupEventHeader,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub bank: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub emissions_mint: Pubkey,
+    pub flags: u64,
+    pub rate: u64,
+    pub total_emissions: u64,
+    pub start_time: i64,
+    pub end_time: i64,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LendingPoolBankUpdateEmissionsEvent {
+    pub header: GroupEventHeader,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub bank: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub emissions_mint: Pubkey,
+    /// Bank flags after the update
+    pub flags: u64,
+    pub rate: u64,
+    pub additional_emissions: u64,
+    pub emissions_remaining: WrappedI80F48,
+    pub start_time: i64,
+    pub end_time: i64,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LendingPoolBankReclaimEmissionsEvent {
+    pub header: GroupEventHeader,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub bank: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub emissions_mint: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LendingPoolBankInitEmissionsProgramEvent {
+    pub header: GroupEventHeader,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub bank: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub emissions_program: Pubkey,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LendingPoolBankAddEmissionsStreamEvent {
+    pub header: GroupEventHeader,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub bank: Pubkey,
+    pub stream_id: u8,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub emissions_mint: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub funder: Pubkey,
+    pub flags: u64,
+    pub rate: u64,
+    pub start_time: i64,
+    pub end_time: i64,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LendingPoolBankFundEmissionsStreamEvent {
+    pub header: GroupEventHeader,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub bank: Pubkey,
+    pub stream_id: u8,
+    pub amount: u64,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SurroundfiGroupInitFeeDistributionEvent {
+    pub header: GroupEventHeader,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub fee_distribution: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey_vec")]
+    pub recipients: Vec<Pubkey>,
+    pub shares_bps: Vec<u16>,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SurroundfiGroupConfigureFeeDistributionEvent {
+    pub header: GroupEventHeader,
+    #[serde(serialize_with = "serialize_pubkey_vec")]
+    pub recipients: Vec<Pubkey>,
+    pub shares_bps: Vec<u16>,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LendingPoolBankDistributeFeesEvent {
+    pub header: GroupEventHeader,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub bank: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub mint: Pubkey,
+    /// In the order of the group's fee distribution
+    #[serde(serialize_with = "serialize_pubkey_vec")]
+    pub recipients: Vec<Pubkey>,
+    /// Sent to each recipient's ATA, before any transfer fee
+    pub amounts: Vec<u64>,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LendingPoolBankClaimReferrerFeesEvent {
+    pub header: GroupEventHeader,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub bank: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub mint: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub referrer: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LendingPoolBankConfigureInsuranceStakingEvent {
+    pub header: GroupEventHeader,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub bank: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub mint: Pubkey,
+    pub fee_share: WrappedI80F48,
+    pub cooldown: i64,
+    pub unstake_window: i64,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LendingPoolBankInitInsuranceStakeEvent {
+    pub header: GroupEventHeader,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub bank: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub insurance_stake: Pubkey,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LendingPoolBankInsuranceStakeEvent {
+    pub header: GroupEventHeader,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub bank: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub mint: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub insurance_stake: Pubkey,
+    pub amount: u64,
+    pub shares: WrappedI80F48,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LendingPoolBankInsuranceRequestUnstakeEvent {
+    pub header: GroupEventHeader,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub bank: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub mint: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub insurance_stake: Pubkey,
+    pub shares: WrappedI80F48,
+    pub unstakeable_at: i64,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LendingPoolBankInsuranceUnstakeEvent {
+    pub header: GroupEventHeader,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub bank: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub mint: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub insurance_stake: Pubkey,
+    pub shares: WrappedI80F48,
+    pub amount: u64,
+}
+
+// surroundfi account events
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SurroundfiAccountCreateEvent {
+    pub header: AccountEventHeader,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SurroundfiAccountCloseEvent {
+    pub header: AccountEventHeader,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SurroundfiAccountInitExtensionEvent {
+    pub header: AccountEventHeader,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub extension: Pubkey,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SurroundfiAccountCloseExtensionEvent {
+    pub header: AccountEventHeader,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub extension: Pubkey,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SurroundfiAccountSetFlagEvent {
+    pub header: AccountEventHeader,
+    pub flag: u64,
+    /// Account flags after the update
+    pub account_flags: u64,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SurroundfiAccountUnsetFlagEvent {
+    pub header: AccountEventHeader,
+    pub flag: u64,
+    /// Account flags after the update
+    pub account_flags: u64,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LendingAccountDepositEvent {
+    pub header: AccountEventHeader,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub bank: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub referrer: Option<ReferrerEventStats>,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LendingAccountRepayEvent {
+    pub header: AccountEventHeader,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub bank: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub close_balance: bool,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LendingAccountBorrowEvent {
+    pub header: AccountEventHeader,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub bank: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub referrer: Option<ReferrerEventStats>,
+}
+
+/// Referrer of a deposit or borrow and its totals on the bank after the action
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, PartialEq, Serialize)]
+pub struct ReferrerEventStats {
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub referrer: Pubkey,
+    /// Share of the origination fee earned on this borrow, zero for deposits
+    pub fee: u64,
+    pub lifetime_fees: u64,
+    pub referred_borrows: u64,
+    pub referred_deposits: u64,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LendingAccountCloseBalanceEvent {
+    pub header: AccountEventHeader,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub bank: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub mint: Pubkey,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LendingAccountStartFlashloanEvent {
+    pub header: AccountEventHeader,
+    pub end_index: u64,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LendingAccountEndFlashloanEvent {
+    pub header: AccountEventHeader,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LendingAccountCallbackFlashloanEvent {
+    pub header: AccountEventHeader,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub bank: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub fee: u64,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub callback_program: Pubkey,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LendingAccountWithdrawEvent {
+    pub header: AccountEventHeader,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub bank: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub close_balance: bool,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LendingAccountOpenLeveragedPositionEvent {
+    pub header: AccountEventHeader,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub collateral_bank: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub debt_bank: Pubkey,
+    /// Collateral deposited, including the swap proceeds
+    pub deposited: u64,
+    /// Debt incurred, including the origination fee
+    pub borrowed: u64,
+    pub swap_proceeds: u64,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LendingAccountCloseLeveragedPositionEvent {
+    pub header: AccountEventHeader,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub collateral_bank: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub debt_bank: Pubkey,
+    /// Collateral withdrawn, including the rest of the collateral if `closed`
+    pub withdrawn: u64,
+    pub repaid: u64,
+    pub swap_proceeds: u64,
+    /// True if the debt was fully repaid
+    pub closed: bool,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LendingAccountLiquidateEvent {
+    pub header: AccountEventHeader,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub liquidatee_surroundfi_account: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub liquidatee_surroundfi_account_authority: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub asset_bank: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub asset_mint: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub liability_bank: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub liability_mint: Pubkey,
+    pub liquidatee_pre_health: f64,
+    pub liquidatee_post_health: f64,
+    pub pre_balances: LiquidationBalances,
+    pub post_balances: LiquidationBalances,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SurroundfiAccountTransferAccountAuthorityEvent {
+    pub header: AccountEventHeader,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub old_account_authority: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub new_account_authority: Pubkey,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LendingAccountPulseHealthEvent {
+    pub header: AccountEventHeader,
+    pub engine_ok: bool,
+    pub healthy: bool,
+    pub asset_value_maint: WrappedI80F48,
+    pub liability_value_maint: WrappedI80F48,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SurroundfiAccountUpdateEmissionsDestinationAccountEvent {
+    pub header: AccountEventHeader,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub emissions_destination_account: Pubkey,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LendingAccountSettleEmissionsEvent {
+    pub header: AccountEventHeader,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub bank: Pubkey,
+    pub emissions_outstanding: WrappedI80F48,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LendingAccountWithdrawEmissionsEvent {
+    pub header: AccountEventHeader,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub bank: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub emissions_mint: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub destination_account: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LendingAccountInitEmissionsAccrualEvent {
+    pub header: AccountEventHeader,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub bank: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub emissions_accrual: Pubkey,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LendingAccountSettleStreamEmissionsEvent {
+    pub header: AccountEventHeader,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub bank: Pubkey,
+    /// None if all streams of the bank were settled
+    pub stream_id: Option<u8>,
+}
+
+#[event]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LendingAccountWithdrawStreamEmissionsEvent {
+    pub header: AccountEventHeader,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub bank: Pubkey,
+    pub stream_id: u8,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub emissions_mint: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub destination_account: Pubkey,
+    pub amount: u64,
+}
+
+// Types nested in events
+
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, PartialEq, Serialize)]
+pub struct InterestRateConfigOpt {
+    pub optimal_utilization_rate: Option<WrappedI80F48>,
+    pub plateau_interest_rate: Option<WrappedI80F48>,
+    pub max_interest_rate: Option<WrappedI80F48>,
+
+    pub insurance_fee_fixed_apr: Option<WrappedI80F48>,
+    pub insurance_ir_fee: Option<WrappedI80F48>,
+    pub protocol_fixed_fee_apr: Option<WrappedI80F48>,
+    pub protocol_ir_fee: Option<WrappedI80F48>,
+    pub protocol_origination_fee: Option<WrappedI80F48>,
+    pub flashloan_fee: Option<WrappedI80F48>,
+    pub flashloan_group_fee: Option<WrappedI80F48>,
+    pub referrer_fee_share: Option<WrappedI80F48>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, PartialEq, Serialize)]
+pub struct BankConfigOpt {
+    pub asset_weight_init: Option<WrappedI80F48>,
+    pub asset_weight_maint: Option<WrappedI80F48>,
+
+    pub liability_weight_init: Option<WrappedI80F48>,
+    pub liability_weight_maint: Option<WrappedI80F48>,
+
+    pub deposit_limit: Option<u64>,
+    pub borrow_limit: Option<u64>,
+
+    pub operational_state: Option<BankOperationalState>,
+
+    pub interest_rate_config: Option<InterestRateConfigOpt>,
+
+    pub risk_tier: Option<RiskTier>,
+
+    pub asset_tag: Option<u8>,
+
+    pub total_asset_value_init_limit: Option<u64>,
+
+    pub oracle_max_age: Option<u16>,
+
+    pub permissionless_bad_debt_settlement: Option<bool>,
+
+    pub freeze_settings: Option<bool>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, PartialEq, Serialize)]
+pub struct StakedSettingsConfig {
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub oracle: Pubkey,
+
+    pub asset_weight_init: WrappedI80F48,
+    pub asset_weight_maint: WrappedI80F48,
+
+    pub deposit_limit: u64,
+    pub total_asset_value_init_limit: u64,
+
+    pub oracle_max_age: u16,
+    pub risk_tier: RiskTier,
+
+    pub stable_liability_weight_init: WrappedI80F48,
+    pub stable_liability_weight_maint: WrappedI80F48,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, PartialEq, Serialize)]
+pub struct StakedSettingsEditConfig {
+    #[serde(serialize_with = "serialize_pubkey_option")]
+    pub oracle: Option<Pubkey>,
+
+    pub asset_weight_init: Option<WrappedI80F48>,
+    pub asset_weight_maint: Option<WrappedI80F48>,
+
+    pub deposit_limit: Option<u64>,
+    pub total_asset_value_init_limit: Option<u64>,
+
+    pub oracle_max_age: Option<u16>,
+    pub risk_tier: Option<RiskTier>,
+
+    pub stable_liability_weight_init: Option<WrappedI80F48>,
+    pub stable_liability_weight_maint: Option<WrappedI80F48>,
+}
@@ -2,6 +2,7 @@ use crate::{
     common::Target,
     utils::{
         big_query::DATE_FORMAT_STR,
+        events::encode_events,
         protos::gcp_pubsub,
         transactions_crawler::{
             TransactionsCrawler, TransactionsCrawlerConfig, TransactionsCrawlerContext,
@@ -116,6 +117,10 @@ pub async fn push_transactions_to_pubsub(
 
             let tx_with_meta = &transaction_data.transaction.transaction;
             let tx_decoded = tx_with_meta.transaction.decode().unwrap();
+            let logs: Option<Vec<String>> = tx_with_meta
+                .meta
+                .as_ref()
+                .and_then(|meta| meta.log_messages.clone().into());
 
             // println!(
             //     "{:?} - {}",
@@ -158,6 +163,15 @@ pub async fn push_transactions_to_pubsub(
                     .map(|meta| serde_json::to_string(&meta).unwrap())
                     .unwrap(),
                 message: general_purpose::STANDARD.encode(tx_decoded.message.serialize()),
+                events: encode_events(
+                    &transaction_data.indexing_address,
+                    &tx_decoded.signatures.first().unwrap().to_string(),
+                    tx_with_meta
+                        .meta
+                        .as_ref()
+                        .and_then(|meta| meta.err.as_ref()),
+                    logs.as_deref().unwrap_or_default(),
+                ),
             };
 
             let message_str = serde_json::to_string(&message).unwrap();
@@ -1,4 +1,4 @@
-use crate::utils::{big_query::DATE_FORMAT_STR, protos::gcp_pubsub};
+use crate::utils::{big_query::DATE_FORMAT_STR, events::encode_events, protos::gcp_pubsub};
 use anyhow::Result;
 use base64::{engine::general_purpose, Engine};
 use chrono::{DateTime, Utc};
@@ -15,6 +15,7 @@ use solana_transaction_status::{
 };
 use std::{
     collections::{BTreeMap, BTreeSet, HashMap},
+    str::FromStr,
     sync::{
         atomic::{AtomicU64, Ordering},
         Arc, Mutex,
@@ -358,6 +359,17 @@ pub async fn push_transactions_to_pubsub(ctx: Arc<Context>) -> Result<()> {
                         .unwrap(),
                         message: general_purpose::STANDARD
                             .encode(transaction_data.transaction.transaction.message.serialize()),
+                        events: encode_events(
+                            &Pubkey::from_str(indexing_address).unwrap(),
+                            &transaction_data.signature.to_string(),
+                            transaction_data.transaction.meta.status.as_ref().err(),
+                            transaction_data
+                                .transaction
+                                .meta
+                                .log_messages
+                                .as_deref()
+                                .unwrap_or_default(),
+                        ),
                     };
 
                     let message_str = serde_json::to_string(&message).unwrap();
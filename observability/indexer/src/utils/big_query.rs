@@ -18,6 +18,7 @@ lazy_static! {
         TableFieldSchema::big_numeric("fee"),
         TableFieldSchema::string("meta"),
         TableFieldSchema::string("message"),
+        TableFieldSchema::string("events"),
     ]);
     pub static ref ACCOUNT_SCHEMA: TableSchema = TableSchema::new(vec![
         TableFieldSchema::string("id"),
@@ -0,0 +1,64 @@
+use itertools::Itertools;
+use solana_sdk::{pubkey::Pubkey, transaction::TransactionError};
+use surroundfi_events::decode_logs;
+use tracing::warn;
+
+/// The events `program_id` emitted in a transaction, decoded with the layout of their schema
+/// version and encoded as a JSON array. Events that fail to decode are reported and left out.
+///
+/// None if the transaction failed (`err`, as in its meta): its changes were rolled back, so the
+/// events it logged before failing never took effect.
+pub fn encode_events(
+    program_id: &Pubkey,
+    signature: &str,
+    err: Option<&TransactionError>,
+    logs: &[String],
+) -> Option<String> {
+    if err.is_some() {
+        return None;
+    }
+
+    let events = decode_logs(program_id, logs)
+        .into_iter()
+        .filter_map(|event| match event {
+            Ok(event) => Some(event),
+            Err(err) => {
+                warn!("Error decoding event of tx {}: {}", signature, err);
+                None
+            }
+        })
+        .collect_vec();
+
+    Some(serde_json::to_string(&events).unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn no_events_for_failed_transactions() {
+        let program_id = Pubkey::new_unique();
+        let logs = vec![
+            format!("Program {} invoke [1]", program_id),
+            format!(
+                "Program {} failed: custom program error: 0x1771",
+                program_id
+            ),
+        ];
+
+        assert_eq!(
+            encode_events(
+                &program_id,
+                "signature",
+                Some(&TransactionError::AccountInUse),
+                &logs
+            ),
+            None
+        );
+        assert_eq!(
+            encode_events(&program_id, "signature", None, &logs),
+            Some("[]".to_string())
+        );
+    }
+}
@@ -14,9 +14,18 @@ pub const FEE_VAULT_SEED: &str = "fee_vault";
 
 pub const FEE_STATE_SEED: &str = "feestate";
 pub const STAKED_SETTINGS_SEED: &str = "staked_settings";
+pub const SURROUNDFI_ACCOUNT_SEED: &str = "surroundfi_account";
+pub const SURROUNDFI_ACCOUNT_EXTENSION_SEED: &str = "surroundfi_account_extension";
+pub const RECEIPT_MINT_SEED: &str = "receipt_mint";
 
 pub const EMISSIONS_AUTH_SEED: &str = "emissions_auth_seed";
 pub const EMISSIONS_TOKEN_ACCOUNT_SEED: &str = "emissions_token_account_seed";
+pub const EMISSIONS_PROGRAM_SEED: &str = "emissions_program";
+pub const EMISSIONS_STREAM_VAULT_SEED: &str = "emissions_stream_vault";
+pub const EMISSIONS_ACCRUAL_SEED: &str = "emissions_accrual";
+pub const FEE_DISTRIBUTION_SEED: &str = "fee_distribution";
+pub const REFERRER_FEES_SEED: &str = "referrer_fees";
+pub const INSURANCE_STAKE_SEED: &str = "insurance_stake";
 
 cfg_if::cfg_if! {
     if #[cfg(feature = "devnet")] {
@@ -54,6 +63,13 @@ pub const NATIVE_STAKE_ID: Pubkey = pubkey!("Stake111111111111111111111111111111
 pub const LIQUIDATION_LIQUIDATOR_FEE: I80F48 = I80F48!(0.025);
 pub const LIQUIDATION_INSURANCE_FEE: I80F48 = I80F48!(0.025);
 
+/// Upper bound of an arena group's `ArenaConfig::max_leverage`
+pub const ARENA_MAX_LEVERAGE: I80F48 = I80F48!(50);
+/// Upper bound of an arena group's `ArenaConfig::liquidation_fee`
+pub const ARENA_MAX_LIQUIDATION_FEE: I80F48 = I80F48!(0.1);
+/// Max borrow, swap and re-deposit rounds of `lending_account_open_leveraged_position`
+pub const ARENA_MAX_LEVERAGE_LEGS: usize = 4;
+
 /// The default fee, in native SOL in native decimals (i.e. lamports) used in testing
 pub const INIT_BANK_ORIGINATION_FEE_DEFAULT: u32 = 10000;
 
@@ -86,8 +102,14 @@ pub const EMPTY_BALANCE_THRESHOLD: I80F48 = I80F48!(1);
 /// The account also needs to have more liabilities than assets.
 ///
 /// This is USD denominated, so 0.001 = $0.1
+///
+/// Default for groups that don't configure their own `bankruptcy_threshold`.
 pub const BANKRUPT_THRESHOLD: I80F48 = I80F48!(0.1);
 
+/// Upper bound of a group's `bankruptcy_threshold`, 100x `BANKRUPT_THRESHOLD`, so accounts with
+/// more than dust collateral can't be handled as bankrupt.
+pub const MAX_BANKRUPT_THRESHOLD: I80F48 = I80F48!(10);
+
 /// Comparios threshold used to account for arithmetic artifacts on balances
 pub const ZERO_AMOUNT_THRESHOLD: I80F48 = I80F48!(0.0001);
 
@@ -99,10 +121,71 @@ pub const FREEZE_SETTINGS: u64 = 1 << 3;
 pub(crate) const EMISSION_FLAGS: u64 = EMISSIONS_FLAG_BORROW_ACTIVE | EMISSIONS_FLAG_LENDING_ACTIVE;
 pub(crate) const GROUP_FLAGS: u64 = PERMISSIONLESS_BAD_DEBT_SETTLEMENT_FLAG | FREEZE_SETTINGS;
 
+/// Fields of a staked collateral bank the admin changed on the bank itself, which
+/// `propagate_staked_settings` leaves as-is (see `BankConfig::staked_settings_overrides`)
+pub const STAKED_OVERRIDE_ORACLE: u8 = 1 << 0;
+pub const STAKED_OVERRIDE_ASSET_WEIGHTS: u8 = 1 << 1;
+pub const STAKED_OVERRIDE_LIABILITY_WEIGHTS: u8 = 1 << 2;
+pub const STAKED_OVERRIDE_DEPOSIT_LIMIT: u8 = 1 << 3;
+pub const STAKED_OVERRIDE_TOTAL_ASSET_VALUE_INIT_LIMIT: u8 = 1 << 4;
+pub const STAKED_OVERRIDE_ORACLE_MAX_AGE: u8 = 1 << 5;
+pub const STAKED_OVERRIDE_RISK_TIER: u8 = 1 << 6;
+
+pub const STAKED_OVERRIDES: u8 = STAKED_OVERRIDE_ORACLE
+    | STAKED_OVERRIDE_ASSET_WEIGHTS
+    | STAKED_OVERRIDE_LIABILITY_WEIGHTS
+    | STAKED_OVERRIDE_DEPOSIT_LIMIT
+    | STAKED_OVERRIDE_TOTAL_ASSET_VALUE_INIT_LIMIT
+    | STAKED_OVERRIDE_ORACLE_MAX_AGE
+    | STAKED_OVERRIDE_RISK_TIER;
+
+/// Token-2022 extensions of a bank's mint, detected when the bank is created (see
+/// `Bank::mint_extensions`)
+pub const MINT_EXTENSION_TRANSFER_FEE: u64 = 1 << 0;
+pub const MINT_EXTENSION_TRANSFER_HOOK: u64 = 1 << 1;
+pub const MINT_EXTENSION_INTEREST_BEARING: u64 = 1 << 2;
+pub const MINT_EXTENSION_MINT_CLOSE_AUTHORITY: u64 = 1 << 3;
+/// Metadata, group and group member extensions (and their pointers)
+pub const MINT_EXTENSION_METADATA: u64 = 1 << 4;
+pub const MINT_EXTENSION_PERMANENT_DELEGATE: u64 = 1 << 5;
+/// Default account state extension with new accounts frozen
+pub const MINT_EXTENSION_DEFAULT_FROZEN: u64 = 1 << 6;
+pub const MINT_EXTENSION_NON_TRANSFERABLE: u64 = 1 << 7;
+/// Confidential transfer and confidential transfer fee extensions
+pub const MINT_EXTENSION_CONFIDENTIAL_TRANSFER: u64 = 1 << 8;
+/// Not an extension: the mint has a freeze authority, which Tokenkeg mints can have too
+pub const MINT_EXTENSION_FREEZE_AUTHORITY: u64 = 1 << 9;
+/// Pausable extension, which lets the issuer halt all transfers of the mint
+pub const MINT_EXTENSION_PAUSABLE: u64 = 1 << 10;
+/// Any extension this program doesn't know about
+pub const MINT_EXTENSION_UNKNOWN: u64 = 1 << 63;
+
+/// Extensions that let the issuer seize, freeze or hide the funds of a bank's vaults. Mints with
+/// any of these can only back a bank if the group permits them, see
+/// `SurroundfiGroup::permitted_mint_extensions`.
+pub const MINT_EXTENSIONS_RESTRICTED: u64 = MINT_EXTENSION_PERMANENT_DELEGATE
+    | MINT_EXTENSION_DEFAULT_FROZEN
+    | MINT_EXTENSION_NON_TRANSFERABLE
+    | MINT_EXTENSION_CONFIDENTIAL_TRANSFER
+    | MINT_EXTENSION_FREEZE_AUTHORITY
+    | MINT_EXTENSION_PAUSABLE
+    | MINT_EXTENSION_UNKNOWN;
+
+/// `ExtensionType` of the Token-2022 pausable mint extension, which the `spl-token-2022` this
+/// program is built with predates
+pub const PAUSABLE_CONFIG_EXTENSION_TYPE: u16 = 26;
+
+/// Year length used by the Token-2022 interest-bearing extension to accrue its rate
+pub const INTEREST_BEARING_SECONDS_PER_YEAR: f64 = 60. * 60. * 24. * 365.24;
+
 /// Cutoff timestamp for balance last_update used in accounting collected emissions.
 /// Any balance updates before this timestamp are ignored, and current_timestamp is used instead.
 pub const MIN_EMISSIONS_START_TIME: u64 = 1681989983;
 
+/// `Balance.emissions_index_checkpoint` stores the bank's emissions index in units of 1/65536
+/// native token per whole share.
+pub const EMISSIONS_INDEX_CHECKPOINT_SCALE: I80F48 = I80F48!(65536);
+
 pub const MAX_EXP_10_I80F48: usize = 24;
 pub const EXP_10_I80F48: [I80F48; MAX_EXP_10_I80F48] = [
     I80F48!(1),                        // 10^0
@@ -174,5 +257,15 @@ pub const ASSET_TAG_DEFAULT: u8 = 0;
 /// `ASSET_TAG_STAKED` positions, but not both
 pub const ASSET_TAG_SOL: u8 = 1;
 /// Staked SOL assets. Accounts with a STAKED position can only deposit other STAKED assets or SOL
-/// (`ASSET_TAG_SOL`) and can only borrow SOL (`ASSET_TAG_SOL`)
+/// (`ASSET_TAG_SOL`) and can only borrow SOL (`ASSET_TAG_SOL`), or `ASSET_TAG_STABLE` assets if
+/// the group enables `STAKED_STABLE_BORROW_ENABLED`
 pub const ASSET_TAG_STAKED: u8 = 2;
+/// Receipt tokens of another bank, priced from that bank's oracle and share value. Comingles like
+/// `ASSET_TAG_DEFAULT`
+pub const ASSET_TAG_RECEIPT: u8 = 3;
+/// Stablecoins designated by the group admin. Comingles like `ASSET_TAG_DEFAULT`, and can also be
+/// borrowed against `ASSET_TAG_STAKED` assets in groups with `STAKED_STABLE_BORROW_ENABLED`
+pub const ASSET_TAG_STABLE: u8 = 4;
+/// Tags from 0 to `MAX_ASSET_TAGS - 1` can be used. Tags above `ASSET_TAG_STABLE` have no built-in
+/// rules, they only comingle as configured in the group's `AssetTagMatrix`
+pub const MAX_ASSET_TAGS: usize = 8;
@@ -152,6 +152,62 @@ pub enum SurroundfiError {
     ArenaBankLimit,
     #[msg("Arena groups cannot return to non-arena status")] // 6074
     ArenaSettingCannotChange,
+    #[msg("Account extension missing from remaining accounts")] // 6075
+    AccountExtensionMissing,
+    #[msg("Receipt tokens are not enabled for this bank")] // 6076
+    ReceiptMintNotSetup,
+    #[msg("Amount too small to mint or redeem any receipt tokens")] // 6077
+    ReceiptAmountTooSmall,
+    #[msg("Invalid emissions stream")] // 6078
+    InvalidEmissionsStream,
+    #[msg("All emissions stream slots of this bank are in use")] // 6079
+    EmissionsStreamsFull,
+    #[msg("Emissions end before they start")] // 6080
+    InvalidEmissionsSchedule,
+    #[msg("Emissions have not ended yet")] // 6081
+    EmissionsNotEnded,
+    #[msg("Too many banks borrowed from within this flashloan")] // 6082
+    FlashloanVolumesFull,
+    #[msg("Invalid fee distribution")] // 6083
+    InvalidFeeDistribution,
+    #[msg("All fee balance slots of this referrer are in use")] // 6084
+    ReferrerFeeBalancesFull,
+    #[msg("Referrer fees account does not belong to this group")] // 6085
+    InvalidReferrerFees,
+    #[msg("Insurance staking is not enabled on this bank")] // 6086
+    InsuranceStakingDisabled,
+    #[msg("Insurance unstake cooldown has not passed")] // 6087
+    InsuranceUnstakeCooldown,
+    #[msg("Not enough insurance stake")] // 6088
+    InsufficientInsuranceStake,
+    #[msg("Asset tag is not supported")] // 6089
+    UnsupportedAssetTag,
+    #[msg("Only arena groups support this")] // 6090
+    NotArenaGroup,
+    #[msg("Account exceeds the arena's max leverage")] // 6091
+    ArenaMaxLeverageExceeded,
+    #[msg("Swap did not return any tokens")] // 6092
+    SwapNoProceeds,
+    #[msg("Invalid transfer hook program or extra account metas")] // 6093
+    InvalidTransferHook,
+    #[msg("Mint has Token-2022 extensions the group doesn't permit")] // 6094
+    MintExtensionNotPermitted,
+    #[msg("Interest-bearing mint missing or invalid in remaining accounts")] // 6095
+    InterestBearingMintRequired,
+    #[msg("Third-party id 0 is reserved for accounts created without one")] // 6096
+    InvalidThirdPartyId,
+    #[msg("Missing the emissions program or accrual of an enrolled balance")] // 6097
+    EmissionsAccrualMissing,
+    #[msg("Referrer can't be the account's authority")] // 6098
+    SelfReferral,
+    #[msg("Insurance unstake window expired, request the unstake again")] // 6099
+    InsuranceUnstakeWindowExpired,
+    #[msg("Swap program is not the arena's swap program")] // 6100
+    SwapProgramNotWhitelisted,
+    #[msg("Group missing from remaining accounts")] // 6101
+    GroupMissing,
+    #[msg("Fees of this group are paid out through its fee distribution")] // 6102
+    FeeDistributionEnabled,
 }
 
 impl From<SurroundfiError> for ProgramError {
@@ -256,6 +312,36 @@ impl From<u32> for SurroundfiError {
             6070 => SurroundfiError::TooSeverePayoff,
             6071 => SurroundfiError::TooSevereLiquidation,
             6072 => SurroundfiError::WorseHealthPostLiquidation,
+            6073 => SurroundfiError::ArenaBankLimit,
+            6074 => SurroundfiError::ArenaSettingCannotChange,
+            6075 => SurroundfiError::AccountExtensionMissing,
+            6076 => SurroundfiError::ReceiptMintNotSetup,
+            6077 => SurroundfiError::ReceiptAmountTooSmall,
+            6078 => SurroundfiError::InvalidEmissionsStream,
+            6079 => SurroundfiError::EmissionsStreamsFull,
+            6080 => SurroundfiError::InvalidEmissionsSchedule,
+            6081 => SurroundfiError::EmissionsNotEnded,
+            6082 => SurroundfiError::FlashloanVolumesFull,
+            6083 => SurroundfiError::InvalidFeeDistribution,
+            6084 => SurroundfiError::ReferrerFeeBalancesFull,
+            6085 => SurroundfiError::InvalidReferrerFees,
+            6086 => SurroundfiError::InsuranceStakingDisabled,
+            6087 => SurroundfiError::InsuranceUnstakeCooldown,
+            6088 => SurroundfiError::InsufficientInsuranceStake,
+            6089 => SurroundfiError::UnsupportedAssetTag,
+            6090 => SurroundfiError::NotArenaGroup,
+            6091 => SurroundfiError::ArenaMaxLeverageExceeded,
+            6092 => SurroundfiError::SwapNoProceeds,
+            6093 => SurroundfiError::InvalidTransferHook,
+            6094 => SurroundfiError::MintExtensionNotPermitted,
+            6095 => SurroundfiError::InterestBearingMintRequired,
+            6096 => SurroundfiError::InvalidThirdPartyId,
+            6097 => SurroundfiError::EmissionsAccrualMissing,
+            6098 => SurroundfiError::SelfReferral,
+            6099 => SurroundfiError::InsuranceUnstakeWindowExpired,
+            6100 => SurroundfiError::SwapProgramNotWhitelisted,
+            6101 => SurroundfiError::GroupMissing,
+            6102 => SurroundfiError::FeeDistributionEnabled,
             _ => SurroundfiError::InternalLogicError,
         }
     }
@@ -1,16 +1,31 @@
-use crate::{state::surroundfi_group::BankConfigOpt, StakedSettingsEditConfig};
+use crate::{
+    constants::MAX_ASSET_TAGS,
+    state::surroundfi_group::{BankConfigOpt, WrappedI80F48},
+    StakedSettingsConfig, StakedSettingsEditConfig,
+};
 use anchor_lang::prelude::*;
 
+/// Layout version of the events in this file, written first in every event header. Bump it on any
+/// change to an event layout, and freeze the previous layouts in the `surroundfi-events` crate.
+///
+/// Events emitted before headers carried a version are version 1. Their first byte is the `signer`
+/// option tag (0 or 1), so versioned events start at 2 and the two stay distinguishable.
+pub const EVENT_SCHEMA_VERSION: u8 = 2;
+
 // Event headers
 
 #[derive(AnchorSerialize, AnchorDeserialize)]
 pub struct GroupEventHeader {
+    /// `EVENT_SCHEMA_VERSION` at emission
+    pub version: u8,
     pub signer: Option<Pubkey>,
     pub surroundfi_group: Pubkey,
 }
 
 #[derive(AnchorSerialize, AnchorDeserialize)]
 pub struct AccountEventHeader {
+    /// `EVENT_SCHEMA_VERSION` at emission
+    pub version: u8,
     pub signer: Option<Pubkey>,
     pub surroundfi_account: Pubkey,
     pub surroundfi_account_authority: Pubkey,
@@ -29,6 +44,28 @@ pub struct SurroundfiGroupConfigureEvent {
     pub header: GroupEventHeader,
     pub admin: Pubkey,
     pub flags: u64,
+    pub bankruptcy_threshold: WrappedI80F48,
+}
+
+#[event]
+pub struct SurroundfiGroupConfigureAssetTagMatrixEvent {
+    pub header: GroupEventHeader,
+    pub coexist: [u8; MAX_ASSET_TAGS],
+    pub liquidation: [u8; MAX_ASSET_TAGS],
+}
+
+#[event]
+pub struct SurroundfiGroupConfigureArenaEvent {
+    pub header: GroupEventHeader,
+    pub max_leverage: WrappedI80F48,
+    pub liquidation_fee: WrappedI80F48,
+    pub swap_program: Pubkey,
+}
+
+#[event]
+pub struct SurroundfiGroupConfigureMintExtensionsEvent {
+    pub header: GroupEventHeader,
+    pub permitted_mint_extensions: u64,
 }
 
 #[event]
@@ -63,12 +100,95 @@ pub struct LendingPoolBankConfigureFrozenEvent {
     pub borrow_limit: u64,
 }
 
+#[event]
+pub struct LendingPoolBankRefreshMintExtensionsEvent {
+    pub header: GroupEventHeader,
+    pub bank: Pubkey,
+    pub mint: Pubkey,
+    pub mint_extensions: u64,
+}
+
+#[event]
+pub struct SurroundfiGroupConfigureFeeEvent {
+    pub header: GroupEventHeader,
+    pub program_fee_enabled: bool,
+    pub group_flags: u64,
+}
+
+#[event]
+pub struct SurroundfiGroupPropagateFeeEvent {
+    pub header: GroupEventHeader,
+    pub global_fee_wallet: Pubkey,
+    pub program_fee_fixed: WrappedI80F48,
+    pub program_fee_rate: WrappedI80F48,
+}
+
+/// The fee state is program-wide, the header's `surroundfi_group` is always the default pubkey
+#[event]
+pub struct FeeStateInitEvent {
+    pub header: GroupEventHeader,
+    pub fee_state: Pubkey,
+    pub global_fee_admin: Pubkey,
+    pub global_fee_wallet: Pubkey,
+    pub bank_init_flat_sol_fee: u32,
+    pub program_fee_fixed: WrappedI80F48,
+    pub program_fee_rate: WrappedI80F48,
+}
+
+/// The fee state is program-wide, the header's `surroundfi_group` is always the default pubkey
+#[event]
+pub struct FeeStateEditEvent {
+    pub header: GroupEventHeader,
+    pub fee_state: Pubkey,
+    pub global_fee_admin: Pubkey,
+    pub global_fee_wallet: Pubkey,
+    pub bank_init_flat_sol_fee: u32,
+    pub program_fee_fixed: WrappedI80F48,
+    pub program_fee_rate: WrappedI80F48,
+}
+
+#[event]
+pub struct SurroundfiGroupInitReferrerFeesEvent {
+    pub header: GroupEventHeader,
+    pub referrer: Pubkey,
+    pub referrer_fees: Pubkey,
+}
+
+#[event]
+pub struct InitStakedSettingsEvent {
+    pub header: GroupEventHeader,
+    pub staked_settings: Pubkey,
+    pub settings: StakedSettingsConfig,
+}
+
+/// Emitted with `group: Pubkey` in place of the header before `EVENT_SCHEMA_VERSION` 2, the
+/// `surroundfi-events` client decodes both layouts.
 #[event]
 pub struct EditStakedSettingsEvent {
-    pub group: Pubkey,
+    pub header: GroupEventHeader,
+    pub staked_settings: Pubkey,
     pub settings: StakedSettingsEditConfig,
 }
 
+#[event]
+pub struct LendingPoolBankPropagateStakedSettingsEvent {
+    pub header: GroupEventHeader,
+    pub bank: Pubkey,
+    pub staked_settings: Pubkey,
+    /// `STAKED_OVERRIDE_*` flags left as configured on the bank
+    pub overrides: u8,
+}
+
+#[event]
+pub struct LendingPoolBankClearStakedSettingsOverridesEvent {
+    pub header: GroupEventHeader,
+    pub bank: Pubkey,
+    /// `STAKED_OVERRIDE_*` flags cleared
+    pub cleared: u8,
+    /// `STAKED_OVERRIDE_*` flags still set
+    pub overrides: u8,
+}
+
 #[event]
 pub struct LendingPoolBankAccrueInterestEvent {
     pub header: GroupEventHeader,
@@ -88,6 +208,8 @@ pub struct LendingPoolBankCollectFeesEvent {
     pub group_fees_outstanding: f64,
     pub insurance_fees_collected: f64,
     pub insurance_fees_outstanding: f64,
+    /// Insurance fees credited to insurance stakers since the last credit, collected or not
+    pub insurance_staker_fees: WrappedI80F48,
 }
 
 #[event]
@@ -97,9 +219,199 @@ pub struct LendingPoolBankHandleBankruptcyEvent {
     pub mint: Pubkey,
     pub bad_debt: f64,
     pub covered_amount: f64,
+    /// Part of the insurance vault's outflow (including any transfer fee) taken from insurance
+    /// stakers
+    pub staker_covered_amount: WrappedI80F48,
     pub socialized_amount: f64,
 }
 
+#[event]
+pub struct LendingPoolBankWithdrawFeesEvent {
+    pub header: GroupEventHeader,
+    pub bank: Pubkey,
+    pub mint: Pubkey,
+    pub destination_account: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct LendingPoolBankWithdrawInsuranceEvent {
+    pub header: GroupEventHeader,
+    pub bank: Pubkey,
+    pub mint: Pubkey,
+    pub destination_account: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct LendingPoolBankInitReceiptMintEvent {
+    pub header: GroupEventHeader,
+    pub bank: Pubkey,
+    pub receipt_mint: Pubkey,
+}
+
+#[event]
+pub struct LendingPoolBankReceiptDepositEvent {
+    pub header: GroupEventHeader,
+    pub bank: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub receipt_amount: u64,
+}
+
+#[event]
+pub struct LendingPoolBankReceiptRedeemEvent {
+    pub header: GroupEventHeader,
+    pub bank: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub receipt_amount: u64,
+}
+
+#[event]
+pub struct LendingPoolBankSetupEmissionsEvent {
+    pub header: GroupEventHeader,
+    pub bank: Pubkey,
+    pub emissions_mint: Pubkey,
+    pub flags: u64,
+    pub rate: u64,
+    pub total_emissions: u64,
+    pub start_time: i64,
+    pub end_time: i64,
+}
+
+#[event]
+pub struct LendingPoolBankUpdateEmissionsEvent {
+    pub header: GroupEventHeader,
+    pub bank: Pubkey,
+    pub emissions_mint: Pubkey,
+    /// Bank flags after the update
+    pub flags: u64,
+    pub rate: u64,
+    pub additional_emissions: u64,
+    pub emissions_remaining: WrappedI80F48,
+    pub start_time: i64,
+    pub end_time: i64,
+}
+
+#[event]
+pub struct LendingPoolBankReclaimEmissionsEvent {
+    pub header: GroupEventHeader,
+    pub bank: Pubkey,
+    pub emissions_mint: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct LendingPoolBankInitEmissionsProgramEvent {
+    pub header: GroupEventHeader,
+    pub bank: Pubkey,
+    pub emissions_program: Pubkey,
+}
+
+#[event]
+pub struct LendingPoolBankAddEmissionsStreamEvent {
+    pub header: GroupEventHeader,
+    pub bank: Pubkey,
+    pub stream_id: u8,
+    pub emissions_mint: Pubkey,
+    pub funder: Pubkey,
+    pub flags: u64,
+    pub rate: u64,
+    pub start_time: i64,
+    pub end_time: i64,
+}
+
+#[event]
+pub struct LendingPoolBankFundEmissionsStreamEvent {
+    pub header: GroupEventHeader,
+    pub bank: Pubkey,
+    pub stream_id: u8,
+    pub amount: u64,
+}
+
+#[event]
+pub struct SurroundfiGroupInitFeeDistributionEvent {
+    pub header: GroupEventHeader,
+    pub fee_distribution: Pubkey,
+    pub recipients: Vec<Pubkey>,
+    pub shares_bps: Vec<u16>,
+}
+
+#[event]
+pub struct SurroundfiGroupConfigureFeeDistributionEvent {
+    pub header: GroupEventHeader,
+    pub recipients: Vec<Pubkey>,
+    pub shares_bps: Vec<u16>,
+}
+
+#[event]
+pub struct LendingPoolBankDistributeFeesEvent {
+    pub header: GroupEventHeader,
+    pub bank: Pubkey,
+    pub mint: Pubkey,
+    /// In the order of the group's fee distribution
+    pub recipients: Vec<Pubkey>,
+    /// Sent to each recipient's ATA, before any transfer fee
+    pub amounts: Vec<u64>,
+}
+
+#[event]
+pub struct LendingPoolBankClaimReferrerFeesEvent {
+    pub header: GroupEventHeader,
+    pub bank: Pubkey,
+    pub mint: Pubkey,
+    pub referrer: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct LendingPoolBankConfigureInsuranceStakingEvent {
+    pub header: GroupEventHeader,
+    pub bank: Pubkey,
+    pub mint: Pubkey,
+    pub fee_share: WrappedI80F48,
+    pub cooldown: i64,
+    pub unstake_window: i64,
+}
+
+#[event]
+pub struct LendingPoolBankInitInsuranceStakeEvent {
+    pub header: GroupEventHeader,
+    pub bank: Pubkey,
+    pub insurance_stake: Pubkey,
+}
+
+#[event]
+pub struct LendingPoolBankInsuranceStakeEvent {
+    pub header: GroupEventHeader,
+    pub bank: Pubkey,
+    pub mint: Pubkey,
+    pub insurance_stake: Pubkey,
+    pub amount: u64,
+    pub shares: WrappedI80F48,
+}
+
+#[event]
+pub struct LendingPoolBankInsuranceRequestUnstakeEvent {
+    pub header: GroupEventHeader,
+    pub bank: Pubkey,
+    pub mint: Pubkey,
+    pub insurance_stake: Pubkey,
+    pub shares: WrappedI80F48,
+    pub unstakeable_at: i64,
+}
+
+#[event]
+pub struct LendingPoolBankInsuranceUnstakeEvent {
+    pub header: GroupEventHeader,
+    pub bank: Pubkey,
+    pub mint: Pubkey,
+    pub insurance_stake: Pubkey,
+    pub shares: WrappedI80F48,
+    pub amount: u64,
+}
+
 // surroundfi account events
 
 #[event]
@@ -107,12 +419,46 @@ pub struct SurroundfiAccountCreateEvent {
     pub header: AccountEventHeader,
 }
 
+#[event]
+pub struct SurroundfiAccountCloseEvent {
+    pub header: AccountEventHeader,
+}
+
+#[event]
+pub struct SurroundfiAccountInitExtensionEvent {
+    pub header: AccountEventHeader,
+    pub extension: Pubkey,
+}
+
+#[event]
+pub struct SurroundfiAccountCloseExtensionEvent {
+    pub header: AccountEventHeader,
+    pub extension: Pubkey,
+}
+
+#[event]
+pub struct SurroundfiAccountSetFlagEvent {
+    pub header: AccountEventHeader,
+    pub flag: u64,
+    /// Account flags after the update
+    pub account_flags: u64,
+}
+
+#[event]
+pub struct SurroundfiAccountUnsetFlagEvent {
+    pub header: AccountEventHeader,
+    pub flag: u64,
+    /// Account flags after the update
+    pub account_flags: u64,
+}
+
 #[event]
 pub struct LendingAccountDepositEvent {
     pub header: AccountEventHeader,
     pub bank: Pubkey,
     pub mint: Pubkey,
     pub amount: u64,
+    pub referrer: Option<ReferrerEventStats>,
 }
 
 #[event]
@@ -130,6 +476,47 @@ pub struct LendingAccountBorrowEvent {
     pub bank: Pubkey,
     pub mint: Pubkey,
     pub amount: u64,
+    pub referrer: Option<ReferrerEventStats>,
+}
+
+/// Referrer of a deposit or borrow and its totals on the bank after the action
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct ReferrerEventStats {
+    pub referrer: Pubkey,
+    /// Share of the origination fee earned on this borrow, zero for deposits
+    pub fee: u64,
+    pub lifetime_fees: u64,
+    pub referred_borrows: u64,
+    pub referred_deposits: u64,
+}
+
+#[event]
+pub struct LendingAccountCloseBalanceEvent {
+    pub header: AccountEventHeader,
+    pub bank: Pubkey,
+    pub mint: Pubkey,
+}
+
+#[event]
+pub struct LendingAccountStartFlashloanEvent {
+    pub header: AccountEventHeader,
+    pub end_index: u64,
+}
+
+#[event]
+pub struct LendingAccountEndFlashloanEvent {
+    pub header: AccountEventHeader,
+}
+
+#[event]
+pub struct LendingAccountCallbackFlashloanEvent {
+    pub header: AccountEventHeader,
+    pub bank: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+    /// Origination and flashloan fees
+    pub fee: u64,
+    pub callback_program: Pubkey,
 }
 
 #[event]
@@ -141,6 +528,31 @@ pub struct LendingAccountWithdrawEvent {
     pub close_balance: bool,
 }
 
+#[event]
+pub struct LendingAccountOpenLeveragedPositionEvent {
+    pub header: AccountEventHeader,
+    pub collateral_bank: Pubkey,
+    pub debt_bank: Pubkey,
+    /// Collateral deposited, including the swap proceeds
+    pub deposited: u64,
+    /// Debt incurred, including the origination fee
+    pub borrowed: u64,
+    pub swap_proceeds: u64,
+}
+
+#[event]
+pub struct LendingAccountCloseLeveragedPositionEvent {
+    pub header: AccountEventHeader,
+    pub collateral_bank: Pubkey,
+    pub debt_bank: Pubkey,
+    /// Collateral withdrawn, including the rest of the collateral if `closed`
+    pub withdrawn: u64,
+    pub repaid: u64,
+    pub swap_proceeds: u64,
+    /// True if the debt was fully repaid
+    pub closed: bool,
+}
+
 #[derive(AnchorSerialize, AnchorDeserialize)]
 pub struct LiquidationBalances {
     pub liquidatee_asset_balance: f64,
@@ -170,3 +582,59 @@ pub struct SurroundfiAccountTransferAccountAuthorityEvent {
     pub old_account_authority: Pubkey,
     pub new_account_authority: Pubkey,
 }
+
+#[event]
+pub struct LendingAccountPulseHealthEvent {
+    pub header: AccountEventHeader,
+    pub engine_ok: bool,
+    pub healthy: bool,
+    pub asset_value_maint: WrappedI80F48,
+    pub liability_value_maint: WrappedI80F48,
+}
+
+#[event]
+pub struct SurroundfiAccountUpdateEmissionsDestinationAccountEvent {
+    pub header: AccountEventHeader,
+    pub emissions_destination_account: Pubkey,
+}
+
+#[event]
+pub struct LendingAccountSettleEmissionsEvent {
+    pub header: AccountEventHeader,
+    pub bank: Pubkey,
+    pub emissions_outstanding: WrappedI80F48,
+}
+
+#[event]
+pub struct LendingAccountWithdrawEmissionsEvent {
+    pub header: AccountEventHeader,
+    pub bank: Pubkey,
+    pub emissions_mint: Pubkey,
+    pub destination_account: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct LendingAccountInitEmissionsAccrualEvent {
+    pub header: AccountEventHeader,
+    pub bank: Pubkey,
+    pub emissions_accrual: Pubkey,
+}
+
+#[event]
+pub struct LendingAccountSettleStreamEmissionsEvent {
+    pub header: AccountEventHeader,
+    pub bank: Pubkey,
+    /// None if all streams of the bank were settled
+    pub stream_id: Option<u8>,
+}
+
+#[event]
+pub struct LendingAccountWithdrawStreamEmissionsEvent {
+    pub header: AccountEventHeader,
+    pub bank: Pubkey,
+    pub stream_id: u8,
+    pub emissions_mint: Pubkey,
+    pub destination_account: Pubkey,
+    pub amount: u64,
+}
@@ -1,12 +1,17 @@
 use crate::{
     bank_signer, check,
     constants::LIQUIDITY_VAULT_AUTHORITY_SEED,
-    events::{AccountEventHeader, LendingAccountBorrowEvent},
+    events::{AccountEventHeader, LendingAccountBorrowEvent, EVENT_SCHEMA_VERSION},
     math_error,
     prelude::{SurroundfiError, SurroundfiGroup, SurroundfiResult},
     state::{
         health_cache::HealthCache,
-        surroundfi_account::{BankAccountWrapper, SurroundfiAccount, RiskEngine, ACCOUNT_DISABLED},
+        referrer_fees::maybe_take_referrer_fees,
+        surroundfi_account::{
+            BankAccountWrapper, SurroundfiAccount, RiskEngine, ACCOUNT_DISABLED,
+            ACCOUNT_FLASHLOAN_LEVERAGE_CAPPED, ACCOUNT_IN_FLASHLOAN,
+        },
+        surroundfi_account_extension::find_account_extension,
         surroundfi_group::{Bank, BankVaultType},
     },
     utils::{self, validate_asset_tags},
@@ -21,9 +26,14 @@ use solana_program::{clock::Clock, sysvar::Sysvar};
 /// 2. Create the user's bank account for the asset borrowed if it does not exist yet
 /// 3. Record liability increase in the bank account
 /// 4. Transfer funds from the bank's liquidity vault to the signer's token account
-/// 5. Verify that the user account is in a healthy state
+/// 5. Split the origination fee between the program, the group and the referrer (if any)
+/// 6. Verify that the user account is in a healthy state
 ///
 /// Will error if there is an existing asset <=> withdrawing is not allowed.
+///
+/// A `ReferrerFees` account of the same group may be passed in remaining accounts, after the mint
+/// (Token-2022 only) and before the risk engine accounts. The referrer then earns
+/// `referrer_fee_share` of the group's part of the origination fee.
 pub fn lending_account_borrow<'info>(
     mut ctx: Context<'_, '_, 'info, 'info, LendingAccountBorrow<'info>>,
     amount: u64,
@@ -44,6 +54,11 @@ pub fn lending_account_borrow<'info>(
         &*bank_loader.load()?,
         token_program.key,
     )?;
+    let maybe_referrer_fees = maybe_take_referrer_fees(
+        &mut ctx.remaining_accounts,
+        &surroundfi_group_loader.key(),
+        &surroundfi_account_loader.load()?.authority,
+    )?;
 
     let mut surroundfi_account = surroundfi_account_loader.load_mut()?;
     let group = &surroundfi_group_loader.load()?;
@@ -62,11 +77,9 @@ pub fn lending_account_borrow<'info>(
     )?;
 
     let mut origination_fee: I80F48 = I80F48::ZERO;
-    {
+    let borrowed_amount = {
         let mut bank = bank_loader.load_mut()?;
 
-        validate_asset_tags(&bank, &surroundfi_account)?;
-
         let liquidity_vault_authority_bump = bank.liquidity_vault_authority_bump;
         let origination_fee_rate: I80F48 = bank
             .config
@@ -74,11 +87,29 @@ pub fn lending_account_borrow<'info>(
             .protocol_origination_fee
             .into();
 
+        // Note: the extension must be released before the risk engine loads it again below
+        let extension_loader =
+            find_account_extension(&surroundfi_account, ctx.remaining_accounts)?;
+        let mut extension = extension_loader
+            .as_ref()
+            .map(|loader| loader.load_mut())
+            .transpose()?;
+
+        validate_asset_tags(
+            &bank,
+            &surroundfi_account,
+            extension.as_deref(),
+            &group.get_asset_tag_matrix(),
+        )?;
+
         let mut bank_account = BankAccountWrapper::find_or_create(
             &bank_loader.key(),
             &mut bank,
             &mut surroundfi_account.lending_account,
+            extension.as_deref_mut(),
         )?;
+        bank_account
+            .settle_emissions_streams(&surroundfi_account_loader.key(), ctx.remaining_accounts)?;
 
         // User needs to borrow amount + fee to receive amount
         let amount_pre_fee = maybe_bank_mint
@@ -123,60 +154,71 @@ pub fn lending_account_borrow<'info>(
             ctx.remaining_accounts,
         )?;
 
-        emit!(LendingAccountBorrowEvent {
-            header: AccountEventHeader {
-                signer: Some(ctx.accounts.authority.key()),
-                surroundfi_account: surroundfi_account_loader.key(),
-                surroundfi_account_authority: surroundfi_account.authority,
-                surroundfi_group: surroundfi_account.group,
-            },
-            bank: bank_loader.key(),
-            mint: bank.mint,
-            amount: amount_pre_fee + origination_fee_u64,
-        });
-    } // release mutable borrow of bank
+        // Liabilities opened within a flashloan are charged the flashloan fee on repay
+        if surroundfi_account.get_flag(ACCOUNT_IN_FLASHLOAN) && bank.has_flashloan_fee() {
+            let balance_index = surroundfi_account
+                .lending_account
+                .get_balance_index(&bank_loader.key(), extension.as_deref())
+                .ok_or_else(|| error!(SurroundfiError::InternalLogicError))?;
+            surroundfi_account
+                .lending_account
+                .record_flashloan_borrow(balance_index, amount_pre_fee + origination_fee_u64)?;
+        }
+
+        amount_pre_fee + origination_fee_u64
+    }; // release mutable borrow of bank
 
     // The program and/or group fee account gains the origination fee
-    {
-        let mut bank = bank_loader.load_mut()?;
+    let referrer_fee = bank_loader.load_mut()?.collect_origination_fee(
+        origination_fee,
+        program_fee_rate,
+        maybe_referrer_fees.is_some(),
+    )?;
 
-        if !origination_fee.is_zero() {
-            let mut bank_fees_after: I80F48 = bank.collected_group_fees_outstanding.into();
-
-            if !program_fee_rate.is_zero() {
-                // Some portion of the origination fee to goes to program fees
-                let program_fee_amount: I80F48 = origination_fee
-                    .checked_mul(program_fee_rate)
-                    .ok_or_else(math_error!())?;
-                // The remainder of the origination fee goes to group fees
-                bank_fees_after = bank_fees_after
-                    .saturating_add(origination_fee.saturating_sub(program_fee_amount));
-
-                // Update the bank's program fees
-                let program_fees_before: I80F48 = bank.collected_program_fees_outstanding.into();
-                bank.collected_program_fees_outstanding = program_fees_before
-                    .saturating_add(program_fee_amount)
-                    .into();
-            } else {
-                // If program fee rate is zero, add the full origination fee to group fees
-                bank_fees_after = bank_fees_after.saturating_add(origination_fee);
-            }
-
-            // Update the bank's group fees
-            bank.collected_group_fees_outstanding = bank_fees_after.into();
+    let referrer = match maybe_referrer_fees {
+        Some(referrer_fees_loader) => {
+            let mut referrer_fees = referrer_fees_loader.load_mut()?;
+            referrer_fees.record_borrow(&bank_loader.key(), borrowed_amount, referrer_fee)?;
+            Some(referrer_fees.event_stats(&bank_loader.key(), referrer_fee.to_num()))
         }
-    }
+        None => None,
+    };
+
+    emit!(LendingAccountBorrowEvent {
+        header: AccountEventHeader {
+            version: EVENT_SCHEMA_VERSION,
+            signer: Some(ctx.accounts.authority.key()),
+            surroundfi_account: surroundfi_account_loader.key(),
+            surroundfi_account_authority: surroundfi_account.authority,
+            surroundfi_group: surroundfi_account.group,
+        },
+        bank: bank_loader.key(),
+        mint: bank_loader.load()?.mint,
+        amount: borrowed_amount,
+        referrer,
+    });
 
     let mut health_cache = HealthCache::zeroed();
     health_cache.timestamp = clock.unix_timestamp;
 
     // Check account health, if below threshold fail transaction
     // Assuming `ctx.remaining_accounts` holds only oracle accounts
-    RiskEngine::check_account_init_health(
+    let risk_engine = RiskEngine::check_account_init_health(
         &surroundfi_account,
         ctx.remaining_accounts,
         &mut Some(&mut health_cache),
     )?;
+
+    // Arena groups can cap leverage beyond what the banks' weights allow
+    let max_leverage = group.get_max_leverage();
+    if let (Some(risk_engine), Some(max_leverage)) = (risk_engine, max_leverage) {
+        risk_engine.check_account_leverage(max_leverage)?;
+    }
+    // Within a flashloan, the cap is checked when it ends
+    if max_leverage.is_some() && surroundfi_account.get_flag(ACCOUNT_IN_FLASHLOAN) {
+        surroundfi_account.set_flag(ACCOUNT_FLASHLOAN_LEVERAGE_CAPPED);
+    }
+
     health_cache.set_engine_ok(true);
     surroundfi_account.health_cache = health_cache;
 
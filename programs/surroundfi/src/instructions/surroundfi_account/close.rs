@@ -1,6 +1,11 @@
 use anchor_lang::prelude::*;
 
-use crate::{check, state::surroundfi_account::SurroundfiAccount, SurroundfiError, SurroundfiResult};
+use crate::{
+    check,
+    events::{AccountEventHeader, SurroundfiAccountCloseEvent, EVENT_SCHEMA_VERSION},
+    state::surroundfi_account::SurroundfiAccount,
+    SurroundfiError, SurroundfiResult,
+};
 
 pub fn close_account(ctx: Context<SurroundfiAccountClose>) -> SurroundfiResult {
     let surroundfi_account = &ctx.accounts.surroundfi_account.load()?;
@@ -11,6 +16,16 @@ pub fn close_account(ctx: Context<SurroundfiAccountClose>) -> SurroundfiResult {
         "Account cannot be closed"
     );
 
+    emit!(SurroundfiAccountCloseEvent {
+        header: AccountEventHeader {
+            version: EVENT_SCHEMA_VERSION,
+            signer: Some(ctx.accounts.authority.key()),
+            surroundfi_account: ctx.accounts.surroundfi_account.key(),
+            surroundfi_account_authority: surroundfi_account.authority,
+            surroundfi_group: surroundfi_account.group,
+        },
+    });
+
     Ok(())
 }
 
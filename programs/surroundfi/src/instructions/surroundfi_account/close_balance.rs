@@ -2,22 +2,26 @@ use anchor_lang::prelude::*;
 
 use crate::{
     check,
+    events::{AccountEventHeader, LendingAccountCloseBalanceEvent, EVENT_SCHEMA_VERSION},
     prelude::*,
     state::{
         surroundfi_account::{BankAccountWrapper, SurroundfiAccount, ACCOUNT_DISABLED},
+        surroundfi_account_extension::find_account_extension,
         surroundfi_group::Bank,
     },
 };
 
-pub fn lending_account_close_balance(ctx: Context<LendingAccountCloseBalance>) -> SurroundfiResult {
+pub fn lending_account_close_balance<'info>(
+    ctx: Context<'_, '_, 'info, 'info, LendingAccountCloseBalance<'info>>,
+) -> SurroundfiResult {
     let LendingAccountCloseBalance {
-        surroundfi_account,
+        surroundfi_account: surroundfi_account_loader,
+        authority,
         bank: bank_loader,
         group: surroundfi_group_loader,
-        ..
     } = ctx.accounts;
 
-    let mut surroundfi_account = surroundfi_account.load_mut()?;
+    let mut surroundfi_account = surroundfi_account_loader.load_mut()?;
     let mut bank = bank_loader.load_mut()?;
 
     check!(
@@ -32,14 +36,33 @@ pub fn lending_account_close_balance(ctx: Context<LendingAccountCloseBalance>) -
         bank_loader.key(),
     )?;
 
+    let extension_loader = find_account_extension(&surroundfi_account, ctx.remaining_accounts)?;
+    let mut extension = extension_loader
+        .as_ref()
+        .map(|loader| loader.load_mut())
+        .transpose()?;
+
     let mut bank_account = BankAccountWrapper::find(
         &bank_loader.key(),
         &mut bank,
         &mut surroundfi_account.lending_account,
+        extension.as_deref_mut(),
     )?;
 
     bank_account.close_balance()?;
 
+    emit!(LendingAccountCloseBalanceEvent {
+        header: AccountEventHeader {
+            version: EVENT_SCHEMA_VERSION,
+            signer: Some(authority.key()),
+            surroundfi_account: surroundfi_account_loader.key(),
+            surroundfi_account_authority: surroundfi_account.authority,
+            surroundfi_group: surroundfi_account.group,
+        },
+        bank: bank_loader.key(),
+        mint: bank.mint,
+    });
+
     Ok(())
 }
 
@@ -1,10 +1,14 @@
 use crate::{
     check,
-    events::{AccountEventHeader, LendingAccountDepositEvent},
+    events::{AccountEventHeader, LendingAccountDepositEvent, EVENT_SCHEMA_VERSION},
     math_error,
     prelude::*,
     state::{
-        surroundfi_account::{BankAccountWrapper, SurroundfiAccount, ACCOUNT_DISABLED},
+        referrer_fees::maybe_take_referrer_fees,
+        surroundfi_account::{
+            BankAccountWrapper, SurroundfiAccount, ACCOUNT_DISABLED, ACCOUNT_IN_FLASHLOAN,
+        },
+        surroundfi_account_extension::find_account_extension,
         surroundfi_group::Bank,
     },
     utils::{self, validate_asset_tags},
@@ -21,6 +25,12 @@ use solana_program::sysvar::Sysvar;
 /// 4. Transfer funds from the signer's token account to the bank's liquidity vault
 ///
 /// Will error if there is an existing liability <=> repaying is not allowed.
+///
+/// Within a flashloan, the part of the deposit that pays off liabilities opened within the
+/// flashloan is charged the bank's flashloan fee, as with `lending_account_repay`.
+///
+/// A `ReferrerFees` account of the same group may be passed in remaining accounts, after the mint
+/// (Token-2022 only). Deposits earn the referrer no fees, they are only counted in its stats.
 pub fn lending_account_deposit<'info>(
     mut ctx: Context<'_, '_, 'info, 'info, LendingAccountDeposit<'info>>,
     amount: u64,
@@ -42,12 +52,28 @@ pub fn lending_account_deposit<'info>(
         &*bank_loader.load()?,
         token_program.key,
     )?;
+    let maybe_referrer_fees = maybe_take_referrer_fees(
+        &mut ctx.remaining_accounts,
+        &surroundfi_group_loader.key(),
+        &surroundfi_account_loader.load()?.authority,
+    )?;
     let deposit_up_to_limit = deposit_up_to_limit.unwrap_or(false);
 
     let mut bank = bank_loader.load_mut()?;
     let mut surroundfi_account = surroundfi_account_loader.load_mut()?;
 
-    validate_asset_tags(&bank, &surroundfi_account)?;
+    let extension_loader = find_account_extension(&surroundfi_account, ctx.remaining_accounts)?;
+    let mut extension = extension_loader
+        .as_ref()
+        .map(|loader| loader.load_mut())
+        .transpose()?;
+
+    validate_asset_tags(
+        &bank,
+        &surroundfi_account,
+        extension.as_deref(),
+        &surroundfi_group_loader.load()?.get_asset_tag_matrix(),
+    )?;
 
     check!(
         !surroundfi_account.get_flag(ACCOUNT_DISABLED),
@@ -88,25 +114,57 @@ pub fn lending_account_deposit<'info>(
         bank_loader.key(),
     )?;
 
+    // Liabilities opened within this flashloan are charged the flashloan fee when paid off
+    let flashloan_volume_closed = if surroundfi_account.get_flag(ACCOUNT_IN_FLASHLOAN) {
+        surroundfi_account
+            .lending_account
+            .record_flashloan_balance_increase(
+                &bank_loader.key(),
+                &bank,
+                extension.as_deref(),
+                deposit_amount,
+            )?
+    } else {
+        0
+    };
+
     let mut bank_account = BankAccountWrapper::find_or_create(
         &bank_loader.key(),
         &mut bank,
         &mut surroundfi_account.lending_account,
+        extension.as_deref_mut(),
     )?;
+    bank_account
+        .settle_emissions_streams(&surroundfi_account_loader.key(), ctx.remaining_accounts)?;
 
     bank_account.deposit(I80F48::from_num(deposit_amount))?;
 
+    let flashloan_fee: u64 = if flashloan_volume_closed > 0 {
+        bank_account
+            .bank
+            .credit_flashloan_fee(I80F48::from_num(flashloan_volume_closed))?
+            .checked_ceil()
+            .ok_or_else(math_error!())?
+            .checked_to_num()
+            .ok_or_else(math_error!())?
+    } else {
+        0
+    };
+    let transfer_amount_post_fee = deposit_amount
+        .checked_add(flashloan_fee)
+        .ok_or_else(math_error!())?;
+
     let amount_pre_fee = maybe_bank_mint
         .as_ref()
         .map(|mint| {
             utils::calculate_pre_fee_spl_deposit_amount(
                 mint.to_account_info(),
-                deposit_amount,
+                transfer_amount_post_fee,
                 clock.epoch,
             )
         })
         .transpose()?
-        .unwrap_or(deposit_amount);
+        .unwrap_or(transfer_amount_post_fee);
 
     bank_account.deposit_spl_transfer(
         amount_pre_fee,
@@ -118,8 +176,18 @@ pub fn lending_account_deposit<'info>(
         ctx.remaining_accounts,
     )?;
 
+    let referrer = match maybe_referrer_fees {
+        Some(referrer_fees_loader) => {
+            let mut referrer_fees = referrer_fees_loader.load_mut()?;
+            referrer_fees.record_deposit(&bank_loader.key(), deposit_amount)?;
+            Some(referrer_fees.event_stats(&bank_loader.key(), 0))
+        }
+        None => None,
+    };
+
     emit!(LendingAccountDepositEvent {
         header: AccountEventHeader {
+            version: EVENT_SCHEMA_VERSION,
             signer: Some(signer.key()),
             surroundfi_account: surroundfi_account_loader.key(),
             surroundfi_account_authority: surroundfi_account.authority,
@@ -128,6 +196,7 @@ pub fn lending_account_deposit<'info>(
         bank: bank_loader.key(),
         mint: bank.mint,
         amount: deposit_amount,
+        referrer,
     });
 
     Ok(())
@@ -0,0 +1,252 @@
+use crate::{
+    check,
+    constants::{NATIVE_STAKE_ID, SPL_SINGLE_POOL_ID},
+    events::{AccountEventHeader, LendingAccountDepositEvent, EVENT_SCHEMA_VERSION},
+    math_error,
+    prelude::*,
+    state::{
+        surroundfi_account::{BankAccountWrapper, SurroundfiAccount, ACCOUNT_DISABLED},
+        surroundfi_account_extension::find_account_extension,
+        surroundfi_group::Bank,
+    },
+    utils::{self, validate_asset_tags},
+};
+use anchor_lang::prelude::*;
+use anchor_spl::token::{Token, TokenAccount};
+use fixed::types::I80F48;
+use solana_program::{
+    clock::Clock,
+    instruction::{AccountMeta, Instruction},
+    program::invoke,
+    stake::{self, state::StakeAuthorize},
+    sysvar::{self, Sysvar},
+};
+
+/// spl-single-pool `SinglePoolInstruction::DepositStake`
+const SINGLE_POOL_DEPOSIT_STAKE_IX: u8 = 2;
+
+/// 1. Hand the stake and withdraw authorities of the user's stake account over to the single pool
+/// 2. Deposit the stake account into the single pool, which mints the LST into the bank's liquidity
+///    vault (any excess lamports go back to the signer)
+/// 3. Accrue interest
+/// 4. Create the user's bank account for the staked bank if it does not exist yet
+/// 5. Record the minted LST as an asset increase in the bank account
+///
+/// The stake account must be delegated to the pool's validator and in the same activation state as
+/// the pool's stake, see the spl-single-pool program for details. The whole stake account is
+/// deposited.
+pub fn lending_account_deposit_stake<'info>(
+    ctx: Context<'_, '_, 'info, 'info, LendingAccountDepositStake<'info>>,
+) -> SurroundfiResult {
+    let LendingAccountDepositStake {
+        surroundfi_account: surroundfi_account_loader,
+        authority: signer,
+        bank: bank_loader,
+        group: surroundfi_group_loader,
+        liquidity_vault: bank_liquidity_vault,
+        stake_account,
+        stake_pool,
+        sol_pool,
+        lst_mint,
+        pool_stake_authority,
+        pool_mint_authority,
+        clock: clock_sysvar,
+        stake_history,
+        token_program,
+        stake_program,
+        ..
+    } = ctx.accounts;
+    let clock = Clock::get()?;
+
+    let mut bank = bank_loader.load_mut()?;
+    let mut surroundfi_account = surroundfi_account_loader.load_mut()?;
+
+    check!(
+        !surroundfi_account.get_flag(ACCOUNT_DISABLED),
+        SurroundfiError::AccountDisabled
+    );
+
+    let extension_loader = find_account_extension(&surroundfi_account, ctx.remaining_accounts)?;
+    let mut extension = extension_loader
+        .as_ref()
+        .map(|loader| loader.load_mut())
+        .transpose()?;
+
+    validate_asset_tags(
+        &bank,
+        &surroundfi_account,
+        extension.as_deref(),
+        &surroundfi_group_loader.load()?.get_asset_tag_matrix(),
+    )?;
+
+    utils::validate_single_pool_accounts(
+        &bank,
+        stake_pool.key,
+        sol_pool.key,
+        pool_stake_authority.key,
+        pool_mint_authority.key,
+    )?;
+
+    for stake_authorize in [StakeAuthorize::Staker, StakeAuthorize::Withdrawer] {
+        invoke(
+            &stake::instruction::authorize(
+                stake_account.key,
+                signer.key,
+                pool_stake_authority.key,
+                stake_authorize,
+                None,
+            ),
+            &[
+                stake_account.to_account_info(),
+                clock_sysvar.to_account_info(),
+                signer.to_account_info(),
+            ],
+        )?;
+    }
+
+    let vault_amount_pre_deposit = bank_liquidity_vault.amount;
+
+    invoke(
+        &Instruction {
+            program_id: SPL_SINGLE_POOL_ID,
+            accounts: vec![
+                AccountMeta::new_readonly(stake_pool.key(), false),
+                AccountMeta::new(sol_pool.key(), false),
+                AccountMeta::new(lst_mint.key(), false),
+                AccountMeta::new_readonly(pool_stake_authority.key(), false),
+                AccountMeta::new_readonly(pool_mint_authority.key(), false),
+                AccountMeta::new(stake_account.key(), false),
+                AccountMeta::new(bank_liquidity_vault.key(), false),
+                AccountMeta::new(signer.key(), false),
+                AccountMeta::new_readonly(clock_sysvar.key(), false),
+                AccountMeta::new_readonly(stake_history.key(), false),
+                AccountMeta::new_readonly(token_program.key(), false),
+                AccountMeta::new_readonly(stake_program.key(), false),
+            ],
+            data: vec![SINGLE_POOL_DEPOSIT_STAKE_IX],
+        },
+        &[
+            stake_pool.to_account_info(),
+            sol_pool.to_account_info(),
+            lst_mint.to_account_info(),
+            pool_stake_authority.to_account_info(),
+            pool_mint_authority.to_account_info(),
+            stake_account.to_account_info(),
+            bank_liquidity_vault.to_account_info(),
+            signer.to_account_info(),
+            clock_sysvar.to_account_info(),
+            stake_history.to_account_info(),
+            token_program.to_account_info(),
+            stake_program.to_account_info(),
+        ],
+    )?;
+
+    bank_liquidity_vault.reload()?;
+    let deposit_amount = bank_liquidity_vault
+        .amount
+        .checked_sub(vault_amount_pre_deposit)
+        .ok_or_else(math_error!())?;
+
+    check!(deposit_amount > 0, SurroundfiError::InvalidTransfer);
+
+    bank.accrue_interest(
+        clock.unix_timestamp,
+        &*surroundfi_group_loader.load()?,
+        #[cfg(not(feature = "client"))]
+        bank_loader.key(),
+    )?;
+
+    let mut bank_account = BankAccountWrapper::find_or_create(
+        &bank_loader.key(),
+        &mut bank,
+        &mut surroundfi_account.lending_account,
+        extension.as_deref_mut(),
+    )?;
+    bank_account
+        .settle_emissions_streams(&surroundfi_account_loader.key(), ctx.remaining_accounts)?;
+
+    bank_account.deposit(I80F48::from_num(deposit_amount))?;
+
+    emit!(LendingAccountDepositEvent {
+        header: AccountEventHeader {
+            version: EVENT_SCHEMA_VERSION,
+            signer: Some(signer.key()),
+            surroundfi_account: surroundfi_account_loader.key(),
+            surroundfi_account_authority: surroundfi_account.authority,
+            surroundfi_group: surroundfi_account.group,
+        },
+        bank: bank_loader.key(),
+        mint: bank.mint,
+        amount: deposit_amount,
+        referrer: None,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct LendingAccountDepositStake<'info> {
+    pub group: AccountLoader<'info, SurroundfiGroup>,
+
+    #[account(
+        mut,
+        has_one = group,
+        has_one = authority
+    )]
+    pub surroundfi_account: AccountLoader<'info, SurroundfiAccount>,
+
+    /// Stake and withdraw authority of `stake_account`, also receives any lamports in excess of
+    /// the stake that the single pool does not take
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = group,
+        has_one = liquidity_vault
+    )]
+    pub bank: AccountLoader<'info, Bank>,
+
+    #[account(mut)]
+    pub liquidity_vault: Box<Account<'info, TokenAccount>>,
+
+    /// CHECK: Validated by the native stake program and the spl-single-pool program
+    #[account(mut)]
+    pub stake_account: AccountInfo<'info>,
+
+    /// CHECK: Validated against the bank's mint, see `utils::validate_single_pool_accounts`
+    pub stake_pool: AccountInfo<'info>,
+
+    /// CHECK: Validated using `stake_pool`
+    #[account(mut)]
+    pub sol_pool: AccountInfo<'info>,
+
+    /// CHECK: Must be the bank's mint (validated using `stake_pool`)
+    #[account(mut)]
+    pub lst_mint: AccountInfo<'info>,
+
+    /// CHECK: Validated using `stake_pool`
+    pub pool_stake_authority: AccountInfo<'info>,
+
+    /// CHECK: Validated using `stake_pool`
+    pub pool_mint_authority: AccountInfo<'info>,
+
+    /// CHECK: Sysvar address check
+    #[account(address = sysvar::clock::ID)]
+    pub clock: AccountInfo<'info>,
+
+    /// CHECK: Sysvar address check
+    #[account(address = sysvar::stake_history::ID)]
+    pub stake_history: AccountInfo<'info>,
+
+    /// Note: spl-single-pool uses a classic Token, never Token22
+    pub token_program: Program<'info, Token>,
+
+    /// CHECK: Program address check
+    #[account(address = NATIVE_STAKE_ID)]
+    pub stake_program: AccountInfo<'info>,
+
+    /// CHECK: Program address check
+    #[account(address = SPL_SINGLE_POOL_ID)]
+    pub single_pool_program: AccountInfo<'info>,
+}
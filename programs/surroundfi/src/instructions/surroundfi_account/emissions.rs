@@ -1,22 +1,43 @@
 use anchor_lang::{prelude::*, Accounts, ToAccountInfo};
 use anchor_spl::{
     associated_token::get_associated_token_address_with_program_id,
-    token_interface::{transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked},
+    token_interface::{Mint, TokenAccount, TokenInterface},
 };
 
 use crate::{
     check,
-    constants::{EMISSIONS_AUTH_SEED, EMISSIONS_TOKEN_ACCOUNT_SEED},
+    constants::{
+        EMISSIONS_ACCRUAL_SEED, EMISSIONS_AUTH_SEED, EMISSIONS_PROGRAM_SEED,
+        EMISSIONS_STREAM_VAULT_SEED, EMISSIONS_TOKEN_ACCOUNT_SEED,
+    },
     debug,
+    events::{
+        AccountEventHeader, LendingAccountInitEmissionsAccrualEvent,
+        LendingAccountSettleEmissionsEvent, LendingAccountSettleStreamEmissionsEvent,
+        LendingAccountWithdrawEmissionsEvent, LendingAccountWithdrawStreamEmissionsEvent,
+        SurroundfiAccountUpdateEmissionsDestinationAccountEvent, EVENT_SCHEMA_VERSION,
+    },
     prelude::{SurroundfiError, SurroundfiResult},
     state::{
+        emissions_program::{find_emissions_streams_accounts, EmissionsAccrual},
         surroundfi_account::{BankAccountWrapper, SurroundfiAccount, ACCOUNT_DISABLED},
+        surroundfi_account_extension::find_account_extension,
         surroundfi_group::{Bank, SurroundfiGroup},
     },
+    utils,
 };
-
+use fixed::types::I80F48;
+
+/// Settle and withdraw the bank's emissions, or, with `stream_id`, the rewards of one stream of the
+/// bank's emissions program. To claim all streams, send one of these per stream in the same
+/// transaction.
+///
+/// For a stream, the emissions program and the account's `EmissionsAccrual` for the bank are
+/// passed in the remaining accounts. The accrual outlives the balance, so rewards of a closed
+/// balance can still be withdrawn.
 pub fn lending_account_withdraw_emissions<'info>(
     ctx: Context<'_, '_, 'info, 'info, LendingAccountWithdrawEmissions<'info>>,
+    stream_id: Option<u8>,
 ) -> SurroundfiResult {
     let mut surroundfi_account = ctx.accounts.surroundfi_account.load_mut()?;
 
@@ -27,45 +48,146 @@ pub fn lending_account_withdraw_emissions<'info>(
 
     let mut bank = ctx.accounts.bank.load_mut()?;
 
-    let mut balance = BankAccountWrapper::find(
-        ctx.accounts.bank.to_account_info().key,
-        &mut bank,
-        &mut surroundfi_account.lending_account,
-    )?;
-
-    // Settle emissions
-    let emissions_settle_amount = balance.settle_emissions_and_get_transfer_amount()?;
+    let extension_loader = find_account_extension(&surroundfi_account, ctx.remaining_accounts)?;
+    let mut extension = extension_loader
+        .as_ref()
+        .map(|loader| loader.load_mut())
+        .transpose()?;
+
+    // For a stream, the emissions program is the vault authority
+    let streams_accounts = stream_id
+        .map(|_| {
+            find_emissions_streams_accounts(
+                &ctx.accounts.surroundfi_account.key(),
+                &ctx.accounts.bank.key(),
+                ctx.remaining_accounts,
+            )?
+            .ok_or_else(|| error!(SurroundfiError::EmissionsAccrualMissing))
+        })
+        .transpose()?;
+
+    let (emissions_settle_amount, authority_bump) = match (stream_id, &streams_accounts) {
+        (Some(stream_id), Some((emissions_program_loader, emissions_accrual_loader))) => {
+            let mut emissions_program = emissions_program_loader.load_mut()?;
+            let mut emissions_accrual = emissions_accrual_loader.load_mut()?;
+            check!(
+                emissions_program.get_stream_mut(stream_id)?.mint
+                    == ctx.accounts.emissions_mint.key(),
+                SurroundfiError::InvalidEmissionsStream
+            );
+
+            // A closed balance keeps what it accrued until then
+            let bank_pk = ctx.accounts.bank.key();
+            let has_balance = surroundfi_account
+                .lending_account
+                .balances_iter(extension.as_deref())
+                .any(|balance| balance.is_active() && balance.bank_pk == bank_pk);
+            if has_balance {
+                BankAccountWrapper::find(
+                    &bank_pk,
+                    &mut bank,
+                    &mut surroundfi_account.lending_account,
+                    extension.as_deref_mut(),
+                )?
+                .accrue_emissions_streams(
+                    &mut emissions_program,
+                    &mut emissions_accrual,
+                    Some(stream_id),
+                    Clock::get()?.unix_timestamp,
+                )?;
+            }
+
+            (
+                emissions_accrual.take_outstanding(stream_id)?,
+                emissions_program.bump,
+            )
+        }
+        _ => {
+            let mut balance = BankAccountWrapper::find(
+                ctx.accounts.bank.to_account_info().key,
+                &mut bank,
+                &mut surroundfi_account.lending_account,
+                extension.as_deref_mut(),
+            )?;
+
+            (
+                balance.settle_emissions_and_get_transfer_amount()?,
+                ctx.bumps.emissions_auth,
+            )
+        }
+    };
 
     if emissions_settle_amount > 0 {
         debug!("Transferring {} emissions to user", emissions_settle_amount);
 
-        let signer_seeds: &[&[&[u8]]] = &[&[
+        let bank_key = ctx.accounts.bank.key().to_bytes();
+        let emissions_mint_key = ctx.accounts.emissions_mint.key().to_bytes();
+        let authority_bump = [authority_bump];
+        let emissions_program_seeds: &[&[u8]] = &[
+            EMISSIONS_PROGRAM_SEED.as_bytes(),
+            &bank_key,
+            &authority_bump,
+        ];
+        let emissions_auth_seeds: &[&[u8]] = &[
             EMISSIONS_AUTH_SEED.as_bytes(),
-            &ctx.accounts.bank.key().to_bytes(),
-            &ctx.accounts.emissions_mint.key().to_bytes(),
-            &[ctx.bumps.emissions_auth],
-        ]];
-
-        transfer_checked(
-            CpiContext::new_with_signer(
-                ctx.accounts.token_program.to_account_info(),
-                TransferChecked {
-                    from: ctx.accounts.emissions_vault.to_account_info(),
-                    to: ctx.accounts.destination_account.to_account_info(),
-                    authority: ctx.accounts.emissions_auth.to_account_info(),
-                    mint: ctx.accounts.emissions_mint.to_account_info(),
-                },
-                signer_seeds,
+            &bank_key,
+            &emissions_mint_key,
+            &authority_bump,
+        ];
+        let (vault_authority, authority_seeds) = match &streams_accounts {
+            Some((emissions_program_loader, _)) => (
+                emissions_program_loader.to_account_info(),
+                emissions_program_seeds,
             ),
+            None => (
+                ctx.accounts.emissions_auth.to_account_info(),
+                emissions_auth_seeds,
+            ),
+        };
+        let signer_seeds: &[&[&[u8]]] = &[authority_seeds];
+
+        utils::transfer_checked_with_hook(
             emissions_settle_amount,
-            ctx.accounts.emissions_mint.decimals,
+            ctx.accounts.emissions_vault.to_account_info(),
+            ctx.accounts.destination_account.to_account_info(),
+            vault_authority,
+            &ctx.accounts.emissions_mint,
+            ctx.accounts.token_program.to_account_info(),
+            signer_seeds,
+            ctx.remaining_accounts,
         )?;
     }
 
+    let header = AccountEventHeader {
+        version: EVENT_SCHEMA_VERSION,
+        signer: Some(ctx.accounts.authority.key()),
+        surroundfi_account: ctx.accounts.surroundfi_account.key(),
+        surroundfi_account_authority: surroundfi_account.authority,
+        surroundfi_group: surroundfi_account.group,
+    };
+    match stream_id {
+        None => emit!(LendingAccountWithdrawEmissionsEvent {
+            header,
+            bank: ctx.accounts.bank.key(),
+            emissions_mint: ctx.accounts.emissions_mint.key(),
+            destination_account: ctx.accounts.destination_account.key(),
+            amount: emissions_settle_amount,
+        }),
+        Some(stream_id) => emit!(LendingAccountWithdrawStreamEmissionsEvent {
+            header,
+            bank: ctx.accounts.bank.key(),
+            stream_id,
+            emissions_mint: ctx.accounts.emissions_mint.key(),
+            destination_account: ctx.accounts.destination_account.key(),
+            amount: emissions_settle_amount,
+        }),
+    }
+
     Ok(())
 }
 
 #[derive(Accounts)]
+#[instruction(stream_id: Option<u8>)]
 pub struct LendingAccountWithdrawEmissions<'info> {
     pub group: AccountLoader<'info, SurroundfiGroup>,
 
@@ -80,14 +202,18 @@ pub struct LendingAccountWithdrawEmissions<'info> {
 
     #[account(
         mut,
-        has_one = group,
-        has_one = emissions_mint
+        has_one = group
     )]
     pub bank: AccountLoader<'info, Bank>,
 
+    /// The bank's `emissions_mint`, or, for a stream, the stream's mint (checked by the handler)
+    #[account(
+        constraint = stream_id.is_some() || bank.load()?.emissions_mint == emissions_mint.key()
+    )]
     pub emissions_mint: InterfaceAccount<'info, Mint>,
 
-    /// CHECK: PDA seeds validated
+    /// CHECK: PDA seeds validated. Unused for a stream, whose vault authority is the emissions
+    /// program.
     #[account(
         seeds = [
             EMISSIONS_AUTH_SEED.as_bytes(),
@@ -98,10 +224,14 @@ pub struct LendingAccountWithdrawEmissions<'info> {
     )]
     pub emissions_auth: AccountInfo<'info>,
 
+    /// The bank's emissions vault, or, for a stream, the stream vault
     #[account(
         mut,
         seeds = [
-            EMISSIONS_TOKEN_ACCOUNT_SEED.as_bytes(),
+            match stream_id {
+                Some(_) => EMISSIONS_STREAM_VAULT_SEED.as_bytes(),
+                None => EMISSIONS_TOKEN_ACCOUNT_SEED.as_bytes(),
+            },
             bank.key().as_ref(),
             emissions_mint.key().as_ref(),
         ],
@@ -115,19 +245,89 @@ pub struct LendingAccountWithdrawEmissions<'info> {
 }
 
 /// Permissionlessly settle unclaimed emissions to a users account.
-pub fn lending_account_settle_emissions(
-    ctx: Context<LendingAccountSettleEmissions>,
+///
+/// Without `stream_id`, settles the bank's emissions, and, if the emissions program and the
+/// account's `EmissionsAccrual` for the bank are passed in the remaining accounts, every stream of
+/// the bank's emissions program. With `stream_id`, settles only that stream, and these accounts are
+/// required. The first stream settlement enrolls the balance in the streams.
+pub fn lending_account_settle_emissions<'info>(
+    ctx: Context<'_, '_, 'info, 'info, LendingAccountSettleEmissions<'info>>,
+    stream_id: Option<u8>,
 ) -> SurroundfiResult {
     let mut surroundfi_account = ctx.accounts.surroundfi_account.load_mut()?;
     let mut bank = ctx.accounts.bank.load_mut()?;
 
+    let extension_loader = find_account_extension(&surroundfi_account, ctx.remaining_accounts)?;
+    let mut extension = extension_loader
+        .as_ref()
+        .map(|loader| loader.load_mut())
+        .transpose()?;
+
+    let streams_accounts = find_emissions_streams_accounts(
+        &ctx.accounts.surroundfi_account.key(),
+        &ctx.accounts.bank.key(),
+        ctx.remaining_accounts,
+    )?;
+    check!(
+        stream_id.is_none() || streams_accounts.is_some(),
+        SurroundfiError::EmissionsAccrualMissing
+    );
+
     let mut balance = BankAccountWrapper::find(
         ctx.accounts.bank.to_account_info().key,
         &mut bank,
         &mut surroundfi_account.lending_account,
+        extension.as_deref_mut(),
     )?;
 
-    balance.claim_emissions(Clock::get()?.unix_timestamp.try_into().unwrap())?;
+    let current_timestamp = Clock::get()?.unix_timestamp;
+
+    if stream_id.is_none() {
+        balance.claim_emissions(current_timestamp.try_into().unwrap())?;
+    }
+    if let Some((emissions_program, emissions_accrual)) = &streams_accounts {
+        let mut emissions_program = emissions_program.load_mut()?;
+        if let Some(stream_id) = stream_id {
+            emissions_program.get_stream_mut(stream_id)?;
+        }
+
+        balance.accrue_emissions_streams(
+            &mut emissions_program,
+            &mut emissions_accrual.load_mut()?,
+            stream_id,
+            current_timestamp,
+        )?;
+    }
+
+    let emissions_outstanding = I80F48::from(balance.balance.emissions_outstanding);
+
+    // Permissionless, so there is no signer to record
+    if stream_id.is_none() {
+        emit!(LendingAccountSettleEmissionsEvent {
+            header: AccountEventHeader {
+                version: EVENT_SCHEMA_VERSION,
+                signer: None,
+                surroundfi_account: ctx.accounts.surroundfi_account.key(),
+                surroundfi_account_authority: surroundfi_account.authority,
+                surroundfi_group: surroundfi_account.group,
+            },
+            bank: ctx.accounts.bank.key(),
+            emissions_outstanding: emissions_outstanding.into(),
+        });
+    }
+    if streams_accounts.is_some() {
+        emit!(LendingAccountSettleStreamEmissionsEvent {
+            header: AccountEventHeader {
+                version: EVENT_SCHEMA_VERSION,
+                signer: None,
+                surroundfi_account: ctx.accounts.surroundfi_account.key(),
+                surroundfi_account_authority: surroundfi_account.authority,
+                surroundfi_group: surroundfi_account.group,
+            },
+            bank: ctx.accounts.bank.key(),
+            stream_id,
+        });
+    }
 
     Ok(())
 }
@@ -157,6 +357,17 @@ pub fn surroundfi_account_update_emissions_destination_account<'info>(
 
     surroundfi_account.emissions_destination_account = ctx.accounts.destination_account.key();
 
+    emit!(SurroundfiAccountUpdateEmissionsDestinationAccountEvent {
+        header: AccountEventHeader {
+            version: EVENT_SCHEMA_VERSION,
+            signer: Some(ctx.accounts.authority.key()),
+            surroundfi_account: ctx.accounts.surroundfi_account.key(),
+            surroundfi_account_authority: surroundfi_account.authority,
+            surroundfi_group: surroundfi_account.group,
+        },
+        emissions_destination_account: ctx.accounts.destination_account.key(),
+    });
+
     Ok(())
 }
 
@@ -210,10 +421,17 @@ pub fn lending_account_withdraw_emissions_permissionless<'info>(
 
     let mut bank = ctx.accounts.bank.load_mut()?;
 
+    let extension_loader = find_account_extension(&surroundfi_account, ctx.remaining_accounts)?;
+    let mut extension = extension_loader
+        .as_ref()
+        .map(|loader| loader.load_mut())
+        .transpose()?;
+
     let mut bank_account = BankAccountWrapper::find(
         ctx.accounts.bank.to_account_info().key,
         &mut bank,
         &mut surroundfi_account.lending_account,
+        extension.as_deref_mut(),
     )?;
 
     // Settle emissions
@@ -229,22 +447,33 @@ pub fn lending_account_withdraw_emissions_permissionless<'info>(
             &[ctx.bumps.emissions_auth],
         ]];
 
-        transfer_checked(
-            CpiContext::new_with_signer(
-                ctx.accounts.token_program.to_account_info(),
-                TransferChecked {
-                    from: ctx.accounts.emissions_vault.to_account_info(),
-                    to: ctx.accounts.destination_account.to_account_info(),
-                    authority: ctx.accounts.emissions_auth.to_account_info(),
-                    mint: ctx.accounts.emissions_mint.to_account_info(),
-                },
-                signer_seeds,
-            ),
+        utils::transfer_checked_with_hook(
             emissions_settle_amount,
-            ctx.accounts.emissions_mint.decimals,
+            ctx.accounts.emissions_vault.to_account_info(),
+            ctx.accounts.destination_account.to_account_info(),
+            ctx.accounts.emissions_auth.to_account_info(),
+            &ctx.accounts.emissions_mint,
+            ctx.accounts.token_program.to_account_info(),
+            signer_seeds,
+            ctx.remaining_accounts,
         )?;
     }
 
+    // Permissionless, so there is no signer to record
+    emit!(LendingAccountWithdrawEmissionsEvent {
+        header: AccountEventHeader {
+            version: EVENT_SCHEMA_VERSION,
+            signer: None,
+            surroundfi_account: ctx.accounts.surroundfi_account.key(),
+            surroundfi_account_authority: surroundfi_account.authority,
+            surroundfi_group: surroundfi_account.group,
+        },
+        bank: ctx.accounts.bank.key(),
+        emissions_mint: ctx.accounts.emissions_mint.key(),
+        destination_account: ctx.accounts.destination_account.key(),
+        amount: emissions_settle_amount,
+    });
+
     Ok(())
 }
 
@@ -296,3 +525,61 @@ pub struct LendingAccountWithdrawEmissionsPermissionless<'info> {
 
     pub token_program: Interface<'info, TokenInterface>,
 }
+
+/// Create the account that tracks emissions stream rewards of the account's balance in a bank.
+/// Streams accrue to the balance from their first settlement after this.
+pub fn lending_account_init_emissions_accrual(
+    ctx: Context<LendingAccountInitEmissionsAccrual>,
+) -> SurroundfiResult {
+    let mut emissions_accrual = ctx.accounts.emissions_accrual.load_init()?;
+    emissions_accrual.initialize(
+        ctx.accounts.surroundfi_account.key(),
+        ctx.accounts.bank.key(),
+    );
+
+    let surroundfi_account = ctx.accounts.surroundfi_account.load()?;
+    emit!(LendingAccountInitEmissionsAccrualEvent {
+        header: AccountEventHeader {
+            version: EVENT_SCHEMA_VERSION,
+            signer: Some(ctx.accounts.authority.key()),
+            surroundfi_account: ctx.accounts.surroundfi_account.key(),
+            surroundfi_account_authority: surroundfi_account.authority,
+            surroundfi_group: surroundfi_account.group,
+        },
+        bank: ctx.accounts.bank.key(),
+        emissions_accrual: ctx.accounts.emissions_accrual.key(),
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct LendingAccountInitEmissionsAccrual<'info> {
+    #[account(
+        has_one = authority,
+        constraint = surroundfi_account.load()?.group == bank.load()?.group,
+    )]
+    pub surroundfi_account: AccountLoader<'info, SurroundfiAccount>,
+
+    pub authority: Signer<'info>,
+
+    pub bank: AccountLoader<'info, Bank>,
+
+    #[account(
+        init,
+        seeds = [
+            EMISSIONS_ACCRUAL_SEED.as_bytes(),
+            surroundfi_account.key().as_ref(),
+            bank.key().as_ref(),
+        ],
+        bump,
+        payer = fee_payer,
+        space = 8 + EmissionsAccrual::LEN
+    )]
+    pub emissions_accrual: AccountLoader<'info, EmissionsAccrual>,
+
+    #[account(mut)]
+    pub fee_payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
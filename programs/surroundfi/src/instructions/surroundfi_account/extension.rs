@@ -0,0 +1,129 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    check,
+    constants::SURROUNDFI_ACCOUNT_EXTENSION_SEED,
+    events::{
+        AccountEventHeader, SurroundfiAccountCloseExtensionEvent,
+        SurroundfiAccountInitExtensionEvent, EVENT_SCHEMA_VERSION,
+    },
+    prelude::*,
+    state::{
+        surroundfi_account::{SurroundfiAccount, ACCOUNT_DISABLED},
+        surroundfi_account_extension::SurroundfiAccountExtension,
+    },
+};
+
+/// Create an extension for the account, holding `MAX_EXTENSION_BALANCES` additional balances.
+/// Once linked, the extension must be passed in remaining accounts of any instruction that
+/// touches balances or checks health.
+pub fn initialize_extension(ctx: Context<SurroundfiAccountInitExtension>) -> SurroundfiResult {
+    let mut surroundfi_account = ctx.accounts.surroundfi_account.load_mut()?;
+
+    check!(
+        !surroundfi_account.get_flag(ACCOUNT_DISABLED),
+        SurroundfiError::AccountDisabled
+    );
+    check!(
+        !surroundfi_account.has_extension(),
+        SurroundfiError::IllegalAction,
+        "Account already has an extension"
+    );
+
+    let mut extension = ctx.accounts.extension.load_init()?;
+    extension.initialize(ctx.accounts.surroundfi_account.key());
+
+    surroundfi_account.extension = ctx.accounts.extension.key();
+
+    emit!(SurroundfiAccountInitExtensionEvent {
+        header: AccountEventHeader {
+            version: EVENT_SCHEMA_VERSION,
+            signer: Some(ctx.accounts.authority.key()),
+            surroundfi_account: ctx.accounts.surroundfi_account.key(),
+            surroundfi_account_authority: surroundfi_account.authority,
+            surroundfi_group: surroundfi_account.group,
+        },
+        extension: ctx.accounts.extension.key(),
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct SurroundfiAccountInitExtension<'info> {
+    #[account(
+        mut,
+        has_one = authority
+    )]
+    pub surroundfi_account: AccountLoader<'info, SurroundfiAccount>,
+
+    #[account(
+        init,
+        seeds = [
+            SURROUNDFI_ACCOUNT_EXTENSION_SEED.as_bytes(),
+            surroundfi_account.key().as_ref()
+        ],
+        bump,
+        payer = fee_payer,
+        space = 8 + SurroundfiAccountExtension::LEN
+    )]
+    pub extension: AccountLoader<'info, SurroundfiAccountExtension>,
+
+    pub authority: Signer<'info>,
+
+    #[account(mut)]
+    pub fee_payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Close an extension with no remaining positions and unlink it from the account.
+pub fn close_extension(ctx: Context<SurroundfiAccountCloseExtension>) -> SurroundfiResult {
+    let mut surroundfi_account = ctx.accounts.surroundfi_account.load_mut()?;
+    let extension = ctx.accounts.extension.load()?;
+
+    check!(
+        extension
+            .balances
+            .iter()
+            .all(|balance| !balance.is_active()),
+        SurroundfiError::IllegalAction,
+        "Extension has active balances"
+    );
+
+    surroundfi_account.extension = Pubkey::default();
+
+    emit!(SurroundfiAccountCloseExtensionEvent {
+        header: AccountEventHeader {
+            version: EVENT_SCHEMA_VERSION,
+            signer: Some(ctx.accounts.authority.key()),
+            surroundfi_account: ctx.accounts.surroundfi_account.key(),
+            surroundfi_account_authority: surroundfi_account.authority,
+            surroundfi_group: surroundfi_account.group,
+        },
+        extension: ctx.accounts.extension.key(),
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct SurroundfiAccountCloseExtension<'info> {
+    #[account(
+        mut,
+        has_one = authority,
+        has_one = extension
+    )]
+    pub surroundfi_account: AccountLoader<'info, SurroundfiAccount>,
+
+    #[account(
+        mut,
+        close = fee_payer
+    )]
+    pub extension: AccountLoader<'info, SurroundfiAccountExtension>,
+
+    pub authority: Signer<'info>,
+
+    #[account(mut)]
+    pub fee_payer: Signer<'info>,
+}
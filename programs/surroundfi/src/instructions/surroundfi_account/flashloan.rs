@@ -1,13 +1,28 @@
 use crate::{
-    check,
+    bank_signer, check,
+    constants::LIQUIDITY_VAULT_AUTHORITY_SEED,
+    events::{
+        AccountEventHeader, LendingAccountCallbackFlashloanEvent, LendingAccountEndFlashloanEvent,
+        LendingAccountStartFlashloanEvent, EVENT_SCHEMA_VERSION,
+    },
+    math_error,
     prelude::*,
-    state::surroundfi_account::{
-        SurroundfiAccount, RiskEngine, ACCOUNT_DISABLED, ACCOUNT_IN_FLASHLOAN,
+    state::{
+        surroundfi_account::{
+            BankAccountWrapper, SurroundfiAccount, RiskEngine, ACCOUNT_DISABLED,
+            ACCOUNT_FLASHLOAN_LEVERAGE_CAPPED, ACCOUNT_IN_FLASHLOAN,
+        },
+        surroundfi_account_extension::find_account_extension,
+        surroundfi_group::{Bank, BankVaultType},
     },
+    utils::{self, validate_asset_tags},
 };
 use anchor_lang::{prelude::*, Discriminator};
+use anchor_spl::token_interface::{TokenAccount, TokenInterface};
+use fixed::types::I80F48;
 use solana_program::{
-    instruction::{get_stack_height, TRANSACTION_LEVEL_STACK_HEIGHT},
+    instruction::{get_stack_height, AccountMeta, Instruction, TRANSACTION_LEVEL_STACK_HEIGHT},
+    program::invoke,
     sysvar::{self, instructions},
 };
 
@@ -23,6 +38,18 @@ pub fn lending_account_start_flashloan(
 
     let mut surroundfi_account = ctx.accounts.surroundfi_account.load_mut()?;
     surroundfi_account.set_flag(ACCOUNT_IN_FLASHLOAN);
+    surroundfi_account.lending_account.clear_flashloan_volumes();
+
+    emit!(LendingAccountStartFlashloanEvent {
+        header: AccountEventHeader {
+            version: EVENT_SCHEMA_VERSION,
+            signer: Some(ctx.accounts.authority.key()),
+            surroundfi_account: ctx.accounts.surroundfi_account.key(),
+            surroundfi_account_authority: surroundfi_account.authority,
+            surroundfi_group: surroundfi_account.group,
+        },
+        end_index,
+    });
 
     Ok(())
 }
@@ -130,13 +157,54 @@ pub fn lending_account_end_flashloan<'info>(
 
     let mut surroundfi_account = ctx.accounts.surroundfi_account.load_mut()?;
 
+    let leverage_capped = surroundfi_account.get_flag(ACCOUNT_FLASHLOAN_LEVERAGE_CAPPED);
     surroundfi_account.unset_flag(ACCOUNT_IN_FLASHLOAN);
+    surroundfi_account.unset_flag(ACCOUNT_FLASHLOAN_LEVERAGE_CAPPED);
+    // Liabilities still open are no longer flashloaned, they won't be charged the flashloan fee
+    surroundfi_account.lending_account.clear_flashloan_volumes();
 
-    RiskEngine::check_account_init_health(&surroundfi_account, ctx.remaining_accounts, &mut None)?;
+    let risk_engine = RiskEngine::check_account_init_health(
+        &surroundfi_account,
+        ctx.remaining_accounts,
+        &mut None,
+    )?;
+    // Note: borrows and withdraws within the flashloan skip the arena's leverage cap, so it is
+    // enforced here, with the group passed in remaining accounts
+    if leverage_capped {
+        let group_loader = find_account_group(&surroundfi_account, ctx.remaining_accounts)?;
+        let max_leverage = group_loader.load()?.get_max_leverage();
+        if let (Some(risk_engine), Some(max_leverage)) = (risk_engine, max_leverage) {
+            risk_engine.check_account_leverage(max_leverage)?;
+        }
+    }
+
+    emit!(LendingAccountEndFlashloanEvent {
+        header: AccountEventHeader {
+            version: EVENT_SCHEMA_VERSION,
+            signer: Some(ctx.accounts.authority.key()),
+            surroundfi_account: ctx.accounts.surroundfi_account.key(),
+            surroundfi_account_authority: surroundfi_account.authority,
+            surroundfi_group: surroundfi_account.group,
+        },
+    });
 
     Ok(())
 }
 
+/// The account's group, which remaining accounts must contain when the end of a flashloan checks
+/// the arena's leverage cap, see `ACCOUNT_FLASHLOAN_LEVERAGE_CAPPED`
+fn find_account_group<'info>(
+    surroundfi_account: &SurroundfiAccount,
+    remaining_ais: &'info [AccountInfo<'info>],
+) -> SurroundfiResult<AccountLoader<'info, SurroundfiGroup>> {
+    let group_ai = remaining_ais
+        .iter()
+        .find(|ai| ai.key.eq(&surroundfi_account.group))
+        .ok_or_else(|| error!(SurroundfiError::GroupMissing))?;
+
+    Ok(AccountLoader::try_from(group_ai)?)
+}
+
 #[derive(Accounts)]
 pub struct LendingAccountEndFlashloan<'info> {
     #[account(
@@ -147,3 +215,338 @@ pub struct LendingAccountEndFlashloan<'info> {
 
     pub authority: Signer<'info>,
 }
+
+/// Flashloan in a single instruction, for programs that can't wrap their instructions with
+/// `lending_account_start_flashloan` / `lending_account_end_flashloan`, e.g. because they hold the
+/// surroundfi account under a PDA and must CPI.
+///
+/// 1. Borrow `amount` into `flashloan_token_account`. Existing assets in the bank are withdrawn
+///    first, as with `lending_account_borrow`. The liability includes the bank's origination fee.
+/// 2. Invoke `callback_program` with `callback_data` and the first `callback_accounts_len`
+///    remaining accounts (after the mint, for Token-2022). Signer and writable privileges are
+///    passed as received, the program never signs for the callback.
+/// 3. Transfer the borrowed amount plus the origination and flashloan fees back from
+///    `flashloan_token_account` (the authority must still be able to move its funds) and restore
+///    the balance. The flashloan fee is due on the liability paid off, as within
+///    `lending_account_start_flashloan` / `lending_account_end_flashloan`.
+/// 4. Check account health against the rest of the remaining accounts.
+///
+/// The callback can't call back into this program: the runtime rejects reentrant CPIs. The
+/// account is in a flashloan for the duration of the callback all the same, it cannot be
+/// liquidated nor enter another flashloan.
+pub fn lending_account_callback_flashloan<'info>(
+    mut ctx: Context<'_, '_, 'info, 'info, LendingAccountCallbackFlashloan<'info>>,
+    amount: u64,
+    callback_accounts_len: u8,
+    callback_data: Vec<u8>,
+) -> SurroundfiResult {
+    let LendingAccountCallbackFlashloan {
+        surroundfi_account: surroundfi_account_loader,
+        authority,
+        bank: bank_loader,
+        flashloan_token_account,
+        liquidity_vault: bank_liquidity_vault,
+        bank_liquidity_vault_authority,
+        callback_program,
+        token_program,
+        group: surroundfi_group_loader,
+    } = ctx.accounts;
+    let clock = Clock::get()?;
+    let maybe_bank_mint = utils::maybe_take_bank_mint(
+        &mut ctx.remaining_accounts,
+        &*bank_loader.load()?,
+        token_program.key,
+    )?;
+    let program_fee_rate: I80F48 = surroundfi_group_loader
+        .load()?
+        .fee_state_cache
+        .program_fee_rate
+        .into();
+
+    check!(
+        callback_program.key() != crate::id(),
+        SurroundfiError::IllegalFlashloan,
+        "Callback can't be this program"
+    );
+
+    check!(
+        ctx.remaining_accounts.len() >= callback_accounts_len as usize,
+        SurroundfiError::IllegalFlashloan,
+        "Missing callback accounts"
+    );
+    let (callback_ais, risk_engine_ais) = ctx
+        .remaining_accounts
+        .split_at(callback_accounts_len as usize);
+
+    // 1. Borrow
+    let amount_pre_fee = maybe_bank_mint
+        .as_ref()
+        .map(|mint| {
+            utils::calculate_pre_fee_spl_deposit_amount(mint.to_account_info(), amount, clock.epoch)
+        })
+        .transpose()?
+        .unwrap_or(amount);
+
+    let origination_fee = {
+        let mut surroundfi_account = surroundfi_account_loader.load_mut()?;
+
+        check!(
+            !surroundfi_account.get_flag(ACCOUNT_DISABLED),
+            SurroundfiError::AccountDisabled
+        );
+        check!(
+            !surroundfi_account.get_flag(ACCOUNT_IN_FLASHLOAN),
+            SurroundfiError::IllegalFlashloan,
+            "Account is already in a flashloan"
+        );
+
+        surroundfi_account.set_flag(ACCOUNT_IN_FLASHLOAN);
+        surroundfi_account.lending_account.clear_flashloan_volumes();
+
+        let mut bank = bank_loader.load_mut()?;
+        bank.accrue_interest(
+            clock.unix_timestamp,
+            &*surroundfi_group_loader.load()?,
+            #[cfg(not(feature = "client"))]
+            bank_loader.key(),
+        )?;
+
+        let liquidity_vault_authority_bump = bank.liquidity_vault_authority_bump;
+        let origination_fee_rate: I80F48 = bank
+            .config
+            .interest_rate_config
+            .protocol_origination_fee
+            .into();
+
+        let extension_loader = find_account_extension(&surroundfi_account, risk_engine_ais)?;
+        let mut extension = extension_loader
+            .as_ref()
+            .map(|loader| loader.load_mut())
+            .transpose()?;
+
+        validate_asset_tags(
+            &bank,
+            &surroundfi_account,
+            extension.as_deref(),
+            &surroundfi_group_loader.load()?.get_asset_tag_matrix(),
+        )?;
+
+        let mut bank_account = BankAccountWrapper::find_or_create(
+            &bank_loader.key(),
+            &mut bank,
+            &mut surroundfi_account.lending_account,
+            extension.as_deref_mut(),
+        )?;
+        bank_account
+            .settle_emissions_streams(&surroundfi_account_loader.key(), ctx.remaining_accounts)?;
+
+        let origination_fee: u64 = I80F48::from_num(amount_pre_fee)
+            .checked_mul(origination_fee_rate)
+            .ok_or_else(math_error!())?
+            .checked_to_num()
+            .ok_or_else(math_error!())?;
+
+        bank_account.borrow(I80F48::from_num(amount_pre_fee + origination_fee))?;
+        let has_flashloan_fee = bank_account.bank.has_flashloan_fee();
+        bank_account.withdraw_spl_transfer(
+            amount_pre_fee,
+            bank_liquidity_vault.to_account_info(),
+            flashloan_token_account.to_account_info(),
+            bank_liquidity_vault_authority.to_account_info(),
+            maybe_bank_mint.as_ref(),
+            token_program.to_account_info(),
+            bank_signer!(
+                BankVaultType::Liquidity,
+                bank_loader.key(),
+                liquidity_vault_authority_bump
+            ),
+            ctx.remaining_accounts,
+        )?;
+
+        // Charged on the liability paid off in step 3
+        if has_flashloan_fee {
+            let balance_index = surroundfi_account
+                .lending_account
+                .get_balance_index(&bank_loader.key(), extension.as_deref())
+                .ok_or_else(|| error!(SurroundfiError::InternalLogicError))?;
+            surroundfi_account
+                .lending_account
+                .record_flashloan_borrow(balance_index, amount_pre_fee + origination_fee)?;
+        }
+
+        origination_fee
+    }; // release the accounts before invoking the callback
+
+    // The program and/or group fee account gains the origination fee
+    bank_loader.load_mut()?.collect_origination_fee(
+        I80F48::from_num(origination_fee),
+        program_fee_rate,
+        false,
+    )?;
+
+    // 2. Callback
+    {
+        let callback_ix = Instruction {
+            program_id: callback_program.key(),
+            accounts: callback_ais
+                .iter()
+                .map(|ai| AccountMeta {
+                    pubkey: *ai.key,
+                    is_signer: ai.is_signer,
+                    is_writable: ai.is_writable,
+                })
+                .collect(),
+            data: callback_data,
+        };
+
+        let mut callback_infos = callback_ais.to_vec();
+        callback_infos.push(callback_program.to_account_info());
+
+        invoke(&callback_ix, &callback_infos)?;
+    }
+
+    // 3. Repay
+    let mut surroundfi_account = surroundfi_account_loader.load_mut()?;
+    {
+        let mut bank = bank_loader.load_mut()?;
+
+        let extension_loader = find_account_extension(&surroundfi_account, risk_engine_ais)?;
+        let mut extension = extension_loader
+            .as_ref()
+            .map(|loader| loader.load_mut())
+            .transpose()?;
+
+        let flashloan_volume_closed = surroundfi_account
+            .lending_account
+            .record_flashloan_balance_increase(
+                &bank_loader.key(),
+                &bank,
+                extension.as_deref(),
+                amount_pre_fee + origination_fee,
+            )?;
+
+        let mut bank_account = BankAccountWrapper::find_or_create(
+            &bank_loader.key(),
+            &mut bank,
+            &mut surroundfi_account.lending_account,
+            extension.as_deref_mut(),
+        )?;
+        bank_account
+            .settle_emissions_streams(&surroundfi_account_loader.key(), ctx.remaining_accounts)?;
+
+        bank_account.increase_balance(I80F48::from_num(amount_pre_fee + origination_fee))?;
+
+        let flashloan_fee: u64 = if flashloan_volume_closed > 0 {
+            bank_account
+                .bank
+                .credit_flashloan_fee(I80F48::from_num(flashloan_volume_closed))?
+                .checked_ceil()
+                .ok_or_else(math_error!())?
+                .checked_to_num()
+                .ok_or_else(math_error!())?
+        } else {
+            0
+        };
+
+        let fee = origination_fee
+            .checked_add(flashloan_fee)
+            .ok_or_else(math_error!())?;
+        let repay_amount_post_fee = amount_pre_fee.checked_add(fee).ok_or_else(math_error!())?;
+        let repay_amount_pre_fee = maybe_bank_mint
+            .as_ref()
+            .map(|mint| {
+                utils::calculate_pre_fee_spl_deposit_amount(
+                    mint.to_account_info(),
+                    repay_amount_post_fee,
+                    clock.epoch,
+                )
+            })
+            .transpose()?
+            .unwrap_or(repay_amount_post_fee);
+
+        bank_account.deposit_spl_transfer(
+            repay_amount_pre_fee,
+            flashloan_token_account.to_account_info(),
+            bank_liquidity_vault.to_account_info(),
+            authority.to_account_info(),
+            maybe_bank_mint.as_ref(),
+            token_program.to_account_info(),
+            ctx.remaining_accounts,
+        )?;
+
+        emit!(LendingAccountCallbackFlashloanEvent {
+            header: AccountEventHeader {
+                version: EVENT_SCHEMA_VERSION,
+                signer: Some(authority.key()),
+                surroundfi_account: surroundfi_account_loader.key(),
+                surroundfi_account_authority: surroundfi_account.authority,
+                surroundfi_group: surroundfi_account.group,
+            },
+            bank: bank_loader.key(),
+            mint: bank.mint,
+            amount: amount_pre_fee,
+            fee,
+            callback_program: callback_program.key(),
+        });
+    }
+
+    // 4. Health
+    surroundfi_account.unset_flag(ACCOUNT_IN_FLASHLOAN);
+    surroundfi_account.unset_flag(ACCOUNT_FLASHLOAN_LEVERAGE_CAPPED);
+    surroundfi_account.lending_account.clear_flashloan_volumes();
+
+    let risk_engine =
+        RiskEngine::check_account_init_health(&surroundfi_account, risk_engine_ais, &mut None)?;
+    // Note: borrows within the callback skip the arena's leverage cap, so it is enforced here
+    let max_leverage = surroundfi_group_loader.load()?.get_max_leverage();
+    if let (Some(risk_engine), Some(max_leverage)) = (risk_engine, max_leverage) {
+        risk_engine.check_account_leverage(max_leverage)?;
+    }
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct LendingAccountCallbackFlashloan<'info> {
+    pub group: AccountLoader<'info, SurroundfiGroup>,
+
+    #[account(
+        mut,
+        has_one = group,
+        has_one = authority
+    )]
+    pub surroundfi_account: AccountLoader<'info, SurroundfiAccount>,
+
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = group,
+        has_one = liquidity_vault
+    )]
+    pub bank: AccountLoader<'info, Bank>,
+
+    /// CHECK: Receives the loan and repays it, token mint/authority are checked at transfer
+    #[account(mut)]
+    pub flashloan_token_account: AccountInfo<'info>,
+
+    /// CHECK: Seed constraint check
+    #[account(
+        mut,
+        seeds = [
+            LIQUIDITY_VAULT_AUTHORITY_SEED.as_bytes(),
+            bank.key().as_ref(),
+        ],
+        bump = bank.load()?.liquidity_vault_authority_bump,
+    )]
+    pub bank_liquidity_vault_authority: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub liquidity_vault: InterfaceAccount<'info, TokenAccount>,
+
+    /// CHECK: Any executable program other than this one
+    #[account(executable)]
+    pub callback_program: AccountInfo<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
@@ -1,5 +1,5 @@
 use crate::{
-    events::{AccountEventHeader, SurroundfiAccountCreateEvent},
+    events::{AccountEventHeader, SurroundfiAccountCreateEvent, EVENT_SCHEMA_VERSION},
     prelude::*,
     state::surroundfi_account::SurroundfiAccount,
 };
@@ -20,6 +20,7 @@ pub fn initialize_account(ctx: Context<SurroundfiAccountInitialize>) -> Surround
 
     emit!(SurroundfiAccountCreateEvent {
         header: AccountEventHeader {
+            version: EVENT_SCHEMA_VERSION,
             signer: Some(authority.key()),
             surroundfi_account: surroundfi_account_loader.key(),
             surroundfi_account_authority: surroundfi_account.authority,
@@ -0,0 +1,89 @@
+use crate::{
+    check,
+    constants::SURROUNDFI_ACCOUNT_SEED,
+    events::{AccountEventHeader, SurroundfiAccountCreateEvent, EVENT_SCHEMA_VERSION},
+    prelude::*,
+    state::surroundfi_account::SurroundfiAccount,
+};
+use anchor_lang::prelude::*;
+
+/// Initialize a surroundfi account at a PDA derived from (group, authority, account_index,
+/// third_party_id). Unlike `initialize_account`, the account address does not sign, so programs
+/// can create accounts owned by their own PDAs via CPI, and clients can find a user's accounts
+/// by derivation alone.
+///
+/// The derivation uses the authority at creation, which the account keeps as `creator`. After a
+/// `transfer_authority` the new authority can't derive the address from its own key: it must be
+/// derived from `creator` instead (or the account found by its `authority`, e.g. with a
+/// `getProgramAccounts` filter).
+///
+/// `None` derives with a third-party id of 0, so `Some(0)` is rejected to keep the two from
+/// mapping to the same address.
+pub fn initialize_account_pda(
+    ctx: Context<SurroundfiAccountInitializePda>,
+    account_index: u16,
+    third_party_id: Option<u16>,
+) -> SurroundfiResult {
+    let SurroundfiAccountInitializePda {
+        authority,
+        surroundfi_group,
+        surroundfi_account: surroundfi_account_loader,
+        ..
+    } = ctx.accounts;
+
+    check!(
+        third_party_id != Some(0),
+        SurroundfiError::InvalidThirdPartyId
+    );
+
+    let mut surroundfi_account = surroundfi_account_loader.load_init()?;
+
+    surroundfi_account.initialize_pda(
+        surroundfi_group.key(),
+        authority.key(),
+        account_index,
+        third_party_id.unwrap_or(0),
+        ctx.bumps.surroundfi_account,
+    );
+
+    emit!(SurroundfiAccountCreateEvent {
+        header: AccountEventHeader {
+            version: EVENT_SCHEMA_VERSION,
+            signer: Some(authority.key()),
+            surroundfi_account: surroundfi_account_loader.key(),
+            surroundfi_account_authority: surroundfi_account.authority,
+            surroundfi_group: surroundfi_account.group,
+        }
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+#[instruction(account_index: u16, third_party_id: Option<u16>)]
+pub struct SurroundfiAccountInitializePda<'info> {
+    pub surroundfi_group: AccountLoader<'info, SurroundfiGroup>,
+
+    #[account(
+        init,
+        seeds = [
+            SURROUNDFI_ACCOUNT_SEED.as_bytes(),
+            surroundfi_group.key().as_ref(),
+            authority.key().as_ref(),
+            &account_index.to_le_bytes(),
+            &third_party_id.unwrap_or(0).to_le_bytes(),
+        ],
+        bump,
+        payer = fee_payer,
+        space = 8 + std::mem::size_of::<SurroundfiAccount>()
+    )]
+    pub surroundfi_account: AccountLoader<'info, SurroundfiAccount>,
+
+    /// Can be a PDA of the calling program when invoked via CPI
+    pub authority: Signer<'info>,
+
+    #[account(mut)]
+    pub fee_payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
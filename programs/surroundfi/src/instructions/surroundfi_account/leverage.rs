@@ -0,0 +1,796 @@
+use crate::{
+    bank_signer, check,
+    constants::{ARENA_MAX_LEVERAGE_LEGS, LIQUIDITY_VAULT_AUTHORITY_SEED, ZERO_AMOUNT_THRESHOLD},
+    events::{
+        AccountEventHeader, LendingAccountCloseLeveragedPositionEvent,
+        LendingAccountOpenLeveragedPositionEvent, EVENT_SCHEMA_VERSION,
+    },
+    math_error,
+    prelude::*,
+    state::{
+        health_cache::HealthCache,
+        surroundfi_account::{
+            BankAccountWrapper, RiskEngine, RiskRequirementType, SurroundfiAccount,
+            ACCOUNT_DISABLED, ACCOUNT_IN_FLASHLOAN,
+        },
+        surroundfi_account_extension::find_account_extension,
+        surroundfi_group::{Bank, BankVaultType},
+    },
+    utils::{self, validate_asset_tags, NumTraitsWithTolerance},
+};
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{TokenAccount, TokenInterface};
+use bytemuck::Zeroable;
+use fixed::types::I80F48;
+use solana_program::{
+    clock::Clock,
+    instruction::{AccountMeta, Instruction},
+    program::invoke,
+    sysvar::Sysvar,
+};
+
+/// One borrow, swap and re-deposit round of `lending_account_open_leveraged_position`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct LeverageLeg {
+    /// Amount of the debt bank's token the signer receives (the origination fee is added to the
+    /// liability)
+    pub borrow_amount: u64,
+    /// Instruction data of the swap of `borrow_amount` into the collateral bank's token
+    pub swap_data: Vec<u8>,
+}
+
+/// Open (or add to) a leveraged position on an arena group's token pair: long the collateral
+/// bank's token, short the debt bank's token.
+/// 1. Accrue interest on both banks
+/// 2. For each of the `legs`:
+///    * Borrow `borrow_amount` from the debt bank into the signer's debt token account
+///    * Swap it into the collateral token, by invoking the arena's swap program (see
+///      `ArenaConfig::swap_program`) with `swap_data` and the first `swap_accounts_len` remaining
+///      accounts (the signer's signature is forwarded)
+///    * Deposit the swap proceeds from the signer's collateral token account, plus
+///      `collateral_amount` on the first leg
+/// 3. Verify that the user account is in a healthy state, and within the arena's max leverage
+///
+/// This loops deposit, borrow and swap until the target leverage is reached, in at most
+/// `ARENA_MAX_LEVERAGE_LEGS` legs. The client picks the legs for the leverage it wants, e.g. each
+/// borrowing `previous deposit value * (1 - 1 / leverage)`. Each leg only adds leverage, so the
+/// max leverage check at the end bounds all of them.
+///
+/// Expected remaining account schema
+/// [
+///    collateral_mint_ai (if token2022 mint),
+///    debt_mint_ai (if token2022 mint),
+///    swap_ais... (`swap_accounts_len` of them),
+///    observation_ais...,
+///    extension_ai (if the account has an extension),
+///  ]
+pub fn lending_account_open_leveraged_position<'info>(
+    mut ctx: Context<'_, '_, 'info, 'info, LendingAccountLeveragedPosition<'info>>,
+    collateral_amount: u64,
+    legs: Vec<LeverageLeg>,
+    swap_accounts_len: u8,
+) -> SurroundfiResult {
+    let LendingAccountLeveragedPosition {
+        surroundfi_account: surroundfi_account_loader,
+        authority: signer,
+        group: surroundfi_group_loader,
+        collateral_bank: collateral_bank_loader,
+        collateral_liquidity_vault,
+        collateral_token_account,
+        collateral_token_program,
+        debt_bank: debt_bank_loader,
+        debt_liquidity_vault,
+        debt_liquidity_vault_authority,
+        debt_token_account,
+        debt_token_program,
+        swap_program,
+        ..
+    } = ctx.accounts;
+    let clock = Clock::get()?;
+
+    check!(
+        !legs.is_empty() && legs.len() <= ARENA_MAX_LEVERAGE_LEGS,
+        SurroundfiError::InvalidConfig,
+        "Between 1 and {} legs",
+        ARENA_MAX_LEVERAGE_LEGS
+    );
+
+    let maybe_collateral_mint = utils::maybe_take_bank_mint(
+        &mut ctx.remaining_accounts,
+        &*collateral_bank_loader.load()?,
+        collateral_token_program.key,
+    )?;
+    let maybe_debt_mint = utils::maybe_take_bank_mint(
+        &mut ctx.remaining_accounts,
+        &*debt_bank_loader.load()?,
+        debt_token_program.key,
+    )?;
+    let (swap_ais, remaining_ais) = split_swap_accounts(ctx.remaining_accounts, swap_accounts_len)?;
+
+    let (asset_tag_matrix, program_fee_rate, max_leverage) = {
+        let group = surroundfi_group_loader.load()?;
+        check!(group.is_arena_group(), SurroundfiError::NotArenaGroup);
+
+        collateral_bank_loader.load_mut()?.accrue_interest(
+            clock.unix_timestamp,
+            &group,
+            #[cfg(not(feature = "client"))]
+            collateral_bank_loader.key(),
+        )?;
+        debt_bank_loader.load_mut()?.accrue_interest(
+            clock.unix_timestamp,
+            &group,
+            #[cfg(not(feature = "client"))]
+            debt_bank_loader.key(),
+        )?;
+
+        (
+            group.get_asset_tag_matrix(),
+            I80F48::from(group.fee_state_cache.program_fee_rate),
+            group.get_max_leverage(),
+        )
+    };
+
+    let mut surroundfi_account = surroundfi_account_loader.load_mut()?;
+
+    check!(
+        !surroundfi_account.get_flag(ACCOUNT_DISABLED),
+        SurroundfiError::AccountDisabled
+    );
+    check!(
+        !surroundfi_account.get_flag(ACCOUNT_IN_FLASHLOAN),
+        SurroundfiError::AccountInFlashloan
+    );
+
+    let mut deposited: u64 = 0;
+    let mut borrowed: u64 = 0;
+    let mut total_swap_proceeds: u64 = 0;
+    for (i, leg) in legs.into_iter().enumerate() {
+        let borrowed_amount = {
+            let mut debt_bank = debt_bank_loader.load_mut()?;
+
+            let liquidity_vault_authority_bump = debt_bank.liquidity_vault_authority_bump;
+            let origination_fee_rate: I80F48 = debt_bank
+                .config
+                .interest_rate_config
+                .protocol_origination_fee
+                .into();
+
+            let extension_loader = find_account_extension(&surroundfi_account, remaining_ais)?;
+            let mut extension = extension_loader
+                .as_ref()
+                .map(|loader| loader.load_mut())
+                .transpose()?;
+
+            validate_asset_tags(
+                &debt_bank,
+                &surroundfi_account,
+                extension.as_deref(),
+                &asset_tag_matrix,
+            )?;
+
+            let mut bank_account = BankAccountWrapper::find_or_create(
+                &debt_bank_loader.key(),
+                &mut debt_bank,
+                &mut surroundfi_account.lending_account,
+                extension.as_deref_mut(),
+            )?;
+            bank_account.settle_emissions_streams(
+                &surroundfi_account_loader.key(),
+                ctx.remaining_accounts,
+            )?;
+
+            // User needs to borrow amount + fee to receive amount
+            let amount_pre_fee = maybe_debt_mint
+                .as_ref()
+                .map(|mint| {
+                    utils::calculate_pre_fee_spl_deposit_amount(
+                        mint.to_account_info(),
+                        leg.borrow_amount,
+                        clock.epoch,
+                    )
+                })
+                .transpose()?
+                .unwrap_or(leg.borrow_amount);
+
+            let origination_fee = I80F48::from_num(amount_pre_fee)
+                .checked_mul(origination_fee_rate)
+                .ok_or_else(math_error!())?;
+            let origination_fee_u64: u64 =
+                origination_fee.checked_to_num().ok_or_else(math_error!())?;
+
+            bank_account.borrow(I80F48::from_num(amount_pre_fee) + origination_fee)?;
+            bank_account.withdraw_spl_transfer(
+                amount_pre_fee,
+                debt_liquidity_vault.to_account_info(),
+                debt_token_account.to_account_info(),
+                debt_liquidity_vault_authority.to_account_info(),
+                maybe_debt_mint.as_ref(),
+                debt_token_program.to_account_info(),
+                bank_signer!(
+                    BankVaultType::Liquidity,
+                    debt_bank_loader.key(),
+                    liquidity_vault_authority_bump
+                ),
+                remaining_ais,
+            )?;
+
+            debt_bank.collect_origination_fee(origination_fee, program_fee_rate, false)?;
+
+            amount_pre_fee + origination_fee_u64
+        }; // release mutable borrow of debt_bank
+
+        // Note: the previous leg's deposit moved tokens out of the collateral token account
+        collateral_token_account.reload()?;
+        let collateral_balance_pre_swap = collateral_token_account.amount;
+        invoke_swap(swap_program, swap_ais, leg.swap_data)?;
+        collateral_token_account.reload()?;
+        let swap_proceeds = collateral_token_account
+            .amount
+            .checked_sub(collateral_balance_pre_swap)
+            .ok_or_else(math_error!())?;
+        check!(swap_proceeds > 0, SurroundfiError::SwapNoProceeds);
+
+        // The signer's own collateral goes in with the first leg
+        let leg_collateral = if i == 0 { collateral_amount } else { 0 };
+        let transfer_amount = leg_collateral
+            .checked_add(swap_proceeds)
+            .ok_or_else(math_error!())?;
+        let deposit_amount = maybe_collateral_mint
+            .as_ref()
+            .map(|mint| {
+                utils::calculate_post_fee_spl_deposit_amount(
+                    mint.to_account_info(),
+                    transfer_amount,
+                    clock.epoch,
+                )
+            })
+            .transpose()?
+            .unwrap_or(transfer_amount);
+
+        {
+            let mut collateral_bank = collateral_bank_loader.load_mut()?;
+
+            // Note: the extension must be released before the risk engine loads it again below
+            let extension_loader = find_account_extension(&surroundfi_account, remaining_ais)?;
+            let mut extension = extension_loader
+                .as_ref()
+                .map(|loader| loader.load_mut())
+                .transpose()?;
+
+            // Note: the account now holds the debt balance too
+            validate_asset_tags(
+                &collateral_bank,
+                &surroundfi_account,
+                extension.as_deref(),
+                &asset_tag_matrix,
+            )?;
+
+            let mut bank_account = BankAccountWrapper::find_or_create(
+                &collateral_bank_loader.key(),
+                &mut collateral_bank,
+                &mut surroundfi_account.lending_account,
+                extension.as_deref_mut(),
+            )?;
+            bank_account.settle_emissions_streams(
+                &surroundfi_account_loader.key(),
+                ctx.remaining_accounts,
+            )?;
+
+            bank_account.deposit(I80F48::from_num(deposit_amount))?;
+            bank_account.deposit_spl_transfer(
+                transfer_amount,
+                collateral_token_account.to_account_info(),
+                collateral_liquidity_vault.to_account_info(),
+                signer.to_account_info(),
+                maybe_collateral_mint.as_ref(),
+                collateral_token_program.to_account_info(),
+                remaining_ais,
+            )?;
+        } // release mutable borrow of collateral_bank
+
+        deposited = deposited
+            .checked_add(deposit_amount)
+            .ok_or_else(math_error!())?;
+        borrowed = borrowed
+            .checked_add(borrowed_amount)
+            .ok_or_else(math_error!())?;
+        total_swap_proceeds = total_swap_proceeds
+            .checked_add(swap_proceeds)
+            .ok_or_else(math_error!())?;
+    }
+
+    emit!(LendingAccountOpenLeveragedPositionEvent {
+        header: AccountEventHeader {
+            version: EVENT_SCHEMA_VERSION,
+            signer: Some(signer.key()),
+            surroundfi_account: surroundfi_account_loader.key(),
+            surroundfi_account_authority: surroundfi_account.authority,
+            surroundfi_group: surroundfi_account.group,
+        },
+        collateral_bank: collateral_bank_loader.key(),
+        debt_bank: debt_bank_loader.key(),
+        deposited,
+        borrowed,
+        swap_proceeds: total_swap_proceeds,
+    });
+
+    check_position_risk(&mut surroundfi_account, remaining_ais, max_leverage, &clock)
+}
+
+/// Reduce (or close) a leveraged position on an arena group's token pair.
+/// 1. Accrue interest on both banks
+/// 2. Withdraw `collateral_amount` from the collateral bank into the signer's collateral token
+///    account
+/// 3. Swap it into the debt token, by invoking the arena's swap program with `swap_data` and the
+///    first `swap_accounts_len` remaining accounts (the signer's signature is forwarded)
+/// 4. Repay the debt bank with the swap proceeds from the signer's debt token account. Proceeds in
+///    excess of the liability stay in the signer's debt token account.
+/// 5. If the liability is fully repaid, withdraw the rest of the collateral to the signer
+/// 6. Verify that the user account is in a healthy state and within the arena's max leverage, or
+///    at least that neither its health nor its leverage got worse. An account that is unhealthy or
+///    above a lowered max leverage can always deleverage.
+///
+/// Expected remaining account schema: same as `lending_account_open_leveraged_position`. The
+/// observation accounts are the same before and after: a partial close keeps both balances open,
+/// and a full close leaves no balance to price.
+pub fn lending_account_close_leveraged_position<'info>(
+    mut ctx: Context<'_, '_, 'info, 'info, LendingAccountLeveragedPosition<'info>>,
+    collateral_amount: u64,
+    swap_accounts_len: u8,
+    swap_data: Vec<u8>,
+) -> SurroundfiResult {
+    let LendingAccountLeveragedPosition {
+        surroundfi_account: surroundfi_account_loader,
+        authority: signer,
+        group: surroundfi_group_loader,
+        collateral_bank: collateral_bank_loader,
+        collateral_liquidity_vault,
+        collateral_liquidity_vault_authority,
+        collateral_token_account,
+        collateral_token_program,
+        debt_bank: debt_bank_loader,
+        debt_liquidity_vault,
+        debt_token_account,
+        debt_token_program,
+        swap_program,
+        ..
+    } = ctx.accounts;
+    let clock = Clock::get()?;
+
+    let maybe_collateral_mint = utils::maybe_take_bank_mint(
+        &mut ctx.remaining_accounts,
+        &*collateral_bank_loader.load()?,
+        collateral_token_program.key,
+    )?;
+    let maybe_debt_mint = utils::maybe_take_bank_mint(
+        &mut ctx.remaining_accounts,
+        &*debt_bank_loader.load()?,
+        debt_token_program.key,
+    )?;
+    let (swap_ais, remaining_ais) = split_swap_accounts(ctx.remaining_accounts, swap_accounts_len)?;
+
+    let max_leverage = {
+        let group = surroundfi_group_loader.load()?;
+        check!(group.is_arena_group(), SurroundfiError::NotArenaGroup);
+
+        collateral_bank_loader.load_mut()?.accrue_interest(
+            clock.unix_timestamp,
+            &group,
+            #[cfg(not(feature = "client"))]
+            collateral_bank_loader.key(),
+        )?;
+        debt_bank_loader.load_mut()?.accrue_interest(
+            clock.unix_timestamp,
+            &group,
+            #[cfg(not(feature = "client"))]
+            debt_bank_loader.key(),
+        )?;
+
+        group.get_max_leverage()
+    };
+
+    let mut surroundfi_account = surroundfi_account_loader.load_mut()?;
+
+    check!(
+        !surroundfi_account.get_flag(ACCOUNT_DISABLED),
+        SurroundfiError::AccountDisabled
+    );
+    check!(
+        !surroundfi_account.get_flag(ACCOUNT_IN_FLASHLOAN),
+        SurroundfiError::AccountInFlashloan
+    );
+
+    let (health_before, leverage_before) = get_position_risk(&surroundfi_account, remaining_ais)?;
+
+    {
+        let mut collateral_bank = collateral_bank_loader.load_mut()?;
+
+        let liquidity_vault_authority_bump = collateral_bank.liquidity_vault_authority_bump;
+
+        let extension_loader = find_account_extension(&surroundfi_account, remaining_ais)?;
+        let mut extension = extension_loader
+            .as_ref()
+            .map(|loader| loader.load_mut())
+            .transpose()?;
+
+        let mut bank_account = BankAccountWrapper::find(
+            &collateral_bank_loader.key(),
+            &mut collateral_bank,
+            &mut surroundfi_account.lending_account,
+            extension.as_deref_mut(),
+        )?;
+        bank_account
+            .settle_emissions_streams(&surroundfi_account_loader.key(), ctx.remaining_accounts)?;
+
+        bank_account.withdraw(I80F48::from_num(collateral_amount))?;
+        bank_account.withdraw_spl_transfer(
+            collateral_amount,
+            collateral_liquidity_vault.to_account_info(),
+            collateral_token_account.to_account_info(),
+            collateral_liquidity_vault_authority.to_account_info(),
+            maybe_collateral_mint.as_ref(),
+            collateral_token_program.to_account_info(),
+            bank_signer!(
+                BankVaultType::Liquidity,
+                collateral_bank_loader.key(),
+                liquidity_vault_authority_bump
+            ),
+            remaining_ais,
+        )?;
+    } // release mutable borrow of collateral_bank
+
+    let debt_balance_pre_swap = debt_token_account.amount;
+    invoke_swap(swap_program, swap_ais, swap_data)?;
+    debt_token_account.reload()?;
+    let swap_proceeds = debt_token_account
+        .amount
+        .checked_sub(debt_balance_pre_swap)
+        .ok_or_else(math_error!())?;
+    check!(swap_proceeds > 0, SurroundfiError::SwapNoProceeds);
+
+    let (repaid, closed) = {
+        let mut debt_bank = debt_bank_loader.load_mut()?;
+
+        let extension_loader = find_account_extension(&surroundfi_account, remaining_ais)?;
+        let mut extension = extension_loader
+            .as_ref()
+            .map(|loader| loader.load_mut())
+            .transpose()?;
+
+        let mut bank_account = BankAccountWrapper::find(
+            &debt_bank_loader.key(),
+            &mut debt_bank,
+            &mut surroundfi_account.lending_account,
+            extension.as_deref_mut(),
+        )?;
+        bank_account
+            .settle_emissions_streams(&surroundfi_account_loader.key(), ctx.remaining_accounts)?;
+
+        let proceeds_post_fee = maybe_debt_mint
+            .as_ref()
+            .map(|mint| {
+                utils::calculate_post_fee_spl_deposit_amount(
+                    mint.to_account_info(),
+                    swap_proceeds,
+                    clock.epoch,
+                )
+            })
+            .transpose()?
+            .unwrap_or(swap_proceeds);
+        let liability = bank_account
+            .bank
+            .get_liability_amount(bank_account.balance.liability_shares.into())?;
+
+        let (repaid, transfer_amount, closed) = if I80F48::from_num(proceeds_post_fee) >= liability
+        {
+            let repaid = bank_account.repay_all()?;
+            let transfer_amount = maybe_debt_mint
+                .as_ref()
+                .map(|mint| {
+                    utils::calculate_pre_fee_spl_deposit_amount(
+                        mint.to_account_info(),
+                        repaid,
+                        clock.epoch,
+                    )
+                })
+                .transpose()?
+                .unwrap_or(repaid);
+            (repaid, transfer_amount, true)
+        } else {
+            bank_account.repay(I80F48::from_num(proceeds_post_fee))?;
+            (proceeds_post_fee, swap_proceeds, false)
+        };
+
+        bank_account.deposit_spl_transfer(
+            transfer_amount,
+            debt_token_account.to_account_info(),
+            debt_liquidity_vault.to_account_info(),
+            signer.to_account_info(),
+            maybe_debt_mint.as_ref(),
+            debt_token_program.to_account_info(),
+            remaining_ais,
+        )?;
+
+        (repaid, closed)
+    }; // release mutable borrow of debt_bank
+
+    let mut withdrawn = collateral_amount;
+    if closed {
+        let mut collateral_bank = collateral_bank_loader.load_mut()?;
+
+        let liquidity_vault_authority_bump = collateral_bank.liquidity_vault_authority_bump;
+
+        // Note: the extension must be released before the risk engine loads it again below
+        let extension_loader = find_account_extension(&surroundfi_account, remaining_ais)?;
+        let mut extension = extension_loader
+            .as_ref()
+            .map(|loader| loader.load_mut())
+            .transpose()?;
+
+        let mut bank_account = BankAccountWrapper::find(
+            &collateral_bank_loader.key(),
+            &mut collateral_bank,
+            &mut surroundfi_account.lending_account,
+            extension.as_deref_mut(),
+        )?;
+        bank_account
+            .settle_emissions_streams(&surroundfi_account_loader.key(), ctx.remaining_accounts)?;
+
+        let remaining_collateral = bank_account
+            .bank
+            .get_asset_amount(bank_account.balance.asset_shares.into())?;
+        if remaining_collateral.is_positive_with_tolerance(ZERO_AMOUNT_THRESHOLD) {
+            let remaining_amount = bank_account.withdraw_all()?;
+            bank_account.withdraw_spl_transfer(
+                remaining_amount,
+                collateral_liquidity_vault.to_account_info(),
+                collateral_token_account.to_account_info(),
+                collateral_liquidity_vault_authority.to_account_info(),
+                maybe_collateral_mint.as_ref(),
+                collateral_token_program.to_account_info(),
+                bank_signer!(
+                    BankVaultType::Liquidity,
+                    collateral_bank_loader.key(),
+                    liquidity_vault_authority_bump
+                ),
+                remaining_ais,
+            )?;
+            withdrawn = withdrawn
+                .checked_add(remaining_amount)
+                .ok_or_else(math_error!())?;
+        }
+    }
+
+    emit!(LendingAccountCloseLeveragedPositionEvent {
+        header: AccountEventHeader {
+            version: EVENT_SCHEMA_VERSION,
+            signer: Some(signer.key()),
+            surroundfi_account: surroundfi_account_loader.key(),
+            surroundfi_account_authority: surroundfi_account.authority,
+            surroundfi_group: surroundfi_account.group,
+        },
+        collateral_bank: collateral_bank_loader.key(),
+        debt_bank: debt_bank_loader.key(),
+        withdrawn,
+        repaid,
+        swap_proceeds,
+        closed,
+    });
+
+    let (health, leverage) = get_position_risk(&surroundfi_account, remaining_ais)?;
+    let within_limits = health >= I80F48::ZERO && max_leverage.map_or(true, |max| leverage <= max);
+    if !within_limits {
+        check!(
+            health >= health_before,
+            SurroundfiError::RiskEngineInitRejected
+        );
+        check!(
+            leverage <= leverage_before,
+            SurroundfiError::ArenaMaxLeverageExceeded
+        );
+        return update_position_health_cache(
+            &mut surroundfi_account,
+            remaining_ais,
+            health,
+            &clock,
+        );
+    }
+
+    check_position_risk(&mut surroundfi_account, remaining_ais, max_leverage, &clock)
+}
+
+/// Splits the `swap_accounts_len` accounts of the swap off the front of `remaining_ais`
+fn split_swap_accounts<'info>(
+    remaining_ais: &'info [AccountInfo<'info>],
+    swap_accounts_len: u8,
+) -> SurroundfiResult<(&'info [AccountInfo<'info>], &'info [AccountInfo<'info>])> {
+    check!(
+        remaining_ais.len() >= swap_accounts_len as usize,
+        SurroundfiError::InvalidConfig,
+        "Not enough remaining accounts for the swap"
+    );
+
+    Ok(remaining_ais.split_at(swap_accounts_len as usize))
+}
+
+/// Note: no PDA of this program signs, the arena's swap program can only move the signer's tokens
+fn invoke_swap<'info>(
+    swap_program: &AccountInfo<'info>,
+    swap_ais: &[AccountInfo<'info>],
+    swap_data: Vec<u8>,
+) -> SurroundfiResult {
+    let accounts = swap_ais
+        .iter()
+        .map(|ai| AccountMeta {
+            pubkey: *ai.key,
+            is_signer: ai.is_signer,
+            is_writable: ai.is_writable,
+        })
+        .collect();
+
+    let mut account_infos = swap_ais.to_vec();
+    account_infos.push(swap_program.clone());
+
+    invoke(
+        &Instruction {
+            program_id: *swap_program.key,
+            accounts,
+            data: swap_data,
+        },
+        &account_infos,
+    )?;
+
+    Ok(())
+}
+
+/// The account's initial health (weighted assets minus weighted liabilities) and its leverage,
+/// see `RiskEngine::get_account_leverage`
+fn get_position_risk<'info>(
+    surroundfi_account: &SurroundfiAccount,
+    remaining_ais: &'info [AccountInfo<'info>],
+) -> SurroundfiResult<(I80F48, I80F48)> {
+    let risk_engine = RiskEngine::new(surroundfi_account, remaining_ais)?;
+    let (assets, liabilities) =
+        risk_engine.get_account_health_components(RiskRequirementType::Initial, &mut None)?;
+    let health = assets.checked_sub(liabilities).ok_or_else(math_error!())?;
+
+    Ok((health, risk_engine.get_account_leverage()?))
+}
+
+fn check_position_risk<'info>(
+    surroundfi_account: &mut SurroundfiAccount,
+    remaining_ais: &'info [AccountInfo<'info>],
+    max_leverage: Option<I80F48>,
+    clock: &Clock,
+) -> SurroundfiResult {
+    let mut health_cache = HealthCache::zeroed();
+    health_cache.timestamp = clock.unix_timestamp;
+
+    // Check account health, if below threshold fail transaction
+    let risk_engine = RiskEngine::check_account_init_health(
+        surroundfi_account,
+        remaining_ais,
+        &mut Some(&mut health_cache),
+    )?;
+
+    if let (Some(risk_engine), Some(max_leverage)) = (risk_engine, max_leverage) {
+        risk_engine.check_account_leverage(max_leverage)?;
+    }
+
+    health_cache.set_engine_ok(true);
+    surroundfi_account.health_cache = health_cache;
+
+    Ok(())
+}
+
+/// Records the account's health in its cache like `check_position_risk`, but without failing on
+/// it, for closes that leave the account outside of the group's limits (though less so)
+fn update_position_health_cache<'info>(
+    surroundfi_account: &mut SurroundfiAccount,
+    remaining_ais: &'info [AccountInfo<'info>],
+    health: I80F48,
+    clock: &Clock,
+) -> SurroundfiResult {
+    let mut health_cache = HealthCache::zeroed();
+    health_cache.timestamp = clock.unix_timestamp;
+
+    {
+        let risk_engine = RiskEngine::new(surroundfi_account, remaining_ais)?;
+        risk_engine.update_health_cache_component(RiskRequirementType::Initial, &mut health_cache);
+        risk_engine
+            .update_health_cache_component(RiskRequirementType::Maintenance, &mut health_cache);
+        risk_engine.update_health_cache_component(RiskRequirementType::Equity, &mut health_cache);
+        risk_engine.maybe_update_extension_health_cache(remaining_ais)?;
+    }
+
+    health_cache.set_healthy(health >= I80F48::ZERO);
+    health_cache.set_engine_ok(true);
+    surroundfi_account.health_cache = health_cache;
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct LendingAccountLeveragedPosition<'info> {
+    pub group: AccountLoader<'info, SurroundfiGroup>,
+
+    #[account(
+        mut,
+        has_one = group,
+        has_one = authority
+    )]
+    pub surroundfi_account: AccountLoader<'info, SurroundfiAccount>,
+
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = group,
+        constraint = collateral_bank.load()?.liquidity_vault == collateral_liquidity_vault.key()
+    )]
+    pub collateral_bank: AccountLoader<'info, Bank>,
+
+    #[account(mut)]
+    pub collateral_liquidity_vault: InterfaceAccount<'info, TokenAccount>,
+
+    /// CHECK: Seed constraint check
+    #[account(
+        seeds = [
+            LIQUIDITY_VAULT_AUTHORITY_SEED.as_bytes(),
+            collateral_bank.key().as_ref(),
+        ],
+        bump = collateral_bank.load()?.liquidity_vault_authority_bump,
+    )]
+    pub collateral_liquidity_vault_authority: AccountInfo<'info>,
+
+    /// The signer's account of the collateral bank's mint, receives the proceeds of the swap
+    /// (open) or the collateral withdrawn (close)
+    #[account(
+        mut,
+        constraint = collateral_token_account.mint == collateral_bank.load()?.mint
+    )]
+    pub collateral_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    pub collateral_token_program: Interface<'info, TokenInterface>,
+
+    #[account(
+        mut,
+        has_one = group,
+        constraint = debt_bank.key() != collateral_bank.key()
+            @ SurroundfiError::SameAssetAndLiabilityBanks,
+        constraint = debt_bank.load()?.liquidity_vault == debt_liquidity_vault.key()
+    )]
+    pub debt_bank: AccountLoader<'info, Bank>,
+
+    #[account(mut)]
+    pub debt_liquidity_vault: InterfaceAccount<'info, TokenAccount>,
+
+    /// CHECK: Seed constraint check
+    #[account(
+        seeds = [
+            LIQUIDITY_VAULT_AUTHORITY_SEED.as_bytes(),
+            debt_bank.key().as_ref(),
+        ],
+        bump = debt_bank.load()?.liquidity_vault_authority_bump,
+    )]
+    pub debt_liquidity_vault_authority: AccountInfo<'info>,
+
+    /// The signer's account of the debt bank's mint, receives the borrow (open) or the proceeds of
+    /// the swap (close)
+    #[account(
+        mut,
+        constraint = debt_token_account.mint == debt_bank.load()?.mint
+    )]
+    pub debt_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    pub debt_token_program: Interface<'info, TokenInterface>,
+
+    /// CHECK: The arena's swap program, invoked with the signer's authority only
+    #[account(
+        executable,
+        constraint = group.load()?.arena_config.is_swap_program(swap_program.key)
+            @ SurroundfiError::SwapProgramNotWhitelisted
+    )]
+    pub swap_program: AccountInfo<'info>,
+}
@@ -1,21 +1,24 @@
-use crate::constants::{
-    INSURANCE_VAULT_SEED, LIQUIDATION_INSURANCE_FEE, LIQUIDATION_LIQUIDATOR_FEE,
+use crate::constants::{INSURANCE_VAULT_SEED, LIQUIDATION_INSURANCE_FEE};
+use crate::events::{
+    AccountEventHeader, LendingAccountLiquidateEvent, LiquidationBalances, EVENT_SCHEMA_VERSION,
 };
-use crate::events::{AccountEventHeader, LendingAccountLiquidateEvent, LiquidationBalances};
+use crate::state::health_cache::HealthCache;
 use crate::state::surroundfi_account::{
-    calc_amount, calc_value, get_remaining_accounts_per_bank, RiskEngine,
+    calc_amount, calc_value, get_remaining_accounts_per_bank, RiskEngine, RiskRequirementType,
 };
+use crate::state::surroundfi_account_extension::find_account_extension;
 use crate::state::surroundfi_group::{Bank, BankVaultType};
 use crate::state::price::{OraclePriceFeedAdapter, OraclePriceType, PriceAdapter, PriceBias};
 use crate::utils::{validate_asset_tags, validate_bank_asset_tags};
 use crate::{
     bank_signer,
     constants::{LIQUIDITY_VAULT_AUTHORITY_SEED, LIQUIDITY_VAULT_SEED},
-    state::surroundfi_account::{BankAccountWrapper, SurroundfiAccount},
+    state::surroundfi_account::{BankAccountWrapper, SurroundfiAccount, ACCOUNT_IN_FLASHLOAN},
 };
-use crate::{check, debug, prelude::*, utils};
+use crate::{check, debug, math_error, prelude::*, utils};
 use anchor_lang::prelude::*;
 use anchor_spl::token_interface::{TokenAccount, TokenInterface};
+use bytemuck::Zeroable;
 use fixed::types::I80F48;
 use solana_program::clock::Clock;
 use solana_program::sysvar::Sysvar;
@@ -32,7 +35,7 @@ use solana_program::sysvar::Sysvar;
 /// - `q_a`: Quantity of `A` to be liquidated
 /// - `p_l`: Price of `L`
 /// - `p_a`: Price of `A`
-/// - `f_l`: Liquidation fee
+/// - `f_l`: Liquidation fee (`LIQUIDATION_LIQUIDATOR_FEE`, or the arena's `liquidation_fee`)
 /// - `f_i`: Insurance fee
 ///
 /// The liquidator invokes this instruction with `q_a` as input (the total amount of collateral to be liquidated).
@@ -73,9 +76,13 @@ use solana_program::sysvar::Sysvar;
 /// [
 ///    liab_mint_ai (if token2022 mint),
 ///    asset_oracle_ai,
+///    asset_mint_ai (if interest-bearing),
 ///    liab_oracle_ai,
+///    liab_mint_ai (if interest-bearing),
 ///    liquidator_observation_ais...,
+///    liquidator_extension_ai (if the liquidator has an extension),
 ///    liquidatee_observation_ais...,
+///    liquidatee_extension_ai (if the liquidatee has an extension),
 ///  ]
 
 pub fn lending_account_liquidate<'info>(
@@ -89,24 +96,47 @@ pub fn lending_account_liquidate<'info>(
         SurroundfiError::SameAssetAndLiabilityBanks
     );
 
-    // Liquidators must repay debts in allowed asset types. A SOL debt can be repaid in any asset. A
-    // Staked Collateral debt must be repaid in SOL or staked collateral. A Default asset debt can
-    // be repaid in any Default asset or SOL.
+    // Liquidators must repay debts in asset types allowed by the group's asset tag matrix. By
+    // default, a SOL debt can be repaid in any asset. A Staked Collateral debt must be repaid in
+    // SOL or staked collateral. A Default asset debt can be repaid in any Default asset or SOL.
     {
+        let asset_tag_matrix = ctx.accounts.group.load()?.get_asset_tag_matrix();
         let asset_bank = ctx.accounts.asset_bank.load()?;
         let liab_bank = ctx.accounts.liab_bank.load()?;
-        validate_bank_asset_tags(&asset_bank, &liab_bank)?;
+        validate_bank_asset_tags(&asset_bank, &liab_bank, &asset_tag_matrix)?;
 
         // Sanity check user/liquidator accounts will not contain positions with mismatching tags
         // after liquidation.
         // * Note: user will be repaid in liab_bank
         let user_acc = ctx.accounts.liquidatee_surroundfi_account.load()?;
-        validate_asset_tags(&liab_bank, &user_acc)?;
+        let user_extension_loader = find_account_extension(&user_acc, ctx.remaining_accounts)?;
+        let user_extension = user_extension_loader
+            .as_ref()
+            .map(|loader| loader.load())
+            .transpose()?;
+        validate_asset_tags(
+            &liab_bank,
+            &user_acc,
+            user_extension.as_deref(),
+            &asset_tag_matrix,
+        )?;
         // * Note: Liquidator repays liab bank, and is paid in asset_bank.
         let liquidator_acc = ctx.accounts.liquidator_surroundfi_account.load()?;
-        validate_asset_tags(&liab_bank, &liquidator_acc)?;
-        validate_asset_tags(&asset_bank, &liquidator_acc)?;
-    } // release immutable borrow of asset_bank/liab_bank + liquidatee/liquidator user accounts
+        let liquidator_extension_loader =
+            find_account_extension(&liquidator_acc, ctx.remaining_accounts)?;
+        let liquidator_extension = liquidator_extension_loader
+            .as_ref()
+            .map(|loader| loader.load())
+            .transpose()?;
+        for bank in [&liab_bank, &asset_bank] {
+            validate_asset_tags(
+                bank,
+                &liquidator_acc,
+                liquidator_extension.as_deref(),
+                &asset_tag_matrix,
+            )?;
+        }
+    } // release immutable borrow of banks + liquidatee/liquidator accounts and extensions
 
     let LendingAccountLiquidate {
         liquidator_surroundfi_account: liquidator_surroundfi_account_loader,
@@ -141,7 +171,18 @@ pub fn lending_account_liquidate<'info>(
         )?;
     }
 
-    let init_liquidatee_remaining_len = liquidatee_surroundfi_account.get_remaining_accounts_len()?;
+    let liquidatee_extension_loader =
+        find_account_extension(&liquidatee_surroundfi_account, ctx.remaining_accounts)?;
+    let liquidator_extension_loader =
+        find_account_extension(&liquidator_surroundfi_account, ctx.remaining_accounts)?;
+
+    let init_liquidatee_remaining_len = {
+        let liquidatee_extension = liquidatee_extension_loader
+            .as_ref()
+            .map(|loader| loader.load())
+            .transpose()?;
+        liquidatee_surroundfi_account.get_remaining_accounts_len(liquidatee_extension.as_deref())?
+    };
 
     let liquidatee_accounts_starting_pos =
         ctx.remaining_accounts.len() - init_liquidatee_remaining_len;
@@ -158,34 +199,63 @@ pub fn lending_account_liquidate<'info>(
     let (pre_balances, post_balances) = {
         let asset_amount = I80F48::from_num(asset_amount);
 
+        // Note: released at the end of this scope, before the risk engine loads them again
+        let mut liquidatee_extension = liquidatee_extension_loader
+            .as_ref()
+            .map(|loader| loader.load_mut())
+            .transpose()?;
+        let mut liquidator_extension = liquidator_extension_loader
+            .as_ref()
+            .map(|loader| loader.load_mut())
+            .transpose()?;
+
         let mut asset_bank = ctx.accounts.asset_bank.load_mut()?;
         let asset_bank_remaining_accounts_len = get_remaining_accounts_per_bank(&asset_bank)? - 1;
 
+        // Prices are per raw amount, scaled by the accrued rate of interest-bearing mints
         let asset_price = {
-            let oracle_ais = &ctx.remaining_accounts[0..asset_bank_remaining_accounts_len];
+            let (oracle_ais, ui_amount_scale) = utils::split_interest_bearing_mint(
+                &asset_bank,
+                asset_bank.is_interest_bearing(),
+                &ctx.remaining_accounts[0..asset_bank_remaining_accounts_len],
+                current_timestamp,
+            )?;
             let asset_pf = OraclePriceFeedAdapter::try_from_bank_config(
                 &asset_bank.config,
                 oracle_ais,
                 &clock,
             )?;
-            asset_pf.get_price_of_type(OraclePriceType::RealTime, Some(PriceBias::Low))?
+            asset_pf
+                .get_price_of_type(OraclePriceType::RealTime, Some(PriceBias::Low))?
+                .checked_mul(ui_amount_scale)
+                .ok_or_else(math_error!())?
         };
 
         let mut liab_bank = ctx.accounts.liab_bank.load_mut()?;
         let liab_bank_remaining_accounts_len = get_remaining_accounts_per_bank(&liab_bank)? - 1;
         let liab_price = {
-            let oracle_ais = &ctx.remaining_accounts[asset_bank_remaining_accounts_len
-                ..(asset_bank_remaining_accounts_len + liab_bank_remaining_accounts_len)];
+            let (oracle_ais, ui_amount_scale) = utils::split_interest_bearing_mint(
+                &liab_bank,
+                liab_bank.is_interest_bearing(),
+                &ctx.remaining_accounts[asset_bank_remaining_accounts_len
+                    ..(asset_bank_remaining_accounts_len + liab_bank_remaining_accounts_len)],
+                current_timestamp,
+            )?;
             let liab_pf = OraclePriceFeedAdapter::try_from_bank_config(
                 &liab_bank.config,
                 oracle_ais,
                 &clock,
             )?;
-            liab_pf.get_price_of_type(OraclePriceType::RealTime, Some(PriceBias::High))?
+            liab_pf
+                .get_price_of_type(OraclePriceType::RealTime, Some(PriceBias::High))?
+                .checked_mul(ui_amount_scale)
+                .ok_or_else(math_error!())?
         };
 
-        let final_discount = I80F48::ONE - (LIQUIDATION_INSURANCE_FEE + LIQUIDATION_LIQUIDATOR_FEE);
-        let liquidator_discount = I80F48::ONE - LIQUIDATION_LIQUIDATOR_FEE;
+        // Arena groups can set their own liquidator fee
+        let liquidator_fee = surroundfi_group_loader.load()?.get_liquidator_fee();
+        let final_discount = I80F48::ONE - (LIQUIDATION_INSURANCE_FEE + liquidator_fee);
+        let liquidator_discount = I80F48::ONE - liquidator_fee;
 
         // Quantity of liability to be paid off by liquidator
         let liab_amount_liquidator = calc_amount(
@@ -230,6 +300,11 @@ pub fn lending_account_liquidate<'info>(
                 &ctx.accounts.liab_bank.key(),
                 &mut liab_bank,
                 &mut liquidator_surroundfi_account.lending_account,
+                liquidator_extension.as_deref_mut(),
+            )?;
+            bank_account.settle_emissions_streams(
+                &liquidator_surroundfi_account_loader.key(),
+                ctx.remaining_accounts,
             )?;
 
             let pre_balance = bank_account
@@ -251,6 +326,11 @@ pub fn lending_account_liquidate<'info>(
                 &ctx.accounts.asset_bank.key(),
                 &mut asset_bank,
                 &mut liquidatee_surroundfi_account.lending_account,
+                liquidatee_extension.as_deref_mut(),
+            )?;
+            bank_account.settle_emissions_streams(
+                &liquidatee_surroundfi_account_loader.key(),
+                ctx.remaining_accounts,
             )?;
 
             let pre_balance = bank_account
@@ -270,10 +350,31 @@ pub fn lending_account_liquidate<'info>(
 
         // Liquidator receives `asset_quantity` amount of collateral
         let (liquidator_asset_pre_balance, liquidator_asset_post_balance) = {
+            // Collateral that pays off liabilities the liquidator opened within a flashloan is
+            // charged the flashloan fee, out of the collateral received
+            let flashloan_volume_closed =
+                if liquidator_surroundfi_account.get_flag(ACCOUNT_IN_FLASHLOAN) {
+                    liquidator_surroundfi_account
+                        .lending_account
+                        .record_flashloan_balance_increase(
+                            &ctx.accounts.asset_bank.key(),
+                            &asset_bank,
+                            liquidator_extension.as_deref(),
+                            asset_amount.checked_to_num().ok_or_else(math_error!())?,
+                        )?
+                } else {
+                    0
+                };
+
             let mut bank_account = BankAccountWrapper::find_or_create(
                 &ctx.accounts.asset_bank.key(),
                 &mut asset_bank,
                 &mut liquidator_surroundfi_account.lending_account,
+                liquidator_extension.as_deref_mut(),
+            )?;
+            bank_account.settle_emissions_streams(
+                &liquidator_surroundfi_account_loader.key(),
+                ctx.remaining_accounts,
             )?;
 
             let pre_balance = bank_account
@@ -282,6 +383,13 @@ pub fn lending_account_liquidate<'info>(
 
             bank_account.increase_balance_in_liquidation(asset_amount)?;
 
+            if flashloan_volume_closed > 0 {
+                let flashloan_fee = bank_account
+                    .bank
+                    .credit_flashloan_fee(I80F48::from_num(flashloan_volume_closed))?;
+                bank_account.decrease_balance_in_liquidation(flashloan_fee)?;
+            }
+
             let post_balance = bank_account
                 .bank
                 .get_asset_amount(bank_account.balance.asset_shares.into())?;
@@ -304,6 +412,11 @@ pub fn lending_account_liquidate<'info>(
                 &ctx.accounts.liab_bank.key(),
                 &mut liab_bank,
                 &mut liquidatee_surroundfi_account.lending_account,
+                liquidatee_extension.as_deref_mut(),
+            )?;
+            liquidatee_liab_bank_account.settle_emissions_streams(
+                &liquidatee_surroundfi_account_loader.key(),
+                ctx.remaining_accounts,
             )?;
 
             let liquidatee_liability_pre_balance =
@@ -348,6 +461,7 @@ pub fn lending_account_liquidate<'info>(
                 .checked_add(insurance_fee_dust)
                 .ok_or(SurroundfiError::MathError)?
                 .into();
+        liab_bank.credit_insurance_stake_fees(I80F48::from_num(insurance_fee_to_transfer))?;
 
         (
             LiquidationBalances {
@@ -367,7 +481,13 @@ pub fn lending_account_liquidate<'info>(
 
     // ## Risk checks ##
 
-    let liquidator_remaining_acc_len = liquidator_surroundfi_account.get_remaining_accounts_len()?;
+    let liquidator_remaining_acc_len = {
+        let liquidator_extension = liquidator_extension_loader
+            .as_ref()
+            .map(|loader| loader.load())
+            .transpose()?;
+        liquidator_surroundfi_account.get_remaining_accounts_len(liquidator_extension.as_deref())?
+    };
     let liquidator_accounts_starting_pos =
         liquidatee_accounts_starting_pos - liquidator_remaining_acc_len;
 
@@ -375,14 +495,37 @@ pub fn lending_account_liquidate<'info>(
         &ctx.remaining_accounts[liquidator_accounts_starting_pos..liquidatee_accounts_starting_pos];
 
     // Verify liquidatee liquidation post health
-    let post_liquidation_health =
-        RiskEngine::new(&liquidatee_surroundfi_account, liquidatee_remaining_accounts)?
+    let mut liquidatee_health_cache = HealthCache::zeroed();
+    liquidatee_health_cache.timestamp = current_timestamp;
+    let post_liquidation_health = {
+        let risk_engine = RiskEngine::new(
+            &liquidatee_surroundfi_account,
+            liquidatee_remaining_accounts,
+        )?;
+        let post_liquidation_health = risk_engine
             .check_post_liquidation_condition_and_get_account_health(
                 &ctx.accounts.liab_bank.key(),
                 pre_liquidation_health,
+                &mut Some(&mut liquidatee_health_cache),
             )?;
 
-    // TODO consider if health cache update here is worth blowing the extra CU
+        // The oracles are already loaded, so the other components are cheap to add
+        risk_engine.update_health_cache_component(
+            RiskRequirementType::Initial,
+            &mut liquidatee_health_cache,
+        );
+        risk_engine.update_health_cache_component(
+            RiskRequirementType::Equity,
+            &mut liquidatee_health_cache,
+        );
+        risk_engine.maybe_update_extension_health_cache(liquidatee_remaining_accounts)?;
+
+        post_liquidation_health
+    };
+    // The account is still at most at the maintenance requirement after liquidation
+    liquidatee_health_cache.set_healthy(post_liquidation_health > I80F48::ZERO);
+    liquidatee_health_cache.set_engine_ok(true);
+    liquidatee_surroundfi_account.health_cache = liquidatee_health_cache;
 
     // Verify liquidator account health
     RiskEngine::check_account_init_health(
@@ -393,6 +536,7 @@ pub fn lending_account_liquidate<'info>(
 
     emit!(LendingAccountLiquidateEvent {
         header: AccountEventHeader {
+            version: EVENT_SCHEMA_VERSION,
             signer: Some(ctx.accounts.authority.key()),
             surroundfi_account: liquidator_surroundfi_account_loader.key(),
             surroundfi_account_authority: liquidator_surroundfi_account.authority,
@@ -2,24 +2,36 @@ pub mod borrow;
 pub mod close;
 pub mod close_balance;
 pub mod deposit;
+pub mod deposit_stake;
 pub mod emissions;
+pub mod extension;
 pub mod flashloan;
 pub mod initialize;
+pub mod initialize_pda;
+pub mod leverage;
 pub mod liquidate;
 pub mod pulse_health;
 pub mod repay;
+pub mod simulate_health;
 pub mod transfer_authority;
 pub mod withdraw;
+pub mod withdraw_stake;
 
 pub use borrow::*;
 pub use close::*;
 pub use close_balance::*;
 pub use deposit::*;
+pub use deposit_stake::*;
 pub use emissions::*;
+pub use extension::*;
 pub use flashloan::*;
 pub use initialize::*;
+pub use initialize_pda::*;
+pub use leverage::*;
 pub use liquidate::*;
 pub use pulse_health::*;
 pub use repay::*;
+pub use simulate_health::*;
 pub use transfer_authority::*;
 pub use withdraw::*;
+pub use withdraw_stake::*;
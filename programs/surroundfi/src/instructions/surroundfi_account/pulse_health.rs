@@ -3,6 +3,7 @@ use bytemuck::Zeroable;
 use solana_program::{clock::Clock, sysvar::Sysvar};
 
 use crate::{
+    events::{AccountEventHeader, LendingAccountPulseHealthEvent, EVENT_SCHEMA_VERSION},
     state::{
         health_cache::HealthCache,
         surroundfi_account::{SurroundfiAccount, RiskEngine},
@@ -24,7 +25,7 @@ pub fn lending_account_pulse_health<'info>(
         ctx.remaining_accounts,
         &mut Some(&mut health_cache),
     ) {
-        Ok(()) => {
+        Ok(_) => {
             health_cache.set_engine_ok(true);
         }
         Err(_) => {
@@ -34,6 +35,21 @@ pub fn lending_account_pulse_health<'info>(
 
     surroundfi_account.health_cache = health_cache;
 
+    // Permissionless, so there is no signer to record
+    emit!(LendingAccountPulseHealthEvent {
+        header: AccountEventHeader {
+            version: EVENT_SCHEMA_VERSION,
+            signer: None,
+            surroundfi_account: ctx.accounts.surroundfi_account.key(),
+            surroundfi_account_authority: surroundfi_account.authority,
+            surroundfi_group: surroundfi_account.group,
+        },
+        engine_ok: health_cache.is_engine_ok(),
+        healthy: health_cache.is_healthy(),
+        asset_value_maint: health_cache.asset_value_maint,
+        liability_value_maint: health_cache.liability_value_maint,
+    });
+
     Ok(())
 }
 
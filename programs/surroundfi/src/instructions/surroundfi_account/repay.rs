@@ -1,9 +1,13 @@
 use crate::{
     check,
-    events::{AccountEventHeader, LendingAccountRepayEvent},
+    events::{AccountEventHeader, LendingAccountRepayEvent, EVENT_SCHEMA_VERSION},
+    math_error,
     prelude::{SurroundfiError, SurroundfiGroup, SurroundfiResult},
     state::{
-        surroundfi_account::{BankAccountWrapper, SurroundfiAccount, ACCOUNT_DISABLED},
+        surroundfi_account::{
+            BankAccountWrapper, SurroundfiAccount, ACCOUNT_DISABLED, ACCOUNT_IN_FLASHLOAN,
+        },
+        surroundfi_account_extension::find_account_extension,
         surroundfi_group::Bank,
     },
     utils,
@@ -57,11 +61,34 @@ pub fn lending_account_repay<'info>(
         bank_loader.key(),
     )?;
 
+    let extension_loader = find_account_extension(&surroundfi_account, ctx.remaining_accounts)?;
+    let mut extension = extension_loader
+        .as_ref()
+        .map(|loader| loader.load_mut())
+        .transpose()?;
+
+    // Liabilities opened within this flashloan are charged the flashloan fee when repaid
+    let flashloan_volume_closed = if surroundfi_account.get_flag(ACCOUNT_IN_FLASHLOAN) {
+        surroundfi_account
+            .lending_account
+            .record_flashloan_balance_increase(
+                &bank_loader.key(),
+                &bank,
+                extension.as_deref(),
+                if repay_all { u64::MAX } else { amount },
+            )?
+    } else {
+        0
+    };
+
     let mut bank_account = BankAccountWrapper::find(
         &bank_loader.key(),
         &mut bank,
         &mut surroundfi_account.lending_account,
+        extension.as_deref_mut(),
     )?;
+    bank_account
+        .settle_emissions_streams(&surroundfi_account_loader.key(), ctx.remaining_accounts)?;
 
     let repay_amount_post_fee = if repay_all {
         bank_account.repay_all()?
@@ -71,20 +98,35 @@ pub fn lending_account_repay<'info>(
         amount
     };
 
-    let repay_amount_pre_fee = maybe_bank_mint
+    let flashloan_fee: u64 = if flashloan_volume_closed > 0 {
+        bank_account
+            .bank
+            .credit_flashloan_fee(I80F48::from_num(flashloan_volume_closed))?
+            .checked_ceil()
+            .ok_or_else(math_error!())?
+            .checked_to_num()
+            .ok_or_else(math_error!())?
+    } else {
+        0
+    };
+    let transfer_amount_post_fee = repay_amount_post_fee
+        .checked_add(flashloan_fee)
+        .ok_or_else(math_error!())?;
+
+    let transfer_amount_pre_fee = maybe_bank_mint
         .as_ref()
         .map(|mint| {
             utils::calculate_pre_fee_spl_deposit_amount(
                 mint.to_account_info(),
-                repay_amount_post_fee,
+                transfer_amount_post_fee,
                 clock.epoch,
             )
         })
         .transpose()?
-        .unwrap_or(repay_amount_post_fee);
+        .unwrap_or(transfer_amount_post_fee);
 
     bank_account.deposit_spl_transfer(
-        repay_amount_pre_fee,
+        transfer_amount_pre_fee,
         signer_token_account.to_account_info(),
         bank_liquidity_vault.to_account_info(),
         signer.to_account_info(),
@@ -95,6 +137,7 @@ pub fn lending_account_repay<'info>(
 
     emit!(LendingAccountRepayEvent {
         header: AccountEventHeader {
+            version: EVENT_SCHEMA_VERSION,
             signer: Some(ctx.accounts.authority.key()),
             surroundfi_account: surroundfi_account_loader.key(),
             surroundfi_account_authority: surroundfi_account.authority,
@@ -0,0 +1,272 @@
+use anchor_lang::{prelude::*, solana_program::program::set_return_data};
+use fixed::types::I80F48;
+
+use crate::{
+    check, math_error,
+    prelude::*,
+    state::{
+        surroundfi_account::{
+            BankAccountWrapper, RiskEngine, RiskRequirementType, SurroundfiAccount,
+            MAX_LENDING_ACCOUNT_BALANCES,
+        },
+        surroundfi_account_extension::find_account_extension,
+        surroundfi_group::{Bank, WrappedI80F48},
+    },
+    utils::validate_asset_tags,
+};
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SimulatedActionType {
+    Deposit,
+    Withdraw,
+    Borrow,
+    Repay,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SimulatedAction {
+    pub bank: Pubkey,
+    pub action_type: SimulatedActionType,
+    /// In native token, e.g. lamports for SOL
+    pub amount: u64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SimulatedBalanceHealth {
+    /// Weighted value in $ at the initial requirement: positive for assets, negative for
+    /// liabilities
+    pub init_value: WrappedI80F48,
+    /// Weighted value in $ at the maintenance requirement: positive for assets, negative for
+    /// liabilities
+    pub maint_value: WrappedI80F48,
+    /// In native token. For assets, the amount that can be withdrawn. For liabilities (or a
+    /// balance opened with a zero borrow), the amount that can be borrowed.
+    pub max_amount: u64,
+}
+
+/// Returned by `lending_account_simulate_health` via `set_return_data`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct SimulatedHealth {
+    pub init_asset_value: WrappedI80F48,
+    pub init_liability_value: WrappedI80F48,
+    pub maint_asset_value: WrappedI80F48,
+    pub maint_liability_value: WrappedI80F48,
+    /// In the order of the account's active balances after the actions, lending account first,
+    /// then extension. Capped at `MAX_LENDING_ACCOUNT_BALANCES` entries so the result fits in the
+    /// return data.
+    pub balances: Vec<SimulatedBalanceHealth>,
+}
+
+/// (Permissionless, read-only) Apply `actions` in order to an in-memory copy of the account and
+/// return the resulting health through `set_return_data`. Nothing is written, intended to be used
+/// with `simulateTransaction`.
+///
+/// * Banks are copied with interest accrued to now, then actions change the copies like their
+///   instruction would (deposit repays liabilities, borrow withdraws assets), so later actions and
+///   the returned health see the result of earlier ones. Nothing is transferred.
+/// * Asset tags, deposit and borrow limits and bank utilization are checked as in the
+///   instructions and fail the simulation. The account's health, risk tiers, operational states
+///   and the arena leverage cap are not: an unhealthy result is returned rather than rejected.
+/// * Any bank used in an action must be in remaining accounts. Since remaining accounts follow
+///   the balances, pass them for the balances as they will be AFTER the actions: a new position
+///   takes the first empty slot.
+/// * To get the max borrowable amount of a bank the account has no position in, pass a zero
+///   borrow for that bank.
+pub fn lending_account_simulate_health<'info>(
+    ctx: Context<'_, '_, 'info, 'info, LendingAccountSimulateHealth<'info>>,
+    actions: Vec<SimulatedAction>,
+) -> SurroundfiResult {
+    let clock = Clock::get()?;
+    let mut surroundfi_account = Box::new(*ctx.accounts.surroundfi_account.load()?);
+    let group = ctx.accounts.group.load()?;
+    let asset_tag_matrix = group.get_asset_tag_matrix();
+    let extension_loader = find_account_extension(&surroundfi_account, ctx.remaining_accounts)?;
+    let mut extension = extension_loader
+        .as_ref()
+        .map(|loader| loader.load().map(|extension| Box::new(*extension)))
+        .transpose()?;
+
+    // Stream rewards don't affect health, so the copy changes balances without settling them
+    let extension_balances = extension
+        .iter_mut()
+        .flat_map(|extension| extension.balances.iter_mut());
+    for balance in surroundfi_account
+        .lending_account
+        .balances
+        .iter_mut()
+        .chain(extension_balances)
+    {
+        balance.emissions_streams_enrolled = 0;
+    }
+
+    // Banks touched by earlier actions keep their simulated state
+    let mut banks: Vec<(Pubkey, Bank)> = Vec::new();
+
+    for action in actions.iter() {
+        let bank_index = match banks.iter().position(|(key, _)| key.eq(&action.bank)) {
+            Some(index) => index,
+            None => {
+                let bank = load_accrued_bank(
+                    &action.bank,
+                    &surroundfi_account.group,
+                    &group,
+                    ctx.remaining_accounts,
+                    clock.unix_timestamp,
+                )?;
+                banks.push((action.bank, bank));
+                banks.len() - 1
+            }
+        };
+        let bank = &mut banks[bank_index].1;
+        let amount = I80F48::from_num(action.amount);
+
+        match action.action_type {
+            SimulatedActionType::Deposit | SimulatedActionType::Borrow => {
+                validate_asset_tags(
+                    bank,
+                    &surroundfi_account,
+                    extension.as_deref(),
+                    &asset_tag_matrix,
+                )?;
+
+                let mut bank_account = BankAccountWrapper::find_or_create(
+                    &action.bank,
+                    bank,
+                    &mut surroundfi_account.lending_account,
+                    extension.as_deref_mut(),
+                )?;
+
+                if action.action_type == SimulatedActionType::Deposit {
+                    bank_account.deposit(amount)?;
+                } else {
+                    bank_account.borrow(amount)?;
+                }
+            }
+            SimulatedActionType::Withdraw | SimulatedActionType::Repay => {
+                let mut bank_account = BankAccountWrapper::find(
+                    &action.bank,
+                    bank,
+                    &mut surroundfi_account.lending_account,
+                    extension.as_deref_mut(),
+                )?;
+
+                if action.action_type == SimulatedActionType::Withdraw {
+                    bank_account.withdraw(amount)?;
+                } else {
+                    bank_account.repay(amount)?;
+                }
+            }
+        }
+    }
+
+    // Balances without actions are priced with interest accrued as well
+    let balance_bank_pks: Vec<Pubkey> = surroundfi_account
+        .lending_account
+        .get_active_balances_iter()
+        .chain(
+            extension
+                .iter()
+                .flat_map(|extension| extension.get_active_balances_iter()),
+        )
+        .map(|balance| balance.bank_pk)
+        .collect();
+    for bank_pk in balance_bank_pks {
+        if !banks.iter().any(|(key, _)| key.eq(&bank_pk)) {
+            let bank = load_accrued_bank(
+                &bank_pk,
+                &surroundfi_account.group,
+                &group,
+                ctx.remaining_accounts,
+                clock.unix_timestamp,
+            )?;
+            banks.push((bank_pk, bank));
+        }
+    }
+
+    let risk_engine = RiskEngine::new_with_extension(
+        &surroundfi_account,
+        extension.as_deref(),
+        ctx.remaining_accounts,
+    )?
+    .with_simulated_banks(&banks);
+
+    let (init_asset_value, init_liability_value) =
+        risk_engine.get_account_health_components(RiskRequirementType::Initial, &mut None)?;
+    let (maint_asset_value, maint_liability_value) =
+        risk_engine.get_account_health_components(RiskRequirementType::Maintenance, &mut None)?;
+
+    let init_values = risk_engine.get_balance_weighted_values(RiskRequirementType::Initial)?;
+    let maint_values = risk_engine.get_balance_weighted_values(RiskRequirementType::Maintenance)?;
+    let max_amounts = risk_engine.get_balance_max_amounts()?;
+
+    let balances = init_values
+        .into_iter()
+        .zip(maint_values)
+        .zip(max_amounts)
+        .take(MAX_LENDING_ACCOUNT_BALANCES)
+        .map(|((init_value, maint_value), max_amount)| {
+            let max_amount: u64 = max_amount
+                .checked_floor()
+                .ok_or_else(math_error!())?
+                .checked_to_num()
+                .ok_or_else(math_error!())?;
+
+            Ok(SimulatedBalanceHealth {
+                init_value: init_value.into(),
+                maint_value: maint_value.into(),
+                max_amount,
+            })
+        })
+        .collect::<SurroundfiResult<Vec<_>>>()?;
+
+    let simulated_health = SimulatedHealth {
+        init_asset_value: init_asset_value.into(),
+        init_liability_value: init_liability_value.into(),
+        maint_asset_value: maint_asset_value.into(),
+        maint_liability_value: maint_liability_value.into(),
+        balances,
+    };
+
+    set_return_data(
+        &simulated_health
+            .try_to_vec()
+            .map_err(|_| error!(SurroundfiError::InternalLogicError))?,
+    );
+
+    Ok(())
+}
+
+/// Copy of the bank in `remaining_ais`, with interest accrued to `current_timestamp`
+fn load_accrued_bank(
+    bank_pk: &Pubkey,
+    group_pk: &Pubkey,
+    group: &SurroundfiGroup,
+    remaining_ais: &[AccountInfo],
+    current_timestamp: i64,
+) -> SurroundfiResult<Bank> {
+    let bank_ai = remaining_ais
+        .iter()
+        .find(|ai| ai.key.eq(bank_pk))
+        .ok_or_else(|| error!(SurroundfiError::InvalidBankAccount))?;
+    let mut bank = *AccountLoader::<Bank>::try_from(bank_ai)?.load()?;
+    check!(bank.group == *group_pk, SurroundfiError::InvalidBankAccount);
+
+    bank.accrue_interest(
+        current_timestamp,
+        group,
+        #[cfg(not(feature = "client"))]
+        *bank_pk,
+    )?;
+
+    Ok(bank)
+}
+
+#[derive(Accounts)]
+pub struct LendingAccountSimulateHealth<'info> {
+    pub group: AccountLoader<'info, SurroundfiGroup>,
+
+    #[account(
+        has_one = group
+    )]
+    pub surroundfi_account: AccountLoader<'info, SurroundfiAccount>,
+}
@@ -1,17 +1,37 @@
-use crate::{prelude::*, state::surroundfi_account::SurroundfiAccount};
+use crate::{
+    events::{
+        AccountEventHeader, SurroundfiAccountTransferAccountAuthorityEvent, EVENT_SCHEMA_VERSION,
+    },
+    prelude::*,
+    state::surroundfi_account::SurroundfiAccount,
+};
 use anchor_lang::prelude::*;
 
 pub fn set_account_transfer_authority(
     ctx: Context<SurroundfiAccountSetAccountAuthority>,
 ) -> SurroundfiResult {
     // Ensure surroundfi_account is dropped out of scope to not exceed stack frame limits
-    {
+    let (old_account_authority, group) = {
         let mut surroundfi_account = ctx.accounts.surroundfi_account.load_mut()?;
+        let old_account_authority = surroundfi_account.authority;
         let new_account_authority = ctx.accounts.new_authority.key();
         surroundfi_account.set_new_account_authority_checked(new_account_authority)?;
-    }
-
-    // TODO: add back event (dropped for memory reasons)
+        (old_account_authority, surroundfi_account.group)
+    };
+
+    // Built on the heap, this frame is too tight for the event on the stack
+    let event = Box::new(SurroundfiAccountTransferAccountAuthorityEvent {
+        header: AccountEventHeader {
+            version: EVENT_SCHEMA_VERSION,
+            signer: Some(ctx.accounts.authority.key()),
+            surroundfi_account: ctx.accounts.surroundfi_account.key(),
+            surroundfi_account_authority: old_account_authority,
+            surroundfi_group: group,
+        },
+        old_account_authority,
+        new_account_authority: ctx.accounts.new_authority.key(),
+    });
+    emit!(*event);
 
     Ok(())
 }
@@ -1,11 +1,15 @@
 use crate::{
     bank_signer, check,
     constants::LIQUIDITY_VAULT_AUTHORITY_SEED,
-    events::{AccountEventHeader, LendingAccountWithdrawEvent},
+    events::{AccountEventHeader, LendingAccountWithdrawEvent, EVENT_SCHEMA_VERSION},
     prelude::*,
     state::{
         health_cache::HealthCache,
-        surroundfi_account::{BankAccountWrapper, SurroundfiAccount, RiskEngine, ACCOUNT_DISABLED},
+        surroundfi_account::{
+            BankAccountWrapper, SurroundfiAccount, RiskEngine, ACCOUNT_DISABLED,
+            ACCOUNT_FLASHLOAN_LEVERAGE_CAPPED, ACCOUNT_IN_FLASHLOAN,
+        },
+        surroundfi_account_extension::find_account_extension,
         surroundfi_group::{Bank, BankVaultType},
     },
     utils,
@@ -66,11 +70,22 @@ pub fn lending_account_withdraw<'info>(
 
         let liquidity_vault_authority_bump = bank.liquidity_vault_authority_bump;
 
+        // Note: the extension must be released before the risk engine loads it again below
+        let extension_loader =
+            find_account_extension(&surroundfi_account, ctx.remaining_accounts)?;
+        let mut extension = extension_loader
+            .as_ref()
+            .map(|loader| loader.load_mut())
+            .transpose()?;
+
         let mut bank_account = BankAccountWrapper::find(
             &bank_loader.key(),
             &mut bank,
             &mut surroundfi_account.lending_account,
+            extension.as_deref_mut(),
         )?;
+        bank_account
+            .settle_emissions_streams(&surroundfi_account_loader.key(), ctx.remaining_accounts)?;
 
         let amount_pre_fee = if withdraw_all {
             bank_account.withdraw_all()?
@@ -109,6 +124,7 @@ pub fn lending_account_withdraw<'info>(
 
         emit!(LendingAccountWithdrawEvent {
             header: AccountEventHeader {
+                version: EVENT_SCHEMA_VERSION,
                 signer: Some(ctx.accounts.authority.key()),
                 surroundfi_account: surroundfi_account_loader.key(),
                 surroundfi_account_authority: surroundfi_account.authority,
@@ -126,11 +142,22 @@ pub fn lending_account_withdraw<'info>(
 
     // Check account health, if below threshold fail transaction
     // Assuming `ctx.remaining_accounts` holds only oracle accounts
-    RiskEngine::check_account_init_health(
+    let risk_engine = RiskEngine::check_account_init_health(
         &surroundfi_account,
         ctx.remaining_accounts,
         &mut Some(&mut health_cache),
     )?;
+
+    // Arena groups can cap leverage beyond what the banks' weights allow
+    let max_leverage = surroundfi_group_loader.load()?.get_max_leverage();
+    if let (Some(risk_engine), Some(max_leverage)) = (risk_engine, max_leverage) {
+        risk_engine.check_account_leverage(max_leverage)?;
+    }
+    // Within a flashloan, the cap is checked when it ends
+    if max_leverage.is_some() && surroundfi_account.get_flag(ACCOUNT_IN_FLASHLOAN) {
+        surroundfi_account.set_flag(ACCOUNT_FLASHLOAN_LEVERAGE_CAPPED);
+    }
+
     health_cache.set_engine_ok(true);
     surroundfi_account.health_cache = health_cache;
 
@@ -0,0 +1,269 @@
+use crate::{
+    bank_signer, check,
+    constants::{LIQUIDITY_VAULT_AUTHORITY_SEED, NATIVE_STAKE_ID, SPL_SINGLE_POOL_ID},
+    events::{AccountEventHeader, LendingAccountWithdrawEvent, EVENT_SCHEMA_VERSION},
+    prelude::*,
+    state::{
+        health_cache::HealthCache,
+        surroundfi_account::{BankAccountWrapper, RiskEngine, SurroundfiAccount, ACCOUNT_DISABLED},
+        surroundfi_account_extension::find_account_extension,
+        surroundfi_group::{Bank, BankVaultType},
+    },
+    utils,
+};
+use anchor_lang::prelude::*;
+use anchor_spl::token::{approve, Approve, Token, TokenAccount};
+use bytemuck::Zeroable;
+use fixed::types::I80F48;
+use solana_program::{
+    clock::Clock,
+    instruction::{AccountMeta, Instruction},
+    program::invoke,
+    sysvar::{self, Sysvar},
+};
+
+/// spl-single-pool `SinglePoolInstruction::WithdrawStake`
+const SINGLE_POOL_WITHDRAW_STAKE_IX: u8 = 3;
+
+/// 1. Accrue interest
+/// 2. Find the user's existing bank account for the staked bank
+/// 3. Record asset decrease in the bank account
+/// 4. Approve the single pool to burn the withdrawn LST from the bank's liquidity vault
+/// 5. Withdraw from the single pool, which burns the LST and splits the matching stake into the
+///    user's stake account, with the signer as its stake and withdraw authority
+/// 6. Verify that the user account is in a healthy state
+///
+/// `stake_account` must be a rent-exempt, uninitialized account of the native stake program's
+/// size, owned by the native stake program (typically created earlier in the same transaction).
+///
+/// Will error if there is no existing asset <=> borrowing is not allowed.
+pub fn lending_account_withdraw_to_stake_account<'info>(
+    ctx: Context<'_, '_, 'info, 'info, LendingAccountWithdrawToStakeAccount<'info>>,
+    amount: u64,
+    withdraw_all: Option<bool>,
+) -> SurroundfiResult {
+    let LendingAccountWithdrawToStakeAccount {
+        surroundfi_account: surroundfi_account_loader,
+        authority: signer,
+        bank: bank_loader,
+        group: surroundfi_group_loader,
+        bank_liquidity_vault_authority,
+        liquidity_vault: bank_liquidity_vault,
+        stake_account,
+        stake_pool,
+        sol_pool,
+        lst_mint,
+        pool_stake_authority,
+        pool_mint_authority,
+        clock: clock_sysvar,
+        token_program,
+        stake_program,
+        ..
+    } = ctx.accounts;
+    let clock = Clock::get()?;
+
+    let withdraw_all = withdraw_all.unwrap_or(false);
+    let mut surroundfi_account = surroundfi_account_loader.load_mut()?;
+
+    check!(
+        !surroundfi_account.get_flag(ACCOUNT_DISABLED),
+        SurroundfiError::AccountDisabled
+    );
+
+    utils::validate_single_pool_accounts(
+        &*bank_loader.load()?,
+        stake_pool.key,
+        sol_pool.key,
+        pool_stake_authority.key,
+        pool_mint_authority.key,
+    )?;
+
+    bank_loader.load_mut()?.accrue_interest(
+        clock.unix_timestamp,
+        &*surroundfi_group_loader.load()?,
+        #[cfg(not(feature = "client"))]
+        bank_loader.key(),
+    )?;
+
+    {
+        let mut bank = bank_loader.load_mut()?;
+
+        let liquidity_vault_authority_bump = bank.liquidity_vault_authority_bump;
+
+        // Note: the extension must be released before the risk engine loads it again below
+        let extension_loader = find_account_extension(&surroundfi_account, ctx.remaining_accounts)?;
+        let mut extension = extension_loader
+            .as_ref()
+            .map(|loader| loader.load_mut())
+            .transpose()?;
+
+        let mut bank_account = BankAccountWrapper::find(
+            &bank_loader.key(),
+            &mut bank,
+            &mut surroundfi_account.lending_account,
+            extension.as_deref_mut(),
+        )?;
+        bank_account
+            .settle_emissions_streams(&surroundfi_account_loader.key(), ctx.remaining_accounts)?;
+
+        let withdraw_amount = if withdraw_all {
+            bank_account.withdraw_all()?
+        } else {
+            bank_account.withdraw(I80F48::from_num(amount))?;
+            amount
+        };
+
+        approve(
+            CpiContext::new_with_signer(
+                token_program.to_account_info(),
+                Approve {
+                    to: bank_liquidity_vault.to_account_info(),
+                    delegate: pool_mint_authority.to_account_info(),
+                    authority: bank_liquidity_vault_authority.to_account_info(),
+                },
+                bank_signer!(
+                    BankVaultType::Liquidity,
+                    bank_loader.key(),
+                    liquidity_vault_authority_bump
+                ),
+            ),
+            withdraw_amount,
+        )?;
+
+        let mut data = vec![SINGLE_POOL_WITHDRAW_STAKE_IX];
+        data.extend_from_slice(signer.key.as_ref());
+        data.extend_from_slice(&withdraw_amount.to_le_bytes());
+
+        invoke(
+            &Instruction {
+                program_id: SPL_SINGLE_POOL_ID,
+                accounts: vec![
+                    AccountMeta::new_readonly(stake_pool.key(), false),
+                    AccountMeta::new(sol_pool.key(), false),
+                    AccountMeta::new(lst_mint.key(), false),
+                    AccountMeta::new_readonly(pool_stake_authority.key(), false),
+                    AccountMeta::new_readonly(pool_mint_authority.key(), false),
+                    AccountMeta::new(stake_account.key(), false),
+                    AccountMeta::new(bank_liquidity_vault.key(), false),
+                    AccountMeta::new_readonly(clock_sysvar.key(), false),
+                    AccountMeta::new_readonly(token_program.key(), false),
+                    AccountMeta::new_readonly(stake_program.key(), false),
+                ],
+                data,
+            },
+            &[
+                stake_pool.to_account_info(),
+                sol_pool.to_account_info(),
+                lst_mint.to_account_info(),
+                pool_stake_authority.to_account_info(),
+                pool_mint_authority.to_account_info(),
+                stake_account.to_account_info(),
+                bank_liquidity_vault.to_account_info(),
+                clock_sysvar.to_account_info(),
+                token_program.to_account_info(),
+                stake_program.to_account_info(),
+            ],
+        )?;
+
+        emit!(LendingAccountWithdrawEvent {
+            header: AccountEventHeader {
+                version: EVENT_SCHEMA_VERSION,
+                signer: Some(signer.key()),
+                surroundfi_account: surroundfi_account_loader.key(),
+                surroundfi_account_authority: surroundfi_account.authority,
+                surroundfi_group: surroundfi_account.group,
+            },
+            bank: bank_loader.key(),
+            mint: bank.mint,
+            amount: withdraw_amount,
+            close_balance: withdraw_all,
+        });
+    }
+
+    let mut health_cache = HealthCache::zeroed();
+    health_cache.timestamp = clock.unix_timestamp;
+
+    // Check account health, if below threshold fail transaction
+    // Assuming `ctx.remaining_accounts` holds only oracle accounts
+    RiskEngine::check_account_init_health(
+        &surroundfi_account,
+        ctx.remaining_accounts,
+        &mut Some(&mut health_cache),
+    )?;
+    health_cache.set_engine_ok(true);
+    surroundfi_account.health_cache = health_cache;
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct LendingAccountWithdrawToStakeAccount<'info> {
+    pub group: AccountLoader<'info, SurroundfiGroup>,
+
+    #[account(
+        mut,
+        has_one = group,
+        has_one = authority
+    )]
+    pub surroundfi_account: AccountLoader<'info, SurroundfiAccount>,
+
+    /// Becomes the stake and withdraw authority of `stake_account`
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = group,
+        has_one = liquidity_vault
+    )]
+    pub bank: AccountLoader<'info, Bank>,
+
+    /// CHECK: Seed constraint check
+    #[account(
+        mut,
+        seeds = [
+            LIQUIDITY_VAULT_AUTHORITY_SEED.as_bytes(),
+            bank.key().as_ref(),
+        ],
+        bump = bank.load()?.liquidity_vault_authority_bump,
+    )]
+    pub bank_liquidity_vault_authority: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub liquidity_vault: Box<Account<'info, TokenAccount>>,
+
+    /// CHECK: Validated by the native stake program and the spl-single-pool program
+    #[account(mut)]
+    pub stake_account: AccountInfo<'info>,
+
+    /// CHECK: Validated against the bank's mint, see `utils::validate_single_pool_accounts`
+    pub stake_pool: AccountInfo<'info>,
+
+    /// CHECK: Validated using `stake_pool`
+    #[account(mut)]
+    pub sol_pool: AccountInfo<'info>,
+
+    /// CHECK: Must be the bank's mint (validated using `stake_pool`)
+    #[account(mut)]
+    pub lst_mint: AccountInfo<'info>,
+
+    /// CHECK: Validated using `stake_pool`
+    pub pool_stake_authority: AccountInfo<'info>,
+
+    /// CHECK: Validated using `stake_pool`
+    pub pool_mint_authority: AccountInfo<'info>,
+
+    /// CHECK: Sysvar address check
+    #[account(address = sysvar::clock::ID)]
+    pub clock: AccountInfo<'info>,
+
+    /// Note: spl-single-pool uses a classic Token, never Token22
+    pub token_program: Program<'info, Token>,
+
+    /// CHECK: Program address check
+    #[account(address = NATIVE_STAKE_ID)]
+    pub stake_program: AccountInfo<'info>,
+
+    /// CHECK: Program address check
+    #[account(address = SPL_SINGLE_POOL_ID)]
+    pub single_pool_program: AccountInfo<'info>,
+}
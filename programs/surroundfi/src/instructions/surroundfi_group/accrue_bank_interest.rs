@@ -1,15 +1,35 @@
 use crate::{
+    events::{GroupEventHeader, LendingPoolBankAccrueInterestEvent, EVENT_SCHEMA_VERSION},
     state::surroundfi_group::{Bank, SurroundfiGroup},
     SurroundfiResult,
 };
 use anchor_lang::prelude::*;
 
+/// `Bank::accrue_interest` emits the `LendingPoolBankAccrueInterestEvent`, except when the bank was
+/// already accrued at this timestamp, where an empty one is emitted here instead.
 pub fn lending_pool_accrue_bank_interest(
     ctx: Context<LendingPoolAccrueBankInterest>,
 ) -> SurroundfiResult {
     let clock = Clock::get()?;
     let mut bank = ctx.accounts.bank.load_mut()?;
 
+    if bank.last_update == clock.unix_timestamp {
+        emit!(LendingPoolBankAccrueInterestEvent {
+            header: GroupEventHeader {
+                version: EVENT_SCHEMA_VERSION,
+                surroundfi_group: ctx.accounts.group.key(),
+                signer: None
+            },
+            bank: ctx.accounts.bank.key(),
+            mint: bank.mint,
+            delta: 0,
+            fees_collected: 0.,
+            insurance_collected: 0.,
+        });
+
+        return Ok(());
+    }
+
     bank.accrue_interest(
         clock.unix_timestamp,
         &*ctx.accounts.group.load()?,
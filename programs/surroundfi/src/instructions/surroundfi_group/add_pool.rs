@@ -5,13 +5,13 @@ use crate::{
         INSURANCE_VAULT_AUTHORITY_SEED, INSURANCE_VAULT_SEED, LIQUIDITY_VAULT_AUTHORITY_SEED,
         LIQUIDITY_VAULT_SEED,
     },
-    events::{GroupEventHeader, LendingPoolBankCreateEvent},
+    events::{GroupEventHeader, LendingPoolBankCreateEvent, EVENT_SCHEMA_VERSION},
     log_pool_info,
     state::{
         fee_state::FeeState,
         surroundfi_group::{Bank, BankConfigCompact, SurroundfiGroup},
     },
-    SurroundfiError, SurroundfiResult,
+    utils, SurroundfiError, SurroundfiResult,
 };
 use anchor_lang::prelude::*;
 use anchor_spl::token_interface::*;
@@ -20,6 +20,10 @@ use anchor_spl::token_interface::*;
 ///
 /// Admin only
 ///
+/// Remaining accounts: for a Token-2022 mint with a transfer hook, the hook program and the
+/// mint's extra account metas account (see `utils::validate_transfer_hook`). For
+/// `OracleSetup::ReceiptToken`, the underlying bank (see `Bank::validate_receipt_mint`)
+///
 /// TODO: Allow for different oracle configurations
 pub fn lending_pool_add_bank(
     ctx: Context<LendingPoolAddBank>,
@@ -49,6 +53,9 @@ pub fn lending_pool_add_bank(
         bank_config.asset_tag != ASSET_TAG_STAKED,
         SurroundfiError::AddedStakedPoolManually
     );
+    utils::validate_transfer_hook(&bank_mint.to_account_info(), ctx.remaining_accounts)?;
+    let mint_extensions =
+        utils::get_mint_extensions(&bank_mint.to_account_info().try_borrow_data()?)?;
 
     let liquidity_vault_bump = ctx.bumps.liquidity_vault;
     let liquidity_vault_authority_bump: u8 = ctx.bumps.liquidity_vault_authority;
@@ -74,16 +81,21 @@ pub fn lending_pool_add_bank(
         fee_vault_authority_bump,
     );
 
+    bank.mint_extensions = mint_extensions;
+
     log_pool_info(&bank);
 
     let mut group = ctx.accounts.surroundfi_group.load_mut()?;
+    group.validate_mint_extensions(mint_extensions)?;
     group.add_bank()?;
 
     bank.config.validate()?;
     bank.config.validate_oracle_age()?;
+    bank.validate_receipt_mint(ctx.remaining_accounts)?;
 
     emit!(LendingPoolBankCreateEvent {
         header: GroupEventHeader {
+            version: EVENT_SCHEMA_VERSION,
             surroundfi_group: ctx.accounts.surroundfi_group.key(),
             signer: Some(*ctx.accounts.admin.key)
         },
@@ -36,4 +36,5 @@ pub fn log_pool_info(bank: &Bank) {
         conf.oracle_max_age as u8,
         bank.flags as u8
     );
+    msg!("mint extensions: {:#x}", bank.mint_extensions);
 }
@@ -7,7 +7,7 @@ use crate::{
         INSURANCE_VAULT_SEED, LIQUIDITY_VAULT_AUTHORITY_SEED, LIQUIDITY_VAULT_SEED,
         SPL_SINGLE_POOL_ID,
     },
-    events::{GroupEventHeader, LendingPoolBankCreateEvent},
+    events::{GroupEventHeader, LendingPoolBankCreateEvent, EVENT_SCHEMA_VERSION},
     log_pool_info,
     state::{
         surroundfi_group::{
@@ -61,11 +61,15 @@ pub fn lending_pool_add_bank_permissionless(
         ..Default::default()
     };
 
+    // Staked collateral can't be borrowed, the liability weights apply to stablecoin borrows
+    // against it instead (see `StakedSettings::stable_liability_weight_init`)
+    let (liability_weight_init, liability_weight_maint) = settings.get_stable_liability_weights();
+
     let default_config: BankConfigCompact = BankConfigCompact {
         asset_weight_init: settings.asset_weight_init,
         asset_weight_maint: settings.asset_weight_maint,
-        liability_weight_init: I80F48!(1.5).into(), // placeholder
-        liability_weight_maint: I80F48!(1.25).into(), // placeholder
+        liability_weight_init,
+        liability_weight_maint,
         deposit_limit: settings.deposit_limit,
         interest_rate_config: default_ir_config.into(), // placeholder
         operational_state: BankOperationalState::Operational,
@@ -112,7 +116,7 @@ pub fn lending_pool_add_bank_permissionless(
     // The mint (for supply) and stake pool (for sol balance) are recorded for price calculation
     bank.config.oracle_keys[1] = lst_mint;
     bank.config.oracle_keys[2] = sol_pool;
-    bank.config.validate_oracle_setup(
+    bank.validate_oracle_setup(
         ctx.remaining_accounts,
         Some(lst_mint),
         Some(stake_pool),
@@ -122,6 +126,7 @@ pub fn lending_pool_add_bank_permissionless(
 
     emit!(LendingPoolBankCreateEvent {
         header: GroupEventHeader {
+            version: EVENT_SCHEMA_VERSION,
             surroundfi_group: ctx.accounts.surroundfi_group.key(),
             signer: Some(group.admin)
         },
@@ -5,13 +5,13 @@ use crate::{
         INSURANCE_VAULT_AUTHORITY_SEED, INSURANCE_VAULT_SEED, LIQUIDITY_VAULT_AUTHORITY_SEED,
         LIQUIDITY_VAULT_SEED,
     },
-    events::{GroupEventHeader, LendingPoolBankCreateEvent},
+    events::{GroupEventHeader, LendingPoolBankCreateEvent, EVENT_SCHEMA_VERSION},
     log_pool_info,
     state::{
         fee_state::FeeState,
         surroundfi_group::{Bank, BankConfigCompact, SurroundfiGroup},
     },
-    SurroundfiError, SurroundfiResult,
+    utils, SurroundfiError, SurroundfiResult,
 };
 use anchor_lang::prelude::*;
 use anchor_spl::token_interface::*;
@@ -20,6 +20,8 @@ use anchor_spl::token_interface::*;
 /// This seed is used by the LendingPoolAddBankWithSeed.bank to generate a
 /// PDA account to sign for newly added bank transactions securely.
 /// The previous lending_pool_add_bank is preserved for backwards-compatibility.
+///
+/// Remaining accounts: same as lending_pool_add_bank
 pub fn lending_pool_add_bank_with_seed(
     ctx: Context<LendingPoolAddBankWithSeed>,
     bank_config: BankConfigCompact,
@@ -49,6 +51,9 @@ pub fn lending_pool_add_bank_with_seed(
         bank_config.asset_tag != ASSET_TAG_STAKED,
         SurroundfiError::AddedStakedPoolManually
     );
+    utils::validate_transfer_hook(&bank_mint.to_account_info(), ctx.remaining_accounts)?;
+    let mint_extensions =
+        utils::get_mint_extensions(&bank_mint.to_account_info().try_borrow_data()?)?;
 
     let liquidity_vault_bump = ctx.bumps.liquidity_vault;
     let liquidity_vault_authority_bump = ctx.bumps.liquidity_vault_authority;
@@ -74,16 +79,21 @@ pub fn lending_pool_add_bank_with_seed(
         fee_vault_authority_bump,
     );
 
+    bank.mint_extensions = mint_extensions;
+
     log_pool_info(&bank);
 
     let mut group = ctx.accounts.surroundfi_group.load_mut()?;
+    group.validate_mint_extensions(mint_extensions)?;
     group.add_bank()?;
 
     bank.config.validate()?;
     bank.config.validate_oracle_age()?;
+    bank.validate_receipt_mint(ctx.remaining_accounts)?;
 
     emit!(LendingPoolBankCreateEvent {
         header: GroupEventHeader {
+            version: EVENT_SCHEMA_VERSION,
             surroundfi_group: ctx.accounts.surroundfi_group.key(),
             signer: Some(*ctx.accounts.admin.key)
         },
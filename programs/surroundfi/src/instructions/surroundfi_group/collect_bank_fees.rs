@@ -1,5 +1,8 @@
 use crate::constants::{FEE_STATE_SEED, FEE_VAULT_AUTHORITY_SEED, INSURANCE_VAULT_AUTHORITY_SEED};
-use crate::events::{GroupEventHeader, LendingPoolBankCollectFeesEvent};
+use crate::events::{
+    GroupEventHeader, LendingPoolBankCollectFeesEvent, LendingPoolBankWithdrawFeesEvent,
+    LendingPoolBankWithdrawInsuranceEvent, EVENT_SCHEMA_VERSION,
+};
 use crate::state::fee_state::FeeState;
 use crate::{
     bank_signer,
@@ -51,6 +54,8 @@ pub fn lending_pool_collect_bank_fees<'info>(
 
     let mut available_liquidity = I80F48::from_num(liquidity_vault.amount);
 
+    let insurance_staker_fees = bank.credit_pending_insurance_stake_fees()?;
+
     let (insurance_fee_transfer_amount, new_outstanding_insurance_fees) = {
         let outstanding = I80F48::from(bank.collected_insurance_fees_outstanding);
         let transfer_amount = min(outstanding, available_liquidity).int();
@@ -64,6 +69,8 @@ pub fn lending_pool_collect_bank_fees<'info>(
     };
 
     bank.collected_insurance_fees_outstanding = new_outstanding_insurance_fees.into();
+    // All of the outstanding fees were just credited, what's left of them still is
+    bank.insurance_stake_fees_credited = new_outstanding_insurance_fees.into();
 
     available_liquidity = available_liquidity
         .checked_sub(insurance_fee_transfer_amount)
@@ -163,6 +170,7 @@ pub fn lending_pool_collect_bank_fees<'info>(
 
     emit!(LendingPoolBankCollectFeesEvent {
         header: GroupEventHeader {
+            version: EVENT_SCHEMA_VERSION,
             surroundfi_group: ctx.accounts.group.key(),
             signer: None
         },
@@ -170,6 +178,7 @@ pub fn lending_pool_collect_bank_fees<'info>(
         mint: liquidity_vault.mint,
         insurance_fees_collected: insurance_fee_transfer_amount.to_num::<f64>(),
         insurance_fees_outstanding: new_outstanding_insurance_fees.to_num::<f64>(),
+        insurance_staker_fees: insurance_staker_fees.into(),
         group_fees_collected: group_fee_transfer_amount.to_num::<f64>(),
         group_fees_outstanding: new_outstanding_group_fees.to_num::<f64>(),
     });
@@ -251,14 +260,20 @@ pub fn lending_pool_withdraw_fees<'info>(
     amount: u64,
 ) -> SurroundfiResult {
     let LendingPoolWithdrawFees {
+        group: surroundfi_group_loader,
         bank: bank_loader,
+        admin,
         fee_vault,
         fee_vault_authority,
         dst_token_account,
         token_program,
-        ..
     } = ctx.accounts;
 
+    check!(
+        !surroundfi_group_loader.load()?.fee_distribution_enabled(),
+        SurroundfiError::FeeDistributionEnabled
+    );
+
     let bank = bank_loader.load()?;
     let maybe_bank_mint =
         utils::maybe_take_bank_mint(&mut ctx.remaining_accounts, &bank, token_program.key)?;
@@ -278,6 +293,18 @@ pub fn lending_pool_withdraw_fees<'info>(
         ctx.remaining_accounts,
     )?;
 
+    emit!(LendingPoolBankWithdrawFeesEvent {
+        header: GroupEventHeader {
+            version: EVENT_SCHEMA_VERSION,
+            signer: Some(admin.key()),
+            surroundfi_group: surroundfi_group_loader.key(),
+        },
+        bank: bank_loader.key(),
+        mint: bank.mint,
+        destination_account: dst_token_account.key(),
+        amount,
+    });
+
     Ok(())
 }
 
@@ -328,18 +355,33 @@ pub fn lending_pool_withdraw_insurance<'info>(
     amount: u64,
 ) -> SurroundfiResult {
     let LendingPoolWithdrawInsurance {
+        group: surroundfi_group_loader,
         bank: bank_loader,
+        admin,
         insurance_vault,
         insurance_vault_authority,
         dst_token_account,
         token_program,
-        ..
     } = ctx.accounts;
 
     let bank = bank_loader.load()?;
     let maybe_bank_mint =
         utils::maybe_take_bank_mint(&mut ctx.remaining_accounts, &bank, token_program.key)?;
 
+    // Staked insurance belongs to stakers, only they can withdraw it
+    let staked_amount: u64 = bank
+        .get_insurance_staked_amount()?
+        .checked_ceil()
+        .ok_or_else(math_error!())?
+        .checked_to_num()
+        .ok_or_else(math_error!())?;
+    check!(
+        amount <= insurance_vault.amount.saturating_sub(staked_amount),
+        SurroundfiError::InsufficientInsuranceStake,
+        "Only {} of the insurance vault is not staked",
+        insurance_vault.amount.saturating_sub(staked_amount)
+    );
+
     bank.withdraw_spl_transfer(
         amount,
         insurance_vault.to_account_info(),
@@ -355,6 +397,18 @@ pub fn lending_pool_withdraw_insurance<'info>(
         ctx.remaining_accounts,
     )?;
 
+    emit!(LendingPoolBankWithdrawInsuranceEvent {
+        header: GroupEventHeader {
+            version: EVENT_SCHEMA_VERSION,
+            signer: Some(admin.key()),
+            surroundfi_group: surroundfi_group_loader.key(),
+        },
+        bank: bank_loader.key(),
+        mint: bank.mint,
+        destination_account: dst_token_account.key(),
+        amount,
+    });
+
     Ok(())
 }
 
@@ -372,7 +426,6 @@ pub struct LendingPoolWithdrawInsurance<'info> {
 
     pub admin: Signer<'info>,
 
-    /// CHECK: ⋐ ͡⋄ ω ͡⋄ ⋑
     #[account(
         mut,
         seeds = [
@@ -381,7 +434,7 @@ pub struct LendingPoolWithdrawInsurance<'info> {
         ],
         bump = bank.load()?.insurance_vault_bump
     )]
-    pub insurance_vault: AccountInfo<'info>,
+    pub insurance_vault: InterfaceAccount<'info, TokenAccount>,
 
     /// CHECK: ⋐ ͡⋄ ω ͡⋄ ⋑
     #[account(
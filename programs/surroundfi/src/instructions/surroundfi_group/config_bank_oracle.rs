@@ -1,5 +1,5 @@
-use crate::constants::FREEZE_SETTINGS;
-use crate::events::{GroupEventHeader, LendingPoolBankConfigureOracleEvent};
+use crate::constants::{FREEZE_SETTINGS, STAKED_OVERRIDE_ORACLE};
+use crate::events::{GroupEventHeader, LendingPoolBankConfigureOracleEvent, EVENT_SCHEMA_VERSION};
 use crate::state::price::OracleSetup;
 use crate::{
     state::surroundfi_group::{Bank, SurroundfiGroup},
@@ -23,6 +23,8 @@ pub fn lending_pool_configure_bank_oracle(
 
         bank.config.oracle_setup = setup_type;
         bank.config.oracle_keys[0] = oracle;
+        bank.config
+            .mark_staked_settings_overrides(STAKED_OVERRIDE_ORACLE);
 
         msg!(
             "setting oracle to type: {:?} key: {:?}",
@@ -30,11 +32,11 @@ pub fn lending_pool_configure_bank_oracle(
             bank.config.oracle_keys[0]
         );
 
-        bank.config
-            .validate_oracle_setup(ctx.remaining_accounts, None, None, None)?;
+        bank.validate_oracle_setup(ctx.remaining_accounts, None, None, None)?;
 
         emit!(LendingPoolBankConfigureOracleEvent {
             header: GroupEventHeader {
+                version: EVENT_SCHEMA_VERSION,
                 surroundfi_group: ctx.accounts.group.key(),
                 signer: Some(*ctx.accounts.admin.key)
             },
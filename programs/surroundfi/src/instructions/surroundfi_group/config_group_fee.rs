@@ -1,4 +1,9 @@
-use crate::{constants::FEE_STATE_SEED, state::fee_state::FeeState, SurroundfiGroup, SurroundfiResult};
+use crate::{
+    constants::FEE_STATE_SEED,
+    events::{GroupEventHeader, SurroundfiGroupConfigureFeeEvent, EVENT_SCHEMA_VERSION},
+    state::fee_state::FeeState,
+    SurroundfiGroup, SurroundfiResult,
+};
 use anchor_lang::prelude::*;
 
 #[derive(Accounts)]
@@ -30,5 +35,15 @@ pub fn config_group_fee(ctx: Context<ConfigGroupFee>, enable_program_fee: bool)
         flag_before
     );
 
+    emit!(SurroundfiGroupConfigureFeeEvent {
+        header: GroupEventHeader {
+            version: EVENT_SCHEMA_VERSION,
+            signer: Some(ctx.accounts.global_fee_admin.key()),
+            surroundfi_group: ctx.accounts.surroundfi_group.key(),
+        },
+        program_fee_enabled: enable_program_fee,
+        group_flags: surroundfi_group.group_flags,
+    });
+
     Ok(())
 }
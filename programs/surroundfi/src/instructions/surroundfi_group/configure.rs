@@ -1,12 +1,26 @@
 use crate::check;
-use crate::events::{GroupEventHeader, SurroundfiGroupConfigureEvent};
+use crate::events::{
+    AccountEventHeader, GroupEventHeader, SurroundfiAccountSetFlagEvent,
+    SurroundfiAccountUnsetFlagEvent, SurroundfiGroupConfigureArenaEvent,
+    SurroundfiGroupConfigureAssetTagMatrixEvent, SurroundfiGroupConfigureEvent,
+    SurroundfiGroupConfigureMintExtensionsEvent, EVENT_SCHEMA_VERSION,
+};
 use crate::prelude::SurroundfiError;
 use crate::state::surroundfi_account::{SurroundfiAccount, ACCOUNT_TRANSFER_AUTHORITY_ALLOWED};
-use crate::{state::surroundfi_group::SurroundfiGroup, SurroundfiResult};
+use crate::{
+    state::surroundfi_group::{ArenaConfig, AssetTagMatrix, GroupConfigOpt, SurroundfiGroup},
+    SurroundfiResult,
+};
 use anchor_lang::prelude::*;
 
 /// Configure margin group.
 ///
+/// `group_config.bankruptcy_threshold` is the asset value (in USD) below which an insolvent account
+/// can be handled as bankrupt, unchanged if `None`.
+///
+/// `staked_stable_borrow_enabled` sets `STAKED_STABLE_BORROW_ENABLED`, letting accounts with staked
+/// collateral borrow `ASSET_TAG_STABLE` assets, unchanged if `None`.
+///
 /// Note: not even the group admin can configure `PROGRAM_FEES_ENABLED`, only the program admin can
 /// with `configure_group_fee`
 ///
@@ -15,21 +29,31 @@ pub fn configure(
     ctx: Context<SurroundfiGroupConfigure>,
     new_admin: Pubkey,
     is_arena_group: bool,
+    group_config: GroupConfigOpt,
+    staked_stable_borrow_enabled: Option<bool>,
 ) -> SurroundfiResult {
     let surroundfi_group = &mut ctx.accounts.surroundfi_group.load_mut()?;
 
     surroundfi_group.update_admin(new_admin);
     surroundfi_group.set_arena_group(is_arena_group)?;
+    if let Some(bankruptcy_threshold) = group_config.bankruptcy_threshold {
+        surroundfi_group.set_bankruptcy_threshold(bankruptcy_threshold.into())?;
+    }
+    if let Some(staked_stable_borrow_enabled) = staked_stable_borrow_enabled {
+        surroundfi_group.set_staked_stable_borrow_enabled(staked_stable_borrow_enabled);
+    }
 
     msg!("flags set to: {:?}", surroundfi_group.group_flags);
 
     emit!(SurroundfiGroupConfigureEvent {
         header: GroupEventHeader {
+            version: EVENT_SCHEMA_VERSION,
             surroundfi_group: ctx.accounts.surroundfi_group.key(),
             signer: Some(*ctx.accounts.admin.key)
         },
         admin: new_admin,
-        flags: surroundfi_group.group_flags
+        flags: surroundfi_group.group_flags,
+        bankruptcy_threshold: surroundfi_group.get_bankruptcy_threshold().into(),
     });
 
     Ok(())
@@ -46,6 +70,122 @@ pub struct SurroundfiGroupConfigure<'info> {
     pub admin: Signer<'info>,
 }
 
+/// Replace the rules of which asset tags can be comingled in an account and liquidated against
+/// each other, see `AssetTagMatrix`. An all-zero matrix restores `AssetTagMatrix::DEFAULT`.
+///
+/// Note: existing accounts are not re-validated. Positions mixing tags the new matrix forbids can
+/// still be reduced, but not increased.
+///
+/// Admin only
+pub fn configure_asset_tag_matrix(
+    ctx: Context<SurroundfiGroupConfigureAssetTagMatrix>,
+    asset_tag_matrix: AssetTagMatrix,
+) -> SurroundfiResult {
+    asset_tag_matrix.validate()?;
+
+    let mut surroundfi_group = ctx.accounts.surroundfi_group.load_mut()?;
+    surroundfi_group.asset_tag_matrix = asset_tag_matrix;
+
+    let asset_tag_matrix = surroundfi_group.get_asset_tag_matrix();
+    emit!(SurroundfiGroupConfigureAssetTagMatrixEvent {
+        header: GroupEventHeader {
+            version: EVENT_SCHEMA_VERSION,
+            surroundfi_group: ctx.accounts.surroundfi_group.key(),
+            signer: Some(*ctx.accounts.admin.key)
+        },
+        coexist: asset_tag_matrix.coexist,
+        liquidation: asset_tag_matrix.liquidation,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct SurroundfiGroupConfigureAssetTagMatrix<'info> {
+    #[account(
+        mut,
+        has_one = admin
+    )]
+    pub surroundfi_group: AccountLoader<'info, SurroundfiGroup>,
+
+    pub admin: Signer<'info>,
+}
+
+/// Set the risk parameters of an arena group's token pair, see `ArenaConfig`. All zeroes
+/// restore the defaults.
+///
+/// Note: existing positions above a lowered max leverage can still be reduced, but not increased.
+///
+/// Admin only
+pub fn configure_arena(
+    ctx: Context<SurroundfiGroupConfigureArena>,
+    arena_config: ArenaConfig,
+) -> SurroundfiResult {
+    let mut surroundfi_group = ctx.accounts.surroundfi_group.load_mut()?;
+    surroundfi_group.set_arena_config(arena_config)?;
+
+    emit!(SurroundfiGroupConfigureArenaEvent {
+        header: GroupEventHeader {
+            version: EVENT_SCHEMA_VERSION,
+            surroundfi_group: ctx.accounts.surroundfi_group.key(),
+            signer: Some(*ctx.accounts.admin.key)
+        },
+        max_leverage: surroundfi_group
+            .get_max_leverage()
+            .unwrap_or_default()
+            .into(),
+        liquidation_fee: surroundfi_group.get_liquidator_fee().into(),
+        swap_program: surroundfi_group.arena_config.swap_program,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct SurroundfiGroupConfigureArena<'info> {
+    #[account(
+        mut,
+        has_one = admin
+    )]
+    pub surroundfi_group: AccountLoader<'info, SurroundfiGroup>,
+
+    pub admin: Signer<'info>,
+}
+
+/// Set which restricted Token-2022 extensions (`MINT_EXTENSIONS_RESTRICTED`) the mints of new
+/// banks may have. Existing banks are unaffected.
+///
+/// Admin only
+pub fn configure_mint_extensions(
+    ctx: Context<SurroundfiGroupConfigureMintExtensions>,
+    permitted_mint_extensions: u64,
+) -> SurroundfiResult {
+    let mut surroundfi_group = ctx.accounts.surroundfi_group.load_mut()?;
+    surroundfi_group.set_permitted_mint_extensions(permitted_mint_extensions)?;
+
+    emit!(SurroundfiGroupConfigureMintExtensionsEvent {
+        header: GroupEventHeader {
+            version: EVENT_SCHEMA_VERSION,
+            surroundfi_group: ctx.accounts.surroundfi_group.key(),
+            signer: Some(*ctx.accounts.admin.key)
+        },
+        permitted_mint_extensions,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct SurroundfiGroupConfigureMintExtensions<'info> {
+    #[account(
+        mut,
+        has_one = admin
+    )]
+    pub surroundfi_group: AccountLoader<'info, SurroundfiGroup>,
+
+    pub admin: Signer<'info>,
+}
+
 /// Only these flags can be configured
 ///
 /// Example:
@@ -71,6 +211,18 @@ pub fn set_account_flag(ctx: Context<SetAccountFlag>, flag: u64) -> SurroundfiRe
 
     surroundfi_account.set_flag(flag);
 
+    emit!(SurroundfiAccountSetFlagEvent {
+        header: AccountEventHeader {
+            version: EVENT_SCHEMA_VERSION,
+            signer: Some(ctx.accounts.admin.key()),
+            surroundfi_account: ctx.accounts.surroundfi_account.key(),
+            surroundfi_account_authority: surroundfi_account.authority,
+            surroundfi_group: surroundfi_account.group,
+        },
+        flag,
+        account_flags: surroundfi_account.account_flags,
+    });
+
     Ok(())
 }
 
@@ -97,6 +249,18 @@ pub fn unset_account_flag(ctx: Context<UnsetAccountFlag>, flag: u64) -> Surround
 
     surroundfi_account.unset_flag(flag);
 
+    emit!(SurroundfiAccountUnsetFlagEvent {
+        header: AccountEventHeader {
+            version: EVENT_SCHEMA_VERSION,
+            signer: Some(ctx.accounts.admin.key()),
+            surroundfi_account: ctx.accounts.surroundfi_account.key(),
+            surroundfi_account_authority: surroundfi_account.authority,
+            surroundfi_group: surroundfi_account.group,
+        },
+        flag,
+        account_flags: surroundfi_account.account_flags,
+    });
+
     Ok(())
 }
 
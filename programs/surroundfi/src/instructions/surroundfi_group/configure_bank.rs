@@ -1,6 +1,8 @@
 use crate::constants::{EMISSIONS_AUTH_SEED, EMISSIONS_TOKEN_ACCOUNT_SEED, FREEZE_SETTINGS};
 use crate::events::{
     GroupEventHeader, LendingPoolBankConfigureEvent, LendingPoolBankConfigureFrozenEvent,
+    LendingPoolBankReclaimEmissionsEvent, LendingPoolBankSetupEmissionsEvent,
+    LendingPoolBankUpdateEmissionsEvent, EVENT_SCHEMA_VERSION,
 };
 use crate::prelude::SurroundfiError;
 use crate::{check, math_error, utils};
@@ -9,7 +11,6 @@ use crate::{
     SurroundfiResult,
 };
 use anchor_lang::prelude::*;
-use anchor_spl::token_2022::{transfer_checked, TransferChecked};
 use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
 use fixed::types::I80F48;
 
@@ -27,6 +28,7 @@ pub fn lending_pool_configure_bank(
 
         emit!(LendingPoolBankConfigureFrozenEvent {
             header: GroupEventHeader {
+                version: EVENT_SCHEMA_VERSION,
                 surroundfi_group: ctx.accounts.group.key(),
                 signer: Some(*ctx.accounts.admin.key)
             },
@@ -45,6 +47,7 @@ pub fn lending_pool_configure_bank(
 
         emit!(LendingPoolBankConfigureEvent {
             header: GroupEventHeader {
+                version: EVENT_SCHEMA_VERSION,
                 surroundfi_group: ctx.accounts.group.key(),
                 signer: Some(*ctx.accounts.admin.key)
             },
@@ -74,11 +77,16 @@ pub struct LendingPoolConfigureBank<'info> {
     pub bank: AccountLoader<'info, Bank>,
 }
 
-pub fn lending_pool_setup_emissions(
-    ctx: Context<LendingPoolSetupEmissions>,
+/// Emissions accrue between `emissions_start_time` (None to start immediately) and
+/// `emissions_end_time` (None to run until `total_emissions` is exhausted). Emissions can be set
+/// up, and funded, ahead of their start.
+pub fn lending_pool_setup_emissions<'info>(
+    ctx: Context<'_, '_, 'info, 'info, LendingPoolSetupEmissions<'info>>,
     emissions_flags: u64,
     emissions_rate: u64,
     total_emissions: u64,
+    emissions_start_time: Option<i64>,
+    emissions_end_time: Option<i64>,
 ) -> SurroundfiResult {
     let mut bank = ctx.accounts.bank.load_mut()?;
 
@@ -89,17 +97,24 @@ pub fn lending_pool_setup_emissions(
 
     bank.emissions_mint = ctx.accounts.emissions_mint.key();
 
+    bank.update_emissions_index(Clock::get()?.unix_timestamp)?;
     bank.override_emissions_flag(emissions_flags);
 
     bank.emissions_rate = emissions_rate;
     bank.emissions_remaining = I80F48::from_num(total_emissions).into();
+    bank.set_emissions_schedule(
+        emissions_start_time.unwrap_or(0),
+        emissions_end_time.unwrap_or(0),
+    )?;
 
     msg!("init emissions with mint: {:?}", bank.emissions_mint,);
     msg!(
-        "flags: {:?} rate: {:?} total: {:?}",
+        "flags: {:?} rate: {:?} total: {:?} start: {:?} end: {:?}",
         emissions_flags,
         emissions_rate,
-        total_emissions
+        total_emissions,
+        bank.emissions_start_time,
+        bank.emissions_end_time
     );
 
     let initial_emissions_amount_pre_fee = utils::calculate_pre_fee_spl_deposit_amount(
@@ -108,20 +123,33 @@ pub fn lending_pool_setup_emissions(
         Clock::get()?.epoch,
     )?;
 
-    transfer_checked(
-        CpiContext::new(
-            ctx.accounts.token_program.to_account_info(),
-            TransferChecked {
-                from: ctx.accounts.emissions_funding_account.to_account_info(),
-                to: ctx.accounts.emissions_token_account.to_account_info(),
-                authority: ctx.accounts.admin.to_account_info(),
-                mint: ctx.accounts.emissions_mint.to_account_info(),
-            },
-        ),
+    utils::transfer_checked_with_hook(
         initial_emissions_amount_pre_fee,
-        ctx.accounts.emissions_mint.decimals,
+        ctx.accounts.emissions_funding_account.to_account_info(),
+        ctx.accounts.emissions_token_account.to_account_info(),
+        ctx.accounts.admin.to_account_info(),
+        &ctx.accounts.emissions_mint,
+        ctx.accounts.token_program.to_account_info(),
+        &[],
+        ctx.remaining_accounts,
     )?;
 
+    let event = Box::new(LendingPoolBankSetupEmissionsEvent {
+        header: GroupEventHeader {
+            version: EVENT_SCHEMA_VERSION,
+            signer: Some(ctx.accounts.admin.key()),
+            surroundfi_group: ctx.accounts.group.key(),
+        },
+        bank: ctx.accounts.bank.key(),
+        emissions_mint: bank.emissions_mint,
+        flags: emissions_flags,
+        rate: emissions_rate,
+        total_emissions,
+        start_time: bank.emissions_start_time,
+        end_time: bank.emissions_end_time,
+    });
+    emit!(*event);
+
     Ok(())
 }
 
@@ -179,11 +207,13 @@ pub struct LendingPoolSetupEmissions<'info> {
     pub system_program: Program<'info, System>,
 }
 
-pub fn lending_pool_update_emissions_parameters(
-    ctx: Context<LendingPoolUpdateEmissionsParameters>,
+pub fn lending_pool_update_emissions_parameters<'info>(
+    ctx: Context<'_, '_, 'info, 'info, LendingPoolUpdateEmissionsParameters<'info>>,
     emissions_flags: Option<u64>,
     emissions_rate: Option<u64>,
     additional_emissions: Option<u64>,
+    emissions_start_time: Option<i64>,
+    emissions_end_time: Option<i64>,
 ) -> SurroundfiResult {
     let mut bank = ctx.accounts.bank.load_mut()?;
 
@@ -197,6 +227,9 @@ pub fn lending_pool_update_emissions_parameters(
         SurroundfiError::EmissionsUpdateError
     );
 
+    // Emissions up to now accrue at the previous parameters
+    bank.update_emissions_index(Clock::get()?.unix_timestamp)?;
+
     if let Some(flags) = emissions_flags {
         msg!("Updating emissions flags to {:#010b}", flags);
         bank.flags = flags;
@@ -207,6 +240,17 @@ pub fn lending_pool_update_emissions_parameters(
         bank.emissions_rate = rate;
     }
 
+    if emissions_start_time.is_some() || emissions_end_time.is_some() {
+        let start_time = emissions_start_time.unwrap_or(bank.emissions_start_time);
+        let end_time = emissions_end_time.unwrap_or(bank.emissions_end_time);
+        msg!(
+            "Updating emissions schedule to start: {} end: {}",
+            start_time,
+            end_time
+        );
+        bank.set_emissions_schedule(start_time, end_time)?;
+    }
+
     if let Some(additional_emissions) = additional_emissions {
         bank.emissions_remaining = I80F48::from(bank.emissions_remaining)
             .checked_add(I80F48::from_num(additional_emissions))
@@ -225,21 +269,35 @@ pub fn lending_pool_update_emissions_parameters(
             Clock::get()?.epoch,
         )?;
 
-        transfer_checked(
-            CpiContext::new(
-                ctx.accounts.token_program.to_account_info(),
-                TransferChecked {
-                    from: ctx.accounts.emissions_funding_account.to_account_info(),
-                    to: ctx.accounts.emissions_token_account.to_account_info(),
-                    authority: ctx.accounts.admin.to_account_info(),
-                    mint: ctx.accounts.emissions_mint.to_account_info(),
-                },
-            ),
+        utils::transfer_checked_with_hook(
             additional_emissions_amount_pre_fee,
-            ctx.accounts.emissions_mint.decimals,
+            ctx.accounts.emissions_funding_account.to_account_info(),
+            ctx.accounts.emissions_token_account.to_account_info(),
+            ctx.accounts.admin.to_account_info(),
+            &ctx.accounts.emissions_mint,
+            ctx.accounts.token_program.to_account_info(),
+            &[],
+            ctx.remaining_accounts,
         )?;
     }
 
+    let event = Box::new(LendingPoolBankUpdateEmissionsEvent {
+        header: GroupEventHeader {
+            version: EVENT_SCHEMA_VERSION,
+            signer: Some(ctx.accounts.admin.key()),
+            surroundfi_group: ctx.accounts.group.key(),
+        },
+        bank: ctx.accounts.bank.key(),
+        emissions_mint: bank.emissions_mint,
+        flags: bank.flags,
+        rate: bank.emissions_rate,
+        additional_emissions: additional_emissions.unwrap_or(0),
+        emissions_remaining: bank.emissions_remaining,
+        start_time: bank.emissions_start_time,
+        end_time: bank.emissions_end_time,
+    });
+    emit!(*event);
+
     Ok(())
 }
 
@@ -279,3 +337,119 @@ pub struct LendingPoolUpdateEmissionsParameters<'info> {
 
     pub token_program: Interface<'info, TokenInterface>,
 }
+
+/// Transfer the emissions that will no longer accrue, once the emissions end time has passed,
+/// from the emissions vault to `destination_account`. Emissions already accrued to balances stay
+/// in the vault for users to withdraw.
+///
+/// Admin only
+pub fn lending_pool_reclaim_emissions<'info>(
+    ctx: Context<'_, '_, 'info, 'info, LendingPoolReclaimEmissions<'info>>,
+) -> SurroundfiResult {
+    let (amount, emissions_auth_bump) = {
+        let mut bank = ctx.accounts.bank.load_mut()?;
+        let current_timestamp = Clock::get()?.unix_timestamp;
+
+        check!(
+            bank.emissions_end_time != 0 && current_timestamp >= bank.emissions_end_time,
+            SurroundfiError::EmissionsNotEnded
+        );
+
+        // Accrue up to the end time first, so only what's left after it is reclaimed
+        bank.update_emissions_index(current_timestamp)?;
+
+        let emissions_remaining = I80F48::from(bank.emissions_remaining);
+        let amount = emissions_remaining
+            .checked_floor()
+            .ok_or_else(math_error!())?;
+        bank.emissions_remaining = emissions_remaining
+            .checked_sub(amount)
+            .ok_or_else(math_error!())?
+            .into();
+
+        let amount: u64 = amount.checked_to_num().ok_or_else(math_error!())?;
+
+        (amount, ctx.bumps.emissions_auth)
+    };
+
+    msg!("Reclaiming {} emissions", amount);
+
+    if amount > 0 {
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            EMISSIONS_AUTH_SEED.as_bytes(),
+            &ctx.accounts.bank.key().to_bytes(),
+            &ctx.accounts.emissions_mint.key().to_bytes(),
+            &[emissions_auth_bump],
+        ]];
+
+        utils::transfer_checked_with_hook(
+            amount,
+            ctx.accounts.emissions_vault.to_account_info(),
+            ctx.accounts.destination_account.to_account_info(),
+            ctx.accounts.emissions_auth.to_account_info(),
+            &ctx.accounts.emissions_mint,
+            ctx.accounts.token_program.to_account_info(),
+            signer_seeds,
+            ctx.remaining_accounts,
+        )?;
+    }
+
+    emit!(LendingPoolBankReclaimEmissionsEvent {
+        header: GroupEventHeader {
+            version: EVENT_SCHEMA_VERSION,
+            surroundfi_group: ctx.accounts.group.key(),
+            signer: Some(*ctx.accounts.admin.key)
+        },
+        bank: ctx.accounts.bank.key(),
+        emissions_mint: ctx.accounts.emissions_mint.key(),
+        amount,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct LendingPoolReclaimEmissions<'info> {
+    #[account(
+        has_one = admin
+    )]
+    pub group: AccountLoader<'info, SurroundfiGroup>,
+
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = group,
+        has_one = emissions_mint
+    )]
+    pub bank: AccountLoader<'info, Bank>,
+
+    pub emissions_mint: InterfaceAccount<'info, Mint>,
+
+    /// CHECK: Asserted by PDA constraints
+    #[account(
+        seeds = [
+            EMISSIONS_AUTH_SEED.as_bytes(),
+            bank.key().as_ref(),
+            emissions_mint.key().as_ref(),
+        ],
+        bump
+    )]
+    pub emissions_auth: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        seeds = [
+            EMISSIONS_TOKEN_ACCOUNT_SEED.as_bytes(),
+            bank.key().as_ref(),
+            emissions_mint.key().as_ref(),
+        ],
+        bump,
+    )]
+    pub emissions_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub destination_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
@@ -1,6 +1,7 @@
 // Global fee admin calls this to edit the fee rate or the fee wallet.
 
 use crate::constants::FEE_STATE_SEED;
+use crate::events::{FeeStateEditEvent, GroupEventHeader, EVENT_SCHEMA_VERSION};
 use crate::state::fee_state;
 use crate::state::surroundfi_group::WrappedI80F48;
 use anchor_lang::prelude::*;
@@ -31,6 +32,21 @@ pub fn edit_fee_state(
         rate
     );
 
+    emit!(FeeStateEditEvent {
+        header: GroupEventHeader {
+            version: EVENT_SCHEMA_VERSION,
+            signer: Some(ctx.accounts.global_fee_admin.key()),
+            // The fee state is program-wide rather than tied to a group
+            surroundfi_group: Pubkey::default(),
+        },
+        fee_state: ctx.accounts.fee_state.key(),
+        global_fee_admin: admin,
+        global_fee_wallet: fee_wallet,
+        bank_init_flat_sol_fee,
+        program_fee_fixed,
+        program_fee_rate,
+    });
+
     Ok(())
 }
 
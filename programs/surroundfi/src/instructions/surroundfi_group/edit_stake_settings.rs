@@ -1,4 +1,4 @@
-use crate::events::EditStakedSettingsEvent;
+use crate::events::{EditStakedSettingsEvent, GroupEventHeader, EVENT_SCHEMA_VERSION};
 // Used by the group admin to edit the default features of staked collateral banks. Remember to
 // propagate afterwards.
 use crate::state::surroundfi_group::{RiskTier, WrappedI80F48};
@@ -30,11 +30,24 @@ pub fn edit_staked_settings(
     );
     set_if_some!(staked_settings.oracle_max_age, settings.oracle_max_age);
     set_if_some!(staked_settings.risk_tier, settings.risk_tier);
+    set_if_some!(
+        staked_settings.stable_liability_weight_init,
+        settings.stable_liability_weight_init
+    );
+    set_if_some!(
+        staked_settings.stable_liability_weight_maint,
+        settings.stable_liability_weight_maint
+    );
 
     staked_settings.validate()?;
 
     emit!(EditStakedSettingsEvent {
-        group: ctx.accounts.surroundfi_group.key(),
+        header: GroupEventHeader {
+            version: EVENT_SCHEMA_VERSION,
+            signer: Some(ctx.accounts.admin.key()),
+            surroundfi_group: ctx.accounts.surroundfi_group.key(),
+        },
+        staked_settings: ctx.accounts.staked_settings.key(),
         settings
     });
 
@@ -72,4 +85,7 @@ pub struct StakedSettingsEditConfig {
     /// worthless as collateral, making all outstanding accounts eligible to be liquidated, and is
     /// generally useful only when creating a staked collateral pool for rewards purposes only.
     pub risk_tier: Option<RiskTier>,
+
+    pub stable_liability_weight_init: Option<WrappedI80F48>,
+    pub stable_liability_weight_maint: Option<WrappedI80F48>,
 }
@@ -0,0 +1,246 @@
+use crate::{
+    check,
+    constants::{EMISSIONS_PROGRAM_SEED, EMISSIONS_STREAM_VAULT_SEED},
+    events::{
+        GroupEventHeader, LendingPoolBankAddEmissionsStreamEvent,
+        LendingPoolBankFundEmissionsStreamEvent, LendingPoolBankInitEmissionsProgramEvent,
+        EVENT_SCHEMA_VERSION,
+    },
+    prelude::*,
+    state::{
+        emissions_program::{EmissionsProgram, EmissionsStream},
+        surroundfi_group::Bank,
+    },
+    utils,
+};
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+
+/// Create the emissions program of a bank, which holds up to `MAX_EMISSIONS_STREAMS` reward
+/// streams in addition to the bank's own emissions.
+///
+/// Admin only
+pub fn lending_pool_init_emissions_program(
+    ctx: Context<LendingPoolInitEmissionsProgram>,
+) -> SurroundfiResult {
+    let mut emissions_program = ctx.accounts.emissions_program.load_init()?;
+    emissions_program.initialize(ctx.accounts.bank.key(), ctx.bumps.emissions_program);
+
+    emit!(LendingPoolBankInitEmissionsProgramEvent {
+        header: GroupEventHeader {
+            version: EVENT_SCHEMA_VERSION,
+            signer: Some(ctx.accounts.admin.key()),
+            surroundfi_group: ctx.accounts.group.key(),
+        },
+        bank: ctx.accounts.bank.key(),
+        emissions_program: ctx.accounts.emissions_program.key(),
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct LendingPoolInitEmissionsProgram<'info> {
+    #[account(
+        has_one = admin
+    )]
+    pub group: AccountLoader<'info, SurroundfiGroup>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        has_one = group
+    )]
+    pub bank: AccountLoader<'info, Bank>,
+
+    #[account(
+        init,
+        seeds = [
+            EMISSIONS_PROGRAM_SEED.as_bytes(),
+            bank.key().as_ref(),
+        ],
+        bump,
+        payer = admin,
+        space = 8 + EmissionsProgram::LEN
+    )]
+    pub emissions_program: AccountLoader<'info, EmissionsProgram>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Add a reward stream paying `emissions_mint` to one side of the bank, between `start_time` and
+/// `end_time` (0 for no end). The stream starts empty, and only `funder` can fund it, see
+/// `lending_pool_fund_emissions_stream`.
+///
+/// Admin only
+pub fn lending_pool_add_emissions_stream(
+    ctx: Context<LendingPoolAddEmissionsStream>,
+    flags: u64,
+    rate: u64,
+    start_time: i64,
+    end_time: i64,
+) -> SurroundfiResult {
+    let mut emissions_program = ctx.accounts.emissions_program.load_mut()?;
+
+    let stream = EmissionsStream::new(
+        ctx.accounts.emissions_mint.key(),
+        ctx.accounts.funder.key(),
+        rate,
+        flags,
+        start_time,
+        end_time,
+        Clock::get()?.unix_timestamp,
+    )?;
+    let stream_id = emissions_program.add_stream(stream)?;
+
+    emit!(LendingPoolBankAddEmissionsStreamEvent {
+        header: GroupEventHeader {
+            version: EVENT_SCHEMA_VERSION,
+            surroundfi_group: ctx.accounts.group.key(),
+            signer: Some(*ctx.accounts.admin.key)
+        },
+        bank: ctx.accounts.bank.key(),
+        stream_id,
+        emissions_mint: stream.mint,
+        funder: stream.funder,
+        flags,
+        rate,
+        start_time,
+        end_time,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct LendingPoolAddEmissionsStream<'info> {
+    #[account(
+        has_one = admin
+    )]
+    pub group: AccountLoader<'info, SurroundfiGroup>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        has_one = group
+    )]
+    pub bank: AccountLoader<'info, Bank>,
+
+    #[account(
+        mut,
+        has_one = bank
+    )]
+    pub emissions_program: AccountLoader<'info, EmissionsProgram>,
+
+    pub emissions_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// CHECK: Any key, recorded as the only signer allowed to fund the stream
+    pub funder: AccountInfo<'info>,
+
+    #[account(
+        init,
+        payer = admin,
+        token::mint = emissions_mint,
+        token::authority = emissions_program,
+        seeds = [
+            EMISSIONS_STREAM_VAULT_SEED.as_bytes(),
+            bank.key().as_ref(),
+            emissions_mint.key().as_ref(),
+        ],
+        bump,
+    )]
+    pub emissions_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Transfer `amount` (net of any transfer fee) into the vault of a stream, making it available
+/// to accrue.
+///
+/// Stream funder only
+pub fn lending_pool_fund_emissions_stream<'info>(
+    ctx: Context<'_, '_, 'info, 'info, LendingPoolFundEmissionsStream<'info>>,
+    stream_id: u8,
+    amount: u64,
+) -> SurroundfiResult {
+    let mut emissions_program = ctx.accounts.emissions_program.load_mut()?;
+    let stream = emissions_program.get_stream_mut(stream_id)?;
+
+    check!(
+        stream.funder == ctx.accounts.funder.key(),
+        SurroundfiError::Unauthorized
+    );
+    check!(
+        stream.mint == ctx.accounts.emissions_mint.key(),
+        SurroundfiError::InvalidEmissionsStream
+    );
+
+    // Accrue at the old funding first, so the new funds don't pay for time already passed
+    stream.update_index(&ctx.accounts.bank.load()?, Clock::get()?.unix_timestamp)?;
+    stream.add_remaining(amount)?;
+
+    let amount_pre_fee = utils::calculate_pre_fee_spl_deposit_amount(
+        ctx.accounts.emissions_mint.to_account_info(),
+        amount,
+        Clock::get()?.epoch,
+    )?;
+
+    utils::transfer_checked_with_hook(
+        amount_pre_fee,
+        ctx.accounts.funding_account.to_account_info(),
+        ctx.accounts.emissions_vault.to_account_info(),
+        ctx.accounts.funder.to_account_info(),
+        &ctx.accounts.emissions_mint,
+        ctx.accounts.token_program.to_account_info(),
+        &[],
+        ctx.remaining_accounts,
+    )?;
+
+    emit!(LendingPoolBankFundEmissionsStreamEvent {
+        header: GroupEventHeader {
+            version: EVENT_SCHEMA_VERSION,
+            surroundfi_group: ctx.accounts.bank.load()?.group,
+            signer: Some(*ctx.accounts.funder.key)
+        },
+        bank: ctx.accounts.bank.key(),
+        stream_id,
+        amount,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct LendingPoolFundEmissionsStream<'info> {
+    pub bank: AccountLoader<'info, Bank>,
+
+    #[account(
+        mut,
+        has_one = bank
+    )]
+    pub emissions_program: AccountLoader<'info, EmissionsProgram>,
+
+    pub funder: Signer<'info>,
+
+    pub emissions_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(
+        mut,
+        seeds = [
+            EMISSIONS_STREAM_VAULT_SEED.as_bytes(),
+            bank.key().as_ref(),
+            emissions_mint.key().as_ref(),
+        ],
+        bump,
+    )]
+    pub emissions_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// CHECK: Token mint/authority are checked at transfer
+    #[account(mut)]
+    pub funding_account: AccountInfo<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
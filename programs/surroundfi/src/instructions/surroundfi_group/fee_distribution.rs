@@ -0,0 +1,268 @@
+use crate::{
+    bank_signer, check,
+    constants::{FEE_DISTRIBUTION_SEED, FEE_VAULT_AUTHORITY_SEED, FEE_VAULT_SEED},
+    events::{
+        GroupEventHeader, LendingPoolBankDistributeFeesEvent,
+        SurroundfiGroupConfigureFeeDistributionEvent, SurroundfiGroupInitFeeDistributionEvent,
+        EVENT_SCHEMA_VERSION,
+    },
+    prelude::*,
+    state::{
+        fee_distribution::{FeeDistribution, FeeRecipientConfig},
+        surroundfi_group::{Bank, BankVaultType},
+    },
+    utils,
+};
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::get_associated_token_address_with_program_id,
+    token_interface::{TokenAccount, TokenInterface},
+};
+
+/// Create the fee distribution of a group, splitting the fees of every bank among up to
+/// `MAX_FEE_RECIPIENTS` recipients. Shares are in basis points and must add up to 100%. From
+/// then on, the admin can no longer withdraw fees directly (see `FEE_DISTRIBUTION_ENABLED`).
+///
+/// Admin only
+pub fn surroundfi_group_init_fee_distribution(
+    ctx: Context<SurroundfiGroupInitFeeDistribution>,
+    recipients: Vec<FeeRecipientConfig>,
+) -> SurroundfiResult {
+    let mut fee_distribution = ctx.accounts.fee_distribution.load_init()?;
+    fee_distribution.initialize(ctx.accounts.group.key(), ctx.bumps.fee_distribution);
+    fee_distribution.set_recipients(&recipients)?;
+    ctx.accounts
+        .group
+        .load_mut()?
+        .set_fee_distribution_enabled();
+
+    emit!(SurroundfiGroupInitFeeDistributionEvent {
+        header: GroupEventHeader {
+            version: EVENT_SCHEMA_VERSION,
+            surroundfi_group: ctx.accounts.group.key(),
+            signer: Some(ctx.accounts.admin.key())
+        },
+        fee_distribution: ctx.accounts.fee_distribution.key(),
+        recipients: recipients
+            .iter()
+            .map(|recipient| recipient.wallet)
+            .collect(),
+        shares_bps: recipients
+            .iter()
+            .map(|recipient| recipient.share_bps)
+            .collect(),
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct SurroundfiGroupInitFeeDistribution<'info> {
+    #[account(
+        mut,
+        has_one = admin
+    )]
+    pub group: AccountLoader<'info, SurroundfiGroup>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        init,
+        seeds = [
+            FEE_DISTRIBUTION_SEED.as_bytes(),
+            group.key().as_ref(),
+        ],
+        bump,
+        payer = admin,
+        space = 8 + FeeDistribution::LEN
+    )]
+    pub fee_distribution: AccountLoader<'info, FeeDistribution>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Replace the recipients of the group's fee distribution. Fees already in the fee vaults are
+/// distributed with the new shares.
+///
+/// Admin only
+pub fn surroundfi_group_configure_fee_distribution(
+    ctx: Context<SurroundfiGroupConfigureFeeDistribution>,
+    recipients: Vec<FeeRecipientConfig>,
+) -> SurroundfiResult {
+    let mut fee_distribution = ctx.accounts.fee_distribution.load_mut()?;
+    fee_distribution.set_recipients(&recipients)?;
+
+    emit_configure_event(
+        ctx.accounts.group.key(),
+        ctx.accounts.admin.key(),
+        &recipients,
+    );
+
+    Ok(())
+}
+
+fn emit_configure_event(group: Pubkey, admin: Pubkey, recipients: &[FeeRecipientConfig]) {
+    emit!(SurroundfiGroupConfigureFeeDistributionEvent {
+        header: GroupEventHeader {
+            version: EVENT_SCHEMA_VERSION,
+            surroundfi_group: group,
+            signer: Some(admin)
+        },
+        recipients: recipients
+            .iter()
+            .map(|recipient| recipient.wallet)
+            .collect(),
+        shares_bps: recipients
+            .iter()
+            .map(|recipient| recipient.share_bps)
+            .collect(),
+    });
+}
+
+#[derive(Accounts)]
+pub struct SurroundfiGroupConfigureFeeDistribution<'info> {
+    #[account(
+        has_one = admin
+    )]
+    pub group: AccountLoader<'info, SurroundfiGroup>,
+
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = group
+    )]
+    pub fee_distribution: AccountLoader<'info, FeeDistribution>,
+}
+
+/// (Permissionless) Move the fee vault balance of a bank to the canonical ATAs of the group's fee
+/// recipients, according to their shares. Rounding dust stays in the vault until the next
+/// distribution, and so does the share of a recipient whose ATA doesn't exist, was closed or is
+/// frozen, which doesn't hold up the other recipients.
+///
+/// Remaining accounts: the bank mint (Token-2022 only), then the recipients' ATAs in the order of
+/// the fee distribution.
+pub fn lending_pool_distribute_fees<'info>(
+    mut ctx: Context<'_, '_, 'info, 'info, LendingPoolDistributeFees<'info>>,
+) -> SurroundfiResult {
+    let LendingPoolDistributeFees {
+        bank: bank_loader,
+        fee_distribution: fee_distribution_loader,
+        fee_vault,
+        fee_vault_authority,
+        token_program,
+        ..
+    } = ctx.accounts;
+
+    let bank = bank_loader.load()?;
+    let fee_distribution = fee_distribution_loader.load()?;
+    let maybe_bank_mint =
+        utils::maybe_take_bank_mint(&mut ctx.remaining_accounts, &bank, token_program.key)?;
+
+    let recipients: Vec<Pubkey> = fee_distribution
+        .active_recipients()
+        .map(|recipient| recipient.wallet)
+        .collect();
+    let mut amounts = fee_distribution.split(fee_vault.amount);
+
+    check!(
+        ctx.remaining_accounts.len() >= recipients.len(),
+        SurroundfiError::InvalidFeeDistribution,
+        "Expected {} recipient ATAs",
+        recipients.len()
+    );
+    let (recipient_atas, transfer_hook_ais) = ctx.remaining_accounts.split_at(recipients.len());
+
+    for ((wallet, amount), ata) in recipients
+        .iter()
+        .zip(amounts.iter_mut())
+        .zip(recipient_atas)
+    {
+        let ata_expected =
+            get_associated_token_address_with_program_id(wallet, &bank.mint, token_program.key);
+        check!(ata.key.eq(&ata_expected), SurroundfiError::InvalidFeeAta);
+
+        if *amount == 0 {
+            continue;
+        }
+        if !can_receive(ata, token_program.key) {
+            msg!("Skipping {}, its ATA can't receive fees", wallet);
+            *amount = 0;
+            continue;
+        }
+
+        bank.withdraw_spl_transfer(
+            *amount,
+            fee_vault.to_account_info(),
+            ata.to_account_info(),
+            fee_vault_authority.to_account_info(),
+            maybe_bank_mint.as_ref(),
+            token_program.to_account_info(),
+            bank_signer!(
+                BankVaultType::Fee,
+                bank_loader.key(),
+                bank.fee_vault_authority_bump
+            ),
+            transfer_hook_ais,
+        )?;
+    }
+
+    emit!(LendingPoolBankDistributeFeesEvent {
+        header: GroupEventHeader {
+            version: EVENT_SCHEMA_VERSION,
+            surroundfi_group: ctx.accounts.group.key(),
+            signer: None
+        },
+        bank: bank_loader.key(),
+        mint: bank.mint,
+        recipients,
+        amounts,
+    });
+
+    Ok(())
+}
+
+/// True if `ata` is an open, unfrozen token account, false if it was never created or was closed
+fn can_receive<'info>(ata: &'info AccountInfo<'info>, token_program: &Pubkey) -> bool {
+    ata.owner == token_program
+        && InterfaceAccount::<TokenAccount>::try_from(ata).is_ok_and(|ata| !ata.is_frozen())
+}
+
+#[derive(Accounts)]
+pub struct LendingPoolDistributeFees<'info> {
+    pub group: AccountLoader<'info, SurroundfiGroup>,
+
+    #[account(
+        has_one = group
+    )]
+    pub bank: AccountLoader<'info, Bank>,
+
+    #[account(
+        has_one = group
+    )]
+    pub fee_distribution: AccountLoader<'info, FeeDistribution>,
+
+    /// CHECK: ⋐ ͡⋄ ω ͡⋄ ⋑
+    #[account(
+        mut,
+        seeds = [
+            FEE_VAULT_SEED.as_bytes(),
+            bank.key().as_ref(),
+        ],
+        bump = bank.load()?.fee_vault_bump
+    )]
+    pub fee_vault: InterfaceAccount<'info, TokenAccount>,
+
+    /// CHECK: ⋐ ͡⋄ ω ͡⋄ ⋑
+    #[account(
+        seeds = [
+            FEE_VAULT_AUTHORITY_SEED.as_bytes(),
+            bank.key().as_ref(),
+        ],
+        bump = bank.load()?.fee_vault_authority_bump
+    )]
+    pub fee_vault_authority: AccountInfo<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
@@ -5,17 +5,20 @@ use crate::{
         PERMISSIONLESS_BAD_DEBT_SETTLEMENT_FLAG, ZERO_AMOUNT_THRESHOLD,
     },
     debug,
-    events::{AccountEventHeader, LendingPoolBankHandleBankruptcyEvent},
+    events::{AccountEventHeader, LendingPoolBankHandleBankruptcyEvent, EVENT_SCHEMA_VERSION},
     math_error,
     prelude::SurroundfiError,
     state::{
-        surroundfi_account::{BankAccountWrapper, SurroundfiAccount, RiskEngine, ACCOUNT_DISABLED},
+        surroundfi_account::{
+            BalanceSide, BankAccountWrapper, SurroundfiAccount, RiskEngine, ACCOUNT_DISABLED,
+        },
+        surroundfi_account_extension::find_account_extension,
         surroundfi_group::{Bank, BankVaultType, SurroundfiGroup},
     },
     utils, SurroundfiResult,
 };
 use anchor_lang::prelude::*;
-use anchor_spl::token_interface::{TokenAccount, TokenInterface};
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
 use fixed::types::I80F48;
 use std::cmp::{max, min};
 
@@ -23,7 +26,7 @@ use std::cmp::{max, min};
 /// 1. Verify account is bankrupt, and lending account belonging to account contains bad debt.
 /// 2. Determine the amount of bad debt covered by the insurance fund and the amount socialized between depositors.
 /// 3. Cover the bad debt of the bankrupt account.
-/// 4. Transfer the insured amount from the insurance fund.
+/// 4. Transfer the insured amount from the insurance fund, insurance stakers take the first loss.
 /// 5. Socialize the loss between lenders if any.
 pub fn lending_pool_handle_bankruptcy<'info>(
     mut ctx: Context<'_, '_, 'info, 'info, LendingPoolHandleBankruptcy<'info>>,
@@ -53,7 +56,8 @@ pub fn lending_pool_handle_bankruptcy<'info>(
 
     let mut surroundfi_account = surroundfi_account_loader.load_mut()?;
 
-    RiskEngine::new(&surroundfi_account, ctx.remaining_accounts)?.check_account_bankrupt()?;
+    RiskEngine::new(&surroundfi_account, ctx.remaining_accounts)?
+        .check_account_bankrupt(surroundfi_group_loader.load()?.get_bankruptcy_threshold())?;
 
     let mut bank = bank_loader.load_mut()?;
 
@@ -64,10 +68,15 @@ pub fn lending_pool_handle_bankruptcy<'info>(
         bank_loader.key(),
     )?;
 
+    let extension_loader = find_account_extension(&surroundfi_account, ctx.remaining_accounts)?;
+    let mut extension = extension_loader
+        .as_ref()
+        .map(|loader| loader.load_mut())
+        .transpose()?;
+
     let lending_account_balance = surroundfi_account
         .lending_account
-        .balances
-        .iter_mut()
+        .balances_iter_mut(extension.as_deref_mut())
         .find(|balance| balance.is_active() && balance.bank_pk == bank_loader.key());
 
     check!(
@@ -84,14 +93,75 @@ pub fn lending_pool_handle_bankruptcy<'info>(
         SurroundfiError::BalanceNotBadDebt
     );
 
+    let (covered_by_insurance, covered_by_stakers, socialized_loss) = cover_bad_debt(
+        &mut bank,
+        bank_loader.key(),
+        bad_debt,
+        insurance_vault,
+        ctx.accounts.insurance_vault_authority.to_account_info(),
+        ctx.accounts.liquidity_vault.to_account_info(),
+        maybe_bank_mint.as_ref(),
+        token_program.to_account_info(),
+        ctx.remaining_accounts,
+        clock.epoch,
+    )?;
+
+    // Settle bad debt.
+    // The liabilities of this account and global total liabilities are reduced by `bad_debt`
+    let mut bank_account = BankAccountWrapper::find_or_create(
+        &bank_loader.key(),
+        &mut bank,
+        &mut surroundfi_account.lending_account,
+        extension.as_deref_mut(),
+    )?;
+    bank_account
+        .settle_emissions_streams(&surroundfi_account_loader.key(), ctx.remaining_accounts)?;
+    bank_account.repay(bad_debt)?;
+
+    surroundfi_account.set_flag(ACCOUNT_DISABLED);
+
+    emit!(LendingPoolBankHandleBankruptcyEvent {
+        header: AccountEventHeader {
+            version: EVENT_SCHEMA_VERSION,
+            signer: Some(ctx.accounts.signer.key()),
+            surroundfi_account: surroundfi_account_loader.key(),
+            surroundfi_account_authority: surroundfi_account.authority,
+            surroundfi_group: surroundfi_account.group,
+        },
+        bank: bank_loader.key(),
+        mint: bank.mint,
+        bad_debt: bad_debt.to_num::<f64>(),
+        covered_amount: covered_by_insurance.to_num::<f64>(),
+        staker_covered_amount: covered_by_stakers.into(),
+        socialized_amount: socialized_loss.to_num::<f64>(),
+    });
+
+    Ok(())
+}
+
+/// Cover `bad_debt` of the bank with its insurance fund, insurance stakers first, and socialize the
+/// rest among depositors. Returns the amount covered by insurance, the part of the insurance
+/// vault's outflow taken from stakers, and the socialized loss.
+#[allow(clippy::too_many_arguments)]
+fn cover_bad_debt<'info>(
+    bank: &mut Bank,
+    bank_pk: Pubkey,
+    bad_debt: I80F48,
+    insurance_vault: &InterfaceAccount<'info, TokenAccount>,
+    insurance_vault_authority: AccountInfo<'info>,
+    liquidity_vault: AccountInfo<'info>,
+    maybe_bank_mint: Option<&InterfaceAccount<'info, Mint>>,
+    token_program: AccountInfo<'info>,
+    remaining_accounts: &[AccountInfo<'info>],
+    epoch: u64,
+) -> SurroundfiResult<(I80F48, I80F48, I80F48)> {
     let (covered_by_insurance, socialized_loss) = {
         let available_insurance_fund: I80F48 = maybe_bank_mint
-            .as_ref()
             .map(|mint| {
                 utils::calculate_post_fee_spl_deposit_amount(
                     mint.to_account_info(),
                     insurance_vault.amount,
-                    clock.epoch,
+                    epoch,
                 )
             })
             .transpose()?
@@ -116,61 +186,38 @@ pub fn lending_pool_handle_bankruptcy<'info>(
     );
 
     let insurance_coverage_deposit_pre_fee = maybe_bank_mint
-        .as_ref()
         .map(|mint| {
             utils::calculate_pre_fee_spl_deposit_amount(
                 mint.to_account_info(),
                 covered_by_insurance_rounded_up,
-                clock.epoch,
+                epoch,
             )
         })
         .transpose()?
         .unwrap_or(covered_by_insurance_rounded_up);
 
+    let covered_by_stakers =
+        bank.slash_insurance_stake(I80F48::from_num(insurance_coverage_deposit_pre_fee))?;
+
     bank.withdraw_spl_transfer(
         insurance_coverage_deposit_pre_fee,
-        ctx.accounts.insurance_vault.to_account_info(),
-        ctx.accounts.liquidity_vault.to_account_info(),
-        ctx.accounts.insurance_vault_authority.to_account_info(),
-        maybe_bank_mint.as_ref(),
-        token_program.to_account_info(),
+        insurance_vault.to_account_info(),
+        liquidity_vault,
+        insurance_vault_authority,
+        maybe_bank_mint,
+        token_program,
         bank_signer!(
             BankVaultType::Insurance,
-            bank_loader.key(),
+            bank_pk,
             bank.insurance_vault_authority_bump
         ),
-        ctx.remaining_accounts,
+        remaining_accounts,
     )?;
 
     // Socialize bad debt among depositors.
     bank.socialize_loss(socialized_loss)?;
 
-    // Settle bad debt.
-    // The liabilities of this account and global total liabilities are reduced by `bad_debt`
-    BankAccountWrapper::find_or_create(
-        &bank_loader.key(),
-        &mut bank,
-        &mut surroundfi_account.lending_account,
-    )?
-    .repay(bad_debt)?;
-
-    surroundfi_account.set_flag(ACCOUNT_DISABLED);
-
-    emit!(LendingPoolBankHandleBankruptcyEvent {
-        header: AccountEventHeader {
-            signer: Some(ctx.accounts.signer.key()),
-            surroundfi_account: surroundfi_account_loader.key(),
-            surroundfi_account_authority: surroundfi_account.authority,
-            surroundfi_group: surroundfi_account.group,
-        },
-        bank: bank_loader.key(),
-        mint: bank.mint,
-        bad_debt: bad_debt.to_num::<f64>(),
-        covered_amount: covered_by_insurance.to_num::<f64>(),
-        socialized_amount: socialized_loss.to_num::<f64>(),
-    });
-
-    Ok(())
+    Ok((covered_by_insurance, covered_by_stakers, socialized_loss))
 }
 
 #[derive(Accounts)]
@@ -226,3 +273,262 @@ pub struct LendingPoolHandleBankruptcy<'info> {
 
     pub token_program: Interface<'info, TokenInterface>,
 }
+
+/// Handle a bankrupt surroundfi account across all of its liabilities at once. The bad debt of each
+/// bank is resolved as in `lending_pool_handle_bankruptcy`, with one event per bank. The asset dust
+/// left on the account is forfeited to the insurance fees of its banks and the unclaimed emissions
+/// of every balance to the remaining emissions of its bank, so no balance is left open.
+///
+/// Remaining accounts, for each liability balance in the order of the account's balances: the
+/// bank, its liquidity vault, insurance vault, insurance vault authority, token program and mint
+/// (Token-2022 only). Then the observation accounts of the account, with the banks of asset
+/// balances writable.
+pub fn lending_pool_handle_account_bankruptcy<'info>(
+    ctx: Context<'_, '_, 'info, 'info, LendingPoolHandleAccountBankruptcy<'info>>,
+) -> SurroundfiResult {
+    let LendingPoolHandleAccountBankruptcy {
+        surroundfi_account: surroundfi_account_loader,
+        group: surroundfi_group_loader,
+        signer,
+    } = ctx.accounts;
+
+    let clock = Clock::get()?;
+
+    let (liability_bank_pks, asset_bank_pks) = {
+        let surroundfi_account = surroundfi_account_loader.load()?;
+        let extension_loader = find_account_extension(&surroundfi_account, ctx.remaining_accounts)?;
+        let extension = extension_loader
+            .as_ref()
+            .map(|loader| loader.load())
+            .transpose()?;
+
+        let bank_pks_with = |side| {
+            surroundfi_account
+                .lending_account
+                .balances_iter(extension.as_deref())
+                .filter(|balance| balance.is_active() && !balance.is_empty(side))
+                .map(|balance| balance.bank_pk)
+                .collect::<Vec<Pubkey>>()
+        };
+
+        (
+            bank_pks_with(BalanceSide::Liabilities),
+            bank_pks_with(BalanceSide::Assets),
+        )
+    };
+    check!(
+        !liability_bank_pks.is_empty(),
+        SurroundfiError::BalanceNotBadDebt
+    );
+
+    let mut remaining_accounts = ctx.remaining_accounts;
+    let mut bankrupt_banks = Vec::with_capacity(liability_bank_pks.len());
+    for bank_pk in liability_bank_pks.iter() {
+        bankrupt_banks.push(BankruptBankAccounts::take(
+            &mut remaining_accounts,
+            bank_pk,
+            &surroundfi_group_loader.key(),
+        )?);
+    }
+
+    let group_admin = surroundfi_group_loader.load()?.admin;
+    for bankrupt_bank in bankrupt_banks.iter() {
+        if !bankrupt_bank
+            .bank
+            .load()?
+            .get_flag(PERMISSIONLESS_BAD_DEBT_SETTLEMENT_FLAG)
+        {
+            check!(signer.key() == group_admin, SurroundfiError::Unauthorized);
+        }
+    }
+
+    let mut surroundfi_account = surroundfi_account_loader.load_mut()?;
+
+    RiskEngine::new(&surroundfi_account, remaining_accounts)?
+        .check_account_bankrupt(surroundfi_group_loader.load()?.get_bankruptcy_threshold())?;
+
+    let extension_loader = find_account_extension(&surroundfi_account, remaining_accounts)?;
+    let mut extension = extension_loader
+        .as_ref()
+        .map(|loader| loader.load_mut())
+        .transpose()?;
+
+    for bankrupt_bank in bankrupt_banks.iter() {
+        let bank_pk = bankrupt_bank.bank.key();
+        let mut bank = bankrupt_bank.bank.load_mut()?;
+
+        bank.accrue_interest(
+            clock.unix_timestamp,
+            &*surroundfi_group_loader.load()?,
+            #[cfg(not(feature = "client"))]
+            bank_pk,
+        )?;
+
+        let mut bank_account = BankAccountWrapper::find(
+            &bank_pk,
+            &mut bank,
+            &mut surroundfi_account.lending_account,
+            extension.as_deref_mut(),
+        )?;
+        bank_account
+            .settle_emissions_streams(&surroundfi_account_loader.key(), remaining_accounts)?;
+        let bad_debt = bank_account
+            .bank
+            .get_liability_amount(bank_account.balance.liability_shares.into())?;
+
+        let (covered_by_insurance, covered_by_stakers, socialized_loss) = cover_bad_debt(
+            bank_account.bank,
+            bank_pk,
+            bad_debt,
+            &bankrupt_bank.insurance_vault,
+            bankrupt_bank.insurance_vault_authority.clone(),
+            bankrupt_bank.liquidity_vault.clone(),
+            bankrupt_bank.maybe_bank_mint.as_ref(),
+            bankrupt_bank.token_program.to_account_info(),
+            remaining_accounts,
+            clock.epoch,
+        )?;
+
+        // Settle bad debt.
+        // The liabilities of this account and global total liabilities are reduced by `bad_debt`
+        bank_account.repay(bad_debt)?;
+
+        // The balance is settled, close it
+        let forfeited_emissions = bank_account.forfeit_emissions_in_bankruptcy()?;
+        bank_account.balance.close()?;
+        debug!(
+            "Forfeited {} of emissions in bank {}",
+            forfeited_emissions, bank_pk
+        );
+
+        emit!(LendingPoolBankHandleBankruptcyEvent {
+            header: AccountEventHeader {
+                version: EVENT_SCHEMA_VERSION,
+                signer: Some(signer.key()),
+                surroundfi_account: surroundfi_account_loader.key(),
+                surroundfi_account_authority: surroundfi_account.authority,
+                surroundfi_group: surroundfi_account.group,
+            },
+            bank: bank_pk,
+            mint: bank.mint,
+            bad_debt: bad_debt.to_num::<f64>(),
+            covered_amount: covered_by_insurance.to_num::<f64>(),
+            staker_covered_amount: covered_by_stakers.into(),
+            socialized_amount: socialized_loss.to_num::<f64>(),
+        });
+    }
+
+    // The remaining assets are worth less than the bankruptcy threshold, they go to the insurance
+    // fees of their banks
+    for bank_pk in asset_bank_pks.iter() {
+        let bank_ai = remaining_accounts
+            .iter()
+            .find(|ai| ai.key.eq(bank_pk))
+            .ok_or_else(|| error!(SurroundfiError::MissingBankAccount))?;
+        let bank_loader = AccountLoader::<Bank>::try_from(bank_ai)?;
+        let mut bank = bank_loader.load_mut()?;
+
+        bank.accrue_interest(
+            clock.unix_timestamp,
+            &*surroundfi_group_loader.load()?,
+            #[cfg(not(feature = "client"))]
+            *bank_pk,
+        )?;
+
+        let mut bank_account = BankAccountWrapper::find(
+            bank_pk,
+            &mut bank,
+            &mut surroundfi_account.lending_account,
+            extension.as_deref_mut(),
+        )?;
+        bank_account
+            .settle_emissions_streams(&surroundfi_account_loader.key(), remaining_accounts)?;
+        let forfeited = bank_account.forfeit_assets_in_bankruptcy()?;
+
+        debug!("Forfeited {} of assets in bank {}", forfeited, bank_pk);
+    }
+
+    surroundfi_account.set_flag(ACCOUNT_DISABLED);
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct LendingPoolHandleAccountBankruptcy<'info> {
+    pub group: AccountLoader<'info, SurroundfiGroup>,
+
+    /// CHECK: The admin signer constraint is only validated (in handler) if any of the banks
+    /// doesn't have the PERMISSIONLESS_BAD_DEBT_SETTLEMENT_FLAG set
+    pub signer: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = group
+    )]
+    pub surroundfi_account: AccountLoader<'info, SurroundfiAccount>,
+}
+
+/// The accounts of one bank in `lending_pool_handle_account_bankruptcy`
+struct BankruptBankAccounts<'info> {
+    bank: AccountLoader<'info, Bank>,
+    liquidity_vault: AccountInfo<'info>,
+    insurance_vault: InterfaceAccount<'info, TokenAccount>,
+    insurance_vault_authority: AccountInfo<'info>,
+    token_program: Interface<'info, TokenInterface>,
+    maybe_bank_mint: Option<InterfaceAccount<'info, Mint>>,
+}
+
+impl<'info> BankruptBankAccounts<'info> {
+    /// Take the accounts of the bank `bank_pk` off `remaining_accounts`, and validate them.
+    fn take(
+        remaining_accounts: &mut &'info [AccountInfo<'info>],
+        bank_pk: &Pubkey,
+        group_pk: &Pubkey,
+    ) -> SurroundfiResult<Self> {
+        let [bank_ai, liquidity_vault, insurance_vault, insurance_vault_authority, token_program, remaining @ ..] =
+            *remaining_accounts
+        else {
+            return err!(SurroundfiError::MissingBankAccount);
+        };
+        *remaining_accounts = remaining;
+
+        check!(
+            bank_ai.key.eq(bank_pk),
+            SurroundfiError::InvalidBankAccount,
+            "Expected bank {}",
+            bank_pk
+        );
+        let bank_loader = AccountLoader::<Bank>::try_from(bank_ai)?;
+        let bank = bank_loader.load()?;
+        check!(bank.group.eq(group_pk), SurroundfiError::InvalidBankAccount);
+        check!(
+            liquidity_vault.key.eq(&bank.liquidity_vault)
+                && insurance_vault.key.eq(&bank.insurance_vault),
+            SurroundfiError::InvalidBankAccount,
+            "Wrong vaults for bank {}",
+            bank_pk
+        );
+        check!(
+            insurance_vault_authority
+                .key
+                .eq(&utils::find_bank_vault_authority_pda(bank_pk, BankVaultType::Insurance).0),
+            SurroundfiError::InvalidBankAccount,
+            "Wrong insurance vault authority for bank {}",
+            bank_pk
+        );
+
+        let token_program = Interface::<TokenInterface>::try_from(token_program)?;
+        let maybe_bank_mint =
+            utils::maybe_take_bank_mint(remaining_accounts, &bank, token_program.key)?;
+        drop(bank);
+
+        Ok(Self {
+            bank: bank_loader,
+            liquidity_vault: liquidity_vault.clone(),
+            insurance_vault: InterfaceAccount::try_from(insurance_vault)?,
+            insurance_vault_authority: insurance_vault_authority.clone(),
+            token_program,
+            maybe_bank_mint,
+        })
+    }
+}
@@ -1,5 +1,6 @@
 // Runs once per program to init the global fee state.
 use crate::constants::FEE_STATE_SEED;
+use crate::events::{FeeStateInitEvent, GroupEventHeader, EVENT_SCHEMA_VERSION};
 use crate::state::fee_state;
 use crate::state::surroundfi_group::WrappedI80F48;
 use anchor_lang::prelude::*;
@@ -23,6 +24,21 @@ pub fn initialize_fee_state(
     fee_state.program_fee_fixed = program_fee_fixed;
     fee_state.program_fee_rate = program_fee_rate;
 
+    emit!(FeeStateInitEvent {
+        header: GroupEventHeader {
+            version: EVENT_SCHEMA_VERSION,
+            signer: Some(ctx.accounts.payer.key()),
+            // The fee state is program-wide rather than tied to a group
+            surroundfi_group: Pubkey::default(),
+        },
+        fee_state: ctx.accounts.fee_state.key(),
+        global_fee_admin: admin_key,
+        global_fee_wallet: fee_wallet,
+        bank_init_flat_sol_fee,
+        program_fee_fixed,
+        program_fee_rate,
+    });
+
     Ok(())
 }
 
@@ -1,5 +1,6 @@
 // Used by the group admin to enable staked collateral banks and configure their default features
 use crate::constants::STAKED_SETTINGS_SEED;
+use crate::events::{GroupEventHeader, InitStakedSettingsEvent, EVENT_SCHEMA_VERSION};
 use crate::state::surroundfi_group::{RiskTier, WrappedI80F48};
 use crate::state::staked_settings::StakedSettings;
 use crate::SurroundfiGroup;
@@ -21,6 +22,8 @@ pub fn initialize_staked_settings(
         settings.total_asset_value_init_limit,
         settings.oracle_max_age,
         settings.risk_tier,
+        settings.stable_liability_weight_init,
+        settings.stable_liability_weight_maint,
     );
 
     msg!(
@@ -40,6 +43,16 @@ pub fn initialize_staked_settings(
 
     staked_settings.validate()?;
 
+    emit!(InitStakedSettingsEvent {
+        header: GroupEventHeader {
+            version: EVENT_SCHEMA_VERSION,
+            signer: Some(ctx.accounts.admin.key()),
+            surroundfi_group: ctx.accounts.surroundfi_group.key(),
+        },
+        staked_settings: ctx.accounts.staked_settings.key(),
+        settings,
+    });
+
     Ok(())
 }
 
@@ -87,4 +100,9 @@ pub struct StakedSettingsConfig {
     /// worthless as collateral, and is generally useful only when creating a staked collateral pool
     /// for rewards purposes only.
     pub risk_tier: RiskTier,
+
+    /// Liability weights of stablecoin borrows against staked collateral, only used in groups with
+    /// `STAKED_STABLE_BORROW_ENABLED`. Zero uses the defaults (1.5 and 1.25).
+    pub stable_liability_weight_init: WrappedI80F48,
+    pub stable_liability_weight_maint: WrappedI80F48,
 }
@@ -1,5 +1,5 @@
 use crate::constants::FEE_STATE_SEED;
-use crate::events::{GroupEventHeader, SurroundfiGroupCreateEvent};
+use crate::events::{GroupEventHeader, SurroundfiGroupCreateEvent, EVENT_SCHEMA_VERSION};
 use crate::state::fee_state::FeeState;
 use crate::{state::surroundfi_group::SurroundfiGroup, SurroundfiResult};
 use anchor_lang::prelude::*;
@@ -36,6 +36,7 @@ pub fn initialize_group(
 
     emit!(SurroundfiGroupCreateEvent {
         header: GroupEventHeader {
+            version: EVENT_SCHEMA_VERSION,
             surroundfi_group: ctx.accounts.surroundfi_group.key(),
             signer: Some(*ctx.accounts.admin.key)
         },
@@ -0,0 +1,421 @@
+use crate::{
+    bank_signer, check,
+    constants::{INSURANCE_STAKE_SEED, INSURANCE_VAULT_AUTHORITY_SEED, INSURANCE_VAULT_SEED},
+    events::{
+        GroupEventHeader, LendingPoolBankConfigureInsuranceStakingEvent,
+        LendingPoolBankInitInsuranceStakeEvent, LendingPoolBankInsuranceRequestUnstakeEvent,
+        LendingPoolBankInsuranceStakeEvent, LendingPoolBankInsuranceUnstakeEvent,
+        EVENT_SCHEMA_VERSION,
+    },
+    math_error,
+    prelude::*,
+    state::{
+        insurance_stake::InsuranceStake,
+        surroundfi_group::{Bank, BankVaultType, WrappedI80F48},
+    },
+    utils,
+};
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{TokenAccount, TokenInterface};
+use fixed::types::I80F48;
+
+/// Enable staking into the insurance fund of a bank, or change its parameters. Stakers earn
+/// `fee_share` of the insurance fees paid into the insurance vault and take the first loss when
+/// the fund covers bad debt. Unstaking takes `cooldown` seconds, after which stakers have
+/// `unstake_window` seconds to withdraw before they must request the unstake again.
+///
+/// Staking can't be disabled once enabled, so stakers can always leave.
+///
+/// Admin only
+pub fn lending_pool_configure_insurance_staking(
+    ctx: Context<LendingPoolConfigureInsuranceStaking>,
+    fee_share: WrappedI80F48,
+    cooldown: i64,
+    unstake_window: i64,
+) -> SurroundfiResult {
+    let fee_share_value: I80F48 = fee_share.into();
+    check!(
+        fee_share_value >= I80F48::ZERO && fee_share_value <= I80F48::ONE,
+        SurroundfiError::InvalidConfig,
+        "Insurance stake fee share must be between 0 and 1"
+    );
+    check!(
+        cooldown > 0,
+        SurroundfiError::InvalidConfig,
+        "Insurance unstake cooldown must be positive"
+    );
+    check!(
+        unstake_window > 0,
+        SurroundfiError::InvalidConfig,
+        "Insurance unstake window must be positive"
+    );
+
+    let mut bank = ctx.accounts.bank.load_mut()?;
+    // Fees that accrued before the change are shared at the previous fee share
+    bank.credit_pending_insurance_stake_fees()?;
+    bank.insurance_stake_fee_share = fee_share;
+    bank.insurance_stake_cooldown = cooldown;
+    bank.insurance_stake_unstake_window = unstake_window;
+
+    emit!(LendingPoolBankConfigureInsuranceStakingEvent {
+        header: GroupEventHeader {
+            version: EVENT_SCHEMA_VERSION,
+            surroundfi_group: ctx.accounts.group.key(),
+            signer: Some(ctx.accounts.admin.key())
+        },
+        bank: ctx.accounts.bank.key(),
+        mint: bank.mint,
+        fee_share,
+        cooldown,
+        unstake_window,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct LendingPoolConfigureInsuranceStaking<'info> {
+    #[account(
+        has_one = admin
+    )]
+    pub group: AccountLoader<'info, SurroundfiGroup>,
+
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = group
+    )]
+    pub bank: AccountLoader<'info, Bank>,
+}
+
+/// Create the account holding a staker's position in the insurance fund of a bank.
+pub fn lending_pool_init_insurance_stake(
+    ctx: Context<LendingPoolInitInsuranceStake>,
+) -> SurroundfiResult {
+    let mut insurance_stake = ctx.accounts.insurance_stake.load_init()?;
+    insurance_stake.initialize(
+        ctx.accounts.bank.key(),
+        ctx.accounts.authority.key(),
+        ctx.bumps.insurance_stake,
+    );
+
+    emit!(LendingPoolBankInitInsuranceStakeEvent {
+        header: GroupEventHeader {
+            version: EVENT_SCHEMA_VERSION,
+            signer: Some(ctx.accounts.authority.key()),
+            surroundfi_group: ctx.accounts.bank.load()?.group,
+        },
+        bank: ctx.accounts.bank.key(),
+        insurance_stake: ctx.accounts.insurance_stake.key(),
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct LendingPoolInitInsuranceStake<'info> {
+    pub bank: AccountLoader<'info, Bank>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        init,
+        seeds = [
+            INSURANCE_STAKE_SEED.as_bytes(),
+            bank.key().as_ref(),
+            authority.key().as_ref(),
+        ],
+        bump,
+        payer = authority,
+        space = 8 + InsuranceStake::LEN
+    )]
+    pub insurance_stake: AccountLoader<'info, InsuranceStake>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Stake `amount` of the bank's mint into its insurance vault. For Token-2022 mints with a
+/// transfer fee, `amount` is what the vault receives, the fee is paid on top.
+///
+/// Accrues the bank's interest first, new shares only earn the insurance fees accrued after they
+/// are issued.
+///
+/// Remaining accounts: the bank mint (Token-2022 only)
+pub fn lending_pool_insurance_stake<'info>(
+    mut ctx: Context<'_, '_, 'info, 'info, LendingPoolInsuranceStake<'info>>,
+    amount: u64,
+) -> SurroundfiResult {
+    let LendingPoolInsuranceStake {
+        bank: bank_loader,
+        insurance_stake: insurance_stake_loader,
+        authority,
+        signer_token_account,
+        insurance_vault,
+        token_program,
+        ..
+    } = ctx.accounts;
+
+    let clock = Clock::get()?;
+    let mut bank = bank_loader.load_mut()?;
+    let maybe_bank_mint =
+        utils::maybe_take_bank_mint(&mut ctx.remaining_accounts, &bank, token_program.key)?;
+
+    // Fees that accrued before this stake belong to the shares already outstanding
+    bank.accrue_interest(
+        clock.unix_timestamp,
+        &*ctx.accounts.group.load()?,
+        #[cfg(not(feature = "client"))]
+        bank_loader.key(),
+    )?;
+    bank.credit_pending_insurance_stake_fees()?;
+
+    let shares = bank.insurance_stake(I80F48::from_num(amount))?;
+    insurance_stake_loader.load_mut()?.add_shares(shares)?;
+
+    let amount_pre_fee = maybe_bank_mint
+        .as_ref()
+        .map(|mint| {
+            utils::calculate_pre_fee_spl_deposit_amount(mint.to_account_info(), amount, clock.epoch)
+        })
+        .transpose()?
+        .unwrap_or(amount);
+
+    bank.deposit_spl_transfer(
+        amount_pre_fee,
+        signer_token_account.to_account_info(),
+        insurance_vault.to_account_info(),
+        authority.to_account_info(),
+        maybe_bank_mint.as_ref(),
+        token_program.to_account_info(),
+        ctx.remaining_accounts,
+    )?;
+
+    emit!(LendingPoolBankInsuranceStakeEvent {
+        header: GroupEventHeader {
+            version: EVENT_SCHEMA_VERSION,
+            surroundfi_group: ctx.accounts.group.key(),
+            signer: Some(authority.key())
+        },
+        bank: bank_loader.key(),
+        mint: bank.mint,
+        insurance_stake: insurance_stake_loader.key(),
+        amount,
+        shares: shares.into(),
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct LendingPoolInsuranceStake<'info> {
+    pub group: AccountLoader<'info, SurroundfiGroup>,
+
+    #[account(
+        mut,
+        has_one = group,
+        has_one = insurance_vault
+    )]
+    pub bank: AccountLoader<'info, Bank>,
+
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = bank,
+        has_one = authority
+    )]
+    pub insurance_stake: AccountLoader<'info, InsuranceStake>,
+
+    /// CHECK: Token mint/authority are checked at transfer
+    #[account(mut)]
+    pub signer_token_account: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub insurance_vault: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+/// Start the cooldown to unstake `amount` native tokens worth of shares, or all shares if
+/// `unstake_all`. Replaces any pending request and restarts the cooldown. Shares stop earning fees
+/// but can be slashed until they are unstaked.
+pub fn lending_pool_insurance_request_unstake(
+    ctx: Context<LendingPoolInsuranceRequestUnstake>,
+    amount: u64,
+    unstake_all: Option<bool>,
+) -> SurroundfiResult {
+    let clock = Clock::get()?;
+    let mut bank = ctx.accounts.bank.load_mut()?;
+    let mut insurance_stake = ctx.accounts.insurance_stake.load_mut()?;
+
+    bank.credit_pending_insurance_stake_fees()?;
+
+    // Fees earned by shares of a replaced request since it was made are forfeited
+    let pending_shares: I80F48 = insurance_stake.unstaking_shares.into();
+    if pending_shares > I80F48::ZERO {
+        let burnt_shares = bank.forfeit_insurance_unstake_gains(
+            pending_shares,
+            insurance_stake.unstaking_amount.into(),
+        )?;
+        insurance_stake.remove_shares(burnt_shares)?;
+    }
+
+    let share_value: I80F48 = bank.insurance_stake_share_value.into();
+    let shares: I80F48 = if unstake_all.unwrap_or(false) {
+        insurance_stake.shares.into()
+    } else {
+        I80F48::from_num(amount)
+            .checked_div(share_value)
+            .ok_or_else(math_error!())?
+    };
+    let unstaking_amount = shares.checked_mul(share_value).ok_or_else(math_error!())?;
+    insurance_stake.request_unstake(shares, unstaking_amount, clock.unix_timestamp)?;
+
+    emit!(LendingPoolBankInsuranceRequestUnstakeEvent {
+        header: GroupEventHeader {
+            version: EVENT_SCHEMA_VERSION,
+            surroundfi_group: bank.group,
+            signer: Some(ctx.accounts.authority.key())
+        },
+        bank: ctx.accounts.bank.key(),
+        mint: bank.mint,
+        insurance_stake: ctx.accounts.insurance_stake.key(),
+        shares: shares.into(),
+        unstakeable_at: clock
+            .unix_timestamp
+            .saturating_add(bank.insurance_stake_cooldown),
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct LendingPoolInsuranceRequestUnstake<'info> {
+    #[account(mut)]
+    pub bank: AccountLoader<'info, Bank>,
+
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = bank,
+        has_one = authority
+    )]
+    pub insurance_stake: AccountLoader<'info, InsuranceStake>,
+}
+
+/// Withdraw the shares requested to be unstaked from the insurance vault, once the cooldown has
+/// passed and until the unstake window closes. The shares are worth at most their value when the
+/// unstake was requested, rounded down.
+///
+/// Remaining accounts: the bank mint (Token-2022 only)
+pub fn lending_pool_insurance_unstake<'info>(
+    mut ctx: Context<'_, '_, 'info, 'info, LendingPoolInsuranceUnstake<'info>>,
+) -> SurroundfiResult {
+    let LendingPoolInsuranceUnstake {
+        bank: bank_loader,
+        insurance_stake: insurance_stake_loader,
+        authority,
+        insurance_vault,
+        insurance_vault_authority,
+        destination_token_account,
+        token_program,
+        ..
+    } = ctx.accounts;
+
+    let clock = Clock::get()?;
+    let mut bank = bank_loader.load_mut()?;
+    let maybe_bank_mint =
+        utils::maybe_take_bank_mint(&mut ctx.remaining_accounts, &bank, token_program.key)?;
+
+    bank.credit_pending_insurance_stake_fees()?;
+
+    let (shares, max_amount) = insurance_stake_loader.load_mut()?.take_unstaking_shares(
+        bank.insurance_stake_cooldown,
+        bank.insurance_stake_unstake_window,
+        clock.unix_timestamp,
+    )?;
+    let burnt_shares = bank.forfeit_insurance_unstake_gains(shares, max_amount)?;
+    let amount = bank
+        .insurance_unstake(shares.checked_sub(burnt_shares).ok_or_else(math_error!())?)?
+        .min(insurance_vault.amount);
+
+    if amount > 0 {
+        bank.withdraw_spl_transfer(
+            amount,
+            insurance_vault.to_account_info(),
+            destination_token_account.to_account_info(),
+            insurance_vault_authority.to_account_info(),
+            maybe_bank_mint.as_ref(),
+            token_program.to_account_info(),
+            bank_signer!(
+                BankVaultType::Insurance,
+                bank_loader.key(),
+                bank.insurance_vault_authority_bump
+            ),
+            ctx.remaining_accounts,
+        )?;
+    }
+
+    emit!(LendingPoolBankInsuranceUnstakeEvent {
+        header: GroupEventHeader {
+            version: EVENT_SCHEMA_VERSION,
+            surroundfi_group: ctx.accounts.group.key(),
+            signer: Some(authority.key())
+        },
+        bank: bank_loader.key(),
+        mint: bank.mint,
+        insurance_stake: insurance_stake_loader.key(),
+        shares: shares.into(),
+        amount,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct LendingPoolInsuranceUnstake<'info> {
+    pub group: AccountLoader<'info, SurroundfiGroup>,
+
+    #[account(
+        mut,
+        has_one = group
+    )]
+    pub bank: AccountLoader<'info, Bank>,
+
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = bank,
+        has_one = authority
+    )]
+    pub insurance_stake: AccountLoader<'info, InsuranceStake>,
+
+    #[account(
+        mut,
+        seeds = [
+            INSURANCE_VAULT_SEED.as_bytes(),
+            bank.key().as_ref(),
+        ],
+        bump = bank.load()?.insurance_vault_bump
+    )]
+    pub insurance_vault: InterfaceAccount<'info, TokenAccount>,
+
+    /// CHECK: Seed constraint
+    #[account(
+        seeds = [
+            INSURANCE_VAULT_AUTHORITY_SEED.as_bytes(),
+            bank.key().as_ref(),
+        ],
+        bump = bank.load()?.insurance_vault_authority_bump
+    )]
+    pub insurance_vault_authority: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub destination_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
@@ -10,12 +10,18 @@ pub mod configure;
 pub mod configure_bank;
 pub mod edit_global_fee;
 pub mod edit_stake_settings;
+pub mod emissions_program;
+pub mod fee_distribution;
 pub mod handle_bankruptcy;
 pub mod init_global_fee_state;
 pub mod init_staked_settings;
 pub mod initialize;
+pub mod insurance_staking;
 pub mod propagate_fee_state;
 pub mod propagate_staked_settings;
+pub mod receipt_tokens;
+pub mod referrer_fees;
+pub mod refresh_mint_extensions;
 
 pub use accrue_bank_interest::*;
 pub use add_pool::*;
@@ -29,9 +35,15 @@ pub use configure::*;
 pub use configure_bank::*;
 pub use edit_global_fee::*;
 pub use edit_stake_settings::*;
+pub use emissions_program::*;
+pub use fee_distribution::*;
 pub use handle_bankruptcy::*;
 pub use init_global_fee_state::*;
 pub use init_staked_settings::*;
 pub use initialize::*;
+pub use insurance_staking::*;
 pub use propagate_fee_state::*;
 pub use propagate_staked_settings::*;
+pub use receipt_tokens::*;
+pub use referrer_fees::*;
+pub use refresh_mint_extensions::*;
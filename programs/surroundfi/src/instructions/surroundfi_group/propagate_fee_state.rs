@@ -1,6 +1,11 @@
 use anchor_lang::prelude::*;
 
-use crate::{constants::FEE_STATE_SEED, state::fee_state::FeeState, SurroundfiGroup};
+use crate::{
+    constants::FEE_STATE_SEED,
+    events::{GroupEventHeader, SurroundfiGroupPropagateFeeEvent, EVENT_SCHEMA_VERSION},
+    state::fee_state::FeeState,
+    SurroundfiGroup,
+};
 
 #[derive(Accounts)]
 pub struct PropagateFee<'info> {
@@ -24,5 +29,17 @@ pub fn propagate_fee(ctx: Context<PropagateFee>) -> Result<()> {
     group.fee_state_cache.program_fee_fixed = fee_state.program_fee_fixed;
     group.fee_state_cache.program_fee_rate = fee_state.program_fee_rate;
 
+    emit!(SurroundfiGroupPropagateFeeEvent {
+        header: GroupEventHeader {
+            version: EVENT_SCHEMA_VERSION,
+            // Permissionless, so there is no signer to record
+            signer: None,
+            surroundfi_group: ctx.accounts.surroundfi_group.key(),
+        },
+        global_fee_wallet: fee_state.global_fee_wallet,
+        program_fee_fixed: fee_state.program_fee_fixed,
+        program_fee_rate: fee_state.program_fee_rate,
+    });
+
     Ok(())
 }
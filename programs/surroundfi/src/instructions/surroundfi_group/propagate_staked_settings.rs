@@ -1,36 +1,78 @@
-use crate::constants::ASSET_TAG_STAKED;
+use crate::constants::{
+    ASSET_TAG_STAKED, STAKED_OVERRIDES, STAKED_OVERRIDE_ASSET_WEIGHTS,
+    STAKED_OVERRIDE_DEPOSIT_LIMIT, STAKED_OVERRIDE_LIABILITY_WEIGHTS, STAKED_OVERRIDE_ORACLE,
+    STAKED_OVERRIDE_ORACLE_MAX_AGE, STAKED_OVERRIDE_RISK_TIER,
+    STAKED_OVERRIDE_TOTAL_ASSET_VALUE_INIT_LIMIT,
+};
+use crate::events::{
+    GroupEventHeader, LendingPoolBankClearStakedSettingsOverridesEvent,
+    LendingPoolBankPropagateStakedSettingsEvent, EVENT_SCHEMA_VERSION,
+};
 // Permissionless ix to propagate a group's staked collateral settings to any bank in that group
 use crate::state::surroundfi_group::Bank;
 use crate::state::staked_settings::StakedSettings;
-use crate::SurroundfiGroup;
+use crate::{check, SurroundfiError, SurroundfiGroup};
 use anchor_lang::prelude::*;
 
+/// Fields the admin configured on the bank itself (see `BankConfig::staked_settings_overrides`)
+/// are left as-is.
 pub fn propagate_staked_settings(ctx: Context<PropagateStakedSettings>) -> Result<()> {
     let settings = ctx.accounts.staked_settings.load()?;
     let mut bank = ctx.accounts.bank.load_mut()?;
 
-    let (oracle_before, oracle_after) = (bank.config.oracle_keys[0], settings.oracle);
-    let (age_before, age_after) = (bank.config.oracle_max_age, settings.oracle_max_age);
+    let oracle_before = bank.config.oracle_keys[0];
+    let age_before = bank.config.oracle_max_age;
+    let overrides = bank.config.staked_settings_overrides;
 
-    bank.config.oracle_keys[0] = settings.oracle;
-    bank.config.asset_weight_init = settings.asset_weight_init;
-    bank.config.asset_weight_maint = settings.asset_weight_maint;
-    bank.config.deposit_limit = settings.deposit_limit;
-    bank.config.total_asset_value_init_limit = settings.total_asset_value_init_limit;
-    bank.config.oracle_max_age = settings.oracle_max_age;
-    bank.config.risk_tier = settings.risk_tier;
+    let config = &mut bank.config;
+    if !config.is_staked_settings_override(STAKED_OVERRIDE_ORACLE) {
+        config.oracle_keys[0] = settings.oracle;
+    }
+    if !config.is_staked_settings_override(STAKED_OVERRIDE_ASSET_WEIGHTS) {
+        config.asset_weight_init = settings.asset_weight_init;
+        config.asset_weight_maint = settings.asset_weight_maint;
+    }
+    if !config.is_staked_settings_override(STAKED_OVERRIDE_DEPOSIT_LIMIT) {
+        config.deposit_limit = settings.deposit_limit;
+    }
+    if !config.is_staked_settings_override(STAKED_OVERRIDE_TOTAL_ASSET_VALUE_INIT_LIMIT) {
+        config.total_asset_value_init_limit = settings.total_asset_value_init_limit;
+    }
+    if !config.is_staked_settings_override(STAKED_OVERRIDE_ORACLE_MAX_AGE) {
+        config.oracle_max_age = settings.oracle_max_age;
+    }
+    if !config.is_staked_settings_override(STAKED_OVERRIDE_RISK_TIER) {
+        config.risk_tier = settings.risk_tier;
+    }
+    if !config.is_staked_settings_override(STAKED_OVERRIDE_LIABILITY_WEIGHTS) {
+        let (liability_weight_init, liability_weight_maint) =
+            settings.get_stable_liability_weights();
+        config.liability_weight_init = liability_weight_init;
+        config.liability_weight_maint = liability_weight_maint;
+    }
 
     // Only validate the oracle info if it has changed
-    if oracle_before != oracle_after {
-        bank.config
-            .validate_oracle_setup(ctx.remaining_accounts, None, None, None)?;
+    if oracle_before != bank.config.oracle_keys[0] {
+        bank.validate_oracle_setup(ctx.remaining_accounts, None, None, None)?;
     }
-    if age_before != age_after {
+    if age_before != bank.config.oracle_max_age {
         bank.config.validate_oracle_age()?;
     }
 
     bank.config.validate()?;
 
+    emit!(LendingPoolBankPropagateStakedSettingsEvent {
+        header: GroupEventHeader {
+            version: EVENT_SCHEMA_VERSION,
+            // Permissionless, so there is no signer to record
+            signer: None,
+            surroundfi_group: ctx.accounts.surroundfi_group.key(),
+        },
+        bank: ctx.accounts.bank.key(),
+        staked_settings: ctx.accounts.staked_settings.key(),
+        overrides,
+    });
+
     Ok(())
 }
 
@@ -53,3 +95,55 @@ pub struct PropagateStakedSettings<'info> {
     )]
     pub bank: AccountLoader<'info, Bank>,
 }
+
+/// Hand the given fields (`STAKED_OVERRIDE_*` flags) of a staked collateral bank back to the
+/// group's `StakedSettings`. Their values are restored by the next `propagate_staked_settings`.
+///
+/// Admin only
+pub fn lending_pool_clear_staked_settings_overrides(
+    ctx: Context<LendingPoolClearStakedSettingsOverrides>,
+    overrides: u8,
+) -> Result<()> {
+    check!(
+        overrides & !STAKED_OVERRIDES == 0,
+        SurroundfiError::InvalidConfig
+    );
+
+    let mut bank = ctx.accounts.bank.load_mut()?;
+    bank.config.staked_settings_overrides &= !overrides;
+
+    msg!(
+        "staked settings overrides left: {:#09b}",
+        bank.config.staked_settings_overrides
+    );
+
+    emit!(LendingPoolBankClearStakedSettingsOverridesEvent {
+        header: GroupEventHeader {
+            version: EVENT_SCHEMA_VERSION,
+            surroundfi_group: ctx.accounts.group.key(),
+            signer: Some(*ctx.accounts.admin.key)
+        },
+        bank: ctx.accounts.bank.key(),
+        cleared: overrides,
+        overrides: bank.config.staked_settings_overrides,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct LendingPoolClearStakedSettingsOverrides<'info> {
+    #[account(
+        has_one = admin,
+    )]
+    pub group: AccountLoader<'info, SurroundfiGroup>,
+
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = group,
+        constraint = bank.load()?.config.asset_tag == ASSET_TAG_STAKED
+    )]
+    pub bank: AccountLoader<'info, Bank>,
+}
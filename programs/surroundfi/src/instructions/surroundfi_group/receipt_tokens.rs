@@ -0,0 +1,376 @@
+use crate::{
+    bank_signer, check,
+    constants::{LIQUIDITY_VAULT_AUTHORITY_SEED, RECEIPT_MINT_SEED},
+    events::{
+        GroupEventHeader, LendingPoolBankInitReceiptMintEvent, LendingPoolBankReceiptDepositEvent,
+        LendingPoolBankReceiptRedeemEvent, EVENT_SCHEMA_VERSION,
+    },
+    math_error,
+    prelude::*,
+    state::surroundfi_group::{Bank, BankVaultType},
+    utils,
+};
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{
+    burn, mint_to, Burn, Mint, MintTo, TokenAccount, TokenInterface,
+};
+use fixed::types::I80F48;
+
+/// Create the receipt token mint of a bank, opting the bank into tokenized deposits. One receipt
+/// token is one asset share of the bank, see `lending_pool_deposit_for_receipt`.
+///
+/// The mint uses the bank's token program and decimals, and the liquidity vault authority as its
+/// mint authority.
+///
+/// Admin only
+pub fn lending_pool_init_receipt_mint(ctx: Context<LendingPoolInitReceiptMint>) -> SurroundfiResult {
+    let mut bank = ctx.accounts.bank.load_mut()?;
+
+    check!(
+        !bank.has_receipt_mint(),
+        SurroundfiError::IllegalAction,
+        "Receipt mint already exists"
+    );
+
+    bank.receipt_mint = ctx.accounts.receipt_mint.key();
+    bank.receipt_mint_bump = ctx.bumps.receipt_mint;
+
+    emit!(LendingPoolBankInitReceiptMintEvent {
+        header: GroupEventHeader {
+            version: EVENT_SCHEMA_VERSION,
+            surroundfi_group: ctx.accounts.group.key(),
+            signer: Some(*ctx.accounts.admin.key)
+        },
+        bank: ctx.accounts.bank.key(),
+        receipt_mint: bank.receipt_mint,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct LendingPoolInitReceiptMint<'info> {
+    #[account(
+        has_one = admin
+    )]
+    pub group: AccountLoader<'info, SurroundfiGroup>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = group,
+        has_one = mint
+    )]
+    pub bank: AccountLoader<'info, Bank>,
+
+    pub mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// CHECK: Seed constraint check
+    #[account(
+        seeds = [
+            LIQUIDITY_VAULT_AUTHORITY_SEED.as_bytes(),
+            bank.key().as_ref(),
+        ],
+        bump = bank.load()?.liquidity_vault_authority_bump,
+    )]
+    pub liquidity_vault_authority: AccountInfo<'info>,
+
+    #[account(
+        init,
+        payer = admin,
+        mint::decimals = mint.decimals,
+        mint::authority = liquidity_vault_authority,
+        mint::token_program = token_program,
+        seeds = [
+            RECEIPT_MINT_SEED.as_bytes(),
+            bank.key().as_ref(),
+        ],
+        bump,
+    )]
+    pub receipt_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+/// 1. Accrue interest
+/// 2. Record asset shares for the deposited amount, rounded down, on the bank only
+/// 3. Transfer funds from the signer's token account to the bank's liquidity vault
+/// 4. Mint one receipt token per share to the signer's receipt token account
+///
+/// Receipt shares earn interest like any other asset shares, but are not held by a surroundfi
+/// account and cannot be borrowed against (unless the receipt tokens are deposited in a bank that
+/// uses `OracleSetup::ReceiptToken`).
+pub fn lending_pool_deposit_for_receipt<'info>(
+    mut ctx: Context<'_, '_, 'info, 'info, LendingPoolDepositForReceipt<'info>>,
+    amount: u64,
+) -> SurroundfiResult {
+    let LendingPoolDepositForReceipt {
+        group: surroundfi_group_loader,
+        bank: bank_loader,
+        authority: signer,
+        signer_token_account,
+        receipt_token_account,
+        receipt_mint,
+        liquidity_vault_authority,
+        liquidity_vault: bank_liquidity_vault,
+        token_program,
+    } = ctx.accounts;
+    let clock = Clock::get()?;
+    let maybe_bank_mint = utils::maybe_take_bank_mint(
+        &mut ctx.remaining_accounts,
+        &*bank_loader.load()?,
+        token_program.key,
+    )?;
+
+    let mut bank = bank_loader.load_mut()?;
+
+    check!(bank.has_receipt_mint(), SurroundfiError::ReceiptMintNotSetup);
+    bank.assert_operational_mode(Some(true))?;
+
+    bank.accrue_interest(
+        clock.unix_timestamp,
+        &*surroundfi_group_loader.load()?,
+        #[cfg(not(feature = "client"))]
+        bank_loader.key(),
+    )?;
+
+    let shares = bank
+        .get_asset_shares(I80F48::from_num(amount))?
+        .checked_floor()
+        .ok_or_else(math_error!())?;
+    let receipt_amount: u64 = shares.checked_to_num().ok_or_else(math_error!())?;
+    check!(receipt_amount > 0, SurroundfiError::ReceiptAmountTooSmall);
+
+    bank.update_emissions_index(clock.unix_timestamp)?;
+    bank.change_receipt_asset_shares(shares, false)?;
+
+    let amount_pre_fee = maybe_bank_mint
+        .as_ref()
+        .map(|mint| {
+            utils::calculate_pre_fee_spl_deposit_amount(
+                mint.to_account_info(),
+                amount,
+                clock.epoch,
+            )
+        })
+        .transpose()?
+        .unwrap_or(amount);
+
+    bank.deposit_spl_transfer(
+        amount_pre_fee,
+        signer_token_account.to_account_info(),
+        bank_liquidity_vault.to_account_info(),
+        signer.to_account_info(),
+        maybe_bank_mint.as_ref(),
+        token_program.to_account_info(),
+        ctx.remaining_accounts,
+    )?;
+
+    mint_to(
+        CpiContext::new_with_signer(
+            token_program.to_account_info(),
+            MintTo {
+                mint: receipt_mint.to_account_info(),
+                to: receipt_token_account.to_account_info(),
+                authority: liquidity_vault_authority.to_account_info(),
+            },
+            bank_signer!(
+                BankVaultType::Liquidity,
+                bank_loader.key(),
+                bank.liquidity_vault_authority_bump
+            ),
+        ),
+        receipt_amount,
+    )?;
+
+    emit!(LendingPoolBankReceiptDepositEvent {
+        header: GroupEventHeader {
+            version: EVENT_SCHEMA_VERSION,
+            surroundfi_group: surroundfi_group_loader.key(),
+            signer: Some(signer.key())
+        },
+        bank: bank_loader.key(),
+        mint: bank.mint,
+        amount,
+        receipt_amount,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct LendingPoolDepositForReceipt<'info> {
+    pub group: AccountLoader<'info, SurroundfiGroup>,
+
+    #[account(
+        mut,
+        has_one = group,
+        has_one = liquidity_vault,
+        has_one = receipt_mint
+    )]
+    pub bank: AccountLoader<'info, Bank>,
+
+    pub authority: Signer<'info>,
+
+    /// CHECK: Token mint/authority are checked at transfer
+    #[account(mut)]
+    pub signer_token_account: AccountInfo<'info>,
+
+    /// CHECK: Token mint is checked at mint_to
+    #[account(mut)]
+    pub receipt_token_account: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub receipt_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// CHECK: Seed constraint check
+    #[account(
+        seeds = [
+            LIQUIDITY_VAULT_AUTHORITY_SEED.as_bytes(),
+            bank.key().as_ref(),
+        ],
+        bump = bank.load()?.liquidity_vault_authority_bump,
+    )]
+    pub liquidity_vault_authority: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub liquidity_vault: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+/// 1. Accrue interest
+/// 2. Burn the signer's receipt tokens and remove the corresponding asset shares from the bank
+/// 3. Transfer the value of those shares, rounded down, from the bank's liquidity vault to the
+///    destination token account
+pub fn lending_pool_redeem_receipt<'info>(
+    mut ctx: Context<'_, '_, 'info, 'info, LendingPoolRedeemReceipt<'info>>,
+    receipt_amount: u64,
+) -> SurroundfiResult {
+    let LendingPoolRedeemReceipt {
+        group: surroundfi_group_loader,
+        bank: bank_loader,
+        authority: signer,
+        receipt_token_account,
+        receipt_mint,
+        destination_token_account,
+        liquidity_vault_authority,
+        liquidity_vault: bank_liquidity_vault,
+        token_program,
+    } = ctx.accounts;
+    let clock = Clock::get()?;
+    let maybe_bank_mint = utils::maybe_take_bank_mint(
+        &mut ctx.remaining_accounts,
+        &*bank_loader.load()?,
+        token_program.key,
+    )?;
+
+    let mut bank = bank_loader.load_mut()?;
+
+    check!(bank.has_receipt_mint(), SurroundfiError::ReceiptMintNotSetup);
+    bank.assert_operational_mode(Some(false))?;
+
+    bank.accrue_interest(
+        clock.unix_timestamp,
+        &*surroundfi_group_loader.load()?,
+        #[cfg(not(feature = "client"))]
+        bank_loader.key(),
+    )?;
+
+    let shares = I80F48::from_num(receipt_amount);
+    let amount: u64 = bank
+        .get_asset_amount(shares)?
+        .checked_floor()
+        .ok_or_else(math_error!())?
+        .checked_to_num()
+        .ok_or_else(math_error!())?;
+    check!(amount > 0, SurroundfiError::ReceiptAmountTooSmall);
+
+    bank.update_emissions_index(clock.unix_timestamp)?;
+    bank.change_receipt_asset_shares(-shares, true)?;
+    bank.check_utilization_ratio()?;
+
+    burn(
+        CpiContext::new(
+            token_program.to_account_info(),
+            Burn {
+                mint: receipt_mint.to_account_info(),
+                from: receipt_token_account.to_account_info(),
+                authority: signer.to_account_info(),
+            },
+        ),
+        receipt_amount,
+    )?;
+
+    bank.withdraw_spl_transfer(
+        amount,
+        bank_liquidity_vault.to_account_info(),
+        destination_token_account.to_account_info(),
+        liquidity_vault_authority.to_account_info(),
+        maybe_bank_mint.as_ref(),
+        token_program.to_account_info(),
+        bank_signer!(
+            BankVaultType::Liquidity,
+            bank_loader.key(),
+            bank.liquidity_vault_authority_bump
+        ),
+        ctx.remaining_accounts,
+    )?;
+
+    emit!(LendingPoolBankReceiptRedeemEvent {
+        header: GroupEventHeader {
+            version: EVENT_SCHEMA_VERSION,
+            surroundfi_group: surroundfi_group_loader.key(),
+            signer: Some(signer.key())
+        },
+        bank: bank_loader.key(),
+        mint: bank.mint,
+        amount,
+        receipt_amount,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct LendingPoolRedeemReceipt<'info> {
+    pub group: AccountLoader<'info, SurroundfiGroup>,
+
+    #[account(
+        mut,
+        has_one = group,
+        has_one = liquidity_vault,
+        has_one = receipt_mint
+    )]
+    pub bank: AccountLoader<'info, Bank>,
+
+    pub authority: Signer<'info>,
+
+    /// CHECK: Token mint/authority are checked at burn
+    #[account(mut)]
+    pub receipt_token_account: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub receipt_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(mut)]
+    pub destination_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    /// CHECK: Seed constraint check
+    #[account(
+        seeds = [
+            LIQUIDITY_VAULT_AUTHORITY_SEED.as_bytes(),
+            bank.key().as_ref(),
+        ],
+        bump = bank.load()?.liquidity_vault_authority_bump,
+    )]
+    pub liquidity_vault_authority: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub liquidity_vault: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
@@ -0,0 +1,164 @@
+use crate::{
+    bank_signer,
+    constants::{LIQUIDITY_VAULT_AUTHORITY_SEED, REFERRER_FEES_SEED},
+    events::{
+        GroupEventHeader, LendingPoolBankClaimReferrerFeesEvent,
+        SurroundfiGroupInitReferrerFeesEvent, EVENT_SCHEMA_VERSION,
+    },
+    prelude::*,
+    state::{
+        referrer_fees::ReferrerFees,
+        surroundfi_group::{Bank, BankVaultType},
+    },
+    utils,
+};
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{TokenAccount, TokenInterface};
+
+/// Create the account where a referrer accrues its share of origination fees in a group. Anyone can
+/// pay for the referrer's account.
+pub fn surroundfi_group_init_referrer_fees(
+    ctx: Context<SurroundfiGroupInitReferrerFees>,
+) -> SurroundfiResult {
+    let mut referrer_fees = ctx.accounts.referrer_fees.load_init()?;
+    referrer_fees.initialize(
+        ctx.accounts.group.key(),
+        ctx.accounts.referrer.key(),
+        ctx.bumps.referrer_fees,
+    );
+
+    emit!(SurroundfiGroupInitReferrerFeesEvent {
+        header: GroupEventHeader {
+            version: EVENT_SCHEMA_VERSION,
+            signer: Some(ctx.accounts.fee_payer.key()),
+            surroundfi_group: ctx.accounts.group.key(),
+        },
+        referrer: ctx.accounts.referrer.key(),
+        referrer_fees: ctx.accounts.referrer_fees.key(),
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct SurroundfiGroupInitReferrerFees<'info> {
+    pub group: AccountLoader<'info, SurroundfiGroup>,
+
+    /// CHECK: Any wallet can be a referrer
+    pub referrer: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub fee_payer: Signer<'info>,
+
+    #[account(
+        init,
+        seeds = [
+            REFERRER_FEES_SEED.as_bytes(),
+            group.key().as_ref(),
+            referrer.key().as_ref(),
+        ],
+        bump,
+        payer = fee_payer,
+        space = 8 + ReferrerFees::LEN
+    )]
+    pub referrer_fees: AccountLoader<'info, ReferrerFees>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Transfer the referrer's outstanding fees on a bank from the liquidity vault, as far as there is
+/// liquidity. Fractions of a token stay outstanding.
+///
+/// Remaining accounts: the bank mint (Token-2022 only)
+///
+/// Referrer only
+pub fn lending_pool_claim_referrer_fees<'info>(
+    mut ctx: Context<'_, '_, 'info, 'info, LendingPoolClaimReferrerFees<'info>>,
+) -> SurroundfiResult {
+    let LendingPoolClaimReferrerFees {
+        bank: bank_loader,
+        referrer_fees: referrer_fees_loader,
+        liquidity_vault_authority,
+        liquidity_vault,
+        destination_token_account,
+        token_program,
+        ..
+    } = ctx.accounts;
+
+    let bank = bank_loader.load()?;
+    let maybe_bank_mint =
+        utils::maybe_take_bank_mint(&mut ctx.remaining_accounts, &bank, token_program.key)?;
+
+    let amount = referrer_fees_loader
+        .load_mut()?
+        .claim(&bank_loader.key(), liquidity_vault.amount)?;
+
+    if amount > 0 {
+        bank.withdraw_spl_transfer(
+            amount,
+            liquidity_vault.to_account_info(),
+            destination_token_account.to_account_info(),
+            liquidity_vault_authority.to_account_info(),
+            maybe_bank_mint.as_ref(),
+            token_program.to_account_info(),
+            bank_signer!(
+                BankVaultType::Liquidity,
+                bank_loader.key(),
+                bank.liquidity_vault_authority_bump
+            ),
+            ctx.remaining_accounts,
+        )?;
+    }
+
+    emit!(LendingPoolBankClaimReferrerFeesEvent {
+        header: GroupEventHeader {
+            version: EVENT_SCHEMA_VERSION,
+            surroundfi_group: ctx.accounts.group.key(),
+            signer: Some(ctx.accounts.referrer.key())
+        },
+        bank: bank_loader.key(),
+        mint: bank.mint,
+        referrer: ctx.accounts.referrer.key(),
+        amount,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct LendingPoolClaimReferrerFees<'info> {
+    pub group: AccountLoader<'info, SurroundfiGroup>,
+
+    pub referrer: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = group,
+        has_one = referrer
+    )]
+    pub referrer_fees: AccountLoader<'info, ReferrerFees>,
+
+    #[account(
+        has_one = group,
+        has_one = liquidity_vault
+    )]
+    pub bank: AccountLoader<'info, Bank>,
+
+    /// CHECK: Seed constraint check
+    #[account(
+        seeds = [
+            LIQUIDITY_VAULT_AUTHORITY_SEED.as_bytes(),
+            bank.key().as_ref(),
+        ],
+        bump = bank.load()?.liquidity_vault_authority_bump
+    )]
+    pub liquidity_vault_authority: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub liquidity_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub destination_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
@@ -0,0 +1,94 @@
+// Permissionless ix to bring a bank's mint extensions up to date with its mint
+use crate::events::{
+    GroupEventHeader, LendingPoolBankRefreshMintExtensionsEvent, EVENT_SCHEMA_VERSION,
+};
+use crate::state::surroundfi_account::SurroundfiAccount;
+use crate::state::surroundfi_account_extension::SurroundfiAccountExtension;
+use crate::state::surroundfi_group::{Bank, SurroundfiGroup};
+use crate::{utils, SurroundfiResult};
+use anchor_lang::prelude::*;
+use anchor_lang::Discriminator;
+use anchor_spl::token_interface::Mint;
+
+/// Recomputes `Bank::mint_extensions` from the bank's mint. Banks created before an extension was
+/// tracked (e.g. interest-bearing mints) have the flag cleared until refreshed.
+///
+/// Remaining accounts: writable `SurroundfiAccount`s and `SurroundfiAccountExtension`s whose
+/// balances in the bank should follow the bank's `Bank::is_interest_bearing`. Balances inherit
+/// the flag when created (see `Balance::bank_interest_bearing`), so existing balances keep pricing
+/// raw amounts until synced here.
+pub fn lending_pool_refresh_mint_extensions<'info>(
+    ctx: Context<'_, '_, 'info, 'info, LendingPoolRefreshMintExtensions<'info>>,
+) -> SurroundfiResult {
+    let mint_extensions =
+        utils::get_mint_extensions(&ctx.accounts.bank_mint.to_account_info().try_borrow_data()?)?;
+
+    let mut bank = ctx.accounts.bank.load_mut()?;
+    let mint_extensions_before = bank.mint_extensions;
+    bank.mint_extensions = mint_extensions;
+    let bank_interest_bearing = bank.is_interest_bearing() as u8;
+
+    msg!(
+        "mint extensions: {:#b} -> {:#b}",
+        mint_extensions_before,
+        mint_extensions
+    );
+
+    let bank_pk = ctx.accounts.bank.key();
+    for ai in ctx.remaining_accounts {
+        let (is_account, is_extension) = {
+            let data = ai.try_borrow_data()?;
+            (
+                data.starts_with(&SurroundfiAccount::DISCRIMINATOR),
+                data.starts_with(&SurroundfiAccountExtension::DISCRIMINATOR),
+            )
+        };
+
+        if is_account {
+            let loader = AccountLoader::<SurroundfiAccount>::try_from(ai)?;
+            let mut surroundfi_account = loader.load_mut()?;
+            surroundfi_account
+                .lending_account
+                .balances
+                .iter_mut()
+                .filter(|balance| balance.is_active() && balance.bank_pk.eq(&bank_pk))
+                .for_each(|balance| balance.bank_interest_bearing = bank_interest_bearing);
+        } else if is_extension {
+            let loader = AccountLoader::<SurroundfiAccountExtension>::try_from(ai)?;
+            let mut extension = loader.load_mut()?;
+            extension
+                .balances
+                .iter_mut()
+                .filter(|balance| balance.is_active() && balance.bank_pk.eq(&bank_pk))
+                .for_each(|balance| balance.bank_interest_bearing = bank_interest_bearing);
+        }
+    }
+
+    emit!(LendingPoolBankRefreshMintExtensionsEvent {
+        header: GroupEventHeader {
+            version: EVENT_SCHEMA_VERSION,
+            // Permissionless, so there is no signer to record
+            signer: None,
+            surroundfi_group: ctx.accounts.group.key(),
+        },
+        bank: bank_pk,
+        mint: ctx.accounts.bank_mint.key(),
+        mint_extensions,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct LendingPoolRefreshMintExtensions<'info> {
+    pub group: AccountLoader<'info, SurroundfiGroup>,
+
+    #[account(
+        mut,
+        has_one = group,
+        constraint = bank.load()?.mint == bank_mint.key()
+    )]
+    pub bank: AccountLoader<'info, Bank>,
+
+    pub bank_mint: Box<InterfaceAccount<'info, Mint>>,
+}
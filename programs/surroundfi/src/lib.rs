@@ -10,8 +10,11 @@ pub mod utils;
 use anchor_lang::prelude::*;
 use instructions::*;
 use prelude::*;
+use state::fee_distribution::FeeRecipientConfig;
 use state::surroundfi_group::WrappedI80F48;
-use state::surroundfi_group::{BankConfigCompact, BankConfigOpt};
+use state::surroundfi_group::{
+    ArenaConfig, AssetTagMatrix, BankConfigCompact, BankConfigOpt, GroupConfigOpt,
+};
 
 declare_id!("DinAro7LsnoGwfdfq68N3Mf1RgtVMCHoJeQ2btM9Q137");
 
@@ -30,8 +33,40 @@ pub mod surroundfi {
         ctx: Context<SurroundfiGroupConfigure>,
         new_admin: Pubkey,
         is_arena_group: bool,
+        group_config: GroupConfigOpt,
+        staked_stable_borrow_enabled: Option<bool>,
     ) -> SurroundfiResult {
-        surroundfi_group::configure(ctx, new_admin, is_arena_group)
+        surroundfi_group::configure(
+            ctx,
+            new_admin,
+            is_arena_group,
+            group_config,
+            staked_stable_borrow_enabled,
+        )
+    }
+
+    /// (admin only) Replace the rules of which asset tags can be comingled and liquidated
+    pub fn surroundfi_group_configure_asset_tag_matrix(
+        ctx: Context<SurroundfiGroupConfigureAssetTagMatrix>,
+        asset_tag_matrix: AssetTagMatrix,
+    ) -> SurroundfiResult {
+        surroundfi_group::configure_asset_tag_matrix(ctx, asset_tag_matrix)
+    }
+
+    /// (admin only) Set the max leverage and liquidation fee of an arena group's token pair
+    pub fn surroundfi_group_configure_arena(
+        ctx: Context<SurroundfiGroupConfigureArena>,
+        arena_config: ArenaConfig,
+    ) -> SurroundfiResult {
+        surroundfi_group::configure_arena(ctx, arena_config)
+    }
+
+    /// (admin only) Set which restricted Token-2022 mint extensions new banks may have
+    pub fn surroundfi_group_configure_mint_extensions(
+        ctx: Context<SurroundfiGroupConfigureMintExtensions>,
+        permitted_mint_extensions: u64,
+    ) -> SurroundfiResult {
+        surroundfi_group::configure_mint_extensions(ctx, permitted_mint_extensions)
     }
 
     pub fn lending_pool_add_bank(
@@ -74,29 +109,109 @@ pub mod surroundfi {
         surroundfi_group::lending_pool_configure_bank_oracle(ctx, setup, oracle)
     }
 
-    pub fn lending_pool_setup_emissions(
-        ctx: Context<LendingPoolSetupEmissions>,
+    /// (permissionless) Recompute a bank's mint extensions from its mint, and sync the
+    /// interest-bearing flag of the balances in the surroundfi accounts passed as remaining
+    /// accounts.
+    pub fn lending_pool_refresh_mint_extensions<'info>(
+        ctx: Context<'_, '_, 'info, 'info, LendingPoolRefreshMintExtensions<'info>>,
+    ) -> SurroundfiResult {
+        surroundfi_group::lending_pool_refresh_mint_extensions(ctx)
+    }
+
+    pub fn lending_pool_setup_emissions<'info>(
+        ctx: Context<'_, '_, 'info, 'info, LendingPoolSetupEmissions<'info>>,
         flags: u64,
         rate: u64,
         total_emissions: u64,
+        start_time: Option<i64>,
+        end_time: Option<i64>,
     ) -> SurroundfiResult {
-        surroundfi_group::lending_pool_setup_emissions(ctx, flags, rate, total_emissions)
+        surroundfi_group::lending_pool_setup_emissions(
+            ctx,
+            flags,
+            rate,
+            total_emissions,
+            start_time,
+            end_time,
+        )
     }
 
-    pub fn lending_pool_update_emissions_parameters(
-        ctx: Context<LendingPoolUpdateEmissionsParameters>,
+    pub fn lending_pool_update_emissions_parameters<'info>(
+        ctx: Context<'_, '_, 'info, 'info, LendingPoolUpdateEmissionsParameters<'info>>,
         emissions_flags: Option<u64>,
         emissions_rate: Option<u64>,
         additional_emissions: Option<u64>,
+        emissions_start_time: Option<i64>,
+        emissions_end_time: Option<i64>,
     ) -> SurroundfiResult {
         surroundfi_group::lending_pool_update_emissions_parameters(
             ctx,
             emissions_flags,
             emissions_rate,
             additional_emissions,
+            emissions_start_time,
+            emissions_end_time,
         )
     }
 
+    /// (admin only) Reclaim unspent emissions from the emissions vault after the emissions end
+    pub fn lending_pool_reclaim_emissions<'info>(
+        ctx: Context<'_, '_, 'info, 'info, LendingPoolReclaimEmissions<'info>>,
+    ) -> SurroundfiResult {
+        surroundfi_group::lending_pool_reclaim_emissions(ctx)
+    }
+
+    /// (admin only) Create the emissions program of a bank, holding additional reward streams
+    pub fn lending_pool_init_emissions_program(
+        ctx: Context<LendingPoolInitEmissionsProgram>,
+    ) -> SurroundfiResult {
+        surroundfi_group::lending_pool_init_emissions_program(ctx)
+    }
+
+    /// (admin only) Add a reward stream to the emissions program of a bank
+    pub fn lending_pool_add_emissions_stream(
+        ctx: Context<LendingPoolAddEmissionsStream>,
+        flags: u64,
+        rate: u64,
+        start_time: i64,
+        end_time: i64,
+    ) -> SurroundfiResult {
+        surroundfi_group::lending_pool_add_emissions_stream(ctx, flags, rate, start_time, end_time)
+    }
+
+    /// (stream funder only) Fund a reward stream of the emissions program of a bank
+    pub fn lending_pool_fund_emissions_stream<'info>(
+        ctx: Context<'_, '_, 'info, 'info, LendingPoolFundEmissionsStream<'info>>,
+        stream_id: u8,
+        amount: u64,
+    ) -> SurroundfiResult {
+        surroundfi_group::lending_pool_fund_emissions_stream(ctx, stream_id, amount)
+    }
+
+    /// (admin only) Create the receipt token mint of a bank, opting it into tokenized deposits
+    pub fn lending_pool_init_receipt_mint(
+        ctx: Context<LendingPoolInitReceiptMint>,
+    ) -> SurroundfiResult {
+        surroundfi_group::lending_pool_init_receipt_mint(ctx)
+    }
+
+    /// Deposit into a bank without a surroundfi account, receiving transferable receipt tokens
+    /// (one per asset share) in return
+    pub fn lending_pool_deposit_for_receipt<'info>(
+        ctx: Context<'_, '_, 'info, 'info, LendingPoolDepositForReceipt<'info>>,
+        amount: u64,
+    ) -> SurroundfiResult {
+        surroundfi_group::lending_pool_deposit_for_receipt(ctx, amount)
+    }
+
+    /// Burn receipt tokens and withdraw the value of the shares they represent
+    pub fn lending_pool_redeem_receipt<'info>(
+        ctx: Context<'_, '_, 'info, 'info, LendingPoolRedeemReceipt<'info>>,
+        receipt_amount: u64,
+    ) -> SurroundfiResult {
+        surroundfi_group::lending_pool_redeem_receipt(ctx, receipt_amount)
+    }
+
     /// Handle bad debt of a bankrupt surroundfi account for a given bank.
     pub fn lending_pool_handle_bankruptcy<'info>(
         ctx: Context<'_, '_, 'info, 'info, LendingPoolHandleBankruptcy<'info>>,
@@ -104,6 +219,13 @@ pub mod surroundfi {
         surroundfi_group::lending_pool_handle_bankruptcy(ctx)
     }
 
+    /// Handle bad debt of a bankrupt surroundfi account for all of its liabilities.
+    pub fn lending_pool_handle_account_bankruptcy<'info>(
+        ctx: Context<'_, '_, 'info, 'info, LendingPoolHandleAccountBankruptcy<'info>>,
+    ) -> SurroundfiResult {
+        surroundfi_group::lending_pool_handle_account_bankruptcy(ctx)
+    }
+
     // User instructions
 
     /// Initialize a surroundfi account for a given group
@@ -111,6 +233,17 @@ pub mod surroundfi {
         surroundfi_account::initialize_account(ctx)
     }
 
+    /// Initialize a surroundfi account at a PDA derived from the group, authority, an index and
+    /// an optional third-party id. Does not require the account itself to sign (CPI-friendly).
+    /// A third-party id of `Some(0)` is rejected, as `None` already derives with 0.
+    pub fn surroundfi_account_initialize_pda(
+        ctx: Context<SurroundfiAccountInitializePda>,
+        account_index: u16,
+        third_party_id: Option<u16>,
+    ) -> SurroundfiResult {
+        surroundfi_account::initialize_account_pda(ctx, account_index, third_party_id)
+    }
+
     pub fn lending_account_deposit<'info>(
         ctx: Context<'_, '_, 'info, 'info, LendingAccountDeposit<'info>>,
         amount: u64,
@@ -135,6 +268,24 @@ pub mod surroundfi {
         surroundfi_account::lending_account_withdraw(ctx, amount, withdraw_all)
     }
 
+    /// Deposit a native stake account into the bank's spl-single-pool and credit the minted LST to
+    /// the staked collateral bank
+    pub fn lending_account_deposit_stake<'info>(
+        ctx: Context<'_, '_, 'info, 'info, LendingAccountDepositStake<'info>>,
+    ) -> SurroundfiResult {
+        surroundfi_account::lending_account_deposit_stake(ctx)
+    }
+
+    /// Withdraw from a staked collateral bank into a native stake account, redeeming the LST with
+    /// the bank's spl-single-pool
+    pub fn lending_account_withdraw_to_stake_account<'info>(
+        ctx: Context<'_, '_, 'info, 'info, LendingAccountWithdrawToStakeAccount<'info>>,
+        amount: u64,
+        withdraw_all: Option<bool>,
+    ) -> SurroundfiResult {
+        surroundfi_account::lending_account_withdraw_to_stake_account(ctx, amount, withdraw_all)
+    }
+
     pub fn lending_account_borrow<'info>(
         ctx: Context<'_, '_, 'info, 'info, LendingAccountBorrow<'info>>,
         amount: u64,
@@ -142,22 +293,67 @@ pub mod surroundfi {
         surroundfi_account::lending_account_borrow(ctx, amount)
     }
 
-    pub fn lending_account_close_balance(
-        ctx: Context<LendingAccountCloseBalance>,
+    /// (arena groups only) Open a leveraged position in one ix: for each of the `legs`, borrow the
+    /// debt bank's token, swap it with the arena's swap program, and deposit the proceeds (plus
+    /// `collateral_amount` on the first leg) as collateral
+    pub fn lending_account_open_leveraged_position<'info>(
+        ctx: Context<'_, '_, 'info, 'info, LendingAccountLeveragedPosition<'info>>,
+        collateral_amount: u64,
+        legs: Vec<LeverageLeg>,
+        swap_accounts_len: u8,
+    ) -> SurroundfiResult {
+        surroundfi_account::lending_account_open_leveraged_position(
+            ctx,
+            collateral_amount,
+            legs,
+            swap_accounts_len,
+        )
+    }
+
+    /// (arena groups only) Reduce or close a leveraged position in one ix: withdraw
+    /// `collateral_amount`, swap it with the arena's swap program, and repay the debt with the
+    /// proceeds
+    pub fn lending_account_close_leveraged_position<'info>(
+        ctx: Context<'_, '_, 'info, 'info, LendingAccountLeveragedPosition<'info>>,
+        collateral_amount: u64,
+        swap_accounts_len: u8,
+        swap_data: Vec<u8>,
+    ) -> SurroundfiResult {
+        surroundfi_account::lending_account_close_leveraged_position(
+            ctx,
+            collateral_amount,
+            swap_accounts_len,
+            swap_data,
+        )
+    }
+
+    pub fn lending_account_close_balance<'info>(
+        ctx: Context<'_, '_, 'info, 'info, LendingAccountCloseBalance<'info>>,
     ) -> SurroundfiResult {
         surroundfi_account::lending_account_close_balance(ctx)
     }
 
+    /// Withdraw the bank's emissions, or the rewards of one stream of its emissions program
     pub fn lending_account_withdraw_emissions<'info>(
         ctx: Context<'_, '_, 'info, 'info, LendingAccountWithdrawEmissions<'info>>,
+        stream_id: Option<u8>,
+    ) -> SurroundfiResult {
+        surroundfi_account::lending_account_withdraw_emissions(ctx, stream_id)
+    }
+
+    /// Settle the bank's emissions and any passed streams, or only one stream of its emissions
+    /// program
+    pub fn lending_account_settle_emissions<'info>(
+        ctx: Context<'_, '_, 'info, 'info, LendingAccountSettleEmissions<'info>>,
+        stream_id: Option<u8>,
     ) -> SurroundfiResult {
-        surroundfi_account::lending_account_withdraw_emissions(ctx)
+        surroundfi_account::lending_account_settle_emissions(ctx, stream_id)
     }
 
-    pub fn lending_account_settle_emissions(
-        ctx: Context<LendingAccountSettleEmissions>,
+    pub fn lending_account_init_emissions_accrual(
+        ctx: Context<LendingAccountInitEmissionsAccrual>,
     ) -> SurroundfiResult {
-        surroundfi_account::lending_account_settle_emissions(ctx)
+        surroundfi_account::lending_account_init_emissions_accrual(ctx)
     }
 
     /// Liquidate a lending account balance of an unhealthy surroundfi account
@@ -181,6 +377,22 @@ pub mod surroundfi {
         surroundfi_account::lending_account_end_flashloan(ctx)
     }
 
+    /// Borrow, invoke `callback_program` with the funds, then pull back the loan plus the
+    /// flashloan fee. Unlike `lending_account_start_flashloan`, usable via CPI.
+    pub fn lending_account_callback_flashloan<'info>(
+        ctx: Context<'_, '_, 'info, 'info, LendingAccountCallbackFlashloan<'info>>,
+        amount: u64,
+        callback_accounts_len: u8,
+        callback_data: Vec<u8>,
+    ) -> SurroundfiResult {
+        surroundfi_account::lending_account_callback_flashloan(
+            ctx,
+            amount,
+            callback_accounts_len,
+            callback_data,
+        )
+    }
+
     pub fn surroundfi_account_update_emissions_destination_account<'info>(
         ctx: Context<'_, '_, 'info, 'info, SurroundfiAccountUpdateEmissionsDestinationAccount<'info>>,
     ) -> SurroundfiResult {
@@ -214,6 +426,91 @@ pub mod surroundfi {
         surroundfi_group::lending_pool_withdraw_insurance(ctx, amount)
     }
 
+    /// (admin only) Enable staking into a bank's insurance fund, or change its parameters
+    pub fn lending_pool_configure_insurance_staking(
+        ctx: Context<LendingPoolConfigureInsuranceStaking>,
+        fee_share: WrappedI80F48,
+        cooldown: i64,
+        unstake_window: i64,
+    ) -> SurroundfiResult {
+        surroundfi_group::lending_pool_configure_insurance_staking(
+            ctx,
+            fee_share,
+            cooldown,
+            unstake_window,
+        )
+    }
+
+    /// Create the account holding the signer's stake in a bank's insurance fund
+    pub fn lending_pool_init_insurance_stake(
+        ctx: Context<LendingPoolInitInsuranceStake>,
+    ) -> SurroundfiResult {
+        surroundfi_group::lending_pool_init_insurance_stake(ctx)
+    }
+
+    /// Stake tokens into a bank's insurance fund, earning insurance fees and taking the first
+    /// loss of bad debt
+    pub fn lending_pool_insurance_stake<'info>(
+        ctx: Context<'_, '_, 'info, 'info, LendingPoolInsuranceStake<'info>>,
+        amount: u64,
+    ) -> SurroundfiResult {
+        surroundfi_group::lending_pool_insurance_stake(ctx, amount)
+    }
+
+    /// Start the cooldown to unstake from a bank's insurance fund
+    pub fn lending_pool_insurance_request_unstake(
+        ctx: Context<LendingPoolInsuranceRequestUnstake>,
+        amount: u64,
+        unstake_all: Option<bool>,
+    ) -> SurroundfiResult {
+        surroundfi_group::lending_pool_insurance_request_unstake(ctx, amount, unstake_all)
+    }
+
+    /// Withdraw the requested unstake from a bank's insurance fund once the cooldown has passed,
+    /// before the unstake window closes
+    pub fn lending_pool_insurance_unstake<'info>(
+        ctx: Context<'_, '_, 'info, 'info, LendingPoolInsuranceUnstake<'info>>,
+    ) -> SurroundfiResult {
+        surroundfi_group::lending_pool_insurance_unstake(ctx)
+    }
+
+    /// (admin only) Create the fee distribution of a group, splitting fees among recipients
+    pub fn surroundfi_group_init_fee_distribution(
+        ctx: Context<SurroundfiGroupInitFeeDistribution>,
+        recipients: Vec<FeeRecipientConfig>,
+    ) -> SurroundfiResult {
+        surroundfi_group::surroundfi_group_init_fee_distribution(ctx, recipients)
+    }
+
+    /// (admin only) Replace the recipients of the group's fee distribution
+    pub fn surroundfi_group_configure_fee_distribution(
+        ctx: Context<SurroundfiGroupConfigureFeeDistribution>,
+        recipients: Vec<FeeRecipientConfig>,
+    ) -> SurroundfiResult {
+        surroundfi_group::surroundfi_group_configure_fee_distribution(ctx, recipients)
+    }
+
+    /// (permissionless) Distribute the fee vault balance of a bank among the group's fee recipients
+    pub fn lending_pool_distribute_fees<'info>(
+        ctx: Context<'_, '_, 'info, 'info, LendingPoolDistributeFees<'info>>,
+    ) -> SurroundfiResult {
+        surroundfi_group::lending_pool_distribute_fees(ctx)
+    }
+
+    /// (permissionless) Create the account where a referrer accrues its share of origination fees
+    pub fn surroundfi_group_init_referrer_fees(
+        ctx: Context<SurroundfiGroupInitReferrerFees>,
+    ) -> SurroundfiResult {
+        surroundfi_group::surroundfi_group_init_referrer_fees(ctx)
+    }
+
+    /// (referrer only) Claim the referrer's outstanding fees on a bank
+    pub fn lending_pool_claim_referrer_fees<'info>(
+        ctx: Context<'_, '_, 'info, 'info, LendingPoolClaimReferrerFees<'info>>,
+    ) -> SurroundfiResult {
+        surroundfi_group::lending_pool_claim_referrer_fees(ctx)
+    }
+
     pub fn set_account_flag(ctx: Context<SetAccountFlag>, flag: u64) -> SurroundfiResult {
         surroundfi_group::set_account_flag(ctx, flag)
     }
@@ -232,6 +529,20 @@ pub mod surroundfi {
         surroundfi_account::close_account(ctx)
     }
 
+    /// Create an extension holding additional balance slots for a surroundfi account
+    pub fn surroundfi_account_init_extension(
+        ctx: Context<SurroundfiAccountInitExtension>,
+    ) -> SurroundfiResult {
+        surroundfi_account::initialize_extension(ctx)
+    }
+
+    /// Close an empty extension and unlink it from its surroundfi account
+    pub fn surroundfi_account_close_extension(
+        ctx: Context<SurroundfiAccountCloseExtension>,
+    ) -> SurroundfiResult {
+        surroundfi_account::close_extension(ctx)
+    }
+
     pub fn lending_account_withdraw_emissions_permissionless<'info>(
         ctx: Context<'_, '_, 'info, 'info, LendingAccountWithdrawEmissionsPermissionless<'info>>,
     ) -> SurroundfiResult {
@@ -249,6 +560,18 @@ pub mod surroundfi {
         surroundfi_account::lending_account_pulse_health(ctx)
     }
 
+    /// (Permissionless, read-only) Apply hypothetical deposits, withdraws, borrows and repays to
+    /// an in-memory copy of the account and return the resulting health (`SimulatedHealth`) via
+    /// return data. Intended for `simulateTransaction`, nothing is written.
+    /// * remaining accounts expected in the same order as borrow, etc, for the balances the user
+    ///   would have AFTER the actions: <bank1, oracle1, bank2, oracle2>
+    pub fn lending_account_simulate_health<'info>(
+        ctx: Context<'_, '_, 'info, 'info, LendingAccountSimulateHealth<'info>>,
+        actions: Vec<SimulatedAction>,
+    ) -> SurroundfiResult {
+        surroundfi_account::lending_account_simulate_health(ctx, actions)
+    }
+
     /// (Runs once per program) Configures the fee state account, where the global admin sets fees
     /// that are assessed to the protocol
     pub fn init_global_fee_state(
@@ -324,6 +647,16 @@ pub mod surroundfi {
     pub fn propagate_staked_settings(ctx: Context<PropagateStakedSettings>) -> SurroundfiResult {
         surroundfi_group::propagate_staked_settings(ctx)
     }
+
+    /// (group admin only) Clear the given `STAKED_OVERRIDE_*` flags on a staked collateral bank,
+    /// so that `propagate_staked_settings` manages those fields again. Configuring a staked bank
+    /// directly (e.g. `lending_pool_configure_bank`) sets the flags for the fields it changes.
+    pub fn lending_pool_clear_staked_settings_overrides(
+        ctx: Context<LendingPoolClearStakedSettingsOverrides>,
+        overrides: u8,
+    ) -> SurroundfiResult {
+        surroundfi_group::lending_pool_clear_staked_settings_overrides(ctx, overrides)
+    }
 }
 
 #[cfg(not(feature = "no-entrypoint"))]
@@ -0,0 +1,405 @@
+use super::surroundfi_group::{Bank, WrappedI80F48};
+use crate::{
+    assert_struct_align, assert_struct_size, check,
+    constants::{
+        EMISSIONS_FLAG_BORROW_ACTIVE, EMISSIONS_FLAG_LENDING_ACTIVE, EXP_10_I80F48,
+        SECONDS_PER_YEAR,
+    },
+    debug, math_error,
+    prelude::{SurroundfiError, SurroundfiResult},
+};
+use anchor_lang::{prelude::*, Discriminator};
+use bytemuck::{Pod, Zeroable};
+use fixed::types::I80F48;
+use std::cmp::{max, min};
+use type_layout::TypeLayout;
+
+pub const MAX_EMISSIONS_STREAMS: usize = 8;
+
+assert_struct_size!(EmissionsProgram, 1256);
+assert_struct_align!(EmissionsProgram, 8);
+/// Additional reward streams of a bank, running alongside (and independently of) the bank's own
+/// `emissions_mint`. A PDA of the bank, which is also the authority of every stream vault.
+///
+/// Streams are added by the group admin and funded by their `funder`, which allows partners to
+/// co-incentivize a bank. A slot, once used, is never reused.
+#[account(zero_copy)]
+#[repr(C)]
+#[derive(PartialEq, Eq, TypeLayout)]
+pub struct EmissionsProgram {
+    pub bank: Pubkey, // 32
+    pub bump: u8,     // 1
+    pub _pad0: [u8; 7],
+    pub streams: [EmissionsStream; MAX_EMISSIONS_STREAMS], // 144 * 8 = 1152
+    pub _padding0: [u64; 8],                               // 64
+}
+
+impl EmissionsProgram {
+    pub const LEN: usize = std::mem::size_of::<EmissionsProgram>();
+
+    pub fn initialize(&mut self, bank: Pubkey, bump: u8) {
+        self.bank = bank;
+        self.bump = bump;
+    }
+
+    pub fn get_stream_mut(&mut self, stream_id: u8) -> SurroundfiResult<&mut EmissionsStream> {
+        let stream = self
+            .streams
+            .get_mut(stream_id as usize)
+            .ok_or_else(|| error!(SurroundfiError::InvalidEmissionsStream))?;
+
+        check!(stream.is_active(), SurroundfiError::InvalidEmissionsStream);
+
+        Ok(stream)
+    }
+
+    /// Add a stream in the first unused slot, returns its id.
+    pub fn add_stream(&mut self, stream: EmissionsStream) -> SurroundfiResult<u8> {
+        check!(
+            !self
+                .streams
+                .iter()
+                .any(|existing| existing.is_active() && existing.mint == stream.mint),
+            SurroundfiError::InvalidEmissionsStream,
+            "Bank already has a stream for this mint"
+        );
+
+        let stream_id = self
+            .streams
+            .iter()
+            .position(|existing| !existing.is_active())
+            .ok_or_else(|| error!(SurroundfiError::EmissionsStreamsFull))?;
+
+        self.streams[stream_id] = stream;
+
+        Ok(stream_id as u8)
+    }
+}
+
+assert_struct_size!(EmissionsStream, 144);
+assert_struct_align!(EmissionsStream, 8);
+#[repr(C)]
+#[derive(
+    AnchorDeserialize, AnchorSerialize, Copy, Clone, Zeroable, Pod, PartialEq, Eq, TypeLayout,
+)]
+pub struct EmissionsStream {
+    /// Rewards are paid in this mint. Pubkey default if the slot is unused.
+    pub mint: Pubkey, // 32
+    /// The only signer allowed to fund this stream
+    pub funder: Pubkey, // 32
+    /// Funded and not yet set aside for any balance, in native token
+    pub remaining: WrappedI80F48, // 16
+    /// Native tokens per year per whole token (UI amount) of balance, as `Bank.emissions_rate`
+    pub rate: u64, // 8
+    /// Exactly one of `EMISSIONS_FLAG_LENDING_ACTIVE` or `EMISSIONS_FLAG_BORROW_ACTIVE`
+    pub flags: u64, // 8
+    /// Unix timestamp, nothing accrues before
+    pub start_time: i64, // 8
+    /// Unix timestamp, nothing accrues after. 0 if the stream runs until `remaining` is exhausted.
+    pub end_time: i64, // 8
+    /// Emissions per whole share (1e(bank.mint_decimals) shares) of the stream's side since the
+    /// stream was added, in native tokens. See `update_index`.
+    pub index: WrappedI80F48, // 16
+    /// Last time `index` was updated
+    pub index_last_update: i64, // 8
+    pub _padding0: [u64; 1], // 8
+}
+
+impl EmissionsStream {
+    pub fn new(
+        mint: Pubkey,
+        funder: Pubkey,
+        rate: u64,
+        flags: u64,
+        start_time: i64,
+        end_time: i64,
+        current_timestamp: i64,
+    ) -> SurroundfiResult<Self> {
+        check!(
+            mint != Pubkey::default(),
+            SurroundfiError::InvalidEmissionsStream
+        );
+        check!(
+            flags == EMISSIONS_FLAG_LENDING_ACTIVE || flags == EMISSIONS_FLAG_BORROW_ACTIVE,
+            SurroundfiError::InvalidEmissionsStream,
+            "A stream rewards either lending or borrowing"
+        );
+        check!(
+            end_time == 0 || end_time > start_time,
+            SurroundfiError::InvalidEmissionsStream,
+            "Stream ends before it starts"
+        );
+
+        Ok(Self {
+            mint,
+            funder,
+            remaining: I80F48::ZERO.into(),
+            rate,
+            flags,
+            start_time,
+            end_time,
+            index: I80F48::ZERO.into(),
+            index_last_update: current_timestamp,
+            _padding0: [0; 1],
+        })
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.mint != Pubkey::default()
+    }
+
+    pub fn is_lending(&self) -> bool {
+        self.flags & EMISSIONS_FLAG_LENDING_ACTIVE != 0
+    }
+
+    /// Must be preceded by `update_index`, so the new funds don't accrue retroactively.
+    pub fn add_remaining(&mut self, amount: u64) -> SurroundfiResult {
+        self.remaining = I80F48::from(self.remaining)
+            .checked_add(I80F48::from_num(amount))
+            .ok_or_else(math_error!())?
+            .into();
+
+        Ok(())
+    }
+
+    /// Accrue the stream since its last update to `index`, as `Bank::update_emissions_index` does
+    /// for the bank's own emissions: each whole share of the stream's side earns `rate` per year
+    /// on its value, or, if this would exceed `remaining`, the remaining emissions are split pro
+    /// rata between all shares of the side. Only the time between `start_time` and `end_time`
+    /// counts. Shares backing receipt tokens don't earn, see `Bank::get_emissions_asset_shares`.
+    pub fn update_index(&mut self, bank: &Bank, current_timestamp: i64) -> SurroundfiResult {
+        let last_update = self.index_last_update;
+        self.index_last_update = current_timestamp;
+
+        let period_start = max(last_update, self.start_time);
+        let period_end = if self.end_time == 0 {
+            current_timestamp
+        } else {
+            min(current_timestamp, self.end_time)
+        };
+        if period_end <= period_start {
+            return Ok(());
+        }
+
+        let (total_shares, share_value) = if self.is_lending() {
+            (bank.get_emissions_asset_shares()?, bank.asset_share_value)
+        } else {
+            (
+                bank.total_liability_shares.into(),
+                bank.liability_share_value,
+            )
+        };
+        let remaining = I80F48::from(self.remaining);
+        if total_shares == I80F48::ZERO || remaining == I80F48::ZERO {
+            return Ok(());
+        }
+
+        let exponent = EXP_10_I80F48[bank.mint_decimals as usize];
+        let mut index_increase = I80F48::from_num(self.rate)
+            .checked_mul(I80F48::from_num(period_end - period_start))
+            .ok_or_else(math_error!())?
+            .checked_div(SECONDS_PER_YEAR)
+            .ok_or_else(math_error!())?
+            .checked_mul(share_value.into())
+            .ok_or_else(math_error!())?;
+        let mut emissions = index_increase
+            .checked_mul(total_shares)
+            .ok_or_else(math_error!())?
+            .checked_div(exponent)
+            .ok_or_else(math_error!())?;
+
+        if emissions > remaining {
+            debug!(
+                "Stream emissions capped: {} ({} calculated)",
+                remaining, emissions
+            );
+            emissions = remaining;
+            index_increase = remaining
+                .checked_mul(exponent)
+                .ok_or_else(math_error!())?
+                .checked_div(total_shares)
+                .ok_or_else(math_error!())?;
+        }
+
+        self.index = I80F48::from(self.index)
+            .checked_add(index_increase)
+            .ok_or_else(math_error!())?
+            .into();
+        self.remaining = remaining
+            .checked_sub(emissions)
+            .ok_or_else(math_error!())?
+            .into();
+
+        Ok(())
+    }
+}
+
+assert_struct_size!(EmissionsAccrual, 480);
+assert_struct_align!(EmissionsAccrual, 8);
+/// Rewards accrued by one account's balance in one bank, for each stream of the bank's
+/// `EmissionsProgram`. A PDA of the account and bank, it outlives the balance.
+#[account(zero_copy)]
+#[repr(C)]
+#[derive(PartialEq, Eq, TypeLayout)]
+pub struct EmissionsAccrual {
+    pub surroundfi_account: Pubkey,                      // 32
+    pub bank: Pubkey,                                    // 32
+    pub streams: [StreamAccrual; MAX_EMISSIONS_STREAMS], // 48 * 8 = 384
+    pub _padding0: [u64; 4],                             // 32
+}
+
+assert_struct_size!(StreamAccrual, 48);
+assert_struct_align!(StreamAccrual, 8);
+#[repr(C)]
+#[derive(
+    AnchorDeserialize, AnchorSerialize, Copy, Clone, Zeroable, Pod, PartialEq, Eq, TypeLayout,
+)]
+pub struct StreamAccrual {
+    /// Accrued and not yet withdrawn, in native token of the stream mint
+    pub outstanding: WrappedI80F48, // 16
+    /// The stream's `index` when the balance was last settled
+    pub index_checkpoint: WrappedI80F48, // 16
+    /// 0 until the stream is first settled for this balance
+    pub last_update: i64, // 8
+    pub _padding0: [u64; 1], // 8
+}
+
+impl EmissionsAccrual {
+    pub const LEN: usize = std::mem::size_of::<EmissionsAccrual>();
+
+    pub fn initialize(&mut self, surroundfi_account: Pubkey, bank: Pubkey) {
+        self.surroundfi_account = surroundfi_account;
+        self.bank = bank;
+    }
+
+    /// Collect the balance's part of `stream` since the last settlement: `shares` (the balance's
+    /// shares of the stream's side, unchanged since then) times the increase of the stream's
+    /// index. `stream` must be up to date, see `EmissionsStream::update_index`.
+    pub fn settle(
+        &mut self,
+        stream_id: u8,
+        stream: &EmissionsStream,
+        shares: I80F48,
+        mint_decimals: u8,
+        current_timestamp: i64,
+    ) -> SurroundfiResult {
+        let accrual = self
+            .streams
+            .get_mut(stream_id as usize)
+            .ok_or_else(|| error!(SurroundfiError::InvalidEmissionsStream))?;
+
+        let index: I80F48 = stream.index.into();
+        let index_delta = max(
+            index
+                .checked_sub(accrual.index_checkpoint.into())
+                .ok_or_else(math_error!())?,
+            I80F48::ZERO,
+        );
+        let emissions = shares
+            .checked_mul(index_delta)
+            .ok_or_else(math_error!())?
+            .checked_div(EXP_10_I80F48[mint_decimals as usize])
+            .ok_or_else(math_error!())?;
+
+        debug!("Stream {} emissions: {}", stream_id, emissions);
+
+        accrual.outstanding = I80F48::from(accrual.outstanding)
+            .checked_add(emissions)
+            .ok_or_else(math_error!())?
+            .into();
+        accrual.index_checkpoint = index.into();
+        accrual.last_update = current_timestamp;
+
+        Ok(())
+    }
+
+    /// Start accruing `stream` from its current index, without collecting anything.
+    pub fn checkpoint(
+        &mut self,
+        stream_id: u8,
+        stream: &EmissionsStream,
+        current_timestamp: i64,
+    ) -> SurroundfiResult {
+        let accrual = self
+            .streams
+            .get_mut(stream_id as usize)
+            .ok_or_else(|| error!(SurroundfiError::InvalidEmissionsStream))?;
+
+        accrual.index_checkpoint = stream.index;
+        accrual.last_update = current_timestamp;
+
+        Ok(())
+    }
+
+    /// Take the whole-token part of the outstanding rewards of a stream, returns the amount to
+    /// transfer.
+    pub fn take_outstanding(&mut self, stream_id: u8) -> SurroundfiResult<u64> {
+        let accrual = self
+            .streams
+            .get_mut(stream_id as usize)
+            .ok_or_else(|| error!(SurroundfiError::InvalidEmissionsStream))?;
+
+        let outstanding = I80F48::from(accrual.outstanding);
+        let outstanding_floored = outstanding.checked_floor().ok_or_else(math_error!())?;
+        accrual.outstanding = outstanding
+            .checked_sub(outstanding_floored)
+            .ok_or_else(math_error!())?
+            .into();
+
+        Ok(outstanding_floored
+            .checked_to_num::<u64>()
+            .ok_or_else(math_error!())?)
+    }
+}
+
+/// Find the bank's `EmissionsProgram` and the account's `EmissionsAccrual` for the bank in
+/// `remaining_ais`, in any position. None if either is missing.
+pub fn find_emissions_streams_accounts<'info>(
+    surroundfi_account: &Pubkey,
+    bank: &Pubkey,
+    remaining_ais: &'info [AccountInfo<'info>],
+) -> SurroundfiResult<
+    Option<(
+        AccountLoader<'info, EmissionsProgram>,
+        AccountLoader<'info, EmissionsAccrual>,
+    )>,
+> {
+    let mut emissions_program = None;
+    let mut emissions_accrual = None;
+
+    // Sized first: other accounts of this program (e.g. the instruction's own bank) may already be
+    // borrowed
+    let candidates = remaining_ais.iter().filter(|ai| {
+        ai.owner.eq(&crate::ID)
+            && (ai.data_len() == 8 + EmissionsProgram::LEN
+                || ai.data_len() == 8 + EmissionsAccrual::LEN)
+    });
+
+    for ai in candidates {
+        let (is_program, is_accrual) = {
+            let data = ai.try_borrow_data()?;
+            (
+                data.starts_with(&EmissionsProgram::DISCRIMINATOR),
+                data.starts_with(&EmissionsAccrual::DISCRIMINATOR),
+            )
+        };
+
+        if is_program && emissions_program.is_none() {
+            let loader = AccountLoader::<EmissionsProgram>::try_from(ai)?;
+            if loader.load()?.bank.eq(bank) {
+                emissions_program = Some(loader);
+            }
+        } else if is_accrual && emissions_accrual.is_none() {
+            let loader = AccountLoader::<EmissionsAccrual>::try_from(ai)?;
+            let matches = {
+                let accrual = loader.load()?;
+                accrual.surroundfi_account.eq(surroundfi_account) && accrual.bank.eq(bank)
+            };
+            if matches {
+                emissions_accrual = Some(loader);
+            }
+        }
+    }
+
+    Ok(emissions_program.zip(emissions_accrual))
+}
@@ -0,0 +1,167 @@
+use crate::{
+    assert_struct_align, assert_struct_size, check,
+    prelude::{SurroundfiError, SurroundfiResult},
+};
+use anchor_lang::prelude::*;
+use bytemuck::{Pod, Zeroable};
+use type_layout::TypeLayout;
+
+pub const MAX_FEE_RECIPIENTS: usize = 8;
+/// Shares of all recipients must add up to this
+pub const FEE_DISTRIBUTION_TOTAL_BPS: u16 = 10_000;
+
+assert_struct_size!(FeeDistribution, 424);
+assert_struct_align!(FeeDistribution, 8);
+/// How the group's fees are split among recipients, see `lending_pool_distribute_fees`. A PDA of
+/// the group.
+#[account(zero_copy)]
+#[repr(C)]
+#[derive(PartialEq, Eq, TypeLayout)]
+pub struct FeeDistribution {
+    pub group: Pubkey, // 32
+    pub bump: u8,      // 1
+    pub _pad0: [u8; 7],
+    pub recipients: [FeeRecipient; MAX_FEE_RECIPIENTS], // 40 * 8 = 320
+    pub _padding0: [u64; 8],                            // 64
+}
+
+assert_struct_size!(FeeRecipient, 40);
+#[repr(C)]
+#[derive(
+    AnchorDeserialize, AnchorSerialize, Copy, Clone, Zeroable, Pod, PartialEq, Eq, TypeLayout,
+)]
+pub struct FeeRecipient {
+    /// Fees go to the canonical ATA of this wallet. Pubkey default if the slot is unused.
+    pub wallet: Pubkey,
+    /// Share of each distribution, in basis points
+    pub share_bps: u16,
+    pub _pad0: [u8; 6],
+}
+
+impl FeeRecipient {
+    pub fn is_active(&self) -> bool {
+        self.wallet != Pubkey::default()
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FeeRecipientConfig {
+    pub wallet: Pubkey,
+    pub share_bps: u16,
+}
+
+impl FeeDistribution {
+    pub const LEN: usize = std::mem::size_of::<FeeDistribution>();
+
+    pub fn initialize(&mut self, group: Pubkey, bump: u8) {
+        self.group = group;
+        self.bump = bump;
+    }
+
+    /// Replace all recipients. Wallets must be unique and shares must add up to
+    /// `FEE_DISTRIBUTION_TOTAL_BPS`.
+    pub fn set_recipients(&mut self, recipients: &[FeeRecipientConfig]) -> SurroundfiResult {
+        check!(
+            !recipients.is_empty() && recipients.len() <= MAX_FEE_RECIPIENTS,
+            SurroundfiError::InvalidFeeDistribution,
+            "Between 1 and {} recipients",
+            MAX_FEE_RECIPIENTS
+        );
+
+        let mut total_bps: u32 = 0;
+        for (i, recipient) in recipients.iter().enumerate() {
+            check!(
+                recipient.wallet != Pubkey::default() && recipient.share_bps > 0,
+                SurroundfiError::InvalidFeeDistribution
+            );
+            check!(
+                !recipients[..i]
+                    .iter()
+                    .any(|other| other.wallet == recipient.wallet),
+                SurroundfiError::InvalidFeeDistribution,
+                "Duplicate recipient"
+            );
+            total_bps += recipient.share_bps as u32;
+        }
+        check!(
+            total_bps == FEE_DISTRIBUTION_TOTAL_BPS as u32,
+            SurroundfiError::InvalidFeeDistribution,
+            "Shares must add up to {} bps",
+            FEE_DISTRIBUTION_TOTAL_BPS
+        );
+
+        self.recipients = [FeeRecipient::zeroed(); MAX_FEE_RECIPIENTS];
+        for (slot, recipient) in self.recipients.iter_mut().zip(recipients) {
+            slot.wallet = recipient.wallet;
+            slot.share_bps = recipient.share_bps;
+        }
+
+        Ok(())
+    }
+
+    pub fn active_recipients(&self) -> impl Iterator<Item = &FeeRecipient> {
+        self.recipients
+            .iter()
+            .filter(|recipient| recipient.is_active())
+    }
+
+    /// Split `amount` by share, rounding down. The remainder is left for the next distribution.
+    pub fn split(&self, amount: u64) -> Vec<u64> {
+        self.active_recipients()
+            .map(|recipient| {
+                (amount as u128 * recipient.share_bps as u128 / FEE_DISTRIBUTION_TOTAL_BPS as u128)
+                    as u64
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn config(share_bps: u16) -> FeeRecipientConfig {
+        FeeRecipientConfig {
+            wallet: Pubkey::new_unique(),
+            share_bps,
+        }
+    }
+
+    #[test]
+    fn set_recipients_and_split() {
+        let mut fee_distribution = FeeDistribution::zeroed();
+
+        fee_distribution
+            .set_recipients(&[config(7_000), config(2_500), config(500)])
+            .unwrap();
+        assert_eq!(fee_distribution.active_recipients().count(), 3);
+        assert_eq!(fee_distribution.split(1_001), vec![700, 250, 50]);
+
+        // Replacing recipients clears the old ones
+        fee_distribution.set_recipients(&[config(10_000)]).unwrap();
+        assert_eq!(fee_distribution.active_recipients().count(), 1);
+        assert_eq!(fee_distribution.split(1_001), vec![1_001]);
+    }
+
+    #[test]
+    fn set_recipients_invalid() {
+        let mut fee_distribution = FeeDistribution::zeroed();
+
+        assert!(fee_distribution.set_recipients(&[]).is_err());
+        assert!(fee_distribution
+            .set_recipients(&[config(5_000), config(4_000)])
+            .is_err());
+        assert!(fee_distribution
+            .set_recipients(&[config(10_000), config(0)])
+            .is_err());
+
+        let duplicate = config(5_000);
+        assert!(fee_distribution
+            .set_recipients(&[duplicate, duplicate])
+            .is_err());
+
+        let too_many = vec![config(1_000); MAX_FEE_RECIPIENTS + 1];
+        assert!(fee_distribution.set_recipients(&too_many).is_err());
+    }
+}
@@ -7,15 +7,16 @@ use type_layout::TypeLayout;
 pub const HEALTHY: u64 = 1;
 pub const ENGINE_OK: u64 = 2;
 
-assert_struct_size!(HealthCache, 304);
+assert_struct_size!(HealthCache, 384);
 assert_struct_align!(HealthCache, 8);
 #[repr(C)]
 #[derive(
     AnchorDeserialize, AnchorSerialize, Copy, Clone, Zeroable, Pod, PartialEq, Eq, TypeLayout, Debug,
 )]
 /// A read-only cache of the internal risk engine's information. Only valid in borrow/withdraw if
-/// the tx does not fail. To see the state in any context, e.g. to figure out if the risk engine is
-/// failing due to some bad price information, use `pulse_health`.
+/// the tx does not fail, and updated for the liquidatee after a liquidation. To see the state in
+/// any context, e.g. to figure out if the risk engine is failing due to some bad price
+/// information, use `pulse_health`.
 pub struct HealthCache {
     pub asset_value: WrappedI80F48,
     pub liability_value: WrappedI80F48,
@@ -32,9 +33,23 @@ pub struct HealthCache {
     pub flags: u64,
     /// Each price corresponds to that index of Balances in the LendingAccount. Useful for debugging
     /// or liquidator consumption, to determine how a user's position is priced internally.
+    /// * Balances that don't fit continue in `SurroundfiAccountExtension::health_cache_prices`
     /// * If a price overflows u64, shows u64::MAX
     /// * If a price is negative for some reason (as several oracles support), pulse will panic
     pub prices: [WrappedI80F48; MAX_LENDING_ACCOUNT_BALANCES],
+    /// Maintenance-weighted value of all assets. The account can be liquidated once this is below
+    /// `liability_value_maint`. Accounts with an extension also record the value of each balance
+    /// there, see `SurroundfiAccountExtension::health_cache_balance_values`.
+    pub asset_value_maint: WrappedI80F48,
+    pub liability_value_maint: WrappedI80F48,
+    /// Unweighted value of all assets
+    pub asset_value_equity: WrappedI80F48,
+    /// Unweighted value of all liabilities
+    pub liability_value_equity: WrappedI80F48,
+    /// Only for accounts with a single collateral and at least one liability: the price of that
+    /// collateral at which the account becomes liquidatable, all other prices unchanged. Zero
+    /// otherwise.
+    pub liquidation_price: WrappedI80F48,
 }
 
 impl HealthCache {
@@ -64,3 +79,17 @@ impl HealthCache {
         }
     }
 }
+
+assert_struct_size!(HealthCacheBalanceValues, 32);
+assert_struct_align!(HealthCacheBalanceValues, 8);
+#[repr(C)]
+#[derive(
+    AnchorDeserialize, AnchorSerialize, Copy, Clone, Zeroable, Pod, PartialEq, Eq, TypeLayout, Debug,
+)]
+/// The maintenance-weighted value of a single balance, see
+/// `SurroundfiAccountExtension::health_cache_balance_values`. Zero for inactive balances and for
+/// balances that couldn't be priced.
+pub struct HealthCacheBalanceValues {
+    pub asset_value_maint: WrappedI80F48,
+    pub liability_value_maint: WrappedI80F48,
+}
@@ -0,0 +1,185 @@
+use crate::{
+    assert_struct_align, assert_struct_size, check, math_error,
+    prelude::{SurroundfiError, SurroundfiResult},
+    state::surroundfi_group::WrappedI80F48,
+};
+use anchor_lang::prelude::*;
+use fixed::types::I80F48;
+use type_layout::TypeLayout;
+
+assert_struct_size!(InsuranceStake, 176);
+assert_struct_align!(InsuranceStake, 8);
+/// A staker's position in the insurance fund of a bank, see `lending_pool_insurance_stake`. A PDA
+/// of the bank and the staker.
+#[account(zero_copy)]
+#[repr(C)]
+#[derive(PartialEq, Eq, TypeLayout)]
+pub struct InsuranceStake {
+    pub bank: Pubkey,      // 32
+    pub authority: Pubkey, // 32
+    pub bump: u8,          // 1
+    pub _pad0: [u8; 7],
+    /// Staking shares, including those waiting to be unstaked
+    pub shares: WrappedI80F48,
+    /// Shares requested to be unstaked. They stop earning fees, but can still be slashed until
+    /// withdrawn.
+    pub unstaking_shares: WrappedI80F48,
+    /// When the unstake was requested, 0 if there is no pending request
+    pub unstake_requested_at: i64,
+    /// Value of `unstaking_shares` when the unstake was requested, the most they can be withdrawn
+    /// for
+    pub unstaking_amount: WrappedI80F48,
+    pub _padding0: [u64; 6], // 48
+}
+
+impl InsuranceStake {
+    pub const LEN: usize = std::mem::size_of::<InsuranceStake>();
+
+    pub fn initialize(&mut self, bank: Pubkey, authority: Pubkey, bump: u8) {
+        self.bank = bank;
+        self.authority = authority;
+        self.bump = bump;
+    }
+
+    pub fn add_shares(&mut self, shares: I80F48) -> SurroundfiResult {
+        self.shares = I80F48::from(self.shares)
+            .checked_add(shares)
+            .ok_or_else(math_error!())?
+            .into();
+
+        Ok(())
+    }
+
+    pub fn remove_shares(&mut self, shares: I80F48) -> SurroundfiResult {
+        self.shares = I80F48::from(self.shares)
+            .checked_sub(shares)
+            .ok_or_else(math_error!())?
+            .into();
+
+        Ok(())
+    }
+
+    /// Request to unstake `shares` currently worth `amount`, replacing any pending request. The
+    /// cooldown restarts.
+    pub fn request_unstake(
+        &mut self,
+        shares: I80F48,
+        amount: I80F48,
+        current_timestamp: i64,
+    ) -> SurroundfiResult {
+        check!(
+            shares > I80F48::ZERO && shares <= I80F48::from(self.shares),
+            SurroundfiError::InsufficientInsuranceStake
+        );
+
+        self.unstaking_shares = shares.into();
+        self.unstaking_amount = amount.into();
+        self.unstake_requested_at = current_timestamp;
+
+        Ok(())
+    }
+
+    /// Remove the pending unstake once `cooldown` seconds have passed since it was requested, and
+    /// before `window` more seconds have. Returns the shares to burn and the most they can be
+    /// withdrawn for.
+    pub fn take_unstaking_shares(
+        &mut self,
+        cooldown: i64,
+        window: i64,
+        current_timestamp: i64,
+    ) -> SurroundfiResult<(I80F48, I80F48)> {
+        let unstaking_shares: I80F48 = self.unstaking_shares.into();
+        check!(
+            unstaking_shares > I80F48::ZERO,
+            SurroundfiError::InsufficientInsuranceStake,
+            "No unstake requested"
+        );
+        check!(
+            current_timestamp >= self.unstake_requested_at.saturating_add(cooldown),
+            SurroundfiError::InsuranceUnstakeCooldown
+        );
+        check!(
+            current_timestamp
+                <= self
+                    .unstake_requested_at
+                    .saturating_add(cooldown)
+                    .saturating_add(window),
+            SurroundfiError::InsuranceUnstakeWindowExpired
+        );
+        let unstaking_amount: I80F48 = self.unstaking_amount.into();
+
+        self.shares = I80F48::from(self.shares)
+            .checked_sub(unstaking_shares)
+            .ok_or_else(math_error!())?
+            .into();
+        self.unstaking_shares = I80F48::ZERO.into();
+        self.unstaking_amount = I80F48::ZERO.into();
+        self.unstake_requested_at = 0;
+
+        Ok((unstaking_shares, unstaking_amount))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytemuck::Zeroable;
+    use fixed_macro::types::I80F48;
+
+    #[test]
+    fn insurance_stake_unstake_after_cooldown() {
+        let mut stake = InsuranceStake::zeroed();
+        stake.add_shares(I80F48!(100)).unwrap();
+
+        assert!(stake
+            .request_unstake(I80F48!(101), I80F48!(101), 1_000)
+            .is_err());
+        assert!(stake
+            .request_unstake(I80F48::ZERO, I80F48::ZERO, 1_000)
+            .is_err());
+        assert!(stake.take_unstaking_shares(60, 30, 1_000).is_err());
+
+        stake
+            .request_unstake(I80F48!(40), I80F48!(40), 1_000)
+            .unwrap();
+        assert!(stake.take_unstaking_shares(60, 30, 1_059).is_err());
+
+        // A new request restarts the cooldown
+        stake
+            .request_unstake(I80F48!(50), I80F48!(55), 1_030)
+            .unwrap();
+        assert!(stake.take_unstaking_shares(60, 30, 1_060).is_err());
+        assert_eq!(
+            stake.take_unstaking_shares(60, 30, 1_090).unwrap(),
+            (I80F48!(50), I80F48!(55))
+        );
+
+        assert_eq!(I80F48::from(stake.shares), I80F48!(50));
+        assert_eq!(I80F48::from(stake.unstaking_shares), I80F48::ZERO);
+        assert_eq!(I80F48::from(stake.unstaking_amount), I80F48::ZERO);
+        assert_eq!(stake.unstake_requested_at, 0);
+        assert!(stake.take_unstaking_shares(60, 30, 2_000).is_err());
+    }
+
+    #[test]
+    fn insurance_stake_unstake_window_expires() {
+        let mut stake = InsuranceStake::zeroed();
+        stake.add_shares(I80F48!(100)).unwrap();
+
+        stake
+            .request_unstake(I80F48!(40), I80F48!(40), 1_000)
+            .unwrap();
+        assert!(stake.take_unstaking_shares(60, 30, 1_091).is_err());
+        assert_eq!(I80F48::from(stake.unstaking_shares), I80F48!(40));
+
+        // Requesting again restarts the cooldown and the window
+        stake
+            .request_unstake(I80F48!(40), I80F48!(40), 1_091)
+            .unwrap();
+        assert_eq!(
+            stake.take_unstaking_shares(60, 30, 1_181).unwrap(),
+            (I80F48!(40), I80F48!(40))
+        );
+        assert_eq!(I80F48::from(stake.shares), I80F48!(60));
+    }
+}
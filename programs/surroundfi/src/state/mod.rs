@@ -1,6 +1,11 @@
+pub mod emissions_program;
+pub mod fee_distribution;
 pub mod fee_state;
 pub mod health_cache;
+pub mod insurance_stake;
 pub mod price;
+pub mod referrer_fees;
 pub mod staked_settings;
 pub mod surroundfi_account;
+pub mod surroundfi_account_extension;
 pub mod surroundfi_group;
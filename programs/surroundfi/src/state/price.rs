@@ -24,7 +24,7 @@ use crate::{
     prelude::*,
 };
 
-use super::surroundfi_group::BankConfig;
+use super::surroundfi_group::{Bank, BankConfig};
 use anchor_lang::prelude::borsh;
 use pyth_solana_receiver_sdk::PYTH_PUSH_ORACLE_ID;
 
@@ -37,6 +37,7 @@ pub enum OracleSetup {
     PythPushOracle,
     SwitchboardPull,
     StakedWithPythPush,
+    ReceiptToken,
 }
 unsafe impl Zeroable for OracleSetup {}
 unsafe impl Pod for OracleSetup {}
@@ -50,6 +51,7 @@ impl OracleSetup {
             3 => Some(Self::PythPushOracle),
             4 => Some(Self::SwitchboardPull),
             5 => Some(Self::StakedWithPythPush),
+            6 => Some(Self::ReceiptToken),
             _ => None,
         }
     }
@@ -86,6 +88,7 @@ pub enum OraclePriceFeedAdapter {
     SwitchboardV2(SwitchboardV2PriceFeed),
     PythPushOracle(PythPushOraclePriceFeed),
     SwitchboardPull(SwitchboardPullPriceFeed),
+    ReceiptToken(ReceiptTokenPriceFeed),
 }
 
 impl OraclePriceFeedAdapter {
@@ -186,6 +189,23 @@ impl OraclePriceFeedAdapter {
                     SwitchboardPullPriceFeed::load_checked(&ais[0], clock.unix_timestamp, max_age)?,
                 ))
             }
+            OracleSetup::ReceiptToken => {
+                check!(ais.len() == 2, SurroundfiError::WrongNumberOfOracleAccounts);
+                if ais[0].key != &bank_config.oracle_keys[0] {
+                    msg!(
+                        "Expected underlying bank: {:?}, got: {:?}",
+                        bank_config.oracle_keys[0],
+                        ais[0].key
+                    );
+                    return Err(error!(SurroundfiError::WrongOracleAccountKeys));
+                }
+
+                let underlying_bank = ReceiptTokenPriceFeed::load_underlying_bank(&ais[0])?;
+
+                Ok(OraclePriceFeedAdapter::ReceiptToken(
+                    ReceiptTokenPriceFeed::load_checked(&underlying_bank, &ais[1..], clock)?,
+                ))
+            }
             OracleSetup::StakedWithPythPush => {
                 check!(ais.len() == 3, SurroundfiError::WrongNumberOfOracleAccounts);
 
@@ -379,6 +399,29 @@ impl OraclePriceFeedAdapter {
 
                 Ok(())
             }
+            OracleSetup::ReceiptToken => {
+                check!(
+                    oracle_ais.len() == 2,
+                    SurroundfiError::WrongNumberOfOracleAccounts
+                );
+                if oracle_ais[0].key != &bank_config.oracle_keys[0] {
+                    msg!(
+                        "Expected underlying bank: {:?}, got: {:?}",
+                        bank_config.oracle_keys[0],
+                        oracle_ais[0].key
+                    );
+                    return Err(error!(SurroundfiError::WrongOracleAccountKeys));
+                }
+
+                let underlying_bank = ReceiptTokenPriceFeed::load_underlying_bank(&oracle_ais[0])?;
+                check!(
+                    underlying_bank.has_receipt_mint(),
+                    SurroundfiError::ReceiptMintNotSetup
+                );
+                ReceiptTokenPriceFeed::check_underlying_oracle_setup(&underlying_bank.config)?;
+
+                Self::validate_bank_config(&underlying_bank.config, &oracle_ais[1..], None, None, None)
+            }
             OracleSetup::StakedWithPythPush => {
                 if lst_mint.is_some() && stake_pool.is_some() && sol_pool.is_some() {
                     check!(
@@ -468,6 +511,94 @@ impl OraclePriceFeedAdapter {
     }
 }
 
+/// Prices receipt tokens of a bank (see `Bank::receipt_mint`): one receipt token is one asset share
+/// of the underlying bank, so its price is the underlying price times `asset_share_value`. Receipt
+/// tokens use the decimals of the underlying mint, so no decimal adjustment is needed.
+///
+/// Note: uses the share value as of the underlying bank's last interest accrual, which slightly
+/// understates the price if interest is pending.
+#[cfg_attr(feature = "client", derive(Clone))]
+pub struct ReceiptTokenPriceFeed {
+    underlying: Box<OraclePriceFeedAdapter>,
+    asset_share_value: I80F48,
+}
+
+impl ReceiptTokenPriceFeed {
+    /// * `ais` - the oracle of the underlying bank
+    ///
+    /// Note: underlying banks with an interest-bearing mint are not supported, since pricing
+    /// their receipts would also need the underlying mint.
+    pub fn load_checked<'info>(
+        underlying_bank: &Bank,
+        ais: &'info [AccountInfo<'info>],
+        clock: &Clock,
+    ) -> SurroundfiResult<Self> {
+        Self::check_underlying_oracle_setup(&underlying_bank.config)?;
+        check!(
+            !underlying_bank.is_interest_bearing(),
+            SurroundfiError::InvalidOracleSetup
+        );
+
+        let underlying =
+            OraclePriceFeedAdapter::try_from_bank_config(&underlying_bank.config, ais, clock)?;
+
+        Ok(Self {
+            underlying: Box::new(underlying),
+            asset_share_value: underlying_bank.asset_share_value.into(),
+        })
+    }
+
+    /// Reads the bank backing a receipt token. We can't use an `AccountLoader` here without the
+    /// `'info` lifetime, so the bank is interpreted from bytes, repeating the sanity checks of
+    /// `AccountLoader`.
+    pub fn load_underlying_bank(ai: &AccountInfo) -> SurroundfiResult<Bank> {
+        check!(ai.owner == &crate::ID, SurroundfiError::InvalidOracleSetup);
+
+        let data = ai.try_borrow_data()?;
+        check!(
+            data.len() >= Bank::LEN + 8,
+            SurroundfiError::InvalidOracleSetup
+        );
+        let discrim: &[u8] = &data[0..8];
+        check!(
+            discrim == Bank::DISCRIMINATOR,
+            SurroundfiError::InvalidOracleSetup
+        );
+
+        Ok(*bytemuck::from_bytes(&data[8..Bank::LEN + 8]))
+    }
+
+    /// The underlying bank must be priced by a single oracle account
+    pub fn check_underlying_oracle_setup(underlying_config: &BankConfig) -> SurroundfiResult {
+        check!(
+            matches!(
+                underlying_config.oracle_setup,
+                OracleSetup::PythLegacy
+                    | OracleSetup::SwitchboardV2
+                    | OracleSetup::PythPushOracle
+                    | OracleSetup::SwitchboardPull
+            ),
+            SurroundfiError::InvalidOracleSetup
+        );
+
+        Ok(())
+    }
+}
+
+impl PriceAdapter for ReceiptTokenPriceFeed {
+    fn get_price_of_type(
+        &self,
+        price_type: OraclePriceType,
+        bias: Option<PriceBias>,
+    ) -> SurroundfiResult<I80F48> {
+        let underlying_price = self.underlying.get_price_of_type(price_type, bias)?;
+
+        Ok(underlying_price
+            .checked_mul(self.asset_share_value)
+            .ok_or_else(math_error!())?)
+    }
+}
+
 #[cfg_attr(feature = "client", derive(Clone, Debug))]
 pub struct PythLegacyPriceFeed {
     ema_price: Box<Price>,
@@ -0,0 +1,259 @@
+use crate::{
+    assert_struct_align, assert_struct_size,
+    events::ReferrerEventStats,
+    math_error,
+    prelude::{SurroundfiError, SurroundfiResult},
+    state::surroundfi_group::WrappedI80F48,
+};
+use anchor_lang::{prelude::*, Discriminator};
+use bytemuck::{Pod, Zeroable};
+use fixed::types::I80F48;
+use type_layout::TypeLayout;
+
+pub const MAX_REFERRER_FEE_BALANCES: usize = 16;
+
+assert_struct_size!(ReferrerFees, 1416);
+assert_struct_align!(ReferrerFees, 8);
+/// Origination fees earned by a referrer (e.g. a front-end or aggregator) on borrows it routed to
+/// a group, see `lending_account_borrow`. A PDA of the group and the referrer.
+#[account(zero_copy)]
+#[repr(C)]
+#[derive(PartialEq, Eq, TypeLayout)]
+pub struct ReferrerFees {
+    pub group: Pubkey,    // 32
+    pub referrer: Pubkey, // 32
+    pub bump: u8,         // 1
+    pub _pad0: [u8; 7],
+    pub balances: [ReferrerFeeBalance; MAX_REFERRER_FEE_BALANCES], // 80 * 16 = 1280
+    pub _padding0: [u64; 8],                                       // 64
+}
+
+assert_struct_size!(ReferrerFeeBalance, 80);
+assert_struct_align!(ReferrerFeeBalance, 8);
+#[repr(C)]
+#[derive(
+    AnchorDeserialize, AnchorSerialize, Copy, Clone, Zeroable, Pod, PartialEq, Eq, TypeLayout,
+)]
+pub struct ReferrerFeeBalance {
+    /// Pubkey default if the slot is unused
+    pub bank: Pubkey,
+    /// Fees earned but not yet claimed, in native tokens of the bank's mint
+    pub outstanding: WrappedI80F48,
+    /// All fees ever earned on this bank, in native tokens of the bank's mint
+    pub lifetime_fees: WrappedI80F48,
+    /// Native amount of all borrows referred on this bank, including the origination fee
+    pub referred_borrows: u64,
+    /// Native amount of all deposits referred on this bank
+    pub referred_deposits: u64,
+}
+
+impl ReferrerFeeBalance {
+    pub fn is_active(&self) -> bool {
+        self.bank != Pubkey::default()
+    }
+}
+
+impl ReferrerFees {
+    pub const LEN: usize = std::mem::size_of::<ReferrerFees>();
+
+    pub fn initialize(&mut self, group: Pubkey, referrer: Pubkey, bump: u8) {
+        self.group = group;
+        self.referrer = referrer;
+        self.bump = bump;
+    }
+
+    pub fn get_balance(&self, bank_pk: &Pubkey) -> Option<&ReferrerFeeBalance> {
+        self.balances
+            .iter()
+            .find(|balance| balance.bank.eq(bank_pk))
+    }
+
+    pub fn get_balance_mut(&mut self, bank_pk: &Pubkey) -> Option<&mut ReferrerFeeBalance> {
+        self.balances
+            .iter_mut()
+            .find(|balance| balance.bank.eq(bank_pk))
+    }
+
+    /// Find the balance of the given bank, or take the first free slot for it.
+    pub fn find_or_create_balance(
+        &mut self,
+        bank_pk: &Pubkey,
+    ) -> SurroundfiResult<&mut ReferrerFeeBalance> {
+        let index = match self
+            .balances
+            .iter()
+            .position(|balance| balance.bank.eq(bank_pk))
+        {
+            Some(index) => index,
+            None => {
+                let index = self
+                    .balances
+                    .iter()
+                    .position(|balance| !balance.is_active())
+                    .ok_or_else(|| error!(SurroundfiError::ReferrerFeeBalancesFull))?;
+                self.balances[index] = ReferrerFeeBalance {
+                    bank: *bank_pk,
+                    ..ReferrerFeeBalance::zeroed()
+                };
+                index
+            }
+        };
+
+        Ok(&mut self.balances[index])
+    }
+
+    /// Credit a referred borrow of `amount` (including the origination fee), of which `fee` goes to
+    /// the referrer.
+    pub fn record_borrow(
+        &mut self,
+        bank_pk: &Pubkey,
+        amount: u64,
+        fee: I80F48,
+    ) -> SurroundfiResult {
+        let balance = self.find_or_create_balance(bank_pk)?;
+        balance.outstanding = I80F48::from(balance.outstanding)
+            .checked_add(fee)
+            .ok_or_else(math_error!())?
+            .into();
+        balance.lifetime_fees = I80F48::from(balance.lifetime_fees)
+            .checked_add(fee)
+            .ok_or_else(math_error!())?
+            .into();
+        balance.referred_borrows = balance.referred_borrows.saturating_add(amount);
+
+        Ok(())
+    }
+
+    pub fn record_deposit(&mut self, bank_pk: &Pubkey, amount: u64) -> SurroundfiResult {
+        let balance = self.find_or_create_balance(bank_pk)?;
+        balance.referred_deposits = balance.referred_deposits.saturating_add(amount);
+
+        Ok(())
+    }
+
+    /// Totals of the given bank for the deposit and borrow events, `fee` is what the action earned.
+    pub fn event_stats(&self, bank_pk: &Pubkey, fee: u64) -> ReferrerEventStats {
+        let balance = self
+            .get_balance(bank_pk)
+            .copied()
+            .unwrap_or_else(ReferrerFeeBalance::zeroed);
+
+        ReferrerEventStats {
+            referrer: self.referrer,
+            fee,
+            lifetime_fees: I80F48::from(balance.lifetime_fees).to_num(),
+            referred_borrows: balance.referred_borrows,
+            referred_deposits: balance.referred_deposits,
+        }
+    }
+
+    /// Take up to `available` whole tokens of the outstanding fees of the given bank. Returns the
+    /// amount taken, fractions of a token stay outstanding.
+    pub fn claim(&mut self, bank_pk: &Pubkey, available: u64) -> SurroundfiResult<u64> {
+        let Some(balance) = self.get_balance_mut(bank_pk) else {
+            return Ok(0);
+        };
+
+        let outstanding: I80F48 = balance.outstanding.into();
+        let claimed = outstanding.min(I80F48::from_num(available)).int();
+        balance.outstanding = outstanding
+            .checked_sub(claimed)
+            .ok_or_else(math_error!())?
+            .into();
+
+        Ok(claimed.checked_to_num().ok_or_else(math_error!())?)
+    }
+}
+
+/// If the next remaining account is a `ReferrerFees` account, take it off `remaining_accounts`.
+/// Referrer fees are optional, so any other account (e.g. the first bank for the risk engine) is
+/// left in place.
+///
+/// Errors if the referrer fees account belongs to another group, or if the referrer is the
+/// `authority` of the account, who would otherwise earn back part of its own fees.
+pub fn maybe_take_referrer_fees<'info>(
+    remaining_accounts: &mut &'info [AccountInfo<'info>],
+    group: &Pubkey,
+    authority: &Pubkey,
+) -> SurroundfiResult<Option<AccountLoader<'info, ReferrerFees>>> {
+    let Some((maybe_referrer_fees, remaining)) = remaining_accounts.split_first() else {
+        return Ok(None);
+    };
+
+    let is_referrer_fees = maybe_referrer_fees.owner.eq(&crate::ID)
+        && maybe_referrer_fees
+            .try_borrow_data()?
+            .starts_with(&ReferrerFees::DISCRIMINATOR);
+    if !is_referrer_fees {
+        return Ok(None);
+    }
+    *remaining_accounts = remaining;
+
+    let referrer_fees = AccountLoader::<ReferrerFees>::try_from(maybe_referrer_fees)?;
+    if !referrer_fees.load()?.group.eq(group) {
+        return err!(SurroundfiError::InvalidReferrerFees);
+    }
+    if referrer_fees.load()?.referrer.eq(authority) {
+        return err!(SurroundfiError::SelfReferral);
+    }
+
+    Ok(Some(referrer_fees))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fixed_macro::types::I80F48;
+
+    #[test]
+    fn referrer_fees_record_and_claim() {
+        let mut referrer_fees = ReferrerFees::zeroed();
+        let bank_a = Pubkey::new_unique();
+        let bank_b = Pubkey::new_unique();
+
+        referrer_fees
+            .record_borrow(&bank_a, 1_010, I80F48!(2.5))
+            .unwrap();
+        referrer_fees
+            .record_borrow(&bank_a, 505, I80F48!(1.25))
+            .unwrap();
+        referrer_fees.record_deposit(&bank_b, 2_000).unwrap();
+
+        let balance_a = referrer_fees.get_balance(&bank_a).unwrap();
+        assert_eq!(I80F48::from(balance_a.outstanding), I80F48!(3.75));
+        assert_eq!(I80F48::from(balance_a.lifetime_fees), I80F48!(3.75));
+        assert_eq!(balance_a.referred_borrows, 1_515);
+        assert_eq!(
+            referrer_fees
+                .get_balance(&bank_b)
+                .unwrap()
+                .referred_deposits,
+            2_000
+        );
+
+        // Limited by what is available, then only whole tokens
+        assert_eq!(referrer_fees.claim(&bank_a, 2).unwrap(), 2);
+        assert_eq!(referrer_fees.claim(&bank_a, 100).unwrap(), 1);
+        let balance_a = referrer_fees.get_balance(&bank_a).unwrap();
+        assert_eq!(I80F48::from(balance_a.outstanding), I80F48!(0.75));
+        assert_eq!(I80F48::from(balance_a.lifetime_fees), I80F48!(3.75));
+
+        // Nothing to claim on a bank without fees
+        assert_eq!(referrer_fees.claim(&bank_b, 100).unwrap(), 0);
+        assert_eq!(referrer_fees.claim(&Pubkey::new_unique(), 100).unwrap(), 0);
+    }
+
+    #[test]
+    fn referrer_fees_balances_full() {
+        let mut referrer_fees = ReferrerFees::zeroed();
+        for _ in 0..MAX_REFERRER_FEE_BALANCES {
+            referrer_fees
+                .record_deposit(&Pubkey::new_unique(), 1)
+                .unwrap();
+        }
+
+        assert!(referrer_fees
+            .record_borrow(&Pubkey::new_unique(), 1, I80F48::ONE)
+            .is_err());
+    }
+}
@@ -34,17 +34,24 @@ pub struct StakedSettings {
     pub risk_tier: RiskTier,
     _pad0: [u8; 5],
 
-    /// The following values are irrelevant because staked collateral positions do not support
-    /// borrowing.
+    /// Liability weights of `ASSET_TAG_STABLE` borrows of accounts with staked collateral, in
+    /// groups with `STAKED_STABLE_BORROW_ENABLED`. Staked collateral banks carry these as their own
+    /// liability weights (staked collateral itself can't be borrowed), and the risk engine applies
+    /// them to Stable liabilities of accounts with a staked position if stricter than the Stable
+    /// bank's weights. Zero means `DEFAULT_STABLE_LIABILITY_WEIGHT_INIT`/`_MAINT`.
+    pub stable_liability_weight_init: WrappedI80F48,
+    pub stable_liability_weight_maint: WrappedI80F48,
+
+    /// The following values are irrelevant because staked collateral positions can't be borrowed.
     // * interest_config,
-    // * liability_weight_init
-    // * liability_weight_maint
     // * borrow_limit
     _reserved0: [u8; 8],
-    _reserved1: [u8; 32],
     _reserved2: [u8; 64],
 }
 
+pub const DEFAULT_STABLE_LIABILITY_WEIGHT_INIT: I80F48 = I80F48!(1.5);
+pub const DEFAULT_STABLE_LIABILITY_WEIGHT_MAINT: I80F48 = I80F48!(1.25);
+
 impl StakedSettings {
     pub const LEN: usize = std::mem::size_of::<StakedSettings>();
 
@@ -58,6 +65,8 @@ impl StakedSettings {
         total_asset_value_init_limit: u64,
         oracle_max_age: u16,
         risk_tier: RiskTier,
+        stable_liability_weight_init: WrappedI80F48,
+        stable_liability_weight_maint: WrappedI80F48,
     ) -> Self {
         StakedSettings {
             key,
@@ -69,6 +78,8 @@ impl StakedSettings {
             total_asset_value_init_limit,
             oracle_max_age,
             risk_tier,
+            stable_liability_weight_init,
+            stable_liability_weight_maint,
             ..Default::default()
         }
     }
@@ -77,9 +88,23 @@ impl StakedSettings {
         self.surroundfi_group = surroundfi_group;
     }
 
-    /// Same as `bank.validate()`, except that liability rates and interest rates do not exist in
-    /// this context (since Staked Collateral accounts cannot be borrowed against and such Banks
-    /// will use placeholders for those values)
+    /// Liability weights (init, maint) of Stable borrows against staked collateral, see
+    /// `stable_liability_weight_init`
+    pub fn get_stable_liability_weights(&self) -> (WrappedI80F48, WrappedI80F48) {
+        let init = I80F48::from(self.stable_liability_weight_init);
+        let maint = I80F48::from(self.stable_liability_weight_maint);
+        if init == I80F48::ZERO && maint == I80F48::ZERO {
+            (
+                DEFAULT_STABLE_LIABILITY_WEIGHT_INIT.into(),
+                DEFAULT_STABLE_LIABILITY_WEIGHT_MAINT.into(),
+            )
+        } else {
+            (init.into(), maint.into())
+        }
+    }
+
+    /// Same as `bank.validate()`, except that interest rates do not exist in this context (since
+    /// Staked Collateral banks cannot be borrowed from and will use placeholders for those values)
     pub fn validate(&self) -> SurroundfiResult {
         let asset_init_w = I80F48::from(self.asset_weight_init);
         let asset_maint_w = I80F48::from(self.asset_weight_maint);
@@ -90,6 +115,14 @@ impl StakedSettings {
         );
         check!(asset_maint_w >= asset_init_w, SurroundfiError::InvalidConfig);
 
+        let (liab_init_w, liab_maint_w) = self.get_stable_liability_weights();
+        let (liab_init_w, liab_maint_w) = (I80F48::from(liab_init_w), I80F48::from(liab_maint_w));
+        check!(liab_init_w >= I80F48::ONE, SurroundfiError::InvalidConfig);
+        check!(
+            liab_maint_w <= liab_init_w && liab_maint_w >= I80F48::ONE,
+            SurroundfiError::InvalidConfig
+        );
+
         if self.risk_tier == RiskTier::Isolated {
             check!(asset_init_w == I80F48::ZERO, SurroundfiError::InvalidConfig);
             check!(asset_maint_w == I80F48::ZERO, SurroundfiError::InvalidConfig);
@@ -112,8 +145,9 @@ impl Default for StakedSettings {
             oracle_max_age: 10,
             risk_tier: RiskTier::Collateral,
             _pad0: [0; 5],
+            stable_liability_weight_init: DEFAULT_STABLE_LIABILITY_WEIGHT_INIT.into(),
+            stable_liability_weight_maint: DEFAULT_STABLE_LIABILITY_WEIGHT_MAINT.into(),
             _reserved0: [0; 8],
-            _reserved1: [0; 32],
             _reserved2: [0; 64],
         }
     }
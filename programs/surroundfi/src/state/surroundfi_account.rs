@@ -1,24 +1,30 @@
 use super::{
-    health_cache::HealthCache,
+    emissions_program::{find_emissions_streams_accounts, EmissionsAccrual, EmissionsProgram},
+    health_cache::{HealthCache, HealthCacheBalanceValues},
+    surroundfi_account_extension::{
+        find_account_extension, SurroundfiAccountExtension, MAX_EXTENSION_BALANCES,
+    },
     surroundfi_group::{Bank, RiskTier, WrappedI80F48},
     price::{OraclePriceFeedAdapter, OraclePriceType, PriceAdapter, PriceBias},
 };
 use crate::{
     assert_struct_align, assert_struct_size, check,
     constants::{
-        ASSET_TAG_DEFAULT, ASSET_TAG_SOL, ASSET_TAG_STAKED, BANKRUPT_THRESHOLD,
-        EMISSIONS_FLAG_BORROW_ACTIVE, EMISSIONS_FLAG_LENDING_ACTIVE, EMPTY_BALANCE_THRESHOLD,
-        EXP_10_I80F48, MIN_EMISSIONS_START_TIME, SECONDS_PER_YEAR, ZERO_AMOUNT_THRESHOLD,
+        ASSET_TAG_DEFAULT, ASSET_TAG_RECEIPT, ASSET_TAG_STABLE, ASSET_TAG_STAKED,
+        EMISSIONS_FLAG_BORROW_ACTIVE, EMISSIONS_FLAG_LENDING_ACTIVE,
+        EMISSIONS_INDEX_CHECKPOINT_SCALE, EMPTY_BALANCE_THRESHOLD, EXP_10_I80F48, MAX_ASSET_TAGS,
+        MIN_EMISSIONS_START_TIME, SECONDS_PER_YEAR, ZERO_AMOUNT_THRESHOLD,
     },
     debug, math_error,
     prelude::{SurroundfiError, SurroundfiResult},
-    utils::NumTraitsWithTolerance,
+    utils::{split_interest_bearing_mint, NumTraitsWithTolerance},
 };
 use anchor_lang::{prelude::*, Discriminator};
 use anchor_spl::token_interface::Mint;
 use bytemuck::{Pod, Zeroable};
 use fixed::types::I80F48;
 use std::{
+    borrow::Cow,
     cmp::{max, min},
     ops::Not,
 };
@@ -44,6 +50,8 @@ pub struct SurroundfiAccount {
     /// - 4: `ACCOUNT_FLAG_DEPRECATED` - Deprecated, available for future use
     /// - 8: `ACCOUNT_TRANSFER_AUTHORITY_ALLOWED` - the admin has flagged with account to be moved,
     ///   original owner can now call `set_account_transfer_authority`
+    /// - 16: `ACCOUNT_FLASHLOAN_LEVERAGE_CAPPED` - Set when risk is taken within a flash loan in an
+    ///   arena group with a leverage cap, which the end of the flash loan then checks.
     pub account_flags: u64, // 8
     /// Set with `update_emissions_destination_account`. Emissions rewards can be withdrawn to the
     /// cannonical ATA of this wallet without the user's input (withdraw_emissions_permissionless).
@@ -51,30 +59,53 @@ pub struct SurroundfiAccount {
     /// manually (withdraw_emissions).
     pub emissions_destination_account: Pubkey, // 32
     pub health_cache: HealthCache,
-    pub _padding0: [u64; 21],
+    /// Only set for accounts created with `initialize_pda`: the index the account was derived
+    /// with. Zero for accounts created from a keypair.
+    pub account_index: u16,
+    /// Only set for accounts created with `initialize_pda`: the optional third-party id used in
+    /// the derivation, zero if none was provided.
+    pub third_party_index: u16,
+    /// Only set for accounts created with `initialize_pda`, zero otherwise.
+    pub bump: u8,
+    pub _pad0: [u8; 3],
+    /// Set with `init_extension`. Holds additional balances once all slots in `lending_account`
+    /// are in use. If pubkey default, the account has no extension.
+    pub extension: Pubkey,
+    /// Only set for accounts created with `initialize_pda`: the authority the account was derived
+    /// with. Unlike `authority`, never changes, so the address can still be derived (from this,
+    /// `account_index` and `third_party_index`) after a `transfer_authority`. Default otherwise.
+    pub creator: Pubkey,
+    pub _padding0: [u64; 2],
 }
 
 pub const ACCOUNT_DISABLED: u64 = 1 << 0;
 pub const ACCOUNT_IN_FLASHLOAN: u64 = 1 << 1;
 pub const ACCOUNT_FLAG_DEPRECATED: u64 = 1 << 2;
 pub const ACCOUNT_TRANSFER_AUTHORITY_ALLOWED: u64 = 1 << 3;
+pub const ACCOUNT_FLASHLOAN_LEVERAGE_CAPPED: u64 = 1 << 4;
 
-/// 4 for `ASSET_TAG_STAKED` (bank, oracle, lst mint, lst pool), 2 for all others (bank, oracle)
+/// 4 for `ASSET_TAG_STAKED` (bank, oracle, lst mint, lst pool), 2 for all others (bank, oracle),
+/// plus the mint for interest-bearing banks (see `Bank::is_interest_bearing`)
 pub fn get_remaining_accounts_per_bank(bank: &Bank) -> SurroundfiResult<usize> {
-    get_remaining_accounts_per_asset_tag(bank.config.asset_tag)
+    let num_accounts = get_remaining_accounts_per_asset_tag(bank.config.asset_tag)?;
+    Ok(num_accounts + bank.is_interest_bearing() as usize)
 }
 
-/// 4 for `ASSET_TAG_STAKED` (bank, oracle, lst mint, lst pool), 2 for all others (bank, oracle)
+/// 4 for `ASSET_TAG_STAKED` (bank, oracle, lst mint, lst pool), 2 for all others (bank, oracle),
+/// plus the mint for balances in interest-bearing banks
 fn get_remaining_accounts_per_balance(balance: &Balance) -> SurroundfiResult<usize> {
-    get_remaining_accounts_per_asset_tag(balance.bank_asset_tag)
+    let num_accounts = get_remaining_accounts_per_asset_tag(balance.bank_asset_tag)?;
+    Ok(num_accounts + balance.is_interest_bearing() as usize)
 }
 
-/// 4 for `ASSET_TAG_STAKED` (bank, oracle, lst mint, lst pool), 2 for all others (bank, oracle)
+/// 4 for `ASSET_TAG_STAKED` (bank, oracle, lst mint, lst pool), 3 for `ASSET_TAG_RECEIPT` (bank,
+/// underlying bank, underlying oracle), 2 for all others (bank, oracle)
 fn get_remaining_accounts_per_asset_tag(asset_tag: u8) -> SurroundfiResult<usize> {
     match asset_tag {
-        ASSET_TAG_DEFAULT | ASSET_TAG_SOL => Ok(2),
+        ASSET_TAG_RECEIPT => Ok(3),
         ASSET_TAG_STAKED => Ok(4),
-        _ => err!(SurroundfiError::AssetTagMismatch),
+        tag if (tag as usize) < MAX_ASSET_TAGS => Ok(2),
+        _ => err!(SurroundfiError::UnsupportedAssetTag),
     }
 }
 
@@ -86,9 +117,30 @@ impl SurroundfiAccount {
         self.emissions_destination_account = Pubkey::default();
     }
 
+    /// Set the initial data for a surroundfi account derived from `SURROUNDFI_ACCOUNT_SEED`.
+    pub fn initialize_pda(
+        &mut self,
+        group: Pubkey,
+        authority: Pubkey,
+        account_index: u16,
+        third_party_index: u16,
+        bump: u8,
+    ) {
+        self.initialize(group, authority);
+        self.creator = authority;
+        self.account_index = account_index;
+        self.third_party_index = third_party_index;
+        self.bump = bump;
+    }
+
     /// Expected length of remaining accounts to be passed in borrow/liquidate, INCLUDING the bank
-    /// key, oracle, and optional accounts like lst mint/pool, etc.
-    pub fn get_remaining_accounts_len(&self) -> SurroundfiResult<usize> {
+    /// key, oracle, and optional accounts like lst mint/pool, interest-bearing mint, etc. If the
+    /// account has an extension, this also includes the balances of the extension and the
+    /// extension itself.
+    pub fn get_remaining_accounts_len(
+        &self,
+        extension: Option<&SurroundfiAccountExtension>,
+    ) -> SurroundfiResult<usize> {
         let mut total = 0usize;
         for balance in self
             .lending_account
@@ -99,9 +151,25 @@ impl SurroundfiAccount {
             let num_accounts = get_remaining_accounts_per_balance(balance)?;
             total += num_accounts;
         }
+
+        if self.has_extension() {
+            let extension =
+                extension.ok_or_else(|| error!(SurroundfiError::AccountExtensionMissing))?;
+            for balance in extension.get_active_balances_iter() {
+                let num_accounts = get_remaining_accounts_per_balance(balance)?;
+                total += num_accounts;
+            }
+            // The extension itself
+            total += 1;
+        }
+
         Ok(total)
     }
 
+    pub fn has_extension(&self) -> bool {
+        self.extension != Pubkey::default()
+    }
+
     pub fn set_flag(&mut self, flag: u64) {
         msg!("Setting account flag {:b}", flag);
         self.account_flags |= flag;
@@ -146,7 +214,8 @@ impl SurroundfiAccount {
             .iter()
             .all(|balance| balance.get_side().is_none());
 
-        !is_disabled && only_has_empty_balances
+        // The extension must be closed first (`close_extension`)
+        !is_disabled && only_has_empty_balances && !self.has_extension()
     }
 }
 
@@ -189,7 +258,17 @@ impl RequirementType {
 pub struct BankAccountWithPriceFeed<'a, 'info> {
     bank: AccountInfo<'info>,
     price_feed: Box<SurroundfiResult<OraclePriceFeedAdapter>>,
-    balance: &'a Balance,
+    /// Borrowed from the `LendingAccount`, or copied out of the `SurroundfiAccountExtension`
+    balance: Cow<'a, Balance>,
+    /// The strictest (init, maint) liability weights of the staked collateral banks the account has
+    /// a balance in, applied to its `ASSET_TAG_STABLE` liabilities (see
+    /// `StakedSettings::stable_liability_weight_init`). None if the account has no staked balance.
+    stable_liability_weights: Option<(I80F48, I80F48)>,
+    /// Converts the raw amounts of interest-bearing mints to UI amounts before pricing (see
+    /// `utils::split_interest_bearing_mint`), one for all other banks.
+    ui_amount_scale: I80F48,
+    /// Used instead of the bank's account data if set, see `RiskEngine::with_simulated_banks`
+    simulated_bank: Option<Box<Bank>>,
 }
 
 pub enum BalanceSide {
@@ -201,14 +280,32 @@ impl<'info> BankAccountWithPriceFeed<'_, 'info> {
     pub fn load<'a>(
         lending_account: &'a LendingAccount,
         remaining_ais: &'info [AccountInfo<'info>],
+    ) -> SurroundfiResult<Vec<BankAccountWithPriceFeed<'a, 'info>>> {
+        Self::load_with_extension(lending_account, None, remaining_ais)
+    }
+
+    /// Same as `load`, but also loads the active balances of the extension, which are expected to
+    /// follow the balances of the `lending_account` in `remaining_ais`.
+    pub fn load_with_extension<'a>(
+        lending_account: &'a LendingAccount,
+        extension: Option<&SurroundfiAccountExtension>,
+        remaining_ais: &'info [AccountInfo<'info>],
     ) -> SurroundfiResult<Vec<BankAccountWithPriceFeed<'a, 'info>>> {
         let clock = Clock::get()?;
         let mut account_index = 0;
+        let mut stable_liability_weights: Option<(I80F48, I80F48)> = None;
+
+        let extension_balances = extension
+            .into_iter()
+            .flat_map(|extension| extension.get_active_balances_iter())
+            .map(|balance| Cow::Owned(*balance));
 
         lending_account
             .balances
             .iter()
             .filter(|balance| balance.is_active())
+            .map(Cow::Borrowed)
+            .chain(extension_balances)
             .map(|balance| {
                 msg!("load: balance_bank_pk: {}, last_update: {}", balance.bank_pk.to_string(), balance.last_update);
                 
@@ -222,7 +319,7 @@ impl<'info> BankAccountWithPriceFeed<'_, 'info> {
                 let bank_al = AccountLoader::<Bank>::try_from(bank_ai)?;
 
                 // Determine number of accounts to process for this balance
-                let num_accounts = get_remaining_accounts_per_balance(balance)?;
+                let num_accounts = get_remaining_accounts_per_balance(&balance)?;
 
                 msg!("load: bank_ai_pk: {}", bank_ai.key.to_string());
                 
@@ -232,9 +329,27 @@ impl<'info> BankAccountWithPriceFeed<'_, 'info> {
                 );
                 let bank = bank_al.load()?;
 
-                // Get the oracle, and the LST mint and sol pool if applicable (staked only)
+                // Staked collateral banks carry the weights of Stable borrows against them
+                if bank.config.asset_tag == ASSET_TAG_STAKED {
+                    let (_, liab_init_w) = bank.config.get_weights(RequirementType::Initial);
+                    let (_, liab_maint_w) = bank.config.get_weights(RequirementType::Maintenance);
+                    stable_liability_weights = Some(match stable_liability_weights {
+                        Some((init_w, maint_w)) => {
+                            (max(init_w, liab_init_w), max(maint_w, liab_maint_w))
+                        }
+                        None => (liab_init_w, liab_maint_w),
+                    });
+                }
+
+                // Get the oracle, and the LST mint and sol pool if applicable (staked only), then
+                // the mint if interest-bearing
                 let oracle_ai_idx = account_index + 1;
-                let oracle_ais = &remaining_ais[oracle_ai_idx..oracle_ai_idx + num_accounts - 1];
+                let (oracle_ais, ui_amount_scale) = split_interest_bearing_mint(
+                    &bank,
+                    balance.is_interest_bearing(),
+                    &remaining_ais[oracle_ai_idx..oracle_ai_idx + num_accounts - 1],
+                    clock.unix_timestamp,
+                )?;
 
                 let price_adapter = Box::new(OraclePriceFeedAdapter::try_from_bank_config(
                     &bank.config,
@@ -248,9 +363,18 @@ impl<'info> BankAccountWithPriceFeed<'_, 'info> {
                     bank: bank_ai.clone(),
                     price_feed: price_adapter,
                     balance,
+                    stable_liability_weights: None,
+                    ui_amount_scale,
+                    simulated_bank: None,
                 })
             })
             .collect::<Result<Vec<_>>>()
+            .map(|mut bank_accounts| {
+                for bank_account in bank_accounts.iter_mut() {
+                    bank_account.stable_liability_weights = stable_liability_weights;
+                }
+                bank_accounts
+            })
     }
 
     #[inline(always)]
@@ -275,30 +399,7 @@ impl<'info> BankAccountWithPriceFeed<'_, 'info> {
     {
         match self.balance.get_side() {
             Some(side) => {
-                // We want lifetime <'a> but we have <'info> and it's a pain to modify everything...
-                // To avoid an unsafe transmuation we just interpret the bank from bytes. Here we
-                // repeat some of the sanity checks from AccountLoader
-                if self.bank.owner != &Bank::owner() {
-                    panic!("bank owned by wrong program, this should never happen");
-                }
-                let bank_data = &self.bank.try_borrow_data()?;
-                if bank_data.len() < Bank::LEN + 8 {
-                    panic!("bank too short, this should never happen");
-                }
-                let bank_discrim: &[u8] = &bank_data[0..8];
-                if bank_discrim != Bank::DISCRIMINATOR {
-                    panic!("bad bank discriminator, this should never happen");
-                }
-                let bank_data: &[u8] = &bank_data[8..];
-                let bank = *bytemuck::from_bytes(bank_data);
-
-                // Our alternative is this transmute, which is probably fine because we are
-                // shortening 'info to 'a, but better not to tempt fate with transmute in case
-                // Anchor messes with lifetimes in a later version.
-
-                // let shorter_bank: &'a AccountInfo<'a> = unsafe { core::mem::transmute(&self.bank) };
-                // let bank_al = AccountLoader::<Bank>::try_from(&shorter_bank)?;
-                // let bank = bank_al.load()?;
+                let bank = self.load_bank()?;
 
                 match side {
                     BalanceSide::Assets => {
@@ -318,6 +419,93 @@ impl<'info> BankAccountWithPriceFeed<'_, 'info> {
         }
     }
 
+    /// Copy the bank out of its account, or the simulated bank if set.
+    fn load_bank(&self) -> SurroundfiResult<Bank> {
+        if let Some(bank) = &self.simulated_bank {
+            return Ok(**bank);
+        }
+
+        // We want lifetime <'a> but we have <'info> and it's a pain to modify everything...
+        // To avoid an unsafe transmuation we just interpret the bank from bytes. Here we
+        // repeat some of the sanity checks from AccountLoader
+        if self.bank.owner != &Bank::owner() {
+            panic!("bank owned by wrong program, this should never happen");
+        }
+        let bank_data = &self.bank.try_borrow_data()?;
+        if bank_data.len() < Bank::LEN + 8 {
+            panic!("bank too short, this should never happen");
+        }
+        let bank_discrim: &[u8] = &bank_data[0..8];
+        if bank_discrim != Bank::DISCRIMINATOR {
+            panic!("bad bank discriminator, this should never happen");
+        }
+        let bank_data: &[u8] = &bank_data[8..];
+
+        // Our alternative is this transmute, which is probably fine because we are
+        // shortening 'info to 'a, but better not to tempt fate with transmute in case
+        // Anchor messes with lifetimes in a later version.
+
+        // let shorter_bank: &'a AccountInfo<'a> = unsafe { core::mem::transmute(&self.bank) };
+        // let bank_al = AccountLoader::<Bank>::try_from(&shorter_bank)?;
+        // let bank = bank_al.load()?;
+
+        Ok(*bytemuck::from_bytes(bank_data))
+    }
+
+    /// Max amount of the balance's token that can be withdrawn (if the balance is an asset) or
+    /// borrowed (otherwise) while keeping `free_collateral`, the account's initial health, above
+    /// zero. Borrows are also capped by the liquidity available in the bank.
+    ///
+    /// Ignores the initial weight discount, deposit/borrow limits and risk tiers.
+    fn calc_max_amount(&self, free_collateral: I80F48) -> SurroundfiResult<I80F48> {
+        let bank = self.load_bank()?;
+
+        match self.balance.get_side() {
+            Some(BalanceSide::Assets) => {
+                let amount = bank.get_asset_amount(self.balance.asset_shares.into())?;
+                let (value, _) = self.calc_weighted_asset_value(RequirementType::Initial, &bank)?;
+
+                // Not counted as collateral, so it can be withdrawn in full
+                if value == I80F48::ZERO {
+                    return Ok(amount);
+                }
+
+                let max_amount = amount
+                    .checked_mul(free_collateral)
+                    .ok_or_else(math_error!())?
+                    .checked_div(value)
+                    .ok_or_else(math_error!())?;
+
+                Ok(min(amount, max_amount))
+            }
+            Some(BalanceSide::Liabilities) | None => {
+                let price_feed = self.try_get_price_feed()?;
+                let liability_weight = self.get_liability_weight(RequirementType::Initial, &bank);
+                let higher_price = price_feed.get_price_of_type(
+                    RequirementType::Initial.get_oracle_price_type(),
+                    Some(PriceBias::High),
+                )?;
+
+                let max_amount = calc_amount(
+                    free_collateral
+                        .checked_div(liability_weight)
+                        .ok_or_else(math_error!())?,
+                    higher_price,
+                    bank.mint_decimals,
+                )?
+                .checked_div(self.ui_amount_scale)
+                .ok_or_else(math_error!())?;
+
+                let available_liquidity = bank
+                    .get_asset_amount(bank.total_asset_shares.into())?
+                    .checked_sub(bank.get_liability_amount(bank.total_liability_shares.into())?)
+                    .ok_or_else(math_error!())?;
+
+                Ok(max(min(max_amount, available_liquidity), I80F48::ZERO))
+            }
+        }
+    }
+
     /// Returns value, the net asset value in $, and the price used to determine that value.
     #[inline(always)]
     fn calc_weighted_asset_value<'a>(
@@ -359,7 +547,7 @@ impl<'info> BankAccountWithPriceFeed<'_, 'info> {
                 }
 
                 let value = calc_value(
-                    bank.get_asset_amount(self.balance.asset_shares.into())?,
+                    self.to_ui_amount(bank.get_asset_amount(self.balance.asset_shares.into())?)?,
                     lower_price,
                     bank.mint_decimals,
                     Some(asset_weight),
@@ -379,9 +567,7 @@ impl<'info> BankAccountWithPriceFeed<'_, 'info> {
         bank: &Bank,
     ) -> SurroundfiResult<(I80F48, I80F48)> {
         let price_feed = self.try_get_price_feed()?;
-        let liability_weight = bank
-            .config
-            .get_weight(requirement_type, BalanceSide::Liabilities);
+        let liability_weight = self.get_liability_weight(requirement_type, bank);
 
         let higher_price = price_feed.get_price_of_type(
             requirement_type.get_oracle_price_type(),
@@ -391,7 +577,7 @@ impl<'info> BankAccountWithPriceFeed<'_, 'info> {
         // If `ASSET_TAG_STAKED` assets can ever be borrowed, accomodate for that here...
 
         let value = calc_value(
-            bank.get_liability_amount(self.balance.liability_shares.into())?,
+            self.to_ui_amount(bank.get_liability_amount(self.balance.liability_shares.into())?)?,
             higher_price,
             bank.mint_decimals,
             Some(liability_weight),
@@ -400,6 +586,30 @@ impl<'info> BankAccountWithPriceFeed<'_, 'info> {
         Ok((value, higher_price))
     }
 
+    /// The bank's liability weight, or the weight required by the account's staked collateral for
+    /// `ASSET_TAG_STABLE` liabilities if stricter.
+    fn get_liability_weight(&self, requirement_type: RequirementType, bank: &Bank) -> I80F48 {
+        let liability_weight = bank
+            .config
+            .get_weight(requirement_type, BalanceSide::Liabilities);
+
+        match (bank.config.asset_tag, self.stable_liability_weights) {
+            (ASSET_TAG_STABLE, Some((init_w, maint_w))) => match requirement_type {
+                RequirementType::Initial => max(liability_weight, init_w),
+                RequirementType::Maintenance => max(liability_weight, maint_w),
+                RequirementType::Equity => liability_weight,
+            },
+            _ => liability_weight,
+        }
+    }
+
+    /// Raw amount of the bank's mint to the amount its price applies to, see `ui_amount_scale`
+    fn to_ui_amount(&self, amount: I80F48) -> SurroundfiResult<I80F48> {
+        amount
+            .checked_mul(self.ui_amount_scale)
+            .ok_or_else(math_error!())
+    }
+
     fn try_get_price_feed(&self) -> SurroundfiResult<&OraclePriceFeedAdapter> {
         match self.price_feed.as_ref() {
             Ok(a) => Ok(a),
@@ -517,8 +727,17 @@ impl<'info> RiskEngine<'_, 'info> {
         surroundfi_account: &'a SurroundfiAccount,
         remaining_ais: &'info [AccountInfo<'info>],
     ) -> SurroundfiResult<RiskEngine<'a, 'info>> {
-        let bank_accounts_with_price =
-            BankAccountWithPriceFeed::load(&surroundfi_account.lending_account, remaining_ais)?;
+        let extension_loader = find_account_extension(surroundfi_account, remaining_ais)?;
+        let extension = extension_loader
+            .as_ref()
+            .map(|loader| loader.load())
+            .transpose()?;
+
+        let bank_accounts_with_price = BankAccountWithPriceFeed::load_with_extension(
+            &surroundfi_account.lending_account,
+            extension.as_deref(),
+            remaining_ais,
+        )?;
 
         Ok(RiskEngine {
             surroundfi_account,
@@ -526,29 +745,109 @@ impl<'info> RiskEngine<'_, 'info> {
         })
     }
 
+    /// Same as `new`, but uses the given extension rather than loading it from `remaining_ais`, e.g.
+    /// to check the health of an account that was modified in memory.
+    pub fn new_with_extension<'a>(
+        surroundfi_account: &'a SurroundfiAccount,
+        extension: Option<&SurroundfiAccountExtension>,
+        remaining_ais: &'info [AccountInfo<'info>],
+    ) -> SurroundfiResult<RiskEngine<'a, 'info>> {
+        check!(
+            !surroundfi_account.get_flag(ACCOUNT_IN_FLASHLOAN),
+            SurroundfiError::AccountInFlashloan
+        );
+
+        let bank_accounts_with_price = BankAccountWithPriceFeed::load_with_extension(
+            &surroundfi_account.lending_account,
+            extension,
+            remaining_ais,
+        )?;
+
+        Ok(RiskEngine {
+            surroundfi_account,
+            bank_accounts_with_price,
+        })
+    }
+
+    /// Price `banks` instead of their accounts, e.g. copies that had actions applied in memory.
+    /// Banks of the account that aren't in `banks` are still read from their accounts.
+    pub fn with_simulated_banks(mut self, banks: &[(Pubkey, Bank)]) -> Self {
+        for bank_account in self.bank_accounts_with_price.iter_mut() {
+            if let Some((_, bank)) = banks.iter().find(|(key, _)| key.eq(bank_account.bank.key)) {
+                bank_account.simulated_bank = Some(Box::new(*bank));
+            }
+        }
+        self
+    }
+
     /// Checks account is healthy after performing actions that increase risk (removing liquidity).
+    /// Returns the risk engine, so further checks (e.g. `check_account_leverage`) can reuse the
+    /// loaded prices.
     ///
     /// `ACCOUNT_IN_FLASHLOAN` behavior.
-    /// - Health check is skipped.
+    /// - Health check is skipped, and no risk engine is returned.
     /// - `remaining_ais` can be an empty vec.
     pub fn check_account_init_health<'a>(
         surroundfi_account: &'a SurroundfiAccount,
         remaining_ais: &'info [AccountInfo<'info>],
         health_cache: &mut Option<&mut HealthCache>,
-    ) -> SurroundfiResult<()> {
+    ) -> SurroundfiResult<Option<RiskEngine<'a, 'info>>> {
         if surroundfi_account.get_flag(ACCOUNT_IN_FLASHLOAN) {
             // Note: The health cache is not applicable to flashloans
-            return Ok(());
+            return Ok(None);
         }
 
         let risk_engine = Self::new_no_flashloan_check(surroundfi_account, remaining_ais)?;
+
+        if let Some(health_cache) = health_cache {
+            risk_engine
+                .update_health_cache_component(RiskRequirementType::Maintenance, health_cache);
+            risk_engine.update_health_cache_component(RiskRequirementType::Equity, health_cache);
+            risk_engine.maybe_update_extension_health_cache(remaining_ais)?;
+        }
+
         let requirement_type = RiskRequirementType::Initial;
         risk_engine.check_account_health(requirement_type, health_cache)?;
 
+        Ok(Some(risk_engine))
+    }
+
+    /// The ratio of the account's total (unweighted) assets to its equity, see
+    /// `ArenaConfig::max_leverage`. One without liabilities, `I80F48::MAX` without equity.
+    pub fn get_account_leverage(&self) -> SurroundfiResult<I80F48> {
+        let (assets, liabilities) =
+            self.get_account_health_components(RiskRequirementType::Equity, &mut None)?;
+
+        if liabilities == I80F48::ZERO {
+            return Ok(I80F48::ONE);
+        }
+
+        let equity = assets.checked_sub(liabilities).ok_or_else(math_error!())?;
+        if equity <= I80F48::ZERO {
+            return Ok(I80F48::MAX);
+        }
+
+        assets.checked_div(equity).ok_or_else(math_error!())
+    }
+
+    /// Checks that the account's total (unweighted) assets are worth at most `max_leverage` times
+    /// its equity, see `ArenaConfig::max_leverage`. Accounts without liabilities always pass.
+    pub fn check_account_leverage(&self, max_leverage: I80F48) -> SurroundfiResult {
+        let leverage = self.get_account_leverage()?;
+        debug!("leverage: {}, max leverage: {}", leverage, max_leverage);
+
+        check!(
+            leverage <= max_leverage,
+            SurroundfiError::ArenaMaxLeverageExceeded
+        );
+
         Ok(())
     }
 
     /// Returns the total assets and liabilities of the account in the form of (assets, liabilities)
+    ///
+    /// If a health cache is provided, records the totals for this requirement type, as well as the
+    /// prices (initial) or the value of each balance and the liquidation price (maintenance).
     pub fn get_account_health_components(
         &self,
         requirement_type: RiskRequirementType,
@@ -556,14 +855,22 @@ impl<'info> RiskEngine<'_, 'info> {
     ) -> SurroundfiResult<(I80F48, I80F48)> {
         let mut total_assets: I80F48 = I80F48::ZERO;
         let mut total_liabilities: I80F48 = I80F48::ZERO;
+        // The (value, price) of the last balance counted as an asset, and how many were counted
+        let mut collateral: Option<(I80F48, I80F48)> = None;
+        let mut collateral_count: usize = 0;
 
         for (i, bank_account) in self.bank_accounts_with_price.iter().enumerate() {
-            let requirement_type = requirement_type.to_weight_type();
             let (asset_val, liab_val, price) =
-                bank_account.calc_weighted_value(requirement_type)?;
+                bank_account.calc_weighted_value(requirement_type.to_weight_type())?;
 
+            // Prices past the end of the cache go to the extension, see
+            // `update_extension_health_cache`
             if let Some(health_cache) = health_cache {
-                health_cache.prices[i] = price.into();
+                if matches!(requirement_type, RiskRequirementType::Initial) {
+                    if let Some(cached_price) = health_cache.prices.get_mut(i) {
+                        *cached_price = price.into();
+                    }
+                }
             }
 
             debug!(
@@ -571,6 +878,11 @@ impl<'info> RiskEngine<'_, 'info> {
                 bank_account.balance.bank_pk, asset_val, liab_val
             );
 
+            if asset_val > I80F48::ZERO {
+                collateral = Some((asset_val, price));
+                collateral_count += 1;
+            }
+
             total_assets = total_assets
                 .checked_add(asset_val)
                 .ok_or_else(math_error!())?;
@@ -580,13 +892,158 @@ impl<'info> RiskEngine<'_, 'info> {
         }
 
         if let Some(health_cache) = health_cache {
-            health_cache.asset_value = total_assets.into();
-            health_cache.liability_value = total_liabilities.into();
+            match requirement_type {
+                RiskRequirementType::Initial => {
+                    health_cache.asset_value = total_assets.into();
+                    health_cache.liability_value = total_liabilities.into();
+                }
+                RiskRequirementType::Maintenance => {
+                    health_cache.asset_value_maint = total_assets.into();
+                    health_cache.liability_value_maint = total_liabilities.into();
+
+                    // The collateral's value is linear in its price, so the account hits the
+                    // maintenance requirement at price * liabilities / value
+                    let liquidation_price = match collateral {
+                        Some((value, price))
+                            if collateral_count == 1 && total_liabilities > I80F48::ZERO =>
+                        {
+                            price
+                                .checked_mul(total_liabilities)
+                                .ok_or_else(math_error!())?
+                                .checked_div(value)
+                                .ok_or_else(math_error!())?
+                        }
+                        _ => I80F48::ZERO,
+                    };
+                    health_cache.liquidation_price = liquidation_price.into();
+                }
+                RiskRequirementType::Equity => {
+                    health_cache.asset_value_equity = total_assets.into();
+                    health_cache.liability_value_equity = total_liabilities.into();
+                }
+            }
         }
 
         Ok((total_assets, total_liabilities))
     }
 
+    /// Records the components of `requirement_type` in the health cache, see
+    /// `get_account_health_components`. The maintenance and equity parts of the cache are
+    /// informational, so an error (e.g. a stale oracle, which only the initial requirement
+    /// tolerates for assets) leaves that part of the cache zeroed rather than failing the
+    /// instruction.
+    pub fn update_health_cache_component(
+        &self,
+        requirement_type: RiskRequirementType,
+        health_cache: &mut HealthCache,
+    ) {
+        let is_maintenance = matches!(requirement_type, RiskRequirementType::Maintenance);
+        let is_equity = matches!(requirement_type, RiskRequirementType::Equity);
+
+        let res =
+            self.get_account_health_components(requirement_type, &mut Some(&mut *health_cache));
+        if res.is_ok() {
+            return;
+        }
+
+        if is_maintenance {
+            health_cache.asset_value_maint = I80F48::ZERO.into();
+            health_cache.liability_value_maint = I80F48::ZERO.into();
+            health_cache.liquidation_price = I80F48::ZERO.into();
+        } else if is_equity {
+            health_cache.asset_value_equity = I80F48::ZERO.into();
+            health_cache.liability_value_equity = I80F48::ZERO.into();
+        }
+    }
+
+    /// Records the price (initial) of each balance past the end of the health cache's arrays, and
+    /// the maintenance-weighted value of every balance, in the extension, see
+    /// `SurroundfiAccountExtension::health_cache_prices`. Informational like the maintenance part
+    /// of the cache, so a balance that can't be priced is recorded as zero.
+    pub fn update_extension_health_cache(&self, extension: &mut SurroundfiAccountExtension) {
+        extension.health_cache_prices = [I80F48::ZERO.into(); MAX_EXTENSION_BALANCES];
+        extension
+            .health_cache_balance_values
+            .fill(HealthCacheBalanceValues::zeroed());
+
+        for (bank_account, cached_price) in self
+            .bank_accounts_with_price
+            .iter()
+            .skip(MAX_LENDING_ACCOUNT_BALANCES)
+            .zip(extension.health_cache_prices.iter_mut())
+        {
+            if let Ok((_, _, price)) = bank_account.calc_weighted_value(RequirementType::Initial) {
+                *cached_price = price.into();
+            }
+        }
+
+        for (bank_account, cached_values) in self
+            .bank_accounts_with_price
+            .iter()
+            .zip(extension.health_cache_balance_values.iter_mut())
+        {
+            if let Ok((asset_val, liab_val, _)) =
+                bank_account.calc_weighted_value(RequirementType::Maintenance)
+            {
+                cached_values.asset_value_maint = asset_val.into();
+                cached_values.liability_value_maint = liab_val.into();
+            }
+        }
+    }
+
+    /// Calls `update_extension_health_cache` if the account has an extension and it was passed as
+    /// writable in `remaining_ais`.
+    pub fn maybe_update_extension_health_cache(
+        &self,
+        remaining_ais: &'info [AccountInfo<'info>],
+    ) -> SurroundfiResult {
+        if let Some(extension_loader) =
+            find_account_extension(self.surroundfi_account, remaining_ais)?
+        {
+            if extension_loader.to_account_info().is_writable {
+                self.update_extension_health_cache(&mut *extension_loader.load_mut()?);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the weighted value of each balance, in the same order as the balances (lending
+    /// account first, then extension): positive for assets, negative for liabilities.
+    pub fn get_balance_weighted_values(
+        &self,
+        requirement_type: RiskRequirementType,
+    ) -> SurroundfiResult<Vec<I80F48>> {
+        let requirement_type = requirement_type.to_weight_type();
+
+        self.bank_accounts_with_price
+            .iter()
+            .map(|bank_account| {
+                let (asset_val, liab_val, _) =
+                    bank_account.calc_weighted_value(requirement_type)?;
+                let value = asset_val.checked_sub(liab_val).ok_or_else(math_error!())?;
+                Ok(value)
+            })
+            .collect()
+    }
+
+    /// Returns, for each balance in the same order as the balances, the max amount that can be
+    /// withdrawn (assets) or borrowed (liabilities or empty balances) before the account fails the
+    /// initial requirement. See `BankAccountWithPriceFeed::calc_max_amount`.
+    pub fn get_balance_max_amounts(&self) -> SurroundfiResult<Vec<I80F48>> {
+        let (assets, liabs) =
+            self.get_account_health_components(RiskRequirementType::Initial, &mut None)?;
+        let free_collateral = max(
+            assets.checked_sub(liabs).ok_or_else(math_error!())?,
+            I80F48::ZERO,
+        );
+
+        self.bank_accounts_with_price
+            .iter()
+            .map(|bank_account| bank_account.calc_max_amount(free_collateral))
+            .collect()
+    }
+
     /// Errors if risk account's liabilities exceed their assets.
     fn check_account_health(
         &self,
@@ -680,10 +1137,13 @@ impl<'info> RiskEngine<'_, 'info> {
     ///
     /// 1. We check that the paid off liability is not zero. Assuming the liquidation always pays off some liability, this ensures that the liquidation was not too large.
     /// 2. We check that the account is still at most at the maintenance requirement level. This ensures that the liquidation was not too large overall.
+    ///
+    /// If a health cache is provided, records the maintenance components of the account.
     pub fn check_post_liquidation_condition_and_get_account_health(
         &self,
         bank_pk: &Pubkey,
         pre_liquidation_health: I80F48,
+        health_cache: &mut Option<&mut HealthCache>,
     ) -> SurroundfiResult<I80F48> {
         check!(
             !self.surroundfi_account.get_flag(ACCOUNT_IN_FLASHLOAN),
@@ -709,7 +1169,7 @@ impl<'info> RiskEngine<'_, 'info> {
         );
 
         let (assets, liabs) =
-            self.get_account_health_components(RiskRequirementType::Maintenance, &mut None)?;
+            self.get_account_health_components(RiskRequirementType::Maintenance, health_cache)?;
 
         let account_health = assets.checked_sub(liabs).ok_or_else(math_error!())?;
 
@@ -733,7 +1193,7 @@ impl<'info> RiskEngine<'_, 'info> {
 
     /// Check that the account is in a bankrupt state.
     /// Account needs to be insolvent and total value of assets need to be below the bankruptcy threshold.
-    pub fn check_account_bankrupt(&self) -> SurroundfiResult {
+    pub fn check_account_bankrupt(&self, bankruptcy_threshold: I80F48) -> SurroundfiResult {
         let (total_assets, total_liabilities) =
             self.get_account_health_components(RiskRequirementType::Equity, &mut None)?;
 
@@ -753,7 +1213,7 @@ impl<'info> RiskEngine<'_, 'info> {
             SurroundfiError::AccountNotBankrupt
         );
         check!(
-            total_assets < BANKRUPT_THRESHOLD && total_liabilities > ZERO_AMOUNT_THRESHOLD,
+            total_assets < bankruptcy_threshold && total_liabilities > ZERO_AMOUNT_THRESHOLD,
             SurroundfiError::AccountNotBankrupt
         );
 
@@ -812,13 +1272,162 @@ assert_struct_align!(LendingAccount, 8);
 )]
 pub struct LendingAccount {
     pub balances: [Balance; MAX_LENDING_ACCOUNT_BALANCES], // 104 * 16 = 1664
-    pub _padding: [u64; 8],                                // 8 * 8 = 64
+    /// Liabilities opened within the current flashloan and not yet repaid, in banks that charge a
+    /// flashloan fee. Only used while `ACCOUNT_IN_FLASHLOAN` is set.
+    pub flashloan_volumes: [FlashloanVolume; MAX_FLASHLOAN_VOLUMES], // 16 * 4 = 64
 }
 
 impl LendingAccount {
     pub fn get_first_empty_balance(&self) -> Option<usize> {
         self.balances.iter().position(|b| !b.is_active())
     }
+
+    /// Iterate over all balance slots, followed by those of the extension, if provided.
+    pub fn balances_iter_mut<'a>(
+        &'a mut self,
+        extension: Option<&'a mut SurroundfiAccountExtension>,
+    ) -> impl Iterator<Item = &'a mut Balance> {
+        self.balances.iter_mut().chain(
+            extension
+                .into_iter()
+                .flat_map(|extension| extension.balances.iter_mut()),
+        )
+    }
+
+    /// Iterate over all balance slots, followed by those of the extension, if provided.
+    pub fn balances_iter<'a>(
+        &'a self,
+        extension: Option<&'a SurroundfiAccountExtension>,
+    ) -> impl Iterator<Item = &'a Balance> {
+        self.balances.iter().chain(
+            extension
+                .into_iter()
+                .flat_map(|extension| extension.balances.iter()),
+        )
+    }
+
+    /// Index of the active balance of `bank_pk`, counting the extension's balances after those
+    /// of the lending account.
+    pub fn get_balance_index(
+        &self,
+        bank_pk: &Pubkey,
+        extension: Option<&SurroundfiAccountExtension>,
+    ) -> Option<usize> {
+        self.balances_iter(extension)
+            .position(|balance| balance.is_active() && balance.bank_pk.eq(bank_pk))
+    }
+
+    /// Record `amount` borrowed within a flashloan from the balance at `balance_index`.
+    pub fn record_flashloan_borrow(
+        &mut self,
+        balance_index: usize,
+        amount: u64,
+    ) -> SurroundfiResult {
+        let balance_index = balance_index as u8;
+
+        let volume = match self
+            .flashloan_volumes
+            .iter()
+            .position(|volume| volume.is_active() && volume.balance_index == balance_index)
+        {
+            Some(index) => &mut self.flashloan_volumes[index],
+            None => {
+                let index = self
+                    .flashloan_volumes
+                    .iter()
+                    .position(|volume| !volume.is_active())
+                    .ok_or_else(|| error!(SurroundfiError::FlashloanVolumesFull))?;
+                self.flashloan_volumes[index].balance_index = balance_index;
+                &mut self.flashloan_volumes[index]
+            }
+        };
+
+        volume.amount = volume
+            .amount
+            .checked_add(amount)
+            .ok_or_else(math_error!())?;
+
+        Ok(())
+    }
+
+    /// Record `amount` repaid within a flashloan to the balance at `balance_index`. Returns the
+    /// part of `amount` that closes liabilities opened within the flashloan, on which the fee is
+    /// due.
+    pub fn record_flashloan_repay(&mut self, balance_index: usize, amount: u64) -> u64 {
+        let balance_index = balance_index as u8;
+
+        match self
+            .flashloan_volumes
+            .iter_mut()
+            .find(|volume| volume.is_active() && volume.balance_index == balance_index)
+        {
+            Some(volume) => {
+                let closed = min(volume.amount, amount);
+                volume.amount -= closed;
+                if volume.amount == 0 {
+                    *volume = FlashloanVolume::zeroed();
+                }
+                closed
+            }
+            None => 0,
+        }
+    }
+
+    /// Record an increase of `amount` to the balance of `bank_pk` within a flashloan, by any
+    /// instruction. The part of `amount` that pays off the balance's liability is a repay, see
+    /// `record_flashloan_repay`. Returns the volume closed, on which the fee is due.
+    pub fn record_flashloan_balance_increase(
+        &mut self,
+        bank_pk: &Pubkey,
+        bank: &Bank,
+        extension: Option<&SurroundfiAccountExtension>,
+        amount: u64,
+    ) -> SurroundfiResult<u64> {
+        let Some(balance_index) = self.get_balance_index(bank_pk, extension) else {
+            return Ok(0);
+        };
+
+        let liability_shares: I80F48 = self
+            .balances_iter(extension)
+            .nth(balance_index)
+            .ok_or_else(|| error!(SurroundfiError::InternalLogicError))?
+            .liability_shares
+            .into();
+        let liability_amount: u64 = bank
+            .get_liability_amount(liability_shares)?
+            .checked_ceil()
+            .ok_or_else(math_error!())?
+            .checked_to_num()
+            .ok_or_else(math_error!())?;
+
+        Ok(self.record_flashloan_repay(balance_index, min(amount, liability_amount)))
+    }
+
+    pub fn clear_flashloan_volumes(&mut self) {
+        self.flashloan_volumes = [FlashloanVolume::zeroed(); MAX_FLASHLOAN_VOLUMES];
+    }
+}
+
+pub const MAX_FLASHLOAN_VOLUMES: usize = 4;
+
+assert_struct_size!(FlashloanVolume, 16);
+assert_struct_align!(FlashloanVolume, 8);
+#[repr(C)]
+#[derive(
+    AnchorDeserialize, AnchorSerialize, Copy, Clone, Zeroable, Pod, PartialEq, Eq, TypeLayout,
+)]
+pub struct FlashloanVolume {
+    /// See `LendingAccount::get_balance_index`
+    pub balance_index: u8,
+    pub _pad0: [u8; 7],
+    /// In native token. 0 if the slot is unused.
+    pub amount: u64,
+}
+
+impl FlashloanVolume {
+    pub fn is_active(&self) -> bool {
+        self.amount != 0
+    }
 }
 
 #[cfg(any(feature = "test", feature = "client"))]
@@ -846,12 +1455,21 @@ pub struct Balance {
     /// Inherited from the bank when the position is first created and CANNOT BE CHANGED after that.
     /// Note that all balances created before the addition of this feature use `ASSET_TAG_DEFAULT`
     pub bank_asset_tag: u8,
-    pub _pad0: [u8; 6],
+    /// Inherited from the bank (`Bank::is_interest_bearing`) when the position is first created.
+    /// If set, the bank's mint follows its oracle accounts in remaining accounts.
+    pub bank_interest_bearing: u8,
+    /// Set once the balance earns the streams of the bank's `EmissionsProgram` (see
+    /// `BankAccountWrapper::settle_emissions_streams`). From then on, every change of the balance
+    /// must settle the streams first.
+    pub emissions_streams_enrolled: u8,
+    pub _pad0: [u8; 4],
     pub asset_shares: WrappedI80F48,
     pub liability_shares: WrappedI80F48,
     pub emissions_outstanding: WrappedI80F48,
     pub last_update: u64,
-    pub _padding: [u64; 1],
+    /// The bank's emissions index of this balance's side when emissions were last claimed, in
+    /// units of 1/`EMISSIONS_INDEX_CHECKPOINT_SCALE`, rounded up. See `Bank::update_emissions_index`
+    pub emissions_index_checkpoint: u64,
 }
 
 impl Balance {
@@ -863,6 +1481,14 @@ impl Balance {
         self.active = value as u8;
     }
 
+    pub fn is_interest_bearing(&self) -> bool {
+        self.bank_interest_bearing != 0
+    }
+
+    pub fn is_emissions_streams_enrolled(&self) -> bool {
+        self.emissions_streams_enrolled != 0
+    }
+
     /// Check whether a balance is empty while accounting for any rounding errors
     /// that might have occured during depositing/withdrawing.
     #[inline]
@@ -905,6 +1531,22 @@ impl Balance {
         Ok(())
     }
 
+    pub fn get_emissions_index_checkpoint(&self) -> I80F48 {
+        I80F48::from_num(self.emissions_index_checkpoint) / EMISSIONS_INDEX_CHECKPOINT_SCALE
+    }
+
+    pub fn set_emissions_index_checkpoint(&mut self, index: I80F48) -> SurroundfiResult {
+        self.emissions_index_checkpoint = index
+            .checked_mul(EMISSIONS_INDEX_CHECKPOINT_SCALE)
+            .ok_or_else(math_error!())?
+            .checked_ceil()
+            .ok_or_else(math_error!())?
+            .checked_to_num()
+            .ok_or_else(math_error!())?;
+
+        Ok(())
+    }
+
     pub fn get_side(&self) -> Option<BalanceSide> {
         let asset_shares = I80F48::from(self.asset_shares);
         let liability_shares = I80F48::from(self.liability_shares);
@@ -927,12 +1569,14 @@ impl Balance {
             active: 0,
             bank_pk: Pubkey::default(),
             bank_asset_tag: ASSET_TAG_DEFAULT,
-            _pad0: [0; 6],
+            bank_interest_bearing: 0,
+            emissions_streams_enrolled: 0,
+            _pad0: [0; 4],
             asset_shares: WrappedI80F48::from(I80F48::ZERO),
             liability_shares: WrappedI80F48::from(I80F48::ZERO),
             emissions_outstanding: WrappedI80F48::from(I80F48::ZERO),
             last_update: 0,
-            _padding: [0; 1],
+            emissions_index_checkpoint: 0,
         }
     }
 }
@@ -940,68 +1584,79 @@ impl Balance {
 pub struct BankAccountWrapper<'a> {
     pub balance: &'a mut Balance,
     pub bank: &'a mut Bank,
+    /// Set by `settle_emissions_streams`, required to change the balance if it is enrolled in the
+    /// bank's emissions streams
+    pub emissions_streams_settled: bool,
 }
 
 impl<'a> BankAccountWrapper<'a> {
-    // Find existing user lending account balance by bank address.
+    // Find existing user lending account balance by bank address, in the lending account or the
+    // extension, if provided.
     pub fn find(
         bank_pk: &Pubkey,
         bank: &'a mut Bank,
         lending_account: &'a mut LendingAccount,
+        extension: Option<&'a mut SurroundfiAccountExtension>,
     ) -> SurroundfiResult<BankAccountWrapper<'a>> {
         let balance = lending_account
-            .balances
-            .iter_mut()
+            .balances_iter_mut(extension)
             .find(|balance| balance.is_active() && balance.bank_pk.eq(bank_pk))
             .ok_or_else(|| error!(SurroundfiError::BankAccountNotFound))?;
 
-        Ok(Self { balance, bank })
+        Ok(Self {
+            balance,
+            bank,
+            emissions_streams_settled: false,
+        })
     }
 
-    // Find existing user lending account balance by bank address.
-    // Create it if not found.
+    // Find existing user lending account balance by bank address, in the lending account or the
+    // extension, if provided.
+    // Create it if not found, in the first empty slot of the lending account, then the extension.
     pub fn find_or_create(
         bank_pk: &Pubkey,
         bank: &'a mut Bank,
         lending_account: &'a mut LendingAccount,
+        extension: Option<&'a mut SurroundfiAccountExtension>,
     ) -> SurroundfiResult<BankAccountWrapper<'a>> {
-        let balance_index = lending_account
-            .balances
-            .iter()
-            .position(|balance| balance.is_active() && balance.bank_pk.eq(bank_pk));
-
-        match balance_index {
-            Some(balance_index) => {
-                let balance = lending_account
-                    .balances
-                    .get_mut(balance_index)
-                    .ok_or_else(|| error!(SurroundfiError::BankAccountNotFound))?;
-
-                Ok(Self { balance, bank })
+        let mut first_empty_balance: Option<&'a mut Balance> = None;
+
+        for balance in lending_account.balances_iter_mut(extension) {
+            if balance.is_active() {
+                if balance.bank_pk.eq(bank_pk) {
+                    return Ok(Self {
+                        balance,
+                        bank,
+                        emissions_streams_settled: false,
+                    });
+                }
+            } else if first_empty_balance.is_none() {
+                first_empty_balance = Some(balance);
             }
-            None => {
-                let empty_index = lending_account
-                    .get_first_empty_balance()
-                    .ok_or_else(|| error!(SurroundfiError::LendingAccountBalanceSlotsFull))?;
+        }
 
-                lending_account.balances[empty_index] = Balance {
-                    active: 1,
-                    bank_pk: *bank_pk,
-                    bank_asset_tag: bank.config.asset_tag,
-                    _pad0: [0; 6],
-                    asset_shares: I80F48::ZERO.into(),
-                    liability_shares: I80F48::ZERO.into(),
-                    emissions_outstanding: I80F48::ZERO.into(),
-                    last_update: Clock::get()?.unix_timestamp as u64,
-                    _padding: [0; 1],
-                };
+        let balance = first_empty_balance
+            .ok_or_else(|| error!(SurroundfiError::LendingAccountBalanceSlotsFull))?;
+
+        *balance = Balance {
+            active: 1,
+            bank_pk: *bank_pk,
+            bank_asset_tag: bank.config.asset_tag,
+            bank_interest_bearing: bank.is_interest_bearing() as u8,
+            emissions_streams_enrolled: 0,
+            _pad0: [0; 4],
+            asset_shares: I80F48::ZERO.into(),
+            liability_shares: I80F48::ZERO.into(),
+            emissions_outstanding: I80F48::ZERO.into(),
+            last_update: Clock::get()?.unix_timestamp as u64,
+            emissions_index_checkpoint: 0,
+        };
 
-                Ok(Self {
-                    balance: lending_account.balances.get_mut(empty_index).unwrap(),
-                    bank,
-                })
-            }
-        }
+        Ok(Self {
+            balance,
+            bank,
+            emissions_streams_settled: false,
+        })
     }
 
     // ------------ Borrow / Lend primitives
@@ -1055,6 +1710,7 @@ impl<'a> BankAccountWrapper<'a> {
 
     /// Withdraw existing asset in full - will error if there is no asset.
     pub fn withdraw_all(&mut self) -> SurroundfiResult<u64> {
+        self.check_emissions_streams_settled()?;
         self.claim_emissions(Clock::get()?.unix_timestamp as u64)?;
 
         let balance = &mut self.balance;
@@ -1104,6 +1760,7 @@ impl<'a> BankAccountWrapper<'a> {
 
     /// Repay existing liability in full - will error if there is no liability.
     pub fn repay_all(&mut self) -> SurroundfiResult<u64> {
+        self.check_emissions_streams_settled()?;
         self.claim_emissions(Clock::get()?.unix_timestamp as u64)?;
 
         let balance = &mut self.balance;
@@ -1175,6 +1832,48 @@ impl<'a> BankAccountWrapper<'a> {
         Ok(())
     }
 
+    /// Forfeit the assets left on a bankrupt account to the bank's insurance fees, and its unclaimed
+    /// emissions (see `forfeit_emissions_in_bankruptcy`), and close the balance. Returns the amount
+    /// of assets forfeited.
+    pub fn forfeit_assets_in_bankruptcy(&mut self) -> SurroundfiResult<I80F48> {
+        self.check_emissions_streams_settled()?;
+        self.claim_emissions(Clock::get()?.unix_timestamp as u64)?;
+
+        let balance = &mut self.balance;
+        let bank = &mut self.bank;
+
+        let asset_shares: I80F48 = balance.asset_shares.into();
+        let asset_amount = bank.get_asset_amount(asset_shares)?;
+
+        balance.change_asset_shares(-asset_shares)?;
+        bank.change_asset_shares(-asset_shares, false)?;
+        bank.collected_insurance_fees_outstanding = asset_amount
+            .checked_add(bank.collected_insurance_fees_outstanding.into())
+            .ok_or_else(math_error!())?
+            .into();
+
+        self.checkpoint_emissions()?;
+        self.forfeit_emissions_in_bankruptcy()?;
+        self.balance.close()?;
+
+        Ok(asset_amount)
+    }
+
+    /// Return the unclaimed emissions of a bankrupt account's balance to the bank's remaining
+    /// emissions, where they are paid out to other balances. The account is disabled, so it could
+    /// never withdraw them, and the balance can be closed. Returns the amount forfeited.
+    pub fn forfeit_emissions_in_bankruptcy(&mut self) -> SurroundfiResult<I80F48> {
+        let emissions_outstanding: I80F48 = self.balance.emissions_outstanding.into();
+
+        self.bank.emissions_remaining = emissions_outstanding
+            .checked_add(self.bank.emissions_remaining.into())
+            .ok_or_else(math_error!())?
+            .into();
+        self.balance.emissions_outstanding = I80F48::ZERO.into();
+
+        Ok(emissions_outstanding)
+    }
+
     // ------------ Internal accounting logic
 
     fn increase_balance_internal(
@@ -1187,6 +1886,7 @@ impl<'a> BankAccountWrapper<'a> {
             balance_delta, operation_type
         );
 
+        self.check_emissions_streams_settled()?;
         self.claim_emissions(Clock::get()?.unix_timestamp as u64)?;
 
         let balance = &mut self.balance;
@@ -1239,6 +1939,8 @@ impl<'a> BankAccountWrapper<'a> {
         balance.change_liability_shares(-liability_shares_decrease)?;
         bank.change_liability_shares(-liability_shares_decrease, true)?;
 
+        self.checkpoint_emissions()?;
+
         Ok(())
     }
 
@@ -1252,6 +1954,7 @@ impl<'a> BankAccountWrapper<'a> {
             balance_delta, operation_type
         );
 
+        self.check_emissions_streams_settled()?;
         self.claim_emissions(Clock::get()?.unix_timestamp as u64)?;
 
         let balance = &mut self.balance;
@@ -1305,75 +2008,208 @@ impl<'a> BankAccountWrapper<'a> {
 
         bank.check_utilization_ratio()?;
 
+        self.checkpoint_emissions()?;
+
         Ok(())
     }
 
     /// Claim any unclaimed emissions and add them to the outstanding emissions amount.
+    ///
+    /// Emissions are set aside for all shares of the bank by `Bank::update_emissions_index`, the
+    /// balance collects its part since its checkpoint. Balances last updated before the bank's
+    /// indexes started first accrue at `emissions_rate` (on the current balance) up to that time,
+    /// within the emissions schedule. This is the migration path from per-balance accrual.
     pub fn claim_emissions(&mut self, current_timestamp: u64) -> SurroundfiResult {
-        if let Some(balance_amount) = match (
-            self.balance.get_side(),
-            self.bank.get_flag(EMISSIONS_FLAG_LENDING_ACTIVE),
-            self.bank.get_flag(EMISSIONS_FLAG_BORROW_ACTIVE),
-        ) {
-            (Some(BalanceSide::Assets), true, _) => Some(
-                self.bank
-                    .get_asset_amount(self.balance.asset_shares.into())?,
-            ),
-            (Some(BalanceSide::Liabilities), _, true) => Some(
-                self.bank
-                    .get_liability_amount(self.balance.liability_shares.into())?,
-            ),
-            _ => None,
-        } {
-            let last_update = if self.balance.last_update < MIN_EMISSIONS_START_TIME {
-                current_timestamp
-            } else {
-                self.balance.last_update
+        self.bank.update_emissions_index(current_timestamp as i64)?;
+
+        let emissions_index_start = self.bank.emissions_index_start as u64;
+        if self.balance.last_update >= MIN_EMISSIONS_START_TIME
+            && self.balance.last_update < emissions_index_start
+        {
+            if let Some(balance_amount) = match (
+                self.balance.get_side(),
+                self.bank.get_flag(EMISSIONS_FLAG_LENDING_ACTIVE),
+                self.bank.get_flag(EMISSIONS_FLAG_BORROW_ACTIVE),
+            ) {
+                (Some(BalanceSide::Assets), true, _) => Some(
+                    self.bank
+                        .get_asset_amount(self.balance.asset_shares.into())?,
+                ),
+                (Some(BalanceSide::Liabilities), _, true) => Some(
+                    self.bank
+                        .get_liability_amount(self.balance.liability_shares.into())?,
+                ),
+                _ => None,
+            } {
+                let period = I80F48::from_num(self.bank.get_emissions_period(
+                    self.balance.last_update as i64,
+                    emissions_index_start as i64,
+                ));
+                let emissions_rate = I80F48::from_num(self.bank.emissions_rate);
+                let emissions = calc_emissions(
+                    period,
+                    balance_amount,
+                    self.bank.mint_decimals as usize,
+                    emissions_rate,
+                )?;
+
+                let emissions_real = min(emissions, I80F48::from(self.bank.emissions_remaining));
+
+                if emissions != emissions_real {
+                    msg!(
+                        "Emissions capped: {} ({} calculated) for period {}s",
+                        emissions_real,
+                        emissions,
+                        period
+                    );
+                }
+
+                self.add_emissions_outstanding(emissions_real)?;
+                self.bank.emissions_remaining = {
+                    I80F48::from(self.bank.emissions_remaining)
+                        .checked_sub(emissions_real)
+                        .ok_or_else(math_error!())?
+                }
+                .into();
+            }
+        }
+
+        if let Some(side) = self.balance.get_side() {
+            let shares: I80F48 = match side {
+                BalanceSide::Assets => self.balance.asset_shares.into(),
+                BalanceSide::Liabilities => self.balance.liability_shares.into(),
             };
-            let period = I80F48::from_num(
-                current_timestamp
-                    .checked_sub(last_update)
+            let index_delta = max(
+                self.bank
+                    .get_emissions_index(side)
+                    .checked_sub(self.balance.get_emissions_index_checkpoint())
                     .ok_or_else(math_error!())?,
+                I80F48::ZERO,
             );
-            let emissions_rate = I80F48::from_num(self.bank.emissions_rate);
-            let emissions = calc_emissions(
-                period,
-                balance_amount,
-                self.bank.mint_decimals as usize,
-                emissions_rate,
+            let emissions = shares
+                .checked_mul(index_delta)
+                .ok_or_else(math_error!())?
+                .checked_div(EXP_10_I80F48[self.bank.mint_decimals as usize])
+                .ok_or_else(math_error!())?;
+
+            self.add_emissions_outstanding(emissions)?;
+        }
+
+        debug!(
+            "Outstanding emissions: {}",
+            I80F48::from(self.balance.emissions_outstanding)
+        );
+
+        self.checkpoint_emissions()?;
+        self.balance.last_update = current_timestamp;
+
+        Ok(())
+    }
+
+    /// Settle the balance's rewards of every active stream of the bank's `EmissionsProgram`, which
+    /// must precede any change of a balance enrolled in the streams. The emissions program and the
+    /// account's `EmissionsAccrual` for the bank are found in `remaining_ais`. No-op for balances
+    /// that are not enrolled, so instructions changing a balance call it unconditionally.
+    pub fn settle_emissions_streams<'info>(
+        &mut self,
+        surroundfi_account: &Pubkey,
+        remaining_ais: &'info [AccountInfo<'info>],
+    ) -> SurroundfiResult {
+        if self.balance.is_emissions_streams_enrolled() {
+            let (emissions_program, emissions_accrual) = find_emissions_streams_accounts(
+                surroundfi_account,
+                &self.balance.bank_pk,
+                remaining_ais,
+            )?
+            .ok_or_else(|| error!(SurroundfiError::EmissionsAccrualMissing))?;
+
+            self.accrue_emissions_streams(
+                &mut emissions_program.load_mut()?,
+                &mut emissions_accrual.load_mut()?,
+                None,
+                Clock::get()?.unix_timestamp,
             )?;
+        }
 
-            let emissions_real = min(emissions, I80F48::from(self.bank.emissions_remaining));
+        self.emissions_streams_settled = true;
 
-            if emissions != emissions_real {
-                msg!(
-                    "Emissions capped: {} ({} calculated) for period {}s",
-                    emissions_real,
-                    emissions,
-                    period
-                );
-            }
+        Ok(())
+    }
 
-            debug!(
-                "Outstanding emissions: {}",
-                I80F48::from(self.balance.emissions_outstanding)
-            );
+    /// Settle the balance's rewards of every active stream, or only of `stream_id`. A balance that
+    /// is not enrolled yet is enrolled instead: it starts to earn every active stream from now.
+    ///
+    /// Rewards are set aside for all shares of the stream's side by
+    /// `EmissionsStream::update_index`, the balance collects its part since its checkpoint. Since
+    /// every change of an enrolled balance settles first, its current shares were held since.
+    pub fn accrue_emissions_streams(
+        &mut self,
+        emissions_program: &mut EmissionsProgram,
+        emissions_accrual: &mut EmissionsAccrual,
+        stream_id: Option<u8>,
+        current_timestamp: i64,
+    ) -> SurroundfiResult {
+        let enrolled = self.balance.is_emissions_streams_enrolled();
 
-            self.balance.emissions_outstanding = {
-                I80F48::from(self.balance.emissions_outstanding)
-                    .checked_add(emissions_real)
-                    .ok_or_else(math_error!())?
+        for (id, stream) in emissions_program.streams.iter_mut().enumerate() {
+            if !stream.is_active() {
+                continue;
             }
-            .into();
-            self.bank.emissions_remaining = {
-                I80F48::from(self.bank.emissions_remaining)
-                    .checked_sub(emissions_real)
-                    .ok_or_else(math_error!())?
+            let id = id as u8;
+
+            stream.update_index(self.bank, current_timestamp)?;
+
+            if !enrolled {
+                emissions_accrual.checkpoint(id, stream, current_timestamp)?;
+            } else if stream_id.map_or(true, |stream_id| stream_id == id) {
+                let shares = if stream.is_lending() {
+                    self.balance.asset_shares
+                } else {
+                    self.balance.liability_shares
+                };
+                emissions_accrual.settle(
+                    id,
+                    stream,
+                    shares.into(),
+                    self.bank.mint_decimals,
+                    current_timestamp,
+                )?;
             }
-            .into();
         }
 
-        self.balance.last_update = current_timestamp;
+        self.balance.emissions_streams_enrolled = 1;
+        self.emissions_streams_settled = true;
+
+        Ok(())
+    }
+
+    fn check_emissions_streams_settled(&self) -> SurroundfiResult {
+        check!(
+            !self.balance.is_emissions_streams_enrolled() || self.emissions_streams_settled,
+            SurroundfiError::EmissionsAccrualMissing
+        );
+
+        Ok(())
+    }
+
+    /// Set the emissions checkpoint to the bank's index for the balance's current side. Must be
+    /// called after the balance changes, since a balance can switch sides.
+    fn checkpoint_emissions(&mut self) -> SurroundfiResult {
+        let index = match self.balance.get_side() {
+            Some(side) => self.bank.get_emissions_index(side),
+            None => I80F48::ZERO,
+        };
+
+        self.balance.set_emissions_index_checkpoint(index)
+    }
+
+    fn add_emissions_outstanding(&mut self, emissions: I80F48) -> SurroundfiResult {
+        self.balance.emissions_outstanding = {
+            I80F48::from(self.balance.emissions_outstanding)
+                .checked_add(emissions)
+                .ok_or_else(math_error!())?
+        }
+        .into();
 
         Ok(())
     }
@@ -1520,18 +2356,26 @@ mod test {
                     active: 1,
                     bank_pk: bank_pk.into(),
                     bank_asset_tag: ASSET_TAG_DEFAULT,
-                    _pad0: [0; 6],
+                    bank_interest_bearing: 0,
+                    emissions_streams_enrolled: 0,
+                    _pad0: [0; 4],
                     asset_shares: WrappedI80F48::default(),
                     liability_shares: WrappedI80F48::default(),
                     emissions_outstanding: WrappedI80F48::default(),
                     last_update: 0,
-                    _padding: [0_u64],
+                    emissions_index_checkpoint: 0,
                 }; 16],
-                _padding: [0; 8],
+                flashloan_volumes: [FlashloanVolume::zeroed(); MAX_FLASHLOAN_VOLUMES],
             },
             account_flags: ACCOUNT_TRANSFER_AUTHORITY_ALLOWED,
             health_cache: HealthCache::zeroed(),
-            _padding0: [0; 21],
+            account_index: 0,
+            third_party_index: 0,
+            bump: 0,
+            _pad0: [0; 3],
+            extension: Pubkey::default(),
+            creator: Pubkey::default(),
+            _padding0: [0; 2],
         };
 
         assert!(acc.get_flag(ACCOUNT_TRANSFER_AUTHORITY_ALLOWED));
@@ -0,0 +1,69 @@
+use super::{
+    health_cache::HealthCacheBalanceValues,
+    surroundfi_account::{Balance, SurroundfiAccount, MAX_LENDING_ACCOUNT_BALANCES},
+    surroundfi_group::WrappedI80F48,
+};
+use crate::{assert_struct_align, assert_struct_size, prelude::*};
+use anchor_lang::prelude::*;
+use type_layout::TypeLayout;
+
+pub const MAX_EXTENSION_BALANCES: usize = 16;
+
+assert_struct_size!(SurroundfiAccountExtension, 3040);
+assert_struct_align!(SurroundfiAccountExtension, 8);
+/// Additional `Balance` slots for accounts that need more than `MAX_LENDING_ACCOUNT_BALANCES`
+/// positions. Linked from `SurroundfiAccount.extension`, and a PDA of that account.
+///
+/// Whenever an account has an extension, the extension must be passed in remaining accounts
+/// after the account's own bank/oracle accounts (which also cover the extension's balances, in
+/// order, after the balances of the main account).
+#[account(zero_copy)]
+#[repr(C)]
+#[derive(PartialEq, Eq, TypeLayout)]
+pub struct SurroundfiAccountExtension {
+    /// The account this extension belongs to
+    pub surroundfi_account: Pubkey, // 32
+    pub balances: [Balance; MAX_EXTENSION_BALANCES], // 104 * 16 = 1664
+    pub _padding0: [u64; 8],                         // 8 * 8 = 64
+    /// Continues the account's `health_cache.prices` for the balances that don't fit in it (past
+    /// `MAX_LENDING_ACCOUNT_BALANCES` active balances). Updated along with the health cache when
+    /// the extension is passed as writable.
+    pub health_cache_prices: [WrappedI80F48; MAX_EXTENSION_BALANCES], // 16 * 16 = 256
+    /// The maintenance-weighted value of every balance: those of the account first (in the order
+    /// of `lending_account.balances`), then those of the extension. Doesn't fit in the account's
+    /// health cache, so only accounts with an extension record it. Updated the same way.
+    pub health_cache_balance_values:
+        [HealthCacheBalanceValues; MAX_LENDING_ACCOUNT_BALANCES + MAX_EXTENSION_BALANCES], // 1024
+}
+
+impl SurroundfiAccountExtension {
+    pub const LEN: usize = std::mem::size_of::<SurroundfiAccountExtension>();
+
+    pub fn initialize(&mut self, surroundfi_account: Pubkey) {
+        self.surroundfi_account = surroundfi_account;
+    }
+
+    pub fn get_active_balances_iter(&self) -> impl Iterator<Item = &Balance> {
+        self.balances.iter().filter(|b| b.is_active())
+    }
+}
+
+/// Finds the extension linked to `surroundfi_account` in `remaining_ais`, if the account has one.
+/// The extension may appear anywhere in `remaining_ais`, it is matched by key.
+///
+/// Errors if the account has an extension but it was not passed.
+pub fn find_account_extension<'info>(
+    surroundfi_account: &SurroundfiAccount,
+    remaining_ais: &'info [AccountInfo<'info>],
+) -> SurroundfiResult<Option<AccountLoader<'info, SurroundfiAccountExtension>>> {
+    if !surroundfi_account.has_extension() {
+        return Ok(None);
+    }
+
+    let extension_ai = remaining_ais
+        .iter()
+        .find(|ai| ai.key.eq(&surroundfi_account.extension))
+        .ok_or_else(|| error!(SurroundfiError::AccountExtensionMissing))?;
+
+    Ok(Some(AccountLoader::try_from(extension_ai)?))
+}
@@ -1,16 +1,22 @@
 use super::{
     surroundfi_account::{BalanceSide, RequirementType},
-    price::{OraclePriceFeedAdapter, OracleSetup},
+    price::{OraclePriceFeedAdapter, OracleSetup, ReceiptTokenPriceFeed},
 };
 #[cfg(not(feature = "client"))]
-use crate::events::{GroupEventHeader, LendingPoolBankAccrueInterestEvent};
+use crate::events::{GroupEventHeader, LendingPoolBankAccrueInterestEvent, EVENT_SCHEMA_VERSION};
 use crate::{
     assert_struct_align, assert_struct_size, check,
     constants::{
-        EMISSION_FLAGS, FEE_VAULT_AUTHORITY_SEED, FEE_VAULT_SEED, GROUP_FLAGS,
-        INSURANCE_VAULT_AUTHORITY_SEED, INSURANCE_VAULT_SEED, LIQUIDITY_VAULT_AUTHORITY_SEED,
-        LIQUIDITY_VAULT_SEED, MAX_ORACLE_KEYS, MAX_PYTH_ORACLE_AGE, MAX_SWB_ORACLE_AGE,
+        ARENA_MAX_LEVERAGE, ARENA_MAX_LIQUIDATION_FEE, BANKRUPT_THRESHOLD,
+        EMISSIONS_FLAG_BORROW_ACTIVE, EMISSIONS_FLAG_LENDING_ACTIVE, EMISSION_FLAGS, EXP_10_I80F48,
+        FEE_VAULT_AUTHORITY_SEED, FEE_VAULT_SEED, GROUP_FLAGS, INSURANCE_VAULT_AUTHORITY_SEED,
+        INSURANCE_VAULT_SEED, LIQUIDATION_LIQUIDATOR_FEE, LIQUIDITY_VAULT_AUTHORITY_SEED,
+        LIQUIDITY_VAULT_SEED, MAX_BANKRUPT_THRESHOLD, MAX_ORACLE_KEYS, MAX_PYTH_ORACLE_AGE,
+        MAX_SWB_ORACLE_AGE, MINT_EXTENSIONS_RESTRICTED, MINT_EXTENSION_INTEREST_BEARING,
         ORACLE_MIN_AGE, PERMISSIONLESS_BAD_DEBT_SETTLEMENT_FLAG, SECONDS_PER_YEAR,
+        STAKED_OVERRIDE_ASSET_WEIGHTS, STAKED_OVERRIDE_DEPOSIT_LIMIT,
+        STAKED_OVERRIDE_LIABILITY_WEIGHTS, STAKED_OVERRIDE_ORACLE_MAX_AGE,
+        STAKED_OVERRIDE_RISK_TIER, STAKED_OVERRIDE_TOTAL_ASSET_VALUE_INIT_LIMIT,
         TOTAL_ASSET_VALUE_INIT_LIMIT_INACTIVE,
     },
     debug, math_error,
@@ -21,7 +27,10 @@ use crate::{
 };
 use crate::{
     borsh::{BorshDeserialize, BorshSerialize},
-    constants::ASSET_TAG_DEFAULT,
+    constants::{
+        ASSET_TAG_DEFAULT, ASSET_TAG_RECEIPT, ASSET_TAG_SOL, ASSET_TAG_STABLE, ASSET_TAG_STAKED,
+        MAX_ASSET_TAGS,
+    },
     constants::FREEZE_SETTINGS,
 };
 use anchor_lang::prelude::borsh;
@@ -40,6 +49,8 @@ use type_layout::TypeLayout;
 
 pub const PROGRAM_FEES_ENABLED: u64 = 1;
 pub const ARENA_GROUP: u64 = 2;
+pub const STAKED_STABLE_BORROW_ENABLED: u64 = 4;
+pub const FEE_DISTRIBUTION_ENABLED: u64 = 8;
 
 assert_struct_size!(SurroundfiGroup, 1056);
 #[account(zero_copy)]
@@ -49,7 +60,11 @@ pub struct SurroundfiGroup {
     /// Bitmask for group settings flags.
     /// * 0: `PROGRAM_FEES_ENABLED` If set, program-level fees are enabled.
     /// * 1: `ARENA_GROUP` If set, this is an arena group, which can only have two banks
-    /// * Bits 1-63: Reserved for future use.
+    /// * 2: `STAKED_STABLE_BORROW_ENABLED` If set, accounts with staked collateral can also borrow
+    ///   `ASSET_TAG_STABLE` assets
+    /// * 3: `FEE_DISTRIBUTION_ENABLED` If set, the group has a `FeeDistribution` and fees can only
+    ///   leave the fee vaults through it
+    /// * Bits 4-63: Reserved for future use.
     pub group_flags: u64,
     /// Caches information from the global `FeeState` so the FeeState can be omitted on certain ixes
     pub fee_state_cache: FeeStateCache,
@@ -59,8 +74,21 @@ pub struct SurroundfiGroup {
     // 0.1.2 went live.
     pub banks: u16,
     pub pad0: [u8; 6],
-
-    pub _padding_0: [[u64; 2]; 26],
+    /// Insolvent accounts with assets worth less than this (in USD) can be handled as bankrupt.
+    /// Zero means `BANKRUPT_THRESHOLD`, see `get_bankruptcy_threshold`.
+    pub bankruptcy_threshold: WrappedI80F48,
+    /// Which asset tags can be comingled. All zero means `AssetTagMatrix::DEFAULT`, see
+    /// `get_asset_tag_matrix`.
+    pub asset_tag_matrix: AssetTagMatrix,
+    /// Arena groups only: risk parameters of the group's token pair. Zeroes mean no extra
+    /// limits, see `get_max_leverage` and `get_liquidator_fee`.
+    pub arena_config: ArenaConfig,
+    /// `MINT_EXTENSIONS_RESTRICTED` extensions that the mints of new banks may have, see
+    /// `validate_mint_extensions`
+    pub permitted_mint_extensions: u64,
+    pub _pad1: [u8; 8],
+
+    pub _padding_0: [[u64; 2]; 19],
     pub _padding_1: [[u64; 2]; 32],
     pub _padding_3: u64,
     pub _padding_4: u64,
@@ -76,6 +104,166 @@ pub struct FeeStateCache {
     pub program_fee_rate: WrappedI80F48,
 }
 
+const fn asset_tag_bit(asset_tag: u8) -> u8 {
+    1 << asset_tag
+}
+
+assert_struct_size!(AssetTagMatrix, 16);
+/// Which asset tags can be comingled, see `validate_asset_tags` and `validate_bank_asset_tags`.
+/// Each row is a bitmask of tags: bit `j` of `coexist[i]` is set if tags `i` and `j` can have
+/// balances in the same account.
+#[derive(
+    AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, Zeroable, Pod, Debug, PartialEq, Eq,
+)]
+#[repr(C)]
+pub struct AssetTagMatrix {
+    /// Tags that can have balances in the same account. Must be symmetric.
+    pub coexist: [u8; MAX_ASSET_TAGS],
+    /// Bit `j` of `liquidation[i]` is set if a liability tagged `j` can be liquidated against
+    /// collateral tagged `i`
+    pub liquidation: [u8; MAX_ASSET_TAGS],
+}
+
+impl AssetTagMatrix {
+    /// The built-in rules: SOL comingles with anything, Default (and Receipt, Stable) assets never
+    /// mix with Staked assets. Liquidations follow the same rules. Other tags can't be used.
+    pub const DEFAULT: Self = {
+        let default_like = asset_tag_bit(ASSET_TAG_DEFAULT)
+            | asset_tag_bit(ASSET_TAG_SOL)
+            | asset_tag_bit(ASSET_TAG_RECEIPT)
+            | asset_tag_bit(ASSET_TAG_STABLE);
+        let staked_like = asset_tag_bit(ASSET_TAG_SOL) | asset_tag_bit(ASSET_TAG_STAKED);
+        // Indexed by tag: DEFAULT, SOL, STAKED, RECEIPT, STABLE
+        let coexist = [
+            default_like,
+            default_like | staked_like,
+            staked_like,
+            default_like,
+            default_like,
+            0,
+            0,
+            0,
+        ];
+
+        AssetTagMatrix {
+            coexist,
+            liquidation: coexist,
+        }
+    };
+
+    pub fn validate(&self) -> SurroundfiResult {
+        for tag_a in 0..MAX_ASSET_TAGS as u8 {
+            for tag_b in 0..MAX_ASSET_TAGS as u8 {
+                check!(
+                    self.can_coexist(tag_a, tag_b)? == self.can_coexist(tag_b, tag_a)?,
+                    SurroundfiError::InvalidConfig,
+                    "Asset tags {} and {} must coexist both ways",
+                    tag_a,
+                    tag_b
+                );
+            }
+        }
+
+        // Accounts may already hold several balances of a built-in tag
+        for tag in ASSET_TAG_DEFAULT..=ASSET_TAG_STABLE {
+            check!(
+                self.can_coexist(tag, tag)? && self.can_liquidate(tag, tag)?,
+                SurroundfiError::InvalidConfig,
+                "Asset tag {} must coexist with and be liquidated against itself",
+                tag
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Lets Staked and Stable assets comingle, and Staked collateral be liquidated for Stable
+    /// liabilities, see `STAKED_STABLE_BORROW_ENABLED`
+    pub fn with_staked_stable_borrow(mut self) -> Self {
+        self.coexist[ASSET_TAG_STAKED as usize] |= asset_tag_bit(ASSET_TAG_STABLE);
+        self.coexist[ASSET_TAG_STABLE as usize] |= asset_tag_bit(ASSET_TAG_STAKED);
+        self.liquidation[ASSET_TAG_STAKED as usize] |= asset_tag_bit(ASSET_TAG_STABLE);
+        self
+    }
+
+    pub fn can_coexist(&self, tag_a: u8, tag_b: u8) -> SurroundfiResult<bool> {
+        Self::is_set(&self.coexist, tag_a, tag_b)
+    }
+
+    pub fn can_liquidate(&self, asset_tag: u8, liab_tag: u8) -> SurroundfiResult<bool> {
+        Self::is_set(&self.liquidation, asset_tag, liab_tag)
+    }
+
+    fn is_set(rows: &[u8; MAX_ASSET_TAGS], tag_a: u8, tag_b: u8) -> SurroundfiResult<bool> {
+        check!(
+            (tag_a as usize) < MAX_ASSET_TAGS && (tag_b as usize) < MAX_ASSET_TAGS,
+            SurroundfiError::UnsupportedAssetTag
+        );
+
+        Ok(rows[tag_a as usize] & asset_tag_bit(tag_b) != 0)
+    }
+}
+
+/// Settings of `surroundfi_group_configure` that are left unchanged if `None`
+#[derive(AnchorDeserialize, AnchorSerialize, Default, Clone, PartialEq, Eq)]
+pub struct GroupConfigOpt {
+    /// See `SurroundfiGroup::bankruptcy_threshold`, at most `MAX_BANKRUPT_THRESHOLD`
+    pub bankruptcy_threshold: Option<WrappedI80F48>,
+}
+
+assert_struct_size!(ArenaConfig, 64);
+/// Risk parameters of the token pair of an arena group (see `ARENA_GROUP`), on top of its banks'
+/// configs
+#[derive(
+    AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, Zeroable, Pod, Debug, PartialEq, Eq,
+)]
+#[repr(C)]
+pub struct ArenaConfig {
+    /// Max ratio of an account's total (unweighted) assets to its equity, e.g. 5 for 5x. Zero means
+    /// the banks' weights are the only limit.
+    pub max_leverage: WrappedI80F48,
+    /// Share of the liquidated collateral's value paid to liquidators. Zero means
+    /// `LIQUIDATION_LIQUIDATOR_FEE`.
+    pub liquidation_fee: WrappedI80F48,
+    /// The only program leveraged positions can swap with, see
+    /// `lending_account_open_leveraged_position`. Default means leveraged positions are disabled.
+    pub swap_program: Pubkey,
+}
+
+impl ArenaConfig {
+    pub fn validate(&self) -> SurroundfiResult {
+        let max_leverage: I80F48 = self.max_leverage.into();
+        check!(
+            max_leverage == I80F48::ZERO
+                || (max_leverage >= I80F48::ONE && max_leverage <= ARENA_MAX_LEVERAGE),
+            SurroundfiError::InvalidConfig,
+            "Max leverage must be zero or between 1 and {}",
+            ARENA_MAX_LEVERAGE
+        );
+
+        let liquidation_fee: I80F48 = self.liquidation_fee.into();
+        check!(
+            liquidation_fee >= I80F48::ZERO && liquidation_fee <= ARENA_MAX_LIQUIDATION_FEE,
+            SurroundfiError::InvalidConfig,
+            "Liquidation fee must be between 0 and {}",
+            ARENA_MAX_LIQUIDATION_FEE
+        );
+
+        check!(
+            self.swap_program != crate::ID,
+            SurroundfiError::InvalidConfig,
+            "Swap program can't be this program"
+        );
+
+        Ok(())
+    }
+
+    /// True if leveraged positions can swap with `program`
+    pub fn is_swap_program(&self, program: &Pubkey) -> bool {
+        self.swap_program != Pubkey::default() && self.swap_program == *program
+    }
+}
+
 impl SurroundfiGroup {
     pub fn update_admin(&mut self, new_admin: Pubkey) {
         if self.admin == new_admin {
@@ -97,7 +285,7 @@ impl SurroundfiGroup {
 
     pub fn get_group_bank_config(&self) -> GroupBankConfig {
         GroupBankConfig {
-            program_fees: self.group_flags == PROGRAM_FEES_ENABLED,
+            program_fees: self.program_fees_enabled() && !self.is_arena_group(),
         }
     }
 
@@ -140,6 +328,120 @@ impl SurroundfiGroup {
         (self.group_flags & ARENA_GROUP) != 0
     }
 
+    pub fn set_staked_stable_borrow_enabled(&mut self, enabled: bool) {
+        if enabled {
+            self.group_flags |= STAKED_STABLE_BORROW_ENABLED;
+        } else {
+            self.group_flags &= !STAKED_STABLE_BORROW_ENABLED;
+        }
+    }
+
+    /// True if accounts with staked collateral can borrow `ASSET_TAG_STABLE` assets
+    pub fn staked_stable_borrow_enabled(&self) -> bool {
+        (self.group_flags & STAKED_STABLE_BORROW_ENABLED) != 0
+    }
+
+    /// Set once the group's `FeeDistribution` is created, it can't be unset
+    pub fn set_fee_distribution_enabled(&mut self) {
+        self.group_flags |= FEE_DISTRIBUTION_ENABLED;
+    }
+
+    /// True if fees are paid out through the group's `FeeDistribution`
+    pub fn fee_distribution_enabled(&self) -> bool {
+        (self.group_flags & FEE_DISTRIBUTION_ENABLED) != 0
+    }
+
+    /// The asset value (in USD) below which an insolvent account can be handled as bankrupt
+    pub fn get_bankruptcy_threshold(&self) -> I80F48 {
+        let bankruptcy_threshold: I80F48 = self.bankruptcy_threshold.into();
+        if bankruptcy_threshold == I80F48::ZERO {
+            BANKRUPT_THRESHOLD
+        } else {
+            bankruptcy_threshold
+        }
+    }
+
+    /// The asset tag rules of this group, `AssetTagMatrix::DEFAULT` unless configured, plus the
+    /// Staked/Stable rules if `STAKED_STABLE_BORROW_ENABLED` is set
+    pub fn get_asset_tag_matrix(&self) -> AssetTagMatrix {
+        let asset_tag_matrix = if self.asset_tag_matrix == AssetTagMatrix::default() {
+            AssetTagMatrix::DEFAULT
+        } else {
+            self.asset_tag_matrix
+        };
+
+        if self.staked_stable_borrow_enabled() {
+            asset_tag_matrix.with_staked_stable_borrow()
+        } else {
+            asset_tag_matrix
+        }
+    }
+
+    /// The max leverage of accounts in this group, None if not an arena group or not configured
+    pub fn get_max_leverage(&self) -> Option<I80F48> {
+        let max_leverage: I80F48 = self.arena_config.max_leverage.into();
+        if self.is_arena_group() && max_leverage != I80F48::ZERO {
+            Some(max_leverage)
+        } else {
+            None
+        }
+    }
+
+    /// The liquidator's share of the collateral liquidated in this group
+    pub fn get_liquidator_fee(&self) -> I80F48 {
+        let liquidation_fee: I80F48 = self.arena_config.liquidation_fee.into();
+        if self.is_arena_group() && liquidation_fee != I80F48::ZERO {
+            liquidation_fee
+        } else {
+            LIQUIDATION_LIQUIDATOR_FEE
+        }
+    }
+
+    pub fn set_arena_config(&mut self, arena_config: ArenaConfig) -> SurroundfiResult {
+        check!(self.is_arena_group(), SurroundfiError::NotArenaGroup);
+        arena_config.validate()?;
+        self.arena_config = arena_config;
+        Ok(())
+    }
+
+    pub fn set_permitted_mint_extensions(
+        &mut self,
+        permitted_mint_extensions: u64,
+    ) -> SurroundfiResult {
+        check!(
+            permitted_mint_extensions & !MINT_EXTENSIONS_RESTRICTED == 0,
+            SurroundfiError::InvalidConfig,
+            "Only restricted extensions need to be permitted"
+        );
+        self.permitted_mint_extensions = permitted_mint_extensions;
+        Ok(())
+    }
+
+    /// Mints with restricted extensions (see `MINT_EXTENSIONS_RESTRICTED`) can only back banks of
+    /// groups that permit all of them.
+    pub fn validate_mint_extensions(&self, mint_extensions: u64) -> SurroundfiResult {
+        let not_permitted =
+            mint_extensions & MINT_EXTENSIONS_RESTRICTED & !self.permitted_mint_extensions;
+        check!(
+            not_permitted == 0,
+            SurroundfiError::MintExtensionNotPermitted,
+            "Mint extensions {:#x} are not permitted",
+            not_permitted
+        );
+        Ok(())
+    }
+
+    pub fn set_bankruptcy_threshold(&mut self, bankruptcy_threshold: I80F48) -> SurroundfiResult {
+        check!(
+            bankruptcy_threshold > I80F48::ZERO && bankruptcy_threshold <= MAX_BANKRUPT_THRESHOLD,
+            SurroundfiError::InvalidConfig,
+            "Bankruptcy threshold must be positive and at most {}",
+            MAX_BANKRUPT_THRESHOLD
+        );
+        self.bankruptcy_threshold = bankruptcy_threshold.into();
+        Ok(())
+    }
+
     // Increment the bank count by 1. If this is an arena group, which only supports two banks,
     // errors if trying to add a third bank. If you managed to create 16,000 banks, congrats, does
     // nothing.
@@ -179,8 +481,10 @@ impl From<InterestRateConfigCompact> for InterestRateConfig {
             protocol_fixed_fee_apr: ir_config.protocol_fixed_fee_apr,
             protocol_ir_fee: ir_config.protocol_ir_fee,
             protocol_origination_fee: ir_config.protocol_origination_fee,
-            _padding0: [0; 16],
-            _padding1: [[0; 32]; 3],
+            flashloan_fee: I80F48::ZERO.into(),
+            flashloan_group_fee: I80F48::ZERO.into(),
+            referrer_fee_share: I80F48::ZERO.into(),
+            _padding1: [[0; 32]; 2],
         }
     }
 }
@@ -231,9 +535,16 @@ pub struct InterestRateConfig {
     /// Earned by the group, goes to `collected_group_fees_outstanding`
     pub protocol_ir_fee: WrappedI80F48,
     pub protocol_origination_fee: WrappedI80F48,
-
-    pub _padding0: [u8; 16],
-    pub _padding1: [[u8; 32]; 3],
+    /// Charged on liabilities opened and repaid within a flashloan, goes to depositors
+    pub flashloan_fee: WrappedI80F48,
+    /// Charged on liabilities opened and repaid within a flashloan, goes to
+    /// `collected_group_fees_outstanding`
+    pub flashloan_group_fee: WrappedI80F48,
+    /// Share of the group's part of `protocol_origination_fee` that goes to the referrer of a
+    /// borrow, if one is passed. Accrues to the referrer's `ReferrerFees`.
+    pub referrer_fee_share: WrappedI80F48,
+
+    pub _padding1: [[u8; 32]; 2],
 }
 
 impl InterestRateConfig {
@@ -270,6 +581,12 @@ impl InterestRateConfig {
         check!(max_ir > I80F48::ZERO, SurroundfiError::InvalidConfig);
         check!(plateau_ir < max_ir, SurroundfiError::InvalidConfig);
 
+        let referrer_fee_share: I80F48 = self.referrer_fee_share.into();
+        check!(
+            referrer_fee_share >= I80F48::ZERO && referrer_fee_share <= I80F48::ONE,
+            SurroundfiError::InvalidConfig
+        );
+
         Ok(())
     }
 
@@ -294,6 +611,9 @@ impl InterestRateConfig {
             self.protocol_origination_fee,
             ir_config.protocol_origination_fee
         );
+        set_if_some!(self.flashloan_fee, ir_config.flashloan_fee);
+        set_if_some!(self.flashloan_group_fee, ir_config.flashloan_group_fee);
+        set_if_some!(self.referrer_fee_share, ir_config.referrer_fee_share);
     }
 }
 
@@ -436,6 +756,9 @@ pub struct InterestRateConfigOpt {
     pub protocol_fixed_fee_apr: Option<WrappedI80F48>,
     pub protocol_ir_fee: Option<WrappedI80F48>,
     pub protocol_origination_fee: Option<WrappedI80F48>,
+    pub flashloan_fee: Option<WrappedI80F48>,
+    pub flashloan_group_fee: Option<WrappedI80F48>,
+    pub referrer_fee_share: Option<WrappedI80F48>,
 }
 
 /// Group level configuration to be used in bank accounts.
@@ -508,7 +831,58 @@ pub struct Bank {
     /// Fees collected and pending withdraw for the `FeeState.global_fee_wallet`'s cannonical ATA for `mint`
     pub collected_program_fees_outstanding: WrappedI80F48,
 
-    pub _padding_0: [[u64; 2]; 27],
+    /// Mint of transferable receipt tokens representing asset shares of this bank, see
+    /// `lending_pool_init_receipt_mint`. Default if receipt tokens are not enabled.
+    pub receipt_mint: Pubkey,
+    pub receipt_mint_bump: u8,
+
+    pub _pad3: [u8; 15], // 1x u8 + 15 = 16
+
+    /// Emissions per whole asset share (1e(bank.mint_decimals) shares) since
+    /// `emissions_index_start`, in native `emissions_mint` tokens. See `update_emissions_index`.
+    pub emissions_asset_index: WrappedI80F48,
+    /// Emissions per whole liability share since `emissions_index_start`, in native
+    /// `emissions_mint` tokens.
+    pub emissions_liability_index: WrappedI80F48,
+    /// Last time the emissions indexes were updated. 0 until the first balance update after
+    /// indexes were introduced, which starts them.
+    pub emissions_index_last_update: i64,
+    /// When the emissions indexes started. Balances last updated before this time settle
+    /// emissions the legacy way (rate x balance x time) up to it.
+    pub emissions_index_start: i64,
+    /// Unix timestamp, emissions don't accrue before. 0 to start immediately.
+    pub emissions_start_time: i64,
+    /// Unix timestamp, emissions don't accrue after. 0 if emissions run until
+    /// `emissions_remaining` is exhausted.
+    pub emissions_end_time: i64,
+
+    /// Staking shares of the insurance fund outstanding, see `lending_pool_insurance_stake`
+    pub insurance_stake_shares: WrappedI80F48,
+    /// Native tokens of the insurance vault per staking share. Reset to 1 whenever no shares are
+    /// outstanding. Zero if bad debt wiped out the stakers.
+    pub insurance_stake_share_value: WrappedI80F48,
+    /// Share of the insurance fees paid into the insurance vault that goes to stakers, the rest
+    /// goes to the protocol's part of the fund
+    pub insurance_stake_fee_share: WrappedI80F48,
+    /// Seconds between requesting an unstake and withdrawing it. Zero if insurance staking is not
+    /// enabled.
+    pub insurance_stake_cooldown: i64,
+    /// Token-2022 extensions and freeze authority (`MINT_EXTENSION_*`) of `mint` when the bank was
+    /// created, so frontends can warn users about them. Interest-bearing mints are priced at their
+    /// UI amount, see `is_interest_bearing`.
+    pub mint_extensions: u64,
+    /// Asset shares deposited through `lending_pool_deposit_for_receipt` and backing receipt
+    /// tokens. Included in `total_asset_shares`, but excluded from emissions.
+    pub receipt_asset_shares: WrappedI80F48,
+    /// Seconds after the cooldown during which a requested unstake can be withdrawn. Once it
+    /// passes, the unstake must be requested again.
+    pub insurance_stake_unstake_window: i64,
+    pub _padding_2: u64,
+    /// Part of `collected_insurance_fees_outstanding` already credited to stakers, see
+    /// `credit_pending_insurance_stake_fees`
+    pub insurance_stake_fees_credited: WrappedI80F48,
+
+    pub _padding_0: [[u64; 2]; 13],
     pub _padding_1: [[u64; 2]; 32], // 16 * 2 * 32 = 1024B
 }
 
@@ -737,6 +1111,9 @@ impl Bank {
             self.update_flag(flag, FREEZE_SETTINGS);
         }
 
+        self.config
+            .mark_staked_settings_overrides(config.get_staked_settings_overrides());
+
         self.config.validate()?;
 
         Ok(())
@@ -746,6 +1123,10 @@ impl Bank {
     pub fn configure_unfrozen_fields_only(&mut self, config: &BankConfigOpt) -> SurroundfiResult {
         set_if_some!(self.config.deposit_limit, config.deposit_limit);
         set_if_some!(self.config.borrow_limit, config.borrow_limit);
+        if config.deposit_limit.is_some() {
+            self.config
+                .mark_staked_settings_overrides(STAKED_OVERRIDE_DEPOSIT_LIMIT);
+        }
         // weights didn't change so no validation is needed
         Ok(())
     }
@@ -777,6 +1158,7 @@ impl Bank {
             #[cfg(not(feature = "client"))]
             emit!(LendingPoolBankAccrueInterestEvent {
                 header: GroupEventHeader {
+                    version: EVENT_SCHEMA_VERSION,
                     surroundfi_group: self.group,
                     signer: None
                 },
@@ -849,6 +1231,7 @@ impl Bank {
 
             emit!(LendingPoolBankAccrueInterestEvent {
                 header: GroupEventHeader {
+                    version: EVENT_SCHEMA_VERSION,
                     surroundfi_group: self.group,
                     signer: None
                 },
@@ -863,6 +1246,9 @@ impl Bank {
         Ok(())
     }
 
+    /// For Token-2022 mints, the extra accounts of the mint's transfer hook (the hook program, its
+    /// extra account metas account and the accounts listed there) are resolved by key from
+    /// `remaining_accounts`, so ixs can pass their remaining accounts as they are.
     pub fn deposit_spl_transfer<'info>(
         &self,
         amount: u64,
@@ -873,8 +1259,9 @@ impl Bank {
         program: AccountInfo<'info>,
         remaining_accounts: &[AccountInfo<'info>],
     ) -> SurroundfiResult {
+        // Insurance stakers deposit into the insurance vault
         check!(
-            to.key.eq(&self.liquidity_vault),
+            to.key.eq(&self.liquidity_vault) || to.key.eq(&self.insurance_vault),
             SurroundfiError::InvalidTransfer
         );
 
@@ -914,6 +1301,7 @@ impl Bank {
         Ok(())
     }
 
+    /// Transfer hook accounts are resolved like in `deposit_spl_transfer`
     pub fn withdraw_spl_transfer<'info>(
         &self,
         amount: u64,
@@ -985,6 +1373,286 @@ impl Bank {
         Ok(())
     }
 
+    pub fn has_flashloan_fee(&self) -> bool {
+        let ir_config = &self.config.interest_rate_config;
+
+        !I80F48::from(ir_config.flashloan_fee).is_zero()
+            || !I80F48::from(ir_config.flashloan_group_fee).is_zero()
+    }
+
+    /// Split a borrow's `origination_fee` between program fees (`program_fee_rate` of it) and group
+    /// fees. If `with_referrer`, the referrer's `referrer_fee_share` of the group's part is carved
+    /// out and returned, for the caller to record in the `ReferrerFees`.
+    pub fn collect_origination_fee(
+        &mut self,
+        origination_fee: I80F48,
+        program_fee_rate: I80F48,
+        with_referrer: bool,
+    ) -> SurroundfiResult<I80F48> {
+        if origination_fee.is_zero() {
+            return Ok(I80F48::ZERO);
+        }
+
+        let mut group_fee = origination_fee;
+
+        if !program_fee_rate.is_zero() {
+            // Some portion of the origination fee to goes to program fees
+            let program_fee_amount: I80F48 = origination_fee
+                .checked_mul(program_fee_rate)
+                .ok_or_else(math_error!())?;
+            // The remainder of the origination fee goes to group fees
+            group_fee = origination_fee.saturating_sub(program_fee_amount);
+
+            // Update the bank's program fees
+            let program_fees_before: I80F48 = self.collected_program_fees_outstanding.into();
+            self.collected_program_fees_outstanding = program_fees_before
+                .saturating_add(program_fee_amount)
+                .into();
+        }
+
+        // The referrer, if any, is paid out of the group's portion
+        let mut referrer_fee = I80F48::ZERO;
+        if with_referrer {
+            let referrer_fee_share: I80F48 =
+                self.config.interest_rate_config.referrer_fee_share.into();
+            referrer_fee = group_fee
+                .checked_mul(referrer_fee_share)
+                .ok_or_else(math_error!())?;
+            group_fee = group_fee.saturating_sub(referrer_fee);
+        }
+
+        // Update the bank's group fees
+        let group_fees_before: I80F48 = self.collected_group_fees_outstanding.into();
+        self.collected_group_fees_outstanding = group_fees_before.saturating_add(group_fee).into();
+
+        Ok(referrer_fee)
+    }
+
+    /// Credit the flashloan fees on `amount` of liabilities opened and repaid within a flashloan
+    /// to depositors and group fees. Returns the total fee, which the caller must collect into the
+    /// liquidity vault.
+    pub fn credit_flashloan_fee(&mut self, amount: I80F48) -> SurroundfiResult<I80F48> {
+        let ir_config = &self.config.interest_rate_config;
+        let depositor_fee = amount
+            .checked_mul(ir_config.flashloan_fee.into())
+            .ok_or_else(math_error!())?;
+        let group_fee = amount
+            .checked_mul(ir_config.flashloan_group_fee.into())
+            .ok_or_else(math_error!())?;
+
+        if !depositor_fee.is_zero() {
+            // Same as `socialize_loss`, in reverse
+            let total_asset_shares: I80F48 = self.total_asset_shares.into();
+            let old_asset_share_value: I80F48 = self.asset_share_value.into();
+
+            let new_share_value = total_asset_shares
+                .checked_mul(old_asset_share_value)
+                .ok_or_else(math_error!())?
+                .checked_add(depositor_fee)
+                .ok_or_else(math_error!())?
+                .checked_div(total_asset_shares)
+                .ok_or_else(math_error!())?;
+
+            self.asset_share_value = new_share_value.into();
+        }
+
+        self.collected_group_fees_outstanding = I80F48::from(self.collected_group_fees_outstanding)
+            .checked_add(group_fee)
+            .ok_or_else(math_error!())?
+            .into();
+
+        debug!(
+            "Flashloan fee on {}: {} to depositors, {} to group",
+            amount, depositor_fee, group_fee
+        );
+
+        Ok(depositor_fee
+            .checked_add(group_fee)
+            .ok_or_else(math_error!())?)
+    }
+
+    pub fn is_insurance_staking_enabled(&self) -> bool {
+        self.insurance_stake_cooldown > 0
+    }
+
+    /// Native tokens of the insurance vault that belong to stakers. The rest of the vault belongs
+    /// to the protocol.
+    pub fn get_insurance_staked_amount(&self) -> SurroundfiResult<I80F48> {
+        Ok(I80F48::from(self.insurance_stake_shares)
+            .checked_mul(self.insurance_stake_share_value.into())
+            .ok_or_else(math_error!())?)
+    }
+
+    /// Issue staking shares for `amount` paid into the insurance vault. Returns the shares.
+    pub fn insurance_stake(&mut self, amount: I80F48) -> SurroundfiResult<I80F48> {
+        check!(
+            self.is_insurance_staking_enabled(),
+            SurroundfiError::InsuranceStakingDisabled
+        );
+
+        let total_shares: I80F48 = self.insurance_stake_shares.into();
+        if total_shares.is_zero() {
+            self.insurance_stake_share_value = I80F48::ONE.into();
+        }
+        let share_value: I80F48 = self.insurance_stake_share_value.into();
+        check!(
+            !share_value.is_zero(),
+            SurroundfiError::InsuranceStakingDisabled,
+            "Stakers were wiped out by bad debt, wait for them to unstake"
+        );
+
+        let shares = amount.checked_div(share_value).ok_or_else(math_error!())?;
+        self.insurance_stake_shares = total_shares
+            .checked_add(shares)
+            .ok_or_else(math_error!())?
+            .into();
+
+        Ok(shares)
+    }
+
+    /// Burn staking shares. Returns their value in native tokens, rounded down.
+    pub fn insurance_unstake(&mut self, shares: I80F48) -> SurroundfiResult<u64> {
+        let total_shares: I80F48 = self.insurance_stake_shares.into();
+        check!(
+            shares <= total_shares,
+            SurroundfiError::InsufficientInsuranceStake
+        );
+
+        let amount = shares
+            .checked_mul(self.insurance_stake_share_value.into())
+            .ok_or_else(math_error!())?
+            .checked_floor()
+            .ok_or_else(math_error!())?
+            .checked_to_num()
+            .ok_or_else(math_error!())?;
+        self.insurance_stake_shares = total_shares
+            .checked_sub(shares)
+            .ok_or_else(math_error!())?
+            .into();
+
+        Ok(amount)
+    }
+
+    /// Cap the value of `shares` being unstaked at `max_amount`, their value when the unstake was
+    /// requested, so they don't earn fees while unstaking. What they gained since goes to the other
+    /// stakers, or to the protocol's part of the fund if there are none. Returns the shares burnt
+    /// to do so, the rest of `shares` is worth at most `max_amount`.
+    pub fn forfeit_insurance_unstake_gains(
+        &mut self,
+        shares: I80F48,
+        max_amount: I80F48,
+    ) -> SurroundfiResult<I80F48> {
+        let total_shares: I80F48 = self.insurance_stake_shares.into();
+        let share_value: I80F48 = self.insurance_stake_share_value.into();
+        let value = shares.checked_mul(share_value).ok_or_else(math_error!())?;
+        if value <= max_amount {
+            return Ok(I80F48::ZERO);
+        }
+
+        let other_shares = total_shares.checked_sub(shares).ok_or_else(math_error!())?;
+        if other_shares.is_zero() {
+            self.insurance_stake_share_value = max_amount
+                .checked_div(shares)
+                .ok_or_else(math_error!())?
+                .into();
+            return Ok(I80F48::ZERO);
+        }
+
+        let new_share_value = self
+            .get_insurance_staked_amount()?
+            .checked_sub(max_amount)
+            .ok_or_else(math_error!())?
+            .checked_div(other_shares)
+            .ok_or_else(math_error!())?;
+        let burnt_shares = shares
+            .checked_sub(
+                max_amount
+                    .checked_div(new_share_value)
+                    .ok_or_else(math_error!())?,
+            )
+            .ok_or_else(math_error!())?;
+        self.insurance_stake_shares = total_shares
+            .checked_sub(burnt_shares)
+            .ok_or_else(math_error!())?
+            .into();
+        self.insurance_stake_share_value = new_share_value.into();
+
+        Ok(burnt_shares)
+    }
+
+    /// Credit stakers with their share of the insurance fees that accrued since the last credit.
+    /// Must run before shares are issued or start unstaking, so fees only go to the shares staked
+    /// while they accrued: fees that accrued without stakers go to the protocol's part of the fund.
+    /// Returns the stakers' part.
+    ///
+    /// Credited fees are worth their value before they are collected into the insurance vault.
+    pub fn credit_pending_insurance_stake_fees(&mut self) -> SurroundfiResult<I80F48> {
+        let outstanding: I80F48 = self.collected_insurance_fees_outstanding.into();
+        let pending = outstanding
+            .checked_sub(self.insurance_stake_fees_credited.into())
+            .ok_or_else(math_error!())?;
+        self.insurance_stake_fees_credited = outstanding.into();
+
+        if pending <= I80F48::ZERO {
+            return Ok(I80F48::ZERO);
+        }
+
+        self.credit_insurance_stake_fees(pending)
+    }
+
+    /// Credit stakers with their share of `insurance_fees`. Returns the stakers' part.
+    pub fn credit_insurance_stake_fees(
+        &mut self,
+        insurance_fees: I80F48,
+    ) -> SurroundfiResult<I80F48> {
+        let total_shares: I80F48 = self.insurance_stake_shares.into();
+        if total_shares.is_zero() {
+            return Ok(I80F48::ZERO);
+        }
+
+        let staker_fees = insurance_fees
+            .checked_mul(self.insurance_stake_fee_share.into())
+            .ok_or_else(math_error!())?;
+        let new_share_value = I80F48::from(self.insurance_stake_share_value)
+            .checked_add(
+                staker_fees
+                    .checked_div(total_shares)
+                    .ok_or_else(math_error!())?,
+            )
+            .ok_or_else(math_error!())?;
+        self.insurance_stake_share_value = new_share_value.into();
+
+        Ok(staker_fees)
+    }
+
+    /// Stakers take the first loss of bad debt covered by the insurance fund. Returns the part of
+    /// `covered_by_insurance` taken from stakers, the rest comes from the protocol's part.
+    pub fn slash_insurance_stake(
+        &mut self,
+        covered_by_insurance: I80F48,
+    ) -> SurroundfiResult<I80F48> {
+        let total_shares: I80F48 = self.insurance_stake_shares.into();
+        if total_shares.is_zero() {
+            return Ok(I80F48::ZERO);
+        }
+
+        let staked_amount = self.get_insurance_staked_amount()?;
+        let slashed = covered_by_insurance.min(staked_amount);
+        let new_share_value = staked_amount
+            .checked_sub(slashed)
+            .ok_or_else(math_error!())?
+            .checked_div(total_shares)
+            .ok_or_else(math_error!())?;
+        self.insurance_stake_share_value = new_share_value.into();
+
+        debug!(
+            "Insurance stakers slashed {} of {} staked",
+            slashed, staked_amount
+        );
+
+        Ok(slashed)
+    }
+
     pub fn assert_operational_mode(
         &self,
         is_asset_or_liability_amount_increasing: Option<bool>,
@@ -1011,6 +1679,226 @@ impl Bank {
         (self.flags & flag) == flag
     }
 
+    /// Validate the oracle setup, see `BankConfig::validate_oracle_setup`. For
+    /// `OracleSetup::ReceiptToken`, the underlying bank must also issue this bank's mint as its
+    /// receipt token.
+    pub fn validate_oracle_setup(
+        &self,
+        ais: &[AccountInfo],
+        lst_mint: Option<Pubkey>,
+        stake_pool: Option<Pubkey>,
+        sol_pool: Option<Pubkey>,
+    ) -> SurroundfiResult {
+        self.config
+            .validate_oracle_setup(ais, lst_mint, stake_pool, sol_pool)?;
+        self.validate_receipt_mint(ais)?;
+
+        Ok(())
+    }
+
+    /// For `OracleSetup::ReceiptToken`, check that the underlying bank (`oracle_keys[0]`, found
+    /// in `ais`) issues this bank's mint as its receipt token. No-op for other oracle setups.
+    pub fn validate_receipt_mint(&self, ais: &[AccountInfo]) -> SurroundfiResult {
+        if self.config.oracle_setup != OracleSetup::ReceiptToken {
+            return Ok(());
+        }
+
+        let underlying_ai = ais
+            .iter()
+            .find(|ai| ai.key == &self.config.oracle_keys[0])
+            .ok_or(SurroundfiError::WrongOracleAccountKeys)?;
+        let underlying_bank = ReceiptTokenPriceFeed::load_underlying_bank(underlying_ai)?;
+        check!(
+            underlying_bank.receipt_mint == self.mint,
+            SurroundfiError::WrongOracleAccountKeys
+        );
+
+        Ok(())
+    }
+
+    /// Change the asset shares backing receipt tokens, along with `total_asset_shares`.
+    pub fn change_receipt_asset_shares(
+        &mut self,
+        shares: I80F48,
+        bypass_deposit_limit: bool,
+    ) -> SurroundfiResult {
+        let receipt_asset_shares: I80F48 = self.receipt_asset_shares.into();
+        self.receipt_asset_shares = receipt_asset_shares
+            .checked_add(shares)
+            .ok_or_else(math_error!())?
+            .into();
+
+        self.change_asset_shares(shares, bypass_deposit_limit)
+    }
+
+    /// Asset shares that earn emissions: all of them but those backing receipt tokens, which
+    /// have no balance to claim emissions to.
+    pub fn get_emissions_asset_shares(&self) -> SurroundfiResult<I80F48> {
+        let total_asset_shares: I80F48 = self.total_asset_shares.into();
+        let emissions_asset_shares = total_asset_shares
+            .checked_sub(self.receipt_asset_shares.into())
+            .ok_or_else(math_error!())?;
+
+        Ok(emissions_asset_shares.max(I80F48::ZERO))
+    }
+
+    /// The bank's mint accrues interest on its UI amount, so the risk engine and liquidation need
+    /// the mint in remaining accounts to price its balances.
+    pub fn is_interest_bearing(&self) -> bool {
+        self.mint_extensions & MINT_EXTENSION_INTEREST_BEARING != 0
+    }
+
+    /// Accrue emissions since the last update to the per-share indexes of the active sides. Only
+    /// the time within the emissions schedule (`emissions_start_time`, `emissions_end_time`)
+    /// counts.
+    ///
+    /// Each whole share earns `emissions_rate` per year on its value, as with the legacy
+    /// per-balance accrual. If this would exceed `emissions_remaining`, the remaining emissions
+    /// are instead split pro rata between all shares of both sides. Emissions are set aside here
+    /// for every share of the bank, balances collect their part in
+    /// `BankAccountWrapper::claim_emissions`. Asset shares backing receipt tokens don't earn
+    /// emissions, see `get_emissions_asset_shares`.
+    pub fn update_emissions_index(&mut self, current_timestamp: i64) -> SurroundfiResult {
+        if self.emissions_index_last_update == 0 {
+            self.emissions_index_start = current_timestamp;
+            self.emissions_index_last_update = current_timestamp;
+            return Ok(());
+        }
+
+        let last_update = self.emissions_index_last_update;
+        self.emissions_index_last_update = current_timestamp;
+        if current_timestamp < last_update {
+            // The clock can only move back in tests, restart from there
+            self.emissions_index_start = self.emissions_index_start.min(current_timestamp);
+            return Ok(());
+        }
+
+        let period = self.get_emissions_period(last_update, current_timestamp);
+        if period <= 0 {
+            return Ok(());
+        }
+
+        let lending_active = self.get_flag(EMISSIONS_FLAG_LENDING_ACTIVE);
+        let borrow_active = self.get_flag(EMISSIONS_FLAG_BORROW_ACTIVE);
+        if self.emissions_rate == 0 || !(lending_active || borrow_active) {
+            return Ok(());
+        }
+
+        // Emissions per whole share of value 1 over the period
+        let emissions_per_share = I80F48::from_num(self.emissions_rate)
+            .checked_mul(I80F48::from_num(period))
+            .ok_or_else(math_error!())?
+            .checked_div(SECONDS_PER_YEAR)
+            .ok_or_else(math_error!())?;
+        let exponent = EXP_10_I80F48[self.mint_decimals as usize];
+
+        let (mut asset_index_increase, mut asset_emissions) = if lending_active {
+            let increase = emissions_per_share
+                .checked_mul(self.asset_share_value.into())
+                .ok_or_else(math_error!())?;
+            let emissions = increase
+                .checked_mul(self.get_emissions_asset_shares()?)
+                .ok_or_else(math_error!())?
+                .checked_div(exponent)
+                .ok_or_else(math_error!())?;
+            (increase, emissions)
+        } else {
+            (I80F48::ZERO, I80F48::ZERO)
+        };
+        let (mut liability_index_increase, mut liability_emissions) = if borrow_active {
+            let increase = emissions_per_share
+                .checked_mul(self.liability_share_value.into())
+                .ok_or_else(math_error!())?;
+            let emissions = increase
+                .checked_mul(self.total_liability_shares.into())
+                .ok_or_else(math_error!())?
+                .checked_div(exponent)
+                .ok_or_else(math_error!())?;
+            (increase, emissions)
+        } else {
+            (I80F48::ZERO, I80F48::ZERO)
+        };
+
+        let total_emissions = asset_emissions
+            .checked_add(liability_emissions)
+            .ok_or_else(math_error!())?;
+        let emissions_remaining: I80F48 = self.emissions_remaining.into();
+
+        if total_emissions > emissions_remaining {
+            let ratio = emissions_remaining
+                .checked_div(total_emissions)
+                .ok_or_else(math_error!())?;
+            msg!(
+                "Emissions capped: {} ({} calculated) for period {}s",
+                emissions_remaining,
+                total_emissions,
+                period
+            );
+
+            for value in [
+                &mut asset_index_increase,
+                &mut asset_emissions,
+                &mut liability_index_increase,
+                &mut liability_emissions,
+            ] {
+                *value = value.checked_mul(ratio).ok_or_else(math_error!())?;
+            }
+        }
+
+        self.emissions_asset_index = I80F48::from(self.emissions_asset_index)
+            .checked_add(asset_index_increase)
+            .ok_or_else(math_error!())?
+            .into();
+        self.emissions_liability_index = I80F48::from(self.emissions_liability_index)
+            .checked_add(liability_index_increase)
+            .ok_or_else(math_error!())?
+            .into();
+        self.emissions_remaining = emissions_remaining
+            .checked_sub(asset_emissions)
+            .ok_or_else(math_error!())?
+            .checked_sub(liability_emissions)
+            .ok_or_else(math_error!())?
+            .max(I80F48::ZERO)
+            .into();
+
+        Ok(())
+    }
+
+    /// Seconds between `from` and `to` during which emissions are scheduled to accrue.
+    pub fn get_emissions_period(&self, from: i64, to: i64) -> i64 {
+        let from = from.max(self.emissions_start_time);
+        let to = if self.emissions_end_time == 0 {
+            to
+        } else {
+            to.min(self.emissions_end_time)
+        };
+
+        to.saturating_sub(from).max(0)
+    }
+
+    pub fn set_emissions_schedule(&mut self, start_time: i64, end_time: i64) -> SurroundfiResult {
+        check!(
+            end_time == 0 || end_time > start_time,
+            SurroundfiError::InvalidEmissionsSchedule
+        );
+
+        self.emissions_start_time = start_time;
+        self.emissions_end_time = end_time;
+
+        Ok(())
+    }
+
+    pub fn get_emissions_index(&self, side: BalanceSide) -> I80F48 {
+        match side {
+            BalanceSide::Assets => self.emissions_asset_index.into(),
+            BalanceSide::Liabilities => self.emissions_liability_index.into(),
+        }
+    }
+
+    pub fn has_receipt_mint(&self) -> bool {
+        self.receipt_mint != Pubkey::default()
+    }
+
     pub(crate) fn override_emissions_flag(&mut self, flag: u64) {
         assert!(Self::verify_emissions_flags(flag));
         self.flags = flag;
@@ -1231,6 +2119,10 @@ pub struct BankConfigCompact {
     /// `ASSET_TAG_DEFAULT` or `ASSET_TAG_STAKED` positions, but not both
     /// * ASSET_TAG_STAKED (2) - Staked SOL assets. Accounts with a STAKED position can only deposit
     /// other STAKED assets or SOL (`ASSET_TAG_SOL`) and can only borrow SOL
+    /// * ASSET_TAG_RECEIPT (3) - Receipt tokens of another bank, priced with
+    ///   `OracleSetup::ReceiptToken`. Comingles like `ASSET_TAG_DEFAULT`
+    /// * ASSET_TAG_STABLE (4) - Stablecoins. Comingles like `ASSET_TAG_DEFAULT`, and can be
+    ///   borrowed against STAKED assets if the group enables `STAKED_STABLE_BORROW_ENABLED`
     pub asset_tag: u8,
 
     pub _pad0: [u8; 6],
@@ -1295,7 +2187,8 @@ impl From<BankConfigCompact> for BankConfig {
             _pad1: [0; 6],
             total_asset_value_init_limit: config.total_asset_value_init_limit,
             oracle_max_age: config.oracle_max_age,
-            _padding0: [0; 6],
+            staked_settings_overrides: 0,
+            _padding0: [0; 5],
             _padding1: [0; 32],
         }
     }
@@ -1358,6 +2251,10 @@ pub struct BankConfig {
     /// `ASSET_TAG_DEFAULT` or `ASSET_TAG_STAKED` positions, but not both
     /// * ASSET_TAG_STAKED (2) - Staked SOL assets. Accounts with a STAKED position can only deposit
     /// other STAKED assets or SOL (`ASSET_TAG_SOL`) and can only borrow SOL
+    /// * ASSET_TAG_RECEIPT (3) - Receipt tokens of another bank, priced with
+    ///   `OracleSetup::ReceiptToken`. Comingles like `ASSET_TAG_DEFAULT`
+    /// * ASSET_TAG_STABLE (4) - Stablecoins. Comingles like `ASSET_TAG_DEFAULT`, and can be
+    ///   borrowed against STAKED assets if the group enables `STAKED_STABLE_BORROW_ENABLED`
     pub asset_tag: u8,
 
     pub _pad1: [u8; 6],
@@ -1375,8 +2272,21 @@ pub struct BankConfig {
     /// Time window in seconds for the oracle price feed to be considered live.
     pub oracle_max_age: u16,
 
-    // Note: 6 bytes of padding to next 8 byte alignment, then end padding
-    pub _padding0: [u8; 6],
+    /// `ASSET_TAG_STAKED` banks only: bitmask of the fields the admin configured on this bank,
+    /// which `propagate_staked_settings` no longer overwrites with the group's `StakedSettings`.
+    /// * `STAKED_OVERRIDE_ORACLE` (1)
+    /// * `STAKED_OVERRIDE_ASSET_WEIGHTS` (2)
+    /// * `STAKED_OVERRIDE_LIABILITY_WEIGHTS` (4)
+    /// * `STAKED_OVERRIDE_DEPOSIT_LIMIT` (8)
+    /// * `STAKED_OVERRIDE_TOTAL_ASSET_VALUE_INIT_LIMIT` (16)
+    /// * `STAKED_OVERRIDE_ORACLE_MAX_AGE` (32)
+    /// * `STAKED_OVERRIDE_RISK_TIER` (64)
+    ///
+    /// Cleared with `lending_pool_clear_staked_settings_overrides`.
+    pub staked_settings_overrides: u8,
+
+    // Note: 5 bytes of padding to next 8 byte alignment, then end padding
+    pub _padding0: [u8; 5],
     pub _padding1: [u8; 32],
 }
 
@@ -1399,7 +2309,8 @@ impl Default for BankConfig {
             _pad1: [0; 6],
             total_asset_value_init_limit: TOTAL_ASSET_VALUE_INIT_LIMIT_INACTIVE,
             oracle_max_age: 0,
-            _padding0: [0; 6],
+            staked_settings_overrides: 0,
+            _padding0: [0; 5],
             _padding1: [0; 32],
         }
     }
@@ -1440,6 +2351,19 @@ impl BankConfig {
         }
     }
 
+    /// True if `propagate_staked_settings` must leave the field(s) of `override_flag` as-is
+    pub fn is_staked_settings_override(&self, override_flag: u8) -> bool {
+        self.staked_settings_overrides & override_flag != 0
+    }
+
+    /// Record that the admin configured these fields, only relevant for `ASSET_TAG_STAKED` banks
+    pub fn mark_staked_settings_overrides(&mut self, overrides: u8) {
+        if self.asset_tag == ASSET_TAG_STAKED && overrides != 0 {
+            msg!("marking staked settings overrides: {:#09b}", overrides);
+            self.staked_settings_overrides |= overrides;
+        }
+    }
+
     pub fn validate(&self) -> SurroundfiResult {
         let asset_init_w = I80F48::from(self.asset_weight_init);
         let asset_maint_w = I80F48::from(self.asset_weight_maint);
@@ -1466,6 +2390,27 @@ impl BankConfig {
             check!(asset_maint_w == I80F48::ZERO, SurroundfiError::InvalidConfig);
         }
 
+        check!(
+            (self.asset_tag as usize) < MAX_ASSET_TAGS,
+            SurroundfiError::UnsupportedAssetTag
+        );
+
+        if self.oracle_setup != OracleSetup::None {
+            self.validate_receipt_asset_tag()?;
+        }
+
+        Ok(())
+    }
+
+    /// `ASSET_TAG_RECEIPT` determines how many oracle accounts a balance in this bank passes, so
+    /// it must be used if and only if the bank is priced by `OracleSetup::ReceiptToken`
+    fn validate_receipt_asset_tag(&self) -> SurroundfiResult {
+        check!(
+            (self.asset_tag == ASSET_TAG_RECEIPT)
+                == (self.oracle_setup == OracleSetup::ReceiptToken),
+            SurroundfiError::InvalidOracleSetup
+        );
+
         Ok(())
     }
 
@@ -1489,6 +2434,7 @@ impl BankConfig {
         stake_pool: Option<Pubkey>,
         sol_pool: Option<Pubkey>,
     ) -> SurroundfiResult {
+        self.validate_receipt_asset_tag()?;
         OraclePriceFeedAdapter::validate_bank_config(self, ais, lst_mint, stake_pool, sol_pool)?;
         Ok(())
     }
@@ -1590,6 +2536,35 @@ pub struct BankConfigOpt {
     pub freeze_settings: Option<bool>,
 }
 
+impl BankConfigOpt {
+    /// The `STAKED_OVERRIDE_*` flags of the fields this config sets
+    pub fn get_staked_settings_overrides(&self) -> u8 {
+        [
+            (
+                self.asset_weight_init.is_some() || self.asset_weight_maint.is_some(),
+                STAKED_OVERRIDE_ASSET_WEIGHTS,
+            ),
+            (
+                self.liability_weight_init.is_some() || self.liability_weight_maint.is_some(),
+                STAKED_OVERRIDE_LIABILITY_WEIGHTS,
+            ),
+            (self.deposit_limit.is_some(), STAKED_OVERRIDE_DEPOSIT_LIMIT),
+            (
+                self.total_asset_value_init_limit.is_some(),
+                STAKED_OVERRIDE_TOTAL_ASSET_VALUE_INIT_LIMIT,
+            ),
+            (
+                self.oracle_max_age.is_some(),
+                STAKED_OVERRIDE_ORACLE_MAX_AGE,
+            ),
+            (self.risk_tier.is_some(), STAKED_OVERRIDE_RISK_TIER),
+        ]
+        .into_iter()
+        .filter(|(is_set, _)| *is_set)
+        .fold(0, |overrides, (_, flag)| overrides | flag)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum BankVaultType {
     Liquidity,
@@ -2046,4 +3021,215 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn insurance_stake_fees_and_slashing() {
+        let mut bank = Bank {
+            insurance_stake_fee_share: I80F48!(0.5).into(),
+            ..Default::default()
+        };
+
+        assert!(bank.insurance_stake(I80F48!(100)).is_err());
+        bank.insurance_stake_cooldown = 60;
+
+        assert_eq!(bank.insurance_stake(I80F48!(100)).unwrap(), I80F48!(100));
+
+        // Half of the insurance fees go to stakers
+        assert_eq!(
+            bank.credit_insurance_stake_fees(I80F48!(20)).unwrap(),
+            I80F48!(10)
+        );
+        assert_eq!(I80F48::from(bank.insurance_stake_share_value), I80F48!(1.1));
+        assert_eq!(bank.insurance_stake(I80F48!(55)).unwrap(), I80F48!(50));
+        assert_eq!(bank.get_insurance_staked_amount().unwrap(), I80F48!(165));
+
+        // Stakers take the first loss, up to what they staked
+        assert_eq!(
+            bank.slash_insurance_stake(I80F48!(15)).unwrap(),
+            I80F48!(15)
+        );
+        assert_eq!(I80F48::from(bank.insurance_stake_share_value), I80F48!(1));
+        assert_eq!(bank.insurance_unstake(I80F48!(50)).unwrap(), 50);
+        assert_eq!(
+            bank.slash_insurance_stake(I80F48!(500)).unwrap(),
+            I80F48!(100)
+        );
+        assert!(bank.insurance_stake(I80F48!(10)).is_err());
+
+        // Once wiped out stakers leave, staking starts over
+        assert_eq!(bank.insurance_unstake(I80F48!(100)).unwrap(), 0);
+        assert_eq!(bank.insurance_stake(I80F48!(10)).unwrap(), I80F48!(10));
+    }
+
+    #[test]
+    fn insurance_unstake_gains_forfeited() {
+        let mut bank = Bank {
+            insurance_stake_fee_share: I80F48!(1).into(),
+            insurance_stake_cooldown: 60,
+            ..Default::default()
+        };
+        bank.insurance_stake(I80F48!(100)).unwrap();
+        bank.insurance_stake(I80F48!(100)).unwrap();
+
+        // 100 shares requested to unstake when worth 100, then fees raise their value to 150
+        bank.credit_insurance_stake_fees(I80F48!(100)).unwrap();
+        let burnt = bank
+            .forfeit_insurance_unstake_gains(I80F48!(100), I80F48!(100))
+            .unwrap();
+        assert_eq!(burnt, I80F48!(50));
+        assert_eq!(I80F48::from(bank.insurance_stake_share_value), I80F48!(2));
+        assert_eq!(bank.insurance_unstake(I80F48!(100) - burnt).unwrap(), 100);
+        assert_eq!(bank.get_insurance_staked_amount().unwrap(), I80F48!(200));
+
+        // Slashed shares are worth less than when requested, nothing to forfeit
+        bank.slash_insurance_stake(I80F48!(100)).unwrap();
+        assert_eq!(
+            bank.forfeit_insurance_unstake_gains(I80F48!(50), I80F48!(150))
+                .unwrap(),
+            I80F48::ZERO
+        );
+
+        // Without other stakers, the gains go to the protocol
+        bank.credit_insurance_stake_fees(I80F48!(100)).unwrap();
+        assert_eq!(
+            bank.forfeit_insurance_unstake_gains(I80F48!(100), I80F48!(150))
+                .unwrap(),
+            I80F48::ZERO
+        );
+        assert_eq!(bank.get_insurance_staked_amount().unwrap(), I80F48!(150));
+    }
+
+    #[test]
+    fn insurance_stake_pending_fees_credited_once() {
+        let mut bank = Bank {
+            insurance_stake_fee_share: I80F48!(0.5).into(),
+            insurance_stake_cooldown: 60,
+            collected_insurance_fees_outstanding: I80F48!(40).into(),
+            ..Default::default()
+        };
+
+        // Fees accrued without stakers go to the protocol
+        assert_eq!(
+            bank.credit_pending_insurance_stake_fees().unwrap(),
+            I80F48::ZERO
+        );
+        assert_eq!(bank.insurance_stake(I80F48!(100)).unwrap(), I80F48!(100));
+        assert_eq!(
+            bank.credit_pending_insurance_stake_fees().unwrap(),
+            I80F48::ZERO
+        );
+        assert_eq!(I80F48::from(bank.insurance_stake_share_value), I80F48!(1));
+
+        // Fees accrued since are credited to the shares outstanding, only once
+        bank.collected_insurance_fees_outstanding = I80F48!(60).into();
+        assert_eq!(
+            bank.credit_pending_insurance_stake_fees().unwrap(),
+            I80F48!(10)
+        );
+        assert_eq!(
+            bank.credit_pending_insurance_stake_fees().unwrap(),
+            I80F48::ZERO
+        );
+        assert_eq!(I80F48::from(bank.insurance_stake_share_value), I80F48!(1.1));
+
+        // Later shares are issued at the credited value
+        assert_eq!(bank.insurance_stake(I80F48!(110)).unwrap(), I80F48!(100));
+        assert_eq!(bank.get_insurance_staked_amount().unwrap(), I80F48!(220));
+    }
+
+    #[test]
+    fn default_asset_tag_matrix() {
+        let matrix = AssetTagMatrix::DEFAULT;
+        let tags = [
+            ASSET_TAG_DEFAULT,
+            ASSET_TAG_SOL,
+            ASSET_TAG_STAKED,
+            ASSET_TAG_RECEIPT,
+            ASSET_TAG_STABLE,
+        ];
+        let is_default_like = |tag| {
+            tag == ASSET_TAG_DEFAULT || tag == ASSET_TAG_RECEIPT || tag == ASSET_TAG_STABLE
+        };
+
+        for tag_a in tags {
+            for tag_b in tags {
+                let forbidden = (is_default_like(tag_a) && tag_b == ASSET_TAG_STAKED)
+                    || (tag_a == ASSET_TAG_STAKED && is_default_like(tag_b));
+                assert_eq!(matrix.can_coexist(tag_a, tag_b).unwrap(), !forbidden);
+                assert_eq!(matrix.can_liquidate(tag_a, tag_b).unwrap(), !forbidden);
+            }
+        }
+
+        // Tags without built-in rules can't be used, tags out of range are errors
+        assert!(!matrix.can_coexist(5, 5).unwrap());
+        assert!(matrix
+            .can_coexist(ASSET_TAG_DEFAULT, MAX_ASSET_TAGS as u8)
+            .is_err());
+        assert!(matrix.validate().is_ok());
+
+        let mut asymmetric = matrix;
+        asymmetric.coexist[5] = 1 << ASSET_TAG_DEFAULT;
+        assert!(asymmetric.validate().is_err());
+
+        // Built-in tags must mix with themselves
+        for tag in tags {
+            let mut no_coexist = matrix;
+            no_coexist.coexist[tag as usize] &= !(1 << tag);
+            assert!(no_coexist.validate().is_err());
+
+            let mut no_liquidation = matrix;
+            no_liquidation.liquidation[tag as usize] &= !(1 << tag);
+            assert!(no_liquidation.validate().is_err());
+        }
+    }
+
+    #[test]
+    fn staked_stable_borrow_asset_tag_matrix() {
+        let mut group = SurroundfiGroup::default();
+        assert_eq!(group.get_asset_tag_matrix(), AssetTagMatrix::DEFAULT);
+
+        group.set_staked_stable_borrow_enabled(true);
+        let matrix = group.get_asset_tag_matrix();
+        assert!(matrix.validate().is_ok());
+        assert!(matrix.can_coexist(ASSET_TAG_STAKED, ASSET_TAG_STABLE).unwrap());
+        assert!(matrix.can_liquidate(ASSET_TAG_STAKED, ASSET_TAG_STABLE).unwrap());
+        // Stable collateral still can't back a borrow of staked assets
+        assert!(!matrix.can_liquidate(ASSET_TAG_STABLE, ASSET_TAG_STAKED).unwrap());
+        assert!(!matrix.can_coexist(ASSET_TAG_STAKED, ASSET_TAG_DEFAULT).unwrap());
+
+        // Program fees don't depend on the flag
+        group.set_program_fee_enabled(true);
+        assert!(group.get_group_bank_config().program_fees);
+
+        group.set_staked_stable_borrow_enabled(false);
+        assert_eq!(group.get_asset_tag_matrix(), AssetTagMatrix::DEFAULT);
+    }
+
+    #[test]
+    fn staked_settings_overrides() {
+        let config_opt = BankConfigOpt {
+            asset_weight_maint: Some(I80F48!(0.8).into()),
+            deposit_limit: Some(1_000),
+            ..Default::default()
+        };
+        let overrides = config_opt.get_staked_settings_overrides();
+        assert_eq!(
+            overrides,
+            STAKED_OVERRIDE_ASSET_WEIGHTS | STAKED_OVERRIDE_DEPOSIT_LIMIT
+        );
+        assert_eq!(BankConfigOpt::default().get_staked_settings_overrides(), 0);
+
+        // Only staked banks track overrides
+        let mut config = BankConfig::default();
+        config.mark_staked_settings_overrides(overrides);
+        assert_eq!(config.staked_settings_overrides, 0);
+
+        config.asset_tag = ASSET_TAG_STAKED;
+        config.mark_staked_settings_overrides(overrides);
+        config.mark_staked_settings_overrides(STAKED_OVERRIDE_RISK_TIER);
+        assert!(config.is_staked_settings_override(STAKED_OVERRIDE_ASSET_WEIGHTS));
+        assert!(config.is_staked_settings_override(STAKED_OVERRIDE_DEPOSIT_LIMIT));
+        assert!(config.is_staked_settings_override(STAKED_OVERRIDE_RISK_TIER));
+        assert!(!config.is_staked_settings_override(STAKED_OVERRIDE_LIABILITY_WEIGHTS));
+    }
 }
@@ -1,9 +1,19 @@
 use crate::{
-    bank_authority_seed, bank_seed,
-    constants::{ASSET_TAG_DEFAULT, ASSET_TAG_SOL, ASSET_TAG_STAKED},
+    bank_authority_seed, bank_seed, check, check_eq,
+    constants::{
+        ASSET_TAG_STAKED, INTEREST_BEARING_SECONDS_PER_YEAR, MINT_EXTENSION_CONFIDENTIAL_TRANSFER,
+        MINT_EXTENSION_DEFAULT_FROZEN, MINT_EXTENSION_FREEZE_AUTHORITY,
+        MINT_EXTENSION_INTEREST_BEARING, MINT_EXTENSION_METADATA,
+        MINT_EXTENSION_MINT_CLOSE_AUTHORITY, MINT_EXTENSION_NON_TRANSFERABLE,
+        MINT_EXTENSION_PAUSABLE, MINT_EXTENSION_PERMANENT_DELEGATE, MINT_EXTENSION_TRANSFER_FEE,
+        MINT_EXTENSION_TRANSFER_HOOK, MINT_EXTENSION_UNKNOWN, PAUSABLE_CONFIG_EXTENSION_TYPE,
+        SPL_SINGLE_POOL_ID,
+    },
+    math_error,
     state::{
         surroundfi_account::SurroundfiAccount,
-        surroundfi_group::{Bank, BankVaultType},
+        surroundfi_account_extension::SurroundfiAccountExtension,
+        surroundfi_group::{AssetTagMatrix, Bank, BankVaultType},
     },
     SurroundfiError, SurroundfiResult,
 };
@@ -13,13 +23,17 @@ use anchor_spl::{
     token_2022::spl_token_2022::{
         self,
         extension::{
+            default_account_state::DefaultAccountState,
+            interest_bearing_mint::InterestBearingConfig,
             transfer_fee::{TransferFee, TransferFeeConfig},
-            BaseStateWithExtensions, StateWithExtensions,
+            transfer_hook, BaseStateWithExtensions, ExtensionType, StateWithExtensions,
         },
+        state::AccountState,
     },
     token_interface::Mint,
 };
 use fixed::types::I80F48;
+use spl_transfer_hook_interface::get_extra_account_metas_address;
 
 pub fn find_bank_vault_pda(bank_pk: &Pubkey, vault_type: BankVaultType) -> (Pubkey, u8) {
     Pubkey::find_program_address(bank_seed!(vault_type, bank_pk), &crate::id())
@@ -147,6 +161,203 @@ pub fn maybe_take_bank_mint<'info>(
     }
 }
 
+/// Flags (`MINT_EXTENSION_*`) of the Token-2022 extensions of a mint, and of its freeze authority.
+/// Only the latter can be set for Tokenkeg mints.
+pub fn get_mint_extensions(mint_data: &[u8]) -> SurroundfiResult<u64> {
+    let mint = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(mint_data)?;
+
+    let mut mint_extensions = 0;
+    if mint.base.freeze_authority.is_some() {
+        mint_extensions |= MINT_EXTENSION_FREEZE_AUTHORITY;
+    }
+
+    // The TLV entries are walked by hand: `get_extension_types` fails on extension types newer
+    // than this program's `spl-token-2022`, which must be flagged rather than rejected
+    let mut tlv_data = mint.get_tlv_data();
+    while let [type_lo, type_hi, length_lo, length_hi, rest @ ..] = tlv_data {
+        let extension_type = u16::from_le_bytes([*type_lo, *type_hi]);
+        let length = u16::from_le_bytes([*length_lo, *length_hi]) as usize;
+
+        mint_extensions |= match ExtensionType::try_from(extension_type) {
+            Ok(ExtensionType::Uninitialized) => break,
+            Ok(ExtensionType::TransferFeeConfig) => MINT_EXTENSION_TRANSFER_FEE,
+            Ok(ExtensionType::TransferHook) => MINT_EXTENSION_TRANSFER_HOOK,
+            Ok(ExtensionType::InterestBearingConfig) => MINT_EXTENSION_INTEREST_BEARING,
+            Ok(ExtensionType::MintCloseAuthority) => MINT_EXTENSION_MINT_CLOSE_AUTHORITY,
+            Ok(ExtensionType::MetadataPointer)
+            | Ok(ExtensionType::TokenMetadata)
+            | Ok(ExtensionType::GroupPointer)
+            | Ok(ExtensionType::TokenGroup)
+            | Ok(ExtensionType::GroupMemberPointer)
+            | Ok(ExtensionType::TokenGroupMember) => MINT_EXTENSION_METADATA,
+            Ok(ExtensionType::PermanentDelegate) => MINT_EXTENSION_PERMANENT_DELEGATE,
+            // Only a frozen default state can lock up the bank's vaults
+            Ok(ExtensionType::DefaultAccountState) => {
+                let default_account_state = mint.get_extension::<DefaultAccountState>()?;
+                if default_account_state.state == AccountState::Frozen as u8 {
+                    MINT_EXTENSION_DEFAULT_FROZEN
+                } else {
+                    0
+                }
+            }
+            Ok(ExtensionType::NonTransferable) => MINT_EXTENSION_NON_TRANSFERABLE,
+            Ok(ExtensionType::ConfidentialTransferMint)
+            | Ok(ExtensionType::ConfidentialTransferFeeConfig) => {
+                MINT_EXTENSION_CONFIDENTIAL_TRANSFER
+            }
+            Err(_) if extension_type == PAUSABLE_CONFIG_EXTENSION_TYPE => MINT_EXTENSION_PAUSABLE,
+            _ => MINT_EXTENSION_UNKNOWN,
+        };
+
+        tlv_data = rest.get(length..).ok_or(ProgramError::InvalidAccountData)?;
+    }
+
+    Ok(mint_extensions)
+}
+
+/// Factor that converts raw amounts of an interest-bearing mint to (unscaled by decimals) UI
+/// amounts at `unix_timestamp`, i.e. the rate accrued by the mint, compounded continuously as in
+/// `InterestBearingConfig::amount_to_ui_amount`. Expects the bank's mint.
+pub fn get_interest_bearing_scale(
+    mint_ai: &AccountInfo,
+    bank: &Bank,
+    unix_timestamp: i64,
+) -> SurroundfiResult<I80F48> {
+    check!(
+        mint_ai.key.eq(&bank.mint),
+        SurroundfiError::InterestBearingMintRequired
+    );
+
+    let mint_data = mint_ai.try_borrow_data()?;
+    let mint = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&mint_data)
+        .map_err(|_| SurroundfiError::InterestBearingMintRequired)?;
+    let config = mint
+        .get_extension::<InterestBearingConfig>()
+        .map_err(|_| SurroundfiError::InterestBearingMintRequired)?;
+
+    let initialization_timestamp = i64::from(config.initialization_timestamp);
+    let last_update_timestamp = i64::from(config.last_update_timestamp);
+    let pre_update_rate = i16::from(config.pre_update_average_rate) as f64 / 10_000.;
+    let current_rate = i16::from(config.current_rate) as f64 / 10_000.;
+
+    let pre_update_timespan = last_update_timestamp.saturating_sub(initialization_timestamp);
+    let post_update_timespan = unix_timestamp.saturating_sub(last_update_timestamp);
+
+    let exponent = (pre_update_rate * pre_update_timespan as f64
+        + current_rate * post_update_timespan as f64)
+        / INTEREST_BEARING_SECONDS_PER_YEAR;
+
+    I80F48::checked_from_num(exponent.exp()).ok_or_else(math_error!())
+}
+
+/// If `interest_bearing`, splits the bank's mint off the end of the bank's oracle accounts, and
+/// returns the remaining oracle accounts with the mint's `get_interest_bearing_scale`. Otherwise,
+/// returns the oracle accounts as is with a scale of one.
+///
+/// `interest_bearing` is the bank's flag where the bank's accounts are laid out per bank, and the
+/// balance's (`Balance::bank_interest_bearing`) where they are laid out per balance, as balances
+/// created before `lending_pool_refresh_mint_extensions` don't pass the mint.
+pub fn split_interest_bearing_mint<'info>(
+    bank: &Bank,
+    interest_bearing: bool,
+    ais: &'info [AccountInfo<'info>],
+    unix_timestamp: i64,
+) -> SurroundfiResult<(&'info [AccountInfo<'info>], I80F48)> {
+    if !interest_bearing {
+        return Ok((ais, I80F48::ONE));
+    }
+
+    let (mint_ai, oracle_ais) = ais
+        .split_last()
+        .ok_or(SurroundfiError::InterestBearingMintRequired)?;
+    let ui_amount_scale = get_interest_bearing_scale(mint_ai, bank, unix_timestamp)?;
+
+    Ok((oracle_ais, ui_amount_scale))
+}
+
+/// A Token-2022 mint with a transfer hook can only back a bank if its hook program is executable,
+/// isn't this program, and has initialized the extra account metas of the mint, otherwise the
+/// bank's transfers can't be resolved. Expects the hook program and its validation account in
+/// `remaining_accounts`.
+///
+/// Ok(()) if Tokenkeg or no hook is set
+pub fn validate_transfer_hook(
+    mint_ai: &AccountInfo,
+    remaining_accounts: &[AccountInfo],
+) -> SurroundfiResult {
+    if mint_ai.owner.eq(&Token::id()) {
+        return Ok(());
+    }
+
+    let mint_data = mint_ai.try_borrow_data()?;
+    let mint = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&mint_data)?;
+    let hook_program_id = match transfer_hook::get_program_id(&mint) {
+        Some(hook_program_id) => hook_program_id,
+        None => return Ok(()),
+    };
+
+    check!(
+        hook_program_id != crate::ID,
+        SurroundfiError::InvalidTransferHook,
+        "Transfer hook can't call back into this program"
+    );
+
+    let find_account = |key: &Pubkey| {
+        remaining_accounts
+            .iter()
+            .find(|ai| ai.key.eq(key))
+            .ok_or(SurroundfiError::InvalidTransferHook)
+    };
+
+    let hook_program = find_account(&hook_program_id)?;
+    check!(
+        hook_program.executable,
+        SurroundfiError::InvalidTransferHook,
+        "Transfer hook program is not executable"
+    );
+
+    let validation_ai = find_account(&get_extra_account_metas_address(
+        mint_ai.key,
+        &hook_program_id,
+    ))?;
+    check!(
+        validation_ai.owner.eq(&hook_program_id) && !validation_ai.data_is_empty(),
+        SurroundfiError::InvalidTransferHook,
+        "Transfer hook extra account metas are not initialized"
+    );
+
+    Ok(())
+}
+
+/// `transfer_checked` that resolves the extra accounts of a Token-2022 transfer hook mint from
+/// `remaining_accounts`, for transfers of mints that aren't a bank's (e.g. emissions). Plain
+/// transfers are unaffected.
+#[allow(clippy::too_many_arguments)]
+pub fn transfer_checked_with_hook<'info>(
+    amount: u64,
+    from: AccountInfo<'info>,
+    to: AccountInfo<'info>,
+    authority: AccountInfo<'info>,
+    mint: &InterfaceAccount<'info, Mint>,
+    program: AccountInfo<'info>,
+    signer_seeds: &[&[&[u8]]],
+    remaining_accounts: &[AccountInfo<'info>],
+) -> SurroundfiResult {
+    spl_token_2022::onchain::invoke_transfer_checked(
+        program.key,
+        from,
+        mint.to_account_info(),
+        to,
+        authority,
+        remaining_accounts,
+        amount,
+        mint.decimals,
+        signer_seeds,
+    )?;
+
+    Ok(())
+}
+
 const ONE_IN_BASIS_POINTS: u128 = 10_000;
 /// backported fix from
 /// https://github.com/solana-labs/solana-program-library/commit/20e6792179fc7f1251579c1c33a4a0feec48e15e
@@ -197,38 +408,44 @@ pub fn hex_to_bytes(hex: &str) -> Vec<u8> {
         .collect()
 }
 
-/// Validate that after a deposit to Bank, the users's account contains either all Default/SOL
-/// balances, or all Staked/Sol balances. Default and Staked assets cannot mix.
-pub fn validate_asset_tags(bank: &Bank, surroundfi_account: &SurroundfiAccount) -> SurroundfiResult {
-    let mut has_default_asset = false;
-    let mut has_staked_asset = false;
-
-    for balance in surroundfi_account.lending_account.balances.iter() {
+/// Validate that after a deposit to (or borrow from) Bank, the user's account only contains
+/// balances whose asset tags can coexist with the bank's tag under the group's `asset_tag_matrix`.
+/// With the default matrix, Default (and Receipt) and Staked assets cannot mix, SOL mixes with
+/// either.
+///
+/// The balances of the account's `extension` count too, it must be provided if the account has
+/// one. Banks with a tag that can't coexist with itself can't be used at all.
+pub fn validate_asset_tags(
+    bank: &Bank,
+    surroundfi_account: &SurroundfiAccount,
+    extension: Option<&SurroundfiAccountExtension>,
+    asset_tag_matrix: &AssetTagMatrix,
+) -> SurroundfiResult {
+    check!(
+        !surroundfi_account.has_extension() || extension.is_some(),
+        SurroundfiError::AccountExtensionMissing
+    );
+
+    let bank_asset_tag = bank.config.asset_tag;
+    check!(
+        asset_tag_matrix.can_coexist(bank_asset_tag, bank_asset_tag)?,
+        SurroundfiError::AssetTagMismatch
+    );
+
+    for balance in surroundfi_account.lending_account.balances_iter(extension) {
         if balance.is_active() {
-            match balance.bank_asset_tag {
-                ASSET_TAG_DEFAULT => has_default_asset = true,
-                ASSET_TAG_SOL => { /* Do nothing, SOL can mix with any asset type */ }
-                ASSET_TAG_STAKED => has_staked_asset = true,
-                _ => panic!("unsupported asset tag"),
-            }
+            check!(
+                asset_tag_matrix.can_coexist(bank_asset_tag, balance.bank_asset_tag)?,
+                SurroundfiError::AssetTagMismatch
+            );
         }
     }
 
-    // 1. Regular assets (DEFAULT) cannot mix with Staked assets
-    if bank.config.asset_tag == ASSET_TAG_DEFAULT && has_staked_asset {
-        return err!(SurroundfiError::AssetTagMismatch);
-    }
-
-    // 2. Staked SOL cannot mix with Regular asset (DEFAULT)
-    if bank.config.asset_tag == ASSET_TAG_STAKED && has_default_asset {
-        return err!(SurroundfiError::AssetTagMismatch);
-    }
-
     Ok(())
 }
 
-/// Validate that two banks are compatible based on their asset tags. See the following combinations
-/// (* is wildcard, e.g. any tag):
+/// Validate that a liability in `liab_bank` can be liquidated against collateral in `asset_bank`
+/// under the group's `asset_tag_matrix`. With the default matrix:
 ///
 /// Allowed:
 /// 1) Default/Default
@@ -238,20 +455,47 @@ pub fn validate_asset_tags(bank: &Bank, surroundfi_account: &SurroundfiAccount)
 /// Forbidden:
 /// 1) Default/Staked
 ///
-/// Returns an error if the two banks have mismatching asset tags according to the above.
-pub fn validate_bank_asset_tags(bank_a: &Bank, bank_b: &Bank) -> SurroundfiResult {
-    let is_bank_a_default = bank_a.config.asset_tag == ASSET_TAG_DEFAULT;
-    let is_bank_a_staked = bank_a.config.asset_tag == ASSET_TAG_STAKED;
-    let is_bank_b_default = bank_b.config.asset_tag == ASSET_TAG_DEFAULT;
-    let is_bank_b_staked = bank_b.config.asset_tag == ASSET_TAG_STAKED;
-    // Note: Sol is compatible with all other tags and doesn't matter...
-
-    // 1. Default assets cannot mix with Staked assets
-    if is_bank_a_default && is_bank_b_staked {
-        return err!(SurroundfiError::AssetTagMismatch);
-    }
-    if is_bank_a_staked && is_bank_b_default {
-        return err!(SurroundfiError::AssetTagMismatch);
+/// Receipt is treated as Default.
+///
+/// Returns an error if the two banks have mismatching asset tags according to the matrix.
+pub fn validate_bank_asset_tags(
+    asset_bank: &Bank,
+    liab_bank: &Bank,
+    asset_tag_matrix: &AssetTagMatrix,
+) -> SurroundfiResult {
+    check!(
+        asset_tag_matrix.can_liquidate(asset_bank.config.asset_tag, liab_bank.config.asset_tag)?,
+        SurroundfiError::AssetTagMismatch
+    );
+
+    Ok(())
+}
+
+/// Validate the spl-single-pool accounts backing a staked collateral bank. The `stake_pool` must
+/// derive the bank's LST mint, which proves it is the bank's pool, and the sol pool and the pool's
+/// stake/mint authorities must in turn derive from it.
+pub fn validate_single_pool_accounts(
+    bank: &Bank,
+    stake_pool: &Pubkey,
+    sol_pool: &Pubkey,
+    pool_stake_authority: &Pubkey,
+    pool_mint_authority: &Pubkey,
+) -> SurroundfiResult {
+    check!(
+        bank.config.asset_tag == ASSET_TAG_STAKED,
+        SurroundfiError::StakePoolValidationFailed
+    );
+
+    let stake_pool_bytes = &stake_pool.to_bytes();
+    for (seed, key) in [
+        (b"mint".as_ref(), &bank.mint),
+        (b"stake".as_ref(), sol_pool),
+        (b"stake_authority".as_ref(), pool_stake_authority),
+        (b"mint_authority".as_ref(), pool_mint_authority),
+    ] {
+        let (exp_key, _) =
+            Pubkey::find_program_address(&[seed, stake_pool_bytes], &SPL_SINGLE_POOL_ID);
+        check_eq!(exp_key, *key, SurroundfiError::StakePoolValidationFailed);
     }
 
     Ok(())
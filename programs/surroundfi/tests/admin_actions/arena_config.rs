@@ -0,0 +1,222 @@
+use fixed_macro::types::I80F48;
+use fixtures::{assert_custom_error, prelude::*, surroundfi_account::SurroundfiAccountFixture};
+use surroundfi::{
+    constants::LIQUIDATION_LIQUIDATOR_FEE, prelude::SurroundfiError,
+    state::surroundfi_group::ArenaConfig,
+};
+use pretty_assertions::assert_eq;
+use solana_program::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+};
+use solana_program_test::*;
+use solana_sdk::{signer::Signer, transaction::Transaction};
+
+fn arena_settings() -> TestSettings {
+    TestSettings {
+        banks: vec![
+            TestBankSetting {
+                mint: BankMint::Usdc,
+                config: None,
+            },
+            TestBankSetting {
+                mint: BankMint::Sol,
+                config: None,
+            },
+        ],
+        protocol_fees: false,
+    }
+}
+
+#[tokio::test]
+async fn configure_arena() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(arena_settings())).await;
+
+    let arena_config = ArenaConfig {
+        max_leverage: I80F48!(5).into(),
+        liquidation_fee: I80F48!(0.05).into(),
+        ..ArenaConfig::default()
+    };
+
+    // Only arena groups have arena settings
+    let res = test_f
+        .surroundfi_group
+        .try_configure_arena(arena_config)
+        .await;
+    assert_custom_error!(res.unwrap_err(), SurroundfiError::NotArenaGroup);
+
+    let admin = test_f.surroundfi_group.load().await.admin;
+    test_f.surroundfi_group.try_update(admin, true).await?;
+
+    for invalid_config in [
+        ArenaConfig {
+            max_leverage: I80F48!(0.5).into(),
+            ..arena_config
+        },
+        ArenaConfig {
+            max_leverage: I80F48!(51).into(),
+            ..arena_config
+        },
+        ArenaConfig {
+            liquidation_fee: I80F48!(0.2).into(),
+            ..arena_config
+        },
+    ] {
+        let res = test_f
+            .surroundfi_group
+            .try_configure_arena(invalid_config)
+            .await;
+        assert_custom_error!(res.unwrap_err(), SurroundfiError::InvalidConfig);
+    }
+
+    test_f
+        .surroundfi_group
+        .try_configure_arena(arena_config)
+        .await?;
+    let group = test_f.surroundfi_group.load().await;
+    assert_eq!(group.arena_config, arena_config);
+    assert_eq!(group.get_max_leverage(), Some(I80F48!(5)));
+    assert_eq!(group.get_liquidator_fee(), I80F48!(0.05));
+
+    // All zeroes restore the defaults
+    test_f
+        .surroundfi_group
+        .try_configure_arena(ArenaConfig::default())
+        .await?;
+    let group = test_f.surroundfi_group.load().await;
+    assert_eq!(group.get_max_leverage(), None);
+    assert_eq!(group.get_liquidator_fee(), LIQUIDATION_LIQUIDATOR_FEE);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn arena_max_leverage_limits_borrows_and_withdrawals() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(arena_settings())).await;
+
+    let usdc_bank_f = test_f.get_bank(&BankMint::Usdc);
+    let sol_bank_f = test_f.get_bank(&BankMint::Sol);
+
+    let admin = test_f.surroundfi_group.load().await.admin;
+    test_f.surroundfi_group.try_update(admin, true).await?;
+    test_f
+        .surroundfi_group
+        .try_configure_arena(ArenaConfig {
+            max_leverage: I80F48!(1.5).into(),
+            ..ArenaConfig::default()
+        })
+        .await?;
+
+    let lp_mfi_account_f = test_f.create_surroundfi_account().await;
+    let lp_usdc_token_account = test_f
+        .usdc_mint
+        .create_token_account_and_mint_to(1_000)
+        .await;
+    lp_mfi_account_f
+        .try_bank_deposit(lp_usdc_token_account.key, usdc_bank_f, 1_000, None)
+        .await?;
+
+    // $100 of SOL collateral: at 1.5x, at most ~$33 of debt
+    let user_mfi_account_f = test_f.create_surroundfi_account().await;
+    let user_sol_token_account = test_f.sol_mint.create_token_account_and_mint_to(10).await;
+    user_mfi_account_f
+        .try_bank_deposit(user_sol_token_account.key, sol_bank_f, 10, None)
+        .await?;
+
+    // The banks' weights allow this borrow, the arena doesn't
+    let user_usdc_token_account = test_f.usdc_mint.create_empty_token_account().await;
+    let res = user_mfi_account_f
+        .try_bank_borrow(user_usdc_token_account.key, usdc_bank_f, 40)
+        .await;
+    assert_custom_error!(res.unwrap_err(), SurroundfiError::ArenaMaxLeverageExceeded);
+
+    user_mfi_account_f
+        .try_bank_borrow(user_usdc_token_account.key, usdc_bank_f, 30)
+        .await?;
+
+    // Flashloans end with the same check, which needs the group: $100 of assets against $60 of
+    // equity is ~1.67x
+    let borrow_ix = user_mfi_account_f
+        .make_bank_borrow_ix(user_usdc_token_account.key, usdc_bank_f, 10)
+        .await;
+    let res = try_arena_flashloan(
+        &test_f,
+        &user_mfi_account_f,
+        vec![borrow_ix.clone()],
+        vec![usdc_bank_f.key],
+        false,
+    )
+    .await;
+    assert_custom_error!(res.unwrap_err(), SurroundfiError::GroupMissing);
+
+    let res = try_arena_flashloan(
+        &test_f,
+        &user_mfi_account_f,
+        vec![borrow_ix],
+        vec![usdc_bank_f.key],
+        true,
+    )
+    .await;
+    assert_custom_error!(res.unwrap_err(), SurroundfiError::ArenaMaxLeverageExceeded);
+
+    // $100 of assets against $69 of equity is ~1.45x
+    let borrow_ix = user_mfi_account_f
+        .make_bank_borrow_ix(user_usdc_token_account.key, usdc_bank_f, 1)
+        .await;
+    try_arena_flashloan(
+        &test_f,
+        &user_mfi_account_f,
+        vec![borrow_ix],
+        vec![usdc_bank_f.key],
+        true,
+    )
+    .await?;
+
+    // $80 of assets against $50 of equity is 1.6x
+    let res = user_mfi_account_f
+        .try_bank_withdraw(user_sol_token_account.key, sol_bank_f, 2, None)
+        .await;
+    assert_custom_error!(res.unwrap_err(), SurroundfiError::ArenaMaxLeverageExceeded);
+
+    user_mfi_account_f
+        .try_bank_withdraw(user_sol_token_account.key, sol_bank_f, 0.5, None)
+        .await?;
+
+    Ok(())
+}
+
+/// Wraps `ixs` in a flashloan, passing the group after the risk engine's accounts of the end
+/// flashloan ix if `pass_group`
+async fn try_arena_flashloan(
+    test_f: &TestFixture,
+    mfi_account_f: &SurroundfiAccountFixture,
+    mut ixs: Vec<Instruction>,
+    include_banks: Vec<Pubkey>,
+    pass_group: bool,
+) -> std::result::Result<(), BanksClientError> {
+    let start_ix = mfi_account_f
+        .make_lending_account_start_flashloan_ix(ixs.len() as u64 + 1)
+        .await;
+    let mut end_ix = mfi_account_f
+        .make_lending_account_end_flashloan_ix(include_banks, vec![])
+        .await;
+    if pass_group {
+        end_ix.accounts.push(AccountMeta::new_readonly(
+            test_f.surroundfi_group.key,
+            false,
+        ));
+    }
+
+    ixs.insert(0, start_ix);
+    ixs.push(end_ix);
+
+    let mut ctx = test_f.context.borrow_mut();
+    let tx = Transaction::new_signed_with_payer(
+        &ixs,
+        Some(&ctx.payer.pubkey().clone()),
+        &[&ctx.payer],
+        ctx.last_blockhash,
+    );
+
+    ctx.banks_client.process_transaction(tx).await
+}
@@ -0,0 +1,182 @@
+use fixtures::{assert_custom_error, prelude::*};
+use surroundfi::{
+    constants::{ASSET_TAG_DEFAULT, ASSET_TAG_STABLE, ASSET_TAG_STAKED, MAX_ASSET_TAGS},
+    prelude::SurroundfiError,
+    state::surroundfi_group::{AssetTagMatrix, BankConfigOpt, STAKED_STABLE_BORROW_ENABLED},
+};
+use pretty_assertions::assert_eq;
+use solana_program_test::*;
+
+const ASSET_TAG_MEME: u8 = 5;
+const ASSET_TAG_RWA: u8 = 6;
+
+#[tokio::test]
+async fn asset_tag_matrix_segregates_custom_tags() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+
+    let usdc_bank_f = test_f.get_bank(&BankMint::Usdc);
+    let meme_bank_f = test_f.get_bank(&BankMint::Sol);
+    let rwa_bank_f = test_f.get_bank(&BankMint::PyUSD);
+
+    let res = meme_bank_f
+        .update_config(
+            BankConfigOpt {
+                asset_tag: Some(MAX_ASSET_TAGS as u8),
+                ..BankConfigOpt::default()
+            },
+            None,
+        )
+        .await;
+    assert!(res.is_err());
+
+    for (bank_f, asset_tag) in [(meme_bank_f, ASSET_TAG_MEME), (rwa_bank_f, ASSET_TAG_RWA)] {
+        bank_f
+            .update_config(
+                BankConfigOpt {
+                    asset_tag: Some(asset_tag),
+                    ..BankConfigOpt::default()
+                },
+                None,
+            )
+            .await?;
+    }
+
+    // Custom tags can't be used with the default matrix
+    let lp_mfi_account_f = test_f.create_surroundfi_account().await;
+    let lp_sol_token_account = test_f.sol_mint.create_token_account_and_mint_to(100).await;
+    let res = lp_mfi_account_f
+        .try_bank_deposit(lp_sol_token_account.key, meme_bank_f, 100, None)
+        .await;
+    assert_custom_error!(res.unwrap_err(), SurroundfiError::AssetTagMismatch);
+
+    // Coexistence must be symmetric
+    let mut asset_tag_matrix = AssetTagMatrix::DEFAULT;
+    asset_tag_matrix.coexist[ASSET_TAG_MEME as usize] =
+        (1 << ASSET_TAG_DEFAULT) | (1 << ASSET_TAG_MEME);
+    let res = test_f
+        .surroundfi_group
+        .try_configure_asset_tag_matrix(asset_tag_matrix)
+        .await;
+    assert_custom_error!(res.unwrap_err(), SurroundfiError::InvalidConfig);
+
+    // Built-in tags must coexist with themselves
+    let mut no_default_diagonal = AssetTagMatrix::DEFAULT;
+    no_default_diagonal.coexist[ASSET_TAG_DEFAULT as usize] &= !(1 << ASSET_TAG_DEFAULT);
+    let res = test_f
+        .surroundfi_group
+        .try_configure_asset_tag_matrix(no_default_diagonal)
+        .await;
+    assert_custom_error!(res.unwrap_err(), SurroundfiError::InvalidConfig);
+
+    // Memecoins can be borrowed against regular assets, RWA tokens only mix with each other
+    asset_tag_matrix.coexist[ASSET_TAG_DEFAULT as usize] |= 1 << ASSET_TAG_MEME;
+    asset_tag_matrix.coexist[ASSET_TAG_RWA as usize] = 1 << ASSET_TAG_RWA;
+    asset_tag_matrix.liquidation[ASSET_TAG_DEFAULT as usize] |= 1 << ASSET_TAG_MEME;
+    test_f
+        .surroundfi_group
+        .try_configure_asset_tag_matrix(asset_tag_matrix)
+        .await?;
+    assert_eq!(
+        test_f.surroundfi_group.load().await.get_asset_tag_matrix(),
+        asset_tag_matrix
+    );
+
+    lp_mfi_account_f
+        .try_bank_deposit(lp_sol_token_account.key, meme_bank_f, 100, None)
+        .await?;
+
+    let borrower_mfi_account_f = test_f.create_surroundfi_account().await;
+    let borrower_usdc_token_account = test_f
+        .usdc_mint
+        .create_token_account_and_mint_to(1_000)
+        .await;
+    borrower_mfi_account_f
+        .try_bank_deposit(borrower_usdc_token_account.key, usdc_bank_f, 1_000, None)
+        .await?;
+    let borrower_sol_token_account = test_f.sol_mint.create_empty_token_account().await;
+    borrower_mfi_account_f
+        .try_bank_borrow(borrower_sol_token_account.key, meme_bank_f, 1)
+        .await?;
+
+    let borrower_pyusd_token_account = test_f
+        .pyusd_mint
+        .create_token_account_and_mint_to(100)
+        .await;
+    let res = borrower_mfi_account_f
+        .try_bank_deposit(borrower_pyusd_token_account.key, rwa_bank_f, 100, None)
+        .await;
+    assert_custom_error!(res.unwrap_err(), SurroundfiError::AssetTagMismatch);
+
+    // An all-zero matrix restores the default rules
+    test_f
+        .surroundfi_group
+        .try_configure_asset_tag_matrix(AssetTagMatrix::default())
+        .await?;
+    assert_eq!(
+        test_f.surroundfi_group.load().await.get_asset_tag_matrix(),
+        AssetTagMatrix::DEFAULT
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn staked_stable_borrow_opt_in() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+
+    let usdc_bank_f = test_f.get_bank(&BankMint::Usdc);
+    let sol_bank_f = test_f.get_bank(&BankMint::Sol);
+
+    usdc_bank_f
+        .update_config(
+            BankConfigOpt {
+                asset_tag: Some(ASSET_TAG_STABLE),
+                ..BankConfigOpt::default()
+            },
+            None,
+        )
+        .await?;
+
+    test_f
+        .surroundfi_group
+        .try_update_staked_stable_borrow(true)
+        .await?;
+    let group = test_f.surroundfi_group.load().await;
+    assert!(group.group_flags & STAKED_STABLE_BORROW_ENABLED != 0);
+    assert!(group.program_fees_enabled());
+    let asset_tag_matrix = group.get_asset_tag_matrix();
+    assert!(asset_tag_matrix.can_coexist(ASSET_TAG_STAKED, ASSET_TAG_STABLE)?);
+    assert!(asset_tag_matrix.can_liquidate(ASSET_TAG_STAKED, ASSET_TAG_STABLE)?);
+
+    // Stablecoins still comingle with regular assets. Borrowing them against staked collateral,
+    // and the stable liability weights that apply, are covered by tests/s11_stableBorrow.spec.ts
+    let lender_mfi_account_f = test_f.create_surroundfi_account().await;
+    let lender_usdc_token_account = test_f
+        .usdc_mint
+        .create_token_account_and_mint_to(1_000)
+        .await;
+    lender_mfi_account_f
+        .try_bank_deposit(lender_usdc_token_account.key, usdc_bank_f, 1_000, None)
+        .await?;
+
+    let borrower_mfi_account_f = test_f.create_surroundfi_account().await;
+    let borrower_sol_token_account = test_f.sol_mint.create_token_account_and_mint_to(100).await;
+    borrower_mfi_account_f
+        .try_bank_deposit(borrower_sol_token_account.key, sol_bank_f, 100, None)
+        .await?;
+    let borrower_usdc_token_account = test_f.usdc_mint.create_empty_token_account().await;
+    borrower_mfi_account_f
+        .try_bank_borrow(borrower_usdc_token_account.key, usdc_bank_f, 100)
+        .await?;
+
+    test_f
+        .surroundfi_group
+        .try_update_staked_stable_borrow(false)
+        .await?;
+    assert_eq!(
+        test_f.surroundfi_group.load().await.get_asset_tag_matrix(),
+        AssetTagMatrix::DEFAULT
+    );
+
+    Ok(())
+}
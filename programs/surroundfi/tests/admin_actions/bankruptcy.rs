@@ -5,8 +5,12 @@ use fixed::types::I80F48;
 use fixed_macro::types::I80F48;
 use fixtures::{assert_custom_error, assert_eq_noise, native, prelude::*};
 use surroundfi::{
+    constants::MAX_BANKRUPT_THRESHOLD,
     prelude::SurroundfiError,
-    state::surroundfi_group::{BankConfig, BankVaultType},
+    state::{
+        surroundfi_account::ACCOUNT_DISABLED,
+        surroundfi_group::{BankConfig, BankVaultType},
+    },
 };
 use pretty_assertions::assert_eq;
 use solana_program_test::*;
@@ -965,3 +969,287 @@ async fn surroundfi_group_handle_bankruptcy_success_not_insured_3_depositors() -
 
     Ok(())
 }
+
+#[tokio::test]
+async fn surroundfi_group_handle_account_bankruptcy_all_liabilities() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+
+    let usdc_bank_f = test_f.get_bank(&BankMint::Usdc);
+    let pyusd_bank_f = test_f.get_bank(&BankMint::PyUSD);
+    let sol_bank_f = test_f.get_bank(&BankMint::Sol);
+
+    let lp_mfi_account_f = test_f.create_surroundfi_account().await;
+    let lp_usdc_token_account = test_f
+        .usdc_mint
+        .create_token_account_and_mint_to(10_000)
+        .await;
+    lp_mfi_account_f
+        .try_bank_deposit(lp_usdc_token_account.key, usdc_bank_f, 10_000, None)
+        .await?;
+    let lp_pyusd_token_account = test_f
+        .pyusd_mint
+        .create_token_account_and_mint_to(10_000)
+        .await;
+    lp_mfi_account_f
+        .try_bank_deposit(lp_pyusd_token_account.key, pyusd_bank_f, 10_000, None)
+        .await?;
+
+    // Borrow USDC and PyUSD against SOL
+    let borrower_mfi_account_f = test_f.create_surroundfi_account().await;
+    let collateral_amount = test_f
+        .get_sufficient_collateral_for_outflow(1_000., &BankMint::Usdc, &BankMint::Sol)
+        .await
+        + test_f
+            .get_sufficient_collateral_for_outflow(1_000., &BankMint::PyUSD, &BankMint::Sol)
+            .await;
+    let borrower_sol_token_account = test_f
+        .sol_mint
+        .create_token_account_and_mint_to(collateral_amount)
+        .await;
+    borrower_mfi_account_f
+        .try_bank_deposit(
+            borrower_sol_token_account.key,
+            sol_bank_f,
+            collateral_amount,
+            None,
+        )
+        .await?;
+    let borrower_usdc_token_account = test_f.usdc_mint.create_empty_token_account().await;
+    borrower_mfi_account_f
+        .try_bank_borrow(borrower_usdc_token_account.key, usdc_bank_f, 1_000)
+        .await?;
+    let borrower_pyusd_token_account = test_f.pyusd_mint.create_empty_token_account().await;
+    borrower_mfi_account_f
+        .try_bank_borrow(borrower_pyusd_token_account.key, pyusd_bank_f, 1_000)
+        .await?;
+
+    // Artificially leave $0.01 of collateral to place the account in a bankrupt state
+    let dust = I80F48::from(native!(0.001, "SOL", f64));
+    let mut borrower_mfi_account = borrower_mfi_account_f.load().await;
+    borrower_mfi_account.lending_account.balances[0].asset_shares = dust.into();
+    borrower_mfi_account_f
+        .set_account(&borrower_mfi_account)
+        .await?;
+    let sol_bank_pre = sol_bank_f.load().await;
+
+    // All liability banks must be passed
+    let res = test_f
+        .surroundfi_group
+        .try_handle_account_bankruptcy(&[usdc_bank_f], &borrower_mfi_account_f)
+        .await;
+    assert_custom_error!(res.unwrap_err(), SurroundfiError::InvalidBankAccount);
+
+    test_f
+        .surroundfi_group
+        .try_handle_account_bankruptcy(&[usdc_bank_f, pyusd_bank_f], &borrower_mfi_account_f)
+        .await?;
+
+    let borrower_mfi_account = borrower_mfi_account_f.load().await;
+    assert!(borrower_mfi_account.get_flag(ACCOUNT_DISABLED));
+    assert!(borrower_mfi_account
+        .lending_account
+        .balances
+        .iter()
+        .all(|balance| !balance.is_active()));
+
+    // The collateral dust went to the SOL bank's insurance fees
+    let sol_bank = sol_bank_f.load().await;
+    assert_eq!(
+        I80F48::from(sol_bank.total_asset_shares),
+        I80F48::from(sol_bank_pre.total_asset_shares) - dust
+    );
+    assert_eq_noise!(
+        I80F48::from(sol_bank.collected_insurance_fees_outstanding),
+        I80F48::from(sol_bank_pre.collected_insurance_fees_outstanding) + dust,
+        I80F48::ONE
+    );
+
+    // Not insured, the lender takes the loss on both banks
+    let lp_mfi_account = lp_mfi_account_f.load().await;
+    for (index, bank_f) in [usdc_bank_f, pyusd_bank_f].into_iter().enumerate() {
+        let bank = bank_f.load().await;
+        assert_eq!(I80F48::from(bank.total_liability_shares), I80F48::ZERO);
+
+        let lender_value = bank.get_asset_amount(
+            lp_mfi_account.lending_account.balances[index]
+                .asset_shares
+                .into(),
+        )?;
+        assert_eq_noise!(
+            lender_value,
+            I80F48::from(native!(9_000, "USDC")),
+            I80F48::ONE
+        );
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn surroundfi_group_handle_account_bankruptcy_forfeits_emissions() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+
+    let usdc_bank_f = test_f.get_bank(&BankMint::Usdc);
+    let sol_bank_f = test_f.get_bank(&BankMint::Sol);
+
+    let lp_mfi_account_f = test_f.create_surroundfi_account().await;
+    let lp_usdc_token_account = test_f
+        .usdc_mint
+        .create_token_account_and_mint_to(10_000)
+        .await;
+    lp_mfi_account_f
+        .try_bank_deposit(lp_usdc_token_account.key, usdc_bank_f, 10_000, None)
+        .await?;
+
+    let borrower_mfi_account_f = test_f.create_surroundfi_account().await;
+    let collateral_amount = test_f
+        .get_sufficient_collateral_for_outflow(1_000., &BankMint::Usdc, &BankMint::Sol)
+        .await;
+    let borrower_sol_token_account = test_f
+        .sol_mint
+        .create_token_account_and_mint_to(collateral_amount)
+        .await;
+    borrower_mfi_account_f
+        .try_bank_deposit(
+            borrower_sol_token_account.key,
+            sol_bank_f,
+            collateral_amount,
+            None,
+        )
+        .await?;
+    let borrower_usdc_token_account = test_f.usdc_mint.create_empty_token_account().await;
+    borrower_mfi_account_f
+        .try_bank_borrow(borrower_usdc_token_account.key, usdc_bank_f, 1_000)
+        .await?;
+
+    // Bankrupt, with unclaimed emissions on both the collateral and the liability
+    let emissions_outstanding = I80F48::from_num(5);
+    let mut borrower_mfi_account = borrower_mfi_account_f.load().await;
+    let balances = &mut borrower_mfi_account.lending_account.balances;
+    balances[0].asset_shares = I80F48::from(native!(0.001, "SOL", f64)).into();
+    balances[0].emissions_outstanding = emissions_outstanding.into();
+    balances[1].emissions_outstanding = emissions_outstanding.into();
+    borrower_mfi_account_f
+        .set_account(&borrower_mfi_account)
+        .await?;
+    let sol_bank_pre = sol_bank_f.load().await;
+    let usdc_bank_pre = usdc_bank_f.load().await;
+
+    test_f
+        .surroundfi_group
+        .try_handle_account_bankruptcy(&[usdc_bank_f], &borrower_mfi_account_f)
+        .await?;
+
+    // The account is disabled, so it could never withdraw them: they go back to the banks and no
+    // balance is left open
+    let borrower_mfi_account = borrower_mfi_account_f.load().await;
+    assert!(borrower_mfi_account.get_flag(ACCOUNT_DISABLED));
+    assert!(borrower_mfi_account
+        .lending_account
+        .balances
+        .iter()
+        .all(|balance| !balance.is_active()));
+
+    for (bank_f, bank_pre) in [(sol_bank_f, sol_bank_pre), (usdc_bank_f, usdc_bank_pre)] {
+        let bank = bank_f.load().await;
+        assert_eq!(
+            I80F48::from(bank.emissions_remaining),
+            I80F48::from(bank_pre.emissions_remaining) + emissions_outstanding
+        );
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn surroundfi_group_handle_bankruptcy_group_threshold() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+
+    let usdc_bank_f = test_f.get_bank(&BankMint::Usdc);
+    let sol_bank_f = test_f.get_bank(&BankMint::Sol);
+
+    let lp_mfi_account_f = test_f.create_surroundfi_account().await;
+    let lp_token_account = test_f
+        .usdc_mint
+        .create_token_account_and_mint_to(1_000)
+        .await;
+    lp_mfi_account_f
+        .try_bank_deposit(lp_token_account.key, usdc_bank_f, 1_000, None)
+        .await?;
+
+    let borrower_mfi_account_f = test_f.create_surroundfi_account().await;
+    let borrower_token_account_sol = test_f.sol_mint.create_token_account_and_mint_to(100).await;
+    borrower_mfi_account_f
+        .try_bank_deposit(borrower_token_account_sol.key, sol_bank_f, 100, None)
+        .await?;
+    let borrower_token_account_usdc = test_f.usdc_mint.create_empty_token_account().await;
+    borrower_mfi_account_f
+        .try_bank_borrow(borrower_token_account_usdc.key, usdc_bank_f, 100)
+        .await?;
+
+    // Leave about $1 of collateral against $100 of debt
+    let mut borrower_mfi_account = borrower_mfi_account_f.load().await;
+    borrower_mfi_account.lending_account.balances[0].asset_shares =
+        I80F48::from(native!(0.1, "SOL", f64)).into();
+    borrower_mfi_account_f
+        .set_account(&borrower_mfi_account)
+        .await?;
+
+    // Above the default threshold of $0.1
+    let res = test_f
+        .surroundfi_group
+        .try_handle_bankruptcy(usdc_bank_f, &borrower_mfi_account_f)
+        .await;
+    assert_custom_error!(res.unwrap_err(), SurroundfiError::AccountNotBankrupt);
+
+    let res = test_f
+        .surroundfi_group
+        .try_update_bankruptcy_threshold(I80F48::ZERO)
+        .await;
+    assert_custom_error!(res.unwrap_err(), SurroundfiError::InvalidConfig);
+
+    // Capped so accounts with more than dust collateral can't be handled as bankrupt
+    let res = test_f
+        .surroundfi_group
+        .try_update_bankruptcy_threshold(MAX_BANKRUPT_THRESHOLD + I80F48::DELTA)
+        .await;
+    assert_custom_error!(res.unwrap_err(), SurroundfiError::InvalidConfig);
+
+    // Still above a $0.5 threshold
+    test_f
+        .surroundfi_group
+        .try_update_bankruptcy_threshold(I80F48!(0.5))
+        .await?;
+    let res = test_f
+        .surroundfi_group
+        .try_handle_bankruptcy(usdc_bank_f, &borrower_mfi_account_f)
+        .await;
+    assert_custom_error!(res.unwrap_err(), SurroundfiError::AccountNotBankrupt);
+
+    // Below the max threshold of $10
+    test_f
+        .surroundfi_group
+        .try_update_bankruptcy_threshold(MAX_BANKRUPT_THRESHOLD)
+        .await?;
+    assert_eq!(
+        test_f
+            .surroundfi_group
+            .load()
+            .await
+            .get_bankruptcy_threshold(),
+        MAX_BANKRUPT_THRESHOLD
+    );
+    test_f
+        .surroundfi_group
+        .try_handle_bankruptcy(usdc_bank_f, &borrower_mfi_account_f)
+        .await?;
+
+    let borrower_mfi_account = borrower_mfi_account_f.load().await;
+    assert!(borrower_mfi_account.get_flag(ACCOUNT_DISABLED));
+    assert_eq!(
+        I80F48::from(borrower_mfi_account.lending_account.balances[1].liability_shares),
+        I80F48::ZERO
+    );
+
+    Ok(())
+}
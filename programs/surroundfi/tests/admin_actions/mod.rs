@@ -1,4 +1,6 @@
 mod account_transfer;
+mod arena_config;
+mod asset_tag_matrix;
 mod bankruptcy;
 mod bankruptcy_auth;
 mod create_surroundfi_group;
@@ -88,6 +88,21 @@ async fn add_bank_success() -> anyhow::Result<()> {
             emissions_remaining,
             emissions_mint,
             collected_program_fees_outstanding,
+            receipt_mint,
+            receipt_mint_bump,
+            emissions_asset_index,
+            emissions_liability_index,
+            emissions_index_last_update,
+            emissions_index_start,
+            emissions_start_time,
+            emissions_end_time,
+            insurance_stake_shares,
+            insurance_stake_share_value,
+            insurance_stake_fee_share,
+            insurance_stake_cooldown,
+            receipt_asset_shares,
+            insurance_stake_unstake_window,
+            insurance_stake_fees_credited,
             _padding_0,
             _padding_1,
             .. // ignore internal padding
@@ -118,8 +133,23 @@ async fn add_bank_success() -> anyhow::Result<()> {
             assert_eq!(emissions_mint, Pubkey::new_from_array([0; 32]));
             assert_eq!(emissions_remaining, I80F48!(0.0).into());
             assert_eq!(collected_program_fees_outstanding, I80F48!(0.0).into());
-
-            assert_eq!(_padding_0, <[[u64; 2]; 27] as Default>::default());
+            assert_eq!(receipt_mint, Pubkey::default());
+            assert_eq!(receipt_mint_bump, 0);
+            assert_eq!(emissions_asset_index, I80F48!(0.0).into());
+            assert_eq!(emissions_liability_index, I80F48!(0.0).into());
+            assert_eq!(emissions_index_last_update, 0);
+            assert_eq!(emissions_index_start, 0);
+            assert_eq!(emissions_start_time, 0);
+            assert_eq!(emissions_end_time, 0);
+            assert_eq!(insurance_stake_shares, I80F48!(0.0).into());
+            assert_eq!(insurance_stake_share_value, I80F48!(0.0).into());
+            assert_eq!(insurance_stake_fee_share, I80F48!(0.0).into());
+            assert_eq!(insurance_stake_cooldown, 0);
+            assert_eq!(receipt_asset_shares, I80F48!(0.0).into());
+            assert_eq!(insurance_stake_unstake_window, 0);
+            assert_eq!(insurance_stake_fees_credited, I80F48!(0.0).into());
+
+            assert_eq!(_padding_0, <[[u64; 2]; 13] as Default>::default());
             assert_eq!(_padding_1, <[[u64; 2]; 32] as Default>::default());
 
             // this is the only loosely checked field
@@ -223,6 +253,21 @@ async fn add_bank_with_seed_success() -> anyhow::Result<()> {
             emissions_remaining,
             emissions_mint,
             collected_program_fees_outstanding,
+            receipt_mint,
+            receipt_mint_bump,
+            emissions_asset_index,
+            emissions_liability_index,
+            emissions_index_last_update,
+            emissions_index_start,
+            emissions_start_time,
+            emissions_end_time,
+            insurance_stake_shares,
+            insurance_stake_share_value,
+            insurance_stake_fee_share,
+            insurance_stake_cooldown,
+            receipt_asset_shares,
+            insurance_stake_unstake_window,
+            insurance_stake_fees_credited,
             _padding_0,
             _padding_1,
             .. // ignore internal padding
@@ -253,8 +298,23 @@ async fn add_bank_with_seed_success() -> anyhow::Result<()> {
             assert_eq!(emissions_mint, Pubkey::new_from_array([0; 32]));
             assert_eq!(emissions_remaining, I80F48!(0.0).into());
             assert_eq!(collected_program_fees_outstanding, I80F48!(0.0).into());
-
-            assert_eq!(_padding_0, <[[u64; 2]; 27] as Default>::default());
+            assert_eq!(receipt_mint, Pubkey::default());
+            assert_eq!(receipt_mint_bump, 0);
+            assert_eq!(emissions_asset_index, I80F48!(0.0).into());
+            assert_eq!(emissions_liability_index, I80F48!(0.0).into());
+            assert_eq!(emissions_index_last_update, 0);
+            assert_eq!(emissions_index_start, 0);
+            assert_eq!(emissions_start_time, 0);
+            assert_eq!(emissions_end_time, 0);
+            assert_eq!(insurance_stake_shares, I80F48!(0.0).into());
+            assert_eq!(insurance_stake_share_value, I80F48!(0.0).into());
+            assert_eq!(insurance_stake_fee_share, I80F48!(0.0).into());
+            assert_eq!(insurance_stake_cooldown, 0);
+            assert_eq!(receipt_asset_shares, I80F48!(0.0).into());
+            assert_eq!(insurance_stake_unstake_window, 0);
+            assert_eq!(insurance_stake_fees_credited, I80F48!(0.0).into());
+
+            assert_eq!(_padding_0, <[[u64; 2]; 13] as Default>::default());
             assert_eq!(_padding_1, <[[u64; 2]; 32] as Default>::default());
 
             // this is the only loosely checked field
@@ -326,6 +386,9 @@ async fn configure_bank_success(bank_mint: BankMint) -> anyhow::Result<()> {
             protocol_fixed_fee_apr: Some(I80F48::from_num(0.51).into()),
             protocol_ir_fee: Some(I80F48::from_num(0.011).into()),
             protocol_origination_fee: Some(I80F48::ZERO.into()),
+            flashloan_fee: Some(I80F48::from_num(0.0009).into()),
+            flashloan_group_fee: Some(I80F48::from_num(0.0001).into()),
+            referrer_fee_share: Some(I80F48::from_num(0.2).into()),
         }),
         ..BankConfigOpt::default()
     };
@@ -381,6 +444,9 @@ async fn configure_bank_success(bank_mint: BankMint) -> anyhow::Result<()> {
         check_bank_field!(interest_rate_config, protocol_fixed_fee_apr);
         check_bank_field!(interest_rate_config, protocol_ir_fee);
         check_bank_field!(interest_rate_config, protocol_origination_fee);
+        check_bank_field!(interest_rate_config, flashloan_fee);
+        check_bank_field!(interest_rate_config, flashloan_group_fee);
+        check_bank_field!(interest_rate_config, referrer_fee_share);
 
         check_bank_field!(asset_weight_init);
         check_bank_field!(asset_weight_maint);
@@ -3,9 +3,14 @@ use anchor_spl::token_2022::spl_token_2022::extension::{
     transfer_fee::TransferFeeConfig, BaseStateWithExtensions,
 };
 use fixtures::{
-    assert_anchor_error,
+    assert_anchor_error, assert_custom_error, native,
+    spl::TokenAccountFixture,
     test::{BankMint, TestFixture, TestSettings},
 };
+use surroundfi::{
+    prelude::SurroundfiError,
+    state::{fee_distribution::FeeRecipientConfig, surroundfi_group::BankVaultType},
+};
 use solana_program_test::tokio;
 use solana_sdk::pubkey::Pubkey;
 use test_case::test_case;
@@ -134,3 +139,172 @@ async fn surroundfi_group_withdraw_fees_and_insurance_fund_as_non_admin_failure(
 
     Ok(())
 }
+
+#[test_case(BankMint::Usdc)]
+#[test_case(BankMint::Sol)]
+#[tokio::test]
+async fn surroundfi_group_distribute_fees_success(bank_mint: BankMint) -> anyhow::Result<()> {
+    let mut test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+
+    // Shares must add up to 100%
+    let wallets = [
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+    ];
+    let res = test_f
+        .surroundfi_group
+        .try_init_fee_distribution(vec![
+            FeeRecipientConfig {
+                wallet: wallets[0],
+                share_bps: 7_000,
+            },
+            FeeRecipientConfig {
+                wallet: wallets[1],
+                share_bps: 2_000,
+            },
+        ])
+        .await;
+    assert!(res.is_err());
+    assert_custom_error!(res.unwrap_err(), SurroundfiError::InvalidFeeDistribution);
+
+    test_f
+        .surroundfi_group
+        .try_init_fee_distribution(vec![
+            FeeRecipientConfig {
+                wallet: wallets[0],
+                share_bps: 7_000,
+            },
+            FeeRecipientConfig {
+                wallet: wallets[1],
+                share_bps: 2_500,
+            },
+            FeeRecipientConfig {
+                wallet: wallets[2],
+                share_bps: 500,
+            },
+        ])
+        .await?;
+
+    let bank_f = test_f.banks.get_mut(&bank_mint).unwrap();
+    let bank = bank_f.load().await;
+    let mut atas = Vec::new();
+    for wallet in wallets.iter() {
+        atas.push(
+            TokenAccountFixture::new_from_ata(
+                test_f.context.clone(),
+                &bank_f.mint.key,
+                wallet,
+                &bank_f.get_token_program(),
+            )
+            .await,
+        );
+    }
+
+    bank_f.mint.mint_to(&bank.fee_vault, 1_000).await;
+
+    let bank_f = test_f.get_bank(&bank_mint);
+    test_f.surroundfi_group.try_distribute_fees(bank_f).await?;
+
+    let decimals = bank_f.mint.mint.decimals;
+    assert_eq!(atas[0].balance().await, native!(700, decimals));
+    assert_eq!(atas[1].balance().await, native!(250, decimals));
+    assert_eq!(atas[2].balance().await, native!(50, decimals));
+
+    // Everything is distributed, a second crank is a no-op
+    test_f.surroundfi_group.try_distribute_fees(bank_f).await?;
+    assert_eq!(atas[0].balance().await, native!(700, decimals));
+
+    // Recipients can be replaced
+    test_f
+        .surroundfi_group
+        .try_configure_fee_distribution(vec![FeeRecipientConfig {
+            wallet: wallets[2],
+            share_bps: 10_000,
+        }])
+        .await?;
+
+    let bank_f = test_f.banks.get_mut(&bank_mint).unwrap();
+    bank_f.mint.mint_to(&bank.fee_vault, 100).await;
+
+    let bank_f = test_f.get_bank(&bank_mint);
+    test_f.surroundfi_group.try_distribute_fees(bank_f).await?;
+    assert_eq!(atas[2].balance().await, native!(150, decimals));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn surroundfi_group_distribute_fees_skips_missing_ata() -> anyhow::Result<()> {
+    let mut test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+
+    let wallets = [
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+    ];
+    test_f
+        .surroundfi_group
+        .try_init_fee_distribution(vec![
+            FeeRecipientConfig {
+                wallet: wallets[0],
+                share_bps: 7_000,
+            },
+            FeeRecipientConfig {
+                wallet: wallets[1],
+                share_bps: 2_500,
+            },
+            FeeRecipientConfig {
+                wallet: wallets[2],
+                share_bps: 500,
+            },
+        ])
+        .await?;
+
+    // Once fees are distributed, the admin can no longer take them directly
+    let bank_f = test_f.banks.get_mut(&BankMint::Usdc).unwrap();
+    let bank = bank_f.load().await;
+    bank_f.mint.mint_to(&bank.fee_vault, 1_000).await;
+    let receiving_account = bank_f.mint.create_empty_token_account().await;
+    let res = bank_f
+        .try_withdraw_fees(&receiving_account, native!(1_000, "USDC"))
+        .await;
+    assert_custom_error!(res.unwrap_err(), SurroundfiError::FeeDistributionEnabled);
+
+    // The second recipient has no ATA yet
+    let mut atas = Vec::new();
+    for wallet in [wallets[0], wallets[2]] {
+        atas.push(
+            TokenAccountFixture::new_from_ata(
+                test_f.context.clone(),
+                &bank_f.mint.key,
+                &wallet,
+                &bank_f.get_token_program(),
+            )
+            .await,
+        );
+    }
+
+    let bank_f = test_f.get_bank(&BankMint::Usdc);
+    test_f.surroundfi_group.try_distribute_fees(bank_f).await?;
+    assert_eq!(atas[0].balance().await, native!(700, "USDC"));
+    assert_eq!(atas[1].balance().await, native!(50, "USDC"));
+
+    // Its share waits in the vault for the next distribution
+    let fee_vault = bank_f.get_vault_token_account(BankVaultType::Fee).await;
+    assert_eq!(fee_vault.balance().await, native!(250, "USDC"));
+
+    let ata = TokenAccountFixture::new_from_ata(
+        test_f.context.clone(),
+        &bank_f.mint.key,
+        &wallets[1],
+        &bank_f.get_token_program(),
+    )
+    .await;
+    test_f.surroundfi_group.try_distribute_fees(bank_f).await?;
+    assert_eq!(ata.balance().await, native!(62.5, "USDC", f64));
+    assert_eq!(atas[0].balance().await, native!(875, "USDC"));
+    assert_eq!(fee_vault.balance().await, 0);
+
+    Ok(())
+}
@@ -49,7 +49,12 @@ async fn account_field_values_reg() -> anyhow::Result<()> {
     assert_eq!(account.account_flags, 0);
     // health cache doesn't exist on these old accounts, but it also doesn't matter since it's read-only
     assert_eq!(account.health_cache, HealthCache::zeroed());
-    assert_eq!(account._padding0, [0; 21]);
+    assert_eq!(account.account_index, 0);
+    assert_eq!(account.third_party_index, 0);
+    assert_eq!(account.bump, 0);
+    assert_eq!(account.extension, Pubkey::default());
+    assert_eq!(account.creator, Pubkey::default());
+    assert_eq!(account._padding0, [0; 2]);
 
     let balance_1 = account.lending_account.balances[0];
     assert!(balance_1.is_active());
@@ -58,7 +63,9 @@ async fn account_field_values_reg() -> anyhow::Result<()> {
         pubkey!("2s37akK2eyBbp8DZgCm7RtsaEz8eJP3Nxd4urLHQv7yB")
     );
     assert_eq!(balance_1.bank_asset_tag, ASSET_TAG_DEFAULT);
-    assert_eq!(balance_1._pad0, [0; 6]);
+    assert!(!balance_1.is_interest_bearing());
+    assert!(!balance_1.is_emissions_streams_enrolled());
+    assert_eq!(balance_1._pad0, [0; 4]);
     assert_eq!(
         I80F48::from(balance_1.asset_shares),
         I80F48::from_str("1650216221.466876226897366").unwrap()
@@ -75,7 +82,7 @@ async fn account_field_values_reg() -> anyhow::Result<()> {
         I80F48::from(balance_1.last_update),
         I80F48::from_str("1711158766").unwrap()
     );
-    assert_eq!(balance_1._padding, [0; 1]);
+    assert_eq!(balance_1.emissions_index_checkpoint, 0);
 
     let balance_2 = account.lending_account.balances[1];
     assert!(balance_2.is_active());
@@ -84,7 +91,9 @@ async fn account_field_values_reg() -> anyhow::Result<()> {
         pubkey!("CCKtUs6Cgwo4aaQUmBPmyoApH2gUDErxNZCAntD6LYGh")
     );
     assert_eq!(balance_2.bank_asset_tag, ASSET_TAG_DEFAULT);
-    assert_eq!(balance_2._pad0, [0; 6]);
+    assert!(!balance_2.is_interest_bearing());
+    assert!(!balance_2.is_emissions_streams_enrolled());
+    assert_eq!(balance_2._pad0, [0; 4]);
     assert_eq!(
         I80F48::from(balance_2.asset_shares),
         I80F48::from_str("0").unwrap()
@@ -101,7 +110,7 @@ async fn account_field_values_reg() -> anyhow::Result<()> {
         I80F48::from(balance_2.last_update),
         I80F48::from_str("1711158793").unwrap()
     );
-    assert_eq!(balance_2._padding, [0; 1]);
+    assert_eq!(balance_2.emissions_index_checkpoint, 0);
 
     // Sample 2
 
@@ -126,7 +135,12 @@ async fn account_field_values_reg() -> anyhow::Result<()> {
         pubkey!("3T1kGHp7CrdeW9Qj1t8NMc2Ks233RyvzVhoaUPWoBEFK")
     );
     assert_eq!(account.account_flags, 0);
-    assert_eq!(account._padding0, [0; 21]);
+    assert_eq!(account.account_index, 0);
+    assert_eq!(account.third_party_index, 0);
+    assert_eq!(account.bump, 0);
+    assert_eq!(account.extension, Pubkey::default());
+    assert_eq!(account.creator, Pubkey::default());
+    assert_eq!(account._padding0, [0; 2]);
 
     let balance_1 = account.lending_account.balances[0];
     assert!(balance_1.is_active());
@@ -135,7 +149,9 @@ async fn account_field_values_reg() -> anyhow::Result<()> {
         pubkey!("6hS9i46WyTq1KXcoa2Chas2Txh9TJAVr6n1t3tnrE23K")
     );
     assert_eq!(balance_1.bank_asset_tag, ASSET_TAG_DEFAULT);
-    assert_eq!(balance_1._pad0, [0; 6]);
+    assert!(!balance_1.is_interest_bearing());
+    assert!(!balance_1.is_emissions_streams_enrolled());
+    assert_eq!(balance_1._pad0, [0; 4]);
     assert_eq!(
         I80F48::from(balance_1.asset_shares),
         I80F48::from_str("470.952530958931234").unwrap()
@@ -152,7 +168,7 @@ async fn account_field_values_reg() -> anyhow::Result<()> {
         I80F48::from(balance_1.last_update),
         I80F48::from_str("1705760628").unwrap()
     );
-    assert_eq!(balance_1._padding, [0; 1]);
+    assert_eq!(balance_1.emissions_index_checkpoint, 0);
 
     let balance_2 = account.lending_account.balances[1];
     assert!(!balance_2.is_active());
@@ -161,7 +177,9 @@ async fn account_field_values_reg() -> anyhow::Result<()> {
         pubkey!("11111111111111111111111111111111")
     );
     assert_eq!(balance_2.bank_asset_tag, ASSET_TAG_DEFAULT);
-    assert_eq!(balance_2._pad0, [0; 6]);
+    assert!(!balance_2.is_interest_bearing());
+    assert!(!balance_2.is_emissions_streams_enrolled());
+    assert_eq!(balance_2._pad0, [0; 4]);
     assert_eq!(
         I80F48::from(balance_2.asset_shares),
         I80F48::from_str("0").unwrap()
@@ -178,7 +196,7 @@ async fn account_field_values_reg() -> anyhow::Result<()> {
         I80F48::from(balance_2.last_update),
         I80F48::from_str("0").unwrap()
     );
-    assert_eq!(balance_2._padding, [0; 1]);
+    assert_eq!(balance_2.emissions_index_checkpoint, 0);
 
     // Sample 3
 
@@ -203,7 +221,12 @@ async fn account_field_values_reg() -> anyhow::Result<()> {
         pubkey!("7hmfVTuXc7HeX3YQjpiCXGVQuTeXonzjp795jorZukVR")
     );
     assert_eq!(account.account_flags, 0);
-    assert_eq!(account._padding0, [0; 21]);
+    assert_eq!(account.account_index, 0);
+    assert_eq!(account.third_party_index, 0);
+    assert_eq!(account.bump, 0);
+    assert_eq!(account.extension, Pubkey::default());
+    assert_eq!(account.creator, Pubkey::default());
+    assert_eq!(account._padding0, [0; 2]);
 
     let balance_1 = account.lending_account.balances[0];
     assert!(!balance_1.is_active());
@@ -212,7 +235,9 @@ async fn account_field_values_reg() -> anyhow::Result<()> {
         pubkey!("11111111111111111111111111111111")
     );
     assert_eq!(balance_1.bank_asset_tag, ASSET_TAG_DEFAULT);
-    assert_eq!(balance_1._pad0, [0; 6]);
+    assert!(!balance_1.is_interest_bearing());
+    assert!(!balance_1.is_emissions_streams_enrolled());
+    assert_eq!(balance_1._pad0, [0; 4]);
     assert_eq!(
         I80F48::from(balance_1.asset_shares),
         I80F48::from_str("0").unwrap()
@@ -229,7 +254,7 @@ async fn account_field_values_reg() -> anyhow::Result<()> {
         I80F48::from(balance_1.last_update),
         I80F48::from_str("0").unwrap()
     );
-    assert_eq!(balance_1._padding, [0; 1]);
+    assert_eq!(balance_1.emissions_index_checkpoint, 0);
 
     Ok(())
 }
@@ -629,6 +654,18 @@ async fn bank_field_values_reg() -> anyhow::Result<()> {
         I80F48::from(bank.config.interest_rate_config.protocol_ir_fee),
         I80F48::from_str("0.05").unwrap()
     );
+    assert_eq!(
+        I80F48::from(bank.config.interest_rate_config.flashloan_fee),
+        I80F48::ZERO
+    );
+    assert_eq!(
+        I80F48::from(bank.config.interest_rate_config.flashloan_group_fee),
+        I80F48::ZERO
+    );
+    assert_eq!(
+        I80F48::from(bank.config.interest_rate_config.referrer_fee_share),
+        I80F48::ZERO
+    );
     assert_eq!(
         bank.config.operational_state,
         BankOperationalState::Operational
@@ -674,7 +711,26 @@ async fn bank_field_values_reg() -> anyhow::Result<()> {
         I80F48::from_str("0").unwrap()
     );
 
-    assert_eq!(bank._padding_0, [[0, 0]; 27]);
+    assert_eq!(bank.receipt_mint, Pubkey::default());
+    // Emissions indexes start at the first balance update after the upgrade
+    assert_eq!(I80F48::from(bank.emissions_asset_index), I80F48::ZERO);
+    assert_eq!(I80F48::from(bank.emissions_liability_index), I80F48::ZERO);
+    assert_eq!(bank.emissions_index_last_update, 0);
+    assert_eq!(bank.emissions_index_start, 0);
+    assert_eq!(bank.emissions_start_time, 0);
+    assert_eq!(bank.emissions_end_time, 0);
+    // Insurance staking is off until configured
+    assert_eq!(I80F48::from(bank.insurance_stake_shares), I80F48::ZERO);
+    assert_eq!(I80F48::from(bank.insurance_stake_share_value), I80F48::ZERO);
+    assert_eq!(I80F48::from(bank.insurance_stake_fee_share), I80F48::ZERO);
+    assert_eq!(bank.insurance_stake_cooldown, 0);
+    assert_eq!(I80F48::from(bank.receipt_asset_shares), I80F48::ZERO);
+    assert_eq!(bank.insurance_stake_unstake_window, 0);
+    assert_eq!(
+        I80F48::from(bank.insurance_stake_fees_credited),
+        I80F48::ZERO
+    );
+    assert_eq!(bank._padding_0, [[0, 0]; 13]);
     assert_eq!(bank._padding_1, [[0, 0]; 32]);
 
     Ok(())
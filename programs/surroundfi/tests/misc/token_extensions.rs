@@ -4,13 +4,31 @@ use anchor_spl::token_2022::spl_token_2022::extension::{
 use fixed::types::I80F48;
 use fixed_macro::types::I80F48;
 use fixtures::{
-    assert_eq_noise, native,
-    spl::SupportedExtension,
-    test::{BankMint, TestBankSetting, TestFixture, TestSettings, DEFAULT_SOL_TEST_BANK_CONFIG},
+    assert_custom_error, assert_eq_noise, native,
+    spl::{MintFixture, SupportedExtension, TokenAccountFixture},
+    test::{
+        BankMint, TestBankSetting, TestFixture, TestSettings, DEFAULT_PYUSD_TEST_BANK_CONFIG,
+        DEFAULT_SOL_TEST_BANK_CONFIG, DEFAULT_TEST_BANK_INTEREST_RATE_CONFIG,
+        DEFAULT_USDC_TEST_BANK_CONFIG,
+    },
+    transfer_hook::TEST_HOOK_ID,
     ui_to_native,
 };
-use surroundfi::state::surroundfi_group::{Bank, BankConfig, BankConfigOpt, BankVaultType};
+use surroundfi::{
+    assert_eq_with_tolerance,
+    constants::{
+        EMISSIONS_FLAG_LENDING_ACTIVE, MINT_EXTENSIONS_RESTRICTED,
+        MINT_EXTENSION_CONFIDENTIAL_TRANSFER, MINT_EXTENSION_FREEZE_AUTHORITY,
+        MINT_EXTENSION_INTEREST_BEARING, MINT_EXTENSION_MINT_CLOSE_AUTHORITY,
+        MINT_EXTENSION_PAUSABLE, MINT_EXTENSION_PERMANENT_DELEGATE, MINT_EXTENSION_TRANSFER_FEE,
+        MINT_EXTENSION_UNKNOWN, MIN_EMISSIONS_START_TIME, PAUSABLE_CONFIG_EXTENSION_TYPE,
+    },
+    instructions::{SimulatedAction, SimulatedActionType},
+    prelude::SurroundfiError,
+    state::surroundfi_group::{Bank, BankConfig, BankConfigOpt, BankVaultType, InterestRateConfig},
+};
 use solana_program_test::tokio;
+use solana_sdk::{account::AccountSharedData, pubkey::Pubkey, timing::SECONDS_PER_YEAR};
 use test_case::test_case;
 
 #[test_case(vec![])]
@@ -193,3 +211,774 @@ async fn surroundfi_account_liquidation_success_with_extension(
 
     Ok(())
 }
+
+#[tokio::test]
+async fn transfer_hook_bank_requires_hook_accounts() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(None).await;
+
+    let hook_mint_f = MintFixture::new_token_22(
+        test_f.context.clone(),
+        None,
+        None,
+        &[SupportedExtension::TransferHook],
+    )
+    .await;
+    let hook_metas = hook_mint_f.transfer_hook_metas().await;
+    assert_eq!(hook_metas[0].pubkey, TEST_HOOK_ID);
+    let extra_metas_address = hook_metas[1].pubkey;
+
+    // Without the extra account metas, the bank's transfers couldn't be resolved
+    let extra_metas_account = test_f
+        .context
+        .borrow_mut()
+        .banks_client
+        .get_account(extra_metas_address)
+        .await?
+        .unwrap();
+    test_f
+        .context
+        .borrow_mut()
+        .set_account(&extra_metas_address, &AccountSharedData::default());
+
+    let res = test_f
+        .surroundfi_group
+        .try_lending_pool_add_bank(&hook_mint_f, *DEFAULT_USDC_TEST_BANK_CONFIG)
+        .await;
+    assert_custom_error!(res.unwrap_err(), SurroundfiError::InvalidTransferHook);
+
+    test_f
+        .context
+        .borrow_mut()
+        .set_account(&extra_metas_address, &extra_metas_account.into());
+
+    let hook_bank_f = test_f
+        .surroundfi_group
+        .try_lending_pool_add_bank(&hook_mint_f, *DEFAULT_USDC_TEST_BANK_CONFIG)
+        .await?;
+
+    // Deposits and withdrawals go through the hook
+    let user_mfi_account_f = test_f.create_surroundfi_account().await;
+    let user_token_account = hook_mint_f.create_token_account_and_mint_to(100).await;
+    user_mfi_account_f
+        .try_bank_deposit(user_token_account.key, &hook_bank_f, 100, None)
+        .await?;
+    user_mfi_account_f
+        .try_bank_withdraw(user_token_account.key, &hook_bank_f, 40, None)
+        .await?;
+    assert_eq!(user_token_account.balance().await, native!(40, "USDC"));
+
+    Ok(())
+}
+
+/// The UsdcT22 bank's mint has a transfer hook, with fees so that collecting them moves tokens
+async fn transfer_hook_fixture() -> TestFixture {
+    TestFixture::new_with_t22_extension(
+        Some(TestSettings {
+            banks: vec![
+                TestBankSetting {
+                    mint: BankMint::UsdcT22,
+                    config: Some(BankConfig {
+                        interest_rate_config: InterestRateConfig {
+                            optimal_utilization_rate: I80F48!(0.9).into(),
+                            plateau_interest_rate: I80F48!(1).into(),
+                            protocol_fixed_fee_apr: I80F48!(0.01).into(),
+                            insurance_fee_fixed_apr: I80F48!(0.01).into(),
+                            ..*DEFAULT_TEST_BANK_INTEREST_RATE_CONFIG
+                        },
+                        ..*DEFAULT_USDC_TEST_BANK_CONFIG
+                    }),
+                },
+                TestBankSetting {
+                    mint: BankMint::Sol,
+                    ..TestBankSetting::default()
+                },
+            ],
+            protocol_fees: false,
+        }),
+        &[SupportedExtension::TransferHook],
+    )
+    .await
+}
+
+#[tokio::test]
+async fn transfer_hook_bank_borrow_repay_fees_and_insurance() -> anyhow::Result<()> {
+    let test_f = transfer_hook_fixture().await;
+    let hook_bank_f = test_f.get_bank(&BankMint::UsdcT22);
+    let sol_bank_f = test_f.get_bank(&BankMint::Sol);
+
+    let lender_mfi_account_f = test_f.create_surroundfi_account().await;
+    let lender_token_account = test_f
+        .usdc_t22_mint
+        .create_token_account_and_mint_to(1_000)
+        .await;
+    lender_mfi_account_f
+        .try_bank_deposit(lender_token_account.key, hook_bank_f, 1_000, None)
+        .await?;
+
+    // Borrows and repays go through the hook
+    let borrower_mfi_account_f = test_f.create_surroundfi_account().await;
+    let borrower_token_account_sol = test_f
+        .sol_mint
+        .create_token_account_and_mint_to(1_000)
+        .await;
+    borrower_mfi_account_f
+        .try_bank_deposit(borrower_token_account_sol.key, sol_bank_f, 1_000, None)
+        .await?;
+    let borrower_token_account = test_f
+        .usdc_t22_mint
+        .create_token_account_and_mint_to(100)
+        .await;
+    borrower_mfi_account_f
+        .try_bank_borrow(borrower_token_account.key, hook_bank_f, 900)
+        .await?;
+    assert_eq!(
+        borrower_token_account.balance().await,
+        native!(1_000, "USDC")
+    );
+
+    test_f.advance_time(24 * 60 * 60).await;
+
+    borrower_mfi_account_f
+        .try_bank_repay(borrower_token_account.key, hook_bank_f, 0, Some(true))
+        .await?;
+    assert!(borrower_token_account.balance().await < native!(100, "USDC"));
+
+    // So do the fee and insurance vaults, both when collecting and withdrawing
+    TokenAccountFixture::new_from_ata(
+        test_f.context.clone(),
+        &test_f.usdc_t22_mint.key,
+        &test_f.surroundfi_group.fee_wallet,
+        &test_f.usdc_t22_mint.token_program,
+    )
+    .await;
+    test_f
+        .surroundfi_group
+        .try_collect_fees(hook_bank_f)
+        .await?;
+
+    let fee_vault_balance = hook_bank_f
+        .get_vault_token_account(BankVaultType::Fee)
+        .await
+        .balance()
+        .await;
+    let insurance_vault_balance = hook_bank_f
+        .get_vault_token_account(BankVaultType::Insurance)
+        .await
+        .balance()
+        .await;
+    assert!(fee_vault_balance > 0);
+    assert!(insurance_vault_balance > 0);
+
+    let fee_receiving_account = test_f.usdc_t22_mint.create_empty_token_account().await;
+    hook_bank_f
+        .try_withdraw_fees(&fee_receiving_account, fee_vault_balance)
+        .await?;
+    assert_eq!(fee_receiving_account.balance().await, fee_vault_balance);
+
+    let insurance_receiving_account = test_f.usdc_t22_mint.create_empty_token_account().await;
+    hook_bank_f
+        .try_withdraw_insurance(&insurance_receiving_account, insurance_vault_balance)
+        .await?;
+    assert_eq!(
+        insurance_receiving_account.balance().await,
+        insurance_vault_balance
+    );
+
+    // And insurance stakes
+    let cooldown = 3_600;
+    hook_bank_f
+        .try_configure_insurance_staking(0.5, cooldown, 600)
+        .await?;
+    hook_bank_f.try_init_insurance_stake().await?;
+    let staker_token_account = test_f
+        .usdc_t22_mint
+        .create_token_account_and_mint_to(10)
+        .await;
+    hook_bank_f
+        .try_insurance_stake(staker_token_account.key, 10)
+        .await?;
+    assert_eq!(staker_token_account.balance().await, 0);
+
+    hook_bank_f
+        .try_insurance_request_unstake(0, Some(true))
+        .await?;
+    test_f.advance_time(cooldown).await;
+    hook_bank_f
+        .try_insurance_unstake(staker_token_account.key)
+        .await?;
+    assert_eq_with_tolerance!(
+        staker_token_account.balance().await as i64,
+        native!(10, "USDC") as i64,
+        1
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn transfer_hook_emissions() -> anyhow::Result<()> {
+    let test_f = transfer_hook_fixture().await;
+    let sol_bank_f = test_f.get_bank(&BankMint::Sol);
+    let hook_mint_f = &test_f.usdc_t22_mint;
+
+    let now = MIN_EMISSIONS_START_TIME as i64;
+    let quarter_year = (SECONDS_PER_YEAR / 4.0) as i64;
+    test_f.set_time(now);
+
+    // Funding, at setup and when adding emissions, goes through the hook
+    let funding_account = hook_mint_f.create_token_account_and_mint_to(20).await;
+    sol_bank_f
+        .try_setup_emissions(
+            EMISSIONS_FLAG_LENDING_ACTIVE,
+            1_000_000,
+            native!(10, "USDC"),
+            hook_mint_f.key,
+            funding_account.key,
+            hook_mint_f.token_program,
+            Some(now),
+            Some(now + SECONDS_PER_YEAR as i64),
+        )
+        .await?;
+    sol_bank_f
+        .try_update_emissions(
+            None,
+            None,
+            Some((native!(10, "USDC"), funding_account.key)),
+            None,
+            None,
+            hook_mint_f.token_program,
+        )
+        .await?;
+    assert_eq!(funding_account.balance().await, 0);
+
+    // 10 SOL earn 10 emissions tokens a year
+    let mfi_account_f = test_f.create_surroundfi_account().await;
+    let user_token_account_sol = test_f.sol_mint.create_token_account_and_mint_to(10).await;
+    mfi_account_f
+        .try_bank_deposit(user_token_account_sol.key, sol_bank_f, 10, None)
+        .await?;
+
+    test_f.advance_time(quarter_year).await;
+
+    let emissions_account = hook_mint_f.create_empty_token_account().await;
+    mfi_account_f
+        .try_withdraw_emissions(sol_bank_f, &emissions_account)
+        .await?;
+    assert_eq_with_tolerance!(
+        emissions_account.balance().await as i64,
+        native!(2.5, "USDC", f64) as i64,
+        native!(0.1, "USDC", f64) as i64
+    );
+
+    // Permissionless withdrawals to the emissions destination too
+    let destination_wallet = Pubkey::new_unique();
+    mfi_account_f
+        .try_update_emissions_destination(destination_wallet)
+        .await?;
+    let destination_account = TokenAccountFixture::new_from_ata(
+        test_f.context.clone(),
+        &hook_mint_f.key,
+        &destination_wallet,
+        &hook_mint_f.token_program,
+    )
+    .await;
+
+    test_f.advance_time(quarter_year).await;
+
+    mfi_account_f
+        .try_withdraw_emissions_permissionless(sol_bank_f, &destination_account)
+        .await?;
+    assert_eq_with_tolerance!(
+        destination_account.balance().await as i64,
+        native!(2.5, "USDC", f64) as i64,
+        native!(0.1, "USDC", f64) as i64
+    );
+
+    // Once ended, what didn't accrue is reclaimed
+    test_f.advance_time(SECONDS_PER_YEAR as i64).await;
+
+    let reclaim_account = hook_mint_f.create_empty_token_account().await;
+    sol_bank_f.try_reclaim_emissions(&reclaim_account).await?;
+    assert_eq_with_tolerance!(
+        reclaim_account.balance().await as i64,
+        native!(10, "USDC") as i64,
+        native!(0.1, "USDC", f64) as i64
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn transfer_hook_emissions_streams() -> anyhow::Result<()> {
+    let test_f = transfer_hook_fixture().await;
+    let sol_bank_f = test_f.get_bank(&BankMint::Sol);
+    let hook_mint_f = &test_f.usdc_t22_mint;
+
+    let start_time = MIN_EMISSIONS_START_TIME as i64;
+    test_f.set_time(start_time);
+
+    sol_bank_f.try_init_emissions_program().await?;
+    sol_bank_f
+        .try_add_emissions_stream(
+            hook_mint_f,
+            EMISSIONS_FLAG_LENDING_ACTIVE,
+            1_000_000,
+            start_time,
+            0,
+        )
+        .await?;
+
+    // Funding and withdrawing the stream go through the hook
+    let funding_account = hook_mint_f.create_token_account_and_mint_to(10).await;
+    sol_bank_f
+        .try_fund_emissions_stream(0, hook_mint_f, funding_account.key, native!(10, "USDC"))
+        .await?;
+    assert_eq!(funding_account.balance().await, 0);
+
+    let mfi_account_f = test_f.create_surroundfi_account().await;
+    let user_token_account_sol = test_f.sol_mint.create_token_account_and_mint_to(10).await;
+    mfi_account_f
+        .try_bank_deposit(user_token_account_sol.key, sol_bank_f, 10, None)
+        .await?;
+    mfi_account_f.try_init_emissions_accrual(sol_bank_f).await?;
+    mfi_account_f.try_settle_emissions(sol_bank_f, None).await?;
+
+    test_f.advance_time((SECONDS_PER_YEAR / 2.0) as i64).await;
+
+    let emissions_account = hook_mint_f.create_empty_token_account().await;
+    mfi_account_f
+        .try_withdraw_stream_emissions(sol_bank_f, 0, &emissions_account)
+        .await?;
+    assert_eq_with_tolerance!(
+        emissions_account.balance().await as i64,
+        native!(5, "USDC") as i64,
+        native!(0.1, "USDC", f64) as i64
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn restricted_mint_extensions_need_group_permission() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(None).await;
+    test_f
+        .surroundfi_group
+        .try_configure_mint_extensions(0)
+        .await?;
+
+    // Benign extensions need no permission, and are recorded on the bank
+    let interest_bearing_mint_f = MintFixture::new_token_22(
+        test_f.context.clone(),
+        None,
+        None,
+        &[
+            SupportedExtension::InterestBearing,
+            SupportedExtension::MintCloseAuthority,
+        ],
+    )
+    .await;
+    let bank_f = test_f
+        .surroundfi_group
+        .try_lending_pool_add_bank(&interest_bearing_mint_f, *DEFAULT_USDC_TEST_BANK_CONFIG)
+        .await?;
+    assert_eq!(
+        bank_f.load().await.mint_extensions,
+        MINT_EXTENSION_INTEREST_BEARING | MINT_EXTENSION_MINT_CLOSE_AUTHORITY
+    );
+
+    // A permanent delegate could move the vaults' funds
+    let delegate_mint_f = MintFixture::new_token_22(
+        test_f.context.clone(),
+        None,
+        None,
+        &[SupportedExtension::PermanentDelegate],
+    )
+    .await;
+    let res = test_f
+        .surroundfi_group
+        .try_lending_pool_add_bank(&delegate_mint_f, *DEFAULT_USDC_TEST_BANK_CONFIG)
+        .await;
+    assert_custom_error!(res.unwrap_err(), SurroundfiError::MintExtensionNotPermitted);
+
+    // Only restricted extensions need a permission
+    let res = test_f
+        .surroundfi_group
+        .try_configure_mint_extensions(MINT_EXTENSION_TRANSFER_FEE)
+        .await;
+    assert_custom_error!(res.unwrap_err(), SurroundfiError::InvalidConfig);
+
+    test_f
+        .surroundfi_group
+        .try_configure_mint_extensions(MINT_EXTENSION_PERMANENT_DELEGATE)
+        .await?;
+    let bank_f = test_f
+        .surroundfi_group
+        .try_lending_pool_add_bank(&delegate_mint_f, *DEFAULT_USDC_TEST_BANK_CONFIG)
+        .await?;
+    assert_eq!(
+        bank_f.load().await.mint_extensions,
+        MINT_EXTENSION_PERMANENT_DELEGATE
+    );
+
+    // Every restricted extension of a mint must be permitted
+    let pyusd_mint_extensions = test_f.pyusd_mint.mint_extensions().await;
+    assert_ne!(
+        pyusd_mint_extensions & MINT_EXTENSION_CONFIDENTIAL_TRANSFER,
+        0
+    );
+    let res = test_f
+        .surroundfi_group
+        .try_lending_pool_add_bank(&test_f.pyusd_mint, *DEFAULT_PYUSD_TEST_BANK_CONFIG)
+        .await;
+    assert_custom_error!(res.unwrap_err(), SurroundfiError::MintExtensionNotPermitted);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn freeze_authority_and_pausable_mints_are_restricted() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(None).await;
+    test_f
+        .surroundfi_group
+        .try_configure_mint_extensions(0)
+        .await?;
+
+    // Even a Tokenkeg mint can freeze the vaults if it has a freeze authority
+    let freezable_mint_f = MintFixture::new(test_f.context.clone(), None, None).await;
+    freezable_mint_f
+        .set_freeze_authority(Some(Pubkey::new_unique()))
+        .await;
+    assert_eq!(
+        freezable_mint_f.mint_extensions().await,
+        MINT_EXTENSION_FREEZE_AUTHORITY
+    );
+
+    let res = test_f
+        .surroundfi_group
+        .try_lending_pool_add_bank(&freezable_mint_f, *DEFAULT_USDC_TEST_BANK_CONFIG)
+        .await;
+    assert_custom_error!(res.unwrap_err(), SurroundfiError::MintExtensionNotPermitted);
+
+    test_f
+        .surroundfi_group
+        .try_configure_mint_extensions(MINT_EXTENSION_FREEZE_AUTHORITY)
+        .await?;
+    let bank_f = test_f
+        .surroundfi_group
+        .try_lending_pool_add_bank(&freezable_mint_f, *DEFAULT_USDC_TEST_BANK_CONFIG)
+        .await?;
+    assert_eq!(
+        bank_f.load().await.mint_extensions,
+        MINT_EXTENSION_FREEZE_AUTHORITY
+    );
+
+    // Pausable mints are newer than the token program here, which can't open their vaults, so
+    // only their detection is checked
+    let pausable_mint_f = MintFixture::new_token_22(
+        test_f.context.clone(),
+        None,
+        None,
+        &[SupportedExtension::MintCloseAuthority],
+    )
+    .await;
+    pausable_mint_f
+        .append_raw_extension(PAUSABLE_CONFIG_EXTENSION_TYPE, &[0; 33])
+        .await;
+    assert_eq!(
+        pausable_mint_f.mint_extensions().await,
+        MINT_EXTENSION_MINT_CLOSE_AUTHORITY | MINT_EXTENSION_PAUSABLE
+    );
+    assert_ne!(MINT_EXTENSION_PAUSABLE & MINT_EXTENSIONS_RESTRICTED, 0);
+
+    // Other extension types this program doesn't know are flagged as unknown
+    pausable_mint_f.append_raw_extension(u16::MAX, &[]).await;
+    assert_eq!(
+        pausable_mint_f.mint_extensions().await,
+        MINT_EXTENSION_MINT_CLOSE_AUTHORITY | MINT_EXTENSION_PAUSABLE | MINT_EXTENSION_UNKNOWN
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn interest_bearing_collateral_priced_at_ui_amount() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+    let sol_bank_f = test_f.get_bank(&BankMint::Sol);
+
+    let interest_bearing_mint_f = MintFixture::new_token_22(
+        test_f.context.clone(),
+        None,
+        None,
+        &[SupportedExtension::InterestBearing],
+    )
+    .await;
+    let interest_bearing_bank_f = test_f
+        .surroundfi_group
+        .try_lending_pool_add_bank(&interest_bearing_mint_f, *DEFAULT_USDC_TEST_BANK_CONFIG)
+        .await?;
+    assert!(interest_bearing_bank_f.load().await.is_interest_bearing());
+
+    let lp_mfi_account_f = test_f.create_surroundfi_account().await;
+    let lp_token_account_sol = test_f.sol_mint.create_token_account_and_mint_to(100).await;
+    lp_mfi_account_f
+        .try_bank_deposit(lp_token_account_sol.key, sol_bank_f, 100, None)
+        .await?;
+
+    // 100% a year for a year: each raw token is now worth e (~2.718) UI tokens, at $1 each
+    interest_bearing_mint_f
+        .backdate_interest_rate(10_000, 31_556_736)
+        .await;
+
+    let user_mfi_account_f = test_f.create_surroundfi_account().await;
+    let user_token_account = interest_bearing_mint_f
+        .create_token_account_and_mint_to(100)
+        .await;
+    let user_token_account_sol = test_f.sol_mint.create_empty_token_account().await;
+    user_mfi_account_f
+        .try_bank_deposit(user_token_account.key, &interest_bearing_bank_f, 100, None)
+        .await?;
+    let user_mfi_account = user_mfi_account_f.load().await;
+    assert!(user_mfi_account.lending_account.balances[0].is_interest_bearing());
+
+    // ~$271.8 of collateral can back $250 of SOL, which the raw amount ($100) couldn't
+    user_mfi_account_f
+        .try_bank_borrow(user_token_account_sol.key, sol_bank_f, 25)
+        .await?;
+
+    // ...but not $300
+    let res = user_mfi_account_f
+        .try_bank_borrow(user_token_account_sol.key, sol_bank_f, 5)
+        .await;
+    assert_custom_error!(res.unwrap_err(), SurroundfiError::RiskEngineInitRejected);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn interest_bearing_liquidation_and_max_borrow() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+    let usdc_bank_f = test_f.get_bank(&BankMint::Usdc);
+
+    let asset_mint_f = MintFixture::new_token_22(
+        test_f.context.clone(),
+        None,
+        None,
+        &[SupportedExtension::InterestBearing],
+    )
+    .await;
+    let asset_bank_f = test_f
+        .surroundfi_group
+        .try_lending_pool_add_bank(
+            &asset_mint_f,
+            BankConfig {
+                asset_weight_init: I80F48!(0.5).into(),
+                asset_weight_maint: I80F48!(0.5).into(),
+                ..*DEFAULT_USDC_TEST_BANK_CONFIG
+            },
+        )
+        .await?;
+    let liab_mint_f = MintFixture::new_token_22(
+        test_f.context.clone(),
+        None,
+        None,
+        &[SupportedExtension::InterestBearing],
+    )
+    .await;
+    let liab_bank_f = test_f
+        .surroundfi_group
+        .try_lending_pool_add_bank(&liab_mint_f, *DEFAULT_USDC_TEST_BANK_CONFIG)
+        .await?;
+
+    // Each raw asset token is worth e (~2.718) UI tokens, at $1 each
+    asset_mint_f
+        .backdate_interest_rate(10_000, 31_556_736)
+        .await;
+
+    let liquidator_mfi_account_f = test_f.create_surroundfi_account().await;
+    let liquidator_token_account = liab_mint_f.create_token_account_and_mint_to(1_000).await;
+    liquidator_mfi_account_f
+        .try_bank_deposit(liquidator_token_account.key, &liab_bank_f, 1_000, None)
+        .await?;
+
+    // ~$135.9 of weighted collateral backs $120 of liabilities
+    let borrower_mfi_account_f = test_f.create_surroundfi_account().await;
+    let borrower_token_account = asset_mint_f.create_token_account_and_mint_to(100).await;
+    let borrower_liab_token_account = liab_mint_f.create_empty_token_account().await;
+    borrower_mfi_account_f
+        .try_bank_deposit(borrower_token_account.key, &asset_bank_f, 100, None)
+        .await?;
+    borrower_mfi_account_f
+        .try_bank_borrow(borrower_liab_token_account.key, &liab_bank_f, 120)
+        .await?;
+
+    let res = liquidator_mfi_account_f
+        .try_liquidate(&borrower_mfi_account_f, &asset_bank_f, 2, &liab_bank_f)
+        .await;
+    assert_custom_error!(res.unwrap_err(), SurroundfiError::HealthyAccount);
+
+    // The liabilities grow to ~$146.6 once each raw liability token is worth e^0.2 UI tokens
+    liab_mint_f.backdate_interest_rate(2_000, 31_556_736).await;
+    let asset_scale = std::f64::consts::E;
+    let liab_scale = 0.2f64.exp();
+
+    liquidator_mfi_account_f
+        .try_liquidate(&borrower_mfi_account_f, &asset_bank_f, 2, &liab_bank_f)
+        .await?;
+
+    let asset_bank = asset_bank_f.load().await;
+    let liab_bank = liab_bank_f.load().await;
+    let liquidator_ma = liquidator_mfi_account_f.load().await;
+    let borrower_ma = borrower_mfi_account_f.load().await;
+
+    // The liquidator gets the 2 raw asset tokens, worth ~$5.44...
+    assert_eq!(
+        asset_bank
+            .get_asset_amount(
+                liquidator_ma.lending_account.balances[1]
+                    .asset_shares
+                    .into()
+            )
+            .unwrap(),
+        I80F48::from(native!(2, "USDC"))
+    );
+
+    // ...and pays for them in raw liability tokens, at a 2.5% discount
+    assert_eq_noise!(
+        liab_bank
+            .get_asset_amount(
+                liquidator_ma.lending_account.balances[0]
+                    .asset_shares
+                    .into()
+            )
+            .unwrap(),
+        I80F48::from(native!(
+            1_000. - 2. * asset_scale * 0.975 / liab_scale,
+            "USDC",
+            f64
+        )),
+        I80F48::from(native!(0.05, "USDC", f64))
+    );
+
+    // The liquidatee's liability is paid off net of the liquidator and insurance fees
+    assert_eq_noise!(
+        liab_bank
+            .get_liability_amount(
+                borrower_ma.lending_account.balances[1]
+                    .liability_shares
+                    .into()
+            )
+            .unwrap(),
+        I80F48::from(native!(
+            120. - 2. * asset_scale * 0.95 / liab_scale,
+            "USDC",
+            f64
+        )),
+        I80F48::from(native!(0.05, "USDC", f64))
+    );
+
+    let insurance_vault = liab_bank_f
+        .get_vault_token_account(BankVaultType::Insurance)
+        .await;
+    assert_eq_noise!(
+        insurance_vault.balance().await as i64,
+        native!(2. * asset_scale * 0.025 / liab_scale, "USDC", f64) as i64,
+        native!(0.01, "USDC", f64) as i64
+    );
+
+    // $100 of collateral can borrow ~81.9 raw liability tokens, worth $100
+    let user_mfi_account_f = test_f.create_surroundfi_account().await;
+    let user_token_account_usdc = test_f.usdc_mint.create_token_account_and_mint_to(100).await;
+    user_mfi_account_f
+        .try_bank_deposit(user_token_account_usdc.key, usdc_bank_f, 100, None)
+        .await?;
+    let health = user_mfi_account_f
+        .try_simulate_health(vec![SimulatedAction {
+            bank: liab_bank_f.key,
+            action_type: SimulatedActionType::Borrow,
+            amount: 0,
+        }])
+        .await?;
+    assert_eq_with_tolerance!(
+        health.balances[1].max_amount as i64,
+        native!(100. / liab_scale, "USDC", f64) as i64,
+        native!(0.5, "USDC", f64) as i64
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn refresh_mint_extensions_of_existing_bank() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+    let sol_bank_f = test_f.get_bank(&BankMint::Sol);
+
+    let interest_bearing_mint_f = MintFixture::new_token_22(
+        test_f.context.clone(),
+        None,
+        None,
+        &[SupportedExtension::InterestBearing],
+    )
+    .await;
+    let interest_bearing_bank_f = test_f
+        .surroundfi_group
+        .try_lending_pool_add_bank(&interest_bearing_mint_f, *DEFAULT_USDC_TEST_BANK_CONFIG)
+        .await?;
+
+    let lp_mfi_account_f = test_f.create_surroundfi_account().await;
+    let lp_token_account_sol = test_f.sol_mint.create_token_account_and_mint_to(100).await;
+    lp_mfi_account_f
+        .try_bank_deposit(lp_token_account_sol.key, sol_bank_f, 100, None)
+        .await?;
+
+    interest_bearing_mint_f
+        .backdate_interest_rate(10_000, 31_556_736)
+        .await;
+
+    let user_mfi_account_f = test_f.create_surroundfi_account().await;
+    let user_token_account = interest_bearing_mint_f
+        .create_token_account_and_mint_to(100)
+        .await;
+    let user_token_account_sol = test_f.sol_mint.create_empty_token_account().await;
+    user_mfi_account_f
+        .try_bank_deposit(user_token_account.key, &interest_bearing_bank_f, 100, None)
+        .await?;
+
+    // As if the bank and the balance were created before interest-bearing mints were tracked
+    interest_bearing_bank_f.set_mint_extensions(0).await;
+    let mut user_mfi_account = user_mfi_account_f.load().await;
+    user_mfi_account.lending_account.balances[0].bank_interest_bearing = 0;
+    user_mfi_account_f.set_account(&user_mfi_account).await?;
+
+    // The raw amount ($100) can't back $150 of SOL
+    let res = user_mfi_account_f
+        .try_bank_borrow(user_token_account_sol.key, sol_bank_f, 15)
+        .await;
+    assert_custom_error!(res.unwrap_err(), SurroundfiError::RiskEngineInitRejected);
+
+    // Refreshing the bank alone leaves existing balances priced as they were
+    test_f
+        .surroundfi_group
+        .try_refresh_mint_extensions(&interest_bearing_bank_f, &[])
+        .await?;
+    assert_eq!(
+        interest_bearing_bank_f.load().await.mint_extensions,
+        MINT_EXTENSION_INTEREST_BEARING
+    );
+    let res = user_mfi_account_f
+        .try_bank_borrow(user_token_account_sol.key, sol_bank_f, 15)
+        .await;
+    assert_custom_error!(res.unwrap_err(), SurroundfiError::RiskEngineInitRejected);
+
+    // Synced balances are priced at the UI amount (~$271.8)
+    test_f
+        .surroundfi_group
+        .try_refresh_mint_extensions(&interest_bearing_bank_f, &[user_mfi_account_f.key])
+        .await?;
+    let user_mfi_account = user_mfi_account_f.load().await;
+    assert!(user_mfi_account.lending_account.balances[0].is_interest_bearing());
+    user_mfi_account_f
+        .try_bank_borrow(user_token_account_sol.key, sol_bank_f, 15)
+        .await?;
+
+    Ok(())
+}
@@ -0,0 +1,349 @@
+use fixtures::{
+    assert_custom_error, bank::BankFixture, prelude::*, surroundfi_account::SurroundfiAccountFixture,
+};
+use fixed::types::I80F48;
+use surroundfi::{
+    prelude::*,
+    state::{
+        surroundfi_account::MAX_LENDING_ACCOUNT_BALANCES,
+        surroundfi_account_extension::MAX_EXTENSION_BALANCES,
+        surroundfi_group::{AssetTagMatrix, BankConfigOpt},
+    },
+};
+use pretty_assertions::assert_eq;
+use solana_program_test::*;
+use solana_sdk::{
+    compute_budget::ComputeBudgetInstruction, pubkey::Pubkey, signer::Signer,
+    transaction::Transaction,
+};
+
+/// 8 SOL-equivalent banks from the test settings, plus `extra` more created with seeds
+async fn setup_sol_eq_banks(test_f: &TestFixture, extra: u64) -> anyhow::Result<Vec<BankFixture>> {
+    let mut banks = vec![
+        test_f.get_bank(&BankMint::SolEquivalent).clone(),
+        test_f.get_bank(&BankMint::SolEquivalent1).clone(),
+        test_f.get_bank(&BankMint::SolEquivalent2).clone(),
+        test_f.get_bank(&BankMint::SolEquivalent3).clone(),
+        test_f.get_bank(&BankMint::SolEquivalent4).clone(),
+        test_f.get_bank(&BankMint::SolEquivalent5).clone(),
+        test_f.get_bank(&BankMint::SolEquivalent6).clone(),
+        test_f.get_bank(&BankMint::SolEquivalent7).clone(),
+    ];
+
+    for seed in 0..extra {
+        let bank_f = test_f
+            .surroundfi_group
+            .try_lending_pool_add_bank_with_seed(
+                &test_f.sol_equivalent_mint,
+                *DEFAULT_SOL_EQUIVALENT_TEST_BANK_CONFIG,
+                seed,
+            )
+            .await?;
+        banks.push(bank_f);
+    }
+
+    Ok(banks)
+}
+
+/// Simulates a borrow and returns the compute units it consumed
+async fn simulate_borrow_compute_units(
+    test_f: &TestFixture,
+    account_f: &SurroundfiAccountFixture,
+    destination_account: Pubkey,
+    bank_f: &BankFixture,
+    ui_amount: f64,
+) -> u64 {
+    let borrow_ix = account_f
+        .make_bank_borrow_ix(destination_account, bank_f, ui_amount)
+        .await;
+
+    let mut ctx = test_f.context.borrow_mut();
+    let tx = Transaction::new_signed_with_payer(
+        &[
+            ComputeBudgetInstruction::set_compute_unit_limit(1_400_000),
+            borrow_ix,
+        ],
+        Some(&ctx.payer.pubkey()),
+        &[&ctx.payer],
+        ctx.last_blockhash,
+    );
+
+    let res = ctx.banks_client.simulate_transaction(tx).await.unwrap();
+    assert!(res.result.unwrap().is_ok());
+    res.simulation_details.unwrap().units_consumed
+}
+
+#[tokio::test]
+async fn account_extension_more_than_16_balances() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings::many_banks_10())).await;
+    let usdc_bank_f = test_f.get_bank(&BankMint::Usdc);
+    let sol_eq_banks = setup_sol_eq_banks(&test_f, 9).await?;
+
+    let lender_mfi_account_f = test_f.create_surroundfi_account().await;
+    let lender_token_account_usdc = test_f
+        .usdc_mint
+        .create_token_account_and_mint_to(1_000)
+        .await;
+    lender_mfi_account_f
+        .try_bank_deposit(lender_token_account_usdc.key, usdc_bank_f, 1_000, None)
+        .await?;
+
+    let user_mfi_account_f = test_f.create_surroundfi_account().await;
+    let user_token_account_sol_eq = test_f
+        .sol_equivalent_mint
+        .create_token_account_and_mint_to(100)
+        .await;
+    let user_token_account_usdc = test_f.usdc_mint.create_empty_token_account().await;
+
+    // Fill every slot of the lending account
+    for bank_f in sol_eq_banks.iter().take(MAX_LENDING_ACCOUNT_BALANCES) {
+        user_mfi_account_f
+            .try_bank_deposit(user_token_account_sol_eq.key, bank_f, 1, None)
+            .await?;
+    }
+
+    // No extension yet, the next position doesn't fit
+    let overflow_bank_f = &sol_eq_banks[MAX_LENDING_ACCOUNT_BALANCES];
+    let res = user_mfi_account_f
+        .try_bank_deposit(user_token_account_sol_eq.key, overflow_bank_f, 1, None)
+        .await;
+    assert!(res.is_err());
+    assert_custom_error!(
+        res.unwrap_err(),
+        SurroundfiError::LendingAccountBalanceSlotsFull
+    );
+
+    user_mfi_account_f.try_init_extension().await?;
+
+    let surroundfi_account = user_mfi_account_f.load().await;
+    assert_eq!(
+        surroundfi_account.extension,
+        user_mfi_account_f.get_extension_address()
+    );
+    let extension = user_mfi_account_f.load_extension().await;
+    assert_eq!(extension.surroundfi_account, user_mfi_account_f.key);
+    assert_eq!(extension.balances.len(), MAX_EXTENSION_BALANCES);
+
+    // A second extension can't be created
+    let res = user_mfi_account_f.try_init_extension().await;
+    assert!(res.is_err());
+
+    // New positions spill over into the extension...
+    user_mfi_account_f
+        .try_bank_deposit(user_token_account_sol_eq.key, overflow_bank_f, 1, None)
+        .await?;
+    user_mfi_account_f
+        .try_bank_borrow(user_token_account_usdc.key, usdc_bank_f, 10)
+        .await?;
+
+    let extension = user_mfi_account_f.load_extension().await;
+    let extension_banks = extension
+        .get_active_balances_iter()
+        .map(|balance| balance.bank_pk)
+        .collect::<Vec<_>>();
+    assert_eq!(extension_banks, vec![overflow_bank_f.key, usdc_bank_f.key]);
+
+    // Their prices don't fit in the account's health cache, the extension holds them instead
+    for price in extension.health_cache_prices.iter().take(2) {
+        assert!(I80F48::from(*price) > I80F48::ZERO);
+    }
+    assert_eq!(I80F48::from(extension.health_cache_prices[2]), I80F48::ZERO);
+    // The value of every balance is recorded there too, those of the account first
+    let values = &extension.health_cache_balance_values;
+    for balance_values in values.iter().take(MAX_LENDING_ACCOUNT_BALANCES + 1) {
+        assert!(I80F48::from(balance_values.asset_value_maint) > I80F48::ZERO);
+    }
+    let usdc_values = values[MAX_LENDING_ACCOUNT_BALANCES + 1];
+    assert!(I80F48::from(usdc_values.liability_value_maint) > I80F48::ZERO);
+    assert_eq!(I80F48::from(usdc_values.asset_value_maint), I80F48::ZERO);
+
+    // ...and the risk engine counts them: the account can't borrow past its total collateral
+    let res = user_mfi_account_f
+        .try_bank_borrow(user_token_account_usdc.key, usdc_bank_f, 500)
+        .await;
+    assert!(res.is_err());
+    assert_custom_error!(res.unwrap_err(), SurroundfiError::RiskEngineInitRejected);
+
+    // Positions in the extension can be repaid and closed as usual
+    let user_token_account_usdc_repay = test_f.usdc_mint.create_token_account_and_mint_to(20).await;
+    user_mfi_account_f
+        .try_bank_repay(
+            user_token_account_usdc_repay.key,
+            usdc_bank_f,
+            0,
+            Some(true),
+        )
+        .await?;
+    user_mfi_account_f
+        .try_bank_withdraw(user_token_account_sol_eq.key, overflow_bank_f, 0, Some(true))
+        .await?;
+
+    let extension = user_mfi_account_f.load_extension().await;
+    assert_eq!(extension.get_active_balances_iter().count(), 0);
+
+    // An empty extension can be closed and unlinked
+    user_mfi_account_f.try_close_extension().await?;
+    let surroundfi_account = user_mfi_account_f.load().await;
+    assert!(!surroundfi_account.has_extension());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn account_extension_close_with_active_balances_fails() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings::many_banks_10())).await;
+    let sol_eq_banks = setup_sol_eq_banks(&test_f, 9).await?;
+
+    let user_mfi_account_f = test_f.create_surroundfi_account().await;
+    let user_token_account_sol_eq = test_f
+        .sol_equivalent_mint
+        .create_token_account_and_mint_to(100)
+        .await;
+
+    user_mfi_account_f.try_init_extension().await?;
+    for bank_f in sol_eq_banks.iter() {
+        user_mfi_account_f
+            .try_bank_deposit(user_token_account_sol_eq.key, bank_f, 1, None)
+            .await?;
+    }
+
+    let res = user_mfi_account_f.try_close_extension().await;
+    assert!(res.is_err());
+    assert_custom_error!(res.unwrap_err(), SurroundfiError::IllegalAction);
+
+    // The account itself can't be closed while it has an extension
+    let res = user_mfi_account_f.try_close_account(0).await;
+    assert!(res.is_err());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn account_extension_balances_count_for_asset_tags() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings::many_banks_10())).await;
+    let usdc_bank_f = test_f.get_bank(&BankMint::Usdc);
+    let sol_eq_banks = setup_sol_eq_banks(&test_f, 1).await?;
+
+    // USDC is tagged as an RWA token, which can't be held with anything else
+    const ASSET_TAG_RWA: u8 = 6;
+    let mut asset_tag_matrix = AssetTagMatrix::DEFAULT;
+    asset_tag_matrix.coexist[ASSET_TAG_RWA as usize] = 1 << ASSET_TAG_RWA;
+    test_f
+        .surroundfi_group
+        .try_configure_asset_tag_matrix(asset_tag_matrix)
+        .await?;
+    usdc_bank_f
+        .update_config(
+            BankConfigOpt {
+                asset_tag: Some(ASSET_TAG_RWA),
+                ..BankConfigOpt::default()
+            },
+            None,
+        )
+        .await?;
+
+    let user_mfi_account_f = test_f.create_surroundfi_account().await;
+    let user_token_account_sol_eq = test_f
+        .sol_equivalent_mint
+        .create_token_account_and_mint_to(100)
+        .await;
+
+    // Fill the lending account so the next position goes to the extension...
+    for bank_f in sol_eq_banks.iter().take(MAX_LENDING_ACCOUNT_BALANCES) {
+        user_mfi_account_f
+            .try_bank_deposit(user_token_account_sol_eq.key, bank_f, 1, None)
+            .await?;
+    }
+    user_mfi_account_f.try_init_extension().await?;
+    let extension_bank_f = &sol_eq_banks[MAX_LENDING_ACCOUNT_BALANCES];
+    user_mfi_account_f
+        .try_bank_deposit(user_token_account_sol_eq.key, extension_bank_f, 1, None)
+        .await?;
+
+    // ...then empty the lending account, leaving the only position in the extension
+    for bank_f in sol_eq_banks.iter().take(MAX_LENDING_ACCOUNT_BALANCES) {
+        user_mfi_account_f
+            .try_bank_withdraw(user_token_account_sol_eq.key, bank_f, 0, Some(true))
+            .await?;
+    }
+    let surroundfi_account = user_mfi_account_f.load().await;
+    assert!(surroundfi_account
+        .lending_account
+        .balances
+        .iter()
+        .all(|balance| !balance.is_active()));
+
+    // The default asset in the extension still can't be held with the RWA token
+    let user_token_account_usdc = test_f.usdc_mint.create_token_account_and_mint_to(100).await;
+    let res = user_mfi_account_f
+        .try_bank_deposit(user_token_account_usdc.key, usdc_bank_f, 100, None)
+        .await;
+    assert!(res.is_err());
+    assert_custom_error!(res.unwrap_err(), SurroundfiError::AssetTagMismatch);
+
+    Ok(())
+}
+
+/// Compares the compute units used by a borrow (the most expensive health check) for an account
+/// using all 16 slots of the lending account against one also using an extension.
+#[tokio::test]
+async fn account_extension_compute_units() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings::many_banks_10())).await;
+    let usdc_bank_f = test_f.get_bank(&BankMint::Usdc);
+    let sol_eq_banks = setup_sol_eq_banks(&test_f, 15).await?;
+
+    let lender_mfi_account_f = test_f.create_surroundfi_account().await;
+    let lender_token_account_usdc = test_f
+        .usdc_mint
+        .create_token_account_and_mint_to(1_000)
+        .await;
+    lender_mfi_account_f
+        .try_bank_deposit(lender_token_account_usdc.key, usdc_bank_f, 1_000, None)
+        .await?;
+
+    let user_token_account_sol_eq = test_f
+        .sol_equivalent_mint
+        .create_token_account_and_mint_to(100)
+        .await;
+    let user_token_account_usdc = test_f.usdc_mint.create_empty_token_account().await;
+
+    // 15 deposits + the borrow = 16 balances, no extension
+    let main_only_account_f = test_f.create_surroundfi_account().await;
+    for bank_f in sol_eq_banks.iter().take(MAX_LENDING_ACCOUNT_BALANCES - 1) {
+        main_only_account_f
+            .try_bank_deposit(user_token_account_sol_eq.key, bank_f, 1, None)
+            .await?;
+    }
+    let main_only_cu = simulate_borrow_compute_units(
+        &test_f,
+        &main_only_account_f,
+        user_token_account_usdc.key,
+        usdc_bank_f,
+        1.,
+    )
+    .await;
+
+    // 23 deposits + the borrow = 24 balances, 8 of them in the extension
+    let extended_account_f = test_f.create_surroundfi_account().await;
+    extended_account_f.try_init_extension().await?;
+    for bank_f in sol_eq_banks.iter().take(MAX_LENDING_ACCOUNT_BALANCES + 7) {
+        extended_account_f
+            .try_bank_deposit(user_token_account_sol_eq.key, bank_f, 1, None)
+            .await?;
+    }
+    let extended_cu = simulate_borrow_compute_units(
+        &test_f,
+        &extended_account_f,
+        user_token_account_usdc.key,
+        usdc_bank_f,
+        1.,
+    )
+    .await;
+
+    // Half again as many balances must cost well under twice as much
+    assert!(extended_cu > main_only_cu);
+    assert!(extended_cu < main_only_cu * 2);
+    assert!(extended_cu < 1_400_000);
+
+    Ok(())
+}
@@ -6,10 +6,14 @@ use fixtures::{assert_custom_error, native, prelude::*, ui_to_native};
 use surroundfi::{
     assert_eq_with_tolerance,
     prelude::*,
-    state::surroundfi_group::{BankConfigOpt, BankVaultType},
+    state::{
+        referrer_fees::ReferrerFees,
+        surroundfi_group::{BankConfigOpt, BankVaultType, InterestRateConfigOpt},
+    },
 };
 use pretty_assertions::assert_eq;
 use solana_program_test::*;
+use solana_sdk::{signature::Keypair, signer::Signer};
 use test_case::test_case;
 
 #[test_case(100., 9., BankMint::Usdc, BankMint::Sol)]
@@ -75,6 +79,8 @@ async fn surroundfi_account_borrow_success(
             None,
         )
         .await?;
+    // Records the value of each balance, see the health cache checks below
+    user_mfi_account_f.try_init_extension().await?;
 
     // -------------------------------------------------------------------------
     // Test
@@ -190,15 +196,44 @@ async fn surroundfi_account_borrow_success(
     assert!(asset_value > deposit_amount * collateral_price_roughly * disc);
     assert!(liab_value > borrow_amount * liablility_price_roughly * disc);
 
+    // Maintenance and equity components are recorded alongside the initial ones
+    let asset_value_maint: I80F48 = health_cache.asset_value_maint.into();
+    let asset_value_maint: f64 = asset_value_maint.to_num();
+    let liab_value_maint: I80F48 = health_cache.liability_value_maint.into();
+    let liab_value_maint: f64 = liab_value_maint.to_num();
+    assert!(asset_value_maint > deposit_amount * collateral_price_roughly * disc);
+    assert!(liab_value_maint > borrow_amount * liablility_price_roughly * disc);
+
+    let asset_value_equity: I80F48 = health_cache.asset_value_equity.into();
+    let asset_value_equity: f64 = asset_value_equity.to_num();
+    let liab_value_equity: I80F48 = health_cache.liability_value_equity.into();
+    let liab_value_equity: f64 = liab_value_equity.to_num();
+    assert!(asset_value_equity > deposit_amount * collateral_price_roughly * disc);
+    assert!(liab_value_equity > borrow_amount * liablility_price_roughly * disc);
+
+    // A single collateral: the account is liquidatable once its price falls by the margin left
+    let liquidation_price: I80F48 = health_cache.liquidation_price.into();
+    let liquidation_price: f64 = liquidation_price.to_num();
+    assert!(liquidation_price > 0.);
+    assert!(liquidation_price < collateral_price_roughly / disc);
+
+    let extension = user_mfi_account_f.load_extension().await;
     for (i, bal) in surroundfi_account.lending_account.balances.iter().enumerate() {
         let shares: I80F48 = bal.asset_shares.into();
         if bal.is_active() {
             let price: I80F48 = health_cache.prices[i].into();
             let price: f64 = price.to_num();
+            let values = extension.health_cache_balance_values[i];
+            let asset_value_maint: I80F48 = values.asset_value_maint.into();
+            let liab_value_maint: I80F48 = values.liability_value_maint.into();
             if shares != I80F48::ZERO {
                 assert!(price >= (collateral_price_roughly * disc));
+                assert!(asset_value_maint > I80F48::ZERO);
+                assert_eq!(liab_value_maint, I80F48::ZERO);
             } else {
                 assert!(price >= (liablility_price_roughly * disc));
+                assert_eq!(asset_value_maint, I80F48::ZERO);
+                assert!(liab_value_maint > I80F48::ZERO);
             }
         }
     }
@@ -485,4 +520,206 @@ async fn isolated_borrows() -> anyhow::Result<()> {
     assert_custom_error!(res.unwrap_err(), SurroundfiError::IsolatedAccountIllegalState);
 
     Ok(())
-}
\ No newline at end of file
+}
+#[tokio::test]
+async fn surroundfi_account_borrow_with_referrer() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+
+    let usdc_bank = test_f.get_bank(&BankMint::Usdc);
+    let sol_bank = test_f.get_bank(&BankMint::Sol);
+    sol_bank
+        .update_config(
+            BankConfigOpt {
+                interest_rate_config: Some(InterestRateConfigOpt {
+                    protocol_origination_fee: Some(I80F48::from_num(0.01).into()),
+                    referrer_fee_share: Some(I80F48::from_num(0.5).into()),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+            None,
+        )
+        .await?;
+
+    let referrer = Keypair::new();
+    test_f
+        .surroundfi_group
+        .try_init_referrer_fees(&referrer.pubkey())
+        .await?;
+    let referrer_fees_key = test_f
+        .surroundfi_group
+        .get_referrer_fees_address(&referrer.pubkey());
+
+    // Fund SOL lender
+    let lender_mfi_account_f = test_f.create_surroundfi_account().await;
+    let lender_token_account_sol = test_f.sol_mint.create_token_account_and_mint_to(100).await;
+    lender_mfi_account_f
+        .try_bank_deposit(lender_token_account_sol.key, sol_bank, 100, None)
+        .await?;
+
+    // Referred deposit and borrow
+    let borrower_mfi_account_f = test_f.create_surroundfi_account().await;
+    let borrower_token_account_usdc = test_f
+        .usdc_mint
+        .create_token_account_and_mint_to(1_000)
+        .await;
+    let borrower_token_account_sol = test_f.sol_mint.create_empty_token_account().await;
+    borrower_mfi_account_f
+        .try_bank_deposit_with_referrer(
+            borrower_token_account_usdc.key,
+            usdc_bank,
+            1_000,
+            referrer_fees_key,
+        )
+        .await?;
+
+    let group_fees_before: I80F48 = sol_bank
+        .load()
+        .await
+        .collected_group_fees_outstanding
+        .into();
+    borrower_mfi_account_f
+        .try_bank_borrow_with_referrer(
+            borrower_token_account_sol.key,
+            sol_bank,
+            2,
+            referrer_fees_key,
+        )
+        .await?;
+
+    let program_fee_rate: I80F48 = test_f
+        .surroundfi_group
+        .load()
+        .await
+        .fee_state_cache
+        .program_fee_rate
+        .into();
+    let origination_fee = I80F48::from_num(native!(2, "SOL")) * I80F48::from_num(0.01);
+    let group_fee = origination_fee - origination_fee * program_fee_rate;
+    let referrer_fee = group_fee * I80F48::from_num(0.5);
+
+    // The referrer takes its share out of the group's part of the origination fee
+    let group_fees_after: I80F48 = sol_bank
+        .load()
+        .await
+        .collected_group_fees_outstanding
+        .into();
+    assert_eq_with_tolerance!(
+        group_fees_after - group_fees_before,
+        group_fee - referrer_fee,
+        1
+    );
+
+    let referrer_fees: ReferrerFees = test_f.load_and_deserialize(&referrer_fees_key).await;
+    let sol_balance = referrer_fees.get_balance(&sol_bank.key).unwrap();
+    assert_eq_with_tolerance!(I80F48::from(sol_balance.outstanding), referrer_fee, 1);
+    assert_eq_with_tolerance!(I80F48::from(sol_balance.lifetime_fees), referrer_fee, 1);
+    assert_eq!(
+        sol_balance.referred_borrows,
+        native!(2, "SOL") + origination_fee.to_num::<u64>()
+    );
+    let usdc_balance = referrer_fees.get_balance(&usdc_bank.key).unwrap();
+    assert_eq!(usdc_balance.referred_deposits, native!(1_000, "USDC"));
+    assert_eq!(I80F48::from(usdc_balance.outstanding), I80F48::ZERO);
+
+    // Only the referrer can claim
+    let referrer_token_account_sol = test_f.sol_mint.create_empty_token_account().await;
+    let res = test_f
+        .surroundfi_group
+        .try_claim_referrer_fees(&Keypair::new(), sol_bank, referrer_token_account_sol.key)
+        .await;
+    assert!(res.is_err());
+
+    test_f
+        .surroundfi_group
+        .try_claim_referrer_fees(&referrer, sol_bank, referrer_token_account_sol.key)
+        .await?;
+    assert_eq!(
+        referrer_token_account_sol.balance().await,
+        referrer_fee.to_num::<u64>()
+    );
+
+    // Only the fraction of a token is left
+    let referrer_fees: ReferrerFees = test_f.load_and_deserialize(&referrer_fees_key).await;
+    let outstanding: I80F48 = referrer_fees
+        .get_balance(&sol_bank.key)
+        .unwrap()
+        .outstanding
+        .into();
+    assert!(outstanding < I80F48::ONE);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn surroundfi_account_borrow_failure_self_referral() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+
+    let usdc_bank = test_f.get_bank(&BankMint::Usdc);
+    let sol_bank = test_f.get_bank(&BankMint::Sol);
+    sol_bank
+        .update_config(
+            BankConfigOpt {
+                interest_rate_config: Some(InterestRateConfigOpt {
+                    protocol_origination_fee: Some(I80F48::from_num(0.01).into()),
+                    referrer_fee_share: Some(I80F48::from_num(0.5).into()),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+            None,
+        )
+        .await?;
+
+    // The account's authority refers itself
+    test_f
+        .surroundfi_group
+        .try_init_referrer_fees(&test_f.payer())
+        .await?;
+    let referrer_fees_key = test_f
+        .surroundfi_group
+        .get_referrer_fees_address(&test_f.payer());
+
+    // Fund SOL lender
+    let lender_mfi_account_f = test_f.create_surroundfi_account().await;
+    let lender_token_account_sol = test_f.sol_mint.create_token_account_and_mint_to(100).await;
+    lender_mfi_account_f
+        .try_bank_deposit(lender_token_account_sol.key, sol_bank, 100, None)
+        .await?;
+
+    let borrower_mfi_account_f = test_f.create_surroundfi_account().await;
+    let borrower_token_account_usdc = test_f
+        .usdc_mint
+        .create_token_account_and_mint_to(1_000)
+        .await;
+    let borrower_token_account_sol = test_f.sol_mint.create_empty_token_account().await;
+
+    let res = borrower_mfi_account_f
+        .try_bank_deposit_with_referrer(
+            borrower_token_account_usdc.key,
+            usdc_bank,
+            1_000,
+            referrer_fees_key,
+        )
+        .await;
+    assert_custom_error!(res.unwrap_err(), SurroundfiError::SelfReferral);
+
+    borrower_mfi_account_f
+        .try_bank_deposit(borrower_token_account_usdc.key, usdc_bank, 1_000, None)
+        .await?;
+
+    let res = borrower_mfi_account_f
+        .try_bank_borrow_with_referrer(
+            borrower_token_account_sol.key,
+            sol_bank,
+            2,
+            referrer_fees_key,
+        )
+        .await;
+    assert_custom_error!(res.unwrap_err(), SurroundfiError::SelfReferral);
+
+    let referrer_fees: ReferrerFees = test_f.load_and_deserialize(&referrer_fees_key).await;
+    assert!(referrer_fees.get_balance(&sol_bank.key).is_none());
+
+    Ok(())
+}
@@ -1,10 +1,16 @@
 use anchor_lang::{InstructionData, ToAccountMetas};
-use fixtures::test::TestFixture;
-use surroundfi::state::surroundfi_account::SurroundfiAccount;
+use fixtures::{
+    assert_custom_error, surroundfi_account::SurroundfiAccountFixture, test::TestFixture,
+};
+use surroundfi::{
+    constants::SURROUNDFI_ACCOUNT_SEED,
+    errors::SurroundfiError,
+    state::surroundfi_account::{SurroundfiAccount, ACCOUNT_TRANSFER_AUTHORITY_ALLOWED},
+};
 use solana_program_test::tokio;
 use solana_sdk::{
-    instruction::Instruction, signature::Keypair, signer::Signer, system_program,
-    transaction::Transaction,
+    compute_budget::ComputeBudgetInstruction, instruction::Instruction, pubkey::Pubkey,
+    signature::Keypair, signer::Signer, system_program, transaction::Transaction,
 };
 
 #[tokio::test]
@@ -54,4 +60,230 @@ async fn surroundfi_account_create_success() -> anyhow::Result<()> {
         .all(|bank| !bank.is_active()));
 
     Ok(())
-}
\ No newline at end of file
+}
+
+#[tokio::test]
+async fn surroundfi_account_create_pda_success() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(None).await;
+
+    let third_party_id = Some(7u16);
+    let create_pda_ix = |account_index: u16| {
+        let (surroundfi_account, _) = Pubkey::find_program_address(
+            &[
+                SURROUNDFI_ACCOUNT_SEED.as_bytes(),
+                test_f.surroundfi_group.key.as_ref(),
+                test_f.payer().as_ref(),
+                &account_index.to_le_bytes(),
+                &third_party_id.unwrap_or(0).to_le_bytes(),
+            ],
+            &surroundfi::id(),
+        );
+        let accounts = surroundfi::accounts::SurroundfiAccountInitializePda {
+            surroundfi_group: test_f.surroundfi_group.key,
+            surroundfi_account,
+            authority: test_f.payer(),
+            fee_payer: test_f.payer(),
+            system_program: system_program::id(),
+        };
+        let ix = Instruction {
+            program_id: surroundfi::id(),
+            accounts: accounts.to_account_metas(Some(true)),
+            data: surroundfi::instruction::SurroundfiAccountInitializePda {
+                account_index,
+                third_party_id,
+            }
+            .data(),
+        };
+        (ix, surroundfi_account)
+    };
+
+    // No keypair for the account itself is needed, only the authority signs
+    let (ix, surroundfi_account_key) = create_pda_ix(0);
+    let tx = Transaction::new_signed_with_payer(
+        &[ix],
+        Some(&test_f.payer()),
+        &[&test_f.payer_keypair()],
+        test_f.get_latest_blockhash().await,
+    );
+    let res = test_f
+        .context
+        .borrow_mut()
+        .banks_client
+        .process_transaction(tx)
+        .await;
+    assert!(res.is_ok());
+
+    let surroundfi_account: SurroundfiAccount =
+        test_f.load_and_deserialize(&surroundfi_account_key).await;
+
+    assert_eq!(surroundfi_account.group, test_f.surroundfi_group.key);
+    assert_eq!(surroundfi_account.authority, test_f.payer());
+    assert_eq!(surroundfi_account.account_index, 0);
+    assert_eq!(surroundfi_account.third_party_index, 7);
+    assert_ne!(surroundfi_account.bump, 0);
+
+    // The same index can't be used twice (the extra ix just keeps the tx signature unique)...
+    let (ix, _) = create_pda_ix(0);
+    let tx = Transaction::new_signed_with_payer(
+        &[ix, ComputeBudgetInstruction::set_compute_unit_price(1)],
+        Some(&test_f.payer()),
+        &[&test_f.payer_keypair()],
+        test_f.get_latest_blockhash().await,
+    );
+    let res = test_f
+        .context
+        .borrow_mut()
+        .banks_client
+        .process_transaction(tx)
+        .await;
+    assert!(res.is_err());
+
+    // ...but the next one is free
+    let (ix, surroundfi_account_key) = create_pda_ix(1);
+    let tx = Transaction::new_signed_with_payer(
+        &[ix],
+        Some(&test_f.payer()),
+        &[&test_f.payer_keypair()],
+        test_f.get_latest_blockhash().await,
+    );
+    let res = test_f
+        .context
+        .borrow_mut()
+        .banks_client
+        .process_transaction(tx)
+        .await;
+    assert!(res.is_ok());
+
+    let surroundfi_account: SurroundfiAccount =
+        test_f.load_and_deserialize(&surroundfi_account_key).await;
+    assert_eq!(surroundfi_account.account_index, 1);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn surroundfi_account_create_pda_after_transfer_authority() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(None).await;
+
+    let account_index = 3u16;
+    let derive = |authority: Pubkey| {
+        Pubkey::find_program_address(
+            &[
+                SURROUNDFI_ACCOUNT_SEED.as_bytes(),
+                test_f.surroundfi_group.key.as_ref(),
+                authority.as_ref(),
+                &account_index.to_le_bytes(),
+                &0u16.to_le_bytes(),
+            ],
+            &surroundfi::id(),
+        )
+    };
+
+    let (surroundfi_account_key, bump) = derive(test_f.payer());
+    let ix = Instruction {
+        program_id: surroundfi::id(),
+        accounts: surroundfi::accounts::SurroundfiAccountInitializePda {
+            surroundfi_group: test_f.surroundfi_group.key,
+            surroundfi_account: surroundfi_account_key,
+            authority: test_f.payer(),
+            fee_payer: test_f.payer(),
+            system_program: system_program::id(),
+        }
+        .to_account_metas(Some(true)),
+        data: surroundfi::instruction::SurroundfiAccountInitializePda {
+            account_index,
+            third_party_id: None,
+        }
+        .data(),
+    };
+    let tx = Transaction::new_signed_with_payer(
+        &[ix],
+        Some(&test_f.payer()),
+        &[&test_f.payer_keypair()],
+        test_f.get_latest_blockhash().await,
+    );
+    test_f
+        .context
+        .borrow_mut()
+        .banks_client
+        .process_transaction(tx)
+        .await?;
+
+    let surroundfi_account_f =
+        SurroundfiAccountFixture::from_key(test_f.context.clone(), surroundfi_account_key);
+    surroundfi_account_f
+        .try_set_flag(ACCOUNT_TRANSFER_AUTHORITY_ALLOWED)
+        .await?;
+    let new_authority = Keypair::new().pubkey();
+    surroundfi_account_f
+        .try_transfer_account_authority(new_authority, None)
+        .await?;
+
+    let surroundfi_account = surroundfi_account_f.load().await;
+    assert_eq!(surroundfi_account.authority, new_authority);
+    assert_eq!(surroundfi_account.creator, test_f.payer());
+
+    // The new authority's key no longer leads to the account...
+    let (derived_from_authority, _) = derive(surroundfi_account.authority);
+    assert_ne!(derived_from_authority, surroundfi_account_key);
+
+    // ...but the creator it keeps still does
+    assert_eq!(
+        derive(surroundfi_account.creator),
+        (surroundfi_account_key, bump)
+    );
+    assert_eq!(surroundfi_account.bump, bump);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn surroundfi_account_create_pda_zero_third_party_id_rejected() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(None).await;
+
+    // `None` derives with a third-party id of 0, `Some(0)` would alias it
+    let (surroundfi_account, _) = Pubkey::find_program_address(
+        &[
+            SURROUNDFI_ACCOUNT_SEED.as_bytes(),
+            test_f.surroundfi_group.key.as_ref(),
+            test_f.payer().as_ref(),
+            &0u16.to_le_bytes(),
+            &0u16.to_le_bytes(),
+        ],
+        &surroundfi::id(),
+    );
+    let ix = Instruction {
+        program_id: surroundfi::id(),
+        accounts: surroundfi::accounts::SurroundfiAccountInitializePda {
+            surroundfi_group: test_f.surroundfi_group.key,
+            surroundfi_account,
+            authority: test_f.payer(),
+            fee_payer: test_f.payer(),
+            system_program: system_program::id(),
+        }
+        .to_account_metas(Some(true)),
+        data: surroundfi::instruction::SurroundfiAccountInitializePda {
+            account_index: 0,
+            third_party_id: Some(0),
+        }
+        .data(),
+    };
+
+    let tx = Transaction::new_signed_with_payer(
+        &[ix],
+        Some(&test_f.payer()),
+        &[&test_f.payer_keypair()],
+        test_f.get_latest_blockhash().await,
+    );
+    let res = test_f
+        .context
+        .borrow_mut()
+        .banks_client
+        .process_transaction(tx)
+        .await;
+
+    assert!(res.is_err());
+    assert_custom_error!(res.unwrap_err(), SurroundfiError::InvalidThirdPartyId);
+
+    Ok(())
+}
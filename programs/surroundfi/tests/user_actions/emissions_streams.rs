@@ -0,0 +1,341 @@
+use fixed::types::I80F48;
+use fixtures::{assert_custom_error, native, prelude::*};
+use surroundfi::{
+    assert_eq_with_tolerance,
+    constants::{
+        EMISSIONS_FLAG_BORROW_ACTIVE, EMISSIONS_FLAG_LENDING_ACTIVE, MIN_EMISSIONS_START_TIME,
+    },
+    prelude::*,
+};
+use pretty_assertions::assert_eq;
+use solana_program_test::*;
+use solana_sdk::{signer::Signer, timing::SECONDS_PER_YEAR, transaction::Transaction};
+
+#[tokio::test]
+async fn emissions_streams() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+    let usdc_bank = test_f.get_bank(&BankMint::Usdc);
+
+    let start_time = MIN_EMISSIONS_START_TIME as i64;
+    let half_year = (SECONDS_PER_YEAR / 2.0) as i64;
+    test_f.set_time(start_time);
+
+    let partner_mint = MintFixture::new_token_22(test_f.context.clone(), None, Some(6), &[]).await;
+    let partner_mint_2 =
+        MintFixture::new_token_22(test_f.context.clone(), None, Some(6), &[]).await;
+
+    // Three streams on the USDC bank: USDC and a partner token for lenders, the latter ending
+    // after half a year, and another partner token for borrowers
+    usdc_bank.try_init_emissions_program().await?;
+    usdc_bank
+        .try_add_emissions_stream(
+            &test_f.usdc_mint,
+            EMISSIONS_FLAG_LENDING_ACTIVE,
+            1_000_000,
+            start_time,
+            0,
+        )
+        .await?;
+    usdc_bank
+        .try_add_emissions_stream(
+            &partner_mint,
+            EMISSIONS_FLAG_LENDING_ACTIVE,
+            2_000_000,
+            start_time,
+            start_time + half_year,
+        )
+        .await?;
+    usdc_bank
+        .try_add_emissions_stream(
+            &partner_mint_2,
+            EMISSIONS_FLAG_BORROW_ACTIVE,
+            1_000_000,
+            start_time,
+            0,
+        )
+        .await?;
+
+    // One stream per mint
+    let res = usdc_bank
+        .try_add_emissions_stream(
+            &partner_mint,
+            EMISSIONS_FLAG_LENDING_ACTIVE,
+            1_000_000,
+            start_time,
+            0,
+        )
+        .await;
+    assert!(res.is_err());
+    assert_custom_error!(res.unwrap_err(), SurroundfiError::InvalidEmissionsStream);
+
+    let usdc_funding_account = test_f.usdc_mint.create_token_account_and_mint_to(200).await;
+    usdc_bank
+        .try_fund_emissions_stream(
+            0,
+            &test_f.usdc_mint,
+            usdc_funding_account.key,
+            native!(200, "USDC"),
+        )
+        .await?;
+    let partner_funding_account = partner_mint.create_token_account_and_mint_to(200).await;
+    usdc_bank
+        .try_fund_emissions_stream(
+            1,
+            &partner_mint,
+            partner_funding_account.key,
+            native!(200, 6),
+        )
+        .await?;
+    let partner_funding_account_2 = partner_mint_2.create_token_account_and_mint_to(10).await;
+    usdc_bank
+        .try_fund_emissions_stream(
+            2,
+            &partner_mint_2,
+            partner_funding_account_2.key,
+            native!(10, 6),
+        )
+        .await?;
+
+    // Streams are funded with their own mint
+    let res = usdc_bank
+        .try_fund_emissions_stream(1, &partner_mint_2, partner_funding_account_2.key, 1)
+        .await;
+    assert!(res.is_err());
+
+    let emissions_program = usdc_bank.load_emissions_program().await;
+    assert_eq!(emissions_program.streams[1].mint, partner_mint.key);
+    assert_eq!(
+        I80F48::from(emissions_program.streams[1].remaining),
+        I80F48::from_num(native!(200, 6))
+    );
+    assert!(!emissions_program.streams[3].is_active());
+
+    // Deposit and start accruing all streams
+    let mfi_account_f = test_f.create_surroundfi_account().await;
+    let user_token_account_usdc = test_f.usdc_mint.create_token_account_and_mint_to(100).await;
+    mfi_account_f
+        .try_bank_deposit(user_token_account_usdc.key, usdc_bank, 50, None)
+        .await?;
+    mfi_account_f.try_init_emissions_accrual(usdc_bank).await?;
+    mfi_account_f.try_settle_emissions(usdc_bank, None).await?;
+
+    // After half a year, each lending stream can be withdrawn independently
+    test_f.advance_time(half_year).await;
+
+    let usdc_emissions_ta = test_f.usdc_mint.create_empty_token_account().await;
+    mfi_account_f
+        .try_withdraw_stream_emissions(usdc_bank, 0, &usdc_emissions_ta)
+        .await?;
+    let partner_emissions_ta = partner_mint.create_empty_token_account().await;
+    mfi_account_f
+        .try_withdraw_stream_emissions(usdc_bank, 1, &partner_emissions_ta)
+        .await?;
+    let partner_emissions_ta_2 = partner_mint_2.create_empty_token_account().await;
+    mfi_account_f
+        .try_withdraw_stream_emissions(usdc_bank, 2, &partner_emissions_ta_2)
+        .await?;
+
+    assert_eq_with_tolerance!(
+        usdc_emissions_ta.balance().await as i64,
+        native!(25, "USDC") as i64,
+        native!(1, "USDC") as i64
+    );
+    assert_eq_with_tolerance!(
+        partner_emissions_ta.balance().await as i64,
+        native!(50, 6) as i64,
+        native!(1, 6) as i64
+    );
+    // Borrow stream, nothing for lenders
+    assert_eq!(partner_emissions_ta_2.balance().await, 0);
+
+    // The vault must match the stream
+    let res = mfi_account_f
+        .try_withdraw_stream_emissions(usdc_bank, 0, &partner_emissions_ta)
+        .await;
+    assert!(res.is_err());
+
+    // The deposit settles the streams first, so the added balance earns from now on, and the
+    // partner stream has ended
+    mfi_account_f
+        .try_bank_deposit(user_token_account_usdc.key, usdc_bank, 50, None)
+        .await?;
+    test_f.advance_time(half_year).await;
+
+    mfi_account_f
+        .try_withdraw_stream_emissions(usdc_bank, 0, &usdc_emissions_ta)
+        .await?;
+    mfi_account_f
+        .try_withdraw_stream_emissions(usdc_bank, 1, &partner_emissions_ta)
+        .await?;
+
+    assert_eq_with_tolerance!(
+        usdc_emissions_ta.balance().await as i64,
+        native!(75, "USDC") as i64,
+        native!(1, "USDC") as i64
+    );
+    assert_eq_with_tolerance!(
+        partner_emissions_ta.balance().await as i64,
+        native!(50, 6) as i64,
+        native!(1, 6) as i64
+    );
+
+    test_f.advance_time(half_year).await;
+
+    mfi_account_f
+        .try_withdraw_stream_emissions(usdc_bank, 0, &usdc_emissions_ta)
+        .await?;
+
+    assert_eq_with_tolerance!(
+        usdc_emissions_ta.balance().await as i64,
+        native!(125, "USDC") as i64,
+        native!(1, "USDC") as i64
+    );
+
+    let emissions_program = usdc_bank.load_emissions_program().await;
+    assert_eq_with_tolerance!(
+        I80F48::from(emissions_program.streams[0].remaining),
+        I80F48::from_num(native!(75, "USDC")),
+        I80F48::from_num(native!(1, "USDC"))
+    );
+
+    let emissions_accrual = mfi_account_f.load_emissions_accrual(usdc_bank).await;
+    assert!(I80F48::from(emissions_accrual.streams[0].outstanding) < I80F48::ONE);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn emissions_streams_underfunded_split_pro_rata() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+    let usdc_bank = test_f.get_bank(&BankMint::Usdc);
+
+    let start_time = MIN_EMISSIONS_START_TIME as i64;
+    test_f.set_time(start_time);
+
+    // 10 USDC for a year of 1 USDC per USDC lent, far less than the 200 USDC owed
+    usdc_bank.try_init_emissions_program().await?;
+    usdc_bank
+        .try_add_emissions_stream(
+            &test_f.usdc_mint,
+            EMISSIONS_FLAG_LENDING_ACTIVE,
+            1_000_000,
+            start_time,
+            0,
+        )
+        .await?;
+    let usdc_funding_account = test_f.usdc_mint.create_token_account_and_mint_to(10).await;
+    usdc_bank
+        .try_fund_emissions_stream(
+            0,
+            &test_f.usdc_mint,
+            usdc_funding_account.key,
+            native!(10, "USDC"),
+        )
+        .await?;
+
+    let lender_1 = test_f.create_surroundfi_account().await;
+    let lender_2 = test_f.create_surroundfi_account().await;
+    for lender in [&lender_1, &lender_2] {
+        let user_token_account_usdc = test_f.usdc_mint.create_token_account_and_mint_to(100).await;
+        lender
+            .try_bank_deposit(user_token_account_usdc.key, usdc_bank, 100, None)
+            .await?;
+        lender.try_init_emissions_accrual(usdc_bank).await?;
+        lender.try_settle_emissions(usdc_bank, Some(0)).await?;
+    }
+
+    test_f.advance_time(SECONDS_PER_YEAR as i64).await;
+
+    // The first claimer only takes its share
+    let lender_1_emissions_ta = test_f.usdc_mint.create_empty_token_account().await;
+    lender_1
+        .try_withdraw_stream_emissions(usdc_bank, 0, &lender_1_emissions_ta)
+        .await?;
+    assert_eq_with_tolerance!(
+        lender_1_emissions_ta.balance().await as i64,
+        native!(5, "USDC") as i64,
+        native!(0.01, "USDC", f64) as i64
+    );
+
+    let lender_2_emissions_ta = test_f.usdc_mint.create_empty_token_account().await;
+    lender_2
+        .try_withdraw_stream_emissions(usdc_bank, 0, &lender_2_emissions_ta)
+        .await?;
+    assert_eq_with_tolerance!(
+        lender_2_emissions_ta.balance().await as i64,
+        native!(5, "USDC") as i64,
+        native!(0.01, "USDC", f64) as i64
+    );
+
+    let emissions_program = usdc_bank.load_emissions_program().await;
+    assert_eq!(
+        I80F48::from(emissions_program.streams[0].remaining),
+        I80F48::ZERO
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn emissions_streams_balance_change_requires_settlement() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+    let usdc_bank = test_f.get_bank(&BankMint::Usdc);
+
+    let start_time = MIN_EMISSIONS_START_TIME as i64;
+    test_f.set_time(start_time);
+
+    usdc_bank.try_init_emissions_program().await?;
+    usdc_bank
+        .try_add_emissions_stream(
+            &test_f.usdc_mint,
+            EMISSIONS_FLAG_LENDING_ACTIVE,
+            1_000_000,
+            start_time,
+            0,
+        )
+        .await?;
+
+    let mfi_account_f = test_f.create_surroundfi_account().await;
+    let user_token_account_usdc = test_f.usdc_mint.create_token_account_and_mint_to(100).await;
+    mfi_account_f
+        .try_bank_deposit(user_token_account_usdc.key, usdc_bank, 50, None)
+        .await?;
+
+    // Settling a stream needs the accrual
+    let res = mfi_account_f.try_settle_emissions(usdc_bank, Some(0)).await;
+    assert!(res.is_err());
+    assert_custom_error!(res.unwrap_err(), SurroundfiError::EmissionsAccrualMissing);
+
+    mfi_account_f.try_init_emissions_accrual(usdc_bank).await?;
+    mfi_account_f
+        .try_settle_emissions(usdc_bank, Some(0))
+        .await?;
+
+    let surroundfi_account = mfi_account_f.load().await;
+    assert!(surroundfi_account.lending_account.balances[0].is_emissions_streams_enrolled());
+
+    // Once enrolled, the balance can't change without the emissions program and accrual, which
+    // the fixture passes last
+    let mut ix = mfi_account_f
+        .make_bank_deposit_ix(user_token_account_usdc.key, usdc_bank, 10, None)
+        .await;
+    ix.accounts.truncate(ix.accounts.len() - 2);
+    let res = {
+        let mut ctx = test_f.context.borrow_mut();
+        let tx = Transaction::new_signed_with_payer(
+            &[ix],
+            Some(&ctx.payer.pubkey()),
+            &[&ctx.payer],
+            ctx.last_blockhash,
+        );
+        ctx.banks_client.process_transaction(tx).await
+    };
+    assert!(res.is_err());
+    assert_custom_error!(res.unwrap_err(), SurroundfiError::EmissionsAccrualMissing);
+
+    mfi_account_f
+        .try_bank_deposit(user_token_account_usdc.key, usdc_bank, 10, None)
+        .await?;
+
+    Ok(())
+}
@@ -1,11 +1,32 @@
 use anchor_lang::{InstructionData, ToAccountMetas};
-use fixtures::{assert_custom_error, prelude::*};
-use surroundfi::prelude::*;
+use anchor_spl::token::spl_token;
+use fixed::types::I80F48;
+use fixed_macro::types::I80F48;
+use fixtures::{
+    assert_custom_error,
+    flashloan_callback::{find_authority_address, forward_data, TEST_CALLBACK_ID},
+    native,
+    prelude::*,
+};
+use surroundfi::{
+    assert_eq_with_tolerance,
+    prelude::*,
+    state::{
+        surroundfi_account::{SurroundfiAccount, ACCOUNT_IN_FLASHLOAN},
+        surroundfi_group::{BankConfigOpt, BankVaultType, InterestRateConfigOpt},
+    },
+};
 use pretty_assertions::assert_eq;
-use solana_program::{instruction::Instruction, pubkey::Pubkey};
+use solana_program::{
+    instruction::{AccountMeta, Instruction, InstructionError},
+    pubkey::Pubkey,
+};
 use solana_program_test::*;
 use solana_sdk::{
-    compute_budget::ComputeBudgetInstruction, signer::Signer, transaction::Transaction,
+    compute_budget::ComputeBudgetInstruction,
+    signature::Keypair,
+    signer::Signer,
+    transaction::{Transaction, TransactionError},
 };
 
 // Flashloan tests
@@ -18,6 +39,12 @@ use solana_sdk::{
 // 7. Flashloan fails because of invalid `end_flashloan` ix order
 // 8. Flashloan fails because `end_flashloan` ix is for another account
 // 9. Flashloan fails because account is already in a flashloan
+// 10. Flashloan fee is charged on liabilities opened and repaid within the flashloan
+// 11. Callback flashloan success
+// 12. Callback flashloan fails because the loan is not repaid
+// 13. Callback flashloan fails on reentrancy
+// 14. Callback flashloan fails because the callback calls back into the program
+// 15. Callback flashloan through a CPI, for an account whose authority is another program's PDA
 
 #[tokio::test]
 async fn flashloan_success_1op() -> anyhow::Result<()> {
@@ -502,4 +529,549 @@ async fn flashloan_fail_already_in_flashloan() -> anyhow::Result<()> {
     assert_custom_error!(res.unwrap_err(), SurroundfiError::IllegalFlashloan);
 
     Ok(())
-}
\ No newline at end of file
+}
+#[tokio::test]
+async fn flashloan_fee() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+
+    let sol_bank = test_f.get_bank(&BankMint::Sol);
+    sol_bank
+        .update_config(
+            BankConfigOpt {
+                interest_rate_config: Some(InterestRateConfigOpt {
+                    flashloan_fee: Some(I80F48!(0.001).into()),
+                    flashloan_group_fee: Some(I80F48!(0.001).into()),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+            None,
+        )
+        .await?;
+
+    // Fund SOL lender
+    let lender_mfi_account_f = test_f.create_surroundfi_account().await;
+    let lender_token_account_f_sol = test_f
+        .sol_mint
+        .create_token_account_and_mint_to(1_000)
+        .await;
+    lender_mfi_account_f
+        .try_bank_deposit(lender_token_account_f_sol.key, sol_bank, 1_000, None)
+        .await?;
+
+    // The borrower only holds enough to pay the fee
+    let borrower_mfi_account_f = test_f.create_surroundfi_account().await;
+    let borrower_token_account_f_sol = test_f.sol_mint.create_token_account_and_mint_to(10).await;
+
+    let borrow_ix = borrower_mfi_account_f
+        .make_bank_borrow_ix(borrower_token_account_f_sol.key, sol_bank, 1_000)
+        .await;
+    let repay_ix = borrower_mfi_account_f
+        .make_bank_repay_ix(
+            borrower_token_account_f_sol.key,
+            sol_bank,
+            1_000,
+            Some(true),
+        )
+        .await;
+
+    borrower_mfi_account_f
+        .try_flashloan(vec![borrow_ix, repay_ix], vec![], vec![])
+        .await?;
+
+    // 0.1% of 1000 SOL to depositors, 0.1% to group fees
+    assert_eq!(
+        borrower_token_account_f_sol.balance().await,
+        native!(8, "SOL")
+    );
+
+    let bank = sol_bank.load().await;
+    assert_eq_with_tolerance!(
+        I80F48::from(bank.collected_group_fees_outstanding),
+        I80F48::from_num(native!(1, "SOL")),
+        I80F48!(1)
+    );
+    assert_eq_with_tolerance!(
+        I80F48::from(bank.asset_share_value),
+        I80F48!(1.001),
+        I80F48!(0.000001)
+    );
+
+    let borrower_mfi_account = borrower_mfi_account_f.load().await;
+    assert!(borrower_mfi_account
+        .lending_account
+        .flashloan_volumes
+        .iter()
+        .all(|volume| !volume.is_active()));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn flashloan_fee_repaid_by_deposit() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+
+    let sol_bank = test_f.get_bank(&BankMint::Sol);
+    sol_bank
+        .update_config(
+            BankConfigOpt {
+                interest_rate_config: Some(InterestRateConfigOpt {
+                    flashloan_fee: Some(I80F48!(0.001).into()),
+                    flashloan_group_fee: Some(I80F48!(0.001).into()),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+            None,
+        )
+        .await?;
+
+    // Fund SOL lender
+    let lender_mfi_account_f = test_f.create_surroundfi_account().await;
+    let lender_token_account_f_sol = test_f
+        .sol_mint
+        .create_token_account_and_mint_to(1_000)
+        .await;
+    lender_mfi_account_f
+        .try_bank_deposit(lender_token_account_f_sol.key, sol_bank, 1_000, None)
+        .await?;
+
+    let borrower_mfi_account_f = test_f.create_surroundfi_account().await;
+    let borrower_token_account_f_sol = test_f.sol_mint.create_token_account_and_mint_to(10).await;
+
+    // A deposit pays off the flashloaned liability, and 5 SOL more
+    let borrow_ix = borrower_mfi_account_f
+        .make_bank_borrow_ix(borrower_token_account_f_sol.key, sol_bank, 1_000)
+        .await;
+    let deposit_ix = borrower_mfi_account_f
+        .make_bank_deposit_ix(borrower_token_account_f_sol.key, sol_bank, 1_005, None)
+        .await;
+
+    borrower_mfi_account_f
+        .try_flashloan(vec![borrow_ix, deposit_ix], vec![], vec![sol_bank.key])
+        .await?;
+
+    // The fee is charged on the 1000 SOL of liability paid off only
+    assert_eq!(
+        borrower_token_account_f_sol.balance().await,
+        native!(3, "SOL")
+    );
+
+    let bank = sol_bank.load().await;
+    assert_eq_with_tolerance!(
+        I80F48::from(bank.collected_group_fees_outstanding),
+        I80F48::from_num(native!(1, "SOL")),
+        I80F48!(1)
+    );
+
+    let borrower_mfi_account = borrower_mfi_account_f.load().await;
+    let balance = borrower_mfi_account
+        .lending_account
+        .get_balance(&sol_bank.key)
+        .unwrap();
+    assert_eq!(I80F48::from(balance.liability_shares), I80F48::ZERO);
+    assert_eq_with_tolerance!(
+        I80F48::from(balance.asset_shares),
+        I80F48::from_num(native!(5, "SOL")),
+        I80F48!(1)
+    );
+    assert!(borrower_mfi_account
+        .lending_account
+        .flashloan_volumes
+        .iter()
+        .all(|volume| !volume.is_active()));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn callback_flashloan_success() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+
+    let sol_bank = test_f.get_bank(&BankMint::Sol);
+    sol_bank
+        .update_config(
+            BankConfigOpt {
+                interest_rate_config: Some(InterestRateConfigOpt {
+                    flashloan_fee: Some(I80F48!(0.001).into()),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+            None,
+        )
+        .await?;
+
+    // Fund SOL lender
+    let lender_mfi_account_f = test_f.create_surroundfi_account().await;
+    let lender_token_account_f_sol = test_f
+        .sol_mint
+        .create_token_account_and_mint_to(1_000)
+        .await;
+    lender_mfi_account_f
+        .try_bank_deposit(lender_token_account_f_sol.key, sol_bank, 1_000, None)
+        .await?;
+
+    // The borrower makes 1 SOL with the loan, and pays 0.1 SOL of fee on 100 SOL
+    let borrower_mfi_account_f = test_f.create_surroundfi_account().await;
+    let borrower_token_account_f_sol = test_f.sol_mint.create_token_account_and_mint_to(1.1).await;
+    let profit_token_account_f_sol = test_f.sol_mint.create_empty_token_account().await;
+
+    let callback_ix = spl_token::instruction::transfer(
+        &spl_token::ID,
+        &borrower_token_account_f_sol.key,
+        &profit_token_account_f_sol.key,
+        &test_f.payer(),
+        &[],
+        native!(1, "SOL"),
+    )?;
+
+    borrower_mfi_account_f
+        .try_callback_flashloan(
+            borrower_token_account_f_sol.key,
+            sol_bank,
+            100,
+            callback_ix.program_id,
+            callback_ix.accounts,
+            callback_ix.data,
+        )
+        .await?;
+
+    assert_eq!(borrower_token_account_f_sol.balance().await, 0);
+    assert_eq!(
+        profit_token_account_f_sol.balance().await,
+        native!(1, "SOL")
+    );
+
+    let borrower_mfi_account = borrower_mfi_account_f.load().await;
+    assert!(!borrower_mfi_account.get_flag(ACCOUNT_IN_FLASHLOAN));
+    let balance = borrower_mfi_account
+        .lending_account
+        .get_balance(&sol_bank.key)
+        .unwrap();
+    assert_eq!(I80F48::from(balance.liability_shares), I80F48::ZERO);
+
+    let bank = sol_bank.load().await;
+    assert_eq_with_tolerance!(
+        I80F48::from(bank.asset_share_value),
+        I80F48!(1.0001),
+        I80F48!(0.000001)
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn callback_flashloan_fail_not_repaid() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+
+    let sol_bank = test_f.get_bank(&BankMint::Sol);
+
+    // Fund SOL lender
+    let lender_mfi_account_f = test_f.create_surroundfi_account().await;
+    let lender_token_account_f_sol = test_f
+        .sol_mint
+        .create_token_account_and_mint_to(1_000)
+        .await;
+    lender_mfi_account_f
+        .try_bank_deposit(lender_token_account_f_sol.key, sol_bank, 1_000, None)
+        .await?;
+
+    // The callback moves part of the loan away, the rest can't repay it
+    let borrower_mfi_account_f = test_f.create_surroundfi_account().await;
+    let borrower_token_account_f_sol = test_f.sol_mint.create_empty_token_account().await;
+    let other_token_account_f_sol = test_f.sol_mint.create_empty_token_account().await;
+
+    let callback_ix = spl_token::instruction::transfer(
+        &spl_token::ID,
+        &borrower_token_account_f_sol.key,
+        &other_token_account_f_sol.key,
+        &test_f.payer(),
+        &[],
+        native!(1, "SOL"),
+    )?;
+
+    let res = borrower_mfi_account_f
+        .try_callback_flashloan(
+            borrower_token_account_f_sol.key,
+            sol_bank,
+            100,
+            callback_ix.program_id,
+            callback_ix.accounts,
+            callback_ix.data,
+        )
+        .await;
+
+    assert!(res.is_err());
+    assert_eq!(other_token_account_f_sol.balance().await, 0);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn callback_flashloan_fail_reentrancy() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+
+    let sol_bank = test_f.get_bank(&BankMint::Sol);
+
+    // Fund SOL lender
+    let lender_mfi_account_f = test_f.create_surroundfi_account().await;
+    let lender_token_account_f_sol = test_f
+        .sol_mint
+        .create_token_account_and_mint_to(1_000)
+        .await;
+    lender_mfi_account_f
+        .try_bank_deposit(lender_token_account_f_sol.key, sol_bank, 1_000, None)
+        .await?;
+
+    let borrower_mfi_account_f = test_f.create_surroundfi_account().await;
+    let borrower_token_account_f_sol = test_f.sol_mint.create_empty_token_account().await;
+
+    // The callback can't be this program
+    let res = borrower_mfi_account_f
+        .try_callback_flashloan(
+            borrower_token_account_f_sol.key,
+            sol_bank,
+            100,
+            surroundfi::id(),
+            vec![],
+            vec![],
+        )
+        .await;
+
+    assert!(res.is_err());
+    assert_custom_error!(res.unwrap_err(), SurroundfiError::IllegalFlashloan);
+
+    // Nor can it run within another flashloan
+    let callback_ix = spl_token::instruction::transfer(
+        &spl_token::ID,
+        &borrower_token_account_f_sol.key,
+        &borrower_token_account_f_sol.key,
+        &test_f.payer(),
+        &[],
+        0,
+    )?;
+    let callback_flashloan_ix = borrower_mfi_account_f
+        .make_callback_flashloan_ix(
+            borrower_token_account_f_sol.key,
+            sol_bank,
+            100,
+            callback_ix.program_id,
+            callback_ix.accounts,
+            callback_ix.data,
+        )
+        .await;
+
+    let res = borrower_mfi_account_f
+        .try_flashloan(vec![callback_flashloan_ix], vec![], vec![])
+        .await;
+
+    assert!(res.is_err());
+    assert_custom_error!(res.unwrap_err(), SurroundfiError::IllegalFlashloan);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn callback_flashloan_fail_callback_reenters() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+
+    let sol_bank = test_f.get_bank(&BankMint::Sol);
+    let usdc_bank = test_f.get_bank(&BankMint::Usdc);
+
+    // Fund SOL and USDC lender
+    let lender_mfi_account_f = test_f.create_surroundfi_account().await;
+    let lender_token_account_f_sol = test_f
+        .sol_mint
+        .create_token_account_and_mint_to(1_000)
+        .await;
+    lender_mfi_account_f
+        .try_bank_deposit(lender_token_account_f_sol.key, sol_bank, 1_000, None)
+        .await?;
+    let lender_token_account_f_usdc = test_f
+        .usdc_mint
+        .create_token_account_and_mint_to(1_000)
+        .await;
+    lender_mfi_account_f
+        .try_bank_deposit(lender_token_account_f_usdc.key, usdc_bank, 1_000, None)
+        .await?;
+
+    let borrower_mfi_account_f = test_f.create_surroundfi_account().await;
+    let borrower_token_account_f_sol = test_f.sol_mint.create_token_account_and_mint_to(10).await;
+    let borrower_token_account_f_usdc = test_f.usdc_mint.create_empty_token_account().await;
+    borrower_mfi_account_f
+        .try_bank_deposit(borrower_token_account_f_sol.key, sol_bank, 10, None)
+        .await?;
+
+    // The callback borrows, or withdraws, through this program
+    let borrow_ix = borrower_mfi_account_f
+        .make_bank_borrow_ix(borrower_token_account_f_usdc.key, usdc_bank, 10)
+        .await;
+    let withdraw_ix = borrower_mfi_account_f
+        .make_bank_withdraw_ix(borrower_token_account_f_sol.key, sol_bank, 10, None)
+        .await;
+
+    for ix in [borrow_ix, withdraw_ix] {
+        let mut callback_accounts = vec![AccountMeta::new_readonly(surroundfi::id(), false)];
+        callback_accounts.extend(ix.accounts);
+
+        let res = borrower_mfi_account_f
+            .try_callback_flashloan(
+                borrower_token_account_f_sol.key,
+                sol_bank,
+                100,
+                TEST_CALLBACK_ID,
+                callback_accounts,
+                forward_data(false, &ix.data),
+            )
+            .await;
+
+        assert!(matches!(
+            res.unwrap_err(),
+            BanksClientError::TransactionError(TransactionError::InstructionError(
+                0,
+                InstructionError::ReentrancyNotAllowed
+            ))
+        ));
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn callback_flashloan_pda_authority() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+
+    // Another program holds the surroundfi account under a PDA, and must CPI to use it
+    let (pda_authority, _) = find_authority_address();
+    let forward_signed = |ix: Instruction| {
+        let mut accounts = vec![AccountMeta::new_readonly(ix.program_id, false)];
+        accounts.extend(ix.accounts.into_iter().map(|meta| AccountMeta {
+            is_signer: meta.is_signer && meta.pubkey != pda_authority,
+            ..meta
+        }));
+        Instruction {
+            program_id: TEST_CALLBACK_ID,
+            accounts,
+            data: forward_data(true, &ix.data),
+        }
+    };
+
+    let sol_bank = test_f.get_bank(&BankMint::Sol);
+    sol_bank
+        .update_config(
+            BankConfigOpt {
+                interest_rate_config: Some(InterestRateConfigOpt {
+                    flashloan_fee: Some(I80F48!(0.001).into()),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+            None,
+        )
+        .await?;
+
+    // Fund SOL lender
+    let lender_mfi_account_f = test_f.create_surroundfi_account().await;
+    let lender_token_account_f_sol = test_f
+        .sol_mint
+        .create_token_account_and_mint_to(1_000)
+        .await;
+    lender_mfi_account_f
+        .try_bank_deposit(lender_token_account_f_sol.key, sol_bank, 1_000, None)
+        .await?;
+
+    // The PDA holds enough to pay the fee on 100 SOL
+    let pda_token_account_f_sol =
+        TokenAccountFixture::new(test_f.context.clone(), &test_f.sol_mint, &pda_authority).await;
+    let mint_to_ix = test_f
+        .sol_mint
+        .make_mint_to_ix(&pda_token_account_f_sol.key, native!(0.1, "SOL", f64));
+
+    let surroundfi_account = Keypair::new();
+    let init_ix = Instruction {
+        program_id: surroundfi::id(),
+        accounts: surroundfi::accounts::SurroundfiAccountInitialize {
+            surroundfi_group: test_f.surroundfi_group.key,
+            surroundfi_account: surroundfi_account.pubkey(),
+            authority: pda_authority,
+            fee_payer: test_f.payer(),
+            system_program: solana_program::system_program::ID,
+        }
+        .to_account_metas(Some(true)),
+        data: surroundfi::instruction::SurroundfiAccountInitialize {}.data(),
+    };
+
+    // The callback moves the loan, which only the PDA can sign for
+    let callback_ix = spl_token::instruction::transfer(
+        &spl_token::ID,
+        &pda_token_account_f_sol.key,
+        &pda_token_account_f_sol.key,
+        &pda_authority,
+        &[],
+        native!(100, "SOL"),
+    )?;
+
+    let mut flashloan_accounts = surroundfi::accounts::LendingAccountCallbackFlashloan {
+        group: test_f.surroundfi_group.key,
+        surroundfi_account: surroundfi_account.pubkey(),
+        authority: pda_authority,
+        bank: sol_bank.key,
+        flashloan_token_account: pda_token_account_f_sol.key,
+        bank_liquidity_vault_authority: sol_bank.get_vault_authority(BankVaultType::Liquidity).0,
+        liquidity_vault: sol_bank.get_vault(BankVaultType::Liquidity).0,
+        callback_program: callback_ix.program_id,
+        token_program: sol_bank.get_token_program(),
+    }
+    .to_account_metas(Some(true));
+    let callback_accounts_len = callback_ix.accounts.len() as u8;
+    flashloan_accounts.extend(callback_ix.accounts);
+    flashloan_accounts.extend([
+        AccountMeta::new_readonly(sol_bank.key, false),
+        AccountMeta::new_readonly(get_oracle_account_key(&sol_bank.load().await.config), false),
+    ]);
+    let flashloan_ix = Instruction {
+        program_id: surroundfi::id(),
+        accounts: flashloan_accounts,
+        data: surroundfi::instruction::LendingAccountCallbackFlashloan {
+            amount: native!(100, "SOL"),
+            callback_accounts_len,
+            callback_data: callback_ix.data,
+        }
+        .data(),
+    };
+
+    {
+        let mut ctx = test_f.context.borrow_mut();
+        let tx = Transaction::new_signed_with_payer(
+            &[
+                mint_to_ix,
+                forward_signed(init_ix),
+                forward_signed(flashloan_ix),
+            ],
+            Some(&ctx.payer.pubkey()),
+            &[&ctx.payer, &surroundfi_account],
+            ctx.last_blockhash,
+        );
+        ctx.banks_client.process_transaction(tx).await?;
+    }
+
+    assert_eq!(pda_token_account_f_sol.balance().await, 0);
+
+    let account: SurroundfiAccount = test_f
+        .load_and_deserialize(&surroundfi_account.pubkey())
+        .await;
+    assert_eq!(account.authority, pda_authority);
+    assert!(!account.get_flag(ACCOUNT_IN_FLASHLOAN));
+    let balance = account.lending_account.get_balance(&sol_bank.key).unwrap();
+    assert_eq!(I80F48::from(balance.liability_shares), I80F48::ZERO);
+
+    let bank = sol_bank.load().await;
+    assert_eq_with_tolerance!(
+        I80F48::from(bank.asset_share_value),
+        I80F48!(1.0001),
+        I80F48!(0.000001)
+    );
+
+    Ok(())
+}
@@ -0,0 +1,398 @@
+use fixed::types::I80F48;
+use fixed_macro::types::I80F48;
+use fixtures::{assert_custom_error, assert_eq_noise, native, prelude::*};
+use surroundfi::{
+    prelude::*,
+    state::surroundfi_group::{BankConfig, BankVaultType, InterestRateConfig},
+};
+use pretty_assertions::assert_eq;
+use solana_program_test::*;
+
+#[tokio::test]
+async fn insurance_staking_disabled() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+    let usdc_bank_f = test_f.get_bank(&BankMint::Usdc);
+
+    let res = usdc_bank_f
+        .try_configure_insurance_staking(1.5, 3_600, 600)
+        .await;
+    assert_custom_error!(res.unwrap_err(), SurroundfiError::InvalidConfig);
+
+    let res = usdc_bank_f
+        .try_configure_insurance_staking(0.5, 0, 600)
+        .await;
+    assert_custom_error!(res.unwrap_err(), SurroundfiError::InvalidConfig);
+
+    let res = usdc_bank_f
+        .try_configure_insurance_staking(0.5, 3_600, 0)
+        .await;
+    assert_custom_error!(res.unwrap_err(), SurroundfiError::InvalidConfig);
+
+    usdc_bank_f.try_init_insurance_stake().await?;
+    let staker_token_account = test_f.usdc_mint.create_token_account_and_mint_to(100).await;
+
+    let res = usdc_bank_f
+        .try_insurance_stake(staker_token_account.key, 100)
+        .await;
+    assert_custom_error!(res.unwrap_err(), SurroundfiError::InsuranceStakingDisabled);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn insurance_staking_first_loss_on_bankruptcy() -> anyhow::Result<()> {
+    let mut test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+    let cooldown = 3_600;
+
+    test_f
+        .get_bank(&BankMint::Usdc)
+        .try_configure_insurance_staking(0.5, cooldown, 600)
+        .await?;
+
+    // Stake 1_000 USDC, the protocol's part of the fund is 500 USDC
+    let usdc_bank_f = test_f.get_bank(&BankMint::Usdc);
+    usdc_bank_f.try_init_insurance_stake().await?;
+    let staker_token_account = test_f
+        .usdc_mint
+        .create_token_account_and_mint_to(1_000)
+        .await;
+    usdc_bank_f
+        .try_insurance_stake(staker_token_account.key, 1_000)
+        .await?;
+
+    let insurance_vault = usdc_bank_f.load().await.insurance_vault;
+    test_f
+        .get_bank_mut(&BankMint::Usdc)
+        .mint
+        .mint_to(&insurance_vault, 500)
+        .await;
+
+    let usdc_bank_f = test_f.get_bank(&BankMint::Usdc);
+    let insurance_stake = usdc_bank_f.load_insurance_stake(&test_f.payer()).await;
+    assert_eq!(
+        I80F48::from(insurance_stake.shares),
+        I80F48::from(native!(1_000, "USDC"))
+    );
+
+    // The admin can only withdraw the protocol's part
+    let admin_token_account = test_f.usdc_mint.create_empty_token_account().await;
+    let res = usdc_bank_f
+        .try_withdraw_insurance(&admin_token_account, native!(600, "USDC"))
+        .await;
+    assert_custom_error!(
+        res.unwrap_err(),
+        SurroundfiError::InsufficientInsuranceStake
+    );
+
+    usdc_bank_f
+        .try_withdraw_insurance(&admin_token_account, native!(100, "USDC"))
+        .await?;
+
+    // Bankrupt a borrower of 600 USDC
+    let lp_mfi_account_f = test_f.create_surroundfi_account().await;
+    let lp_token_account = test_f
+        .usdc_mint
+        .create_token_account_and_mint_to(2_000)
+        .await;
+    lp_mfi_account_f
+        .try_bank_deposit(lp_token_account.key, usdc_bank_f, 2_000, None)
+        .await?;
+
+    let sol_bank_f = test_f.get_bank(&BankMint::Sol);
+    let user_mfi_account_f = test_f.create_surroundfi_account().await;
+    let collateral_amount = test_f
+        .get_sufficient_collateral_for_outflow(600., &BankMint::Usdc, &BankMint::Sol)
+        .await;
+    let user_sol_token_account = test_f
+        .sol_mint
+        .create_token_account_and_mint_to(collateral_amount)
+        .await;
+    let user_usdc_token_account = test_f.usdc_mint.create_empty_token_account().await;
+    user_mfi_account_f
+        .try_bank_deposit(
+            user_sol_token_account.key,
+            sol_bank_f,
+            collateral_amount,
+            None,
+        )
+        .await?;
+    user_mfi_account_f
+        .try_bank_borrow(user_usdc_token_account.key, usdc_bank_f, 600)
+        .await?;
+
+    let mut user_mfi_account = user_mfi_account_f.load().await;
+    user_mfi_account.lending_account.balances[0].asset_shares = I80F48::ZERO.into();
+    user_mfi_account_f.set_account(&user_mfi_account).await?;
+
+    test_f
+        .surroundfi_group
+        .try_handle_bankruptcy(usdc_bank_f, &user_mfi_account_f)
+        .await?;
+
+    // Stakers covered the bad debt, the protocol's part is untouched
+    let usdc_bank = usdc_bank_f.load().await;
+    assert_eq_noise!(
+        usdc_bank.get_insurance_staked_amount()?,
+        I80F48::from(native!(400, "USDC")),
+        I80F48::from(native!(0.01, "USDC", f64))
+    );
+    let insurance_vault_balance = usdc_bank_f
+        .get_vault_token_account(BankVaultType::Insurance)
+        .await
+        .balance()
+        .await;
+    assert_eq_noise!(
+        insurance_vault_balance as i64,
+        native!(800, "USDC") as i64,
+        native!(0.01, "USDC", f64) as i64
+    );
+
+    // Unstake everything after the cooldown
+    usdc_bank_f
+        .try_insurance_request_unstake(0, Some(true))
+        .await?;
+
+    let staker_destination = test_f.usdc_mint.create_empty_token_account().await;
+    let res = usdc_bank_f
+        .try_insurance_unstake(staker_destination.key)
+        .await;
+    assert_custom_error!(res.unwrap_err(), SurroundfiError::InsuranceUnstakeCooldown);
+
+    test_f.advance_time(cooldown).await;
+
+    usdc_bank_f
+        .try_insurance_unstake(staker_destination.key)
+        .await?;
+
+    assert_eq_noise!(
+        staker_destination.balance().await as i64,
+        native!(400, "USDC") as i64,
+        native!(0.01, "USDC", f64) as i64
+    );
+    assert_eq_noise!(
+        usdc_bank_f
+            .get_vault_token_account(BankVaultType::Insurance)
+            .await
+            .balance()
+            .await as i64,
+        native!(400, "USDC") as i64,
+        native!(0.01, "USDC", f64) as i64
+    );
+
+    let usdc_bank = usdc_bank_f.load().await;
+    assert_eq!(I80F48::from(usdc_bank.insurance_stake_shares), I80F48::ZERO);
+    let insurance_stake = usdc_bank_f.load_insurance_stake(&test_f.payer()).await;
+    assert_eq!(I80F48::from(insurance_stake.shares), I80F48::ZERO);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn insurance_unstake_window_expired() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+    let usdc_bank_f = test_f.get_bank(&BankMint::Usdc);
+    let cooldown = 3_600;
+    let unstake_window = 600;
+
+    usdc_bank_f
+        .try_configure_insurance_staking(0.5, cooldown, unstake_window)
+        .await?;
+    usdc_bank_f.try_init_insurance_stake().await?;
+    let staker_token_account = test_f.usdc_mint.create_token_account_and_mint_to(100).await;
+    usdc_bank_f
+        .try_insurance_stake(staker_token_account.key, 100)
+        .await?;
+
+    usdc_bank_f.try_insurance_request_unstake(40, None).await?;
+
+    // Too late to withdraw, the request stays pending
+    test_f.advance_time(cooldown + unstake_window + 60).await;
+
+    let staker_destination = test_f.usdc_mint.create_empty_token_account().await;
+    let res = usdc_bank_f
+        .try_insurance_unstake(staker_destination.key)
+        .await;
+    assert_custom_error!(
+        res.unwrap_err(),
+        SurroundfiError::InsuranceUnstakeWindowExpired
+    );
+
+    let insurance_stake = usdc_bank_f.load_insurance_stake(&test_f.payer()).await;
+    assert_eq!(
+        I80F48::from(insurance_stake.unstaking_shares),
+        I80F48::from(native!(40, "USDC"))
+    );
+
+    // Requesting again restarts the cooldown
+    usdc_bank_f.try_insurance_request_unstake(40, None).await?;
+    let res = usdc_bank_f
+        .try_insurance_unstake(staker_destination.key)
+        .await;
+    assert_custom_error!(res.unwrap_err(), SurroundfiError::InsuranceUnstakeCooldown);
+
+    test_f.advance_time(cooldown).await;
+    usdc_bank_f
+        .try_insurance_unstake(staker_destination.key)
+        .await?;
+
+    assert_eq!(staker_destination.balance().await, native!(40, "USDC"));
+    let insurance_stake = usdc_bank_f.load_insurance_stake(&test_f.payer()).await;
+    assert_eq!(
+        I80F48::from(insurance_stake.shares),
+        I80F48::from(native!(60, "USDC"))
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn insurance_late_staker_earns_no_earlier_fees() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings {
+        banks: vec![
+            TestBankSetting {
+                mint: BankMint::Usdc,
+                config: Some(BankConfig {
+                    interest_rate_config: InterestRateConfig {
+                        insurance_fee_fixed_apr: I80F48!(0.1).into(),
+                        ..*DEFAULT_TEST_BANK_INTEREST_RATE_CONFIG
+                    },
+                    ..*DEFAULT_USDC_TEST_BANK_CONFIG
+                }),
+            },
+            TestBankSetting {
+                mint: BankMint::Sol,
+                ..TestBankSetting::default()
+            },
+        ],
+        protocol_fees: false,
+    }))
+    .await;
+    let usdc_bank_f = test_f.get_bank(&BankMint::Usdc);
+    let sol_bank_f = test_f.get_bank(&BankMint::Sol);
+    let cooldown = 3_600;
+    let month = 30 * 24 * 60 * 60;
+
+    usdc_bank_f
+        .try_configure_insurance_staking(0.5, cooldown, 600)
+        .await?;
+    usdc_bank_f.try_init_insurance_stake().await?;
+
+    // The program fee ata needs to exist to collect fees
+    TokenAccountFixture::new_from_ata(
+        test_f.context.clone(),
+        &test_f.usdc_mint.key,
+        &test_f.surroundfi_group.fee_wallet,
+        &test_f.usdc_mint.token_program,
+    )
+    .await;
+
+    // Insurance fees accrue on a 5_000 USDC borrow
+    let lender_mfi_account_f = test_f.create_surroundfi_account().await;
+    let lender_token_account = test_f
+        .usdc_mint
+        .create_token_account_and_mint_to(10_000)
+        .await;
+    lender_mfi_account_f
+        .try_bank_deposit(lender_token_account.key, usdc_bank_f, 10_000, None)
+        .await?;
+
+    let borrower_mfi_account_f = test_f.create_surroundfi_account().await;
+    let collateral_amount = test_f
+        .get_sufficient_collateral_for_outflow(5_000., &BankMint::Usdc, &BankMint::Sol)
+        .await;
+    let borrower_sol_token_account = test_f
+        .sol_mint
+        .create_token_account_and_mint_to(collateral_amount)
+        .await;
+    borrower_mfi_account_f
+        .try_bank_deposit(
+            borrower_sol_token_account.key,
+            sol_bank_f,
+            collateral_amount,
+            None,
+        )
+        .await?;
+    let borrower_usdc_token_account = test_f.usdc_mint.create_empty_token_account().await;
+    borrower_mfi_account_f
+        .try_bank_borrow(borrower_usdc_token_account.key, usdc_bank_f, 5_000)
+        .await?;
+
+    test_f.advance_time(month).await;
+
+    // Staking accrues the fees first, they go to the protocol's part of the fund since nobody was
+    // staked while they accrued. Collecting them right after doesn't credit them to the new stake.
+    let staker_token_account = test_f.usdc_mint.create_token_account_and_mint_to(100).await;
+    usdc_bank_f
+        .try_insurance_stake(staker_token_account.key, 100)
+        .await?;
+
+    let usdc_bank = usdc_bank_f.load().await;
+    let earlier_fees = I80F48::from(usdc_bank.collected_insurance_fees_outstanding);
+    assert!(earlier_fees > I80F48::ZERO);
+    assert_eq!(
+        I80F48::from(usdc_bank.insurance_stake_fees_credited),
+        earlier_fees
+    );
+
+    test_f
+        .surroundfi_group
+        .try_collect_fees(usdc_bank_f)
+        .await?;
+
+    let usdc_bank = usdc_bank_f.load().await;
+    assert_eq!(
+        I80F48::from(usdc_bank.collected_insurance_fees_outstanding),
+        I80F48::ZERO
+    );
+    assert_eq!(
+        I80F48::from(usdc_bank.insurance_stake_fees_credited),
+        I80F48::ZERO
+    );
+    assert_eq!(
+        I80F48::from(usdc_bank.insurance_stake_share_value),
+        I80F48::ONE
+    );
+
+    // Fees accrued while staked are shared with the staker
+    test_f.advance_time(month).await;
+    test_f
+        .surroundfi_group
+        .try_accrue_interest(usdc_bank_f)
+        .await?;
+    let staked_fees = I80F48::from(
+        usdc_bank_f
+            .load()
+            .await
+            .collected_insurance_fees_outstanding,
+    );
+    test_f
+        .surroundfi_group
+        .try_collect_fees(usdc_bank_f)
+        .await?;
+
+    let staker_fees = staked_fees / 2;
+    let usdc_bank = usdc_bank_f.load().await;
+    assert_eq_noise!(
+        usdc_bank.get_insurance_staked_amount()?,
+        I80F48::from(native!(100, "USDC")) + staker_fees,
+        I80F48!(1)
+    );
+
+    usdc_bank_f
+        .try_insurance_request_unstake(0, Some(true))
+        .await?;
+    test_f.advance_time(cooldown).await;
+
+    let staker_destination = test_f.usdc_mint.create_empty_token_account().await;
+    usdc_bank_f
+        .try_insurance_unstake(staker_destination.key)
+        .await?;
+
+    assert_eq_noise!(
+        staker_destination.balance().await as i64,
+        (I80F48::from(native!(100, "USDC")) + staker_fees).to_num::<i64>(),
+        1
+    );
+
+    Ok(())
+}
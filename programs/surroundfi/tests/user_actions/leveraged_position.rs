@@ -0,0 +1,492 @@
+use anchor_spl::token::spl_token;
+use fixed::types::I80F48;
+use fixed_macro::types::I80F48;
+use fixtures::{
+    assert_custom_error,
+    flashloan_callback::{forward_data, TEST_CALLBACK_ID},
+    native,
+    prelude::*,
+    surroundfi_account::SurroundfiAccountFixture,
+};
+use surroundfi::{
+    assert_eq_with_tolerance, instructions::LeverageLeg, prelude::SurroundfiError,
+    state::surroundfi_group::ArenaConfig,
+};
+use pretty_assertions::assert_eq;
+use solana_program::instruction::{AccountMeta, Instruction};
+use solana_program_test::*;
+
+// Leveraged position tests
+// The token program stands in for the arena's swap program: each "swap" is a transfer from a
+// market maker's token account, authorized by the signer's forwarded signature.
+
+fn arena_settings() -> TestSettings {
+    TestSettings {
+        banks: vec![
+            TestBankSetting {
+                mint: BankMint::Usdc,
+                config: None,
+            },
+            TestBankSetting {
+                mint: BankMint::Sol,
+                config: None,
+            },
+        ],
+        protocol_fees: false,
+    }
+}
+
+/// An arena group of the USDC and SOL banks, that swaps with the token program, and 1_000 USDC of
+/// liquidity
+async fn arena_fixture(max_leverage: f64) -> anyhow::Result<TestFixture> {
+    let test_f = TestFixture::new(Some(arena_settings())).await;
+
+    let admin = test_f.surroundfi_group.load().await.admin;
+    test_f.surroundfi_group.try_update(admin, true).await?;
+    configure_max_leverage(&test_f, max_leverage).await?;
+
+    let lp_mfi_account_f = test_f.create_surroundfi_account().await;
+    let lp_usdc_token_account = test_f
+        .usdc_mint
+        .create_token_account_and_mint_to(1_000)
+        .await;
+    lp_mfi_account_f
+        .try_bank_deposit(
+            lp_usdc_token_account.key,
+            test_f.get_bank(&BankMint::Usdc),
+            1_000,
+            None,
+        )
+        .await?;
+
+    Ok(test_f)
+}
+
+async fn configure_max_leverage(test_f: &TestFixture, max_leverage: f64) -> anyhow::Result<()> {
+    test_f
+        .surroundfi_group
+        .try_configure_arena(ArenaConfig {
+            max_leverage: I80F48::from_num(max_leverage).into(),
+            swap_program: spl_token::ID,
+            ..ArenaConfig::default()
+        })
+        .await?;
+
+    Ok(())
+}
+
+fn swap_ix(
+    test_f: &TestFixture,
+    market_maker: &TokenAccountFixture,
+    destination: &TokenAccountFixture,
+    amount: u64,
+) -> anyhow::Result<Instruction> {
+    Ok(spl_token::instruction::transfer(
+        &spl_token::ID,
+        &market_maker.key,
+        &destination.key,
+        &test_f.payer(),
+        &[],
+        amount,
+    )?)
+}
+
+/// Opens 10 SOL of collateral at 2x: borrows 100 USDC and "swaps" it for 10 SOL
+async fn open_2x(
+    test_f: &TestFixture,
+    user_mfi_account_f: &SurroundfiAccountFixture,
+    user_sol_token_account: &TokenAccountFixture,
+    user_usdc_token_account: &TokenAccountFixture,
+    sol_market_maker: &TokenAccountFixture,
+) -> anyhow::Result<()> {
+    let swap = swap_ix(
+        test_f,
+        sol_market_maker,
+        user_sol_token_account,
+        native!(10, "SOL"),
+    )?;
+    user_mfi_account_f
+        .try_open_leveraged_position(
+            test_f.get_bank(&BankMint::Sol),
+            user_sol_token_account.key,
+            test_f.get_bank(&BankMint::Usdc),
+            user_usdc_token_account.key,
+            10,
+            vec![LeverageLeg {
+                borrow_amount: native!(100, "USDC"),
+                swap_data: swap.data,
+            }],
+            swap.program_id,
+            swap.accounts,
+        )
+        .await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn open_leveraged_position_success() -> anyhow::Result<()> {
+    let test_f = arena_fixture(3.).await?;
+
+    let usdc_bank_f = test_f.get_bank(&BankMint::Usdc);
+    let sol_bank_f = test_f.get_bank(&BankMint::Sol);
+
+    let user_mfi_account_f = test_f.create_surroundfi_account().await;
+    let user_sol_token_account = test_f.sol_mint.create_token_account_and_mint_to(10).await;
+    let user_usdc_token_account = test_f.usdc_mint.create_empty_token_account().await;
+    let sol_market_maker = test_f.sol_mint.create_token_account_and_mint_to(100).await;
+
+    // Two legs: 50 USDC for 5 SOL, then 25 USDC for 2.5 SOL
+    let first_swap = swap_ix(
+        &test_f,
+        &sol_market_maker,
+        &user_sol_token_account,
+        native!(5, "SOL"),
+    )?;
+    let second_swap = swap_ix(
+        &test_f,
+        &sol_market_maker,
+        &user_sol_token_account,
+        native!(2.5, "SOL", f64),
+    )?;
+    user_mfi_account_f
+        .try_open_leveraged_position(
+            sol_bank_f,
+            user_sol_token_account.key,
+            usdc_bank_f,
+            user_usdc_token_account.key,
+            10,
+            vec![
+                LeverageLeg {
+                    borrow_amount: native!(50, "USDC"),
+                    swap_data: first_swap.data,
+                },
+                LeverageLeg {
+                    borrow_amount: native!(25, "USDC"),
+                    swap_data: second_swap.data,
+                },
+            ],
+            first_swap.program_id,
+            first_swap.accounts,
+        )
+        .await?;
+
+    // All the SOL was deposited, the swaps didn't spend the borrowed USDC
+    assert_eq!(user_sol_token_account.balance().await, 0);
+    assert_eq!(user_usdc_token_account.balance().await, native!(75, "USDC"));
+
+    let user_mfi_account = user_mfi_account_f.load().await;
+    let sol_balance = user_mfi_account
+        .lending_account
+        .get_balance(&sol_bank_f.key)
+        .unwrap();
+    assert_eq_with_tolerance!(
+        sol_bank_f
+            .load()
+            .await
+            .get_asset_amount(sol_balance.asset_shares.into())?,
+        I80F48!(17_500_000_000),
+        I80F48!(1)
+    );
+    let usdc_balance = user_mfi_account
+        .lending_account
+        .get_balance(&usdc_bank_f.key)
+        .unwrap();
+    assert_eq_with_tolerance!(
+        usdc_bank_f
+            .load()
+            .await
+            .get_liability_amount(usdc_balance.liability_shares.into())?,
+        I80F48!(75_000_000),
+        I80F48!(10_000)
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn open_leveraged_position_failures() -> anyhow::Result<()> {
+    let test_f = arena_fixture(2.).await?;
+
+    let usdc_bank_f = test_f.get_bank(&BankMint::Usdc);
+    let sol_bank_f = test_f.get_bank(&BankMint::Sol);
+
+    let user_mfi_account_f = test_f.create_surroundfi_account().await;
+    let user_sol_token_account = test_f.sol_mint.create_token_account_and_mint_to(10).await;
+    let user_usdc_token_account = test_f.usdc_mint.create_empty_token_account().await;
+    let sol_market_maker = test_f.sol_mint.create_token_account_and_mint_to(100).await;
+
+    // $250 of SOL against $100 of equity is 2.5x
+    let swap = swap_ix(
+        &test_f,
+        &sol_market_maker,
+        &user_sol_token_account,
+        native!(15, "SOL"),
+    )?;
+    let leg = LeverageLeg {
+        borrow_amount: native!(150, "USDC"),
+        swap_data: swap.data.clone(),
+    };
+    let res = user_mfi_account_f
+        .try_open_leveraged_position(
+            sol_bank_f,
+            user_sol_token_account.key,
+            usdc_bank_f,
+            user_usdc_token_account.key,
+            10,
+            vec![leg.clone()],
+            swap.program_id,
+            swap.accounts.clone(),
+        )
+        .await;
+    assert_custom_error!(res.unwrap_err(), SurroundfiError::ArenaMaxLeverageExceeded);
+
+    // No legs, or more than the max
+    for legs in [vec![], vec![leg.clone(); 5]] {
+        let res = user_mfi_account_f
+            .try_open_leveraged_position(
+                sol_bank_f,
+                user_sol_token_account.key,
+                usdc_bank_f,
+                user_usdc_token_account.key,
+                10,
+                legs,
+                swap.program_id,
+                swap.accounts.clone(),
+            )
+            .await;
+        assert_custom_error!(res.unwrap_err(), SurroundfiError::InvalidConfig);
+    }
+
+    // Any other program would be invoked with the signer's signature
+    let mut forward_accounts = vec![AccountMeta::new_readonly(spl_token::ID, false)];
+    forward_accounts.extend(swap.accounts.clone());
+    let res = user_mfi_account_f
+        .try_open_leveraged_position(
+            sol_bank_f,
+            user_sol_token_account.key,
+            usdc_bank_f,
+            user_usdc_token_account.key,
+            10,
+            vec![LeverageLeg {
+                borrow_amount: native!(50, "USDC"),
+                swap_data: forward_data(false, &swap.data),
+            }],
+            TEST_CALLBACK_ID,
+            forward_accounts,
+        )
+        .await;
+    assert_custom_error!(res.unwrap_err(), SurroundfiError::SwapProgramNotWhitelisted);
+
+    // Without a swap program, leveraged positions are disabled
+    test_f
+        .surroundfi_group
+        .try_configure_arena(ArenaConfig {
+            max_leverage: I80F48!(2).into(),
+            ..ArenaConfig::default()
+        })
+        .await?;
+    let res = user_mfi_account_f
+        .try_open_leveraged_position(
+            sol_bank_f,
+            user_sol_token_account.key,
+            usdc_bank_f,
+            user_usdc_token_account.key,
+            10,
+            vec![leg],
+            swap.program_id,
+            swap.accounts,
+        )
+        .await;
+    assert_custom_error!(res.unwrap_err(), SurroundfiError::SwapProgramNotWhitelisted);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn close_leveraged_position_success() -> anyhow::Result<()> {
+    let test_f = arena_fixture(3.).await?;
+
+    let usdc_bank_f = test_f.get_bank(&BankMint::Usdc);
+    let sol_bank_f = test_f.get_bank(&BankMint::Sol);
+
+    let user_mfi_account_f = test_f.create_surroundfi_account().await;
+    let user_sol_token_account = test_f.sol_mint.create_token_account_and_mint_to(10).await;
+    let user_usdc_token_account = test_f.usdc_mint.create_empty_token_account().await;
+    let sol_market_maker = test_f.sol_mint.create_token_account_and_mint_to(100).await;
+    let usdc_market_maker = test_f
+        .usdc_mint
+        .create_token_account_and_mint_to(1_000)
+        .await;
+
+    open_2x(
+        &test_f,
+        &user_mfi_account_f,
+        &user_sol_token_account,
+        &user_usdc_token_account,
+        &sol_market_maker,
+    )
+    .await?;
+
+    // Partial close: 5 SOL for 50 USDC
+    let swap = swap_ix(
+        &test_f,
+        &usdc_market_maker,
+        &user_usdc_token_account,
+        native!(50, "USDC"),
+    )?;
+    user_mfi_account_f
+        .try_close_leveraged_position(
+            sol_bank_f,
+            user_sol_token_account.key,
+            usdc_bank_f,
+            user_usdc_token_account.key,
+            5,
+            swap.program_id,
+            swap.accounts,
+            swap.data,
+        )
+        .await?;
+
+    assert_eq!(user_sol_token_account.balance().await, native!(5, "SOL"));
+    let user_mfi_account = user_mfi_account_f.load().await;
+    let usdc_balance = user_mfi_account
+        .lending_account
+        .get_balance(&usdc_bank_f.key)
+        .unwrap();
+    assert_eq_with_tolerance!(
+        usdc_bank_f
+            .load()
+            .await
+            .get_liability_amount(usdc_balance.liability_shares.into())?,
+        I80F48!(50_000_000),
+        I80F48!(10_000)
+    );
+
+    // Full close: 5 SOL for 60 USDC repays all the debt, and withdraws the rest of the collateral
+    let swap = swap_ix(
+        &test_f,
+        &usdc_market_maker,
+        &user_usdc_token_account,
+        native!(60, "USDC"),
+    )?;
+    user_mfi_account_f
+        .try_close_leveraged_position(
+            sol_bank_f,
+            user_sol_token_account.key,
+            usdc_bank_f,
+            user_usdc_token_account.key,
+            5,
+            swap.program_id,
+            swap.accounts,
+            swap.data,
+        )
+        .await?;
+
+    assert_eq!(user_sol_token_account.balance().await, native!(20, "SOL"));
+    // The borrowed 100 USDC, plus the ~10 USDC of excess proceeds
+    assert_eq_with_tolerance!(
+        user_usdc_token_account.balance().await as i64,
+        native!(110, "USDC") as i64,
+        native!(0.01, "USDC", f64) as i64
+    );
+    let user_mfi_account = user_mfi_account_f.load().await;
+    assert!(user_mfi_account
+        .lending_account
+        .get_balance(&sol_bank_f.key)
+        .is_none());
+    assert!(user_mfi_account
+        .lending_account
+        .get_balance(&usdc_bank_f.key)
+        .is_none());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn close_leveraged_position_deleverages_above_max_leverage() -> anyhow::Result<()> {
+    let test_f = arena_fixture(3.).await?;
+
+    let usdc_bank_f = test_f.get_bank(&BankMint::Usdc);
+    let sol_bank_f = test_f.get_bank(&BankMint::Sol);
+
+    let user_mfi_account_f = test_f.create_surroundfi_account().await;
+    let user_sol_token_account = test_f.sol_mint.create_token_account_and_mint_to(10).await;
+    let user_usdc_token_account = test_f.usdc_mint.create_empty_token_account().await;
+    let sol_market_maker = test_f.sol_mint.create_token_account_and_mint_to(100).await;
+    let usdc_market_maker = test_f
+        .usdc_mint
+        .create_token_account_and_mint_to(1_000)
+        .await;
+
+    open_2x(
+        &test_f,
+        &user_mfi_account_f,
+        &user_sol_token_account,
+        &user_usdc_token_account,
+        &sol_market_maker,
+    )
+    .await?;
+
+    // The account is now above the max leverage
+    configure_max_leverage(&test_f, 1.5).await?;
+    let health_cache_before = user_mfi_account_f.load().await.health_cache;
+
+    // 2x to 1.8x: still above the max, but less leveraged
+    let swap = swap_ix(
+        &test_f,
+        &usdc_market_maker,
+        &user_usdc_token_account,
+        native!(20, "USDC"),
+    )?;
+    user_mfi_account_f
+        .try_close_leveraged_position(
+            sol_bank_f,
+            user_sol_token_account.key,
+            usdc_bank_f,
+            user_usdc_token_account.key,
+            2,
+            swap.program_id,
+            swap.accounts,
+            swap.data,
+        )
+        .await?;
+
+    assert_eq!(user_sol_token_account.balance().await, native!(2, "SOL"));
+
+    // The health cache still records the smaller position
+    let health_cache = user_mfi_account_f.load().await.health_cache;
+    assert!(health_cache.is_engine_ok());
+    assert!(health_cache.is_healthy());
+    assert!(
+        I80F48::from(health_cache.asset_value_maint)
+            < I80F48::from(health_cache_before.asset_value_maint)
+    );
+    assert!(
+        I80F48::from(health_cache.liability_value_maint)
+            < I80F48::from(health_cache_before.liability_value_maint)
+    );
+
+    // A bad swap ($10 of SOL for 1 USDC) loses equity
+    let swap = swap_ix(
+        &test_f,
+        &usdc_market_maker,
+        &user_usdc_token_account,
+        native!(1, "USDC"),
+    )?;
+    let res = user_mfi_account_f
+        .try_close_leveraged_position(
+            sol_bank_f,
+            user_sol_token_account.key,
+            usdc_bank_f,
+            user_usdc_token_account.key,
+            1,
+            swap.program_id,
+            swap.accounts,
+            swap.data,
+        )
+        .await;
+    assert_custom_error!(res.unwrap_err(), SurroundfiError::RiskEngineInitRejected);
+
+    Ok(())
+}
@@ -427,6 +427,8 @@ async fn surroundfi_account_liquidation_success_swb() -> anyhow::Result<()> {
         .await?;
 
     let borrower_mfi_account_f = test_f.create_surroundfi_account().await;
+    // Records the value of each balance, see the health cache checks below
+    borrower_mfi_account_f.try_init_extension().await?;
     let borrower_token_account_sol = test_f.sol_mint.create_token_account_and_mint_to(100).await;
     let borrower_token_account_usdc = test_f.usdc_mint.create_empty_token_account().await;
 
@@ -512,6 +514,26 @@ async fn surroundfi_account_liquidation_success_swb() -> anyhow::Result<()> {
         native!(0.001, "USDC", f64) as i64
     );
 
+    // The liquidatee's health cache reflects its post-liquidation state
+    let health_cache = borrower_ma.health_cache;
+    assert!(health_cache.is_engine_ok());
+    assert!(!health_cache.is_healthy());
+    let asset_value_maint: I80F48 = health_cache.asset_value_maint.into();
+    let liab_value_maint: I80F48 = health_cache.liability_value_maint.into();
+    assert!(asset_value_maint < liab_value_maint);
+    // SOL (~$10) is the only collateral, still liquidatable at its current price
+    let liquidation_price: I80F48 = health_cache.liquidation_price.into();
+    assert!(liquidation_price > I80F48::from_num(10));
+
+    let extension = borrower_mfi_account_f.load_extension().await;
+    let sol_values = extension.health_cache_balance_values[0];
+    assert!(I80F48::from(sol_values.asset_value_maint) > I80F48::ZERO);
+    assert_eq!(I80F48::from(sol_values.liability_value_maint), I80F48::ZERO);
+    let usdc_values = extension.health_cache_balance_values[1];
+    assert_eq!(I80F48::from(usdc_values.asset_value_maint), I80F48::ZERO);
+    assert!(I80F48::from(usdc_values.liability_value_maint) > I80F48::ZERO);
+    assert_eq!(I80F48::from(sol_values.asset_value_maint), asset_value_maint);
+
     Ok(())
 }
 
@@ -1,11 +1,17 @@
+mod account_extension;
 mod borrow;
 mod close_account;
 mod close_balance;
 mod create_account;
 mod deposit;
+mod emissions_streams;
 mod flash_loan;
+mod insurance_staking;
+mod leveraged_position;
 mod liquidate;
+mod receipt_tokens;
 mod repay;
+mod simulate_health;
 mod withdraw;
 
 use anchor_lang::prelude::Clock;
@@ -143,6 +149,7 @@ async fn surroundfi_account_correct_balance_selection_after_closing_position() -
         &usdc_bank_f.key,
         &mut usdc_bank,
         &mut surroundfi_account.lending_account,
+        None,
     );
 
     assert!(bank_account.is_ok());
@@ -180,6 +187,8 @@ async fn emissions_test() -> anyhow::Result<()> {
             usdc_bank.mint.key,
             funding_account.key,
             usdc_bank.get_token_program(),
+            None,
+            None,
         )
         .await?;
 
@@ -199,6 +208,8 @@ async fn emissions_test() -> anyhow::Result<()> {
             sol_emissions_mint.key,
             funding_account.key,
             sol_emissions_mint.token_program,
+            None,
+            None,
         )
         .await?;
 
@@ -217,6 +228,8 @@ async fn emissions_test() -> anyhow::Result<()> {
             sol_emissions_mint_2.key,
             funding_account.key,
             sol_emissions_mint_2.token_program,
+            None,
+            None,
         )
         .await;
 
@@ -354,6 +367,8 @@ async fn emissions_test_2() -> anyhow::Result<()> {
             usdc_bank.mint.key,
             funding_account.key,
             usdc_bank.get_token_program(),
+            None,
+            None,
         )
         .await?;
 
@@ -373,6 +388,8 @@ async fn emissions_test_2() -> anyhow::Result<()> {
             Some(EMISSIONS_FLAG_BORROW_ACTIVE),
             Some(500_000),
             Some((native!(25, "USDC"), funding_account.key)),
+            None,
+            None,
             usdc_bank.get_token_program(),
         )
         .await?;
@@ -391,6 +408,165 @@ async fn emissions_test_2() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn emissions_capped_pro_rata() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+
+    let usdc_bank = test_f.get_bank(&BankMint::Usdc);
+
+    let funding_account = test_f.usdc_mint.create_token_account_and_mint_to(100).await;
+
+    // 10 USDC of emissions, at a rate paying 100 USDC per year to the deposits below
+    usdc_bank
+        .try_setup_emissions(
+            EMISSIONS_FLAG_LENDING_ACTIVE,
+            1_000_000,
+            native!(10, "USDC"),
+            usdc_bank.mint.key,
+            funding_account.key,
+            usdc_bank.get_token_program(),
+            None,
+            None,
+        )
+        .await?;
+
+    test_f.set_time(MIN_EMISSIONS_START_TIME as i64);
+
+    let lender_1 = test_f.create_surroundfi_account().await;
+    let lender_1_token_account = test_f.usdc_mint.create_token_account_and_mint_to(50).await;
+    lender_1
+        .try_bank_deposit(lender_1_token_account.key, usdc_bank, 50, None)
+        .await?;
+
+    let lender_2 = test_f.create_surroundfi_account().await;
+    let lender_2_token_account = test_f.usdc_mint.create_token_account_and_mint_to(50).await;
+    lender_2
+        .try_bank_deposit(lender_2_token_account.key, usdc_bank, 50, None)
+        .await?;
+
+    test_f.advance_time(SECONDS_PER_YEAR as i64).await;
+
+    // The first claimer only gets its share of the capped emissions
+    let lender_1_emissions = test_f.usdc_mint.create_empty_token_account().await;
+    lender_1
+        .try_withdraw_emissions(usdc_bank, &lender_1_emissions)
+        .await?;
+
+    assert_eq_with_tolerance!(
+        lender_1_emissions.balance().await as i64,
+        native!(5, "USDC") as i64,
+        native!(0.1, "USDC", f64) as i64
+    );
+
+    let lender_2_emissions = test_f.usdc_mint.create_empty_token_account().await;
+    lender_2
+        .try_withdraw_emissions(usdc_bank, &lender_2_emissions)
+        .await?;
+
+    assert_eq_with_tolerance!(
+        lender_2_emissions.balance().await as i64,
+        native!(5, "USDC") as i64,
+        native!(0.1, "USDC", f64) as i64
+    );
+
+    let usdc_bank_data = usdc_bank.load().await;
+    assert!(I80F48::from(usdc_bank_data.emissions_remaining) < I80F48::ONE);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn emissions_schedule() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+
+    let usdc_bank = test_f.get_bank(&BankMint::Usdc);
+
+    let funding_account = test_f.usdc_mint.create_token_account_and_mint_to(100).await;
+
+    let now = MIN_EMISSIONS_START_TIME as i64;
+    test_f.set_time(now);
+
+    let start_time = now + (SECONDS_PER_YEAR / 4.0) as i64;
+    let end_time = now + (SECONDS_PER_YEAR * 3.0 / 4.0) as i64;
+
+    let res = usdc_bank
+        .try_setup_emissions(
+            EMISSIONS_FLAG_LENDING_ACTIVE,
+            1_000_000,
+            native!(100, "USDC"),
+            usdc_bank.mint.key,
+            funding_account.key,
+            usdc_bank.get_token_program(),
+            Some(end_time),
+            Some(start_time),
+        )
+        .await;
+
+    assert_custom_error!(res.unwrap_err(), SurroundfiError::InvalidEmissionsSchedule);
+
+    // Pre-funded emissions, accruing for half a year only
+    usdc_bank
+        .try_setup_emissions(
+            EMISSIONS_FLAG_LENDING_ACTIVE,
+            1_000_000,
+            native!(100, "USDC"),
+            usdc_bank.mint.key,
+            funding_account.key,
+            usdc_bank.get_token_program(),
+            Some(start_time),
+            Some(end_time),
+        )
+        .await?;
+
+    let lender = test_f.create_surroundfi_account().await;
+    let lender_token_account = test_f.usdc_mint.create_token_account_and_mint_to(50).await;
+    lender
+        .try_bank_deposit(lender_token_account.key, usdc_bank, 50, None)
+        .await?;
+
+    // Nothing accrues before the start
+    test_f.advance_time((SECONDS_PER_YEAR / 8.0) as i64).await;
+
+    let lender_emissions = test_f.usdc_mint.create_empty_token_account().await;
+    lender
+        .try_withdraw_emissions(usdc_bank, &lender_emissions)
+        .await?;
+
+    assert_eq!(lender_emissions.balance().await, 0);
+
+    // Unspent emissions can't be reclaimed before the end
+    let reclaim_account = test_f.usdc_mint.create_empty_token_account().await;
+    let res = usdc_bank.try_reclaim_emissions(&reclaim_account).await;
+
+    assert_custom_error!(res.unwrap_err(), SurroundfiError::EmissionsNotEnded);
+
+    // Nothing accrues after the end
+    test_f.advance_time(SECONDS_PER_YEAR as i64).await;
+
+    lender
+        .try_withdraw_emissions(usdc_bank, &lender_emissions)
+        .await?;
+
+    assert_eq_with_tolerance!(
+        lender_emissions.balance().await as i64,
+        native!(25, "USDC") as i64,
+        native!(0.1, "USDC", f64) as i64
+    );
+
+    usdc_bank.try_reclaim_emissions(&reclaim_account).await?;
+
+    assert_eq_with_tolerance!(
+        reclaim_account.balance().await as i64,
+        native!(75, "USDC") as i64,
+        native!(0.1, "USDC", f64) as i64
+    );
+
+    let usdc_bank_data = usdc_bank.load().await;
+    assert!(I80F48::from(usdc_bank_data.emissions_remaining) < I80F48::ONE);
+
+    Ok(())
+}
+
 #[tokio::test]
 async fn emissions_setup_t22_with_fee() -> anyhow::Result<()> {
     let test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
@@ -412,6 +588,8 @@ async fn emissions_setup_t22_with_fee() -> anyhow::Result<()> {
             bank_f.mint.key,
             funding_account.key,
             bank_f.get_token_program(),
+            None,
+            None,
         )
         .await?;
 
@@ -445,6 +623,8 @@ async fn emissions_setup_t22_with_fee() -> anyhow::Result<()> {
             Some(EMISSIONS_FLAG_BORROW_ACTIVE),
             Some(500_000),
             Some((native!(25, bank_f.mint.mint.decimals), funding_account.key)),
+            None,
+            None,
             bank_f.get_token_program(),
         )
         .await?;
@@ -0,0 +1,257 @@
+use fixed::types::I80F48;
+use fixed_macro::types::I80F48;
+use fixtures::{assert_custom_error, bank::BankFixture, native, prelude::*};
+use surroundfi::{
+    assert_eq_with_tolerance,
+    constants::{ASSET_TAG_RECEIPT, EMISSIONS_FLAG_LENDING_ACTIVE, MIN_EMISSIONS_START_TIME},
+    prelude::*,
+    state::{price::OracleSetup, surroundfi_group::BankConfig},
+};
+use pretty_assertions::assert_eq;
+use solana_program_test::*;
+use solana_sdk::timing::SECONDS_PER_YEAR;
+
+async fn create_receipt_token_account(
+    test_f: &TestFixture,
+    bank_f: &BankFixture,
+) -> TokenAccountFixture {
+    let payer = test_f.payer();
+    TokenAccountFixture::new_with_token_program(
+        test_f.context.clone(),
+        &bank_f.get_receipt_mint_address(),
+        &payer,
+        &bank_f.get_token_program(),
+    )
+    .await
+}
+
+#[tokio::test]
+async fn receipt_tokens_deposit_and_redeem() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+    let usdc_bank_f = test_f.get_bank(&BankMint::Usdc);
+
+    let user_token_account_usdc = test_f
+        .usdc_mint
+        .create_token_account_and_mint_to(1_000)
+        .await;
+
+    // Receipt tokens are opt-in
+    let res = usdc_bank_f
+        .try_deposit_for_receipt(
+            user_token_account_usdc.key,
+            user_token_account_usdc.key,
+            100,
+        )
+        .await;
+    assert!(res.is_err());
+
+    usdc_bank_f.try_init_receipt_mint().await?;
+
+    let bank = usdc_bank_f.load().await;
+    assert_eq!(bank.receipt_mint, usdc_bank_f.get_receipt_mint_address());
+    assert!(bank.has_receipt_mint());
+
+    // The mint can only be created once
+    let res = usdc_bank_f.try_init_receipt_mint().await;
+    assert!(res.is_err());
+
+    let receipt_token_account = create_receipt_token_account(&test_f, usdc_bank_f).await;
+    usdc_bank_f
+        .try_deposit_for_receipt(user_token_account_usdc.key, receipt_token_account.key, 100)
+        .await?;
+
+    // One receipt token per share, the shares are recorded on the bank only
+    assert_eq!(receipt_token_account.balance().await, native!(100, "USDC"));
+    assert_eq!(
+        user_token_account_usdc.balance().await,
+        native!(900, "USDC")
+    );
+    let bank = usdc_bank_f.load().await;
+    assert_eq!(
+        I80F48::from(bank.total_asset_shares),
+        I80F48::from_num(native!(100, "USDC"))
+    );
+    assert_eq!(
+        I80F48::from(bank.receipt_asset_shares),
+        I80F48::from_num(native!(100, "USDC"))
+    );
+
+    // Shares appreciate like any other deposit
+    usdc_bank_f.set_asset_share_value(I80F48!(2)).await;
+
+    usdc_bank_f
+        .try_redeem_receipt(
+            receipt_token_account.key,
+            user_token_account_usdc.key,
+            native!(25, "USDC"),
+        )
+        .await?;
+
+    assert_eq!(receipt_token_account.balance().await, native!(75, "USDC"));
+    assert_eq!(
+        user_token_account_usdc.balance().await,
+        native!(950, "USDC")
+    );
+    let bank = usdc_bank_f.load().await;
+    assert_eq!(
+        I80F48::from(bank.total_asset_shares),
+        I80F48::from_num(native!(75, "USDC"))
+    );
+    assert_eq!(
+        I80F48::from(bank.receipt_asset_shares),
+        I80F48::from_num(native!(75, "USDC"))
+    );
+
+    let res = usdc_bank_f
+        .try_redeem_receipt(receipt_token_account.key, user_token_account_usdc.key, 0)
+        .await;
+    assert!(res.is_err());
+    assert_custom_error!(res.unwrap_err(), SurroundfiError::ReceiptAmountTooSmall);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn receipt_tokens_as_collateral() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+    let usdc_bank_f = test_f.get_bank(&BankMint::Usdc);
+    let sol_bank_f = test_f.get_bank(&BankMint::Sol);
+
+    // Mint 100 receipt tokens of the USDC bank
+    usdc_bank_f.try_init_receipt_mint().await?;
+    let user_token_account_usdc = test_f.usdc_mint.create_token_account_and_mint_to(100).await;
+    let receipt_token_account = create_receipt_token_account(&test_f, usdc_bank_f).await;
+    usdc_bank_f
+        .try_deposit_for_receipt(user_token_account_usdc.key, receipt_token_account.key, 100)
+        .await?;
+
+    // List the receipt token in its own bank, priced from the USDC bank
+    let receipt_mint_f = MintFixture::fetch(
+        test_f.context.clone(),
+        usdc_bank_f.get_receipt_mint_address(),
+    )
+    .await;
+    let receipt_bank_f = test_f
+        .surroundfi_group
+        .try_lending_pool_add_bank(
+            &receipt_mint_f,
+            BankConfig {
+                asset_tag: ASSET_TAG_RECEIPT,
+                oracle_setup: OracleSetup::ReceiptToken,
+                oracle_keys: create_oracle_key_array(usdc_bank_f.key),
+                ..*DEFAULT_USDC_TEST_BANK_CONFIG
+            },
+        )
+        .await?;
+
+    // Receipt banks must use the receipt asset tag
+    let res = test_f
+        .surroundfi_group
+        .try_lending_pool_add_bank(
+            &receipt_mint_f,
+            BankConfig {
+                oracle_setup: OracleSetup::ReceiptToken,
+                oracle_keys: create_oracle_key_array(usdc_bank_f.key),
+                ..*DEFAULT_USDC_TEST_BANK_CONFIG
+            },
+        )
+        .await;
+    assert!(res.is_err());
+
+    // The underlying bank must issue the receipt mint
+    let sol_bank_f = test_f.get_bank(&BankMint::Sol);
+    sol_bank_f.try_init_receipt_mint().await?;
+    let res = test_f
+        .surroundfi_group
+        .try_lending_pool_add_bank(
+            &receipt_mint_f,
+            BankConfig {
+                asset_tag: ASSET_TAG_RECEIPT,
+                oracle_setup: OracleSetup::ReceiptToken,
+                oracle_keys: create_oracle_key_array(sol_bank_f.key),
+                ..*DEFAULT_USDC_TEST_BANK_CONFIG
+            },
+        )
+        .await;
+    assert!(res.is_err());
+    assert_custom_error!(res.unwrap_err(), SurroundfiError::WrongOracleAccountKeys);
+
+    let lender_mfi_account_f = test_f.create_surroundfi_account().await;
+    let lender_token_account_sol = test_f.sol_mint.create_token_account_and_mint_to(100).await;
+    lender_mfi_account_f
+        .try_bank_deposit(lender_token_account_sol.key, sol_bank_f, 100, None)
+        .await?;
+
+    let user_mfi_account_f = test_f.create_surroundfi_account().await;
+    user_mfi_account_f
+        .try_bank_deposit(receipt_token_account.key, &receipt_bank_f, 100, None)
+        .await?;
+
+    // Each share is now worth $2, so 100 receipt tokens back $200 (SOL is $10)
+    usdc_bank_f.set_asset_share_value(I80F48!(2)).await;
+
+    let user_token_account_sol = test_f.sol_mint.create_empty_token_account().await;
+    user_mfi_account_f
+        .try_bank_borrow(user_token_account_sol.key, sol_bank_f, 19)
+        .await?;
+
+    let res = user_mfi_account_f
+        .try_bank_borrow(user_token_account_sol.key, sol_bank_f, 2)
+        .await;
+    assert!(res.is_err());
+    assert_custom_error!(res.unwrap_err(), SurroundfiError::RiskEngineInitRejected);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn receipt_tokens_earn_no_emissions() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+    let usdc_bank_f = test_f.get_bank(&BankMint::Usdc);
+
+    // 10 USDC of emissions, at a rate paying 100 USDC per year to the deposits below
+    let funding_account = test_f.usdc_mint.create_token_account_and_mint_to(100).await;
+    usdc_bank_f
+        .try_setup_emissions(
+            EMISSIONS_FLAG_LENDING_ACTIVE,
+            1_000_000,
+            native!(10, "USDC"),
+            usdc_bank_f.mint.key,
+            funding_account.key,
+            usdc_bank_f.get_token_program(),
+            None,
+            None,
+        )
+        .await?;
+
+    test_f.set_time(MIN_EMISSIONS_START_TIME as i64);
+
+    usdc_bank_f.try_init_receipt_mint().await?;
+    let user_token_account_usdc = test_f.usdc_mint.create_token_account_and_mint_to(50).await;
+    let receipt_token_account = create_receipt_token_account(&test_f, usdc_bank_f).await;
+    usdc_bank_f
+        .try_deposit_for_receipt(user_token_account_usdc.key, receipt_token_account.key, 50)
+        .await?;
+
+    let lender = test_f.create_surroundfi_account().await;
+    let lender_token_account = test_f.usdc_mint.create_token_account_and_mint_to(50).await;
+    lender
+        .try_bank_deposit(lender_token_account.key, usdc_bank_f, 50, None)
+        .await?;
+
+    test_f.advance_time(SECONDS_PER_YEAR as i64).await;
+
+    // Shares backing receipt tokens don't dilute the lender's share of the capped emissions
+    let lender_emissions = test_f.usdc_mint.create_empty_token_account().await;
+    lender
+        .try_withdraw_emissions(usdc_bank_f, &lender_emissions)
+        .await?;
+
+    assert_eq_with_tolerance!(
+        lender_emissions.balance().await as i64,
+        native!(10, "USDC") as i64,
+        native!(0.1, "USDC", f64) as i64
+    );
+
+    Ok(())
+}
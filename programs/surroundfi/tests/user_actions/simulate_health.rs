@@ -0,0 +1,265 @@
+use fixed::types::I80F48;
+use fixed_macro::types::I80F48;
+use fixtures::{assert_custom_error, native, prelude::*};
+use surroundfi::{
+    assert_eq_with_tolerance,
+    instructions::{SimulatedAction, SimulatedActionType},
+    prelude::*,
+    state::surroundfi_group::BankConfigOpt,
+};
+use pretty_assertions::assert_eq;
+use solana_program_test::*;
+use solana_sdk::timing::SECONDS_PER_YEAR;
+
+#[tokio::test]
+async fn simulate_health_actions() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+    let usdc_bank_f = test_f.get_bank(&BankMint::Usdc);
+    let sol_bank_f = test_f.get_bank(&BankMint::Sol);
+
+    let lender_mfi_account_f = test_f.create_surroundfi_account().await;
+    let lender_token_account_sol = test_f.sol_mint.create_token_account_and_mint_to(100).await;
+    lender_mfi_account_f
+        .try_bank_deposit(lender_token_account_sol.key, sol_bank_f, 100, None)
+        .await?;
+
+    let user_mfi_account_f = test_f.create_surroundfi_account().await;
+    let user_token_account_usdc = test_f.usdc_mint.create_token_account_and_mint_to(100).await;
+    user_mfi_account_f
+        .try_bank_deposit(user_token_account_usdc.key, usdc_bank_f, 100, None)
+        .await?;
+
+    // No actions: the current state of the account, all of the deposit can be withdrawn
+    let health = user_mfi_account_f.try_simulate_health(vec![]).await?;
+    assert_eq_with_tolerance!(
+        I80F48::from(health.init_asset_value),
+        I80F48!(100),
+        I80F48!(1)
+    );
+    assert_eq!(I80F48::from(health.init_liability_value), I80F48::ZERO);
+    assert_eq!(health.balances.len(), 1);
+    assert_eq_with_tolerance!(
+        health.balances[0].max_amount as i64,
+        native!(100, "USDC") as i64,
+        1
+    );
+
+    // Borrow $50 of SOL (SOL is $10)
+    let health = user_mfi_account_f
+        .try_simulate_health(vec![SimulatedAction {
+            bank: sol_bank_f.key,
+            action_type: SimulatedActionType::Borrow,
+            amount: native!(5, "SOL"),
+        }])
+        .await?;
+    assert_eq_with_tolerance!(
+        I80F48::from(health.init_liability_value),
+        I80F48!(50),
+        I80F48!(1)
+    );
+    assert_eq!(health.balances.len(), 2);
+    assert!(I80F48::from(health.balances[1].maint_value) < I80F48::ZERO);
+    assert_eq_with_tolerance!(
+        health.balances[0].max_amount as i64,
+        native!(50, "USDC") as i64,
+        native!(1, "USDC") as i64
+    );
+    assert_eq_with_tolerance!(
+        health.balances[1].max_amount as i64,
+        native!(5, "SOL") as i64,
+        native!(0.1, "SOL", f64) as i64
+    );
+
+    // A zero borrow returns the max borrowable amount of a new position
+    let health = user_mfi_account_f
+        .try_simulate_health(vec![SimulatedAction {
+            bank: sol_bank_f.key,
+            action_type: SimulatedActionType::Borrow,
+            amount: 0,
+        }])
+        .await?;
+    assert_eq_with_tolerance!(
+        health.balances[1].max_amount as i64,
+        native!(10, "SOL") as i64,
+        native!(0.1, "SOL", f64) as i64
+    );
+
+    // Actions apply in order, an unhealthy result is reported rather than rejected
+    let health = user_mfi_account_f
+        .try_simulate_health(vec![
+            SimulatedAction {
+                bank: usdc_bank_f.key,
+                action_type: SimulatedActionType::Withdraw,
+                amount: native!(50, "USDC"),
+            },
+            SimulatedAction {
+                bank: sol_bank_f.key,
+                action_type: SimulatedActionType::Borrow,
+                amount: native!(6, "SOL"),
+            },
+        ])
+        .await?;
+    assert!(I80F48::from(health.init_liability_value) > I80F48::from(health.init_asset_value));
+    assert_eq!(health.balances[0].max_amount, 0);
+    assert_eq!(health.balances[1].max_amount, 0);
+
+    // Nothing is written
+    let surroundfi_account = user_mfi_account_f.load().await;
+    assert_eq!(
+        surroundfi_account
+            .lending_account
+            .get_active_balances_iter()
+            .count(),
+        1
+    );
+
+    // Same rules as the instructions: can't repay a position that doesn't exist
+    let res = user_mfi_account_f
+        .try_simulate_health(vec![SimulatedAction {
+            bank: sol_bank_f.key,
+            action_type: SimulatedActionType::Repay,
+            amount: native!(1, "SOL"),
+        }])
+        .await;
+    assert!(res.is_err());
+    assert_custom_error!(res.unwrap_err(), SurroundfiError::BankAccountNotFound);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn simulate_health_accrues_interest() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+    let usdc_bank_f = test_f.get_bank(&BankMint::Usdc);
+    let sol_bank_f = test_f.get_bank(&BankMint::Sol);
+
+    let lender_mfi_account_f = test_f.create_surroundfi_account().await;
+    let lender_token_account_usdc = test_f
+        .usdc_mint
+        .create_token_account_and_mint_to(1_000)
+        .await;
+    lender_mfi_account_f
+        .try_bank_deposit(lender_token_account_usdc.key, usdc_bank_f, 1_000, None)
+        .await?;
+
+    // 50% utilization, at the optimal rate of 60% APR
+    let user_mfi_account_f = test_f.create_surroundfi_account().await;
+    let user_token_account_sol = test_f.sol_mint.create_token_account_and_mint_to(100).await;
+    user_mfi_account_f
+        .try_bank_deposit(user_token_account_sol.key, sol_bank_f, 100, None)
+        .await?;
+    let user_token_account_usdc = test_f.usdc_mint.create_empty_token_account().await;
+    user_mfi_account_f
+        .try_bank_borrow(user_token_account_usdc.key, usdc_bank_f, 500)
+        .await?;
+
+    test_f.advance_time((SECONDS_PER_YEAR / 4.0) as i64).await;
+    let now = test_f.get_clock().await.unix_timestamp;
+    test_f.set_pyth_oracle_timestamp(PYTH_USDC_FEED, now).await;
+    test_f.set_pyth_oracle_timestamp(PYTH_SOL_FEED, now).await;
+
+    // The banks haven't accrued, the simulation does: $500 of debt at 15% for the quarter
+    let health = user_mfi_account_f.try_simulate_health(vec![]).await?;
+    assert_eq_with_tolerance!(
+        I80F48::from(health.init_liability_value),
+        I80F48!(575),
+        I80F48!(1)
+    );
+    assert_eq_with_tolerance!(
+        I80F48::from(health.init_asset_value),
+        I80F48!(1_000),
+        I80F48!(1)
+    );
+    assert_eq!(
+        I80F48::from(usdc_bank_f.load().await.liability_share_value),
+        I80F48::ONE
+    );
+
+    // Each action applies to the accrued banks as left by the previous ones
+    let health = user_mfi_account_f
+        .try_simulate_health(vec![
+            SimulatedAction {
+                bank: usdc_bank_f.key,
+                action_type: SimulatedActionType::Repay,
+                amount: native!(100, "USDC"),
+            },
+            SimulatedAction {
+                bank: sol_bank_f.key,
+                action_type: SimulatedActionType::Withdraw,
+                amount: native!(10, "SOL"),
+            },
+            SimulatedAction {
+                bank: usdc_bank_f.key,
+                action_type: SimulatedActionType::Borrow,
+                amount: native!(50, "USDC"),
+            },
+        ])
+        .await?;
+    assert_eq_with_tolerance!(
+        I80F48::from(health.init_liability_value),
+        I80F48!(525),
+        I80F48!(1)
+    );
+    assert_eq_with_tolerance!(
+        I80F48::from(health.init_asset_value),
+        I80F48!(900),
+        I80F48!(1)
+    );
+    assert_eq_with_tolerance!(
+        health.balances[0].max_amount as i64,
+        native!(37.5, "SOL", f64) as i64,
+        native!(0.1, "SOL", f64) as i64
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn simulate_health_checks_limits_on_simulated_banks() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+    let usdc_bank_f = test_f.get_bank(&BankMint::Usdc);
+
+    usdc_bank_f
+        .update_config(
+            BankConfigOpt {
+                deposit_limit: Some(native!(1_100, "USDC")),
+                ..Default::default()
+            },
+            None,
+        )
+        .await?;
+
+    let lender_mfi_account_f = test_f.create_surroundfi_account().await;
+    let lender_token_account_usdc = test_f
+        .usdc_mint
+        .create_token_account_and_mint_to(1_000)
+        .await;
+    lender_mfi_account_f
+        .try_bank_deposit(lender_token_account_usdc.key, usdc_bank_f, 1_000, None)
+        .await?;
+
+    let user_mfi_account_f = test_f.create_surroundfi_account().await;
+    let deposit = SimulatedAction {
+        bank: usdc_bank_f.key,
+        action_type: SimulatedActionType::Deposit,
+        amount: native!(60, "USDC"),
+    };
+
+    let health = user_mfi_account_f
+        .try_simulate_health(vec![deposit])
+        .await?;
+    assert_eq_with_tolerance!(
+        I80F48::from(health.init_asset_value),
+        I80F48!(60),
+        I80F48!(1)
+    );
+
+    // The second deposit sees the first in the bank's total deposits
+    let res = user_mfi_account_f
+        .try_simulate_health(vec![deposit, deposit])
+        .await;
+    assert!(res.is_err());
+    assert_custom_error!(res.unwrap_err(), SurroundfiError::BankAssetCapacityExceeded);
+
+    Ok(())
+}
@@ -0,0 +1,81 @@
+//! Program state processor
+//!
+//! Forwards an instruction to another program, optionally signing for this program's authority
+//! PDA. Stands in for the programs that use callback flashloans in tests: as the callback, or as
+//! the authority of a surroundfi account.
+use solana_program::{
+    account_info::AccountInfo,
+    entrypoint::ProgramResult,
+    instruction::{AccountMeta, Instruction},
+    msg,
+    program::{invoke, invoke_signed},
+    program_error::ProgramError,
+    pubkey,
+    pubkey::Pubkey,
+};
+
+pub static TEST_CALLBACK_ID: Pubkey = pubkey!("CALLBACKCALLBACKCALLBACKCALLBACKCALLBACKCAL");
+
+pub const AUTHORITY_SEED: &[u8] = b"authority";
+
+/// Invoke the program of the first account with the rest of the accounts, privileges as received.
+pub const FORWARD: u8 = 0;
+/// As `FORWARD`, also signing for the authority PDA.
+pub const FORWARD_SIGNED: u8 = 1;
+/// Do nothing.
+pub const NOOP: u8 = 2;
+
+#[cfg(not(feature = "no-entrypoint"))]
+solana_program::entrypoint!(process);
+
+pub fn find_authority_address() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[AUTHORITY_SEED], &TEST_CALLBACK_ID)
+}
+
+/// Instruction data to forward `data` to the program of the first account.
+pub fn forward_data(signed: bool, data: &[u8]) -> Vec<u8> {
+    let mut forward_data = vec![if signed { FORWARD_SIGNED } else { FORWARD }];
+    forward_data.extend_from_slice(data);
+    forward_data
+}
+
+/// Processes a [FORWARD], [FORWARD_SIGNED] or [NOOP] instruction.
+pub fn process(program_id: &Pubkey, accounts: &[AccountInfo], input: &[u8]) -> ProgramResult {
+    let (&tag, data) = input
+        .split_first()
+        .ok_or(ProgramError::InvalidInstructionData)?;
+
+    match tag {
+        FORWARD | FORWARD_SIGNED => {
+            msg!("Instruction: Forward");
+            let (program_info, account_infos) = accounts
+                .split_first()
+                .ok_or(ProgramError::NotEnoughAccountKeys)?;
+            let (authority, bump) = Pubkey::find_program_address(&[AUTHORITY_SEED], program_id);
+
+            let ix = Instruction {
+                program_id: *program_info.key,
+                accounts: account_infos
+                    .iter()
+                    .map(|ai| AccountMeta {
+                        pubkey: *ai.key,
+                        is_signer: ai.is_signer || (tag == FORWARD_SIGNED && *ai.key == authority),
+                        is_writable: ai.is_writable,
+                    })
+                    .collect(),
+                data: data.to_vec(),
+            };
+
+            if tag == FORWARD_SIGNED {
+                invoke_signed(&ix, accounts, &[&[AUTHORITY_SEED, &[bump]]])
+            } else {
+                invoke(&ix, accounts)
+            }
+        }
+        NOOP => {
+            msg!("Instruction: Noop");
+            Ok(())
+        }
+        _ => Err(ProgramError::InvalidInstructionData),
+    }
+}
@@ -1,7 +1,11 @@
 use super::utils::load_and_deserialize;
-use crate::prelude::{
-    get_emissions_authority_address, get_emissions_token_account_address, MintFixture,
-    TokenAccountFixture,
+use crate::{
+    prelude::{
+        get_emissions_authority_address, get_emissions_program_address,
+        get_emissions_stream_vault_address, get_emissions_token_account_address,
+        transfer_hook_metas, MintFixture, TokenAccountFixture,
+    },
+    ui_to_native,
 };
 use anchor_lang::{
     prelude::{AccountMeta, Pubkey},
@@ -11,7 +15,10 @@ use anchor_lang::{
 use fixed::types::I80F48;
 use surroundfi::{
     bank_authority_seed,
+    constants::{INSURANCE_STAKE_SEED, RECEIPT_MINT_SEED},
     state::{
+        emissions_program::EmissionsProgram,
+        insurance_stake::InsuranceStake,
         surroundfi_group::{Bank, BankConfigOpt, BankVaultType},
         price::{OraclePriceFeedAdapter, OraclePriceType, PriceAdapter},
     },
@@ -216,7 +223,10 @@ impl BankFixture {
         emissions_mint: Pubkey,
         funding_account: Pubkey,
         token_program: Pubkey,
+        start_time: Option<i64>,
+        end_time: Option<i64>,
     ) -> Result<(), BanksClientError> {
+        let transfer_hook_metas = transfer_hook_metas(self.ctx.clone(), emissions_mint).await;
         let ix = Instruction {
             program_id: surroundfi::id(),
             accounts: surroundfi::accounts::LendingPoolSetupEmissions {
@@ -234,11 +244,16 @@ impl BankFixture {
                 token_program,
                 system_program: solana_program::system_program::id(),
             }
-            .to_account_metas(Some(true)),
+            .to_account_metas(Some(true))
+            .into_iter()
+            .chain(transfer_hook_metas)
+            .collect(),
             data: surroundfi::instruction::LendingPoolSetupEmissions {
                 rate,
                 flags,
                 total_emissions,
+                start_time,
+                end_time,
             }
             .data(),
         };
@@ -268,9 +283,12 @@ impl BankFixture {
         emissions_flags: Option<u64>,
         emissions_rate: Option<u64>,
         additional_emissions: Option<(u64, Pubkey)>,
+        emissions_start_time: Option<i64>,
+        emissions_end_time: Option<i64>,
         token_program: Pubkey,
     ) -> Result<(), BanksClientError> {
         let bank = self.load().await;
+        let transfer_hook_metas = transfer_hook_metas(self.ctx.clone(), bank.emissions_mint).await;
 
         let ix = Instruction {
             program_id: surroundfi::id(),
@@ -287,11 +305,16 @@ impl BankFixture {
                 .0,
                 token_program,
             }
-            .to_account_metas(Some(true)),
+            .to_account_metas(Some(true))
+            .into_iter()
+            .chain(transfer_hook_metas)
+            .collect(),
             data: surroundfi::instruction::LendingPoolUpdateEmissionsParameters {
                 emissions_flags,
                 emissions_rate,
                 additional_emissions: additional_emissions.map(|(a, _)| a),
+                emissions_start_time,
+                emissions_end_time,
             }
             .data(),
         };
@@ -316,12 +339,181 @@ impl BankFixture {
         Ok(())
     }
 
+    pub async fn try_reclaim_emissions(
+        &self,
+        destination_account: &TokenAccountFixture,
+    ) -> Result<(), BanksClientError> {
+        let bank = self.load().await;
+        let transfer_hook_metas = transfer_hook_metas(self.ctx.clone(), bank.emissions_mint).await;
+
+        let ix = Instruction {
+            program_id: surroundfi::id(),
+            accounts: surroundfi::accounts::LendingPoolReclaimEmissions {
+                group: bank.group,
+                admin: self.ctx.borrow().payer.pubkey(),
+                bank: self.key,
+                emissions_mint: bank.emissions_mint,
+                emissions_auth: get_emissions_authority_address(self.key, bank.emissions_mint).0,
+                emissions_vault: get_emissions_token_account_address(
+                    self.key,
+                    bank.emissions_mint,
+                )
+                .0,
+                destination_account: destination_account.key,
+                token_program: destination_account.token_program,
+            }
+            .to_account_metas(Some(true))
+            .into_iter()
+            .chain(transfer_hook_metas)
+            .collect(),
+            data: surroundfi::instruction::LendingPoolReclaimEmissions {}.data(),
+        };
+
+        let tx = {
+            let ctx = self.ctx.borrow_mut();
+
+            Transaction::new_signed_with_payer(
+                &[ix],
+                Some(&ctx.payer.pubkey()),
+                &[&ctx.payer],
+                ctx.last_blockhash,
+            )
+        };
+
+        self.ctx
+            .borrow_mut()
+            .banks_client
+            .process_transaction(tx)
+            .await?;
+
+        Ok(())
+    }
+
+    pub fn get_emissions_program_address(&self) -> Pubkey {
+        get_emissions_program_address(self.key).0
+    }
+
+    pub async fn load_emissions_program(&self) -> EmissionsProgram {
+        load_and_deserialize::<EmissionsProgram>(
+            self.ctx.clone(),
+            &self.get_emissions_program_address(),
+        )
+        .await
+    }
+
+    pub async fn try_init_emissions_program(&self) -> Result<(), BanksClientError> {
+        let ix = Instruction {
+            program_id: surroundfi::id(),
+            accounts: surroundfi::accounts::LendingPoolInitEmissionsProgram {
+                group: self.load().await.group,
+                admin: self.ctx.borrow().payer.pubkey(),
+                bank: self.key,
+                emissions_program: self.get_emissions_program_address(),
+                system_program: solana_program::system_program::id(),
+            }
+            .to_account_metas(Some(true)),
+            data: surroundfi::instruction::LendingPoolInitEmissionsProgram {}.data(),
+        };
+
+        let mut ctx = self.ctx.borrow_mut();
+        let tx = Transaction::new_signed_with_payer(
+            &[ix],
+            Some(&ctx.payer.pubkey()),
+            &[&ctx.payer],
+            ctx.last_blockhash,
+        );
+
+        ctx.banks_client.process_transaction(tx).await
+    }
+
+    /// The stream's funder is the payer
+    pub async fn try_add_emissions_stream(
+        &self,
+        emissions_mint: &MintFixture,
+        flags: u64,
+        rate: u64,
+        start_time: i64,
+        end_time: i64,
+    ) -> Result<(), BanksClientError> {
+        let ix = Instruction {
+            program_id: surroundfi::id(),
+            accounts: surroundfi::accounts::LendingPoolAddEmissionsStream {
+                group: self.load().await.group,
+                admin: self.ctx.borrow().payer.pubkey(),
+                bank: self.key,
+                emissions_program: self.get_emissions_program_address(),
+                emissions_mint: emissions_mint.key,
+                funder: self.ctx.borrow().payer.pubkey(),
+                emissions_vault: get_emissions_stream_vault_address(self.key, emissions_mint.key).0,
+                token_program: emissions_mint.token_program,
+                system_program: solana_program::system_program::id(),
+            }
+            .to_account_metas(Some(true)),
+            data: surroundfi::instruction::LendingPoolAddEmissionsStream {
+                flags,
+                rate,
+                start_time,
+                end_time,
+            }
+            .data(),
+        };
+
+        let mut ctx = self.ctx.borrow_mut();
+        let tx = Transaction::new_signed_with_payer(
+            &[ix],
+            Some(&ctx.payer.pubkey()),
+            &[&ctx.payer],
+            ctx.last_blockhash,
+        );
+
+        ctx.banks_client.process_transaction(tx).await
+    }
+
+    pub async fn try_fund_emissions_stream(
+        &self,
+        stream_id: u8,
+        emissions_mint: &MintFixture,
+        funding_account: Pubkey,
+        amount: u64,
+    ) -> Result<(), BanksClientError> {
+        let transfer_hook_metas = emissions_mint.transfer_hook_metas().await;
+        let ix = Instruction {
+            program_id: surroundfi::id(),
+            accounts: surroundfi::accounts::LendingPoolFundEmissionsStream {
+                bank: self.key,
+                emissions_program: self.get_emissions_program_address(),
+                funder: self.ctx.borrow().payer.pubkey(),
+                emissions_mint: emissions_mint.key,
+                emissions_vault: get_emissions_stream_vault_address(self.key, emissions_mint.key).0,
+                funding_account,
+                token_program: emissions_mint.token_program,
+            }
+            .to_account_metas(Some(true))
+            .into_iter()
+            .chain(transfer_hook_metas)
+            .collect(),
+            data: surroundfi::instruction::LendingPoolFundEmissionsStream { stream_id, amount }
+                .data(),
+        };
+
+        let mut ctx = self.ctx.borrow_mut();
+        let tx = Transaction::new_signed_with_payer(
+            &[ix],
+            Some(&ctx.payer.pubkey()),
+            &[&ctx.payer],
+            ctx.last_blockhash,
+        );
+
+        ctx.banks_client.process_transaction(tx).await
+    }
+
     pub async fn try_withdraw_fees(
         &self,
         receiving_account: &TokenAccountFixture,
         amount: u64,
     ) -> Result<(), BanksClientError> {
         let bank = self.load().await;
+        let transfer_hook_metas = self.mint.transfer_hook_metas().await;
         let mut ctx = self.ctx.borrow_mut();
         let signer_pk = ctx.payer.pubkey();
         let (fee_vault_authority, _) = Pubkey::find_program_address(
@@ -342,6 +534,7 @@ impl BankFixture {
         if self.mint.token_program == spl_token_2022::ID {
             accounts.push(AccountMeta::new_readonly(self.mint.key, false));
         }
+        accounts.extend(transfer_hook_metas);
 
         let ix = Instruction {
             program_id: surroundfi::id(),
@@ -367,6 +560,7 @@ impl BankFixture {
         amount: u64,
     ) -> Result<(), BanksClientError> {
         let bank = self.load().await;
+        let transfer_hook_metas = self.mint.transfer_hook_metas().await;
         let mut ctx = self.ctx.borrow_mut();
         let signer_pk = ctx.payer.pubkey();
         let (insurance_vault_authority, _) = Pubkey::find_program_address(
@@ -387,6 +581,7 @@ impl BankFixture {
         if self.mint.token_program == spl_token_2022::ID {
             accounts.push(AccountMeta::new_readonly(self.mint.key, false));
         }
+        accounts.extend(transfer_hook_metas);
 
         let ix = Instruction {
             program_id: surroundfi::id(),
@@ -406,6 +601,336 @@ impl BankFixture {
         Ok(())
     }
 
+    pub fn get_insurance_stake_address(&self, authority: &Pubkey) -> Pubkey {
+        Pubkey::find_program_address(
+            &[
+                INSURANCE_STAKE_SEED.as_bytes(),
+                self.key.as_ref(),
+                authority.as_ref(),
+            ],
+            &surroundfi::id(),
+        )
+        .0
+    }
+
+    pub async fn load_insurance_stake(&self, authority: &Pubkey) -> InsuranceStake {
+        load_and_deserialize::<InsuranceStake>(
+            self.ctx.clone(),
+            &self.get_insurance_stake_address(authority),
+        )
+        .await
+    }
+
+    pub async fn try_configure_insurance_staking(
+        &self,
+        fee_share: f64,
+        cooldown: i64,
+        unstake_window: i64,
+    ) -> Result<(), BanksClientError> {
+        let bank = self.load().await;
+        let mut ctx = self.ctx.borrow_mut();
+
+        let ix = Instruction {
+            program_id: surroundfi::id(),
+            accounts: surroundfi::accounts::LendingPoolConfigureInsuranceStaking {
+                group: bank.group,
+                admin: ctx.payer.pubkey(),
+                bank: self.key,
+            }
+            .to_account_metas(Some(true)),
+            data: surroundfi::instruction::LendingPoolConfigureInsuranceStaking {
+                fee_share: I80F48::from_num(fee_share).into(),
+                cooldown,
+                unstake_window,
+            }
+            .data(),
+        };
+
+        let tx = Transaction::new_signed_with_payer(
+            &[ix],
+            Some(&ctx.payer.pubkey().clone()),
+            &[&ctx.payer],
+            ctx.last_blockhash,
+        );
+
+        ctx.banks_client.process_transaction(tx).await
+    }
+
+    /// Creates the insurance stake of the payer
+    pub async fn try_init_insurance_stake(&self) -> Result<(), BanksClientError> {
+        let mut ctx = self.ctx.borrow_mut();
+        let authority = ctx.payer.pubkey();
+
+        let ix = Instruction {
+            program_id: surroundfi::id(),
+            accounts: surroundfi::accounts::LendingPoolInitInsuranceStake {
+                bank: self.key,
+                authority,
+                insurance_stake: self.get_insurance_stake_address(&authority),
+                system_program: solana_program::system_program::id(),
+            }
+            .to_account_metas(Some(true)),
+            data: surroundfi::instruction::LendingPoolInitInsuranceStake {}.data(),
+        };
+
+        let tx = Transaction::new_signed_with_payer(
+            &[ix],
+            Some(&ctx.payer.pubkey().clone()),
+            &[&ctx.payer],
+            ctx.last_blockhash,
+        );
+
+        ctx.banks_client.process_transaction(tx).await
+    }
+
+    /// * `signer_token_account` - must be owned by the payer
+    pub async fn try_insurance_stake<T: Into<f64>>(
+        &self,
+        signer_token_account: Pubkey,
+        ui_amount: T,
+    ) -> Result<(), BanksClientError> {
+        let bank = self.load().await;
+        let transfer_hook_metas = self.mint.transfer_hook_metas().await;
+        let mut ctx = self.ctx.borrow_mut();
+        let authority = ctx.payer.pubkey();
+
+        let mut accounts = surroundfi::accounts::LendingPoolInsuranceStake {
+            group: bank.group,
+            bank: self.key,
+            authority,
+            insurance_stake: self.get_insurance_stake_address(&authority),
+            signer_token_account,
+            insurance_vault: bank.insurance_vault,
+            token_program: self.get_token_program(),
+        }
+        .to_account_metas(Some(true));
+        if self.mint.token_program == spl_token_2022::ID {
+            accounts.push(AccountMeta::new_readonly(self.mint.key, false));
+        }
+        accounts.extend(transfer_hook_metas);
+
+        let ix = Instruction {
+            program_id: surroundfi::id(),
+            accounts,
+            data: surroundfi::instruction::LendingPoolInsuranceStake {
+                amount: ui_to_native!(ui_amount.into(), self.mint.mint.decimals),
+            }
+            .data(),
+        };
+
+        let tx = Transaction::new_signed_with_payer(
+            &[ix],
+            Some(&ctx.payer.pubkey().clone()),
+            &[&ctx.payer],
+            ctx.last_blockhash,
+        );
+
+        ctx.banks_client.process_transaction(tx).await
+    }
+
+    pub async fn try_insurance_request_unstake<T: Into<f64>>(
+        &self,
+        ui_amount: T,
+        unstake_all: Option<bool>,
+    ) -> Result<(), BanksClientError> {
+        let mut ctx = self.ctx.borrow_mut();
+        let authority = ctx.payer.pubkey();
+
+        let ix = Instruction {
+            program_id: surroundfi::id(),
+            accounts: surroundfi::accounts::LendingPoolInsuranceRequestUnstake {
+                bank: self.key,
+                authority,
+                insurance_stake: self.get_insurance_stake_address(&authority),
+            }
+            .to_account_metas(Some(true)),
+            data: surroundfi::instruction::LendingPoolInsuranceRequestUnstake {
+                amount: ui_to_native!(ui_amount.into(), self.mint.mint.decimals),
+                unstake_all,
+            }
+            .data(),
+        };
+
+        let tx = Transaction::new_signed_with_payer(
+            &[ix],
+            Some(&ctx.payer.pubkey().clone()),
+            &[&ctx.payer],
+            ctx.last_blockhash,
+        );
+
+        ctx.banks_client.process_transaction(tx).await
+    }
+
+    pub async fn try_insurance_unstake(
+        &self,
+        destination_token_account: Pubkey,
+    ) -> Result<(), BanksClientError> {
+        let bank = self.load().await;
+        let transfer_hook_metas = self.mint.transfer_hook_metas().await;
+        let mut ctx = self.ctx.borrow_mut();
+        let authority = ctx.payer.pubkey();
+
+        let mut accounts = surroundfi::accounts::LendingPoolInsuranceUnstake {
+            group: bank.group,
+            bank: self.key,
+            authority,
+            insurance_stake: self.get_insurance_stake_address(&authority),
+            insurance_vault: bank.insurance_vault,
+            insurance_vault_authority: self.get_vault_authority(BankVaultType::Insurance).0,
+            destination_token_account,
+            token_program: self.get_token_program(),
+        }
+        .to_account_metas(Some(true));
+        if self.mint.token_program == spl_token_2022::ID {
+            accounts.push(AccountMeta::new_readonly(self.mint.key, false));
+        }
+        accounts.extend(transfer_hook_metas);
+
+        let ix = Instruction {
+            program_id: surroundfi::id(),
+            accounts,
+            data: surroundfi::instruction::LendingPoolInsuranceUnstake {}.data(),
+        };
+
+        let tx = Transaction::new_signed_with_payer(
+            &[ix],
+            Some(&ctx.payer.pubkey().clone()),
+            &[&ctx.payer],
+            ctx.last_blockhash,
+        );
+
+        ctx.banks_client.process_transaction(tx).await
+    }
+
+    pub fn get_receipt_mint_address(&self) -> Pubkey {
+        Pubkey::find_program_address(
+            &[RECEIPT_MINT_SEED.as_bytes(), self.key.as_ref()],
+            &surroundfi::id(),
+        )
+        .0
+    }
+
+    pub async fn try_init_receipt_mint(&self) -> Result<(), BanksClientError> {
+        let bank = self.load().await;
+        let mut ctx = self.ctx.borrow_mut();
+
+        let ix = Instruction {
+            program_id: surroundfi::id(),
+            accounts: surroundfi::accounts::LendingPoolInitReceiptMint {
+                group: bank.group,
+                admin: ctx.payer.pubkey(),
+                bank: self.key,
+                mint: self.mint.key,
+                liquidity_vault_authority: self.get_vault_authority(BankVaultType::Liquidity).0,
+                receipt_mint: self.get_receipt_mint_address(),
+                token_program: self.get_token_program(),
+                system_program: solana_program::system_program::id(),
+            }
+            .to_account_metas(Some(true)),
+            data: surroundfi::instruction::LendingPoolInitReceiptMint {}.data(),
+        };
+
+        let tx = Transaction::new_signed_with_payer(
+            &[ix],
+            Some(&ctx.payer.pubkey().clone()),
+            &[&ctx.payer],
+            ctx.last_blockhash,
+        );
+
+        ctx.banks_client.process_transaction(tx).await
+    }
+
+    /// * `receipt_token_account` - must be owned by the payer
+    pub async fn try_deposit_for_receipt<T: Into<f64>>(
+        &self,
+        signer_token_account: Pubkey,
+        receipt_token_account: Pubkey,
+        ui_amount: T,
+    ) -> Result<(), BanksClientError> {
+        let bank = self.load().await;
+        let transfer_hook_metas = self.mint.transfer_hook_metas().await;
+        let mut ctx = self.ctx.borrow_mut();
+
+        let mut accounts = surroundfi::accounts::LendingPoolDepositForReceipt {
+            group: bank.group,
+            bank: self.key,
+            authority: ctx.payer.pubkey(),
+            signer_token_account,
+            receipt_token_account,
+            receipt_mint: bank.receipt_mint,
+            liquidity_vault_authority: self.get_vault_authority(BankVaultType::Liquidity).0,
+            liquidity_vault: bank.liquidity_vault,
+            token_program: self.get_token_program(),
+        }
+        .to_account_metas(Some(true));
+        if self.mint.token_program == spl_token_2022::ID {
+            accounts.push(AccountMeta::new_readonly(self.mint.key, false));
+        }
+        accounts.extend(transfer_hook_metas);
+
+        let ix = Instruction {
+            program_id: surroundfi::id(),
+            accounts,
+            data: surroundfi::instruction::LendingPoolDepositForReceipt {
+                amount: ui_to_native!(ui_amount.into(), self.mint.mint.decimals),
+            }
+            .data(),
+        };
+
+        let tx = Transaction::new_signed_with_payer(
+            &[ix],
+            Some(&ctx.payer.pubkey().clone()),
+            &[&ctx.payer],
+            ctx.last_blockhash,
+        );
+
+        ctx.banks_client.process_transaction(tx).await
+    }
+
+    /// * `receipt_amount` - native amount of receipt tokens (i.e. shares) to redeem
+    pub async fn try_redeem_receipt(
+        &self,
+        receipt_token_account: Pubkey,
+        destination_token_account: Pubkey,
+        receipt_amount: u64,
+    ) -> Result<(), BanksClientError> {
+        let bank = self.load().await;
+        let transfer_hook_metas = self.mint.transfer_hook_metas().await;
+        let mut ctx = self.ctx.borrow_mut();
+
+        let mut accounts = surroundfi::accounts::LendingPoolRedeemReceipt {
+            group: bank.group,
+            bank: self.key,
+            authority: ctx.payer.pubkey(),
+            receipt_token_account,
+            receipt_mint: bank.receipt_mint,
+            destination_token_account,
+            liquidity_vault_authority: self.get_vault_authority(BankVaultType::Liquidity).0,
+            liquidity_vault: bank.liquidity_vault,
+            token_program: self.get_token_program(),
+        }
+        .to_account_metas(Some(true));
+        if self.mint.token_program == spl_token_2022::ID {
+            accounts.push(AccountMeta::new_readonly(self.mint.key, false));
+        }
+        accounts.extend(transfer_hook_metas);
+
+        let ix = Instruction {
+            program_id: surroundfi::id(),
+            accounts,
+            data: surroundfi::instruction::LendingPoolRedeemReceipt { receipt_amount }.data(),
+        };
+
+        let tx = Transaction::new_signed_with_payer(
+            &[ix],
+            Some(&ctx.payer.pubkey().clone()),
+            &[&ctx.payer],
+            ctx.last_blockhash,
+        );
+
+        ctx.banks_client.process_transaction(tx).await
+    }
+
     pub async fn get_vault_token_account(&self, vault_type: BankVaultType) -> TokenAccountFixture {
         let (vault, _) = self.get_vault(vault_type);
 
@@ -429,6 +954,24 @@ impl BankFixture {
             .borrow_mut()
             .set_account(&self.key, &bank_ai.into());
     }
+
+    pub async fn set_mint_extensions(&self, mint_extensions: u64) {
+        let mut bank_ai = self
+            .ctx
+            .borrow_mut()
+            .banks_client
+            .get_account(self.key)
+            .await
+            .unwrap()
+            .unwrap();
+        let bank = bytemuck::from_bytes_mut::<Bank>(&mut bank_ai.data.as_mut_slice()[8..]);
+
+        bank.mint_extensions = mint_extensions;
+
+        self.ctx
+            .borrow_mut()
+            .set_account(&self.key, &bank_ai.into());
+    }
 }
 
 impl Debug for BankFixture {
@@ -8,4 +8,5 @@ pub mod spl;
 pub mod test;
 // pub mod transfer_hook;
 pub mod utils;
+pub use flashloan_callback;
 pub use transfer_hook;
@@ -14,7 +14,8 @@ use anchor_spl::{
                 interest_bearing_mint::InterestBearingConfig,
                 mint_close_authority::MintCloseAuthority, permanent_delegate::PermanentDelegate,
                 transfer_fee::TransferFee, transfer_hook::TransferHook, BaseState,
-                BaseStateWithExtensions, ExtensionType, StateWithExtensionsOwned,
+                BaseStateWithExtensions, BaseStateWithExtensionsMut, ExtensionType,
+                StateWithExtensionsMut, StateWithExtensionsOwned,
             },
         },
     },
@@ -245,6 +246,29 @@ impl MintFixture {
         }
     }
 
+    /// Wraps an existing mint, e.g. one created by the program. The payer is usually not the mint
+    /// authority of such mints, so `mint_to` and friends won't work.
+    pub async fn fetch(ctx: Rc<RefCell<ProgramTestContext>>, address: Pubkey) -> MintFixture {
+        let mint_account = ctx
+            .borrow_mut()
+            .banks_client
+            .get_account(address)
+            .await
+            .unwrap()
+            .unwrap();
+        let mint =
+            StateWithExtensionsOwned::<spl_token_2022::state::Mint>::unpack(mint_account.data)
+                .unwrap()
+                .base;
+
+        MintFixture {
+            ctx,
+            key: address,
+            mint,
+            token_program: mint_account.owner,
+        }
+    }
+
     #[allow(unused)]
     pub async fn reload(&mut self) {
         let mint_account = self
@@ -345,6 +369,99 @@ impl MintFixture {
 
         StateWithExtensionsOwned::unpack(mint_account.data).unwrap()
     }
+
+    /// `MINT_EXTENSION_*` flags of the mint, as recorded on its banks
+    pub async fn mint_extensions(&self) -> u64 {
+        let mint_account = self
+            .ctx
+            .borrow_mut()
+            .banks_client
+            .get_account(self.key)
+            .await
+            .unwrap()
+            .unwrap();
+
+        surroundfi::utils::get_mint_extensions(&mint_account.data).unwrap()
+    }
+
+    /// Rewrites the interest-bearing config of the mint, as if it had accrued `rate` (in basis
+    /// points) for the last `seconds`
+    pub async fn backdate_interest_rate(&self, rate: i16, seconds: i64) {
+        let mut ctx = self.ctx.borrow_mut();
+        let clock: Clock = ctx.banks_client.get_sysvar().await.unwrap();
+        let mut mint_account = ctx
+            .banks_client
+            .get_account(self.key)
+            .await
+            .unwrap()
+            .unwrap();
+
+        {
+            let mut mint = StateWithExtensionsMut::<spl_token_2022::state::Mint>::unpack(
+                &mut mint_account.data,
+            )
+            .unwrap();
+            let config = mint.get_extension_mut::<InterestBearingConfig>().unwrap();
+            let since = clock.unix_timestamp - seconds;
+            config.initialization_timestamp = since.into();
+            config.last_update_timestamp = since.into();
+            config.pre_update_average_rate = rate.into();
+            config.current_rate = rate.into();
+        }
+
+        ctx.set_account(&self.key, &AccountSharedData::from(mint_account));
+    }
+
+    /// Rewrites the freeze authority of the mint
+    pub async fn set_freeze_authority(&self, freeze_authority: Option<Pubkey>) {
+        let mut ctx = self.ctx.borrow_mut();
+        let mut mint_account = ctx
+            .banks_client
+            .get_account(self.key)
+            .await
+            .unwrap()
+            .unwrap();
+
+        let base_data = &mut mint_account.data[..spl_token_2022::state::Mint::LEN];
+        let mut mint = spl_token_2022::state::Mint::unpack_from_slice(base_data).unwrap();
+        mint.freeze_authority = freeze_authority.into();
+        mint.pack_into_slice(base_data);
+
+        ctx.set_account(&self.key, &AccountSharedData::from(mint_account));
+    }
+
+    /// Appends a raw TLV entry to the extensions of the mint, for extension types the
+    /// `spl-token-2022` in use can't initialize. The mint must already have an extension.
+    pub async fn append_raw_extension(&self, extension_type: u16, value: &[u8]) {
+        let mut ctx = self.ctx.borrow_mut();
+        let mut mint_account = ctx
+            .banks_client
+            .get_account(self.key)
+            .await
+            .unwrap()
+            .unwrap();
+
+        mint_account
+            .data
+            .extend_from_slice(&extension_type.to_le_bytes());
+        mint_account
+            .data
+            .extend_from_slice(&(value.len() as u16).to_le_bytes());
+        mint_account.data.extend_from_slice(value);
+        mint_account.lamports = ctx
+            .banks_client
+            .get_rent()
+            .await
+            .unwrap()
+            .minimum_balance(mint_account.data.len());
+
+        ctx.set_account(&self.key, &AccountSharedData::from(mint_account));
+    }
+
+    /// The hook program and extra account metas account of a transfer hook mint, empty otherwise
+    pub async fn transfer_hook_metas(&self) -> Vec<AccountMeta> {
+        transfer_hook_metas(self.ctx.clone(), self.key).await
+    }
 }
 
 pub struct TokenAccountFixture {
@@ -638,6 +755,42 @@ pub async fn balance_of(ctx: Rc<RefCell<ProgramTestContext>>, pubkey: Pubkey) ->
     token_account.amount
 }
 
+/// The hook program and extra account metas account of `mint` if it is a transfer hook mint,
+/// empty otherwise. Passed in the remaining accounts of every ix that transfers the mint.
+pub async fn transfer_hook_metas(
+    ctx: Rc<RefCell<ProgramTestContext>>,
+    mint: Pubkey,
+) -> Vec<AccountMeta> {
+    let mint_account = ctx
+        .borrow_mut()
+        .banks_client
+        .get_account(mint)
+        .await
+        .unwrap()
+        .unwrap();
+    if mint_account.owner != token_2022::ID {
+        return vec![];
+    }
+
+    let mint_state =
+        StateWithExtensionsOwned::<spl_token_2022::state::Mint>::unpack(mint_account.data).unwrap();
+    let hook_program_id = mint_state
+        .get_extension::<TransferHook>()
+        .ok()
+        .and_then(|hook| Option::<Pubkey>::from(hook.program_id));
+
+    match hook_program_id {
+        Some(hook_program_id) => vec![
+            AccountMeta::new_readonly(hook_program_id, false),
+            AccountMeta::new_readonly(
+                get_extra_account_metas_address(&mint, &hook_program_id),
+                false,
+            ),
+        ],
+        None => vec![],
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum SupportedExtension {
     MintCloseAuthority,
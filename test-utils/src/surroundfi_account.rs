@@ -1,10 +1,16 @@
 use super::{bank::BankFixture, prelude::*};
 use crate::ui_to_native;
 use anchor_lang::{prelude::*, system_program, InstructionData, ToAccountMetas};
-use surroundfi::state::{
-    surroundfi_account::SurroundfiAccount,
-    surroundfi_group::{Bank, BankVaultType},
-    price::OracleSetup,
+use surroundfi::{
+    constants::SURROUNDFI_ACCOUNT_EXTENSION_SEED,
+    instructions::{LeverageLeg, SimulatedAction, SimulatedHealth},
+    state::{
+        emissions_program::EmissionsAccrual,
+        surroundfi_account::SurroundfiAccount,
+        surroundfi_account_extension::SurroundfiAccountExtension,
+        surroundfi_group::{Bank, BankVaultType},
+        price::OracleSetup,
+    },
 };
 use solana_program::{instruction::Instruction, sysvar};
 use solana_program_test::{BanksClientError, ProgramTestContext};
@@ -61,14 +67,38 @@ impl SurroundfiAccountFixture {
         }
     }
 
+    /// Wraps an account that already exists, e.g. one created with `initialize_pda`
+    pub fn from_key(ctx: Rc<RefCell<ProgramTestContext>>, key: Pubkey) -> SurroundfiAccountFixture {
+        SurroundfiAccountFixture { ctx, key }
+    }
+
     pub async fn make_bank_deposit_ix<T: Into<f64>>(
         &self,
         funding_account: Pubkey,
         bank: &BankFixture,
         ui_amount: T,
         deposit_up_to_limit: Option<bool>,
+    ) -> Instruction {
+        self.make_bank_deposit_with_referrer_ix(
+            funding_account,
+            bank,
+            ui_amount,
+            deposit_up_to_limit,
+            None,
+        )
+        .await
+    }
+
+    pub async fn make_bank_deposit_with_referrer_ix<T: Into<f64>>(
+        &self,
+        funding_account: Pubkey,
+        bank: &BankFixture,
+        ui_amount: T,
+        deposit_up_to_limit: Option<bool>,
+        referrer_fees: Option<Pubkey>,
     ) -> Instruction {
         let surroundfi_account = self.load().await;
+        let emissions_streams_metas = self.emissions_streams_account_metas(bank).await;
         let ctx = self.ctx.borrow_mut();
 
         let mut accounts = surroundfi::accounts::LendingAccountDeposit {
@@ -84,6 +114,11 @@ impl SurroundfiAccountFixture {
         if bank.mint.token_program == spl_token_2022::ID {
             accounts.push(AccountMeta::new_readonly(bank.mint.key, false));
         }
+        if let Some(referrer_fees) = referrer_fees {
+            accounts.push(AccountMeta::new(referrer_fees, false));
+        }
+        accounts.extend(Self::extension_account_metas(&surroundfi_account));
+        accounts.extend(emissions_streams_metas);
 
         Instruction {
             program_id: surroundfi::id(),
@@ -150,6 +185,36 @@ impl SurroundfiAccountFixture {
         Ok(())
     }
 
+    pub async fn try_bank_deposit_with_referrer<T: Into<f64>>(
+        &self,
+        funding_account: Pubkey,
+        bank: &BankFixture,
+        ui_amount: T,
+        referrer_fees: Pubkey,
+    ) -> anyhow::Result<(), BanksClientError> {
+        let ix = self
+            .make_bank_deposit_with_referrer_ix(
+                funding_account,
+                bank,
+                ui_amount,
+                None,
+                Some(referrer_fees),
+            )
+            .await;
+
+        let mut ctx = self.ctx.borrow_mut();
+        let tx = Transaction::new_signed_with_payer(
+            &[ix],
+            Some(&ctx.payer.pubkey().clone()),
+            &[&ctx.payer],
+            ctx.last_blockhash,
+        );
+
+        ctx.banks_client.process_transaction(tx).await?;
+
+        Ok(())
+    }
+
     pub async fn make_bank_withdraw_ix<T: Into<f64>>(
         &self,
         destination_account: Pubkey,
@@ -193,6 +258,7 @@ impl SurroundfiAccountFixture {
                 .load_observation_account_metas(vec![], exclude_vec)
                 .await,
         );
+        ix.accounts.extend(self.emissions_streams_account_metas(bank).await);
 
         ix
     }
@@ -204,10 +270,36 @@ impl SurroundfiAccountFixture {
         ui_amount: T,
         withdraw_all: Option<bool>,
     ) -> anyhow::Result<(), BanksClientError> {
-        let ix = self
+        let ui_amount = ui_amount.into();
+        let mut ix = self
             .make_bank_withdraw_ix(destination_account, bank, ui_amount, withdraw_all)
             .await;
 
+        if bank.mint.token_program == spl_token_2022::ID {
+            let fetch_account_data_fn = |key| async move {
+                Ok(self
+                    .ctx
+                    .borrow_mut()
+                    .banks_client
+                    .get_account(key)
+                    .await
+                    .map(|acc| acc.map(|a| a.data))?)
+            };
+
+            let payer = self.ctx.borrow().payer.pubkey();
+            let _ = spl_transfer_hook_interface::offchain::add_extra_account_metas_for_execute(
+                &mut ix,
+                &super::transfer_hook::TEST_HOOK_ID,
+                &bank.get_vault(BankVaultType::Liquidity).0,
+                &bank.mint.key,
+                &destination_account,
+                &payer,
+                ui_to_native!(ui_amount, bank.mint.mint.decimals),
+                fetch_account_data_fn,
+            )
+            .await;
+        }
+
         let mut ctx = self.ctx.borrow_mut();
         let tx = Transaction::new_signed_with_payer(
             &[ix],
@@ -226,6 +318,17 @@ impl SurroundfiAccountFixture {
         destination_account: Pubkey,
         bank: &BankFixture,
         ui_amount: T,
+    ) -> Instruction {
+        self.make_bank_borrow_with_referrer_ix(destination_account, bank, ui_amount, None)
+            .await
+    }
+
+    pub async fn make_bank_borrow_with_referrer_ix<T: Into<f64>>(
+        &self,
+        destination_account: Pubkey,
+        bank: &BankFixture,
+        ui_amount: T,
+        referrer_fees: Option<Pubkey>,
     ) -> Instruction {
         let surroundfi_account = self.load().await;
 
@@ -243,6 +346,9 @@ impl SurroundfiAccountFixture {
         if bank.mint.token_program == spl_token_2022::ID {
             accounts.push(AccountMeta::new_readonly(bank.mint.key, false));
         }
+        if let Some(referrer_fees) = referrer_fees {
+            accounts.push(AccountMeta::new(referrer_fees, false));
+        }
 
         let mut ix = Instruction {
             program_id: surroundfi::id(),
@@ -258,6 +364,7 @@ impl SurroundfiAccountFixture {
                 .load_observation_account_metas(vec![bank.key], vec![])
                 .await,
         );
+        ix.accounts.extend(self.emissions_streams_account_metas(bank).await);
 
         ix
     }
@@ -324,6 +431,35 @@ impl SurroundfiAccountFixture {
         Ok(())
     }
 
+    pub async fn try_bank_borrow_with_referrer<T: Into<f64>>(
+        &self,
+        destination_account: Pubkey,
+        bank: &BankFixture,
+        ui_amount: T,
+        referrer_fees: Pubkey,
+    ) -> anyhow::Result<(), BanksClientError> {
+        let ix = self
+            .make_bank_borrow_with_referrer_ix(
+                destination_account,
+                bank,
+                ui_amount,
+                Some(referrer_fees),
+            )
+            .await;
+
+        let mut ctx = self.ctx.borrow_mut();
+        let tx = Transaction::new_signed_with_payer(
+            &[ix],
+            Some(&ctx.payer.pubkey().clone()),
+            &[&ctx.payer],
+            ctx.last_blockhash,
+        );
+
+        ctx.banks_client.process_transaction(tx).await?;
+
+        Ok(())
+    }
+
     pub async fn make_bank_repay_ix<T: Into<f64>>(
         &self,
         funding_account: Pubkey,
@@ -332,6 +468,8 @@ impl SurroundfiAccountFixture {
         repay_all: Option<bool>,
     ) -> Instruction {
         let surroundfi_account = self.load().await;
+        let emissions_streams_metas = self.emissions_streams_account_metas(bank).await;
+        let transfer_hook_metas = bank.mint.transfer_hook_metas().await;
         let ctx = self.ctx.borrow_mut();
 
         let mut accounts = surroundfi::accounts::LendingAccountRepay {
@@ -347,6 +485,9 @@ impl SurroundfiAccountFixture {
         if bank.mint.token_program == spl_token_2022::ID {
             accounts.push(AccountMeta::new_readonly(bank.mint.key, false));
         }
+        accounts.extend(Self::extension_account_metas(&surroundfi_account));
+        accounts.extend(emissions_streams_metas);
+        accounts.extend(transfer_hook_metas);
 
         Instruction {
             program_id: surroundfi::id(),
@@ -397,7 +538,10 @@ impl SurroundfiAccountFixture {
                 authority: ctx.payer.pubkey(),
                 bank: bank.key,
             }
-            .to_account_metas(Some(true)),
+            .to_account_metas(Some(true))
+            .into_iter()
+            .chain(Self::extension_account_metas(&surroundfi_account))
+            .collect(),
             data: surroundfi::instruction::LendingAccountCloseBalance.data(),
         };
 
@@ -445,10 +589,12 @@ impl SurroundfiAccountFixture {
             accounts.push(AccountMeta::new_readonly(liab_bank_fixture.mint.key, false));
         }
 
-        let oracle_accounts = vec![asset_bank.config, liab_bank.config]
+        // Each oracle is followed by the bank's mint if interest-bearing
+        let oracle_accounts = vec![asset_bank, liab_bank]
             .iter()
-            .map(|config| {
-                AccountMeta::new_readonly(
+            .flat_map(|bank| {
+                let config = &bank.config;
+                let oracle = AccountMeta::new_readonly(
                     {
                         match config.oracle_setup {
                             OracleSetup::PythPushOracle => {
@@ -458,7 +604,11 @@ impl SurroundfiAccountFixture {
                         }
                     },
                     false,
-                )
+                );
+                let mint = bank
+                    .is_interest_bearing()
+                    .then(|| AccountMeta::new_readonly(bank.mint, false));
+                std::iter::once(oracle).chain(mint)
             })
             .collect::<Vec<AccountMeta>>();
 
@@ -535,10 +685,12 @@ impl SurroundfiAccountFixture {
         recv_account: &TokenAccountFixture,
     ) -> std::result::Result<(), BanksClientError> {
         let emissions_mint = bank.load().await.emissions_mint;
+        let surroundfi_account = self.load().await;
+        let transfer_hook_metas = transfer_hook_metas(self.ctx.clone(), emissions_mint).await;
         let ix = Instruction {
             program_id: surroundfi::id(),
             accounts: surroundfi::accounts::LendingAccountWithdrawEmissions {
-                group: self.load().await.group,
+                group: surroundfi_account.group,
                 surroundfi_account: self.key,
                 authority: self.ctx.borrow().payer.pubkey(),
                 emissions_mint,
@@ -548,8 +700,222 @@ impl SurroundfiAccountFixture {
                 bank: bank.key,
                 token_program: recv_account.token_program,
             }
+            .to_account_metas(Some(true))
+            .into_iter()
+            .chain(Self::extension_account_metas(&surroundfi_account))
+            .chain(transfer_hook_metas)
+            .collect(),
+            data: surroundfi::instruction::LendingAccountWithdrawEmissions { stream_id: None }
+                .data(),
+        };
+
+        let mut ctx = self.ctx.borrow_mut();
+        let tx = Transaction::new_signed_with_payer(
+            &[ix],
+            Some(&ctx.payer.pubkey().clone()),
+            &[&ctx.payer],
+            ctx.last_blockhash,
+        );
+
+        ctx.banks_client.process_transaction(tx).await
+    }
+
+    pub async fn try_update_emissions_destination(
+        &self,
+        destination_account: Pubkey,
+    ) -> std::result::Result<(), BanksClientError> {
+        let ix = Instruction {
+            program_id: surroundfi::id(),
+            accounts: surroundfi::accounts::SurroundfiAccountUpdateEmissionsDestinationAccount {
+                surroundfi_account: self.key,
+                authority: self.ctx.borrow().payer.pubkey(),
+                destination_account,
+            }
+            .to_account_metas(Some(true)),
+            data: surroundfi::instruction::SurroundfiAccountUpdateEmissionsDestinationAccount {}
+                .data(),
+        };
+
+        let mut ctx = self.ctx.borrow_mut();
+        let tx = Transaction::new_signed_with_payer(
+            &[ix],
+            Some(&ctx.payer.pubkey().clone()),
+            &[&ctx.payer],
+            ctx.last_blockhash,
+        );
+
+        ctx.banks_client.process_transaction(tx).await
+    }
+
+    /// `recv_account` must be the ATA of the account's emissions destination
+    pub async fn try_withdraw_emissions_permissionless(
+        &self,
+        bank: &BankFixture,
+        recv_account: &TokenAccountFixture,
+    ) -> std::result::Result<(), BanksClientError> {
+        let emissions_mint = bank.load().await.emissions_mint;
+        let surroundfi_account = self.load().await;
+        let transfer_hook_metas = transfer_hook_metas(self.ctx.clone(), emissions_mint).await;
+        let ix = Instruction {
+            program_id: surroundfi::id(),
+            accounts: surroundfi::accounts::LendingAccountWithdrawEmissionsPermissionless {
+                group: surroundfi_account.group,
+                surroundfi_account: self.key,
+                bank: bank.key,
+                emissions_mint,
+                emissions_auth: get_emissions_authority_address(bank.key, emissions_mint).0,
+                emissions_vault: get_emissions_token_account_address(bank.key, emissions_mint).0,
+                destination_account: recv_account.key,
+                token_program: recv_account.token_program,
+            }
+            .to_account_metas(Some(true))
+            .into_iter()
+            .chain(Self::extension_account_metas(&surroundfi_account))
+            .chain(transfer_hook_metas)
+            .collect(),
+            data: surroundfi::instruction::LendingAccountWithdrawEmissionsPermissionless {}.data(),
+        };
+
+        let mut ctx = self.ctx.borrow_mut();
+        let tx = Transaction::new_signed_with_payer(
+            &[ix],
+            Some(&ctx.payer.pubkey().clone()),
+            &[&ctx.payer],
+            ctx.last_blockhash,
+        );
+
+        ctx.banks_client.process_transaction(tx).await
+    }
+
+    pub async fn load_emissions_accrual(&self, bank: &BankFixture) -> EmissionsAccrual {
+        load_and_deserialize::<EmissionsAccrual>(
+            self.ctx.clone(),
+            &get_emissions_accrual_address(self.key, bank.key).0,
+        )
+        .await
+    }
+
+    pub async fn try_init_emissions_accrual(
+        &self,
+        bank: &BankFixture,
+    ) -> std::result::Result<(), BanksClientError> {
+        let ix = Instruction {
+            program_id: surroundfi::id(),
+            accounts: surroundfi::accounts::LendingAccountInitEmissionsAccrual {
+                surroundfi_account: self.key,
+                authority: self.ctx.borrow().payer.pubkey(),
+                bank: bank.key,
+                emissions_accrual: get_emissions_accrual_address(self.key, bank.key).0,
+                fee_payer: self.ctx.borrow().payer.pubkey(),
+                system_program: system_program::ID,
+            }
             .to_account_metas(Some(true)),
-            data: surroundfi::instruction::LendingAccountWithdrawEmissions {}.data(),
+            data: surroundfi::instruction::LendingAccountInitEmissionsAccrual {}.data(),
+        };
+
+        let mut ctx = self.ctx.borrow_mut();
+        let tx = Transaction::new_signed_with_payer(
+            &[ix],
+            Some(&ctx.payer.pubkey().clone()),
+            &[&ctx.payer],
+            ctx.last_blockhash,
+        );
+
+        ctx.banks_client.process_transaction(tx).await
+    }
+
+    /// The bank's emissions program and this account's emissions accrual for the bank (both
+    /// writable), if the accrual exists. Passed after all other remaining accounts.
+    pub async fn emissions_streams_account_metas(&self, bank: &BankFixture) -> Vec<AccountMeta> {
+        let emissions_accrual = get_emissions_accrual_address(self.key, bank.key).0;
+        let accrual_exists = self
+            .ctx
+            .borrow_mut()
+            .banks_client
+            .get_account(emissions_accrual)
+            .await
+            .unwrap()
+            .is_some();
+
+        if accrual_exists {
+            vec![
+                AccountMeta::new(bank.get_emissions_program_address(), false),
+                AccountMeta::new(emissions_accrual, false),
+            ]
+        } else {
+            vec![]
+        }
+    }
+
+    /// Settle the bank's emissions and, if the account has an accrual for the bank, all its streams
+    /// (`stream_id` None), or only the stream `stream_id`.
+    pub async fn try_settle_emissions(
+        &self,
+        bank: &BankFixture,
+        stream_id: Option<u8>,
+    ) -> std::result::Result<(), BanksClientError> {
+        let surroundfi_account = self.load().await;
+        let emissions_streams_metas = self.emissions_streams_account_metas(bank).await;
+        let ix = Instruction {
+            program_id: surroundfi::id(),
+            accounts: surroundfi::accounts::LendingAccountSettleEmissions {
+                surroundfi_account: self.key,
+                bank: bank.key,
+            }
+            .to_account_metas(Some(true))
+            .into_iter()
+            .chain(Self::extension_account_metas(&surroundfi_account))
+            .chain(emissions_streams_metas)
+            .collect(),
+            data: surroundfi::instruction::LendingAccountSettleEmissions { stream_id }.data(),
+        };
+
+        let mut ctx = self.ctx.borrow_mut();
+        let tx = Transaction::new_signed_with_payer(
+            &[ix],
+            Some(&ctx.payer.pubkey().clone()),
+            &[&ctx.payer],
+            ctx.last_blockhash,
+        );
+
+        ctx.banks_client.process_transaction(tx).await
+    }
+
+    pub async fn try_withdraw_stream_emissions(
+        &self,
+        bank: &BankFixture,
+        stream_id: u8,
+        recv_account: &TokenAccountFixture,
+    ) -> std::result::Result<(), BanksClientError> {
+        let surroundfi_account = self.load().await;
+        let emissions_mint = recv_account.token.mint;
+        let transfer_hook_metas = transfer_hook_metas(self.ctx.clone(), emissions_mint).await;
+        let ix = Instruction {
+            program_id: surroundfi::id(),
+            accounts: surroundfi::accounts::LendingAccountWithdrawEmissions {
+                group: surroundfi_account.group,
+                surroundfi_account: self.key,
+                authority: self.ctx.borrow().payer.pubkey(),
+                bank: bank.key,
+                emissions_mint,
+                emissions_auth: get_emissions_authority_address(bank.key, emissions_mint).0,
+                emissions_vault: get_emissions_stream_vault_address(bank.key, emissions_mint).0,
+                destination_account: recv_account.key,
+                token_program: recv_account.token_program,
+            }
+            .to_account_metas(Some(true))
+            .into_iter()
+            .chain(Self::extension_account_metas(&surroundfi_account))
+            .chain([
+                AccountMeta::new(bank.get_emissions_program_address(), false),
+                AccountMeta::new(get_emissions_accrual_address(self.key, bank.key).0, false),
+            ])
+            .chain(transfer_hook_metas)
+            .collect(),
+            data: surroundfi::instruction::LendingAccountWithdrawEmissions {
+                stream_id: Some(stream_id),
+            }
+            .data(),
         };
 
         let mut ctx = self.ctx.borrow_mut();
@@ -682,24 +1048,303 @@ impl SurroundfiAccountFixture {
         ctx.banks_client.process_transaction(tx).await
     }
 
+    pub async fn make_callback_flashloan_ix<T: Into<f64>>(
+        &self,
+        flashloan_token_account: Pubkey,
+        bank: &BankFixture,
+        ui_amount: T,
+        callback_program: Pubkey,
+        callback_accounts: Vec<AccountMeta>,
+        callback_data: Vec<u8>,
+    ) -> Instruction {
+        let surroundfi_account = self.load().await;
+
+        let mut accounts = surroundfi::accounts::LendingAccountCallbackFlashloan {
+            group: surroundfi_account.group,
+            surroundfi_account: self.key,
+            authority: self.ctx.borrow().payer.pubkey(),
+            bank: bank.key,
+            flashloan_token_account,
+            bank_liquidity_vault_authority: bank.get_vault_authority(BankVaultType::Liquidity).0,
+            liquidity_vault: bank.get_vault(BankVaultType::Liquidity).0,
+            callback_program,
+            token_program: bank.get_token_program(),
+        }
+        .to_account_metas(Some(true));
+        if bank.mint.token_program == spl_token_2022::ID {
+            accounts.push(AccountMeta::new_readonly(bank.mint.key, false));
+        }
+
+        let callback_accounts_len = callback_accounts.len() as u8;
+        accounts.extend(callback_accounts);
+        accounts.extend(
+            self.load_observation_account_metas(vec![bank.key], vec![])
+                .await,
+        );
+
+        Instruction {
+            program_id: surroundfi::id(),
+            accounts,
+            data: surroundfi::instruction::LendingAccountCallbackFlashloan {
+                amount: ui_to_native!(ui_amount.into(), bank.mint.mint.decimals),
+                callback_accounts_len,
+                callback_data,
+            }
+            .data(),
+        }
+    }
+
+    pub async fn try_callback_flashloan<T: Into<f64>>(
+        &self,
+        flashloan_token_account: Pubkey,
+        bank: &BankFixture,
+        ui_amount: T,
+        callback_program: Pubkey,
+        callback_accounts: Vec<AccountMeta>,
+        callback_data: Vec<u8>,
+    ) -> std::result::Result<(), BanksClientError> {
+        let ix = self
+            .make_callback_flashloan_ix(
+                flashloan_token_account,
+                bank,
+                ui_amount,
+                callback_program,
+                callback_accounts,
+                callback_data,
+            )
+            .await;
+
+        let mut ctx = self.ctx.borrow_mut();
+
+        let tx = Transaction::new_signed_with_payer(
+            &[ix],
+            Some(&ctx.payer.pubkey().clone()),
+            &[&ctx.payer],
+            ctx.last_blockhash,
+        );
+
+        ctx.banks_client.process_transaction(tx).await
+    }
+
+    /// `swap_accounts` are passed to `swap_program` in every leg
+    #[allow(clippy::too_many_arguments)]
+    pub async fn make_open_leveraged_position_ix<T: Into<f64>>(
+        &self,
+        collateral_bank: &BankFixture,
+        collateral_token_account: Pubkey,
+        debt_bank: &BankFixture,
+        debt_token_account: Pubkey,
+        ui_collateral_amount: T,
+        legs: Vec<LeverageLeg>,
+        swap_program: Pubkey,
+        swap_accounts: Vec<AccountMeta>,
+    ) -> Instruction {
+        let swap_accounts_len = swap_accounts.len() as u8;
+        let accounts = self
+            .leveraged_position_account_metas(
+                collateral_bank,
+                collateral_token_account,
+                debt_bank,
+                debt_token_account,
+                swap_program,
+                swap_accounts,
+            )
+            .await;
+
+        Instruction {
+            program_id: surroundfi::id(),
+            accounts,
+            data: surroundfi::instruction::LendingAccountOpenLeveragedPosition {
+                collateral_amount: ui_to_native!(
+                    ui_collateral_amount.into(),
+                    collateral_bank.mint.mint.decimals
+                ),
+                legs,
+                swap_accounts_len,
+            }
+            .data(),
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub async fn try_open_leveraged_position<T: Into<f64>>(
+        &self,
+        collateral_bank: &BankFixture,
+        collateral_token_account: Pubkey,
+        debt_bank: &BankFixture,
+        debt_token_account: Pubkey,
+        ui_collateral_amount: T,
+        legs: Vec<LeverageLeg>,
+        swap_program: Pubkey,
+        swap_accounts: Vec<AccountMeta>,
+    ) -> std::result::Result<(), BanksClientError> {
+        let ix = self
+            .make_open_leveraged_position_ix(
+                collateral_bank,
+                collateral_token_account,
+                debt_bank,
+                debt_token_account,
+                ui_collateral_amount,
+                legs,
+                swap_program,
+                swap_accounts,
+            )
+            .await;
+
+        self.send_with_compute_budget(ix).await
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub async fn make_close_leveraged_position_ix<T: Into<f64>>(
+        &self,
+        collateral_bank: &BankFixture,
+        collateral_token_account: Pubkey,
+        debt_bank: &BankFixture,
+        debt_token_account: Pubkey,
+        ui_collateral_amount: T,
+        swap_program: Pubkey,
+        swap_accounts: Vec<AccountMeta>,
+        swap_data: Vec<u8>,
+    ) -> Instruction {
+        let swap_accounts_len = swap_accounts.len() as u8;
+        let accounts = self
+            .leveraged_position_account_metas(
+                collateral_bank,
+                collateral_token_account,
+                debt_bank,
+                debt_token_account,
+                swap_program,
+                swap_accounts,
+            )
+            .await;
+
+        Instruction {
+            program_id: surroundfi::id(),
+            accounts,
+            data: surroundfi::instruction::LendingAccountCloseLeveragedPosition {
+                collateral_amount: ui_to_native!(
+                    ui_collateral_amount.into(),
+                    collateral_bank.mint.mint.decimals
+                ),
+                swap_accounts_len,
+                swap_data,
+            }
+            .data(),
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub async fn try_close_leveraged_position<T: Into<f64>>(
+        &self,
+        collateral_bank: &BankFixture,
+        collateral_token_account: Pubkey,
+        debt_bank: &BankFixture,
+        debt_token_account: Pubkey,
+        ui_collateral_amount: T,
+        swap_program: Pubkey,
+        swap_accounts: Vec<AccountMeta>,
+        swap_data: Vec<u8>,
+    ) -> std::result::Result<(), BanksClientError> {
+        let ix = self
+            .make_close_leveraged_position_ix(
+                collateral_bank,
+                collateral_token_account,
+                debt_bank,
+                debt_token_account,
+                ui_collateral_amount,
+                swap_program,
+                swap_accounts,
+                swap_data,
+            )
+            .await;
+
+        self.send_with_compute_budget(ix).await
+    }
+
+    /// The accounts of a leveraged position ix, then the token-2022 mints, `swap_accounts` and the
+    /// observation accounts of both banks
+    async fn leveraged_position_account_metas(
+        &self,
+        collateral_bank: &BankFixture,
+        collateral_token_account: Pubkey,
+        debt_bank: &BankFixture,
+        debt_token_account: Pubkey,
+        swap_program: Pubkey,
+        swap_accounts: Vec<AccountMeta>,
+    ) -> Vec<AccountMeta> {
+        let surroundfi_account = self.load().await;
+
+        let mut accounts = surroundfi::accounts::LendingAccountLeveragedPosition {
+            group: surroundfi_account.group,
+            surroundfi_account: self.key,
+            authority: self.ctx.borrow().payer.pubkey(),
+            collateral_bank: collateral_bank.key,
+            collateral_liquidity_vault: collateral_bank.get_vault(BankVaultType::Liquidity).0,
+            collateral_liquidity_vault_authority: collateral_bank
+                .get_vault_authority(BankVaultType::Liquidity)
+                .0,
+            collateral_token_account,
+            collateral_token_program: collateral_bank.get_token_program(),
+            debt_bank: debt_bank.key,
+            debt_liquidity_vault: debt_bank.get_vault(BankVaultType::Liquidity).0,
+            debt_liquidity_vault_authority: debt_bank
+                .get_vault_authority(BankVaultType::Liquidity)
+                .0,
+            debt_token_account,
+            debt_token_program: debt_bank.get_token_program(),
+            swap_program,
+        }
+        .to_account_metas(Some(true));
+        for bank in [collateral_bank, debt_bank] {
+            if bank.mint.token_program == spl_token_2022::ID {
+                accounts.push(AccountMeta::new_readonly(bank.mint.key, false));
+            }
+        }
+        accounts.extend(swap_accounts);
+        accounts.extend(
+            self.load_observation_account_metas(vec![collateral_bank.key, debt_bank.key], vec![])
+                .await,
+        );
+
+        accounts
+    }
+
+    async fn send_with_compute_budget(
+        &self,
+        ix: Instruction,
+    ) -> std::result::Result<(), BanksClientError> {
+        let compute_budget_ix = ComputeBudgetInstruction::set_compute_unit_limit(1_400_000);
+
+        let mut ctx = self.ctx.borrow_mut();
+        let tx = Transaction::new_signed_with_payer(
+            &[compute_budget_ix, ix],
+            Some(&ctx.payer.pubkey().clone()),
+            &[&ctx.payer],
+            ctx.last_blockhash,
+        );
+
+        ctx.banks_client.process_transaction(tx).await
+    }
+
     pub async fn load_observation_account_metas(
         &self,
         include_banks: Vec<Pubkey>,
         exclude_banks: Vec<Pubkey>,
     ) -> Vec<AccountMeta> {
         let surroundfi_account = self.load().await;
-        // Check all active banks in surroundfi account balances
-        let mut bank_pks = surroundfi_account
+        // Check all active banks in surroundfi account balances, then those of the extension
+        let mut balances = surroundfi_account
             .lending_account
-            .balances
+            .get_active_balances_iter()
+            .copied()
+            .collect::<Vec<_>>();
+        if surroundfi_account.has_extension() {
+            let extension = self.load_extension().await;
+            balances.extend(extension.get_active_balances_iter().copied());
+        }
+        let mut bank_pks = balances
             .iter()
-            .filter_map(|balance| {
-                if balance.is_active() {
-                    Some(balance.bank_pk)
-                } else {
-                    None
-                }
-            })
+            .map(|balance| balance.bank_pk)
             .collect::<Vec<_>>();
 
         // Add bank pks in include_banks if they are not already in bank_pks
@@ -719,37 +1364,165 @@ impl SurroundfiAccountFixture {
         }
 
         // Bank -> AccountMetas
-        let account_metas = banks
-            .iter()
-            .zip(bank_pks.iter())
-            .flat_map(|(bank, bank_pk)| {
-                let oracle_key = {
-                    let oracle_key = bank.config.oracle_keys[0];
-                    match bank.config.oracle_setup {
-                        OracleSetup::PythPushOracle => {
-                            get_oracle_id_from_feed_id(oracle_key).unwrap()
-                        }
-                        _ => oracle_key,
-                    }
-                };
-
-                vec![
-                    AccountMeta {
-                        pubkey: *bank_pk,
-                        is_signer: false,
-                        is_writable: false,
-                    },
-                    AccountMeta {
-                        pubkey: oracle_key,
-                        is_signer: false,
-                        is_writable: false,
-                    },
-                ]
-            })
-            .collect::<Vec<_>>();
+        let mut account_metas = vec![];
+        for (bank, bank_pk) in banks.iter().zip(bank_pks.iter()) {
+            account_metas.push(AccountMeta::new_readonly(*bank_pk, false));
+            account_metas.push(AccountMeta::new_readonly(
+                get_oracle_account_key(&bank.config),
+                false,
+            ));
+
+            // Receipt banks also pass the oracle of the underlying bank (`oracle_keys[0]`)
+            if bank.config.oracle_setup == OracleSetup::ReceiptToken {
+                let underlying_bank =
+                    load_and_deserialize::<Bank>(self.ctx.clone(), &bank.config.oracle_keys[0])
+                        .await;
+                account_metas.push(AccountMeta::new_readonly(
+                    get_oracle_account_key(&underlying_bank.config),
+                    false,
+                ));
+            }
+
+            // Existing balances pass the mint if they were created (or refreshed) as such
+            let interest_bearing = balances
+                .iter()
+                .find(|balance| balance.bank_pk.eq(bank_pk))
+                .map_or(bank.is_interest_bearing(), |balance| {
+                    balance.is_interest_bearing()
+                });
+            if interest_bearing {
+                account_metas.push(AccountMeta::new_readonly(bank.mint, false));
+            }
+        }
+        account_metas.extend(Self::extension_account_metas(&surroundfi_account));
+
         account_metas
     }
 
+    /// The extension (writable), if the account has one. Passed after all other remaining accounts.
+    pub fn extension_account_metas(surroundfi_account: &SurroundfiAccount) -> Vec<AccountMeta> {
+        if surroundfi_account.has_extension() {
+            vec![AccountMeta::new(surroundfi_account.extension, false)]
+        } else {
+            vec![]
+        }
+    }
+
+    pub fn get_extension_address(&self) -> Pubkey {
+        Pubkey::find_program_address(
+            &[SURROUNDFI_ACCOUNT_EXTENSION_SEED.as_bytes(), self.key.as_ref()],
+            &surroundfi::id(),
+        )
+        .0
+    }
+
+    pub async fn try_init_extension(&self) -> std::result::Result<(), BanksClientError> {
+        let mut ctx = self.ctx.borrow_mut();
+
+        let ix = Instruction {
+            program_id: surroundfi::id(),
+            accounts: surroundfi::accounts::SurroundfiAccountInitExtension {
+                surroundfi_account: self.key,
+                extension: self.get_extension_address(),
+                authority: ctx.payer.pubkey(),
+                fee_payer: ctx.payer.pubkey(),
+                system_program: system_program::ID,
+            }
+            .to_account_metas(Some(true)),
+            data: surroundfi::instruction::SurroundfiAccountInitExtension {}.data(),
+        };
+
+        let tx = Transaction::new_signed_with_payer(
+            &[ix],
+            Some(&ctx.payer.pubkey().clone()),
+            &[&ctx.payer],
+            ctx.last_blockhash,
+        );
+
+        ctx.banks_client.process_transaction(tx).await
+    }
+
+    pub async fn try_close_extension(&self) -> std::result::Result<(), BanksClientError> {
+        let mut ctx = self.ctx.borrow_mut();
+
+        let ix = Instruction {
+            program_id: surroundfi::id(),
+            accounts: surroundfi::accounts::SurroundfiAccountCloseExtension {
+                surroundfi_account: self.key,
+                extension: self.get_extension_address(),
+                authority: ctx.payer.pubkey(),
+                fee_payer: ctx.payer.pubkey(),
+            }
+            .to_account_metas(Some(true)),
+            data: surroundfi::instruction::SurroundfiAccountCloseExtension {}.data(),
+        };
+
+        let tx = Transaction::new_signed_with_payer(
+            &[ix],
+            Some(&ctx.payer.pubkey().clone()),
+            &[&ctx.payer],
+            ctx.last_blockhash,
+        );
+
+        ctx.banks_client.process_transaction(tx).await
+    }
+
+    /// Simulates `lending_account_simulate_health` with the given actions and decodes its return
+    /// data. Banks used in the actions are appended to the remaining accounts if the account has no
+    /// position in them yet.
+    pub async fn try_simulate_health(
+        &self,
+        actions: Vec<SimulatedAction>,
+    ) -> std::result::Result<SimulatedHealth, BanksClientError> {
+        let surroundfi_account = self.load().await;
+
+        let mut ix = Instruction {
+            program_id: surroundfi::id(),
+            accounts: surroundfi::accounts::LendingAccountSimulateHealth {
+                group: surroundfi_account.group,
+                surroundfi_account: self.key,
+            }
+            .to_account_metas(Some(true)),
+            data: surroundfi::instruction::LendingAccountSimulateHealth {
+                actions: actions.clone(),
+            }
+            .data(),
+        };
+        ix.accounts.extend_from_slice(
+            &self
+                .load_observation_account_metas(
+                    actions.iter().map(|action| action.bank).collect(),
+                    vec![],
+                )
+                .await,
+        );
+
+        let mut ctx = self.ctx.borrow_mut();
+        let tx = Transaction::new_signed_with_payer(
+            &[
+                ComputeBudgetInstruction::set_compute_unit_limit(1_400_000),
+                ix,
+            ],
+            Some(&ctx.payer.pubkey()),
+            &[&ctx.payer],
+            ctx.last_blockhash,
+        );
+
+        let res = ctx.banks_client.simulate_transaction(tx).await?;
+        res.result.unwrap()?;
+
+        let return_data = res.simulation_details.unwrap().return_data.unwrap();
+        Ok(SimulatedHealth::try_from_slice(&return_data.data).unwrap())
+    }
+
+    pub async fn load_extension(&self) -> SurroundfiAccountExtension {
+        load_and_deserialize::<SurroundfiAccountExtension>(
+            self.ctx.clone(),
+            &self.get_extension_address(),
+        )
+        .await
+    }
+
     pub async fn set_account(&self, sfi_account: &SurroundfiAccount) -> anyhow::Result<()> {
         let mut ctx = self.ctx.borrow_mut();
         let mut account = ctx.banks_client.get_account(self.key).await?.unwrap();
@@ -1,21 +1,26 @@
 use super::{bank::BankFixture, surroundfi_account::SurroundfiAccountFixture};
-use crate::prelude::{get_oracle_id_from_feed_id, MintFixture};
+use crate::prelude::{get_oracle_account_key, get_oracle_id_from_feed_id, MintFixture};
 use crate::utils::*;
 use anchor_lang::{prelude::*, solana_program::system_program, InstructionData};
 
 use anchor_spl::associated_token::get_associated_token_address_with_program_id;
 use anyhow::Result;
 use bytemuck::bytes_of;
+use fixed::types::I80F48;
 use surroundfi::constants::{
-    FEE_STATE_SEED, INIT_BANK_ORIGINATION_FEE_DEFAULT, PROTOCOL_FEE_FIXED_DEFAULT,
-    PROTOCOL_FEE_RATE_DEFAULT,
+    FEE_DISTRIBUTION_SEED, FEE_STATE_SEED, INIT_BANK_ORIGINATION_FEE_DEFAULT,
+    PROTOCOL_FEE_FIXED_DEFAULT, PROTOCOL_FEE_RATE_DEFAULT, REFERRER_FEES_SEED,
 };
+use surroundfi::state::fee_distribution::{FeeDistribution, FeeRecipientConfig};
 use surroundfi::state::fee_state::FeeState;
+use surroundfi::state::surroundfi_account::BalanceSide;
 use surroundfi::state::surroundfi_group::BankConfigCompact;
 use surroundfi::state::price::OracleSetup;
 use surroundfi::{
     prelude::SurroundfiGroup,
-    state::surroundfi_group::{BankConfig, BankConfigOpt, BankVaultType},
+    state::surroundfi_group::{
+        ArenaConfig, AssetTagMatrix, Bank, BankConfig, BankConfigOpt, BankVaultType, GroupConfigOpt,
+    },
 };
 use solana_program::sysvar;
 use solana_program_test::*;
@@ -77,6 +82,8 @@ impl SurroundfiGroupFixture {
                 data: surroundfi::instruction::SurroundfiGroupConfigure {
                     new_admin: admin,
                     is_arena_group: false,
+                    group_config: GroupConfigOpt::default(),
+                    staked_stable_borrow_enabled: None,
                 }
                 .data(),
             };
@@ -185,7 +192,7 @@ impl SurroundfiGroupFixture {
         }
         .to_account_metas(Some(true));
 
-        let init_ix = Instruction {
+        let mut init_ix = Instruction {
             program_id: surroundfi::id(),
             accounts,
             data: surroundfi::instruction::LendingPoolAddBank {
@@ -193,6 +200,16 @@ impl SurroundfiGroupFixture {
             }
             .data(),
         };
+        init_ix
+            .accounts
+            .extend(bank_asset_mint_fixture.transfer_hook_metas().await);
+        if bank_config.oracle_setup == OracleSetup::ReceiptToken {
+            // Add-bank checks the underlying bank issues the receipt mint
+            init_ix.accounts.push(AccountMeta::new_readonly(
+                bank_config.oracle_keys[0],
+                false,
+            ));
+        }
 
         let feed_oracle = {
             if bank_config.oracle_setup == OracleSetup::PythPushOracle
@@ -204,13 +221,25 @@ impl SurroundfiGroupFixture {
             }
         };
 
-        let config_oracle_ix = self.make_lending_pool_configure_bank_oracle_ix(
+        let mut config_oracle_ix = self.make_lending_pool_configure_bank_oracle_ix(
             &bank_fixture,
             bank_config.oracle_setup as u8,
             bank_config.oracle_keys[0],
             feed_oracle,
         );
 
+        if bank_config.oracle_setup == OracleSetup::ReceiptToken {
+            // Receipt banks are priced by the oracle of the underlying bank (`oracle_keys[0]`)
+            let underlying_bank =
+                load_and_deserialize::<Bank>(self.ctx.clone(), &bank_config.oracle_keys[0]).await;
+            config_oracle_ix
+                .accounts
+                .push(AccountMeta::new_readonly(
+                    get_oracle_account_key(&underlying_bank.config),
+                    false,
+                ));
+        }
+
         let tx = Transaction::new_signed_with_payer(
             &[init_ix, config_oracle_ix],
             Some(&self.ctx.borrow().payer.pubkey().clone()),
@@ -274,7 +303,7 @@ impl SurroundfiGroupFixture {
         }
         .to_account_metas(Some(true));
 
-        let init_ix = Instruction {
+        let mut init_ix = Instruction {
             program_id: surroundfi::id(),
             accounts,
             data: surroundfi::instruction::LendingPoolAddBankWithSeed {
@@ -283,6 +312,16 @@ impl SurroundfiGroupFixture {
             }
             .data(),
         };
+        init_ix
+            .accounts
+            .extend(bank_asset_mint_fixture.transfer_hook_metas().await);
+        if bank_config.oracle_setup == OracleSetup::ReceiptToken {
+            // Add-bank checks the underlying bank issues the receipt mint
+            init_ix.accounts.push(AccountMeta::new_readonly(
+                bank_config.oracle_keys[0],
+                false,
+            ));
+        }
 
         let feed_oracle = {
             if bank_config.oracle_setup == OracleSetup::PythPushOracle
@@ -413,6 +452,44 @@ impl SurroundfiGroupFixture {
         Ok(())
     }
 
+    /// `accounts` are the surroundfi accounts and extensions whose balances in `bank` to sync
+    pub async fn try_refresh_mint_extensions(
+        &self,
+        bank: &BankFixture,
+        accounts: &[Pubkey],
+    ) -> Result<(), BanksClientError> {
+        let mut ctx = self.ctx.borrow_mut();
+
+        let mut account_metas = surroundfi::accounts::LendingPoolRefreshMintExtensions {
+            group: self.key,
+            bank: bank.key,
+            bank_mint: bank.mint.key,
+        }
+        .to_account_metas(Some(true));
+        account_metas.extend(
+            accounts
+                .iter()
+                .map(|account| AccountMeta::new(*account, false)),
+        );
+
+        let ix = Instruction {
+            program_id: surroundfi::id(),
+            accounts: account_metas,
+            data: surroundfi::instruction::LendingPoolRefreshMintExtensions {}.data(),
+        };
+
+        let tx = Transaction::new_signed_with_payer(
+            &[ix],
+            Some(&ctx.payer.pubkey().clone()),
+            &[&ctx.payer],
+            ctx.last_blockhash,
+        );
+
+        ctx.banks_client.process_transaction(tx).await?;
+
+        Ok(())
+    }
+
     pub async fn try_update(
         &self,
         new_admin: Pubkey,
@@ -428,6 +505,182 @@ impl SurroundfiGroupFixture {
             data: surroundfi::instruction::SurroundfiGroupConfigure {
                 new_admin,
                 is_arena_group,
+                group_config: GroupConfigOpt::default(),
+                staked_stable_borrow_enabled: None,
+            }
+            .data(),
+        };
+
+        let tx = Transaction::new_signed_with_payer(
+            &[ix],
+            Some(&self.ctx.borrow().payer.pubkey().clone()),
+            &[&self.ctx.borrow().payer],
+            self.ctx.borrow().last_blockhash,
+        );
+
+        self.ctx
+            .borrow_mut()
+            .banks_client
+            .process_transaction(tx)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Let accounts with staked collateral borrow stablecoin banks, keeping the other settings
+    pub async fn try_update_staked_stable_borrow(
+        &self,
+        enabled: bool,
+    ) -> Result<(), BanksClientError> {
+        let group = self.load().await;
+        let ix = Instruction {
+            program_id: surroundfi::id(),
+            accounts: surroundfi::accounts::SurroundfiGroupConfigure {
+                surroundfi_group: self.key,
+                admin: self.ctx.borrow().payer.pubkey(),
+            }
+            .to_account_metas(Some(true)),
+            data: surroundfi::instruction::SurroundfiGroupConfigure {
+                new_admin: group.admin,
+                is_arena_group: group.is_arena_group(),
+                group_config: GroupConfigOpt::default(),
+                staked_stable_borrow_enabled: Some(enabled),
+            }
+            .data(),
+        };
+
+        let tx = Transaction::new_signed_with_payer(
+            &[ix],
+            Some(&self.ctx.borrow().payer.pubkey().clone()),
+            &[&self.ctx.borrow().payer],
+            self.ctx.borrow().last_blockhash,
+        );
+
+        self.ctx
+            .borrow_mut()
+            .banks_client
+            .process_transaction(tx)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Configure the group's bankruptcy threshold, keeping its other settings
+    pub async fn try_update_bankruptcy_threshold(
+        &self,
+        bankruptcy_threshold: I80F48,
+    ) -> Result<(), BanksClientError> {
+        let group = self.load().await;
+        let ix = Instruction {
+            program_id: surroundfi::id(),
+            accounts: surroundfi::accounts::SurroundfiGroupConfigure {
+                surroundfi_group: self.key,
+                admin: self.ctx.borrow().payer.pubkey(),
+            }
+            .to_account_metas(Some(true)),
+            data: surroundfi::instruction::SurroundfiGroupConfigure {
+                new_admin: group.admin,
+                is_arena_group: group.is_arena_group(),
+                group_config: GroupConfigOpt {
+                    bankruptcy_threshold: Some(bankruptcy_threshold.into()),
+                },
+                staked_stable_borrow_enabled: None,
+            }
+            .data(),
+        };
+
+        let tx = Transaction::new_signed_with_payer(
+            &[ix],
+            Some(&self.ctx.borrow().payer.pubkey().clone()),
+            &[&self.ctx.borrow().payer],
+            self.ctx.borrow().last_blockhash,
+        );
+
+        self.ctx
+            .borrow_mut()
+            .banks_client
+            .process_transaction(tx)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn try_configure_asset_tag_matrix(
+        &self,
+        asset_tag_matrix: AssetTagMatrix,
+    ) -> Result<(), BanksClientError> {
+        let ix = Instruction {
+            program_id: surroundfi::id(),
+            accounts: surroundfi::accounts::SurroundfiGroupConfigureAssetTagMatrix {
+                surroundfi_group: self.key,
+                admin: self.ctx.borrow().payer.pubkey(),
+            }
+            .to_account_metas(Some(true)),
+            data: surroundfi::instruction::SurroundfiGroupConfigureAssetTagMatrix {
+                asset_tag_matrix,
+            }
+            .data(),
+        };
+
+        let tx = Transaction::new_signed_with_payer(
+            &[ix],
+            Some(&self.ctx.borrow().payer.pubkey().clone()),
+            &[&self.ctx.borrow().payer],
+            self.ctx.borrow().last_blockhash,
+        );
+
+        self.ctx
+            .borrow_mut()
+            .banks_client
+            .process_transaction(tx)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn try_configure_arena(
+        &self,
+        arena_config: ArenaConfig,
+    ) -> Result<(), BanksClientError> {
+        let ix = Instruction {
+            program_id: surroundfi::id(),
+            accounts: surroundfi::accounts::SurroundfiGroupConfigureArena {
+                surroundfi_group: self.key,
+                admin: self.ctx.borrow().payer.pubkey(),
+            }
+            .to_account_metas(Some(true)),
+            data: surroundfi::instruction::SurroundfiGroupConfigureArena { arena_config }.data(),
+        };
+
+        let tx = Transaction::new_signed_with_payer(
+            &[ix],
+            Some(&self.ctx.borrow().payer.pubkey().clone()),
+            &[&self.ctx.borrow().payer],
+            self.ctx.borrow().last_blockhash,
+        );
+
+        self.ctx
+            .borrow_mut()
+            .banks_client
+            .process_transaction(tx)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn try_configure_mint_extensions(
+        &self,
+        permitted_mint_extensions: u64,
+    ) -> Result<(), BanksClientError> {
+        let ix = Instruction {
+            program_id: surroundfi::id(),
+            accounts: surroundfi::accounts::SurroundfiGroupConfigureMintExtensions {
+                surroundfi_group: self.key,
+                admin: self.ctx.borrow().payer.pubkey(),
+            }
+            .to_account_metas(Some(true)),
+            data: surroundfi::instruction::SurroundfiGroupConfigureMintExtensions {
+                permitted_mint_extensions,
             }
             .data(),
         };
@@ -449,6 +702,7 @@ impl SurroundfiGroupFixture {
     }
 
     pub async fn try_collect_fees(&self, bank: &BankFixture) -> Result<()> {
+        let transfer_hook_metas = bank.mint.transfer_hook_metas().await;
         let mut ctx = self.ctx.borrow_mut();
 
         let fee_ata = get_associated_token_address_with_program_id(
@@ -472,6 +726,7 @@ impl SurroundfiGroupFixture {
         if bank.mint.token_program == spl_token_2022::ID {
             accounts.push(AccountMeta::new_readonly(bank.mint.key, false));
         }
+        accounts.extend(transfer_hook_metas);
 
         let ix = Instruction {
             program_id: surroundfi::id(),
@@ -491,6 +746,194 @@ impl SurroundfiGroupFixture {
         Ok(())
     }
 
+    pub fn get_fee_distribution_address(&self) -> Pubkey {
+        Pubkey::find_program_address(
+            &[FEE_DISTRIBUTION_SEED.as_bytes(), self.key.as_ref()],
+            &surroundfi::id(),
+        )
+        .0
+    }
+
+    pub async fn try_init_fee_distribution(
+        &self,
+        recipients: Vec<FeeRecipientConfig>,
+    ) -> Result<(), BanksClientError> {
+        let mut ctx = self.ctx.borrow_mut();
+
+        let ix = Instruction {
+            program_id: surroundfi::id(),
+            accounts: surroundfi::accounts::SurroundfiGroupInitFeeDistribution {
+                group: self.key,
+                admin: ctx.payer.pubkey(),
+                fee_distribution: self.get_fee_distribution_address(),
+                system_program: system_program::id(),
+            }
+            .to_account_metas(Some(true)),
+            data: surroundfi::instruction::SurroundfiGroupInitFeeDistribution { recipients }.data(),
+        };
+
+        let tx = Transaction::new_signed_with_payer(
+            &[ix],
+            Some(&ctx.payer.pubkey().clone()),
+            &[&ctx.payer],
+            ctx.last_blockhash,
+        );
+
+        ctx.banks_client.process_transaction(tx).await
+    }
+
+    pub async fn try_configure_fee_distribution(
+        &self,
+        recipients: Vec<FeeRecipientConfig>,
+    ) -> Result<(), BanksClientError> {
+        let mut ctx = self.ctx.borrow_mut();
+
+        let ix = Instruction {
+            program_id: surroundfi::id(),
+            accounts: surroundfi::accounts::SurroundfiGroupConfigureFeeDistribution {
+                group: self.key,
+                admin: ctx.payer.pubkey(),
+                fee_distribution: self.get_fee_distribution_address(),
+            }
+            .to_account_metas(Some(true)),
+            data: surroundfi::instruction::SurroundfiGroupConfigureFeeDistribution { recipients }
+                .data(),
+        };
+
+        let tx = Transaction::new_signed_with_payer(
+            &[ix],
+            Some(&ctx.payer.pubkey().clone()),
+            &[&ctx.payer],
+            ctx.last_blockhash,
+        );
+
+        ctx.banks_client.process_transaction(tx).await
+    }
+
+    /// The recipients' ATAs must already exist
+    pub async fn try_distribute_fees(&self, bank: &BankFixture) -> Result<(), BanksClientError> {
+        let fee_distribution_key = self.get_fee_distribution_address();
+        let fee_distribution: FeeDistribution =
+            load_and_deserialize(self.ctx.clone(), &fee_distribution_key).await;
+
+        let mut accounts = surroundfi::accounts::LendingPoolDistributeFees {
+            group: self.key,
+            bank: bank.key,
+            fee_distribution: fee_distribution_key,
+            fee_vault: bank.get_vault(BankVaultType::Fee).0,
+            fee_vault_authority: bank.get_vault_authority(BankVaultType::Fee).0,
+            token_program: bank.get_token_program(),
+        }
+        .to_account_metas(Some(true));
+        if bank.mint.token_program == spl_token_2022::ID {
+            accounts.push(AccountMeta::new_readonly(bank.mint.key, false));
+        }
+        accounts.extend(fee_distribution.active_recipients().map(|recipient| {
+            AccountMeta::new(
+                get_associated_token_address_with_program_id(
+                    &recipient.wallet,
+                    &bank.mint.key,
+                    &bank.get_token_program(),
+                ),
+                false,
+            )
+        }));
+        accounts.extend(bank.mint.transfer_hook_metas().await);
+
+        let ix = Instruction {
+            program_id: surroundfi::id(),
+            accounts,
+            data: surroundfi::instruction::LendingPoolDistributeFees {}.data(),
+        };
+
+        let mut ctx = self.ctx.borrow_mut();
+        let tx = Transaction::new_signed_with_payer(
+            &[ix],
+            Some(&ctx.payer.pubkey().clone()),
+            &[&ctx.payer],
+            ctx.last_blockhash,
+        );
+
+        ctx.banks_client.process_transaction(tx).await
+    }
+
+    pub fn get_referrer_fees_address(&self, referrer: &Pubkey) -> Pubkey {
+        Pubkey::find_program_address(
+            &[
+                REFERRER_FEES_SEED.as_bytes(),
+                self.key.as_ref(),
+                referrer.as_ref(),
+            ],
+            &surroundfi::id(),
+        )
+        .0
+    }
+
+    pub async fn try_init_referrer_fees(&self, referrer: &Pubkey) -> Result<(), BanksClientError> {
+        let mut ctx = self.ctx.borrow_mut();
+
+        let ix = Instruction {
+            program_id: surroundfi::id(),
+            accounts: surroundfi::accounts::SurroundfiGroupInitReferrerFees {
+                group: self.key,
+                referrer: *referrer,
+                fee_payer: ctx.payer.pubkey(),
+                referrer_fees: self.get_referrer_fees_address(referrer),
+                system_program: system_program::id(),
+            }
+            .to_account_metas(Some(true)),
+            data: surroundfi::instruction::SurroundfiGroupInitReferrerFees {}.data(),
+        };
+
+        let tx = Transaction::new_signed_with_payer(
+            &[ix],
+            Some(&ctx.payer.pubkey().clone()),
+            &[&ctx.payer],
+            ctx.last_blockhash,
+        );
+
+        ctx.banks_client.process_transaction(tx).await
+    }
+
+    pub async fn try_claim_referrer_fees(
+        &self,
+        referrer: &Keypair,
+        bank: &BankFixture,
+        destination_token_account: Pubkey,
+    ) -> Result<(), BanksClientError> {
+        let mut accounts = surroundfi::accounts::LendingPoolClaimReferrerFees {
+            group: self.key,
+            referrer: referrer.pubkey(),
+            referrer_fees: self.get_referrer_fees_address(&referrer.pubkey()),
+            bank: bank.key,
+            liquidity_vault_authority: bank.get_vault_authority(BankVaultType::Liquidity).0,
+            liquidity_vault: bank.get_vault(BankVaultType::Liquidity).0,
+            destination_token_account,
+            token_program: bank.get_token_program(),
+        }
+        .to_account_metas(Some(true));
+        if bank.mint.token_program == spl_token_2022::ID {
+            accounts.push(AccountMeta::new_readonly(bank.mint.key, false));
+        }
+        accounts.extend(bank.mint.transfer_hook_metas().await);
+
+        let ix = Instruction {
+            program_id: surroundfi::id(),
+            accounts,
+            data: surroundfi::instruction::LendingPoolClaimReferrerFees {}.data(),
+        };
+
+        let mut ctx = self.ctx.borrow_mut();
+        let tx = Transaction::new_signed_with_payer(
+            &[ix],
+            Some(&ctx.payer.pubkey().clone()),
+            &[&ctx.payer, referrer],
+            ctx.last_blockhash,
+        );
+
+        ctx.banks_client.process_transaction(tx).await
+    }
+
     pub async fn try_handle_bankruptcy(
         &self,
         bank: &BankFixture,
@@ -526,6 +969,7 @@ impl SurroundfiGroupFixture {
                 .load_observation_account_metas(vec![], vec![])
                 .await,
         );
+        accounts.extend(bank.mint.transfer_hook_metas().await);
 
         let mut ctx = self.ctx.borrow_mut();
 
@@ -547,6 +991,75 @@ impl SurroundfiGroupFixture {
         ctx.banks_client.process_transaction(tx).await
     }
 
+    /// `banks` are the banks of the account's liabilities, in the order of its balances
+    pub async fn try_handle_account_bankruptcy(
+        &self,
+        banks: &[&BankFixture],
+        surroundfi_account: &SurroundfiAccountFixture,
+    ) -> Result<(), BanksClientError> {
+        let mut accounts = surroundfi::accounts::LendingPoolHandleAccountBankruptcy {
+            group: self.key,
+            signer: self.ctx.borrow().payer.pubkey(),
+            surroundfi_account: surroundfi_account.key,
+        }
+        .to_account_metas(Some(true));
+
+        for bank in banks {
+            accounts.extend([
+                AccountMeta::new(bank.key, false),
+                AccountMeta::new(bank.get_vault(BankVaultType::Liquidity).0, false),
+                AccountMeta::new(bank.get_vault(BankVaultType::Insurance).0, false),
+                AccountMeta::new_readonly(
+                    bank.get_vault_authority(BankVaultType::Insurance).0,
+                    false,
+                ),
+                AccountMeta::new_readonly(bank.get_token_program(), false),
+            ]);
+            if bank.mint.token_program == spl_token_2022::ID {
+                accounts.push(AccountMeta::new_readonly(bank.mint.key, false));
+            }
+        }
+
+        // The banks of asset balances are writable, their dust is forfeited
+        let asset_bank_pks = surroundfi_account
+            .load()
+            .await
+            .lending_account
+            .balances
+            .iter()
+            .filter(|balance| balance.is_active() && !balance.is_empty(BalanceSide::Assets))
+            .map(|balance| balance.bank_pk)
+            .collect::<Vec<_>>();
+        let mut observation_metas = surroundfi_account
+            .load_observation_account_metas(vec![], vec![])
+            .await;
+        for meta in observation_metas.iter_mut() {
+            if asset_bank_pks.contains(&meta.pubkey) {
+                meta.is_writable = true;
+            }
+        }
+        accounts.append(&mut observation_metas);
+
+        let mut ctx = self.ctx.borrow_mut();
+
+        let ix = Instruction {
+            program_id: surroundfi::id(),
+            accounts,
+            data: surroundfi::instruction::LendingPoolHandleAccountBankruptcy {}.data(),
+        };
+
+        let compute_budget_ix = ComputeBudgetInstruction::set_compute_unit_limit(1_400_000);
+
+        let tx = Transaction::new_signed_with_payer(
+            &[compute_budget_ix, ix],
+            Some(&ctx.payer.pubkey()),
+            &[&ctx.payer],
+            ctx.last_blockhash,
+        );
+
+        ctx.banks_client.process_transaction(tx).await
+    }
+
     pub fn get_size() -> usize {
         8 + mem::size_of::<SurroundfiGroup>()
     }
@@ -1,6 +1,7 @@
 use super::surroundfi_account::SurroundfiAccountFixture;
 use crate::{
-    bank::BankFixture, surroundfi_group::*, native, spl::*, transfer_hook::TEST_HOOK_ID, utils::*,
+    bank::BankFixture, flashloan_callback::TEST_CALLBACK_ID, surroundfi_group::*, native, spl::*,
+    transfer_hook::TEST_HOOK_ID, utils::*,
 };
 
 use anchor_lang::prelude::*;
@@ -12,7 +13,7 @@ use solana_sdk::{account::AccountSharedData, entrypoint::ProgramResult};
 use fixed_macro::types::I80F48;
 use lazy_static::lazy_static;
 use surroundfi::{
-    constants::MAX_ORACLE_KEYS,
+    constants::{MAX_ORACLE_KEYS, MINT_EXTENSIONS_RESTRICTED},
     state::{
         surroundfi_group::{BankConfig, BankOperationalState, InterestRateConfig, RiskTier},
         price::OracleSetup,
@@ -255,6 +256,15 @@ pub fn get_oracle_id_from_feed_id(feed_id: Pubkey) -> Option<Pubkey> {
     }
 }
 
+/// The oracle account to pass for a bank priced by a single oracle account, resolving Pyth push
+/// feed ids to their price account
+pub fn get_oracle_account_key(config: &BankConfig) -> Pubkey {
+    match config.oracle_setup {
+        OracleSetup::PythPushOracle => get_oracle_id_from_feed_id(config.oracle_keys[0]).unwrap(),
+        _ => config.oracle_keys[0],
+    }
+}
+
 pub fn create_oracle_key_array(pyth_oracle: Pubkey) -> [Pubkey; MAX_ORACLE_KEYS] {
     let mut keys = [Pubkey::default(); MAX_ORACLE_KEYS];
     keys[0] = pyth_oracle;
@@ -453,6 +463,7 @@ impl TestFixture {
         program.prefer_bpf(true);
         program.add_program("surroundfi", surroundfi::ID, None);
         program.add_program("test_transfer_hook", TEST_HOOK_ID, None);
+        program.add_program("test_flashloan_callback", TEST_CALLBACK_ID, None);
         #[cfg(feature = "lip")]
         program.add_program(
             "liquidity_incentive_program",
@@ -638,6 +649,17 @@ impl TestFixture {
             .set_protocol_fees_flag(test_settings.clone().unwrap_or_default().protocol_fees)
             .await;
 
+        // Permit the restricted extensions of the fixture mints, tests of the mint extension policy
+        // reset this
+        let mut permitted_mint_extensions = 0;
+        for mint_f in [&usdc_t22_mint_f, &pyusd_mint_f, &t22_with_fee_mint_f] {
+            permitted_mint_extensions |= mint_f.mint_extensions().await;
+        }
+        tester_group
+            .try_configure_mint_extensions(permitted_mint_extensions & MINT_EXTENSIONS_RESTRICTED)
+            .await
+            .unwrap();
+
         let mut banks = HashMap::new();
         if let Some(test_settings) = test_settings.clone() {
             for bank in test_settings.banks.iter() {
@@ -646,6 +646,41 @@ pub fn get_emissions_token_account_address(
     )
 }
 
+pub fn get_emissions_program_address(bank_pk: Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            surroundfi::constants::EMISSIONS_PROGRAM_SEED.as_bytes(),
+            bank_pk.as_ref(),
+        ],
+        &surroundfi::id(),
+    )
+}
+
+pub fn get_emissions_stream_vault_address(bank_pk: Pubkey, emissions_mint: Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            surroundfi::constants::EMISSIONS_STREAM_VAULT_SEED.as_bytes(),
+            bank_pk.as_ref(),
+            emissions_mint.as_ref(),
+        ],
+        &surroundfi::id(),
+    )
+}
+
+pub fn get_emissions_accrual_address(
+    surroundfi_account_pk: Pubkey,
+    bank_pk: Pubkey,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            surroundfi::constants::EMISSIONS_ACCRUAL_SEED.as_bytes(),
+            surroundfi_account_pk.as_ref(),
+            bank_pk.as_ref(),
+        ],
+        &surroundfi::id(),
+    )
+}
+
 pub fn get_max_deposit_amount_pre_fee(amount: f64) -> f64 {
     amount * (1f64 + MAX_FEE_BASIS_POINTS as f64 / 10_000f64)
 }
@@ -116,6 +116,8 @@ describe("Init group", () => {
     assertBNEqual(settingsAcc.totalAssetValueInitLimit, 150_000_000);
     assert.equal(settingsAcc.oracleMaxAge, 60);
     assert.deepEqual(settingsAcc.riskTier, { collateral: {} });
+    assertI80F48Approx(settingsAcc.stableLiabilityWeightInit, 1.5);
+    assertI80F48Approx(settingsAcc.stableLiabilityWeightMaint, 1.25);
   });
 
   it("(attacker) Tries to edit staked settings - should fail", async () => {
@@ -129,6 +131,8 @@ describe("Init group", () => {
       riskTier: {
         isolated: undefined,
       },
+      stableLiabilityWeightInit: null,
+      stableLiabilityWeightMaint: null,
     };
     let failed = false;
     try {
@@ -166,6 +170,8 @@ describe("Init group", () => {
       riskTier: {
         collateral: undefined,
       },
+      stableLiabilityWeightInit: null,
+      stableLiabilityWeightMaint: null,
     };
     const [settingsKey] = deriveStakedSettings(
       program.programId,
@@ -205,6 +211,8 @@ describe("Init group", () => {
       totalAssetValueInitLimit: null,
       oracleMaxAge: 60,
       riskTier: null,
+      stableLiabilityWeightInit: null,
+      stableLiabilityWeightMaint: null,
     };
     const [settingsKey] = deriveStakedSettings(
       program.programId,
@@ -245,6 +253,8 @@ describe("Init group", () => {
       riskTier: {
         isolated: undefined,
       },
+      stableLiabilityWeightInit: null,
+      stableLiabilityWeightMaint: null,
     };
     const [settingsKey] = deriveStakedSettings(
       program.programId,
@@ -44,7 +44,12 @@ describe("Config group", () => {
     await groupAdmin.mrgnProgram.provider.sendAndConfirm!(
       new Transaction().add(
         await groupAdmin.mrgnProgram.methods
-          .marginfiGroupConfigure(groupAdmin.wallet.publicKey, false)
+          .marginfiGroupConfigure(
+            groupAdmin.wallet.publicKey,
+            false,
+            { bankruptcyThreshold: null },
+            null
+          )
           .accountsPartial({
             marginfiGroup: marginfiGroup.publicKey,
             admin: newAdmin.publicKey,
@@ -12,6 +12,7 @@ import {
   bankrunProgram,
 } from "./rootHooks";
 import {
+  configureBank,
   editStakedSettings,
   propagateStakedSettings,
 } from "./utils/group-instructions";
@@ -26,7 +27,9 @@ import {
   assertBankrunTxFailed,
 } from "./utils/genericTests";
 import {
+  BankConfigOptWithAssetTag,
   defaultStakedInterestSettings,
+  STAKED_OVERRIDE_DEPOSIT_LIMIT,
   StakedSettingsEdit,
 } from "./utils/types";
 
@@ -60,6 +63,8 @@ describe("Edit and propagate staked settings", () => {
       riskTier: {
         collateral: undefined,
       },
+      stableLiabilityWeightInit: bigNumberToWrappedI80F48(1.6),
+      stableLiabilityWeightMaint: bigNumberToWrappedI80F48(1.3),
     };
     let tx = new Transaction().add(
       await bankrunProgram.methods
@@ -86,6 +91,8 @@ describe("Edit and propagate staked settings", () => {
     assertBNEqual(settingsAcc.totalAssetValueInitLimit, 43);
     assert.equal(settingsAcc.oracleMaxAge, 44);
     assert.deepEqual(settingsAcc.riskTier, { collateral: {} });
+    assertI80F48Approx(settingsAcc.stableLiabilityWeightInit, 1.6);
+    assertI80F48Approx(settingsAcc.stableLiabilityWeightMaint, 1.3);
   });
 
   it("(permissionless) Propagate staked settings to a bank - happy path", async () => {
@@ -110,6 +117,9 @@ describe("Edit and propagate staked settings", () => {
     assertBNEqual(config.totalAssetValueInitLimit, 43);
     assert.equal(config.oracleMaxAge, 44);
     assert.deepEqual(config.riskTier, { collateral: {} });
+    // Staked banks carry the liability weights of stablecoin borrows against them
+    assertI80F48Approx(config.liabilityWeightInit, 1.6);
+    assertI80F48Approx(config.liabilityWeightMaint, 1.3);
   });
 
   it("(admin) sets a bad oracle - fails at propagation", async () => {
@@ -121,6 +131,8 @@ describe("Edit and propagate staked settings", () => {
       totalAssetValueInitLimit: null,
       oracleMaxAge: null,
       riskTier: null,
+      stableLiabilityWeightInit: null,
+      stableLiabilityWeightMaint: null,
     };
     let tx = new Transaction().add(
       await bankrunProgram.methods
@@ -169,6 +181,8 @@ describe("Edit and propagate staked settings", () => {
       totalAssetValueInitLimit: defaultSettings.totalAssetValueInitLimit,
       oracleMaxAge: defaultSettings.oracleMaxAge,
       riskTier: defaultSettings.riskTier,
+      stableLiabilityWeightInit: defaultSettings.stableLiabilityWeightInit,
+      stableLiabilityWeightMaint: defaultSettings.stableLiabilityWeightMaint,
     };
     // Note you can pack propagates into the edit tx, so with a LUT you can easily propagate
     // hundreds of banks in the same ts as edit
@@ -190,5 +204,135 @@ describe("Edit and propagate staked settings", () => {
     tx.recentBlockhash = await getBankrunBlockhash(bankrunContext);
     tx.sign(groupAdmin.wallet);
     await banksClient.processTransaction(tx);
+
+    const bank = await bankrunProgram.account.bank.fetch(bankKey);
+    assertI80F48Approx(bank.config.liabilityWeightInit, 1.5);
+    assertI80F48Approx(bank.config.liabilityWeightMaint, 1.25);
+  });
+
+  it("(admin) overrides the deposit limit on the bank itself - happy path", async () => {
+    const bankConfigOpt: BankConfigOptWithAssetTag = {
+      assetWeightInit: null,
+      assetWeightMaint: null,
+      liabilityWeightInit: null,
+      liabilityWeightMaint: null,
+      depositLimit: new BN(123),
+      borrowLimit: null,
+      riskTier: null,
+      assetTag: null,
+      totalAssetValueInitLimit: null,
+      interestRateConfig: null,
+      operationalState: null,
+      oracleMaxAge: null,
+      permissionlessBadDebtSettlement: null,
+      freezeSettings: null,
+    };
+    let tx = new Transaction().add(
+      await configureBank(bankrunProgram, {
+        bank: bankKey,
+        bankConfigOpt: bankConfigOpt,
+      })
+    );
+    tx.recentBlockhash = await getBankrunBlockhash(bankrunContext);
+    tx.sign(groupAdmin.wallet);
+    await banksClient.processTransaction(tx);
+
+    const bank = await bankrunProgram.account.bank.fetch(bankKey);
+    assertBNEqual(bank.config.depositLimit, 123);
+    const overrides = bank.config.stakedSettingsOverrides;
+    assert.equal(
+      overrides & STAKED_OVERRIDE_DEPOSIT_LIMIT,
+      STAKED_OVERRIDE_DEPOSIT_LIMIT
+    );
+  });
+
+  it("(permissionless) Propagate leaves the overridden deposit limit as-is", async () => {
+    let tx = new Transaction().add(
+      await editStakedSettings(bankrunProgram, {
+        settingsKey: settingsKey,
+        settings: {
+          oracle: null,
+          assetWeightInit: bigNumberToWrappedI80F48(0.25),
+          assetWeightMaint: null,
+          depositLimit: new BN(456),
+          totalAssetValueInitLimit: null,
+          oracleMaxAge: null,
+          riskTier: null,
+          stableLiabilityWeightInit: null,
+          stableLiabilityWeightMaint: null,
+        },
+      }),
+      await propagateStakedSettings(bankrunProgram, {
+        settings: settingsKey,
+        bank: bankKey,
+      })
+    );
+    tx.recentBlockhash = await getBankrunBlockhash(bankrunContext);
+    tx.sign(groupAdmin.wallet);
+    await banksClient.processTransaction(tx);
+
+    const bank = await bankrunProgram.account.bank.fetch(bankKey);
+    assertBNEqual(bank.config.depositLimit, 123);
+    // Fields that are not overridden still follow the settings
+    assertI80F48Approx(bank.config.assetWeightInit, 0.25);
+  });
+
+  it("(admin) clears the override, propagate restores the deposit limit - happy path", async () => {
+    let tx = new Transaction().add(
+      await bankrunProgram.methods
+        .lendingPoolClearStakedSettingsOverrides(STAKED_OVERRIDE_DEPOSIT_LIMIT)
+        .accountsPartial({
+          group: marginfiGroup.publicKey,
+          admin: groupAdmin.wallet.publicKey,
+          bank: bankKey,
+        })
+        .instruction(),
+      await propagateStakedSettings(bankrunProgram, {
+        settings: settingsKey,
+        bank: bankKey,
+      })
+    );
+    tx.recentBlockhash = await getBankrunBlockhash(bankrunContext);
+    tx.sign(groupAdmin.wallet);
+    await banksClient.processTransaction(tx);
+
+    const bank = await bankrunProgram.account.bank.fetch(bankKey);
+    assertBNEqual(bank.config.depositLimit, 456);
+    assert.equal(
+      bank.config.stakedSettingsOverrides & STAKED_OVERRIDE_DEPOSIT_LIMIT,
+      0
+    );
+  });
+
+  it("(admin) restores the default deposit limit and weights - happy path", async () => {
+    const defaultSettings = defaultStakedInterestSettings(
+      oracles.wsolOracle.publicKey
+    );
+    let tx = new Transaction().add(
+      await editStakedSettings(bankrunProgram, {
+        settingsKey: settingsKey,
+        settings: {
+          oracle: null,
+          assetWeightInit: defaultSettings.assetWeightInit,
+          assetWeightMaint: null,
+          depositLimit: defaultSettings.depositLimit,
+          totalAssetValueInitLimit: null,
+          oracleMaxAge: null,
+          riskTier: null,
+          stableLiabilityWeightInit: null,
+          stableLiabilityWeightMaint: null,
+        },
+      }),
+      await propagateStakedSettings(bankrunProgram, {
+        settings: settingsKey,
+        bank: bankKey,
+      })
+    );
+    tx.recentBlockhash = await getBankrunBlockhash(bankrunContext);
+    tx.sign(groupAdmin.wallet);
+    await banksClient.processTransaction(tx);
+
+    const bank = await bankrunProgram.account.bank.fetch(bankKey);
+    assertBNEqual(bank.config.depositLimit, defaultSettings.depositLimit);
   });
 });
@@ -217,6 +217,8 @@ describe("Liquidate user (including staked assets)", () => {
       totalAssetValueInitLimit: defaultSettings.totalAssetValueInitLimit,
       oracleMaxAge: defaultSettings.oracleMaxAge,
       riskTier: defaultSettings.riskTier,
+      stableLiabilityWeightInit: null,
+      stableLiabilityWeightMaint: null,
     };
     let editTx = new Transaction().add(
       await editStakedSettings(groupAdmin.mrgnBankrunProgram, {
@@ -184,6 +184,7 @@ describe("Set up emissions on staked collateral assets", () => {
       await withdrawEmissionsIx(user.mrgnBankrunProgram, {
         marginfiAccount: userAccount,
         bank: validators[0].bank,
+        emissionsMint: ecosystem.tokenBMint.publicKey,
         tokenAccount: users[2].tokenBAccount,
       })
     );
@@ -234,6 +235,7 @@ describe("Set up emissions on staked collateral assets", () => {
       await withdrawEmissionsIx(user.mrgnBankrunProgram, {
         marginfiAccount: userAccount,
         bank: validators[0].bank,
+        emissionsMint: ecosystem.tokenBMint.publicKey,
         tokenAccount: user.tokenBAccount,
       })
     );
@@ -280,6 +282,7 @@ describe("Set up emissions on staked collateral assets", () => {
       await withdrawEmissionsIx(user.mrgnBankrunProgram, {
         marginfiAccount: userAccount,
         bank: validators[0].bank,
+        emissionsMint: ecosystem.tokenBMint.publicKey,
         tokenAccount: user.tokenBAccount,
       })
     );
@@ -475,6 +478,7 @@ describe("Set up emissions on staked collateral assets", () => {
       await withdrawEmissionsIx(user.mrgnBankrunProgram, {
         marginfiAccount: userAccount,
         bank: validators[0].bank,
+        emissionsMint: ecosystem.tokenBMint.publicKey,
         tokenAccount: user.tokenBAccount,
       })
     );
@@ -0,0 +1,280 @@
+import { BN } from "@coral-xyz/anchor";
+import {
+  Keypair,
+  LAMPORTS_PER_SOL,
+  PublicKey,
+  StakeProgram,
+  SystemProgram,
+  Transaction,
+} from "@solana/web3.js";
+import {
+  bankrunContext,
+  bankRunProvider,
+  bankrunProgram,
+  banksClient,
+  marginfiGroup,
+  users,
+  validators,
+  verbose,
+} from "./rootHooks";
+import {
+  createStakeAccount,
+  delegateStake,
+  getEpochAndSlot,
+  getStakeAccount,
+} from "./utils/stake-utils";
+import {
+  assertBankrunTxFailed,
+  assertKeysEqual,
+  getTokenBalance,
+} from "./utils/genericTests";
+import { getBankrunBlockhash } from "./utils/spl-staking-utils";
+import {
+  accountInit,
+  depositStakeIx,
+  withdrawToStakeAccountIx,
+} from "./utils/user-instructions";
+import { assert } from "chai";
+
+describe("Deposit and withdraw native stake", () => {
+  const stake = 5;
+  /** A fresh account for user 0, with no balances other than the ones opened here */
+  const accountKeypair = Keypair.generate();
+  /** User 0's stake account delegated to validator 0 */
+  let v0StakeAccount: PublicKey;
+  /** User 0's stake account delegated to validator 1 */
+  let v1StakeAccount: PublicKey;
+  let vaultKey: PublicKey;
+  let deposited: number;
+
+  before(async () => {
+    const bank = await bankrunProgram.account.bank.fetch(validators[0].bank);
+    vaultKey = bank.liquidityVault;
+  });
+
+  it("(user 0) Creates a new account and stakes to validators 0 and 1", async () => {
+    const user = users[0];
+    let initTx = new Transaction().add(
+      await accountInit(user.mrgnBankrunProgram, {
+        marginfiGroup: marginfiGroup.publicKey,
+        marginfiAccount: accountKeypair.publicKey,
+        authority: user.wallet.publicKey,
+        feePayer: user.wallet.publicKey,
+      })
+    );
+    initTx.recentBlockhash = await getBankrunBlockhash(bankrunContext);
+    initTx.sign(user.wallet, accountKeypair);
+    await banksClient.processTransaction(initTx);
+
+    v0StakeAccount = await stakeAndDelegate(validators[0].voteAccount);
+    v1StakeAccount = await stakeAndDelegate(validators[1].voteAccount);
+  });
+
+  const stakeAndDelegate = async (voteAccount: PublicKey) => {
+    const user = users[0];
+    let { createTx, stakeAccountKeypair } = createStakeAccount(
+      user,
+      stake * LAMPORTS_PER_SOL
+    );
+    createTx.add(
+      delegateStake(user, stakeAccountKeypair.publicKey, voteAccount)
+    );
+    createTx.recentBlockhash = await getBankrunBlockhash(bankrunContext);
+    createTx.sign(user.wallet, stakeAccountKeypair);
+    await banksClient.processTransaction(createTx);
+    return stakeAccountKeypair.publicKey;
+  };
+
+  it("Advance the epoch so the stake activates", async () => {
+    let { epoch } = await getEpochAndSlot(banksClient);
+    bankrunContext.warpToEpoch(BigInt(epoch + 1));
+
+    // Stall a few slots to end the rewards period, see s01
+    let { slot: slotAfterWarp } = await getEpochAndSlot(banksClient);
+    for (let i = 0; i < 3; i++) {
+      bankrunContext.warpToSlot(BigInt(i + slotAfterWarp + 1));
+      const dummyTx = new Transaction().add(
+        SystemProgram.transfer({
+          fromPubkey: users[0].wallet.publicKey,
+          toPubkey: bankrunProgram.provider.publicKey,
+          lamports: i,
+        })
+      );
+      dummyTx.recentBlockhash = await getBankrunBlockhash(bankrunContext);
+      dummyTx.sign(users[0].wallet);
+      await banksClient.processTransaction(dummyTx);
+    }
+  });
+
+  it("(user 0) deposits stake with another validator's pool - should fail", async () => {
+    const user = users[0];
+    let tx = new Transaction().add(
+      await depositStakeIx(user.mrgnBankrunProgram, {
+        marginfiAccount: accountKeypair.publicKey,
+        bank: validators[0].bank,
+        stakeAccount: v1StakeAccount,
+        splPool: validators[1].splPool,
+      })
+    );
+    tx.recentBlockhash = await getBankrunBlockhash(bankrunContext);
+    tx.sign(user.wallet);
+    let result = await banksClient.tryProcessTransaction(tx);
+
+    // StakePoolValidationFailed
+    assertBankrunTxFailed(result, "0x17a0");
+  });
+
+  it("(user 0) deposits stake delegated to another validator - should fail", async () => {
+    const user = users[0];
+    let tx = new Transaction().add(
+      await depositStakeIx(user.mrgnBankrunProgram, {
+        marginfiAccount: accountKeypair.publicKey,
+        bank: validators[0].bank,
+        stakeAccount: v1StakeAccount,
+        splPool: validators[0].splPool,
+      })
+    );
+    tx.recentBlockhash = await getBankrunBlockhash(bankrunContext);
+    tx.sign(user.wallet);
+    let result = await banksClient.tryProcessTransaction(tx);
+
+    // The single pool rejects the stake (validator mismatch)
+    assert(result.result, "TX succeeded when it should have failed");
+  });
+
+  it("(user 0) deposits stake - happy path", async () => {
+    const user = users[0];
+    const vaultBefore = await getTokenBalance(bankRunProvider, vaultKey);
+
+    let tx = new Transaction().add(
+      await depositStakeIx(user.mrgnBankrunProgram, {
+        marginfiAccount: accountKeypair.publicKey,
+        bank: validators[0].bank,
+        stakeAccount: v0StakeAccount,
+        splPool: validators[0].splPool,
+      })
+    );
+    tx.recentBlockhash = await getBankrunBlockhash(bankrunContext);
+    tx.sign(user.wallet);
+    await banksClient.processTransaction(tx);
+
+    // The whole stake account went to the pool
+    const stakeAccountInfo = await banksClient.getAccount(v0StakeAccount);
+    assert.ok(stakeAccountInfo === null);
+
+    const vaultAfter = await getTokenBalance(bankRunProvider, vaultKey);
+    deposited = vaultAfter - vaultBefore;
+    assert.isAbove(deposited, 0);
+    if (verbose) {
+      console.log("deposited LST: " + deposited.toLocaleString());
+    }
+
+    const userAcc = await bankrunProgram.account.marginfiAccount.fetch(
+      accountKeypair.publicKey
+    );
+    const balance = userAcc.lendingAccount.balances[0];
+    assert.equal(balance.active, 1);
+    assertKeysEqual(balance.bankPk, validators[0].bank);
+  });
+
+  it("(user 0) withdraws stake with another validator's pool - should fail", async () => {
+    const user = users[0];
+    const stakeAccountKeypair = Keypair.generate();
+    let tx = new Transaction().add(
+      await createUninitializedStakeAccountIx(stakeAccountKeypair.publicKey),
+      await withdrawToStakeAccountIx(user.mrgnBankrunProgram, {
+        marginfiAccount: accountKeypair.publicKey,
+        bank: validators[0].bank,
+        stakeAccount: stakeAccountKeypair.publicKey,
+        splPool: validators[1].splPool,
+        remaining: [],
+        amount: new BN(0),
+        withdrawAll: true,
+      })
+    );
+    tx.recentBlockhash = await getBankrunBlockhash(bankrunContext);
+    tx.sign(user.wallet, stakeAccountKeypair);
+    let result = await banksClient.tryProcessTransaction(tx);
+
+    // StakePoolValidationFailed
+    assertBankrunTxFailed(result, "0x17a0");
+  });
+
+  it("(user 0) withdraws stake into an initialized stake account - should fail", async () => {
+    const user = users[0];
+    let tx = new Transaction().add(
+      await withdrawToStakeAccountIx(user.mrgnBankrunProgram, {
+        marginfiAccount: accountKeypair.publicKey,
+        bank: validators[0].bank,
+        stakeAccount: v1StakeAccount,
+        splPool: validators[0].splPool,
+        remaining: [],
+        amount: new BN(0),
+        withdrawAll: true,
+      })
+    );
+    tx.recentBlockhash = await getBankrunBlockhash(bankrunContext);
+    tx.sign(user.wallet);
+    let result = await banksClient.tryProcessTransaction(tx);
+
+    // The stake program refuses to split into an account that is already in use
+    assert(result.result, "TX succeeded when it should have failed");
+  });
+
+  it("(user 0) withdraws all stake - happy path", async () => {
+    const user = users[0];
+    const stakeAccountKeypair = Keypair.generate();
+    const vaultBefore = await getTokenBalance(bankRunProvider, vaultKey);
+
+    let tx = new Transaction().add(
+      await createUninitializedStakeAccountIx(stakeAccountKeypair.publicKey),
+      await withdrawToStakeAccountIx(user.mrgnBankrunProgram, {
+        marginfiAccount: accountKeypair.publicKey,
+        bank: validators[0].bank,
+        stakeAccount: stakeAccountKeypair.publicKey,
+        splPool: validators[0].splPool,
+        // The only balance is closed, so there is nothing left to price
+        remaining: [],
+        amount: new BN(0),
+        withdrawAll: true,
+      })
+    );
+    tx.recentBlockhash = await getBankrunBlockhash(bankrunContext);
+    tx.sign(user.wallet, stakeAccountKeypair);
+    await banksClient.processTransaction(tx);
+
+    const vaultAfter = await getTokenBalance(bankRunProvider, vaultKey);
+    // Note: share conversion may round down by a lamport
+    assert.approximately(vaultBefore - vaultAfter, deposited, 1);
+
+    const userAcc = await bankrunProgram.account.marginfiAccount.fetch(
+      accountKeypair.publicKey
+    );
+    assert.equal(userAcc.lendingAccount.balances[0].active, 0);
+
+    // The user owns a stake account delegated to the same validator again
+    const stakeAccountInfo = await bankRunProvider.connection.getAccountInfo(
+      stakeAccountKeypair.publicKey
+    );
+    const stakeAcc = getStakeAccount(stakeAccountInfo.data);
+    assertKeysEqual(stakeAcc.meta.authorized.staker, user.wallet.publicKey);
+    assertKeysEqual(stakeAcc.meta.authorized.withdrawer, user.wallet.publicKey);
+    assertKeysEqual(
+      stakeAcc.stake.delegation.voterPubkey,
+      validators[0].voteAccount
+    );
+    assert.isAbove(Number(stakeAcc.stake.delegation.stake), 0);
+  });
+
+  /** A rent-exempt, uninitialized stake account for user 0, which must sign with `stakeAccount` */
+  const createUninitializedStakeAccountIx = async (stakeAccount: PublicKey) => {
+    const rent = await banksClient.getRent();
+    return SystemProgram.createAccount({
+      fromPubkey: users[0].wallet.publicKey,
+      newAccountPubkey: stakeAccount,
+      lamports: Number(rent.minimumBalance(BigInt(StakeProgram.space))),
+      space: StakeProgram.space,
+      programId: StakeProgram.programId,
+    });
+  };
+});
@@ -0,0 +1,417 @@
+import { BN, Program, workspace } from "@coral-xyz/anchor";
+import {
+  Keypair,
+  LAMPORTS_PER_SOL,
+  PublicKey,
+  Transaction,
+} from "@solana/web3.js";
+import { Marginfi } from "../target/types/marginfi";
+import {
+  bankKeypairUsdc,
+  bankrunContext,
+  bankrunProgram,
+  bankRunProvider,
+  banksClient,
+  ecosystem,
+  groupAdmin,
+  marginfiGroup,
+  oracles,
+  users,
+  validators,
+} from "./rootHooks";
+import {
+  assertBankrunTxFailed,
+  assertI80F48Approx,
+  assertI80F48Equal,
+} from "./utils/genericTests";
+import { assert } from "chai";
+import {
+  accountInit,
+  borrowIx,
+  depositIx,
+  healthPulse,
+  liquidateIx,
+} from "./utils/user-instructions";
+import { LST_ATA, USER_ACCOUNT } from "./utils/mocks";
+import { getBankrunBlockhash } from "./utils/spl-staking-utils";
+import {
+  bigNumberToWrappedI80F48,
+  getMint,
+  wrappedI80F48toBigNumber,
+} from "@mrgnlabs/mrgn-common";
+import {
+  ASSET_TAG_STABLE,
+  BankConfigOptWithAssetTag,
+  defaultStakedInterestSettings,
+  StakedSettingsEdit,
+} from "./utils/types";
+import {
+  configureBank,
+  editStakedSettings,
+  groupConfigure,
+  propagateStakedSettings,
+} from "./utils/group-instructions";
+import { deriveStakedSettings } from "./utils/pdas";
+import { getStakeAccount } from "./utils/stake-utils";
+
+const STABLE_ACCOUNT = "g0_stableAcc";
+
+describe("Borrow stablecoins against staked collateral", () => {
+  const program = workspace.Marginfi as Program<Marginfi>;
+  let settingsKey: PublicKey;
+  let stakedPrice: number;
+  before(async () => {
+    [settingsKey] = deriveStakedSettings(
+      program.programId,
+      marginfiGroup.publicKey
+    );
+
+    const solPool = await bankRunProvider.connection.getAccountInfo(
+      validators[0].splSolPool
+    );
+    const splStakePool = getStakeAccount(solPool.data);
+    const stakeActual = Number(splStakePool.stake.delegation.stake);
+    const mintData = await getMint(
+      bankRunProvider.connection,
+      validators[0].splMint
+    );
+    // there is 1 SOL used to init the pool that is non-refundable and doesn't count as stake
+    stakedPrice =
+      (oracles.wsolPrice * (stakeActual - LAMPORTS_PER_SOL)) /
+      Number(mintData.supply);
+  });
+
+  const confidenceInterval = 0.0212; // 1% confidence * CONF_INTERVAL_MULTIPLE
+
+  /**
+   * Staked SOL (hereinafter Staked) is worth ~$305 (~$298.57 low), USDC is worth ~$1 (~$1.02 high)
+   *
+   * User 3 deposits 1 Staked and borrows 100 USDC. At asset weights 0.8/0.9:
+   * * init: assets $238.86, debt $153.18 at the stable liability weight of 1.5
+   * * borrowing 80 more makes the debt $275.72, over the assets, but only $183.82 at the USDC
+   *   bank's own weight of 1
+   * * maint at a stable liability weight of 4: assets $268.72, debt $408.48, so liquidation is
+   *   allowed
+   */
+  const stakedRemaining = () => [
+    validators[0].bank,
+    oracles.wsolOracle.publicKey,
+    validators[0].splMint,
+    validators[0].splSolPool,
+  ];
+
+  const editAndPropagate = async (settings: StakedSettingsEdit) => {
+    let tx = new Transaction().add(
+      await editStakedSettings(groupAdmin.mrgnBankrunProgram, {
+        settingsKey: settingsKey,
+        settings: settings,
+      }),
+      await propagateStakedSettings(bankrunProgram, {
+        settings: settingsKey,
+        bank: validators[0].bank,
+        oracle: oracles.wsolOracle.publicKey,
+      })
+    );
+    tx.recentBlockhash = await getBankrunBlockhash(bankrunContext);
+    tx.sign(groupAdmin.wallet);
+    await banksClient.processTransaction(tx);
+  };
+
+  const stableWeightsEdit = (init: number, maint: number) => {
+    const settings: StakedSettingsEdit = {
+      oracle: null,
+      assetWeightInit: null,
+      assetWeightMaint: null,
+      depositLimit: null,
+      totalAssetValueInitLimit: null,
+      oracleMaxAge: null,
+      riskTier: null,
+      stableLiabilityWeightInit: bigNumberToWrappedI80F48(init),
+      stableLiabilityWeightMaint: bigNumberToWrappedI80F48(maint),
+    };
+    return settings;
+  };
+
+  const initAccount = async (userIndex: number) => {
+    const user = users[userIndex];
+    const accKeypair = Keypair.generate();
+    user.accounts.set(STABLE_ACCOUNT, accKeypair.publicKey);
+
+    let tx = new Transaction().add(
+      await accountInit(user.mrgnBankrunProgram, {
+        marginfiGroup: marginfiGroup.publicKey,
+        marginfiAccount: accKeypair.publicKey,
+        authority: user.wallet.publicKey,
+        feePayer: user.wallet.publicKey,
+      })
+    );
+    tx.recentBlockhash = await getBankrunBlockhash(bankrunContext);
+    tx.sign(user.wallet, accKeypair);
+    await banksClient.processTransaction(tx);
+    return accKeypair.publicKey;
+  };
+
+  it("(admin) tags USDC as stable, enables staked stable borrows and sets the staked weights", async () => {
+    const bankConfigOpt: BankConfigOptWithAssetTag = {
+      assetWeightInit: null,
+      assetWeightMaint: null,
+      liabilityWeightInit: null,
+      liabilityWeightMaint: null,
+      depositLimit: null,
+      borrowLimit: null,
+      riskTier: null,
+      assetTag: ASSET_TAG_STABLE,
+      totalAssetValueInitLimit: null,
+      interestRateConfig: null,
+      operationalState: null,
+      oracleMaxAge: null,
+      permissionlessBadDebtSettlement: null,
+      freezeSettings: null,
+    };
+    let tx = new Transaction().add(
+      await configureBank(groupAdmin.mrgnBankrunProgram, {
+        bank: bankKeypairUsdc.publicKey,
+        bankConfigOpt: bankConfigOpt,
+      }),
+      await groupConfigure(groupAdmin.mrgnBankrunProgram, {
+        marginfiGroup: marginfiGroup.publicKey,
+        stakedStableBorrowEnabled: true,
+      })
+    );
+    tx.recentBlockhash = await getBankrunBlockhash(bankrunContext);
+    tx.sign(groupAdmin.wallet);
+    await banksClient.processTransaction(tx);
+
+    // s08 left the staked asset weights low, and the init limit would discount them further
+    const defaultSettings = defaultStakedInterestSettings(
+      oracles.wsolOracle.publicKey
+    );
+    await editAndPropagate({
+      ...stableWeightsEdit(1.5, 1.25),
+      assetWeightInit: defaultSettings.assetWeightInit,
+      assetWeightMaint: defaultSettings.assetWeightMaint,
+      totalAssetValueInitLimit: new BN(1_000_000_000_000),
+    });
+
+    const bank = await bankrunProgram.account.bank.fetch(
+      bankKeypairUsdc.publicKey
+    );
+    assert.equal(bank.config.assetTag, ASSET_TAG_STABLE);
+    assertI80F48Equal(bank.config.liabilityWeightInit, 1);
+    const stakedBank = await bankrunProgram.account.bank.fetch(
+      validators[0].bank
+    );
+    assertI80F48Approx(stakedBank.config.liabilityWeightInit, 1.5);
+    assertI80F48Approx(stakedBank.config.liabilityWeightMaint, 1.25);
+  });
+
+  it("(user 0) deposits USDC to lend - happy path", async () => {
+    const user = users[0];
+    let tx = new Transaction().add(
+      await depositIx(user.mrgnBankrunProgram, {
+        marginfiAccount: user.accounts.get(USER_ACCOUNT),
+        bank: bankKeypairUsdc.publicKey,
+        tokenAccount: user.usdcAccount,
+        amount: new BN(1000 * 10 ** ecosystem.usdcDecimals),
+      })
+    );
+    tx.recentBlockhash = await getBankrunBlockhash(bankrunContext);
+    tx.sign(user.wallet);
+    await banksClient.processTransaction(tx);
+  });
+
+  it("(user 3) deposits staked and borrows USDC against it - succeeds", async () => {
+    const user = users[3];
+    const userAccount = await initAccount(3);
+
+    let tx = new Transaction().add(
+      await depositIx(user.mrgnBankrunProgram, {
+        marginfiAccount: userAccount,
+        bank: validators[0].bank,
+        tokenAccount: user.accounts.get(LST_ATA),
+        amount: new BN(1 * 10 ** ecosystem.wsolDecimals),
+      }),
+      await borrowIx(user.mrgnBankrunProgram, {
+        marginfiAccount: userAccount,
+        bank: bankKeypairUsdc.publicKey,
+        tokenAccount: user.usdcAccount,
+        remaining: [
+          ...stakedRemaining(),
+          bankKeypairUsdc.publicKey,
+          oracles.usdcOracle.publicKey,
+        ],
+        amount: new BN(100 * 10 ** ecosystem.usdcDecimals),
+      })
+    );
+    tx.recentBlockhash = await getBankrunBlockhash(bankrunContext);
+    tx.sign(user.wallet);
+    await banksClient.processTransaction(tx);
+
+    const userAcc = await bankrunProgram.account.marginfiAccount.fetch(
+      userAccount
+    );
+    const balances = userAcc.lendingAccount.balances;
+    assert.equal(balances[1].active, 1);
+    assert.ok(balances[1].bankPk.equals(bankKeypairUsdc.publicKey));
+  });
+
+  it("(user 3) USDC debt is weighted by the staked bank's stable liability weights", async () => {
+    const user = users[3];
+    const userAccount = user.accounts.get(STABLE_ACCOUNT);
+
+    let tx = new Transaction().add(
+      await healthPulse(user.mrgnBankrunProgram, {
+        marginfiAccount: userAccount,
+        remaining: [
+          ...stakedRemaining(),
+          bankKeypairUsdc.publicKey,
+          oracles.usdcOracle.publicKey,
+        ],
+      })
+    );
+    tx.recentBlockhash = await getBankrunBlockhash(bankrunContext);
+    tx.sign(user.wallet);
+    await banksClient.processTransaction(tx);
+
+    const userAcc = await bankrunProgram.account.marginfiAccount.fetch(
+      userAccount
+    );
+    const cache = userAcc.healthCache;
+    const liabValue = wrappedI80F48toBigNumber(cache.liabilityValue).toNumber();
+    const liabValueMaint = wrappedI80F48toBigNumber(
+      cache.liabilityValueMaint
+    ).toNumber();
+    // The USDC bank's own liability weights are 1
+    const expected = 100 * oracles.usdcPrice * 1.5;
+    assert.approximately(
+      liabValue,
+      expected,
+      expected * confidenceInterval * 2
+    );
+    assert.approximately(liabValue / liabValueMaint, 1.5 / 1.25, 0.001);
+
+    const assetValue = wrappedI80F48toBigNumber(cache.assetValue).toNumber();
+    const expectedAssets = stakedPrice * 0.8;
+    assert.approximately(
+      assetValue,
+      expectedAssets,
+      expectedAssets * confidenceInterval * 2
+    );
+  });
+
+  it("(user 3) borrows more USDC than the stable liability weight allows - should fail", async () => {
+    const user = users[3];
+    const userAccount = user.accounts.get(STABLE_ACCOUNT);
+
+    let tx = new Transaction().add(
+      await borrowIx(user.mrgnBankrunProgram, {
+        marginfiAccount: userAccount,
+        bank: bankKeypairUsdc.publicKey,
+        tokenAccount: user.usdcAccount,
+        remaining: [
+          ...stakedRemaining(),
+          bankKeypairUsdc.publicKey,
+          oracles.usdcOracle.publicKey,
+        ],
+        amount: new BN(80 * 10 ** ecosystem.usdcDecimals),
+      })
+    );
+    tx.recentBlockhash = await getBankrunBlockhash(bankrunContext);
+    tx.sign(user.wallet);
+    let result = await banksClient.tryProcessTransaction(tx);
+    // RiskEngineInitRejected
+    assertBankrunTxFailed(result, "0x1779");
+  });
+
+  it("(user 1) liquidates user 3's staked collateral once the stable weights tighten", async () => {
+    const liquidatee = users[3];
+    const liquidator = users[1];
+    const liquidateeAccount = liquidatee.accounts.get(STABLE_ACCOUNT);
+    const liquidatorAccount = await initAccount(1);
+
+    let depositTx = new Transaction().add(
+      await depositIx(liquidator.mrgnBankrunProgram, {
+        marginfiAccount: liquidatorAccount,
+        bank: bankKeypairUsdc.publicKey,
+        tokenAccount: liquidator.usdcAccount,
+        amount: new BN(100 * 10 ** ecosystem.usdcDecimals),
+      })
+    );
+    depositTx.recentBlockhash = await getBankrunBlockhash(bankrunContext);
+    depositTx.sign(liquidator.wallet);
+    await banksClient.processTransaction(depositTx);
+
+    const liquidateAmount = new BN(0.1 * 10 ** ecosystem.wsolDecimals);
+    const liquidate = async () => {
+      let tx = new Transaction().add(
+        await liquidateIx(liquidator.mrgnBankrunProgram, {
+          assetBankKey: validators[0].bank,
+          liabilityBankKey: bankKeypairUsdc.publicKey,
+          liquidatorMarginfiAccount: liquidatorAccount,
+          liquidateeMarginfiAccount: liquidateeAccount,
+          remaining: [
+            oracles.wsolOracle.publicKey,
+            validators[0].splMint,
+            validators[0].splSolPool,
+            oracles.usdcOracle.publicKey,
+            bankKeypairUsdc.publicKey,
+            oracles.usdcOracle.publicKey,
+            ...stakedRemaining(),
+            ...stakedRemaining(),
+            bankKeypairUsdc.publicKey,
+            oracles.usdcOracle.publicKey,
+          ],
+          amount: liquidateAmount,
+        })
+      );
+      tx.recentBlockhash = await getBankrunBlockhash(bankrunContext);
+      tx.sign(liquidator.wallet);
+      return banksClient.tryProcessTransaction(tx);
+    };
+
+    let result = await liquidate();
+    // HealthyAccount
+    assertBankrunTxFailed(result, "0x17b4");
+
+    await editAndPropagate(stableWeightsEdit(4, 4));
+
+    const liquidateeBefore = await bankrunProgram.account.marginfiAccount.fetch(
+      liquidateeAccount
+    );
+    result = await liquidate();
+    assert.isNull(result.result);
+
+    const liquidateeAfter = await bankrunProgram.account.marginfiAccount.fetch(
+      liquidateeAccount
+    );
+    const liquidatorAfter = await bankrunProgram.account.marginfiAccount.fetch(
+      liquidatorAccount
+    );
+    const before = liquidateeBefore.lendingAccount.balances;
+    const after = liquidateeAfter.lendingAccount.balances;
+    assertI80F48Equal(
+      after[0].assetShares,
+      wrappedI80F48toBigNumber(before[0].assetShares).toNumber() -
+        liquidateAmount.toNumber()
+    );
+    assert.isTrue(
+      wrappedI80F48toBigNumber(after[1].liabilityShares).lt(
+        wrappedI80F48toBigNumber(before[1].liabilityShares)
+      )
+    );
+    assertI80F48Equal(
+      liquidatorAfter.lendingAccount.balances[1].assetShares,
+      liquidateAmount.toNumber()
+    );
+  });
+
+  it("(admin) restores the default stable liability weights", async () => {
+    await editAndPropagate(stableWeightsEdit(1.5, 1.25));
+
+    const stakedBank = await bankrunProgram.account.bank.fetch(
+      validators[0].bank
+    );
+    assertI80F48Approx(stakedBank.config.liabilityWeightInit, 1.5);
+    assertI80F48Approx(stakedBank.config.liabilityWeightMaint, 1.25);
+  });
+});
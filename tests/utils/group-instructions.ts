@@ -139,11 +139,13 @@ export const addBankWithSeed = (
  * newAdmin - (Optional) pass null to keep current admin
  * marginfiGroup's admin - must sign
  * isArena - default false
+ * stakedStableBorrowEnabled - (Optional) let staked collateral borrow ASSET_TAG_STABLE banks
  */
 export type GroupConfigureArgs = {
   newAdmin?: PublicKey | null; // optional; pass null or leave undefined to keep current admin
   marginfiGroup: PublicKey;
   isArena?: boolean; // optional; defaults to false if not provided
+  stakedStableBorrowEnabled?: boolean | null; // optional; leave undefined to keep current setting
 };
 
 export const groupConfigure = async (
@@ -157,7 +159,12 @@ export const groupConfigure = async (
     newAdmin = group.admin;
   }
   const ix = program.methods
-    .marginfiGroupConfigure(newAdmin, isArena)
+    .marginfiGroupConfigure(
+      newAdmin,
+      isArena,
+      { bankruptcyThreshold: null },
+      args.stakedStableBorrowEnabled ?? null
+    )
     .accounts({
       marginfiGroup: args.marginfiGroup,
       // admin: // implied from group
@@ -245,6 +252,10 @@ export type SetupEmissionsArgs = {
   emissionsFlags: BN;
   emissionsRate: BN;
   totalEmissions: BN;
+  /** Unix timestamp, null (default) to start immediately */
+  startTime?: BN | null;
+  /** Unix timestamp, null (default) to run until emissions are exhausted */
+  endTime?: BN | null;
 };
 
 export const setupEmissions = (
@@ -255,7 +266,9 @@ export const setupEmissions = (
     .lendingPoolSetupEmissions(
       args.emissionsFlags,
       args.emissionsRate,
-      args.totalEmissions
+      args.totalEmissions,
+      args.startTime ?? null,
+      args.endTime ?? null
     )
     .accounts({
       bank: args.bank,
@@ -277,6 +290,8 @@ export type UpdateEmissionsArgs = {
   emissionsFlags: BN | null;
   emissionsRate: BN | null;
   additionalEmissions: BN | null;
+  startTime?: BN | null;
+  endTime?: BN | null;
 };
 
 export const updateEmissions = (
@@ -287,7 +302,9 @@ export const updateEmissions = (
     .lendingPoolUpdateEmissionsParameters(
       args.emissionsFlags,
       args.emissionsRate,
-      args.additionalEmissions
+      args.additionalEmissions,
+      args.startTime ?? null,
+      args.endTime ?? null
     )
     .accounts({
       bank: args.bank,
@@ -316,7 +316,8 @@ export const createSimpleMint = async (
       mint.publicKey,
       decimals,
       payer,
-      payer,
+      // A freeze authority is a restricted mint extension, banks need the group to permit it
+      null,
       TOKEN_PROGRAM_ID
     )
   );
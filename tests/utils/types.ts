@@ -30,6 +30,7 @@ export const FREEZE_SETTINGS = 8;
 export const ASSET_TAG_DEFAULT = 0;
 export const ASSET_TAG_SOL = 1;
 export const ASSET_TAG_STAKED = 2;
+export const ASSET_TAG_STABLE = 4;
 
 export const ORACLE_SETUP_NONE = 0;
 export const ORACLE_SETUP_PYTH_LEGACY = 1;
@@ -42,6 +43,14 @@ export const HEALTH_CACHE_NONE = 0;
 export const HEALTH_CACHE_HEALTHY = 1;
 export const HEALTH_CACHE_ENGINE_OK = 2;
 
+export const STAKED_OVERRIDE_ORACLE = 1 << 0;
+export const STAKED_OVERRIDE_ASSET_WEIGHTS = 1 << 1;
+export const STAKED_OVERRIDE_LIABILITY_WEIGHTS = 1 << 2;
+export const STAKED_OVERRIDE_DEPOSIT_LIMIT = 1 << 3;
+export const STAKED_OVERRIDE_TOTAL_ASSET_VALUE_INIT_LIMIT = 1 << 4;
+export const STAKED_OVERRIDE_ORACLE_MAX_AGE = 1 << 5;
+export const STAKED_OVERRIDE_RISK_TIER = 1 << 6;
+
 
 /**
  * The default bank config has
@@ -182,6 +191,8 @@ export const defaultStakedInterestSettings = (oracle: PublicKey) => {
     riskTier: {
       collateral: undefined,
     },
+    stableLiabilityWeightInit: bigNumberToWrappedI80F48(1.5),
+    stableLiabilityWeightMaint: bigNumberToWrappedI80F48(1.25),
   };
   return settings;
 };
@@ -269,6 +280,10 @@ export type StakedSettingsConfig = {
   oracleMaxAge: number;
   /** Collateral = 0, Isolated = 1 */
   riskTier: RiskTierRaw;
+
+  /** Liability weights of stablecoin borrows against staked collateral, zero for the defaults */
+  stableLiabilityWeightInit: WrappedI80F48;
+  stableLiabilityWeightMaint: WrappedI80F48;
 };
 
 export interface StakedSettingsEdit {
@@ -282,4 +297,7 @@ export interface StakedSettingsEdit {
 
   oracleMaxAge: number | null;
   riskTier: { collateral: {} } | { isolated: {} } | null;
+
+  stableLiabilityWeightInit: WrappedI80F48 | null;
+  stableLiabilityWeightMaint: WrappedI80F48 | null;
 }
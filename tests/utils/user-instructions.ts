@@ -1,8 +1,21 @@
 import { BN, Program } from "@coral-xyz/anchor";
-import { AccountMeta, PublicKey } from "@solana/web3.js";
+import {
+  AccountMeta,
+  PublicKey,
+  StakeProgram,
+  SYSVAR_CLOCK_PUBKEY,
+  SYSVAR_STAKE_HISTORY_PUBKEY,
+} from "@solana/web3.js";
 import { Marginfi } from "../../target/types/marginfi";
 import { TOKEN_PROGRAM_ID } from "@solana/spl-token";
+import {
+  findPoolMintAddress,
+  findPoolMintAuthorityAddress,
+  findPoolStakeAddress,
+  findPoolStakeAuthorityAddress,
+} from "@solana/spl-single-pool-classic";
 import { deriveLiquidityVault } from "./pdas";
+import { SINGLE_POOL_PROGRAM_ID } from "./types";
 
 export type AccountInitArgs = {
   marginfiGroup: PublicKey;
@@ -87,7 +100,7 @@ export const settleEmissionsIx = (
   args: SettleEmissionsArgs
 ) => {
   const ix = program.methods
-    .lendingAccountSettleEmissions()
+    .lendingAccountSettleEmissions(null)
     .accounts({
       marginfiAccount: args.marginfiAccount,
       bank: args.bank,
@@ -100,6 +113,8 @@ export const settleEmissionsIx = (
 export type WithdrawEmissionsArgs = {
   marginfiAccount: PublicKey;
   bank: PublicKey;
+  /** The bank's `emissions_mint` */
+  emissionsMint: PublicKey;
   tokenAccount: PublicKey;
 };
 
@@ -115,13 +130,13 @@ export const withdrawEmissionsIx = (
   args: WithdrawEmissionsArgs
 ) => {
   const ix = program.methods
-    .lendingAccountWithdrawEmissions()
+    .lendingAccountWithdrawEmissions(null)
     .accounts({
       // group: args.marginfiGroup, // implied from bank
       marginfiAccount: args.marginfiAccount,
       // authority: args.authority, // implied from marginfiAccount
       bank: args.bank,
-      // emissions_mint // implied from bank
+      emissionsMint: args.emissionsMint,
       // emissions_auth // pda derived from bank
       // emissions_vault // pda derived from bank
       destinationAccount: args.tokenAccount,
@@ -418,3 +433,117 @@ export const healthPulse = (
     .remainingAccounts(oracleMeta)
     .instruction();
 };
+
+export type DepositStakeArgs = {
+  marginfiAccount: PublicKey;
+  bank: PublicKey;
+  stakeAccount: PublicKey;
+  /** The spl single pool, all other pool accounts are derived from it */
+  splPool: PublicKey;
+};
+
+/**
+ * Deposit a native stake account into a staked collateral bank
+ * * `authority` - marginfiAccount's authority must sign and be the stake and withdraw authority of
+ *   `stakeAccount`. The whole stake account is deposited.
+ * @param program
+ * @param args
+ * @returns
+ */
+export const depositStakeIx = async (
+  program: Program<Marginfi>,
+  args: DepositStakeArgs
+) => {
+  const [lstMint, poolStakeAuthority, poolMintAuthority, solPool] =
+    await Promise.all([
+      findPoolMintAddress(SINGLE_POOL_PROGRAM_ID, args.splPool),
+      findPoolStakeAuthorityAddress(SINGLE_POOL_PROGRAM_ID, args.splPool),
+      findPoolMintAuthorityAddress(SINGLE_POOL_PROGRAM_ID, args.splPool),
+      findPoolStakeAddress(SINGLE_POOL_PROGRAM_ID, args.splPool),
+    ]);
+  return program.methods
+    .lendingAccountDepositStake()
+    .accountsPartial({
+      // marginfiGroup: args.marginfiGroup, // implied from bank
+      marginfiAccount: args.marginfiAccount,
+      // authority: args.authority, // implied from marginfiAccount
+      bank: args.bank,
+      // liquidityVault = deriveLiquidityVault(id, bank)
+      stakeAccount: args.stakeAccount,
+      stakePool: args.splPool,
+      solPool,
+      lstMint,
+      poolStakeAuthority,
+      poolMintAuthority,
+      clock: SYSVAR_CLOCK_PUBKEY,
+      stakeHistory: SYSVAR_STAKE_HISTORY_PUBKEY,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      stakeProgram: StakeProgram.programId,
+      singlePoolProgram: SINGLE_POOL_PROGRAM_ID,
+    })
+    .instruction();
+};
+
+export type WithdrawToStakeAccountArgs = {
+  marginfiAccount: PublicKey;
+  bank: PublicKey;
+  stakeAccount: PublicKey;
+  /** The spl single pool, all other pool accounts are derived from it */
+  splPool: PublicKey;
+  remaining: PublicKey[];
+  amount: BN;
+  withdrawAll?: boolean;
+};
+
+/**
+ * Withdraw from a staked collateral bank into a native stake account
+ * * `authority` - marginfiAccount's authority must sign, and becomes the stake and withdraw
+ *   authority of `stakeAccount`
+ * * `stakeAccount` - a rent-exempt, uninitialized account of `StakeProgram.space`, owned by the
+ *   stake program, typically created earlier in the same tx
+ * * `remaining` - as for `withdrawIx`
+ * @param program
+ * @param args
+ * @returns
+ */
+export const withdrawToStakeAccountIx = async (
+  program: Program<Marginfi>,
+  args: WithdrawToStakeAccountArgs
+) => {
+  const oracleMeta: AccountMeta[] = args.remaining.map((pubkey) => ({
+    pubkey,
+    isSigner: false,
+    isWritable: false,
+  }));
+  const [lstMint, poolStakeAuthority, poolMintAuthority, solPool] =
+    await Promise.all([
+      findPoolMintAddress(SINGLE_POOL_PROGRAM_ID, args.splPool),
+      findPoolStakeAuthorityAddress(SINGLE_POOL_PROGRAM_ID, args.splPool),
+      findPoolMintAuthorityAddress(SINGLE_POOL_PROGRAM_ID, args.splPool),
+      findPoolStakeAddress(SINGLE_POOL_PROGRAM_ID, args.splPool),
+    ]);
+  // False is the same as null, so if false we'll just pass null
+  const all = args.withdrawAll === true ? true : null;
+  return program.methods
+    .lendingAccountWithdrawToStakeAccount(args.amount, all)
+    .accountsPartial({
+      // marginfiGroup: args.marginfiGroup, // implied from bank
+      marginfiAccount: args.marginfiAccount,
+      // authority: args.authority, // implied from marginfiAccount
+      bank: args.bank,
+      // bankLiquidityVaultAuthority = deriveLiquidityVaultAuthority(id, bank);
+      // liquidityVault = deriveLiquidityVault(id, bank)
+      stakeAccount: args.stakeAccount,
+      stakePool: args.splPool,
+      solPool,
+      lstMint,
+      poolStakeAuthority,
+      poolMintAuthority,
+      clock: SYSVAR_CLOCK_PUBKEY,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      stakeProgram: StakeProgram.programId,
+      singlePoolProgram: SINGLE_POOL_PROGRAM_ID,
+    })
+    .remainingAccounts(oracleMeta)
+    .instruction();
+};